@@ -16,10 +16,12 @@
  * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
  */
 
-#[cfg(feature = "ipc")]
+#![allow(unexpected_cfgs)]
+
+#[cfg(feature = "arrow")]
 use ark_0_zheng::bridge::write_dummy_state;
 
-#[cfg(feature = "ipc")]
+#[cfg(feature = "arrow")]
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Ark-0 (Zheng): Writing 'ark_state.arrow' via Zero-Copy Bridge...");
     // Write to meta directory so python can find it easily
@@ -28,7 +30,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-#[cfg(not(feature = "ipc"))]
+#[cfg(not(feature = "arrow"))]
 fn main() {
-    println!("IPC feature disabled. Skipping write_state example.");
+    println!("Arrow feature disabled. Skipping write_state example.");
 }
@@ -90,13 +90,6 @@ pub struct Lambda {
     pub body: Box<MastNode>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
-pub struct TryCatch {
-    pub try_block: Box<MastNode>,
-    pub catch_var: String,
-    pub catch_block: Box<MastNode>,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct Import {
     pub path: String,
@@ -115,6 +108,28 @@ pub struct EnumDecl {
     pub variants: Vec<EnumVariantDef>,
 }
 
+impl EnumDecl {
+    /// `Result { Ok(Any), Err(Any) }` and `Option { Some(Any), None }`,
+    /// which every program may use without declaring them. The success
+    /// variant comes first, so its tag is 0 in every backend.
+    pub fn builtins() -> Vec<EnumDecl> {
+        let decl = |name: &str, variants: [(&str, usize); 2]| EnumDecl {
+            name: name.to_string(),
+            variants: variants
+                .iter()
+                .map(|(variant, arity)| EnumVariantDef {
+                    name: variant.to_string(),
+                    fields: vec![ArkType::Any; *arity],
+                })
+                .collect(),
+        };
+        vec![
+            decl("Result", [("Ok", 1), ("Err", 1)]),
+            decl("Option", [("Some", 1), ("None", 0)]),
+        ]
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct EnumVariantDef {
     pub name: String,
@@ -174,11 +189,24 @@ pub enum Statement {
     LetDestructure {
         names: Vec<String>,
         value: Expression,
+        /// `let {x, y} := p` binds each name to the struct field of the same
+        /// name instead of to list positions.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        by_field: bool,
+        /// `...rest` binding for whatever the named positions (or fields)
+        /// did not take.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rest: Option<String>,
     },
     SetField {
+        /// Root variable being mutated.
         obj_name: String,
         field: String,
         value: Expression,
+        /// Full object expression when the target is not a plain variable
+        /// (`a.b.c := v`, `xs[0].field := v`). `obj_name` is then its root.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        obj: Option<Box<Expression>>,
     },
     Return(Expression),
     Block(Vec<Statement>),
@@ -191,16 +219,44 @@ pub enum Statement {
     While {
         condition: Expression,
         body: Vec<Statement>,
+        /// `'name: while ...` lets a nested loop's `break 'name` or
+        /// `continue 'name` target this loop.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
     },
     For {
         variable: String,
         iterable: Expression,
         body: Vec<Statement>,
+        /// `'name: for ...`, as for `While`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
     },
 
-    Break,
-    Continue,
+    /// `try { body } catch name { handler }`: if `body` fails, runs
+    /// `handler` with `name` bound to the error (see
+    /// `RuntimeError::to_value`).
+    TryCatch {
+        body: Vec<Statement>,
+        catch_var: String,
+        handler: Vec<Statement>,
+    },
+
+    /// `break`, or `break 'name` to leave the enclosing loop labelled `name`.
+    Break {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
+    },
+    /// `continue`, or `continue 'name` for the loop labelled `name`.
+    Continue {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
+    },
     Function(FunctionDef),
+    /// `#[export]` on a top-level `let`; the wrapped statement is always a
+    /// `Let`. Backends treat it as the plain binding, and the WASM backend
+    /// also exports the bound value as a global.
+    Export(Box<Statement>),
 
     // New Nodes
     Import(Import),
@@ -223,6 +279,11 @@ pub enum Expression {
     StructInit {
         fields: Vec<(String, Expression)>,
     },
+    /// Map literal: `{ "k": v, ... }`. Keys are expressions evaluating to
+    /// strings; later entries overwrite earlier ones with the same key.
+    MapInit {
+        entries: Vec<(Expression, Expression)>,
+    },
     GetField {
         obj: Box<Expression>,
         field: String,
@@ -240,4 +301,800 @@ pub enum Expression {
         variant: String,
         args: Vec<Expression>,
     },
+    /// `expr?`: the payload of an `Ok`/`Some` value, or returns an `Err`/
+    /// `None` value from the enclosing function unchanged.
+    Propagate(Box<Expression>),
+}
+
+// =============================================================================
+// Visitor
+// =============================================================================
+
+/// Canonical read-only traversal over the AST.
+///
+/// Every pass that needs to enumerate nodes in a stable order (lambda lifting,
+/// analysis, codegen pre-scans) should go through this trait instead of
+/// hand-rolling its own recursion, so that independent passes always agree on
+/// the order in which nodes are seen. Override the `visit_*` hooks and call the
+/// matching `walk_*` function to continue into children.
+pub trait Visitor {
+    fn visit_node(&mut self, node: &ArkNode) {
+        walk_node(self, node);
+    }
+
+    fn visit_function(&mut self, func: &FunctionDef) {
+        walk_function(self, func);
+    }
+
+    fn visit_statement(&mut self, stmt: &Statement) {
+        walk_statement(self, stmt);
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        walk_expression(self, expr);
+    }
+}
+
+pub fn walk_node<V: Visitor + ?Sized>(visitor: &mut V, node: &ArkNode) {
+    match node {
+        ArkNode::Function(func) => visitor.visit_function(func),
+        ArkNode::Statement(stmt) => visitor.visit_statement(stmt),
+        ArkNode::Expression(expr) => visitor.visit_expression(expr),
+        ArkNode::Type(_) => {}
+    }
+}
+
+pub fn walk_function<V: Visitor + ?Sized>(visitor: &mut V, func: &FunctionDef) {
+    visitor.visit_node(&func.body.content);
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Statement) {
+    match stmt {
+        Statement::SetField { obj, value, .. } => {
+            if let Some(obj) = obj {
+                visitor.visit_expression(obj);
+            }
+            visitor.visit_expression(value);
+        }
+        Statement::Let { value, .. }
+        | Statement::LetDestructure { value, .. }
+        | Statement::Return(value)
+        | Statement::Expression(value) => visitor.visit_expression(value),
+        Statement::Block(stmts) => {
+            for s in stmts {
+                visitor.visit_statement(s);
+            }
+        }
+        Statement::If {
+            condition,
+            then_block,
+            else_block,
+        } => {
+            visitor.visit_expression(condition);
+            for s in then_block {
+                visitor.visit_statement(s);
+            }
+            if let Some(else_stmts) = else_block {
+                for s in else_stmts {
+                    visitor.visit_statement(s);
+                }
+            }
+        }
+        Statement::While {
+            condition, body, ..
+        } => {
+            visitor.visit_expression(condition);
+            for s in body {
+                visitor.visit_statement(s);
+            }
+        }
+        Statement::For { iterable, body, .. } => {
+            visitor.visit_expression(iterable);
+            for s in body {
+                visitor.visit_statement(s);
+            }
+        }
+        Statement::TryCatch { body, handler, .. } => {
+            for s in body.iter().chain(handler) {
+                visitor.visit_statement(s);
+            }
+        }
+        Statement::Function(func) => visitor.visit_function(func),
+        Statement::Export(inner) => visitor.visit_statement(inner),
+        Statement::ImplBlock(block) => {
+            for method in &block.methods {
+                visitor.visit_function(method);
+            }
+        }
+        Statement::Break { .. }
+        | Statement::Continue { .. }
+        | Statement::Import(_)
+        | Statement::StructDecl(_)
+        | Statement::EnumDecl(_)
+        | Statement::TraitDecl(_) => {}
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expression) {
+    match expr {
+        Expression::Call { args, .. } | Expression::EnumInit { args, .. } => {
+            for arg in args {
+                visitor.visit_expression(arg);
+            }
+        }
+        Expression::List(items) => {
+            for item in items {
+                visitor.visit_expression(item);
+            }
+        }
+        Expression::StructInit { fields } => {
+            for (_, val) in fields {
+                visitor.visit_expression(val);
+            }
+        }
+        Expression::MapInit { entries } => {
+            for (key, val) in entries {
+                visitor.visit_expression(key);
+                visitor.visit_expression(val);
+            }
+        }
+        Expression::GetField { obj, .. } | Expression::Propagate(obj) => {
+            visitor.visit_expression(obj)
+        }
+        Expression::Match { scrutinee, arms } => {
+            visitor.visit_expression(scrutinee);
+            for (_, arm) in arms {
+                visitor.visit_expression(arm);
+            }
+        }
+        Expression::Lambda { body, .. } => {
+            for s in body {
+                visitor.visit_statement(s);
+            }
+        }
+        Expression::Variable(_) | Expression::Literal(_) | Expression::Integer(_) => {}
+    }
+}
+
+// =============================================================================
+// Source Rendering
+// =============================================================================
+
+/// Render an AST back into Ark source, indented by four spaces per level.
+///
+/// Parsing the output yields the same AST for anything the parser can
+/// produce. Nodes the surface syntax cannot express (lambdas, enum
+/// constructors and patterns, `match` in expression position) are rendered
+/// in their closest readable form.
+pub fn to_source(node: &ArkNode) -> String {
+    let mut out = String::new();
+    match node {
+        ArkNode::Statement(Statement::Block(stmts)) => write_block_body(&mut out, stmts, 0),
+        ArkNode::Statement(stmt) => write_statement(&mut out, stmt, 0),
+        ArkNode::Function(func) => {
+            write_function(&mut out, func, 0);
+            out.push('\n');
+        }
+        ArkNode::Expression(expr) => {
+            out.push_str(&expression_source(expr));
+            out.push('\n');
+        }
+        ArkNode::Type(ty) => {
+            out.push_str(&ty.to_string());
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Binding strength of each level of the expression grammar, loosest first.
+const PREC_OR: u8 = 1;
+const PREC_AND: u8 = 2;
+const PREC_COMPARE: u8 = 3;
+const PREC_RANGE: u8 = 4;
+const PREC_SUM: u8 = 5;
+const PREC_PRODUCT: u8 = 6;
+const PREC_UNARY: u8 = 7;
+const PREC_POSTFIX: u8 = 8;
+
+fn binary_operator(name: &str) -> Option<(&'static str, u8)> {
+    Some(match name {
+        "or" => ("||", PREC_OR),
+        "and" => ("&&", PREC_AND),
+        "eq" => ("==", PREC_COMPARE),
+        "neq" => ("!=", PREC_COMPARE),
+        "lt" => ("<", PREC_COMPARE),
+        "gt" => (">", PREC_COMPARE),
+        "le" => ("<=", PREC_COMPARE),
+        "ge" => (">=", PREC_COMPARE),
+        "range_exclusive" => ("..", PREC_RANGE),
+        "range_inclusive" => ("..=", PREC_RANGE),
+        "add" => ("+", PREC_SUM),
+        "sub" => ("-", PREC_SUM),
+        "mul" => ("*", PREC_PRODUCT),
+        "div" => ("/", PREC_PRODUCT),
+        "mod" => ("%", PREC_PRODUCT),
+        _ => return None,
+    })
+}
+
+fn unary_operator(name: &str) -> Option<&'static str> {
+    match name {
+        "not" => Some("!"),
+        "neg" => Some("-"),
+        "bit_not" => Some("~"),
+        _ => None,
+    }
+}
+
+fn indent(out: &mut String, level: usize) {
+    for _ in 0..level {
+        out.push_str("    ");
+    }
+}
+
+fn write_block_body(out: &mut String, stmts: &[Statement], level: usize) {
+    for stmt in stmts {
+        write_statement(out, stmt, level);
+    }
+}
+
+/// Write ` {`, the indented statements and the closing `}` (no newline).
+fn write_braced(out: &mut String, stmts: &[Statement], level: usize) {
+    out.push_str(" {\n");
+    write_block_body(out, stmts, level + 1);
+    indent(out, level);
+    out.push('}');
+}
+
+/// Write a loop's `'name: ` prefix, if it has a label.
+fn write_label(out: &mut String, label: Option<&str>) {
+    if let Some(label) = label {
+        out.push_str(&format!("'{}: ", label));
+    }
+}
+
+/// Write the ` 'name` after a `break` or `continue` that names its loop.
+fn write_label_ref(out: &mut String, label: Option<&str>) {
+    if let Some(label) = label {
+        out.push_str(&format!(" '{}", label));
+    }
+}
+
+/// Write a function definition; the current line is already indented.
+fn write_function(out: &mut String, func: &FunctionDef, level: usize) {
+    for attr in &func.attributes {
+        out.push_str(&format!("#[{}]\n", attr));
+        indent(out, level);
+    }
+    let params: Vec<String> = func
+        .inputs
+        .iter()
+        .map(|(n, ty)| match ty {
+            ArkType::Any => n.clone(),
+            ty => format!("{}: {}", n, ty),
+        })
+        .collect();
+    out.push_str(&format!("func {}({})", func.name, params.join(", ")));
+    if func.output != ArkType::Any {
+        out.push_str(&format!(" => {}", func.output));
+    }
+    match &func.body.content {
+        ArkNode::Statement(Statement::Block(stmts)) => write_braced(out, stmts, level),
+        ArkNode::Statement(stmt) => write_braced(out, std::slice::from_ref(stmt), level),
+        ArkNode::Expression(expr) => write_braced(out, &[Statement::Return(expr.clone())], level),
+        ArkNode::Function(_) | ArkNode::Type(_) => write_braced(out, &[], level),
+    }
+}
+
+fn write_statement(out: &mut String, stmt: &Statement, level: usize) {
+    indent(out, level);
+    match stmt {
+        Statement::Let { name, ty, value } => {
+            out.push_str("let ");
+            out.push_str(name);
+            if let Some(ty) = ty {
+                out.push_str(&format!(": {}", ty));
+            }
+            out.push_str(&format!(" := {}", expression_source(value)));
+        }
+        Statement::LetDestructure {
+            names,
+            value,
+            by_field,
+            rest,
+        } => {
+            let mut bindings = names.clone();
+            if let Some(rest) = rest {
+                bindings.push(format!("...{}", rest));
+            }
+            let (open, close) = if *by_field { ("{", "}") } else { ("(", ")") };
+            out.push_str(&format!(
+                "let {}{}{} := {}",
+                open,
+                bindings.join(", "),
+                close,
+                expression_source(value)
+            ));
+        }
+        Statement::SetField {
+            obj_name,
+            field,
+            value,
+            obj,
+        } => {
+            let target = match obj {
+                Some(obj) => operand_source(obj, PREC_POSTFIX),
+                None => obj_name.clone(),
+            };
+            out.push_str(&format!(
+                "{}.{} := {}",
+                target,
+                field,
+                expression_source(value)
+            ));
+        }
+        // The parser reads a bare `return` as returning this literal.
+        Statement::Return(Expression::Literal(s)) if s == "unit" => out.push_str("return"),
+        Statement::Return(value) => {
+            out.push_str(&format!("return {}", expression_source(value)));
+        }
+        // A nested block has no syntax of its own; it runs like `if true`.
+        Statement::Block(stmts) => {
+            out.push_str("if true");
+            write_braced(out, stmts, level);
+        }
+        Statement::TryCatch {
+            body,
+            catch_var,
+            handler,
+        } => {
+            out.push_str("try");
+            write_braced(out, body, level);
+            out.push_str(&format!(" catch {}", catch_var));
+            write_braced(out, handler, level);
+        }
+        Statement::Expression(Expression::Match { scrutinee, arms }) => {
+            write_match(out, scrutinee, arms, level);
+        }
+        Statement::Expression(expr) => out.push_str(&expression_source(expr)),
+        Statement::If {
+            condition,
+            then_block,
+            else_block,
+        } => write_if(out, condition, then_block, else_block.as_deref(), level),
+        Statement::While {
+            condition,
+            body,
+            label,
+        } => {
+            write_label(out, label.as_deref());
+            out.push_str(&format!("while {}", expression_source(condition)));
+            write_braced(out, body, level);
+        }
+        Statement::For {
+            variable,
+            iterable,
+            body,
+            label,
+        } => {
+            write_label(out, label.as_deref());
+            out.push_str(&format!(
+                "for {} in {}",
+                variable,
+                expression_source(iterable)
+            ));
+            write_braced(out, body, level);
+        }
+        Statement::Function(func) => write_function(out, func, level),
+        Statement::ImplBlock(block) => {
+            match &block.trait_name {
+                Some(t) => out.push_str(&format!("impl {} for {} {{\n", t, block.target_type)),
+                None => out.push_str(&format!("impl {} {{\n", block.target_type)),
+            }
+            for method in &block.methods {
+                indent(out, level + 1);
+                write_function(out, method, level + 1);
+                out.push('\n');
+            }
+            indent(out, level);
+            out.push('}');
+        }
+        Statement::Break { label } => {
+            out.push_str("break");
+            write_label_ref(out, label.as_deref());
+        }
+        Statement::Export(inner) => {
+            out.push_str("#[export]\n");
+            write_statement(out, inner, level);
+        }
+        Statement::Continue { label } => {
+            out.push_str("continue");
+            write_label_ref(out, label.as_deref());
+        }
+        Statement::Import(import) => {
+            let dotted = !import.path.ends_with(".ark")
+                && import.path.split('.').all(|part| {
+                    part.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                        && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                });
+            if dotted {
+                out.push_str(&format!("import {}", import.path));
+            } else {
+                out.push_str(&format!("import {}", literal_source(&import.path)));
+            }
+            if let Some(alias) = &import.alias {
+                out.push_str(&format!(" as {}", alias));
+            }
+        }
+        Statement::StructDecl(decl) => {
+            out.push_str(&format!("class {} {{\n", decl.name));
+            for (field, _) in &decl.fields {
+                indent(out, level + 1);
+                out.push_str(field);
+                out.push('\n');
+            }
+            indent(out, level);
+            out.push('}');
+        }
+        Statement::EnumDecl(decl) => {
+            out.push_str(&format!("enum {} {{\n", decl.name));
+            for variant in &decl.variants {
+                indent(out, level + 1);
+                out.push_str(&variant.name);
+                if !variant.fields.is_empty() {
+                    let fields: Vec<String> =
+                        variant.fields.iter().map(|t| t.to_string()).collect();
+                    out.push_str(&format!("({})", fields.join(", ")));
+                }
+                out.push_str(",\n");
+            }
+            indent(out, level);
+            out.push('}');
+        }
+        Statement::TraitDecl(decl) => {
+            out.push_str(&format!("trait {} {{\n", decl.name));
+            for method in &decl.methods {
+                indent(out, level + 1);
+                let inputs: Vec<String> = method
+                    .inputs
+                    .iter()
+                    .map(|(n, ty)| match ty {
+                        ArkType::Any => n.clone(),
+                        ty => format!("{}: {}", n, ty),
+                    })
+                    .collect();
+                out.push_str(&format!("func {}({})", method.name, inputs.join(", ")));
+                if method.output != ArkType::Any {
+                    out.push_str(&format!(" => {}", method.output));
+                }
+                out.push('\n');
+            }
+            indent(out, level);
+            out.push('}');
+        }
+    }
+    out.push('\n');
+}
+
+fn write_if(
+    out: &mut String,
+    condition: &Expression,
+    then_block: &[Statement],
+    else_block: Option<&[Statement]>,
+    level: usize,
+) {
+    out.push_str(&format!("if {}", expression_source(condition)));
+    write_braced(out, then_block, level);
+    match else_block {
+        Some(
+            [
+                Statement::If {
+                    condition,
+                    then_block,
+                    else_block,
+                },
+            ],
+        ) => {
+            out.push_str(" else ");
+            write_if(out, condition, then_block, else_block.as_deref(), level);
+        }
+        Some(stmts) => {
+            out.push_str(" else");
+            write_braced(out, stmts, level);
+        }
+        None => {}
+    }
+}
+
+fn write_match(
+    out: &mut String,
+    scrutinee: &Expression,
+    arms: &[(Pattern, Expression)],
+    level: usize,
+) {
+    out.push_str(&format!("match {} {{\n", expression_source(scrutinee)));
+    for (pattern, body) in arms {
+        indent(out, level + 1);
+        out.push_str(&format!(
+            "{} => {},\n",
+            pattern_source(pattern),
+            expression_source(body)
+        ));
+    }
+    indent(out, level);
+    out.push('}');
+}
+
+/// A variant of the built-in `Result` and `Option` enums is written as the
+/// parser reads it (`Ok(x)`, `None`); any other as `Enum.Variant(..)`.
+fn enum_source(enum_name: &str, variant: &str, fields: String) -> String {
+    match (enum_name, variant) {
+        ("Option", "None") if fields.is_empty() => "None".to_string(),
+        ("Result", "Ok" | "Err") | ("Option", "Some") => format!("{}({})", variant, fields),
+        _ => format!("{}.{}({})", enum_name, variant, fields),
+    }
+}
+
+fn pattern_source(pattern: &Pattern) -> String {
+    match pattern {
+        // Integer patterns are stored as their decimal text.
+        Pattern::Literal(s)
+            if s.parse::<i64>()
+                .is_ok_and(|n| n >= 0 && n.to_string() == *s) =>
+        {
+            s.clone()
+        }
+        Pattern::Literal(s) => literal_source(s),
+        Pattern::Variable(name) => name.clone(),
+        Pattern::Wildcard => "_".to_string(),
+        Pattern::EnumVariant {
+            enum_name,
+            variant,
+            bindings,
+        } => enum_source(enum_name, variant, bindings.join(", ")),
+    }
+}
+
+fn literal_source(s: &str) -> String {
+    match s {
+        "true" | "false" | "nil" => return s.to_string(),
+        _ => {}
+    }
+    if is_fstring_source(s) {
+        return s.to_string();
+    }
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Whether `s` is a parsed f-string (`f"..."`, escapes kept verbatim) that
+/// lexes back to itself when written out as is.
+fn is_fstring_source(s: &str) -> bool {
+    let Some(body) = s
+        .strip_prefix("f\"")
+        .and_then(|rest| rest.strip_suffix('"'))
+    else {
+        return false;
+    };
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            // An escape consumes the next character, which may be a quote.
+            '\\' => {
+                if chars.next().is_none() {
+                    return false;
+                }
+            }
+            '"' => return false,
+            _ => {}
+        }
+    }
+    true
+}
+
+/// Precedence of the outermost construct of `expr` as rendered.
+fn expression_precedence(expr: &Expression) -> u8 {
+    match expr {
+        Expression::Call {
+            function_hash,
+            args,
+        } => match (binary_operator(function_hash), args.len()) {
+            (Some((_, prec)), 2) => prec,
+            _ if args.len() == 1 && unary_operator(function_hash).is_some() => PREC_UNARY,
+            _ => PREC_POSTFIX,
+        },
+        Expression::Integer(n) if *n < 0 => PREC_UNARY,
+        _ => PREC_POSTFIX,
+    }
+}
+
+/// Render `expr`, parenthesized if it binds looser than `min_prec`.
+fn operand_source(expr: &Expression, min_prec: u8) -> String {
+    let source = expression_source(expr);
+    if expression_precedence(expr) < min_prec {
+        format!("({})", source)
+    } else {
+        source
+    }
+}
+
+fn expression_source(expr: &Expression) -> String {
+    match expr {
+        Expression::Variable(name) => name.clone(),
+        Expression::Literal(s) => literal_source(s),
+        Expression::Integer(n) => n.to_string(),
+        Expression::Call {
+            function_hash,
+            args,
+        } => {
+            if let (Some((op, prec)), [lhs, rhs]) =
+                (binary_operator(function_hash), args.as_slice())
+            {
+                // Operators are left-associative; ranges do not chain at all.
+                let lhs_prec = if prec == PREC_RANGE { prec + 1 } else { prec };
+                return format!(
+                    "{} {} {}",
+                    operand_source(lhs, lhs_prec),
+                    op,
+                    operand_source(rhs, prec + 1)
+                );
+            }
+            if let (Some(op), [operand]) = (unary_operator(function_hash), args.as_slice()) {
+                return format!("{}{}", op, operand_source(operand, PREC_UNARY));
+            }
+            if let ("get_item", [obj, index]) = (function_hash.as_str(), args.as_slice()) {
+                return format!(
+                    "{}[{}]",
+                    operand_source(obj, PREC_POSTFIX),
+                    expression_source(index)
+                );
+            }
+            format!("{}({})", function_hash, comma_list(args))
+        }
+        Expression::List(items) => format!("[{}]", comma_list(items)),
+        Expression::StructInit { fields } => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|(k, v)| format!("{}: {}", k, expression_source(v)))
+                .collect();
+            format!("{{{}}}", fields.join(", "))
+        }
+        Expression::MapInit { entries } => {
+            let entries: Vec<String> = entries
+                .iter()
+                .map(|(k, v)| format!("{}: {}", expression_source(k), expression_source(v)))
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        Expression::GetField { obj, field } => {
+            format!("{}.{}", operand_source(obj, PREC_POSTFIX), field)
+        }
+        Expression::Match { scrutinee, arms } => {
+            let arms: Vec<String> = arms
+                .iter()
+                .map(|(p, body)| format!("{} => {}", pattern_source(p), expression_source(body)))
+                .collect();
+            format!(
+                "match {} {{ {} }}",
+                expression_source(scrutinee),
+                arms.join(", ")
+            )
+        }
+        Expression::Lambda { params, body } => {
+            let mut out = format!("func({})", params.join(", "));
+            write_braced(&mut out, body, 0);
+            out
+        }
+        Expression::EnumInit {
+            enum_name,
+            variant,
+            args,
+        } => enum_source(enum_name, variant, comma_list(args)),
+        Expression::Propagate(inner) => format!("{}?", operand_source(inner, PREC_POSTFIX)),
+    }
+}
+
+fn comma_list(exprs: &[Expression]) -> String {
+    exprs
+        .iter()
+        .map(expression_source)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_source;
+
+    #[test]
+    fn test_to_source_round_trip() {
+        let source = r#"
+import lib.std.string
+import "../shared/util.ark" as util
+
+#[export]
+func area(w, h) {
+    return (w + 1) * h - -2 % 3
+}
+
+class Point {
+    x
+    y
+}
+
+enum Shape {
+    Circle(Int),
+    Rect(Int, List<Int>),
+    Empty
+}
+
+trait Drawable {
+    func draw(self, scale: Int) => Str
+}
+
+impl Drawable for Point {
+    func draw(self, scale) {
+        return f"point {self.x} \"q\""
+    }
+}
+
+let p: Point := {x: 1, y: 2}
+let (a, b, ...rest) := [1, 2, 3, 4]
+let {x, y} := p
+p.x := a + b * 2
+p.inner.y := !(a < b) && b >= 2 || false
+xs := [1, 2]
+xs[0].z := nil
+for i in 0..10 {
+    if i % 2 == 0 {
+        continue
+    } else if i > 7 {
+        break
+    } else {
+        sys.log("info", "odd\t\"i\"\n")
+    }
+}
+while a <= 3 {
+    a += 1
+}
+match a {
+    1 => "one",
+    n => n,
+    _ => sys.fs.read("x")
+}
+func noop() {
+    return
+}
+r := Ok(load("x")?)
+match r {
+    Ok(v) => v,
+    Err(_) => None,
+    None => Some(0)
+}
+print(area(2, 3)[0], ~a, (1 + 2).y, 1..=3)
+"#;
+        let ast = parse_source(source, "round_trip.ark").expect("parse failed");
+        let rendered = to_source(&ast);
+        let reparsed = parse_source(&rendered, "rendered.ark")
+            .unwrap_or_else(|e| panic!("rendered source failed to parse: {:?}\n{}", e, rendered));
+
+        assert_eq!(reparsed, ast, "rendered source:\n{}", rendered);
+        assert_eq!(to_source(&reparsed), rendered);
+        assert!(rendered.contains("    return (w + 1) * h - -2 % 3\n"));
+        assert!(rendered.contains("} else if i > 7 {\n"));
+    }
 }
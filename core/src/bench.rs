@@ -0,0 +1,204 @@
+/*
+ * Copyright (c) 2026 Mohamad Al-Zawahreh (dba Sovereign Systems).
+ *
+ * This file is part of the Ark Sovereign Compiler.
+ *
+ * LICENSE: DUAL-LICENSED (AGPLv3 or COMMERCIAL).
+ *
+ * 1. OPEN SOURCE: You may use this file under the terms of the GNU Affero
+ * General Public License v3.0. If you link to this code, your ENTIRE
+ * application must be open-sourced under AGPLv3.
+ *
+ * 2. COMMERCIAL: For proprietary use, you must obtain a Commercial License
+ * from Sovereign Systems.
+ *
+ * PATENT NOTICE: Protected by US Patent App #63/935,467.
+ * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
+ */
+
+//! Timing for `#[bench]` functions (`ark bench`).
+//!
+//! A benchmark is measured through a callback that runs it a given number
+//! of times and reports how long that took, so the same procedure times
+//! the VM and the WASM backend. The callback is first run with doubling
+//! batch sizes until the warmup time has passed; the last batch sets how
+//! many iterations one sample needs to last about `sample_time`. Each
+//! sample then gives one time per iteration, and the samples are summed up
+//! in [`Stats`].
+
+use std::time::Duration;
+
+/// How long to warm up and how to sample.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchConfig {
+    pub warmup: Duration,
+    pub samples: usize,
+    pub sample_time: Duration,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self {
+            warmup: Duration::from_millis(300),
+            samples: 10,
+            sample_time: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Time per iteration over the samples of one benchmark, in nanoseconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    /// Iterations in each sample
+    pub iterations: u64,
+    pub samples: usize,
+    pub mean_ns: f64,
+    pub median_ns: f64,
+    pub std_dev_ns: f64,
+    pub min_ns: f64,
+    pub max_ns: f64,
+}
+
+impl Stats {
+    /// Summarize per-iteration times; `None` if there are none.
+    pub fn from_samples(iterations: u64, per_iter_ns: &[f64]) -> Option<Self> {
+        if per_iter_ns.is_empty() {
+            return None;
+        }
+        let mut sorted = per_iter_ns.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let n = sorted.len();
+        let mean = sorted.iter().sum::<f64>() / n as f64;
+        let median = if n.is_multiple_of(2) {
+            (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0
+        } else {
+            sorted[n / 2]
+        };
+        let variance = if n > 1 {
+            sorted.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / (n - 1) as f64
+        } else {
+            0.0
+        };
+        Some(Self {
+            iterations,
+            samples: n,
+            mean_ns: mean,
+            median_ns: median,
+            std_dev_ns: variance.sqrt(),
+            min_ns: sorted[0],
+            max_ns: sorted[n - 1],
+        })
+    }
+
+    /// Iterations per second, from the median.
+    pub fn per_second(&self) -> f64 {
+        if self.median_ns > 0.0 {
+            1e9 / self.median_ns
+        } else {
+            f64::INFINITY
+        }
+    }
+
+    /// Standard deviation as a percentage of the mean.
+    pub fn relative_std_dev(&self) -> f64 {
+        if self.mean_ns > 0.0 {
+            self.std_dev_ns / self.mean_ns * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Warm up and sample a benchmark. `run(n)` runs it `n` times and returns
+/// how long that took; its first error stops the measurement.
+pub fn measure<E>(
+    config: &BenchConfig,
+    mut run: impl FnMut(u64) -> Result<Duration, E>,
+) -> Result<Stats, E> {
+    let mut batch = 1u64;
+    let mut warmed = Duration::ZERO;
+    let per_iter = loop {
+        let elapsed = run(batch)?;
+        warmed += elapsed;
+        if warmed >= config.warmup {
+            break Duration::from_nanos((elapsed.as_nanos() / batch as u128) as u64);
+        }
+        batch = batch.saturating_mul(2);
+    };
+
+    let iterations = if per_iter.is_zero() {
+        batch
+    } else {
+        (config.sample_time.as_nanos() / per_iter.as_nanos()).clamp(1, u64::MAX as u128) as u64
+    };
+    let mut times = Vec::with_capacity(config.samples);
+    for _ in 0..config.samples.max(1) {
+        let elapsed = run(iterations)?;
+        times.push(elapsed.as_nanos() as f64 / iterations as f64);
+    }
+    Ok(Stats::from_samples(iterations, &times).expect("at least one sample"))
+}
+
+/// A duration in nanoseconds, in the unit that keeps it readable.
+pub fn format_ns(ns: f64) -> String {
+    if ns >= 1e9 {
+        format!("{:.2} s", ns / 1e9)
+    } else if ns >= 1e6 {
+        format!("{:.2} ms", ns / 1e6)
+    } else if ns >= 1e3 {
+        format!("{:.2} µs", ns / 1e3)
+    } else {
+        format!("{:.1} ns", ns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_summarize_the_samples() {
+        let stats = Stats::from_samples(4, &[40.0, 10.0, 30.0, 20.0]).expect("samples");
+        assert_eq!(stats.samples, 4);
+        assert_eq!(stats.mean_ns, 25.0);
+        assert_eq!(stats.median_ns, 25.0);
+        assert_eq!((stats.min_ns, stats.max_ns), (10.0, 40.0));
+        assert!((stats.std_dev_ns - 12.909944).abs() < 1e-5);
+        assert_eq!(stats.per_second(), 4e7);
+        assert!(Stats::from_samples(1, &[]).is_none());
+    }
+
+    #[test]
+    fn test_measure_warms_up_then_sizes_samples() {
+        let config = BenchConfig {
+            warmup: Duration::from_micros(700),
+            samples: 3,
+            sample_time: Duration::from_micros(50),
+        };
+        // A pretend benchmark taking 1µs per iteration
+        let mut batches = Vec::new();
+        let stats = measure(&config, |n| {
+            batches.push(n);
+            Ok::<_, ()>(Duration::from_micros(n))
+        })
+        .expect("measure failed");
+
+        // 1+2+4+...+512 µs passes the 700µs warmup at the batch of 512
+        assert_eq!(batches[..10], [1, 2, 4, 8, 16, 32, 64, 128, 256, 512]);
+        assert_eq!(batches[10..], [50, 50, 50]);
+        assert_eq!(stats.iterations, 50);
+        assert_eq!(stats.median_ns, 1000.0);
+        assert_eq!(stats.relative_std_dev(), 0.0);
+
+        let failed = measure(&config, |_| Err::<Duration, _>("trap"));
+        assert_eq!(failed, Err("trap"));
+    }
+
+    #[test]
+    fn test_durations_are_formatted_in_a_readable_unit() {
+        assert_eq!(format_ns(12.34), "12.3 ns");
+        assert_eq!(format_ns(4_560.0), "4.56 µs");
+        assert_eq!(format_ns(7_890_000.0), "7.89 ms");
+        assert_eq!(format_ns(2.5e9), "2.50 s");
+    }
+}
@@ -0,0 +1,353 @@
+/*
+ * Copyright (c) 2026 Mohamad Al-Zawahreh (dba Sovereign Systems).
+ *
+ * This file is part of the Ark Sovereign Compiler.
+ *
+ * LICENSE: DUAL-LICENSED (AGPLv3 or COMMERCIAL).
+ *
+ * 1. OPEN SOURCE: You may use this file under the terms of the GNU Affero
+ * General Public License v3.0. If you link to this code, your ENTIRE
+ * application must be open-sourced under AGPLv3.
+ *
+ * 2. COMMERCIAL: For proprietary use, you must obtain a Commercial License
+ * from Sovereign Systems.
+ *
+ * PATENT NOTICE: Protected by US Patent App #63/935,467.
+ * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
+ */
+
+//! Arbitrary-precision integers for the `sys.bigint.*` intrinsics.
+//!
+//! A [`BigInt`] is a sign and a magnitude of 32-bit limbs, least
+//! significant first, with no zero limb at the top; zero has no limbs and
+//! is never negative, so equal numbers are equal values. The WASM backend
+//! keeps the same limbs in linear memory (`HEAP_TAG_BIGINT` in
+//! `wasm_codegen.rs`) and computes the same results: `modulo` is in
+//! `0..|modulus|` whatever the signs, and remainders are found by
+//! shift-and-subtract in both.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Largest power of ten in a limb, for decimal conversion nine digits at
+/// a time
+const DECIMAL_CHUNK: u32 = 1_000_000_000;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    limbs: Vec<u32>,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("not a decimal integer: {0:?}")]
+pub struct ParseBigIntError(String);
+
+impl BigInt {
+    fn from_parts(negative: bool, mut limbs: Vec<u32>) -> Self {
+        trim(&mut limbs);
+        Self {
+            negative: negative && !limbs.is_empty(),
+            limbs,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// `self` modulo `modulus`, in `0..|modulus|`; `None` when `modulus` is
+    /// zero.
+    pub fn modulo(&self, modulus: &BigInt) -> Option<BigInt> {
+        if modulus.is_zero() {
+            return None;
+        }
+        let rem = rem_mag(&self.limbs, &modulus.limbs);
+        let rem = if self.negative && !rem.is_empty() {
+            sub_mag(&modulus.limbs, &rem)
+        } else {
+            rem
+        };
+        Some(Self::from_parts(false, rem))
+    }
+
+    /// `self` to the power `exponent`, modulo `modulus`, by square and
+    /// multiply; `None` when `modulus` is zero or `exponent` negative.
+    pub fn pow_mod(&self, exponent: &BigInt, modulus: &BigInt) -> Option<BigInt> {
+        if exponent.negative {
+            return None;
+        }
+        let mut base = self.modulo(modulus)?;
+        let mut result = BigInt::from(1).modulo(modulus)?;
+        for limb in &exponent.limbs {
+            for bit in 0..32 {
+                if (limb >> bit) & 1 == 1 {
+                    result = (&result * &base).modulo(modulus)?;
+                }
+                base = (&base * &base).modulo(modulus)?;
+            }
+        }
+        Some(result)
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let magnitude = value.unsigned_abs();
+        Self::from_parts(value < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+impl FromStr for BigInt {
+    type Err = ParseBigIntError;
+
+    /// Decimal digits with an optional leading `-`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ParseBigIntError(s.to_string()));
+        }
+        let mut limbs = Vec::new();
+        for chunk in digits.as_bytes().chunks(9) {
+            let value = chunk
+                .iter()
+                .fold(0u32, |acc, b| acc * 10 + (b - b'0') as u32);
+            mul_small_add(&mut limbs, 10u32.pow(chunk.len() as u32), value);
+        }
+        Ok(Self::from_parts(negative, limbs))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut limbs = self.limbs.clone();
+        let mut chunks = Vec::new();
+        while !limbs.is_empty() {
+            chunks.push(div_small(&mut limbs, DECIMAL_CHUNK));
+        }
+        if self.negative {
+            write!(f, "-")?;
+        }
+        match chunks.pop() {
+            Some(top) => write!(f, "{}", top)?,
+            None => write!(f, "0")?,
+        }
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_mag(&self.limbs, &other.limbs),
+            (true, true) => cmp_mag(&other.limbs, &self.limbs),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_parts(self.negative, add_mag(&self.limbs, &other.limbs));
+        }
+        match cmp_mag(&self.limbs, &other.limbs) {
+            Ordering::Less => {
+                BigInt::from_parts(other.negative, sub_mag(&other.limbs, &self.limbs))
+            }
+            _ => BigInt::from_parts(self.negative, sub_mag(&self.limbs, &other.limbs)),
+        }
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                // At most (2^32-1)^2 + 2 * (2^32-1), which fits in a u64
+                let t = limbs[i + j] as u64 + a as u64 * b as u64 + carry;
+                limbs[i + j] = t as u32;
+                carry = t >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        BigInt::from_parts(self.negative != other.negative, limbs)
+    }
+}
+
+fn trim(limbs: &mut Vec<u32>) {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+}
+
+/// Compare trimmed magnitudes.
+fn cmp_mag(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut sum = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+    for (i, &limb) in long.iter().enumerate() {
+        let t = limb as u64 + short.get(i).copied().unwrap_or(0) as u64 + carry;
+        sum.push(t as u32);
+        carry = t >> 32;
+    }
+    sum.push(carry as u32);
+    trim(&mut sum);
+    sum
+}
+
+/// `a - b` for `a >= b`, trimmed.
+fn sub_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut diff = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, &limb) in a.iter().enumerate() {
+        let mut t = limb as i64 - b.get(i).copied().unwrap_or(0) as i64 - borrow;
+        borrow = (t < 0) as i64;
+        if t < 0 {
+            t += 1 << 32;
+        }
+        diff.push(t as u32);
+    }
+    trim(&mut diff);
+    diff
+}
+
+/// Remainder of `a` by a nonzero `m`, bit by bit from the top of `a`.
+fn rem_mag(a: &[u32], m: &[u32]) -> Vec<u32> {
+    let mut rem: Vec<u32> = Vec::with_capacity(m.len() + 1);
+    for bit in (0..a.len() * 32).rev() {
+        let mut carry = (a[bit / 32] >> (bit % 32)) & 1;
+        for limb in rem.iter_mut() {
+            let top = *limb >> 31;
+            *limb = (*limb << 1) | carry;
+            carry = top;
+        }
+        if carry != 0 {
+            rem.push(carry);
+        }
+        if cmp_mag(&rem, m) != Ordering::Less {
+            rem = sub_mag(&rem, m);
+        }
+    }
+    rem
+}
+
+/// `limbs = limbs * factor + addend`.
+fn mul_small_add(limbs: &mut Vec<u32>, factor: u32, addend: u32) {
+    let mut carry = addend as u64;
+    for limb in limbs.iter_mut() {
+        let t = *limb as u64 * factor as u64 + carry;
+        *limb = t as u32;
+        carry = t >> 32;
+    }
+    if carry != 0 {
+        limbs.push(carry as u32);
+    }
+}
+
+/// Divide `limbs` by `divisor` in place, keeping it trimmed, and return
+/// the remainder.
+fn div_small(limbs: &mut Vec<u32>, divisor: u32) -> u32 {
+    let mut rem = 0u64;
+    for limb in limbs.iter_mut().rev() {
+        let cur = (rem << 32) | *limb as u64;
+        *limb = (cur / divisor as u64) as u32;
+        rem = cur % divisor as u64;
+    }
+    trim(limbs);
+    rem as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(s: &str) -> BigInt {
+        s.parse().expect("parse failed")
+    }
+
+    #[test]
+    fn test_decimal_round_trip() {
+        for s in [
+            "0",
+            "-1",
+            "4294967296",
+            "1000000000",
+            "-340282366920938463463374607431768211456",
+        ] {
+            assert_eq!(big(s).to_string(), s);
+        }
+        assert_eq!(big("-0"), BigInt::default());
+        assert_eq!(big("000123").to_string(), "123");
+        assert_eq!(BigInt::from(i64::MIN).to_string(), i64::MIN.to_string());
+        assert!("".parse::<BigInt>().is_err());
+        assert!("12a".parse::<BigInt>().is_err());
+        assert!("-".parse::<BigInt>().is_err());
+    }
+
+    #[test]
+    fn test_arithmetic_past_i64() {
+        let a = big("9223372036854775807");
+        assert_eq!((&a + &BigInt::from(1)).to_string(), "9223372036854775808");
+        assert_eq!(
+            (&a * &a).to_string(),
+            "85070591730234615847396907784232501249"
+        );
+        assert_eq!((&big("-5") + &big("3")).to_string(), "-2");
+        assert_eq!((&big("5") + &big("-5")), BigInt::default());
+        assert_eq!((&big("-4") * &big("6")).to_string(), "-24");
+        assert!(big("-10") < big("-9"));
+        assert!(big("18446744073709551616") > big("18446744073709551615"));
+    }
+
+    #[test]
+    fn test_modulo_and_pow_mod() {
+        assert_eq!(big("-7").modulo(&big("3")), Some(big("2")));
+        assert_eq!(big("7").modulo(&big("-3")), Some(big("1")));
+        assert_eq!(big("7").modulo(&BigInt::default()), None);
+
+        // 2^255 - 19, the Curve25519 prime: a^(p-1) = 1 mod p
+        let p =
+            big("57896044618658097711785492504343953926634992332820282019728792003956564819949");
+        let p_minus_1 = &p + &big("-1");
+        assert_eq!(big("9").pow_mod(&p_minus_1, &p), Some(big("1")));
+        assert_eq!(big("4").pow_mod(&big("13"), &big("497")), Some(big("445")));
+        assert_eq!(
+            big("4").pow_mod(&big("0"), &big("1")),
+            Some(BigInt::default())
+        );
+        assert_eq!(big("4").pow_mod(&big("-1"), &big("7")), None);
+    }
+}
@@ -16,7 +16,7 @@
  * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
  */
 
-use ark_0_zheng::{checker, compiler::Compiler, loader, vm::VM};
+use ark_0_zheng::{artifact::TrustPolicy, checker, compiler::Compiler, loader, vm::VM};
 use std::env;
 use std::fs;
 
@@ -26,8 +26,16 @@ fn main() {
     if args.len() > 1 {
         let filename = &args[1];
 
+        // An unsigned program may not run once trust roots are set
+        let policy = match TrustPolicy::from_env() {
+            Ok(policy) => policy,
+            Err(e) => {
+                println!("ARK_TRUSTED_KEYS: {}", e);
+                return;
+            }
+        };
         match fs::read_to_string(filename) {
-            Ok(json_content) => match loader::load_ark_program(&json_content) {
+            Ok(json_content) => match loader::load_unsigned_program(&json_content, &policy) {
                 Ok(mast) => {
                     // Phase 3: The Linear Shield
                     if let Err(e) = checker::LinearChecker::check(&mast.content) {
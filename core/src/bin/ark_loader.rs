@@ -20,35 +20,84 @@
 //!
 //! Usage:
 //!   ark run <file.ark>           Parse and execute an Ark source file
+//!   ark run --backend native <file.ark>
+//!                                JIT-compile to machine code and execute
 //!   ark run <file.json>          Load and execute a JSON MAST file (legacy)
-//!   ark check <file.ark>         Parse and run linear type checker
+//!   ark run <file.arkc>          Execute a compiled program once its hash and
+//!                                signature verify
+//!   ark run --allow-net[=hosts] --allow-fs[=paths] ... <file.ark>
+//!                                Run with only the capabilities granted;
+//!                                without flags, a program may not touch
+//!                                files, the network, commands, AI or threads
+//!   ark run --profile[=<file>] <file.ark>
+//!                                Profile the run: a table of calls, time and
+//!                                allocations per function, and folded stacks
+//!                                for flamegraph tools in <file>
+//!   ark run --record[=<file>] <file.ark>
+//!                                Record the last instructions executed, with
+//!                                their operands, as JSON lines in <file>
+//!   ark check <file.ark>         Parse and run the linear and type checker
+//!       --error-format human|json
+//!                                Render errors against the source (default)
+//!                                or print one JSON object per line
 //!   ark parse <file.ark>         Parse and dump AST as JSON
+//!   ark fmt [--check] <path>...  Format .ark files (or check they are formatted)
+//!   ark test [--wasm] <file.ark> Run a program's #[test] functions on the VM
+//!                                (and in WASM)
+//!   ark bench [--wasm] [--json] <file.ark>
+//!                                Time a program's #[bench] functions
+//!   ark debug [--listen <addr>] <file.ark>
+//!                                Step through a program in the terminal, or
+//!                                for a frontend connecting to <addr>
+//!   ark debug --replay [--listen <addr>] <trace.jsonl>
+//!                                Step forwards and backwards through a
+//!                                recorded trace
+//!   ark dap [--listen <addr>]    Debug Adapter Protocol server for editors
+//!                                (needs the ipc feature)
 //!   ark version                  Print version
 //!   ark help                     Print usage
 
 use ark_0_zheng::adn;
+use ark_0_zheng::artifact::{self, CompiledProgram, TrustPolicy};
+use ark_0_zheng::bench::{self, BenchConfig, Stats};
+use ark_0_zheng::capability::Capabilities;
 use ark_0_zheng::compiler::Compiler;
 use ark_0_zheng::debugger::{self, DebugAction, DebugState, StepMode};
 use ark_0_zheng::diagnostic::{
     self, DiagnosticConfig, DiagnosticHistory, DiagnosticProbe, HistoryEntry, LinearAudit,
     OverlayEffectiveness, PipelineHealth, ProbeType, ReportTier, UserDefinedGate,
 };
+use ark_0_zheng::diagnostics::{Diagnostic, ErrorFormat};
+use ark_0_zheng::formatter::{self, FormatError};
 use ark_0_zheng::governance::{Decision, DualBand, GovernedPipeline, Phase};
-use ark_0_zheng::loader::load_ark_program;
+use ark_0_zheng::intrinsics;
+use ark_0_zheng::loader::{
+    self, Linker, load_ark_program, load_compiled_program, load_unsigned_program,
+};
+use ark_0_zheng::package::{
+    self, Dependency, Installer, Manifest, PackageError, Source, VersionReq,
+};
 use ark_0_zheng::parser;
 use ark_0_zheng::persistent::{PMap, PVec};
+use ark_0_zheng::profiler::Profile;
+use ark_0_zheng::repl::{self, Session};
 use ark_0_zheng::runtime::Value;
-use ark_0_zheng::vm::VM;
+use ark_0_zheng::trace::{self, Event, ExecutionTrace, TraceCursor};
+use ark_0_zheng::vm::{VM, VmConfig};
 use ark_0_zheng::wasm_codegen::WasmCodegen;
 use ark_0_zheng::wasm_runner;
 use ark_0_zheng::wit_gen;
+use rustyline::DefaultEditor;
+use rustyline::error::ReadlineError;
 use std::cell::RefCell;
 use std::env;
 use std::fs;
-use std::io::{self, BufRead, Write};
+use std::io::{self, BufReader};
+use std::net::{TcpListener, TcpStream};
 use std::path::Path;
 use std::process;
 use std::rc::Rc;
+use std::time::Instant;
 
 const VERSION: &str = "1.3.0";
 
@@ -67,11 +116,24 @@ fn main() {
         "build" => cmd_build(&args[2..]),
         "wit" => cmd_wit(&args[2..]),
         "debug" => cmd_debug(&args[2..]),
+        #[cfg(feature = "ipc")]
+        "dap" => cmd_dap(&args[2..]),
+        #[cfg(not(feature = "ipc"))]
+        "dap" => {
+            eprintln!("Error: this ark was built without the ipc feature");
+            eprintln!("Rebuild it with: cargo build --features ipc");
+            process::exit(1);
+        }
         "repl" => cmd_repl(),
         "adn" => cmd_adn(&args[2..]),
         "check" => cmd_check(&args[2..]),
         "diagnose" => cmd_diagnose(&args[2..]),
         "parse" => cmd_parse(&args[2..]),
+        "fmt" => cmd_fmt(&args[2..]),
+        "test" => cmd_test(&args[2..]),
+        "bench" => cmd_bench(&args[2..]),
+        "pkg" => cmd_pkg(&args[2..]),
+        "keygen" => cmd_keygen(&args[2..]),
         "version" | "--version" | "-v" => {
             println!("Ark Sovereign Compiler v{}", VERSION);
         }
@@ -93,14 +155,33 @@ fn print_usage() {
     println!();
     println!("Usage:");
     println!("  ark run <file.ark|file.json>    Parse and execute a program");
+    println!("      --backend vm|native           Run on the VM (default) or as native code");
+    println!("      --allow-read[=<paths>]        Grant reads under the paths (default: all)");
+    println!("      --allow-write[=<paths>]       Grant writes under the paths (default: all)");
+    println!("      --allow-fs[=<paths>]          Grant both");
+    println!("      --allow-net[=<hosts>]         Grant host or host:port patterns (default: all)");
+    println!("      --allow-run[=<commands>]      Grant running commands (default: all)");
+    println!("      --allow-ai, --allow-threads   Grant AI providers, OS threads");
+    println!("      --allow-all                   Grant everything (default: nothing)");
+    println!("      --profile[=<file>]            Print time and allocations per function and");
+    println!("                                    write folded stacks (default: <file>.folded)");
+    println!("      --record[=<file>]             Write the last instructions executed as JSON");
+    println!("                                    lines (default: <file>.trace.jsonl)");
     println!("  ark run-wasm <file.wasm>        Execute a compiled WASM binary via wasmtime");
     println!("  ark build <file.ark> [-o out]    Compile to native WASM binary");
     println!("  ark build <file.ark> --run       Compile and immediately execute");
+    println!("  ark build <file.ark> -o out.arkc Compile to a hash-addressed program for the VM");
+    println!("      --sign <key.pem>              Sign the .wasm or .arkc output");
+    println!("      --error-format human|json     How build and check print errors");
     println!("  ark wit <file.ark>               Generate WIT interface definition");
     println!("  ark repl                        Interactive REPL with persistent state");
     println!("  ark debug <file.ark>            Interactive step-through debugger");
+    println!("      --listen <addr>               Serve the JSON debug protocol over TCP");
+    println!("      --replay <trace.jsonl>        Step both ways through a recorded trace");
+    println!("  ark dap [--listen <addr>]        Debug Adapter Protocol server for editors");
+    println!("                                    (default 127.0.0.1:4711; ipc feature)");
     println!("  ark adn <file.ark>              Parse and output as ADN (Ark Data Notation)");
-    println!("  ark check <file.ark|file.json>  Run the linear type checker");
+    println!("  ark check <file.ark|file.json>  Run the linear and type checker");
     println!("  ark diagnose <file.ark> [opts]   Run diagnostic proof suite");
     println!("      --tier free|developer|pro     Report detail level");
     println!("      --json                        JSON output");
@@ -113,10 +194,100 @@ fn print_usage() {
     println!("      --history                     Show diagnostic trend table");
     println!("      --gate \"name:X,key:Y,op:Z,val:W,sev:S\"  Custom gate");
     println!("  ark parse <file.ark>            Parse and dump AST as JSON");
+    println!("  ark fmt <file.ark|dir>...       Format Ark sources in place");
+    println!("      --check                       Only report files that need formatting");
+    println!("  ark test <file.ark>             Run the #[test] functions in a program");
+    println!("      --wasm                        Also run each test compiled to WASM");
+    println!("  ark bench <file.ark>            Time the #[bench] functions in a program");
+    println!("      --wasm                        Also time them compiled to WASM and compare");
+    println!("      --json                        One JSON object per measurement");
+    println!("  ark pkg add <name>[@<version>]   Add a registry dependency to ark.toml");
+    println!("      --git <url> [--rev <rev>]     ...or a git one");
+    println!("      --path <dir>                  ...or a local one");
+    println!("  ark pkg install                 Fetch dependencies and write ark.lock");
+    println!("  ark pkg publish [--token <t>]   Upload this package to the registry");
+    println!("  ark keygen <key.pem>            Create a signing key and <key>.pub.pem");
     println!("  ark version                     Print version info");
     println!("  ark help                        Print this help message");
 }
 
+/// Read `--error-format human|json` from a command's arguments.
+fn error_format(args: &[String]) -> ErrorFormat {
+    match args.iter().position(|a| a == "--error-format") {
+        Some(i) => match args.get(i + 1).map(|v| v.parse::<ErrorFormat>()) {
+            Some(Ok(format)) => format,
+            Some(Err(e)) => {
+                eprintln!("Error: {}", e);
+                process::exit(1);
+            }
+            None => {
+                eprintln!("Error: --error-format requires a value (human or json)");
+                process::exit(1);
+            }
+        },
+        None => ErrorFormat::Human,
+    }
+}
+
+/// Print compiler diagnostics to stderr in the requested format.
+fn report_diagnostics(format: ErrorFormat, diagnostics: &[Diagnostic], source: &str) {
+    eprintln!("{}", format.emit(diagnostics, source));
+}
+
+/// Print diagnostics for a linked program, each rendered against the file
+/// its span points into.
+fn report_linked_diagnostics(format: ErrorFormat, diagnostics: &[Diagnostic], linker: &Linker) {
+    for (i, diagnostic) in diagnostics.iter().enumerate() {
+        let source = diagnostic
+            .span
+            .as_ref()
+            .and_then(|span| linker.source(&span.file))
+            .unwrap_or("");
+        if i > 0 && format == ErrorFormat::Human {
+            eprintln!();
+        }
+        report_diagnostics(format, std::slice::from_ref(diagnostic), source);
+    }
+}
+
+/// A linker for `filename` that can also import the packages installed
+/// for the project around it.
+fn linker_for(filename: &str) -> Linker {
+    let mut linker = Linker::from_env();
+    let Some(project) = package::find_project(Path::new(filename)) else {
+        return linker;
+    };
+    match package::installed(&project, &package::cache_dir()) {
+        Ok(packages) => {
+            for installed in packages {
+                linker.add_package(&installed.name, installed.root, installed.entry);
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    }
+    linker
+}
+
+/// Parse an Ark source file and link in the modules it imports, exiting
+/// with the error if that fails. The linker keeps every file's source.
+fn link_program(
+    source: &str,
+    filename: &str,
+    format: ErrorFormat,
+) -> (ark_0_zheng::ast::ArkNode, Linker) {
+    let mut linker = linker_for(filename);
+    match linker.link(source, filename) {
+        Ok(program) => (program, linker),
+        Err(e) => {
+            report_linked_diagnostics(format, &[e.to_diagnostic()], &linker);
+            process::exit(1);
+        }
+    }
+}
+
 // =============================================================================
 // BUILD — Compile Ark source to native WASM binary
 // =============================================================================
@@ -124,18 +295,38 @@ fn print_usage() {
 /// Compile an Ark program to a native .wasm binary.
 ///
 /// Usage:
-///   ark build <file.ark> [-o output.wasm]
+///   ark build <file.ark> [-o output.wasm|output.arkc] [--sign key.pem]
+///       [--error-format human|json]
 ///
-/// If -o is not specified, output defaults to <file>.wasm.
+/// If -o is not specified, output defaults to <file>.wasm. An `.arkc`
+/// output is the linked program for the VM rather than a WASM binary.
 fn cmd_build(args: &[String]) {
     if args.is_empty() {
         eprintln!("Error: 'build' requires a file argument");
-        eprintln!("Usage: ark build <file.ark> [-o output.wasm] [--run]");
+        eprintln!(
+            "Usage: ark build <file.ark> [-o output.wasm|output.arkc] [--sign key.pem] [--run] [--error-format human|json]"
+        );
         process::exit(1);
     }
 
     let filename = &args[0];
     let run_after = args.iter().any(|a| a == "--run");
+    let format = error_format(args);
+    let signing_key = args.iter().position(|a| a == "--sign").map(|i| {
+        let Some(path) = args.get(i + 1) else {
+            eprintln!("Error: --sign requires a key file");
+            process::exit(1);
+        };
+        artifact::load_signing_key(Path::new(path)).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        })
+    });
+    let signed = if signing_key.is_some() {
+        ", signed"
+    } else {
+        ""
+    };
 
     // Parse -o flag
     let output_path = if args.len() >= 3 && args[1] == "-o" {
@@ -151,14 +342,24 @@ fn cmd_build(args: &[String]) {
         process::exit(1);
     });
 
-    // Parse source
-    let ast = match parser::parse_source(&source, filename) {
-        Ok(node) => node,
-        Err(e) => {
-            eprintln!("{}", e);
+    // Parse source and link its imports
+    let (ast, linker) = link_program(&source, filename, format);
+
+    if output_path.ends_with(".arkc") {
+        let program = CompiledProgram::new(ast, signing_key.as_ref()).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
             process::exit(1);
-        }
-    };
+        });
+        fs::write(&output_path, program.to_json()).unwrap_or_else(|e| {
+            eprintln!("Error: Cannot write '{}': {}", output_path, e);
+            process::exit(1);
+        });
+        println!(
+            "✓ Compiled program written: {} (hash {}{})",
+            output_path, program.mast.hash, signed
+        );
+        return;
+    }
 
     // Compile to WASM
     println!("Compiling {} → {}", filename, output_path);
@@ -170,15 +371,23 @@ fn cmd_build(args: &[String]) {
                 eprintln!("The compiled binary is malformed — this is a compiler bug.");
                 process::exit(1);
             }
+            let wasm_bytes = match &signing_key {
+                Some(key) => artifact::sign_wasm(&wasm_bytes, key).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }),
+                None => wasm_bytes,
+            };
 
             fs::write(&output_path, &wasm_bytes).unwrap_or_else(|e| {
                 eprintln!("Error: Cannot write '{}': {}", output_path, e);
                 process::exit(1);
             });
             println!(
-                "✓ WASM binary written: {} ({} bytes, validated)",
+                "✓ WASM binary written: {} ({} bytes, validated{})",
                 output_path,
-                wasm_bytes.len()
+                wasm_bytes.len(),
+                signed
             );
 
             // If --run flag, execute the compiled WASM immediately
@@ -199,7 +408,7 @@ fn cmd_build(args: &[String]) {
             }
         }
         Err(e) => {
-            eprintln!("WASM Compilation Error: {}", e);
+            report_linked_diagnostics(format, &[e.to_diagnostic()], &linker);
             process::exit(1);
         }
     }
@@ -212,11 +421,14 @@ fn cmd_build(args: &[String]) {
 /// Execute a compiled WASM binary using the wasmtime runtime.
 ///
 /// Usage:
-///   ark run-wasm <file.wasm>
+///   ark run-wasm <file.wasm> [args...]
+///
+/// The program sees `<file.wasm> [args...]` as its arguments and inherits
+/// the environment.
 fn cmd_run_wasm(args: &[String]) {
     if args.is_empty() {
         eprintln!("Error: 'run-wasm' requires a .wasm file argument");
-        eprintln!("Usage: ark run-wasm <file.wasm>");
+        eprintln!("Usage: ark run-wasm <file.wasm> [args...]");
         process::exit(1);
     }
 
@@ -228,7 +440,12 @@ fn cmd_run_wasm(args: &[String]) {
 
     println!("Executing {} via wasmtime...", filename);
 
-    match wasm_runner::run_wasm(&wasm_bytes) {
+    let state = wasm_runner::HostState {
+        args: args.to_vec(),
+        env: env::vars().collect(),
+        ..Default::default()
+    };
+    match wasm_runner::run_wasm_with_state(&wasm_bytes, state) {
         Ok(output) => {
             if !output.stdout.is_empty() {
                 print!("{}", output.stdout);
@@ -310,80 +527,105 @@ fn cmd_wit(args: &[String]) {
 
 /// Run an Ark program from either .ark source or .json MAST
 fn cmd_run(args: &[String]) {
+    // Flags go before the file; everything after it is the script's. The
+    // program may only do what `--allow-*` flags grant, nothing without.
+    let mut args = args;
+    let mut backend = "vm";
+    let mut capabilities = Capabilities::none();
+    let mut granted = false;
+    // `--profile[=<file>]`: where to write the folded stacks, if anywhere
+    let mut profile: Option<Option<String>> = None;
+    // `--record[=<file>]`: where to write the execution trace, if anywhere
+    let mut record: Option<Option<String>> = None;
+    while let Some(flag) = args.first().filter(|a| a.starts_with("--")) {
+        if flag == "--profile" || flag.starts_with("--profile=") {
+            profile = Some(flag.strip_prefix("--profile=").map(str::to_string));
+            args = &args[1..];
+            continue;
+        }
+        if flag == "--record" || flag.starts_with("--record=") {
+            record = Some(flag.strip_prefix("--record=").map(str::to_string));
+            args = &args[1..];
+            continue;
+        }
+        if flag == "--backend" {
+            let Some(name) = args.get(1) else {
+                eprintln!("Error: --backend requires vm or native");
+                process::exit(1);
+            };
+            backend = name.as_str();
+            args = &args[2..];
+            continue;
+        }
+        if !capabilities.grant(flag) {
+            eprintln!("Error: unknown flag '{}'", flag);
+            process::exit(1);
+        }
+        granted = true;
+        args = &args[1..];
+    }
+    if !matches!(backend, "vm" | "native") {
+        eprintln!(
+            "Error: unknown backend '{}' (expected vm or native)",
+            backend
+        );
+        process::exit(1);
+    }
     if args.is_empty() {
         eprintln!("Error: 'run' requires a file argument");
-        eprintln!("Usage: ark run <file.ark>");
+        eprintln!(
+            "Usage: ark run [--backend vm|native] [--allow-*] [--profile[=<file>]] [--record[=<file>]] <file.ark>"
+        );
         process::exit(1);
     }
 
     let filename = &args[0];
-    let raw_source = fs::read_to_string(filename).unwrap_or_else(|e| {
+    let source = fs::read_to_string(filename).unwrap_or_else(|e| {
         eprintln!("Error: Cannot read '{}': {}", filename, e);
         process::exit(1);
     });
+    // sys.args sees the script path first, then whatever followed it
+    ark_0_zheng::intrinsics::set_script_args(args.to_vec());
 
-    // ── Import Resolution ──
-    // Scan source for `import lib.std.*` lines, load and prepend module sources.
-    let mut prepend = String::new();
-    for line in raw_source.lines() {
-        let trimmed = line.trim();
-        if trimmed.starts_with("import ") {
-            let module_path = trimmed
-                .strip_prefix("import ")
-                .expect("import prefix verified")
-                .trim();
-            // Convert dotted path to file path: lib.std.string -> lib/std/string.ark
-            let file_path = format!("{}.ark", module_path.replace('.', "/"));
-            if let Ok(module_source) = fs::read_to_string(&file_path) {
-                prepend.push_str(&format!("// [import: {}]\n", module_path));
-                prepend.push_str(&module_source);
-                prepend.push('\n');
-            }
-        }
-    }
-
-    // Build final source: prepended modules + original source with import lines commented out
-    let source = if prepend.is_empty() {
-        raw_source
-    } else {
-        let filtered: Vec<&str> = raw_source
-            .lines()
-            .map(|l| {
-                if l.trim().starts_with("import ") {
-                    "// (import resolved)"
-                } else {
-                    l
-                }
-            })
-            .collect();
-        format!("{}\n{}", prepend, filtered.join("\n"))
-    };
+    // Compiled programs only run if their hash and signature verify; with
+    // trust roots set, one that cannot be signed never runs
+    let policy = TrustPolicy::from_env().unwrap_or_else(|e| {
+        eprintln!("Error: ARK_TRUSTED_KEYS: {}", e);
+        process::exit(1);
+    });
 
     // Determine file type
     let ast = if filename.ends_with(".ark") {
-        // Native Ark source → parse directly
-        match parser::parse_source(&source, filename) {
-            Ok(node) => node,
+        // Native Ark source → parse and link its imports, keeping function
+        // spans for backtraces
+        link_program(&source, filename, ErrorFormat::Human).0
+    } else if filename.ends_with(".arkc") {
+        match load_compiled_program(&source, &policy) {
+            Ok(mast) => mast.content,
             Err(e) => {
-                eprintln!("{}", e);
+                eprintln!("Load Error: {}", e);
                 process::exit(1);
             }
         }
     } else if filename.ends_with(".json") {
         // Legacy JSON MAST
-        match load_ark_program(&source) {
+        match load_unsigned_program(&source, &policy) {
             Ok(mast) => mast.content,
             Err(e) => {
-                eprintln!("Load Error: {:?}", e);
+                eprintln!("Load Error: {}", e);
                 process::exit(1);
             }
         }
     } else {
         // Try as .ark first, fall back to JSON
-        match parser::parse_source(&source, filename) {
+        match linker_for(filename).link(&source, filename) {
             Ok(node) => node,
-            Err(_) => match load_ark_program(&source) {
+            Err(_) => match load_unsigned_program(&source, &policy) {
                 Ok(mast) => mast.content,
+                Err(e @ loader::LoadError::Signature(_)) => {
+                    eprintln!("Load Error: {}", e);
+                    process::exit(1);
+                }
                 Err(e) => {
                     eprintln!(
                         "Error: Cannot parse '{}' as Ark source or JSON MAST: {:?}",
@@ -395,6 +637,23 @@ fn cmd_run(args: &[String]) {
         }
     };
 
+    if backend == "native" {
+        if granted {
+            eprintln!("Error: --allow-* flags need the vm backend");
+            process::exit(1);
+        }
+        if profile.is_some() {
+            eprintln!("Error: --profile needs the vm backend");
+            process::exit(1);
+        }
+        if record.is_some() {
+            eprintln!("Error: --record needs the vm backend");
+            process::exit(1);
+        }
+        run_native(&ast);
+        return;
+    }
+
     // Build program args for VM
     let mut ark_args = Vec::new();
     for arg in args {
@@ -414,7 +673,14 @@ fn cmd_run(args: &[String]) {
         .parse::<u8>()
         .unwrap_or(0);
 
-    match VM::new(chunk, &hash, security_level) {
+    let config = VmConfig {
+        security_level,
+        capabilities: Some(capabilities),
+        profile: profile.is_some(),
+        record_trace: record.is_some().then_some(trace::DEFAULT_CAPACITY),
+        ..VmConfig::default()
+    };
+    match VM::with_config(chunk, &hash, config) {
         Ok(mut vm) => {
             // Inject args
             if let Some(scope) = vm.scopes.get_mut(0) {
@@ -425,10 +691,27 @@ fn cmd_run(args: &[String]) {
             }
 
             // Execute
-            match vm.run() {
+            let result = vm.run();
+            if let (Some(path), Some(report)) = (&profile, vm.profile()) {
+                let path = path
+                    .clone()
+                    .unwrap_or_else(|| format!("{}.folded", filename));
+                write_profile(report, &path);
+            }
+            if let (Some(path), Some(recording)) = (&record, vm.execution_trace()) {
+                let path = path
+                    .clone()
+                    .unwrap_or_else(|| format!("{}.trace.jsonl", filename));
+                write_trace(recording, &path);
+            }
+            match result {
                 Ok(_) => {}
                 Err(e) => {
                     eprintln!("Runtime Error: {}", e);
+                    eprintln!(
+                        "{}",
+                        ark_0_zheng::vm::format_backtrace(&ark_0_zheng::vm::last_error_trace())
+                    );
                     process::exit(1);
                 }
             }
@@ -440,7 +723,38 @@ fn cmd_run(args: &[String]) {
     }
 }
 
-/// Check linear types in a program
+/// Print a profile's summary table to stderr and write its folded stacks
+/// to `path`, for flamegraph tools.
+fn write_profile(profile: &Profile, path: &str) {
+    eprint!("{}", profile.summary());
+    match fs::write(path, profile.folded()) {
+        Ok(()) => eprintln!("Folded stacks written to {}", path),
+        Err(e) => eprintln!("Error: Cannot write '{}': {}", path, e),
+    }
+}
+
+/// Write a recorded execution trace to `path` as JSON lines, for
+/// `ark debug --replay`.
+fn write_trace(recording: &ExecutionTrace, path: &str) {
+    match fs::write(path, recording.to_json_lines()) {
+        Ok(()) => eprintln!(
+            "Trace of the last {} instructions written to {}",
+            recording.len(),
+            path
+        ),
+        Err(e) => eprintln!("Error: Cannot write '{}': {}", path, e),
+    }
+}
+
+/// JIT-compile a parsed program with the Cranelift backend and run it.
+fn run_native(ast: &ark_0_zheng::ast::ArkNode) {
+    if let Err(e) = ark_0_zheng::native_codegen::run(ast) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+/// Check linear and static types in a program
 fn cmd_check(args: &[String]) {
     if args.is_empty() {
         eprintln!("Error: 'check' requires a file argument");
@@ -448,22 +762,17 @@ fn cmd_check(args: &[String]) {
     }
 
     let filename = &args[0];
+    let format = error_format(args);
     let source = fs::read_to_string(filename).unwrap_or_else(|e| {
         eprintln!("Error: Cannot read '{}': {}", filename, e);
         process::exit(1);
     });
 
-    let ast = if filename.ends_with(".ark") {
-        match parser::parse_source(&source, filename) {
-            Ok(node) => node,
-            Err(e) => {
-                eprintln!("{}", e);
-                process::exit(1);
-            }
-        }
+    let (ast, linker) = if filename.ends_with(".ark") {
+        link_program(&source, filename, format)
     } else {
         match load_ark_program(&source) {
-            Ok(mast) => mast.content,
+            Ok(mast) => (mast.content, Linker::from_env()),
             Err(e) => {
                 eprintln!("Load Error: {:?}", e);
                 process::exit(1);
@@ -472,15 +781,184 @@ fn cmd_check(args: &[String]) {
     };
 
     println!("Running Linear Check on {}...", filename);
-    match ark_0_zheng::checker::LinearChecker::check(&ast) {
-        Ok(_) => println!("✓ Linear Check Passed"),
+    match ark_0_zheng::checker::LinearChecker::check_diagnostics(&ast) {
+        Ok(diagnostics) if diagnostics.is_empty() => println!("✓ Linear Check Passed"),
+        Ok(diagnostics) => {
+            println!("✓ Linear Check Passed");
+            let rendered: Vec<Diagnostic> = diagnostics.iter().map(|d| d.to_diagnostic()).collect();
+            report_linked_diagnostics(format, &rendered, &linker);
+            if format == ErrorFormat::Human {
+                eprintln!("✗ Type Check Failed: {} error(s)", diagnostics.len());
+            }
+            process::exit(1);
+        }
         Err(e) => {
-            eprintln!("✗ Linear Check Failed: {}", e);
+            report_linked_diagnostics(format, &[e.to_diagnostic()], &linker);
+            if format == ErrorFormat::Human {
+                eprintln!("✗ Linear Check Failed");
+            }
             process::exit(1);
         }
     }
 }
 
+// =============================================================================
+// PKG — Manage package dependencies
+// =============================================================================
+
+/// Manage the dependencies in `ark.toml`.
+///
+/// Usage:
+///   ark pkg add <name>[@<version>]             Registry dependency
+///   ark pkg add <name> --git <url> [--rev <rev>]
+///   ark pkg add <name> --path <dir>
+///   ark pkg install
+///   ark pkg publish [--token <token>]
+///
+/// `add` creates `ark.toml` in the working directory if no project
+/// encloses it, then installs. The registry is `ARK_REGISTRY` or the
+/// manifest's `[registry] url`; `publish` also reads `ARK_REGISTRY_TOKEN`.
+fn cmd_pkg(args: &[String]) {
+    fn usage() -> ! {
+        eprintln!(
+            "Usage: ark pkg add <name>[@<version>] [--git <url> [--rev <rev>] | --path <dir>]"
+        );
+        eprintln!("       ark pkg install");
+        eprintln!("       ark pkg publish [--token <token>]");
+        process::exit(1);
+    }
+    fn fail(e: PackageError) -> ! {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+    let flag = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .and_then(|i| args.get(i + 1))
+            .cloned()
+    };
+    let cwd = env::current_dir().unwrap_or_else(|e| {
+        eprintln!("Error: Cannot read the working directory: {}", e);
+        process::exit(1);
+    });
+    let project = || {
+        package::find_project(&cwd)
+            .unwrap_or_else(|| fail(PackageError::NoManifest(cwd.display().to_string())))
+    };
+    let installer = Installer::from_env();
+
+    match args.first().map(String::as_str) {
+        Some("add") => {
+            let Some(spec) = args.get(1).filter(|a| !a.starts_with("--")) else {
+                usage()
+            };
+            let (name, requirement) = spec.split_once('@').unwrap_or((spec.as_str(), "*"));
+            let source = if let Some(url) = flag("--git") {
+                Source::Git {
+                    url,
+                    rev: flag("--rev"),
+                }
+            } else if let Some(dir) = flag("--path") {
+                Source::Path(dir.into())
+            } else {
+                Source::Registry(VersionReq::parse(requirement).unwrap_or_else(|e| fail(e)))
+            };
+
+            let dir = package::find_project(&cwd).unwrap_or_else(|| {
+                // Package names are lowercase letters, digits, `_` and `-`
+                let name = cwd.file_name().map_or("app".to_string(), |n| {
+                    n.to_string_lossy()
+                        .to_lowercase()
+                        .chars()
+                        .map(|c| match c {
+                            'a'..='z' | '0'..='9' | '-' => c,
+                            _ => '_',
+                        })
+                        .collect()
+                });
+                let path = cwd.join(package::MANIFEST);
+                fs::write(&path, Manifest::template(&name)).unwrap_or_else(|e| {
+                    eprintln!("Error: Cannot write '{}': {}", path.display(), e);
+                    process::exit(1);
+                });
+                println!("Created {}", path.display());
+                cwd.clone()
+            });
+            let dependency = Dependency {
+                name: name.to_string(),
+                source,
+            };
+            package::add_dependency(&dir, &dependency).unwrap_or_else(|e| fail(e));
+            println!("Added {}", dependency.to_toml());
+            let locked = installer.install(&dir).unwrap_or_else(|e| fail(e));
+            println!(
+                "✓ {} package(s) installed, {} written",
+                locked.len(),
+                package::LOCKFILE
+            );
+        }
+        Some("install") => {
+            let locked = installer.install(&project()).unwrap_or_else(|e| fail(e));
+            for p in &locked {
+                println!("  {} {} ({})", p.name, p.version, p.source);
+            }
+            println!(
+                "✓ {} package(s) installed, {} written",
+                locked.len(),
+                package::LOCKFILE
+            );
+        }
+        Some("publish") => {
+            let token = flag("--token").or_else(|| env::var("ARK_REGISTRY_TOKEN").ok());
+            let manifest = installer
+                .publish(&project(), token.as_deref())
+                .unwrap_or_else(|e| fail(e));
+            println!("✓ Published {} {}", manifest.name, manifest.version);
+        }
+        _ => usage(),
+    }
+}
+
+// =============================================================================
+// KEYGEN — Create a key for signing build artifacts
+// =============================================================================
+
+/// Create an Ed25519 key for `ark build --sign`.
+///
+/// Usage:
+///   ark keygen <key.pem>
+///
+/// Writes the private key to `<key.pem>` and the public key, for
+/// `ARK_TRUSTED_KEYS`, to `<key>.pub.pem`. Neither file is overwritten.
+fn cmd_keygen(args: &[String]) {
+    let Some(path) = args.first() else {
+        eprintln!("Error: 'keygen' requires an output file");
+        eprintln!("Usage: ark keygen <key.pem>");
+        process::exit(1);
+    };
+    let private = Path::new(path);
+    let public = private.with_extension("pub.pem");
+    let key = ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng);
+
+    let write_new = |path: &Path, contents: String| {
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options
+            .open(path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .unwrap_or_else(|e| {
+                eprintln!("Error: Cannot write '{}': {}", path.display(), e);
+                process::exit(1);
+            });
+    };
+    write_new(private, artifact::signing_key_pem(&key));
+    write_new(&public, artifact::verifying_key_pem(&key.verifying_key()));
+    println!("✓ Signing key written: {}", private.display());
+    println!("✓ Public key written: {}", public.display());
+}
+
 // =============================================================================
 // DIAGNOSE — Run the diagnostic proof suite
 // =============================================================================
@@ -952,13 +1430,378 @@ fn cmd_parse(args: &[String]) {
     }
 }
 
+/// Every `.ark` file under `path`, or `path` itself if it is a file.
+fn collect_ark_files(path: &Path, files: &mut Vec<std::path::PathBuf>) {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return;
+    }
+    let mut entries: Vec<_> = match fs::read_dir(path) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+        Err(e) => {
+            eprintln!("Error: Cannot read '{}': {}", path.display(), e);
+            return;
+        }
+    };
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() || entry.extension().is_some_and(|ext| ext == "ark") {
+            collect_ark_files(&entry, files);
+        }
+    }
+}
+
+/// Format Ark sources in place.
+///
+/// Usage:
+///   ark fmt [--check] <file.ark|dir>...
+///
+/// With --check, files are left untouched and the command fails if any of
+/// them is not formatted.
+fn cmd_fmt(args: &[String]) {
+    let check = args.iter().any(|a| a == "--check");
+    let paths: Vec<&String> = args.iter().filter(|a| *a != "--check").collect();
+    if paths.is_empty() {
+        eprintln!("Error: 'fmt' requires a file or directory argument");
+        eprintln!("Usage: ark fmt [--check] <file.ark|dir>...");
+        process::exit(1);
+    }
+
+    let mut files = Vec::new();
+    for path in paths {
+        collect_ark_files(Path::new(path), &mut files);
+    }
+
+    let mut failed = false;
+    for file in &files {
+        let name = file.display().to_string();
+        let source = match fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("Error: Cannot read '{}': {}", name, e);
+                failed = true;
+                continue;
+            }
+        };
+        let formatted = match formatter::format_source(&source, &name) {
+            Ok(formatted) => formatted,
+            Err(FormatError::Parse(e)) => {
+                eprintln!("{}", e.to_diagnostic().render(&source));
+                failed = true;
+                continue;
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                failed = true;
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("Would reformat: {}", name);
+            failed = true;
+        } else if let Err(e) = fs::write(file, &formatted) {
+            eprintln!("Error: Cannot write '{}': {}", name, e);
+            failed = true;
+        } else {
+            println!("Formatted {}", name);
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// Run the `#[test]` functions of an Ark program.
+///
+/// Usage:
+///   ark test [--wasm] <file.ark>
+///
+/// Each test runs on a fresh VM after the program's top level. Failed
+/// `assert`, `assert_eq` and `assert_err` calls are collected rather than
+/// stopping the test; a test passes if none failed and it raised no error.
+/// With --wasm every test is also compiled to WebAssembly and run there.
+fn cmd_test(args: &[String]) {
+    let wasm = args.iter().any(|a| a == "--wasm");
+    let Some(filename) = args.iter().find(|a| *a != "--wasm") else {
+        eprintln!("Error: 'test' requires a file argument");
+        eprintln!("Usage: ark test [--wasm] <file.ark>");
+        process::exit(1);
+    };
+    let source = fs::read_to_string(filename).unwrap_or_else(|e| {
+        eprintln!("Error: Cannot read '{}': {}", filename, e);
+        process::exit(1);
+    });
+    let (ast, _) = link_program(&source, filename, ErrorFormat::Human);
+    let tests = loader::test_functions(&ast);
+    if tests.is_empty() {
+        println!("No #[test] functions in {}", filename);
+        return;
+    }
+
+    let wasm_bytes = wasm.then(|| {
+        WasmCodegen::compile_to_bytes(&export_functions(ast.clone(), &tests)).unwrap_or_else(|e| {
+            eprintln!("Error: cannot compile the tests to WASM: {}", e);
+            process::exit(1);
+        })
+    });
+    let chunk = Compiler::new().compile(&ast);
+
+    println!("running {} test(s) from {}", tests.len(), filename);
+    let mut failures: Vec<(String, Vec<String>)> = Vec::new();
+    for test in &tests {
+        let mut runs = vec![(test.clone(), run_vm_test(&chunk, filename, test))];
+        if let Some(bytes) = &wasm_bytes {
+            runs.push((format!("{} (wasm)", test), run_wasm_test(bytes, test)));
+        }
+        for (name, problems) in runs {
+            if problems.is_empty() {
+                println!("test {} ... ok", name);
+            } else {
+                println!("test {} ... FAILED", name);
+                failures.push((name, problems));
+            }
+        }
+    }
+
+    let total = tests.len() * if wasm { 2 } else { 1 };
+    if !failures.is_empty() {
+        println!();
+        println!("failures:");
+        for (name, problems) in &failures {
+            println!();
+            println!("---- {} ----", name);
+            for problem in problems {
+                println!("{}", problem);
+            }
+        }
+    }
+    println!();
+    println!(
+        "test result: {}. {} passed; {} failed",
+        if failures.is_empty() { "ok" } else { "FAILED" },
+        total - failures.len(),
+        failures.len()
+    );
+    if !failures.is_empty() {
+        process::exit(1);
+    }
+}
+
+/// Run one test on a fresh VM, returning what went wrong.
+fn run_vm_test(chunk: &ark_0_zheng::bytecode::Chunk, filename: &str, test: &str) -> Vec<String> {
+    let hash = format!("ark_test_{}", filename);
+    let mut vm = match VM::new(chunk.clone(), &hash, 0) {
+        Ok(vm) => vm,
+        Err(e) => return vec![format!("VM Initialization Error: {}", e)],
+    };
+    if let Err(e) = vm.run() {
+        return vec![format!("Runtime Error: {}", e)];
+    }
+    intrinsics::begin_test();
+    let result = vm.call_public_function(test, Vec::new());
+    let mut problems = intrinsics::end_test();
+    if let Err(e) = result {
+        problems.push(format!("Runtime Error: {}", e));
+    }
+    problems
+}
+
+/// Run one exported test in WASM, returning what went wrong: the assertion
+/// failures it logged, or the trap that stopped it.
+fn run_wasm_test(bytes: &[u8], test: &str) -> Vec<String> {
+    match wasm_runner::call_exported_with_output(bytes, test, &[]) {
+        Ok((_, output)) => output
+            .stderr
+            .lines()
+            .filter_map(|line| line.strip_prefix("[ERROR] "))
+            .filter(|line| line.starts_with("assertion failed"))
+            .map(str::to_string)
+            .collect(),
+        Err(e) => vec![format!("WASM Error: {}", e.message)],
+    }
+}
+
+/// `program` with the named functions exported, so the WASM host can call
+/// them.
+fn export_functions(
+    mut program: ark_0_zheng::ast::ArkNode,
+    names: &[String],
+) -> ark_0_zheng::ast::ArkNode {
+    use ark_0_zheng::ast::{ArkNode, Statement};
+    if let ArkNode::Statement(Statement::Block(stmts)) = &mut program {
+        for stmt in stmts {
+            match stmt {
+                Statement::Function(func)
+                    if names.contains(&func.name)
+                        && !func.attributes.iter().any(|a| a == "export") =>
+                {
+                    func.attributes.push("export".to_string());
+                }
+                _ => {}
+            }
+        }
+    }
+    program
+}
+
+/// Benchmark the `#[bench]` functions of an Ark program.
+///
+/// Usage:
+///   ark bench [--wasm] [--json] <file.ark>
+///
+/// Each benchmark is warmed up, then timed over several samples on one VM
+/// (see [`ark_0_zheng::bench`]). With --wasm it is timed the same way
+/// compiled to WebAssembly, and the two are compared. --json prints one
+/// JSON object per measurement instead of the table, for keeping results
+/// to compare against later.
+fn cmd_bench(args: &[String]) {
+    let wasm = args.iter().any(|a| a == "--wasm");
+    let json = args.iter().any(|a| a == "--json");
+    let Some(filename) = args.iter().find(|a| !a.starts_with("--")) else {
+        eprintln!("Error: 'bench' requires a file argument");
+        eprintln!("Usage: ark bench [--wasm] [--json] <file.ark>");
+        process::exit(1);
+    };
+    let source = fs::read_to_string(filename).unwrap_or_else(|e| {
+        eprintln!("Error: Cannot read '{}': {}", filename, e);
+        process::exit(1);
+    });
+    let (ast, _) = link_program(&source, filename, ErrorFormat::Human);
+    let benches = loader::bench_functions(&ast);
+    if benches.is_empty() {
+        println!("No #[bench] functions in {}", filename);
+        return;
+    }
+
+    let wasm_bytes = wasm.then(|| {
+        let program = export_functions(ast.clone(), &benches);
+        WasmCodegen::compile_to_bytes(&program).unwrap_or_else(|e| {
+            eprintln!("Error: cannot compile the benchmarks to WASM: {}", e);
+            process::exit(1);
+        })
+    });
+    let chunk = Compiler::new().compile(&ast);
+    let config = BenchConfig::default();
+    let width = benches.iter().map(|b| b.len()).max().unwrap_or(0);
+
+    if !json {
+        println!("running {} benchmark(s) from {}", benches.len(), filename);
+    }
+    let mut failed = false;
+    for name in &benches {
+        let mut results = vec![("vm", bench_vm(&chunk, filename, name, &config))];
+        if let Some(bytes) = &wasm_bytes {
+            let timed = bench::measure(&config, |n| {
+                wasm_runner::time_exported(bytes, name, n).map_err(|e| e.message)
+            });
+            results.push(("wasm", timed));
+        }
+        for (backend, result) in &results {
+            match result {
+                Ok(stats) if json => println!(
+                    "{}",
+                    serde_json::json!({
+                        "bench": name,
+                        "backend": backend,
+                        "iterations": stats.iterations,
+                        "samples": stats.samples,
+                        "mean_ns": stats.mean_ns,
+                        "median_ns": stats.median_ns,
+                        "std_dev_ns": stats.std_dev_ns,
+                        "min_ns": stats.min_ns,
+                        "max_ns": stats.max_ns,
+                        "per_second": stats.per_second(),
+                    })
+                ),
+                Ok(stats) => println!(
+                    "bench {:<width$} {:<4} {:>10}/iter (± {:>4.1}%) {:>14.0} iter/s",
+                    name,
+                    backend,
+                    bench::format_ns(stats.median_ns),
+                    stats.relative_std_dev(),
+                    stats.per_second(),
+                ),
+                Err(e) => {
+                    eprintln!("bench {} ({}) failed: {}", name, backend, e);
+                    failed = true;
+                }
+            }
+        }
+        match results.as_slice() {
+            [(_, Ok(vm)), (_, Ok(wasm))] if !json => println!(
+                "      {:<width$} wasm is {:.2}x the speed of vm",
+                "",
+                vm.median_ns / wasm.median_ns
+            ),
+            _ => {}
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// Time one benchmark on a VM that has run the program's top level.
+fn bench_vm(
+    chunk: &ark_0_zheng::bytecode::Chunk,
+    filename: &str,
+    name: &str,
+    config: &BenchConfig,
+) -> Result<Stats, String> {
+    let hash = format!("ark_bench_{}", filename);
+    let mut vm = VM::new(chunk.clone(), &hash, 0).map_err(|e| e.to_string())?;
+    vm.run().map_err(|e| e.to_string())?;
+    bench::measure(config, |n| {
+        let start = Instant::now();
+        for _ in 0..n {
+            // The step limit is per call, not per benchmark
+            vm.step_count = 0;
+            vm.call_public_function(name, Vec::new())
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(start.elapsed())
+    })
+}
+
 /// Interactive step-through debugger
 fn cmd_debug(args: &[String]) {
+    // `--listen <addr>` hands the session to a frontend over TCP;
+    // `--replay` steps through a recorded trace instead of a program
+    let mut args = args;
+    let mut listen: Option<&str> = None;
+    let mut replay = false;
+    while let Some(flag) = args.first().filter(|a| a.starts_with("--")) {
+        match flag.as_str() {
+            "--replay" => {
+                replay = true;
+                args = &args[1..];
+            }
+            "--listen" => {
+                let Some(addr) = args.get(1) else {
+                    eprintln!("Error: --listen requires an address, e.g. 127.0.0.1:4711");
+                    process::exit(1);
+                };
+                listen = Some(addr.as_str());
+                args = &args[2..];
+            }
+            _ => {
+                eprintln!("Error: unknown flag '{}'", flag);
+                process::exit(1);
+            }
+        }
+    }
     if args.is_empty() {
         eprintln!("Error: 'debug' requires a file argument");
-        eprintln!("Usage: ark debug <file.ark>");
+        eprintln!("Usage: ark debug [--listen <addr>] [--replay] <file.ark|trace.jsonl>");
         process::exit(1);
     }
+    if replay {
+        replay_trace(&args[0], listen);
+        return;
+    }
 
     let filename = &args[0];
     let source = fs::read_to_string(filename).unwrap_or_else(|e| {
@@ -969,7 +1812,7 @@ fn cmd_debug(args: &[String]) {
     // Store source lines for display
     let source_lines: Vec<&str> = source.lines().collect();
 
-    let ast = match parser::parse_source(&source, filename) {
+    let ast = match parser::parse_source_with_spans(&source, filename) {
         Ok(node) => node,
         Err(e) => {
             eprintln!("{}", e);
@@ -984,6 +1827,11 @@ fn cmd_debug(args: &[String]) {
     // Hash for VM
     let hash = format!("ark_debug_{}", filename);
 
+    if let Some(addr) = listen {
+        debug_remote(chunk, &hash, addr);
+        return;
+    }
+
     // Setup VM with debugger
     match VM::new(chunk, &hash, 0) {
         Ok(mut vm) => {
@@ -1191,6 +2039,10 @@ fn cmd_debug(args: &[String]) {
                 }
                 Err(e) => {
                     eprintln!("\x1b[1;31m[ARK DEBUGGER]\x1b[0m Runtime Error: {}", e);
+                    eprintln!(
+                        "{}",
+                        ark_0_zheng::vm::format_backtrace(&ark_0_zheng::vm::last_error_trace())
+                    );
                     process::exit(1);
                 }
             }
@@ -1202,11 +2054,111 @@ fn cmd_debug(args: &[String]) {
     }
 }
 
+/// Wait for a debugger frontend to connect to `addr`.
+fn accept_frontend(addr: &str) -> TcpStream {
+    let listener = TcpListener::bind(addr).unwrap_or_else(|e| {
+        eprintln!("Error: Cannot listen on {}: {}", addr, e);
+        process::exit(1);
+    });
+    eprintln!(
+        "\x1b[1;36m[ARK DEBUGGER]\x1b[0m Waiting for a frontend on {}",
+        addr
+    );
+    match listener.accept() {
+        Ok((stream, _)) => stream,
+        Err(e) => {
+            eprintln!("Error: Cannot accept a connection: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Another handle on the frontend's connection.
+fn clone_stream(stream: &TcpStream) -> TcpStream {
+    stream.try_clone().unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    })
+}
+
+/// Run a program with a frontend on `addr` stepping it, and tell the
+/// frontend how the run finished.
+fn debug_remote(chunk: ark_0_zheng::bytecode::Chunk, hash: &str, addr: &str) {
+    let mut vm = VM::new(chunk, hash, 0).unwrap_or_else(|e| {
+        eprintln!("VM Initialization Error: {}", e);
+        process::exit(1);
+    });
+    let stream = accept_frontend(addr);
+    vm.debug_hook = Some(trace::live_hook(
+        BufReader::new(clone_stream(&stream)),
+        clone_stream(&stream),
+    ));
+    let finished = match vm.run() {
+        Ok(result) => Event::Finished {
+            result: Some(debugger::format_value(&result)),
+            error: None,
+        },
+        Err(e) => Event::Finished {
+            result: None,
+            error: Some(e.to_string()),
+        },
+    };
+    // The frontend may already have gone
+    let _ = trace::send(&mut clone_stream(&stream), &finished);
+}
+
+/// Serve the Debug Adapter Protocol to one editor. It is spoken over TCP
+/// so that the program's own output stays out of the protocol stream.
+#[cfg(feature = "ipc")]
+fn cmd_dap(args: &[String]) {
+    let addr = match args {
+        [] => "127.0.0.1:4711",
+        [flag, addr] if flag == "--listen" => addr.as_str(),
+        _ => {
+            eprintln!("Usage: ark dap [--listen <addr>]");
+            process::exit(1);
+        }
+    };
+    let stream = accept_frontend(addr);
+    let served = ark_0_zheng::bridge::serve(BufReader::new(clone_stream(&stream)), stream);
+    if let Err(e) = served {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+/// Serve a trace written by `ark run --record` to a frontend: over TCP
+/// with `--listen`, otherwise on stdin and stdout.
+fn replay_trace(path: &str, listen: Option<&str>) {
+    let text = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Error: Cannot read '{}': {}", path, e);
+        process::exit(1);
+    });
+    let entries = trace::parse_json_lines(&text).unwrap_or_else(|e| {
+        eprintln!("Error: '{}' is not a recorded trace: {}", path, e);
+        process::exit(1);
+    });
+    let mut cursor = TraceCursor::new(entries);
+    let served = match listen {
+        Some(addr) => {
+            let stream = accept_frontend(addr);
+            trace::serve(&mut cursor, BufReader::new(clone_stream(&stream)), stream)
+        }
+        None => trace::serve(&mut cursor, io::stdin().lock(), io::stdout().lock()),
+    };
+    if let Err(e) = served {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
 // =============================================================================
 // REPL — Interactive Read-Eval-Print Loop
 // =============================================================================
 
-/// Interactive REPL with persistent state across lines.
+/// Interactive REPL on the VM. Definitions persist from one input to the
+/// next; input with a bracket or string left open continues on the next
+/// line. History is kept in `~/.ark_history`.
 fn cmd_repl() {
     println!("\x1b[1;36m╔══════════════════════════════════════════╗\x1b[0m");
     println!(
@@ -1220,36 +2172,30 @@ fn cmd_repl() {
     println!();
     println!("  Type \x1b[1;33m:help\x1b[0m for commands, \x1b[1;33m:quit\x1b[0m to exit.\n");
 
-    let stdin = io::stdin();
-    let mut line_num: usize = 0;
-
-    loop {
-        line_num += 1;
-        print!("\x1b[1;32mark[{}]\x1b[0m> ", line_num);
-        io::stdout().flush().unwrap_or(());
-
-        let mut input = String::new();
-        match stdin.lock().read_line(&mut input) {
-            Ok(0) => {
-                // EOF
-                println!();
-                break;
-            }
-            Ok(_) => {}
-            Err(e) => {
-                eprintln!("Read error: {}", e);
-                break;
-            }
-        }
+    let mut editor = DefaultEditor::new().unwrap_or_else(|e| {
+        eprintln!("Error: cannot start the line editor: {}", e);
+        process::exit(1);
+    });
+    let history = env::var_os("HOME").map(|home| Path::new(&home).join(".ark_history"));
+    if let Some(path) = &history {
+        // No history yet is fine
+        let _ = editor.load_history(path);
+    }
 
+    let mut session = Session::new();
+    let mut line_num: usize = 1;
+    while let Some(input) = read_repl_input(&mut editor, line_num) {
         let trimmed = input.trim();
         if trimmed.is_empty() {
-            line_num -= 1;
             continue;
         }
+        let _ = editor.add_history_entry(trimmed);
 
-        // Handle REPL meta-commands
-        match trimmed {
+        let (command, rest) = match trimmed.split_once(char::is_whitespace) {
+            Some((command, rest)) => (command, rest.trim()),
+            None => (trimmed, ""),
+        };
+        let reply = match command {
             ":quit" | ":q" | ":exit" => {
                 println!("\x1b[0;90mGoodbye.\x1b[0m");
                 break;
@@ -1289,36 +2235,66 @@ fn cmd_repl() {
                 );
                 continue;
             }
-            _ => {}
+            ":type" | ":t" | ":ast" | ":wasm" if rest.is_empty() => {
+                eprintln!("  Usage: {} <expression>", command);
+                continue;
+            }
+            ":type" | ":t" => session.type_of(rest),
+            ":ast" => repl::ast(rest),
+            ":wasm" => session.wasm(rest),
+            _ if command.starts_with(':') => {
+                eprintln!("  Unknown command '{}'; type :help for the list", command);
+                continue;
+            }
+            _ => {
+                line_num += 1;
+                match session.eval(&input) {
+                    Ok(Value::Unit) => continue,
+                    Ok(value) => Ok(adn::to_adn_pretty(&value)),
+                    Err(e) => Err(e),
+                }
+            }
+        };
+        match reply {
+            Ok(text) => println!("  \x1b[1;34m=>\x1b[0m {}", text),
+            Err(e) => eprintln!("  \x1b[1;31mError:\x1b[0m {}", e),
         }
+    }
 
-        // Try to parse and evaluate the input as Ark source
-        match parser::parse_source(trimmed, "<repl>") {
-            Ok(ast) => {
-                // Compile and execute
-                let compiler = Compiler::new();
-                let chunk = compiler.compile(&ast);
-                let hash = format!("repl_line_{}", line_num);
-                match VM::new(chunk, &hash, 0) {
-                    Ok(mut vm) => {
-                        match vm.run() {
-                            Ok(result) => {
-                                // Format output in ADN
-                                let adn_output = adn::to_adn_pretty(&result);
-                                println!("  \x1b[1;34m=>\x1b[0m {}", adn_output);
-                            }
-                            Err(e) => {
-                                eprintln!("  \x1b[1;31mRuntime Error:\x1b[0m {}", e);
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("  \x1b[1;31mVM Error:\x1b[0m {}", e);
-                    }
+    if let Some(path) = &history {
+        if let Err(e) = editor.save_history(path) {
+            eprintln!("Warning: cannot save history to {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// One REPL input, read over as many lines as it takes to close its
+/// brackets and strings. Ctrl-C drops what has been typed so far; `None`
+/// means end of input.
+fn read_repl_input(editor: &mut DefaultEditor, line_num: usize) -> Option<String> {
+    let mut input = String::new();
+    loop {
+        let prompt = if input.is_empty() {
+            format!("\x1b[1;32mark[{}]\x1b[0m> ", line_num)
+        } else {
+            "   ...> ".to_string()
+        };
+        match editor.readline(&prompt) {
+            Ok(line) => {
+                input.push_str(&line);
+                input.push('\n');
+                if !repl::is_incomplete(&input) {
+                    return Some(input);
                 }
             }
+            Err(ReadlineError::Interrupted) => return Some(String::new()),
+            Err(ReadlineError::Eof) => {
+                println!();
+                return None;
+            }
             Err(e) => {
-                eprintln!("  \x1b[1;31mParse Error:\x1b[0m {}", e);
+                eprintln!("Read error: {}", e);
+                return None;
             }
         }
     }
@@ -1330,13 +2306,18 @@ fn print_repl_help() {
     println!("  \x1b[1;33m:help\x1b[0m    (:h)    Show this help");
     println!("  \x1b[1;33m:quit\x1b[0m    (:q)    Exit the REPL");
     println!("  \x1b[1;33m:version\x1b[0m (:v)    Show version");
+    println!("  \x1b[1;33m:type\x1b[0m <e> (:t)  Show the type the checker gives e");
+    println!("  \x1b[1;33m:ast\x1b[0m <e>        Show the AST of e as JSON");
+    println!("  \x1b[1;33m:wasm\x1b[0m <e>       Show the WebAssembly text compiled for e");
     println!("  \x1b[1;33m:pvec\x1b[0m           Demo persistent vector");
     println!("  \x1b[1;33m:pmap\x1b[0m           Demo persistent map");
     println!();
     println!("  \x1b[1;37mExamples:\x1b[0m");
-    println!("  \x1b[0;90m  let x = 42\x1b[0m");
-    println!("  \x1b[0;90m  fn double(n: Int) -> Int {{ n + n }}\x1b[0m");
-    println!("  \x1b[0;90m  double(21)\x1b[0m");
+    println!("  \x1b[0;90m  x := 42\x1b[0m");
+    println!("  \x1b[0;90m  func double(n: Int) => Int {{\x1b[0m");
+    println!("  \x1b[0;90m      return n + n\x1b[0m");
+    println!("  \x1b[0;90m  }}\x1b[0m");
+    println!("  \x1b[0;90m  double(x)\x1b[0m");
     println!();
 }
 
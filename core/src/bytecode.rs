@@ -1,3 +1,4 @@
+use crate::ast::Span;
 use crate::runtime::Value;
 // Removed unused imports
 
@@ -58,6 +59,11 @@ pub enum OpCode {
 
     // Enums
     MakeEnum(String, String, usize), // (enum_name, variant, field_count)
+
+    // Error Handling
+    SetupTry(usize), // Catch block address
+    PopTry,
+    Propagate, // `?`: unwrap Ok/Some, or return Err/None
 }
 
 #[derive(Debug, Clone)]
@@ -66,6 +72,10 @@ pub struct Chunk {
     pub constants: Vec<Value>,
     pub source_map: Vec<SourceLocation>,
     current_loc: SourceLocation,
+    /// Function this chunk is the body of; `None` for a program's top level.
+    pub name: Option<String>,
+    /// Where that function is defined, if it was parsed with spans.
+    pub span: Option<Span>,
 }
 
 impl Default for Chunk {
@@ -81,6 +91,8 @@ impl Chunk {
             constants: Vec::new(),
             source_map: Vec::new(),
             current_loc: SourceLocation::default(),
+            name: None,
+            span: None,
         }
     }
 
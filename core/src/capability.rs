@@ -21,6 +21,7 @@
 //! - Runtime: Capabilities prevent privilege escalation
 
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 
 /// A specific permission granted to an agent.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -121,6 +122,161 @@ impl CapabilityCheck {
     }
 }
 
+/// Commands a program may run when nothing else is granted.
+pub const DEFAULT_EXEC: &[&str] = &["ls", "grep", "cat", "echo", "date", "whoami", "clear"];
+
+/// What a running program may do outside the VM. Every intrinsic that
+/// touches the file system, the network, other processes, AI providers or
+/// threads checks it first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Directories whose files may be read (relative ones are resolved
+    /// against the working directory when checked)
+    pub fs_read: Vec<PathBuf>,
+    /// Directories whose files may be written
+    pub fs_write: Vec<PathBuf>,
+    /// Hosts that may be connected to or listened on, as `host` or
+    /// `host:port` patterns
+    pub net: Vec<String>,
+    /// Programs that may be run, as patterns over their file name
+    pub exec: Vec<String>,
+    /// May query AI providers
+    pub ai: bool,
+    /// May spawn OS threads
+    pub threads: bool,
+}
+
+/// Nothing, as programs run by `ark run` get unless flags grant more.
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl Capabilities {
+    /// Nothing outside the VM.
+    pub fn none() -> Self {
+        Capabilities {
+            fs_read: Vec::new(),
+            fs_write: Vec::new(),
+            net: Vec::new(),
+            exec: Vec::new(),
+            ai: false,
+            threads: false,
+        }
+    }
+
+    /// Everything.
+    pub fn all() -> Self {
+        Capabilities {
+            fs_read: vec![PathBuf::from("/")],
+            fs_write: vec![PathBuf::from("/")],
+            net: vec!["*".to_string()],
+            exec: vec!["*".to_string()],
+            ai: true,
+            threads: true,
+        }
+    }
+
+    /// What intrinsics allow when no program's capabilities are in force,
+    /// as when the host calls them directly or embeds a VM without
+    /// [`VmConfig::capabilities`](crate::vm::VmConfig::capabilities):
+    /// files under the working directory, the network, AI providers,
+    /// threads, and the commands in [`DEFAULT_EXEC`] (any command if
+    /// `ARK_UNSAFE_EXEC=true`).
+    pub fn host_default() -> Self {
+        let unsafe_exec = std::env::var("ARK_UNSAFE_EXEC").is_ok_and(|v| v == "true");
+        Capabilities {
+            fs_read: vec![PathBuf::from(".")],
+            fs_write: vec![PathBuf::from(".")],
+            net: vec!["*".to_string()],
+            exec: if unsafe_exec {
+                vec!["*".to_string()]
+            } else {
+                DEFAULT_EXEC.iter().map(|c| c.to_string()).collect()
+            },
+            ai: true,
+            threads: true,
+        }
+    }
+
+    /// Add what the command-line flag `flag` grants: `--allow-read`,
+    /// `--allow-write`, `--allow-fs` and `--allow-net`, `--allow-run` with
+    /// an optional `=a,b` list (everything without one), or `--allow-ai`,
+    /// `--allow-threads` and `--allow-all`. Returns `false` if `flag` is
+    /// not one of them.
+    pub fn grant(&mut self, flag: &str) -> bool {
+        let (name, list) = match flag.split_once('=') {
+            Some((name, list)) => (name, Some(list)),
+            None => (flag, None),
+        };
+        let items = |all: &str| -> Vec<String> {
+            match list {
+                Some(list) => list
+                    .split(',')
+                    .filter(|item| !item.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+                None => vec![all.to_string()],
+            }
+        };
+        match name {
+            "--allow-all" if list.is_none() => *self = Capabilities::all(),
+            "--allow-read" => self.fs_read.extend(items("/").into_iter().map(Into::into)),
+            "--allow-write" => self.fs_write.extend(items("/").into_iter().map(Into::into)),
+            "--allow-fs" => {
+                let paths = items("/");
+                self.fs_read.extend(paths.iter().map(Into::into));
+                self.fs_write.extend(paths.into_iter().map(Into::into));
+            }
+            "--allow-net" => self.net.extend(items("*")),
+            "--allow-run" => self.exec.extend(items("*")),
+            "--allow-ai" if list.is_none() => self.ai = true,
+            "--allow-threads" if list.is_none() => self.threads = true,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Whether `path`, already canonical, lies under a readable directory.
+    pub fn may_read(&self, path: &Path) -> bool {
+        within_roots(&self.fs_read, path)
+    }
+
+    /// Whether `path`, already canonical, lies under a writable directory.
+    pub fn may_write(&self, path: &Path) -> bool {
+        within_roots(&self.fs_write, path)
+    }
+
+    /// Whether `host` may be reached on `port`, or listened on there.
+    pub fn may_connect(&self, host: &str, port: u16) -> bool {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let address = format!("{}:{}", host, port);
+        self.net
+            .iter()
+            .any(|pattern| glob_matches(pattern, host) || glob_matches(pattern, &address))
+    }
+
+    /// Whether `program` may be run. Patterns match its file name, or the
+    /// program as written.
+    pub fn may_exec(&self, program: &str) -> bool {
+        let name = Path::new(program)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("");
+        self.exec
+            .iter()
+            .any(|pattern| glob_matches(pattern, name) || glob_matches(pattern, program))
+    }
+}
+
+fn within_roots(roots: &[PathBuf], path: &Path) -> bool {
+    roots.iter().any(|root| {
+        let root = std::fs::canonicalize(root).unwrap_or_else(|_| root.clone());
+        path.starts_with(root)
+    })
+}
+
 /// Checks whether a required capability matches any granted capability.
 ///
 /// Pattern matching rules:
@@ -367,6 +523,56 @@ mod tests {
         assert!(!has_capability(&caps, &Capability::FileWrite("/tmp/x".to_string())).is_granted());
     }
 
+    #[test]
+    fn test_capabilities_grants() {
+        let dir = std::env::temp_dir().join(format!("ark_caps_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        let dir = std::fs::canonicalize(&dir).expect("canonicalize");
+        let caps = Capabilities {
+            fs_read: vec![dir.clone()],
+            net: vec!["*.example.com".to_string(), "localhost:8080".to_string()],
+            exec: vec!["git".to_string()],
+            ..Capabilities::none()
+        };
+        assert!(caps.may_read(&dir.join("data.txt")));
+        assert!(!caps.may_write(&dir.join("data.txt")));
+        assert!(!caps.may_read(Path::new("/etc/passwd")));
+        assert!(caps.may_connect("api.example.com", 443));
+        assert!(caps.may_connect("localhost", 8080));
+        assert!(!caps.may_connect("localhost", 8081));
+        assert!(caps.may_exec("/usr/bin/git"));
+        assert!(!caps.may_exec("rm"));
+        assert!(!caps.ai && !caps.threads);
+
+        let all = Capabilities::all();
+        assert!(all.may_write(&dir) && all.may_connect("[::1]", 80) && all.may_exec("rm"));
+        std::fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn test_flags_grant_only_what_they_name() {
+        // Without flags a program gets nothing
+        let mut caps = Capabilities::default();
+        assert_eq!(caps, Capabilities::none());
+        assert!(!caps.may_read(Path::new("/tmp/data.txt")));
+        assert!(!caps.may_connect("api.example.com", 443));
+        assert!(!caps.may_exec("ls"));
+
+        assert!(caps.grant("--allow-net=api.example.com,localhost:8080"));
+        assert!(caps.grant("--allow-read=/srv/data"));
+        assert!(caps.grant("--allow-threads"));
+        assert!(caps.may_connect("api.example.com", 443));
+        assert!(!caps.may_connect("example.org", 443));
+        assert!(caps.may_read(Path::new("/srv/data/a.txt")));
+        assert!(!caps.may_write(Path::new("/srv/data/a.txt")));
+        assert!(caps.threads && !caps.ai && caps.exec.is_empty());
+
+        assert!(!caps.grant("--allow-ai=openai"));
+        assert!(!caps.grant("--allow-everything"));
+        assert!(caps.grant("--allow-all"));
+        assert_eq!(caps, Capabilities::all());
+    }
+
     #[test]
     fn test_ark_specific_capabilities() {
         assert!(capability_matches(
@@ -16,9 +16,14 @@
  * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
  */
 
-use crate::ast::{ArkNode, EnumDecl, Expression, FunctionDef, Statement, TraitDecl};
+use crate::ast::{
+    self, ArkNode, EnumDecl, Expression, FunctionDef, Span, Statement, StructDecl, TraitDecl,
+    Visitor,
+};
+use crate::diagnostics::Diagnostic;
 use crate::types::ArkType;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -70,6 +75,103 @@ pub enum TypeError {
         expected: usize,
         got: usize,
     },
+    #[error(
+        "Struct '{name}' contains itself by value ({cycle}); wrap the field in a List or Optional"
+    )]
+    InfiniteSizeType { name: String, cycle: String },
+    #[error("'{construct}' condition must be Boolean or Integer, got {got}")]
+    NonBooleanCondition { construct: String, got: String },
+    #[error("'?' needs a Result or Option, got {0}")]
+    InvalidPropagation(String),
+    #[error("Destructuring expects {expected} elements, but the list literal has {got}")]
+    DestructureLength { expected: String, got: usize },
+}
+
+impl LinearError {
+    /// This error as a shared [`Diagnostic`]. Linearity errors carry no
+    /// location.
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        match self {
+            LinearError::DoubleUse(_) => Diagnostic::error("E0120", self.to_string())
+                .with_help("a linear value is moved by its first use; use the result instead"),
+            LinearError::UnusedResource(_) => Diagnostic::error("E0121", self.to_string())
+                .with_help("consume it before the end of its block, e.g. return it"),
+            LinearError::NotFound(_) => Diagnostic::error("E0122", self.to_string()),
+        }
+    }
+}
+
+impl TypeError {
+    /// Stable diagnostic code for this kind of error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            TypeError::Mismatch { .. } => "E0101",
+            TypeError::UnknownEnum(_) => "E0102",
+            TypeError::UnknownVariant { .. } => "E0103",
+            TypeError::VariantFieldCount { .. } => "E0104",
+            TypeError::UnknownTrait(_) => "E0105",
+            TypeError::ReturnMismatch { .. } => "E0106",
+            TypeError::ArgMismatch { .. } => "E0107",
+            TypeError::ArgCount { .. } => "E0108",
+            TypeError::InfiniteSizeType { .. } => "E0109",
+            TypeError::NonBooleanCondition { .. } => "E0110",
+            TypeError::InvalidPropagation(_) => "E0111",
+            TypeError::DestructureLength { .. } => "E0112",
+        }
+    }
+
+    fn help(&self) -> Option<&'static str> {
+        match self {
+            TypeError::NonBooleanCondition { .. } => {
+                Some("compare the value explicitly, e.g. `x != \"\"`")
+            }
+            TypeError::InvalidPropagation(_) => {
+                Some("`?` applies to values built with `Ok`/`Err` or `Some`/`None`")
+            }
+            TypeError::DestructureLength { .. } => {
+                Some("add `...rest` to bind any remaining elements")
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A type error and where it was found. Only function bodies carry spans,
+/// so the location is that of the enclosing function.
+#[derive(Debug, Clone)]
+pub struct TypeDiagnostic {
+    pub error: TypeError,
+    /// Enclosing function; `None` at top level.
+    pub function: Option<String>,
+    /// Where that function is defined, if the source was parsed with spans.
+    pub span: Option<Span>,
+}
+
+impl TypeDiagnostic {
+    /// This error as a shared [`Diagnostic`], spanning its function.
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let message = match &self.function {
+            Some(function) => format!("{} (in '{}')", self.error, function),
+            None => self.error.to_string(),
+        };
+        let diagnostic = Diagnostic::error(self.error.code(), message).with_span(self.span.clone());
+        match self.error.help() {
+            Some(help) => diagnostic.with_help(help),
+            None => diagnostic,
+        }
+    }
+}
+
+impl fmt::Display for TypeDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(span) = &self.span {
+            write!(f, "{}:{}:{}: ", span.file, span.start_line, span.start_col)?;
+        }
+        if let Some(function) = &self.function {
+            write!(f, "in '{}': ", function)?;
+        }
+        write!(f, "{}", self.error)
+    }
 }
 
 /// Registry entry for a declared enum type.
@@ -88,6 +190,21 @@ pub struct TraitTypeInfo {
     pub methods: Vec<String>,
 }
 
+/// A variable the checker bound, with the type it declared or inferred.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BindingType {
+    pub name: String,
+    pub ty: ArkType,
+    /// Function the binding is in; `None` at the top level.
+    pub function: Option<String>,
+}
+
+impl fmt::Display for BindingType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.ty)
+    }
+}
+
 #[derive(Debug, Clone)]
 struct VarState {
     is_linear: bool,
@@ -104,9 +221,36 @@ pub struct LinearChecker {
     pub warnings: Vec<String>,
     func_return_types: Vec<ArkType>,
     // Compile-time type enforcement
-    pub type_errors: Vec<TypeError>,
+    pub diagnostics: Vec<TypeDiagnostic>,
+    // Every typed binding, for tooling
+    pub bindings: Vec<BindingType>,
     enum_registry: HashMap<String, EnumTypeInfo>,
     trait_registry: HashMap<String, TraitTypeInfo>,
+    struct_registry: HashMap<String, Vec<(String, ArkType)>>,
+    // Declared signatures of named functions: (parameter types, return type)
+    functions: HashMap<String, (Vec<ArkType>, ArkType)>,
+    // Function being checked, for diagnostics
+    function: Option<String>,
+    span: Option<Span>,
+}
+
+/// Name of a type as diagnostics print it (`Integer`, `List<String>`).
+fn describe(ty: &ArkType) -> String {
+    match ty {
+        ArkType::Integer => "Integer".to_string(),
+        ArkType::String => "String".to_string(),
+        ArkType::Boolean => "Boolean".to_string(),
+        ArkType::List(inner) => format!("List<{}>", describe(inner)),
+        ArkType::Map(key, value) => format!("Map<{}, {}>", describe(key), describe(value)),
+        ArkType::Optional(inner) => format!("{}?", describe(inner)),
+        ArkType::Shared(name)
+        | ArkType::Linear(name)
+        | ArkType::Affine(name)
+        | ArkType::Struct(name, _)
+        | ArkType::Enum(name)
+        | ArkType::Trait(name) => name.clone(),
+        other => other.to_string(),
+    }
 }
 
 impl Default for LinearChecker {
@@ -117,32 +261,125 @@ impl Default for LinearChecker {
 
 impl LinearChecker {
     pub fn new() -> Self {
-        LinearChecker {
+        let mut checker = LinearChecker {
             var_states: HashMap::new(),
             scope_stack: Vec::new(),
             warnings: Vec::new(),
             func_return_types: Vec::new(),
-            type_errors: Vec::new(),
+            diagnostics: Vec::new(),
+            bindings: Vec::new(),
             enum_registry: HashMap::new(),
             trait_registry: HashMap::new(),
+            struct_registry: HashMap::new(),
+            functions: HashMap::new(),
+            function: None,
+            span: None,
+        };
+        for decl in EnumDecl::builtins() {
+            checker.register_enum(&decl);
         }
+        checker
     }
 
     pub fn check(node: &ArkNode) -> Result<(), LinearError> {
-        let mut checker = LinearChecker::new();
-        checker.traverse_node(node)?;
-        // Report type errors as warnings (non-fatal for now)
-        for err in &checker.type_errors {
-            checker.warnings.push(format!("TypeError: {}", err));
-        }
-        Ok(())
+        // Type errors are non-fatal here; `check_diagnostics` reports them
+        Self::check_diagnostics(node).map(|_| ())
     }
 
     /// Full type-enforced check that returns both linearity and type errors.
     pub fn check_with_types(node: &ArkNode) -> Result<Vec<TypeError>, LinearError> {
+        let diagnostics = Self::check_diagnostics(node)?;
+        Ok(diagnostics.into_iter().map(|d| d.error).collect())
+    }
+
+    /// Like `check_with_types`, but keeps every error's function and span.
+    /// Linearity errors still abort the check; type errors are collected.
+    pub fn check_diagnostics(node: &ArkNode) -> Result<Vec<TypeDiagnostic>, LinearError> {
         let mut checker = LinearChecker::new();
+        checker.declare_items(node);
         checker.traverse_node(node)?;
-        Ok(checker.type_errors)
+        for err in Self::check_struct_sizes(node) {
+            checker.report(err);
+        }
+        Ok(checker.diagnostics)
+    }
+
+    /// The type of every parameter and variable binding the checker could
+    /// determine, in program order. A linearity error stops the walk, so
+    /// bindings after it are missing.
+    pub fn binding_types(node: &ArkNode) -> Vec<BindingType> {
+        let mut checker = LinearChecker::new();
+        checker.declare_items(node);
+        let _ = checker.traverse_node(node);
+        checker.bindings
+    }
+
+    /// Record a type error against the function being checked.
+    fn report(&mut self, error: TypeError) {
+        self.warnings.push(error.to_string());
+        self.diagnostics.push(TypeDiagnostic {
+            error,
+            function: self.function.clone(),
+            span: self.span.clone(),
+        });
+    }
+
+    /// Register every enum, trait, struct and function signature in the
+    /// program up front, so uses may precede declarations. A function name
+    /// declared twice with different signatures is left unchecked.
+    fn declare_items(&mut self, node: &ArkNode) {
+        #[derive(Default)]
+        struct Items {
+            enums: Vec<EnumDecl>,
+            traits: Vec<TraitDecl>,
+            structs: Vec<StructDecl>,
+            functions: Vec<FunctionDef>,
+        }
+        impl Visitor for Items {
+            fn visit_statement(&mut self, stmt: &Statement) {
+                match stmt {
+                    Statement::EnumDecl(decl) => self.enums.push(decl.clone()),
+                    Statement::TraitDecl(decl) => self.traits.push(decl.clone()),
+                    Statement::StructDecl(decl) => self.structs.push(decl.clone()),
+                    Statement::Function(func) => self.functions.push(func.clone()),
+                    _ => {}
+                }
+                ast::walk_statement(self, stmt);
+            }
+        }
+        let mut items = Items::default();
+        items.visit_node(node);
+
+        for decl in &items.enums {
+            self.register_enum(decl);
+        }
+        for decl in &items.traits {
+            self.register_trait(decl);
+        }
+        for decl in items.structs {
+            self.struct_registry.insert(decl.name, decl.fields);
+        }
+        let mut ambiguous = HashSet::new();
+        for func in items.functions {
+            let signature = (
+                func.inputs
+                    .into_iter()
+                    .map(|(_, ty)| ty)
+                    .collect::<Vec<_>>(),
+                func.output,
+            );
+            match self.functions.get(&func.name) {
+                Some(existing) if *existing != signature => {
+                    ambiguous.insert(func.name);
+                }
+                _ => {
+                    self.functions.insert(func.name, signature);
+                }
+            }
+        }
+        for name in ambiguous {
+            self.functions.remove(&name);
+        }
     }
 
     /// Register an enum declaration in the type registry.
@@ -172,27 +409,222 @@ impl LinearChecker {
         );
     }
 
+    /// Find struct declarations that contain themselves by value, directly
+    /// or through other structs. Such a type has no finite layout; the cycle
+    /// must go through an indirection (List, Map, Optional).
+    pub fn check_struct_sizes(node: &ArkNode) -> Vec<TypeError> {
+        struct Collector(Vec<StructDecl>);
+        impl Visitor for Collector {
+            fn visit_statement(&mut self, stmt: &Statement) {
+                if let Statement::StructDecl(decl) = stmt {
+                    self.0.push(decl.clone());
+                }
+                ast::walk_statement(self, stmt);
+            }
+        }
+        let mut collector = Collector(Vec::new());
+        collector.visit_node(node);
+
+        let structs: HashMap<&str, &StructDecl> = collector
+            .0
+            .iter()
+            .map(|decl| (decl.name.as_str(), decl))
+            .collect();
+        // Struct names held by value; anything else is either a scalar or an
+        // indirection and cannot make the layout infinite.
+        let by_value = |decl: &StructDecl| -> Vec<String> {
+            decl.fields
+                .iter()
+                .filter_map(|(_, ty)| match ty {
+                    ArkType::Struct(name, _) if structs.contains_key(name.as_str()) => {
+                        Some(name.clone())
+                    }
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let mut errors = Vec::new();
+        let mut reported: Vec<String> = Vec::new();
+        for decl in &collector.0 {
+            if reported.contains(&decl.name) {
+                continue;
+            }
+            // DFS for a path leading back to `decl`
+            let mut stack = vec![vec![decl.name.clone()]];
+            let mut seen: Vec<String> = Vec::new();
+            while let Some(path) = stack.pop() {
+                let last = &path[path.len() - 1];
+                let Some(current) = structs.get(last.as_str()) else {
+                    continue;
+                };
+                let mut cycle = None;
+                for next in by_value(current) {
+                    if next == decl.name {
+                        let mut full = path.clone();
+                        full.push(next);
+                        cycle = Some(full);
+                        break;
+                    }
+                    if !seen.contains(&next) {
+                        seen.push(next.clone());
+                        let mut extended = path.clone();
+                        extended.push(next);
+                        stack.push(extended);
+                    }
+                }
+                if let Some(cycle) = cycle {
+                    reported.extend(cycle.iter().cloned());
+                    errors.push(TypeError::InfiniteSizeType {
+                        name: decl.name.clone(),
+                        cycle: cycle.join(" -> "),
+                    });
+                    break;
+                }
+            }
+        }
+        errors
+    }
+
+    /// Parameter and return types of intrinsics whose arguments are fixed.
+    /// Operators are typed by `operator_type` instead, since they accept
+    /// several operand types.
     fn get_intrinsic_signature(name: &str) -> Option<(Vec<ArkType>, ArkType)> {
+        let result = || ArkType::Enum("Result".to_string());
+        let list = || ArkType::List(Box::new(ArkType::Any));
         match name {
-            "intrinsic_add" | "add" | "intrinsic_sub" | "sub" | "intrinsic_mul" | "mul"
-            | "intrinsic_div" | "div" => Some((
-                vec![
-                    ArkType::Shared("Integer".to_string()),
-                    ArkType::Shared("Integer".to_string()),
-                ],
-                ArkType::Shared("Integer".to_string()),
-            )),
-            "intrinsic_gt" | "gt" | "intrinsic_lt" | "lt" | "intrinsic_eq" | "eq" => Some((
-                vec![
-                    ArkType::Shared("Integer".to_string()),
-                    ArkType::Shared("Integer".to_string()),
-                ],
-                ArkType::Shared("Boolean".to_string()),
-            )),
+            "sys.time.now" | "intrinsic_time_now" => Some((vec![], ArkType::Integer)),
+            "sys.time.sleep" | "intrinsic_time_sleep" => {
+                Some((vec![ArkType::Integer], ArkType::Unit))
+            }
+            "sys.fs.read" | "intrinsic_fs_read" => Some((vec![ArkType::String], ArkType::String)),
+            "sys.fs.write" | "intrinsic_fs_write" => {
+                Some((vec![ArkType::String, ArkType::String], ArkType::Any))
+            }
+            "sys.fs.try_read" => Some((vec![ArkType::String], result())),
+            "sys.fs.try_write" => Some((vec![ArkType::String, ArkType::String], result())),
+            "sys.json.parse" | "intrinsic_json_parse" => {
+                Some((vec![ArkType::String], ArkType::Any))
+            }
+            "sys.json.try_parse" => Some((vec![ArkType::String], result())),
+            "sys.json.stringify" | "intrinsic_json_stringify" => {
+                Some((vec![ArkType::Any], ArkType::String))
+            }
+            "sys.type_of" | "intrinsic_type_of" => Some((vec![ArkType::Any], ArkType::String)),
+            // sys.len -> [len, original_val]
+            "sys.len" | "intrinsic_len" => Some((vec![ArkType::Any], list())),
+            // sys.list.get -> [val, list]
+            "sys.list.get" | "intrinsic_list_get" => Some((vec![list(), ArkType::Integer], list())),
+            "sys.list.append" | "intrinsic_list_append" => {
+                Some((vec![list(), ArkType::Any], list()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Result type of an arithmetic, comparison or logical operator call,
+    /// given what is known of its operands.
+    fn operator_type(&self, name: &str, args: &[Expression]) -> Option<ArkType> {
+        let operand = |i: usize| {
+            args.get(i)
+                .and_then(|arg| self.infer_expression_type(arg))
+                .map(|ty| self.normalize(&ty))
+        };
+        match name.strip_prefix("intrinsic_").unwrap_or(name) {
+            "eq" | "neq" | "lt" | "gt" | "le" | "ge" | "and" | "or" | "not" => {
+                Some(ArkType::Boolean)
+            }
+            "neg" => operand(0),
+            op @ ("add" | "sub" | "mul" | "div" | "mod") => match (operand(0)?, operand(1)?) {
+                (ArkType::Integer, ArkType::Integer) => Some(ArkType::Integer),
+                (ArkType::Float, ArkType::Integer | ArkType::Float)
+                | (ArkType::Integer, ArkType::Float) => Some(ArkType::Float),
+                (ArkType::String, _) | (_, ArkType::String) if op == "add" => Some(ArkType::String),
+                _ => None,
+            },
             _ => None,
         }
     }
 
+    /// Type of a literal as the interpreter reads it. The parser also uses
+    /// literals as placeholders for nodes it does not model yet.
+    fn literal_type(literal: &str) -> Option<ArkType> {
+        match literal {
+            "true" | "false" => Some(ArkType::Boolean),
+            "unit" | "nil" | "void" | "lambda" | "block" => None,
+            _ if literal.parse::<i64>().is_ok() => Some(ArkType::Integer),
+            _ if literal.contains('.') && literal.parse::<f64>().is_ok() => Some(ArkType::Float),
+            _ => Some(ArkType::String),
+        }
+    }
+
+    /// Canonical form of a type for comparison: legacy `Shared("Integer")`
+    /// style names become the structured types, linearity is dropped and
+    /// user type names resolve to the enum or struct they declare.
+    fn normalize(&self, ty: &ArkType) -> ArkType {
+        match ty {
+            ArkType::Shared(name) | ArkType::Linear(name) | ArkType::Affine(name) => {
+                match name.as_str() {
+                    "Integer" | "Int" => ArkType::Integer,
+                    "Float" => ArkType::Float,
+                    "String" | "Str" => ArkType::String,
+                    "Boolean" | "Bool" => ArkType::Boolean,
+                    "Unit" | "Void" => ArkType::Unit,
+                    "Any" => ArkType::Any,
+                    "Unknown" => ArkType::Unknown,
+                    _ => match name
+                        .strip_prefix("List<")
+                        .and_then(|rest| rest.strip_suffix('>'))
+                    {
+                        Some(inner) => ArkType::List(Box::new(
+                            self.normalize(&ArkType::Shared(inner.to_string())),
+                        )),
+                        None => self.named_type(name),
+                    },
+                }
+            }
+            ArkType::Struct(name, fields) if fields.is_empty() => self.named_type(name),
+            ArkType::List(inner) => ArkType::List(Box::new(self.normalize(inner))),
+            ArkType::Map(key, value) => ArkType::Map(
+                Box::new(self.normalize(key)),
+                Box::new(self.normalize(value)),
+            ),
+            ArkType::Optional(inner) => ArkType::Optional(Box::new(self.normalize(inner))),
+            other => other.clone(),
+        }
+    }
+
+    /// A user type annotation: the parser cannot tell enums from structs.
+    fn named_type(&self, name: &str) -> ArkType {
+        if self.enum_registry.contains_key(name) {
+            ArkType::Enum(name.to_string())
+        } else if self.trait_registry.contains_key(name) {
+            ArkType::Trait(name.to_string())
+        } else {
+            ArkType::Struct(name.to_string(), vec![])
+        }
+    }
+
+    /// Whether a value of type `got` may be used where `expected` is
+    /// declared. Unknown types are assumed to fit.
+    fn compatible(&self, got: &ArkType, expected: &ArkType) -> bool {
+        fn fits(got: &ArkType, expected: &ArkType) -> bool {
+            match (got, expected) {
+                (ArkType::Any | ArkType::Unknown, _) | (_, ArkType::Any | ArkType::Unknown) => true,
+                // Implementations are not tracked, so any value may satisfy a trait
+                (_, ArkType::Trait(_)) => true,
+                (ArkType::Struct(a, _), ArkType::Struct(b, _)) => a == b,
+                (ArkType::List(a), ArkType::List(b)) => fits(a, b),
+                (ArkType::Map(k1, v1), ArkType::Map(k2, v2)) => fits(k1, k2) && fits(v1, v2),
+                (ArkType::Optional(a), ArkType::Optional(b)) => fits(a, b),
+                (ArkType::Unit, ArkType::Optional(_)) => true,
+                (_, ArkType::Optional(inner)) => fits(got, inner),
+                _ => got.is_compatible(expected),
+            }
+        }
+        fits(&self.normalize(got), &self.normalize(expected))
+    }
+
     fn get_intrinsic_return_linearity(name: &str) -> Vec<bool> {
         match name {
             // sys.mem.read -> [val, buf]
@@ -240,6 +672,13 @@ impl LinearChecker {
     }
 
     fn declare_var(&mut self, name: String, ty: Option<ArkType>, is_linear: bool) {
+        if let Some(ty) = &ty {
+            self.bindings.push(BindingType {
+                name: name.clone(),
+                ty: ty.clone(),
+                function: self.function.clone(),
+            });
+        }
         let state = VarState {
             is_linear,
             is_active: is_linear, // Only linear vars track activity
@@ -296,6 +735,11 @@ impl LinearChecker {
         self.var_states.contains_key(name)
     }
 
+    #[cfg(test)]
+    pub fn type_errors(&self) -> Vec<TypeError> {
+        self.diagnostics.iter().map(|d| d.error.clone()).collect()
+    }
+
     #[cfg(test)]
     pub fn get_var_type(&self, name: &str) -> Option<ArkType> {
         if let Some(states) = self.var_states.get(name) {
@@ -309,6 +753,8 @@ impl LinearChecker {
     pub fn check_function(&mut self, func: &FunctionDef) -> Result<(), LinearError> {
         self.enter_scope();
         self.func_return_types.clear();
+        self.function = Some(func.name.clone());
+        self.span = func.body.span.clone();
 
         // 1. Register input arguments
         for (name, ty) in &func.inputs {
@@ -320,36 +766,37 @@ impl LinearChecker {
 
         // Check return type consistency
         if !self.func_return_types.is_empty() {
-            let first = &self.func_return_types[0];
-            for ty in &self.func_return_types[1..] {
-                if ty != first {
-                    self.warnings.push(format!(
-                        "Inconsistent return types: {:?} vs {:?}",
-                        first, ty
-                    ));
+            let returned: Vec<ArkType> = self
+                .func_return_types
+                .iter()
+                .map(|ty| self.normalize(ty))
+                .filter(|ty| !matches!(ty, ArkType::Unknown))
+                .collect();
+            if let Some(first) = returned.first() {
+                for ty in &returned[1..] {
+                    if ty != first {
+                        self.warnings.push(format!(
+                            "Inconsistent return types: {} vs {}",
+                            describe(first),
+                            describe(ty)
+                        ));
+                    }
                 }
             }
 
             // Check against declared return type
-            for ty in &self.func_return_types {
-                if ty != &func.output {
-                    self.warnings.push(format!(
-                        "Return type mismatch: declared {:?}, got {:?}",
-                        func.output, ty
-                    ));
-                    self.type_errors.push(TypeError::ReturnMismatch {
-                        expected: format!("{:?}", func.output),
-                        got: format!("{:?}", ty),
+            for ty in returned {
+                if !self.compatible(&ty, &func.output) {
+                    self.report(TypeError::ReturnMismatch {
+                        expected: describe(&self.normalize(&func.output)),
+                        got: describe(&ty),
                     });
                 }
             }
         } else {
             // No explicit return. Assume Void.
             // Warn if declared type is not Void.
-            let is_void = match &func.output {
-                ArkType::Shared(name) => name == "Void" || name == "Unit",
-                _ => false,
-            };
+            let is_void = matches!(self.normalize(&func.output), ArkType::Unit | ArkType::Any);
             if !is_void {
                 self.warnings.push(format!(
                     "Function declared to return {:?} but has no return statements",
@@ -374,11 +821,26 @@ impl LinearChecker {
     fn check_statement(&mut self, stmt: &Statement) -> Result<(), LinearError> {
         match stmt {
             Statement::Let { name, ty, value } => self.check_let(name, ty, value),
-            Statement::LetDestructure { names, value } => self.check_let_destructure(names, value),
+            Statement::LetDestructure {
+                names,
+                value,
+                by_field,
+                rest,
+            } => {
+                if !by_field {
+                    self.check_destructure_length(names.len(), rest.is_some(), value);
+                }
+                self.check_let_destructure(names, value)?;
+                if let Some(rest) = rest {
+                    self.declare_var(rest.clone(), None, false);
+                }
+                Ok(())
+            }
             Statement::SetField {
                 obj_name,
                 field,
                 value,
+                ..
             } => self.check_set_field(obj_name, field, value),
             Statement::Return(expr) => self.check_return(expr),
             Statement::Block(stmts) => self.check_block(stmts),
@@ -388,12 +850,20 @@ impl LinearChecker {
                 then_block,
                 else_block,
             } => self.check_if(condition, then_block, else_block),
-            Statement::While { condition, body } => self.check_while(condition, body),
+            Statement::While {
+                condition, body, ..
+            } => self.check_while(condition, body),
+            Statement::TryCatch {
+                body,
+                catch_var,
+                handler,
+            } => self.check_try(body, catch_var, handler),
             Statement::For { .. }
             | Statement::Import(_)
-            | Statement::Break
-            | Statement::Continue => Ok(()), // Not checked yet
+            | Statement::Break { .. }
+            | Statement::Continue { .. } => Ok(()), // Not checked yet
             Statement::Function(func_def) => self.check_nested_function(func_def),
+            Statement::Export(inner) => self.check_statement(inner),
             Statement::StructDecl(_) => Ok(()),
             Statement::EnumDecl(decl) => {
                 self.register_enum(decl);
@@ -409,18 +879,8 @@ impl LinearChecker {
 
     fn infer_expression_type(&self, expr: &Expression) -> Option<ArkType> {
         match expr {
-            Expression::Literal(s) => {
-                if s.parse::<i64>().is_ok() {
-                    Some(ArkType::Shared("Integer".to_string()))
-                } else if s == "true" || s == "false" {
-                    Some(ArkType::Shared("Boolean".to_string()))
-                } else if s.starts_with('"') {
-                    Some(ArkType::Shared("String".to_string()))
-                } else {
-                    // Assume String for other literals
-                    Some(ArkType::Shared("String".to_string()))
-                }
-            }
+            Expression::Integer(_) => Some(ArkType::Integer),
+            Expression::Literal(s) => Self::literal_type(s),
             Expression::Variable(name) => {
                 if let Some(states) = self.var_states.get(name) {
                     if let Some(state) = states.last() {
@@ -430,29 +890,63 @@ impl LinearChecker {
                 None
             }
             Expression::List(items) => {
-                if items.is_empty() {
-                    return Some(ArkType::Shared("List<Any>".to_string()));
+                Some(ArkType::List(Box::new(self.element_type(items.iter()))))
+            }
+            Expression::MapInit { entries } => Some(ArkType::Map(
+                Box::new(self.element_type(entries.iter().map(|(key, _)| key))),
+                Box::new(self.element_type(entries.iter().map(|(_, value)| value))),
+            )),
+            Expression::EnumInit { enum_name, .. } => Some(ArkType::Enum(enum_name.clone())),
+            Expression::GetField { obj, field } => {
+                let ArkType::Struct(name, _) = self.normalize(&self.infer_expression_type(obj)?)
+                else {
+                    return None;
+                };
+                let fields = self.struct_registry.get(&name)?;
+                let (_, ty) = fields.iter().find(|(f, _)| f == field)?;
+                Some(ty.clone())
+            }
+            Expression::Call {
+                function_hash,
+                args,
+            } => {
+                if self.var_states.contains_key(function_hash) {
+                    // A closure held in a variable; its signature is unknown
+                    return None;
                 }
-                let first_ty = self.infer_expression_type(&items[0]);
-                if let Some(ty) = first_ty {
-                    let ty_name = match ty {
-                        ArkType::Shared(n) | ArkType::Linear(n) | ArkType::Affine(n) => n.clone(),
-                        _ => "Unknown".to_string(),
-                    };
-                    Some(ArkType::Shared(format!("List<{}>", ty_name)))
-                } else {
-                    Some(ArkType::Shared("List<Unknown>".to_string()))
+                let output = match self.functions.get(function_hash) {
+                    Some((_, output)) => output.clone(),
+                    None => match Self::get_intrinsic_signature(function_hash) {
+                        Some((_, output)) => output,
+                        None => return self.operator_type(function_hash, args),
+                    },
+                };
+                match self.normalize(&output) {
+                    ArkType::Any => None,
+                    _ => Some(output),
                 }
             }
-            Expression::EnumInit {
-                enum_name,
-                variant,
-                args: _,
-            } => Some(ArkType::Enum(format!("{}::{}", enum_name, variant))),
             _ => None,
         }
     }
 
+    /// Common type of list elements or map keys/values: `Any` when they
+    /// disagree or any of them is unknown.
+    fn element_type<'a>(&self, items: impl Iterator<Item = &'a Expression>) -> ArkType {
+        let mut common: Option<ArkType> = None;
+        for item in items {
+            let ty = self
+                .infer_expression_type(item)
+                .map(|ty| self.normalize(&ty))
+                .unwrap_or(ArkType::Any);
+            common = match common {
+                Some(prev) if prev != ty => return ArkType::Any,
+                _ => Some(ty),
+            };
+        }
+        common.unwrap_or(ArkType::Any)
+    }
+
     fn check_let(
         &mut self,
         name: &str,
@@ -465,15 +959,11 @@ impl LinearChecker {
         // Check for mismatch
         if let Some(explicit_ty) = ty {
             if let Some(inferred) = &inferred_ty {
-                if explicit_ty != inferred {
-                    self.warnings.push(format!(
-                        "Type mismatch for '{}': expected {:?}, got {:?}",
-                        name, explicit_ty, inferred
-                    ));
-                    self.type_errors.push(TypeError::Mismatch {
+                if !self.compatible(inferred, explicit_ty) {
+                    self.report(TypeError::Mismatch {
                         name: name.to_string(),
-                        expected: format!("{:?}", explicit_ty),
-                        got: format!("{:?}", inferred),
+                        expected: describe(&self.normalize(explicit_ty)),
+                        got: describe(&self.normalize(inferred)),
                     });
                 }
             }
@@ -503,6 +993,16 @@ impl LinearChecker {
         // Use inferred type if explicit type is missing
         let final_ty = ty.clone().or(inferred_ty);
 
+        // `:=` also assigns to a binding in an enclosing block, so once a
+        // name is rebound to another type, none of its types can be relied on
+        if let Some(states) = self.var_states.get_mut(name) {
+            for state in states.iter_mut() {
+                if state.ty.is_some() && state.ty != final_ty {
+                    state.ty = None;
+                }
+            }
+        }
+
         // Determine linearity of new var
         let is_linear =
             inferred_linear || final_ty.as_ref().map(|t| t.is_linear()).unwrap_or(false);
@@ -588,6 +1088,7 @@ impl LinearChecker {
         then_block: &[Statement],
         else_block: &Option<Vec<Statement>>,
     ) -> Result<(), LinearError> {
+        self.check_condition("if", condition);
         self.check_expression(condition)?;
         self.enter_scope();
         for stmt in then_block {
@@ -605,11 +1106,28 @@ impl LinearChecker {
         Ok(())
     }
 
+    fn check_try(
+        &mut self,
+        body: &[Statement],
+        catch_var: &str,
+        handler: &[Statement],
+    ) -> Result<(), LinearError> {
+        self.check_block(body)?;
+        self.enter_scope();
+        self.declare_var(catch_var.to_string(), None, false);
+        for stmt in handler {
+            self.check_statement(stmt)?;
+        }
+        self.exit_scope()?;
+        Ok(())
+    }
+
     fn check_while(
         &mut self,
         condition: &Expression,
         body: &[Statement],
     ) -> Result<(), LinearError> {
+        self.check_condition("while", condition);
         self.check_expression(condition)?;
         self.enter_scope();
         for stmt in body {
@@ -619,10 +1137,159 @@ impl LinearChecker {
         Ok(())
     }
 
+    /// Only Booleans and Integers are truthy; any other value is always false
+    /// in the interpreter (and, being a nonzero pointer, always true in WASM).
+    fn check_condition(&mut self, construct: &str, condition: &Expression) {
+        let Some(ty) = self.infer_expression_type(condition) else {
+            return;
+        };
+        let ty = self.normalize(&ty);
+        if !matches!(
+            ty,
+            ArkType::String
+                | ArkType::Float
+                | ArkType::List(_)
+                | ArkType::Map(..)
+                | ArkType::Struct(..)
+                | ArkType::Function(..)
+                | ArkType::Enum(_)
+        ) {
+            return;
+        }
+        self.report(TypeError::NonBooleanCondition {
+            construct: construct.to_string(),
+            got: describe(&ty),
+        });
+    }
+
+    /// `?` on a value known not to be a `Result` or `Option` always fails.
+    fn check_propagate(&mut self, operand: &Expression) {
+        let Some(ty) = self.infer_expression_type(operand) else {
+            return;
+        };
+        let ty = self.normalize(&ty);
+        match &ty {
+            ArkType::Any | ArkType::Unknown | ArkType::Optional(_) | ArkType::Trait(_) => {}
+            ArkType::Enum(n) if n == "Result" || n == "Option" => {}
+            _ => self.report(TypeError::InvalidPropagation(describe(&ty))),
+        }
+    }
+
+    /// A list literal with too few (or, without `...rest`, too many)
+    /// elements for the names being bound can never destructure.
+    fn check_destructure_length(&mut self, names: usize, has_rest: bool, value: &Expression) {
+        let Expression::List(items) = value else {
+            return;
+        };
+        let fits = if has_rest {
+            items.len() >= names
+        } else {
+            items.len() == names
+        };
+        if !fits {
+            self.report(TypeError::DestructureLength {
+                expected: if has_rest {
+                    format!("at least {}", names)
+                } else {
+                    names.to_string()
+                },
+                got: items.len(),
+            });
+        }
+    }
+
+    /// Check a call's arity and argument types against the callee's
+    /// declared signature, or its operands if it is an operator.
+    fn check_call(&mut self, name: &str, args: &[Expression]) {
+        if self.var_states.contains_key(name) {
+            // A closure held in a variable; its signature is unknown
+            return;
+        }
+        let signature = self
+            .functions
+            .get(name)
+            .cloned()
+            .or_else(|| Self::get_intrinsic_signature(name));
+        let Some((inputs, _)) = signature else {
+            self.check_operator(name, args);
+            return;
+        };
+        if args.len() != inputs.len() {
+            self.report(TypeError::ArgCount {
+                func: name.to_string(),
+                expected: inputs.len(),
+                got: args.len(),
+            });
+            return;
+        }
+        for (index, (arg, expected)) in args.iter().zip(&inputs).enumerate() {
+            let Some(got) = self.infer_expression_type(arg) else {
+                continue;
+            };
+            if !self.compatible(&got, expected) {
+                self.report(TypeError::ArgMismatch {
+                    func: name.to_string(),
+                    index,
+                    expected: describe(&self.normalize(expected)),
+                    got: describe(&self.normalize(&got)),
+                });
+            }
+        }
+    }
+
+    /// Arithmetic takes numbers; `+` and ordering comparisons also take two
+    /// Strings. Mixed operands are rejected even where the interpreter
+    /// coerces them, since compiled code cannot.
+    fn check_operator(&mut self, name: &str, args: &[Expression]) {
+        let op = name.strip_prefix("intrinsic_").unwrap_or(name);
+        let (accepts_strings, operands) = match op {
+            "add" | "lt" | "gt" | "le" | "ge" => (true, "Integer, Float or String"),
+            "sub" | "mul" | "div" | "mod" => (false, "Integer or Float"),
+            _ => return,
+        };
+        let [left, right] = args else {
+            return;
+        };
+        let operand = |expr: &Expression| {
+            self.infer_expression_type(expr)
+                .map(|ty| self.normalize(&ty))
+                .filter(|ty| !matches!(ty, ArkType::Any | ArkType::Unknown))
+        };
+        let is_number = |ty: &ArkType| matches!(ty, ArkType::Integer | ArkType::Float);
+        let fits = |ty: &ArkType| is_number(ty) || (accepts_strings && *ty == ArkType::String);
+
+        let mismatch = match (operand(left), operand(right)) {
+            (Some(left), _) if !fits(&left) => Some((0, operands.to_string(), left)),
+            (Some(left), Some(right)) if !fits(&right) || is_number(&left) != is_number(&right) => {
+                Some((1, describe(&left), right))
+            }
+            (None, Some(right)) if !fits(&right) => Some((1, operands.to_string(), right)),
+            _ => None,
+        };
+        if let Some((index, expected, got)) = mismatch {
+            self.report(TypeError::ArgMismatch {
+                func: name.to_string(),
+                index,
+                expected,
+                got: describe(&got),
+            });
+        }
+    }
+
     fn check_nested_function(&mut self, func_def: &FunctionDef) -> Result<(), LinearError> {
-        // Check function body with new scope to ensure isolation
+        // Check function body with new scope to ensure isolation, but with
+        // the program's declarations and signatures
         let mut function_checker = LinearChecker::new();
-        function_checker.check_function(func_def)
+        function_checker.enum_registry = self.enum_registry.clone();
+        function_checker.trait_registry = self.trait_registry.clone();
+        function_checker.struct_registry = self.struct_registry.clone();
+        function_checker.functions = self.functions.clone();
+        let result = function_checker.check_function(func_def);
+        self.bindings.append(&mut function_checker.bindings);
+        result?;
+        self.warnings.append(&mut function_checker.warnings);
+        self.diagnostics.append(&mut function_checker.diagnostics);
+        Ok(())
     }
 
     fn check_expression(&mut self, expr: &Expression) -> Result<(), LinearError> {
@@ -636,50 +1303,21 @@ impl LinearChecker {
                     self.check_expression(arg)?;
                 }
 
-                if let Some((input_types, _)) = Self::get_intrinsic_signature(function_hash) {
-                    if args.len() != input_types.len() {
-                        self.warnings.push(format!(
-                            "Argument count mismatch for '{}': expected {}, got {}",
-                            function_hash,
-                            input_types.len(),
-                            args.len()
-                        ));
-                        self.type_errors.push(TypeError::ArgCount {
-                            func: function_hash.clone(),
-                            expected: input_types.len(),
-                            got: args.len(),
-                        });
-                    } else {
-                        for (i, arg) in args.iter().enumerate() {
-                            if let Some(inferred) = self.infer_expression_type(arg) {
-                                if inferred != input_types[i] {
-                                    self.warnings.push(format!("Argument type mismatch for '{}' at index {}: expected {:?}, got {:?}", function_hash, i, input_types[i], inferred));
-                                    self.type_errors.push(TypeError::ArgMismatch {
-                                        func: function_hash.clone(),
-                                        index: i,
-                                        expected: format!("{:?}", input_types[i]),
-                                        got: format!("{:?}", inferred),
-                                    });
-                                }
-                            }
-                        }
-                    }
-                }
+                self.check_call(function_hash, args);
                 Ok(())
             }
             Expression::GetField { obj, field } => {
                 self.check_expression(obj)?;
                 if let Some(ty) = self.infer_expression_type(obj) {
-                    match ty {
-                        ArkType::Shared(name)
-                            if name == "Integer" || name == "String" || name == "Boolean" =>
-                        {
+                    match self.normalize(&ty) {
+                        ty @ (ArkType::Integer | ArkType::String | ArkType::Boolean) => {
                             self.warnings.push(format!(
                                 "Invalid field access '{}' on primitive type {:?}",
-                                field, name
+                                field,
+                                describe(&ty)
                             ));
                         }
-                        ArkType::Shared(name) if name.starts_with("List<") => {
+                        ArkType::List(_) => {
                             self.warnings
                                 .push(format!("Invalid field access '{}' on List type", field));
                         }
@@ -700,6 +1338,13 @@ impl LinearChecker {
                 }
                 Ok(())
             }
+            Expression::MapInit { entries } => {
+                for (key, value) in entries {
+                    self.check_expression(key)?;
+                    self.check_expression(value)?;
+                }
+                Ok(())
+            }
             Expression::EnumInit {
                 enum_name,
                 variant,
@@ -711,7 +1356,7 @@ impl LinearChecker {
                     if let Some(&expected_fields) = info.variants.get(variant) {
                         // Validate field count
                         if args.len() != expected_fields {
-                            self.type_errors.push(TypeError::VariantFieldCount {
+                            self.report(TypeError::VariantFieldCount {
                                 enum_name: enum_name.clone(),
                                 variant: variant.clone(),
                                 expected: expected_fields,
@@ -719,14 +1364,13 @@ impl LinearChecker {
                             });
                         }
                     } else {
-                        self.type_errors.push(TypeError::UnknownVariant {
+                        self.report(TypeError::UnknownVariant {
                             enum_name: enum_name.clone(),
                             variant: variant.clone(),
                         });
                     }
                 } else {
-                    self.type_errors
-                        .push(TypeError::UnknownEnum(enum_name.clone()));
+                    self.report(TypeError::UnknownEnum(enum_name.clone()));
                 }
                 // Check sub-expressions
                 for arg in args {
@@ -734,6 +1378,10 @@ impl LinearChecker {
                 }
                 Ok(())
             }
+            Expression::Propagate(inner) => {
+                self.check_propagate(inner);
+                self.check_expression(inner)
+            }
             _ => Ok(()),
         }
     }
@@ -1070,6 +1718,8 @@ mod tests {
                                 Expression::Literal("0".to_string()),
                             ],
                         },
+                        by_field: false,
+                        rest: None,
                     },
                     Statement::Return(Expression::Variable("val".to_string())),
                 ])))
@@ -1175,6 +1825,8 @@ mod tests {
                             function_hash: "unknown_func".to_string(),
                             args: vec![Expression::Variable("buf".to_string())],
                         },
+                        by_field: false,
+                        rest: None,
                     },
                     Statement::Return(Expression::Variable("val".to_string())),
                 ])))
@@ -1240,7 +1892,7 @@ mod tests {
         checker.check_statement(&stmt).expect("operation failed");
 
         let ty = checker.get_var_type("x");
-        assert_eq!(ty, Some(ArkType::Shared("Integer".to_string())));
+        assert_eq!(ty, Some(ArkType::Integer));
     }
 
     #[test]
@@ -1255,7 +1907,7 @@ mod tests {
         checker.check_statement(&stmt).expect("operation failed");
 
         let ty = checker.get_var_type("s");
-        assert_eq!(ty, Some(ArkType::Shared("String".to_string())));
+        assert_eq!(ty, Some(ArkType::String));
     }
 
     #[test]
@@ -1270,7 +1922,7 @@ mod tests {
         checker.check_statement(&stmt).expect("operation failed");
 
         let ty = checker.get_var_type("lst");
-        assert_eq!(ty, Some(ArkType::Shared("List<Integer>".to_string())));
+        assert_eq!(ty, Some(ArkType::List(Box::new(ArkType::Integer))));
     }
 
     #[test]
@@ -1388,7 +2040,7 @@ mod tests {
         };
         checker.check_expression(&expr).expect("operation failed");
         assert!(
-            checker.type_errors.is_empty(),
+            checker.type_errors().is_empty(),
             "Expected no type errors for valid enum init"
         );
     }
@@ -1412,9 +2064,9 @@ mod tests {
             args: vec![],
         };
         checker.check_expression(&expr).expect("operation failed");
-        assert_eq!(checker.type_errors.len(), 1);
+        assert_eq!(checker.type_errors().len(), 1);
         assert!(matches!(
-            &checker.type_errors[0],
+            &checker.type_errors()[0],
             TypeError::UnknownVariant { .. }
         ));
     }
@@ -1438,9 +2090,9 @@ mod tests {
             args: vec![],
         };
         checker.check_expression(&expr).expect("operation failed");
-        assert_eq!(checker.type_errors.len(), 1);
+        assert_eq!(checker.type_errors().len(), 1);
         assert!(matches!(
-            &checker.type_errors[0],
+            &checker.type_errors()[0],
             TypeError::VariantFieldCount {
                 expected: 1,
                 got: 0,
@@ -1524,9 +2176,242 @@ mod tests {
         checker.check_statement(&stmt).expect("operation failed");
 
         assert!(
-            !checker.type_errors.is_empty(),
+            !checker.type_errors().is_empty(),
             "Expected type errors for mismatched let binding"
         );
-        assert!(matches!(&checker.type_errors[0], TypeError::Mismatch { name, .. } if name == "x"));
+        assert!(
+            matches!(&checker.type_errors()[0], TypeError::Mismatch { name, .. } if name == "x")
+        );
+    }
+
+    fn struct_decls(decls: &[(&str, Vec<(&str, ArkType)>)]) -> ArkNode {
+        ArkNode::Statement(Statement::Block(
+            decls
+                .iter()
+                .map(|(name, fields)| {
+                    Statement::StructDecl(StructDecl {
+                        name: name.to_string(),
+                        fields: fields
+                            .iter()
+                            .map(|(f, ty)| (f.to_string(), ty.clone()))
+                            .collect(),
+                    })
+                })
+                .collect(),
+        ))
+    }
+
+    fn named(name: &str) -> ArkType {
+        ArkType::Struct(name.to_string(), vec![])
+    }
+
+    #[test]
+    fn test_directly_recursive_struct_has_infinite_size() {
+        // struct Node { value: Int, next: Node }
+        let node = struct_decls(&[(
+            "Node",
+            vec![("value", ArkType::Integer), ("next", named("Node"))],
+        )]);
+        let errors = LinearChecker::check_with_types(&node).expect("check failed");
+        assert!(
+            errors.iter().any(|e| matches!(
+                e,
+                TypeError::InfiniteSizeType { name, cycle } if name == "Node" && cycle == "Node -> Node"
+            )),
+            "expected InfiniteSizeType, got {:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn test_mutually_recursive_structs_reported_once() {
+        let node = struct_decls(&[
+            ("A", vec![("b", named("B"))]),
+            ("B", vec![("a", named("A"))]),
+        ]);
+        let errors = LinearChecker::check_struct_sizes(&node);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(
+            matches!(&errors[0], TypeError::InfiniteSizeType { cycle, .. } if cycle == "A -> B -> A")
+        );
+    }
+
+    #[test]
+    fn test_list_indirected_struct_is_finite() {
+        // struct Node { value: Int, children: List<Node> }
+        let node = struct_decls(&[(
+            "Node",
+            vec![
+                ("value", ArkType::Integer),
+                ("children", ArkType::List(Box::new(named("Node")))),
+            ],
+        )]);
+        assert!(LinearChecker::check_struct_sizes(&node).is_empty());
+    }
+
+    fn condition_errors(source: &str) -> Vec<TypeError> {
+        let node = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+        LinearChecker::check_with_types(&node)
+            .expect("check failed")
+            .into_iter()
+            .filter(|e| matches!(e, TypeError::NonBooleanCondition { .. }))
+            .collect()
+    }
+
+    #[test]
+    fn test_string_while_condition_rejected() {
+        let errors = condition_errors("s := \"go\"\nwhile s {\n    print(1)\n}");
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(matches!(
+            &errors[0],
+            TypeError::NonBooleanCondition { construct, got } if construct == "while" && got == "String"
+        ));
+
+        let errors = condition_errors("if [1, 2] {\n    print(1)\n}");
+        assert!(
+            matches!(&errors[..], [TypeError::NonBooleanCondition { construct, .. }] if construct == "if")
+        );
+    }
+
+    #[test]
+    fn test_literal_destructure_length_mismatch() {
+        let errors = |source: &str| -> Vec<TypeError> {
+            let node = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+            LinearChecker::check_with_types(&node)
+                .expect("check failed")
+                .into_iter()
+                .filter(|e| matches!(e, TypeError::DestructureLength { .. }))
+                .collect()
+        };
+        assert!(matches!(
+            &errors("let [a, b, c] := [1]")[..],
+            [TypeError::DestructureLength { expected, got: 1 }] if expected == "3"
+        ));
+        assert_eq!(errors("let [a, b, ...rest] := [1]").len(), 1);
+        assert!(errors("let [a, b] := [1, 2]").is_empty());
+        assert!(errors("let [a, ...rest] := [1, 2, 3]").is_empty());
+    }
+
+    #[test]
+    fn test_integer_and_boolean_conditions_accepted() {
+        assert!(condition_errors("n := 3\nwhile n {\n    n := n - 1\n}").is_empty());
+        assert!(condition_errors("if true {\n    print(1)\n}").is_empty());
+    }
+
+    #[test]
+    fn test_builtin_result_option_and_propagation() {
+        let errors = |source: &str| -> Vec<TypeError> {
+            let node = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+            LinearChecker::check_with_types(&node)
+                .expect("check failed")
+                .into_iter()
+                .filter(|e| {
+                    matches!(
+                        e,
+                        TypeError::UnknownEnum(_)
+                            | TypeError::VariantFieldCount { .. }
+                            | TypeError::InvalidPropagation(_)
+                    )
+                })
+                .collect()
+        };
+        assert!(errors("r := Ok(1)\no := None\nx := r?\ny := o?").is_empty());
+        assert!(matches!(
+            &errors("x := Some(1, 2)")[..],
+            [TypeError::VariantFieldCount {
+                expected: 1,
+                got: 2,
+                ..
+            }]
+        ));
+        assert!(matches!(
+            &errors("s := \"text\"\nx := s?")[..],
+            [TypeError::InvalidPropagation(got)] if got == "String"
+        ));
+    }
+
+    #[test]
+    fn test_calls_and_lets_checked_against_signatures() {
+        let source = r#"
+func area(w: Int, h: Int) => Int {
+    return w * h
+}
+func label(n: Int) => Str {
+    bad := area(n, "wide")
+    return n
+}
+let size: Int := label(3)
+area(1)
+"#;
+        let node =
+            crate::parser::parse_source_with_spans(source, "test.ark").expect("parse failed");
+        let diagnostics = LinearChecker::check_diagnostics(&node).expect("check failed");
+        let errors: Vec<&TypeError> = diagnostics.iter().map(|d| &d.error).collect();
+        assert!(
+            matches!(
+                &errors[..],
+                [
+                    TypeError::ArgMismatch { func, index: 1, expected, got },
+                    TypeError::ReturnMismatch { .. },
+                    TypeError::Mismatch { name, .. },
+                    TypeError::ArgCount { expected: 2, got: 1, .. },
+                ] if func == "area" && expected == "Integer" && got == "String" && name == "size"
+            ),
+            "{:?}",
+            errors
+        );
+
+        // Errors inside a function point at it; top-level ones have no span
+        assert_eq!(diagnostics[0].function.as_deref(), Some("label"));
+        let span = diagnostics[0].span.as_ref().expect("function span");
+        assert_eq!(span.file, "test.ark");
+        assert!(
+            diagnostics[0]
+                .to_string()
+                .starts_with(&format!("test.ark:{}:", span.start_line))
+        );
+        assert!(diagnostics[2].function.is_none() && diagnostics[2].span.is_none());
+
+        let shared = diagnostics[0].to_diagnostic();
+        assert_eq!(shared.code, "E0107");
+        assert!(shared.message.ends_with("(in 'label')"));
+        assert_eq!(shared.span.as_ref(), Some(span));
+    }
+
+    #[test]
+    fn test_inferred_let_types_flow_into_operators() {
+        let errors = |source: &str| -> Vec<TypeError> {
+            let node = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+            LinearChecker::check_with_types(&node).expect("check failed")
+        };
+        let found = errors("s := sys.fs.read(\"a.txt\")\nn := s - 1");
+        assert!(
+            matches!(&found[..], [TypeError::ArgMismatch { func, index: 0, .. }] if func == "sub"),
+            "{:?}",
+            found
+        );
+        assert!(errors("a := 1\nb := a * 2.5\nc := \"x\" + \"y\"\nd := c < \"z\"").is_empty());
+        // Untyped parameters accept anything
+        assert!(errors("func id(x) {\n    return x\n}\na := id(\"s\")\nb := id(1)").is_empty());
+    }
+
+    #[test]
+    fn test_binding_types_cover_parameters_and_lets() {
+        let source =
+            "func area(w: Int, h: Int) => Int {\n    a := w * h\n    return a\n}\nname := \"box\"";
+        let node = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+        let bindings: Vec<String> = LinearChecker::binding_types(&node)
+            .iter()
+            .map(|b| format!("{:?} {}", b.function.as_deref(), b))
+            .collect();
+        assert_eq!(
+            bindings,
+            vec![
+                "Some(\"area\") w: Int",
+                "Some(\"area\") h: Int",
+                "Some(\"area\") a: Int",
+                "None name: Str",
+            ]
+        );
     }
 }
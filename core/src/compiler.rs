@@ -16,10 +16,13 @@
  * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
  */
 
-use crate::ast::{ArkNode, Expression, FunctionDef, Statement};
+use crate::ast::{walk_function, ArkNode, Expression, FunctionDef, Span, Statement, Visitor};
 use crate::bytecode::{Chunk, OpCode};
-use crate::runtime::Value;
-use std::collections::HashSet;
+use crate::diagnostics::Diagnostic;
+use crate::eval::Interpreter;
+use crate::runtime::{Scope, Value};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::sync::Arc;
 
@@ -43,6 +46,21 @@ impl fmt::Display for CompileError {
 
 impl std::error::Error for CompileError {}
 
+impl CompileError {
+    /// This error as a shared [`Diagnostic`]. Errors raised without a
+    /// source file carry no span: the compiler counts statements, not lines.
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let span = (self.file != "unknown").then(|| Span {
+            start_line: self.line as u32,
+            start_col: self.column as u32,
+            end_line: self.line as u32,
+            end_col: self.column as u32,
+            file: self.file.clone(),
+        });
+        Diagnostic::error("E0201", self.message.clone()).with_span(span)
+    }
+}
+
 /// optimization pipeline
 pub fn optimize(node: ArkNode, level: u8) -> ArkNode {
     if level == 0 {
@@ -51,9 +69,11 @@ pub fn optimize(node: ArkNode, level: u8) -> ArkNode {
 
     let mut current = node;
 
-    // Level 1: Constant Folding
+    // Level 1: Constant Folding (including calls to `#[const]` functions)
     if level >= 1 {
+        CONST_FNS.with(|fns| *fns.borrow_mut() = const_functions(&current));
         current = fold_constants(&current);
+        CONST_FNS.with(|fns| fns.borrow_mut().clear());
     }
 
     // Level 2: Dead Code Elimination
@@ -64,6 +84,61 @@ pub fn optimize(node: ArkNode, level: u8) -> ArkNode {
     current
 }
 
+thread_local! {
+    /// `#[const]` functions of the program currently being folded, by name.
+    static CONST_FNS: RefCell<HashMap<String, FunctionDef>> = RefCell::new(HashMap::new());
+}
+
+/// Every function in `node` marked `#[const]`.
+fn const_functions(node: &ArkNode) -> HashMap<String, FunctionDef> {
+    struct ConstCollector(HashMap<String, FunctionDef>);
+    impl Visitor for ConstCollector {
+        fn visit_function(&mut self, func: &FunctionDef) {
+            if func.attributes.iter().any(|a| a == "const") {
+                self.0.insert(func.name.clone(), func.clone());
+            }
+            walk_function(self, func);
+        }
+    }
+    let mut collector = ConstCollector(HashMap::new());
+    collector.visit_node(node);
+    collector.0
+}
+
+/// Value of a constant argument expression, if it is one.
+fn constant_value(expr: &Expression) -> Option<Value> {
+    match expr {
+        Expression::Integer(n) => Some(Value::Integer(*n)),
+        Expression::Literal(s) if s == "true" || s == "false" => Some(Value::Boolean(s == "true")),
+        Expression::Literal(s) => s.parse::<i64>().ok().map(Value::Integer),
+        _ => None,
+    }
+}
+
+/// Evaluate a call to a `#[const]` function whose arguments are all
+/// constants with the tree-walking interpreter. Returns the result as a
+/// literal, or `None` (leaving the call in place) when the callee is not
+/// `#[const]`, an argument is not constant, evaluation fails, or the result
+/// is not an integer or boolean.
+fn fold_const_call(name: &str, args: &[Expression]) -> Option<Expression> {
+    let func = CONST_FNS.with(|fns| fns.borrow().get(name).cloned())?;
+    if func.inputs.len() != args.len() {
+        return None;
+    }
+    let mut scope = Scope::new();
+    for ((param, _), arg) in func.inputs.iter().zip(args) {
+        scope.set(param.clone(), constant_value(arg)?);
+    }
+    let result = Interpreter::new()
+        .eval(&func.body.content, &mut scope)
+        .ok()?;
+    match result {
+        Value::Integer(n) => Some(Expression::Integer(n)),
+        Value::Boolean(b) => Some(Expression::Literal(b.to_string())),
+        _ => None,
+    }
+}
+
 fn fold_constants(node: &ArkNode) -> ArkNode {
     match node {
         ArkNode::Expression(expr) => ArkNode::Expression(fold_expr(expr)),
@@ -94,36 +169,66 @@ fn fold_stmt(stmt: &Statement) -> Statement {
                 .as_ref()
                 .map(|b| b.iter().map(fold_stmt).collect()),
         },
-        Statement::While { condition, body } => Statement::While {
+        Statement::While {
+            condition,
+            body,
+            label,
+        } => Statement::While {
             condition: fold_expr(condition),
             body: body.iter().map(fold_stmt).collect(),
+            label: label.clone(),
         },
         Statement::For {
             variable,
             iterable,
             body,
+            label,
         } => Statement::For {
             variable: variable.clone(),
             iterable: fold_expr(iterable),
             body: body.iter().map(fold_stmt).collect(),
+            label: label.clone(),
+        },
+        Statement::TryCatch {
+            body,
+            catch_var,
+            handler,
+        } => Statement::TryCatch {
+            body: body.iter().map(fold_stmt).collect(),
+            catch_var: catch_var.clone(),
+            handler: handler.iter().map(fold_stmt).collect(),
+        },
+        Statement::Break { label } => Statement::Break {
+            label: label.clone(),
+        },
+        Statement::Continue { label } => Statement::Continue {
+            label: label.clone(),
         },
-        Statement::Break => Statement::Break,
-        Statement::Continue => Statement::Continue,
         Statement::Import(i) => Statement::Import(i.clone()),
         Statement::StructDecl(s) => Statement::StructDecl(s.clone()),
         Statement::Function(f) => Statement::Function(fold_func(f)),
-        Statement::LetDestructure { names, value } => Statement::LetDestructure {
+        Statement::Export(inner) => Statement::Export(Box::new(fold_stmt(inner))),
+        Statement::LetDestructure {
+            names,
+            value,
+            by_field,
+            rest,
+        } => Statement::LetDestructure {
             names: names.clone(),
             value: fold_expr(value),
+            by_field: *by_field,
+            rest: rest.clone(),
         },
         Statement::SetField {
             obj_name,
             field,
             value,
+            obj,
         } => Statement::SetField {
             obj_name: obj_name.clone(),
             field: field.clone(),
             value: fold_expr(value),
+            obj: obj.as_ref().map(|o| Box::new(fold_expr(o))),
         },
         Statement::EnumDecl(e) => Statement::EnumDecl(e.clone()),
         Statement::TraitDecl(t) => Statement::TraitDecl(t.clone()),
@@ -146,6 +251,7 @@ fn fold_func(func: &FunctionDef) -> FunctionDef {
     // We need to update hash. MastNode::new(content) does that.
     if let Ok(new_mast) = crate::ast::MastNode::new(new_content) {
         new_func.body = Box::new(new_mast);
+        new_func.body.span = func.body.span.clone();
     }
     new_func
 }
@@ -158,6 +264,10 @@ fn fold_expr(expr: &Expression) -> Expression {
         } => {
             let folded_args: Vec<Expression> = args.iter().map(fold_expr).collect();
 
+            if let Some(folded) = fold_const_call(function_hash, &folded_args) {
+                return folded;
+            }
+
             // Try to fold if args are literals
             if folded_args
                 .iter()
@@ -271,10 +381,17 @@ fn fold_expr(expr: &Expression) -> Expression {
                 .map(|(k, v)| (k.clone(), fold_expr(v)))
                 .collect(),
         },
+        Expression::MapInit { entries } => Expression::MapInit {
+            entries: entries
+                .iter()
+                .map(|(k, v)| (fold_expr(k), fold_expr(v)))
+                .collect(),
+        },
         Expression::GetField { obj, field } => Expression::GetField {
             obj: Box::new(fold_expr(obj)),
             field: field.clone(),
         },
+        Expression::Propagate(inner) => Expression::Propagate(Box::new(fold_expr(inner))),
         _ => expr.clone(),
     }
 }
@@ -292,6 +409,7 @@ fn dce_func(func: &FunctionDef) -> FunctionDef {
     let new_content = eliminate_dead_code(&func.body.content);
     if let Ok(new_mast) = crate::ast::MastNode::new(new_content) {
         new_func.body = Box::new(new_mast);
+        new_func.body.span = func.body.span.clone();
     }
     new_func
 }
@@ -347,11 +465,16 @@ fn dce_stmt(stmt: &Statement) -> Statement {
                 }
             }
         }
-        Statement::While { condition, body } => match condition {
+        Statement::While {
+            condition,
+            body,
+            label,
+        } => match condition {
             Expression::Literal(s) if s == "false" => Statement::Block(vec![]),
             _ => Statement::While {
                 condition: condition.clone(),
                 body: body.iter().map(dce_stmt).collect(),
+                label: label.clone(),
             },
         },
         _ => stmt.clone(),
@@ -486,6 +609,8 @@ impl Compiler {
                 func_compiler.visit(&func_def.body.content, true)?;
 
                 func_compiler.chunk.write(OpCode::Ret);
+                func_compiler.chunk.name = Some(func_def.name.clone());
+                func_compiler.chunk.span = func_def.body.span.clone();
 
                 let compiled_chunk = func_compiler.chunk;
                 // 5. Emit Push(Value::Function(Arc::new(chunk)))
@@ -556,7 +681,9 @@ impl Compiler {
                 }
                 Ok(())
             }
-            Statement::While { condition, body } => {
+            Statement::While {
+                condition, body, ..
+            } => {
                 let loop_start_idx = self.chunk.code.len();
                 self.visit_expr(condition)?;
                 let jump_idx = self.chunk.code.len();
@@ -577,15 +704,84 @@ impl Compiler {
                 }
                 Ok(())
             }
+            Statement::TryCatch {
+                body,
+                catch_var,
+                handler,
+            } => {
+                //   SetupTry(catch)
+                //   body...
+                //   PopTry
+                //   Jmp(end)
+                // catch:                       ; error struct on the stack
+                //   Store(catch_var)
+                //   handler...
+                // end:
+                let setup_idx = self.chunk.code.len();
+                self.chunk.write(OpCode::SetupTry(0));
+                self.scopes.push(HashSet::new());
+                if body.is_empty() && preserve {
+                    self.chunk.write(OpCode::Push(Value::Unit));
+                }
+                for (i, s) in body.iter().enumerate() {
+                    self.visit_stmt(s, preserve && i == body.len() - 1)?;
+                }
+                self.scopes.pop();
+                self.chunk.write(OpCode::PopTry);
+                let jump_idx = self.chunk.code.len();
+                self.chunk.write(OpCode::Jmp(0));
+
+                let catch_idx = self.chunk.code.len();
+                self.chunk.code[setup_idx] = OpCode::SetupTry(catch_idx);
+                self.scopes.push(HashSet::from([catch_var.clone()]));
+                self.chunk.write(OpCode::Store(catch_var.clone()));
+                if handler.is_empty() && preserve {
+                    self.chunk.write(OpCode::Push(Value::Unit));
+                }
+                for (i, s) in handler.iter().enumerate() {
+                    self.visit_stmt(s, preserve && i == handler.len() - 1)?;
+                }
+                self.scopes.pop();
+
+                let end_idx = self.chunk.code.len();
+                self.chunk.code[jump_idx] = OpCode::Jmp(end_idx);
+                Ok(())
+            }
             Statement::Return(expr) => {
                 self.visit_expr(expr)?;
                 self.chunk.write(OpCode::Ret);
                 Ok(())
             }
-            Statement::LetDestructure { names, value } => {
+            Statement::LetDestructure {
+                names,
+                value,
+                by_field,
+                rest,
+            } => {
+                if let Some(rest) = rest {
+                    return Err(CompileError {
+                        message: format!(
+                            "rest binding '...{}' is not supported by the bytecode compiler",
+                            rest
+                        ),
+                        line: self.current_line as usize,
+                        column: 0,
+                        file: "unknown".into(),
+                    });
+                }
                 self.visit_expr(value)?;
-                self.chunk.write(OpCode::Destructure);
+                if *by_field {
+                    self.chunk
+                        .write(OpCode::Store("__destructure_struct".to_string()));
+                } else {
+                    self.chunk.write(OpCode::Destructure);
+                }
                 for name in names {
+                    if *by_field {
+                        self.chunk
+                            .write(OpCode::Load("__destructure_struct".to_string()));
+                        self.chunk.write(OpCode::GetField(name.clone()));
+                    }
                     self.chunk.write(OpCode::Store(name.clone()));
                     if let Some(scope) = self.scopes.last_mut() {
                         scope.insert(name.clone());
@@ -597,13 +793,26 @@ impl Compiler {
                 obj_name,
                 field,
                 value,
+                obj,
             } => {
+                if obj.is_some() {
+                    return Err(CompileError {
+                        message: format!(
+                            "nested field assignment to '{}.{}' is not supported by the bytecode compiler",
+                            obj_name, field
+                        ),
+                        line: self.current_line as usize,
+                        column: 0,
+                        file: "unknown".into(),
+                    });
+                }
                 self.visit_expr(value)?;
                 self.chunk.write(OpCode::Load(obj_name.clone()));
                 self.chunk.write(OpCode::SetField(field.clone()));
                 self.chunk.write(OpCode::Store(obj_name.clone()));
                 Ok(())
             }
+            Statement::Export(inner) => self.visit_stmt(inner, preserve),
             Statement::Import(_)
             | Statement::StructDecl(_)
             | Statement::EnumDecl(_)
@@ -623,9 +832,11 @@ impl Compiler {
                     }
                     func_compiler.visit(&method.body.content, true)?;
                     func_compiler.chunk.write(OpCode::Ret);
+                    let qualified_name = format!("{}::{}", impl_blk.target_type, method.name);
+                    func_compiler.chunk.name = Some(qualified_name.clone());
+                    func_compiler.chunk.span = method.body.span.clone();
                     let compiled_chunk = func_compiler.chunk;
                     let func_val = Value::Function(Arc::new(compiled_chunk));
-                    let qualified_name = format!("{}::{}", impl_blk.target_type, method.name);
                     self.chunk.write(OpCode::Push(func_val));
                     self.chunk.write(OpCode::Store(qualified_name.clone()));
                     if let Some(scope) = self.scopes.last_mut() {
@@ -634,7 +845,7 @@ impl Compiler {
                 }
                 Ok(())
             }
-            Statement::For { .. } | Statement::Break | Statement::Continue => {
+            Statement::For { .. } | Statement::Break { .. } | Statement::Continue { .. } => {
                 // Not supported in bytecode compiler yet
                 Ok(())
             }
@@ -658,12 +869,31 @@ impl Compiler {
                 self.chunk.write(OpCode::MakeStruct(fields.len()));
                 Ok(())
             }
+            Expression::MapInit { entries } => {
+                // Thread the map through pmap.assoc once per entry
+                self.chunk
+                    .write(OpCode::Load("intrinsic_pmap_new".to_string()));
+                self.chunk.write(OpCode::Call(0));
+                for (key, value) in entries {
+                    self.visit_expr(key)?;
+                    self.visit_expr(value)?;
+                    self.chunk
+                        .write(OpCode::Load("intrinsic_pmap_assoc".to_string()));
+                    self.chunk.write(OpCode::Call(3));
+                }
+                Ok(())
+            }
             Expression::GetField { obj, field } => {
                 self.visit_expr(obj)?;
                 self.chunk.write(OpCode::GetField(field.clone()));
                 Ok(())
             }
 
+            // `true`/`false` are booleans, as in the tree-walker
+            Expression::Literal(s) if s == "true" || s == "false" => {
+                self.chunk.write(OpCode::Push(Value::Boolean(s == "true")));
+                Ok(())
+            }
             Expression::Literal(s) => {
                 self.chunk.write(OpCode::Push(Value::String(s.clone())));
                 Ok(())
@@ -859,6 +1089,11 @@ impl Compiler {
                 ));
                 Ok(())
             }
+            Expression::Propagate(inner) => {
+                self.visit_expr(inner)?;
+                self.chunk.write(OpCode::Propagate);
+                Ok(())
+            }
         }
     }
 }
@@ -962,6 +1197,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_const_fn_call_folds_to_literal() {
+        let source =
+            "#[const]\nfunc square(x) { return x * x }\nlet a := square(5)\nlet b := square(a)";
+        let ast = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+        let stmts = match optimize(ast, 1) {
+            ArkNode::Statement(Statement::Block(stmts)) => stmts,
+            other => panic!("Expected Block, got {:?}", other),
+        };
+        let value_of = |want: &str| {
+            stmts.iter().find_map(|s| match s {
+                Statement::Let { name, value, .. } if name == want => Some(value.clone()),
+                _ => None,
+            })
+        };
+        assert_eq!(value_of("a"), Some(Expression::Integer(25)));
+        // Non-constant argument: the call stays
+        assert!(matches!(
+            value_of("b"),
+            Some(Expression::Call { ref function_hash, .. }) if function_hash == "square"
+        ));
+    }
+
     #[test]
     fn test_compile_error_format() {
         let err = CompileError {
@@ -8,6 +8,7 @@
 
 use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
 use sha2::{Digest, Sha256, Sha512};
+use sha3::Keccak256;
 use std::convert::TryInto;
 
 // ============================================================================
@@ -35,6 +36,18 @@ pub fn hash_double_sha256(data: &[u8]) -> [u8; 32] {
     hash_sha256(&hash_sha256(data))
 }
 
+/// Ethereum's Keccak-256: the original Keccak padding, which differs from
+/// the later SHA3-256 standard.
+pub fn hash_keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+pub fn hash_blake3(data: &[u8]) -> [u8; 32] {
+    *blake3::hash(data).as_bytes()
+}
+
 pub fn hash_to_hex(hash: &[u8]) -> String {
     hex::encode(hash)
 }
@@ -44,15 +57,17 @@ pub fn hash(data: &[u8]) -> String {
     hash_to_hex(&hash_sha256(data))
 }
 
+/// Compares two byte strings without branching on their contents. A length
+/// mismatch still scans the shared prefix so timing only reveals the lengths.
 pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
-    if a.len() != b.len() {
-        return false;
-    }
-    let mut res = 0;
+    let mut res: u8 = (a.len() != b.len()) as u8;
     for (x, y) in a.iter().zip(b.iter()) {
         res |= x ^ y;
     }
-    res == 0
+    // Fold any set bit down to bit 0 instead of comparing, so the final
+    // decision is a mask rather than an early-exit branch.
+    let res = std::hint::black_box(res);
+    ((res | res.wrapping_neg()) >> 7) == 0
 }
 
 // ============================================================================
@@ -356,6 +371,13 @@ mod tests {
         assert!(!constant_time_eq(a, b));
     }
 
+    #[test]
+    fn test_constant_time_eq_length_mismatch() {
+        assert!(!constant_time_eq(b"secret", b"secret!"));
+        assert!(!constant_time_eq(b"", b"x"));
+        assert!(constant_time_eq(b"", b""));
+    }
+
     #[test]
     fn test_derive_key_deterministic() {
         let seed = b"test_seed";
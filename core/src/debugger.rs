@@ -6,8 +6,29 @@
 
 use crate::bytecode::Chunk;
 use crate::runtime::{Scope, Value};
+use std::cell::Cell;
 use std::collections::HashSet;
 
+thread_local! {
+    static BREAK_REQUESTED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Ask an attached debugger to pause before the next instruction
+/// (`sys.debug.break()`).
+pub fn request_break() {
+    BREAK_REQUESTED.with(|flag| flag.set(true));
+}
+
+/// Whether a pause was asked for and not yet taken.
+pub fn break_requested() -> bool {
+    BREAK_REQUESTED.with(Cell::get)
+}
+
+/// Take the pending pause request, if any.
+pub fn take_break_request() -> bool {
+    BREAK_REQUESTED.with(|flag| flag.replace(false))
+}
+
 /// Debugger step mode
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum StepMode {
@@ -36,6 +57,8 @@ pub struct DebugState {
     pub step_mode: StepMode,
     /// Last source line the debugger stopped at
     pub last_line: u32,
+    /// Source line of the last instruction checked
+    pub previous_line: u32,
     /// Call depth when step-over was initiated
     pub step_over_depth: usize,
     /// Whether we are in a stepping state (should stop at next line)
@@ -48,6 +71,7 @@ impl Default for DebugState {
             breakpoints: HashSet::new(),
             step_mode: StepMode::StepInto,
             last_line: u32::MAX,
+            previous_line: u32::MAX,
             step_over_depth: 0,
             stepping: true,
         }
@@ -82,21 +106,28 @@ impl DebugState {
 
     /// Check whether the debugger should pause at this IP
     pub fn should_break(&mut self, ip: usize, chunk: &Chunk, frame_depth: usize) -> bool {
+        // The program asked for a pause with sys.debug.break()
+        if take_break_request() {
+            return true;
+        }
+
         let loc = match chunk.get_source_loc(ip) {
             Some(loc) if loc.line > 0 => loc,
             _ => return false,
         };
 
         let current_line = loc.line;
+        let entered = current_line != self.previous_line;
+        self.previous_line = current_line;
 
-        // Always skip if we haven't moved to a new line
-        if current_line == self.last_line && !self.breakpoints.contains(&current_line) {
-            return false;
+        // Breakpoints stop once each time their line is entered
+        if self.breakpoints.contains(&current_line) {
+            return entered;
         }
 
-        // Check breakpoints first (always stop)
-        if self.breakpoints.contains(&current_line) {
-            return true;
+        // Always skip if we haven't moved to a new line
+        if current_line == self.last_line {
+            return false;
         }
 
         // Check step mode
@@ -127,6 +158,9 @@ pub fn format_value(val: &Value) -> String {
             format!("[{}]", inner.join(", "))
         }
         Value::Buffer(buf) => format!("<buffer {} bytes>", buf.len()),
+        Value::BufferView { offset, len, .. } => {
+            format!("<buffer view {} bytes @{}>", len, offset)
+        }
         Value::Struct(fields) => {
             let inner: Vec<String> = fields
                 .iter()
@@ -136,6 +170,9 @@ pub fn format_value(val: &Value) -> String {
         }
         Value::PVec(pv) => format!("{}", pv),
         Value::PMap(pm) => format!("{}", pm),
+        Value::BigInt(n) => n.to_string(),
+        Value::Decimal(d) => format!("{}d", d),
+        Value::Tensor(t) => format!("#tensor{}", t),
         Value::LinearObject { id, .. } => format!("<linear {}>", id),
         Value::Return(val) => format!("return({})", format_value(val)),
         Value::EnumValue {
@@ -277,4 +314,38 @@ mod tests {
 
         assert!(!state.should_break(0, &chunk, 0));
     }
+
+    #[test]
+    fn test_breakpoint_stops_once_per_visit() {
+        let mut state = DebugState::new();
+        state.step_mode = StepMode::Continue;
+        state.stepping = false;
+        state.add_breakpoint(2);
+
+        let mut chunk = Chunk::new();
+        for line in [2, 2, 3, 2] {
+            chunk.set_source_pos(line, 0);
+            chunk.write(crate::bytecode::OpCode::Pop);
+        }
+
+        let stops: Vec<bool> = (0..4).map(|ip| state.should_break(ip, &chunk, 0)).collect();
+        assert_eq!(stops, [true, false, false, true]);
+    }
+
+    #[test]
+    fn test_should_break_when_the_program_asks() {
+        let mut state = DebugState::new();
+        state.step_mode = StepMode::Continue;
+        state.stepping = false;
+
+        let mut chunk = Chunk::new();
+        chunk.set_source_pos(3, 0);
+        chunk.write(crate::bytecode::OpCode::Push(Value::Integer(1)));
+
+        request_break();
+        assert!(break_requested());
+        assert!(state.should_break(0, &chunk, 0));
+        assert!(!break_requested());
+        assert!(!state.should_break(0, &chunk, 0));
+    }
 }
@@ -0,0 +1,345 @@
+/*
+ * Copyright (c) 2026 Mohamad Al-Zawahreh (dba Sovereign Systems).
+ *
+ * This file is part of the Ark Sovereign Compiler.
+ *
+ * LICENSE: DUAL-LICENSED (AGPLv3 or COMMERCIAL).
+ *
+ * 1. OPEN SOURCE: You may use this file under the terms of the GNU Affero
+ * General Public License v3.0. If you link to this code, your ENTIRE
+ * application must be open-sourced under AGPLv3.
+ *
+ * 2. COMMERCIAL: For proprietary use, you must obtain a Commercial License
+ * from Sovereign Systems.
+ *
+ * PATENT NOTICE: Protected by US Patent App #63/935,467.
+ * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
+ */
+
+//! Fixed-point decimals for the `dec.*` intrinsics and `12.50d` literals.
+//!
+//! A [`Decimal`] counts units of `10^-scale` in an `i128`, so `12.50d` is
+//! 1250 units at scale 2. Sums and differences take the larger scale of
+//! their operands and products the sum of both, so they are exact; only
+//! division and [`Decimal::round`] drop digits, rounding half away from
+//! zero. Anything that would leave the `i128` range is an error rather
+//! than a wrap. Values compare by what they are worth, so `1.0d` equals
+//! `1.00d`.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Most digits after the point a decimal may carry
+pub const MAX_SCALE: u32 = 28;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Decimal {
+    units: i128,
+    scale: u32,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DecimalError {
+    #[error("not a decimal number: {0:?}")]
+    Parse(String),
+    #[error("result is out of range")]
+    Overflow,
+    #[error("division by zero")]
+    DivisionByZero,
+    #[error("scale {0} is above the maximum of {MAX_SCALE}")]
+    Scale(u32),
+}
+
+impl Decimal {
+    /// `units` of `10^-scale`.
+    pub fn new(units: i128, scale: u32) -> Result<Self, DecimalError> {
+        if scale > MAX_SCALE {
+            return Err(DecimalError::Scale(scale));
+        }
+        Ok(Self { units, scale })
+    }
+
+    pub fn units(&self) -> i128 {
+        self.units
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn checked_add(&self, other: &Decimal) -> Result<Decimal, DecimalError> {
+        let scale = self.scale.max(other.scale);
+        let units = rescale(self.units, self.scale, scale)?
+            .checked_add(rescale(other.units, other.scale, scale)?)
+            .ok_or(DecimalError::Overflow)?;
+        Ok(Self { units, scale })
+    }
+
+    pub fn checked_sub(&self, other: &Decimal) -> Result<Decimal, DecimalError> {
+        let negated = other.units.checked_neg().ok_or(DecimalError::Overflow)?;
+        self.checked_add(&Self {
+            units: negated,
+            scale: other.scale,
+        })
+    }
+
+    /// The exact product, unless it needs more than [`MAX_SCALE`] digits
+    /// after the point, when it is rounded to that many.
+    pub fn checked_mul(&self, other: &Decimal) -> Result<Decimal, DecimalError> {
+        let units = self
+            .units
+            .checked_mul(other.units)
+            .ok_or(DecimalError::Overflow)?;
+        let scale = self.scale + other.scale;
+        if scale > MAX_SCALE {
+            return Ok(Self {
+                units: rescale(units, scale, MAX_SCALE)?,
+                scale: MAX_SCALE,
+            });
+        }
+        Ok(Self { units, scale })
+    }
+
+    /// `self / other` to `scale` digits after the point.
+    pub fn checked_div(&self, other: &Decimal, scale: u32) -> Result<Decimal, DecimalError> {
+        if scale > MAX_SCALE {
+            return Err(DecimalError::Scale(scale));
+        }
+        if other.units == 0 {
+            return Err(DecimalError::DivisionByZero);
+        }
+        // self / other * 10^scale = self.units * 10^shift / other.units
+        let shift = scale as i64 + other.scale as i64 - self.scale as i64;
+        let (numerator, denominator) = if shift >= 0 {
+            let factor = pow10(shift as u32)?;
+            (
+                self.units
+                    .checked_mul(factor)
+                    .ok_or(DecimalError::Overflow)?,
+                other.units,
+            )
+        } else {
+            let factor = pow10((-shift) as u32)?;
+            (
+                self.units,
+                other
+                    .units
+                    .checked_mul(factor)
+                    .ok_or(DecimalError::Overflow)?,
+            )
+        };
+        Ok(Self {
+            units: div_round(numerator, denominator)?,
+            scale,
+        })
+    }
+
+    /// `self` with exactly `scale` digits after the point.
+    pub fn round(&self, scale: u32) -> Result<Decimal, DecimalError> {
+        if scale > MAX_SCALE {
+            return Err(DecimalError::Scale(scale));
+        }
+        Ok(Self {
+            units: rescale(self.units, self.scale, scale)?,
+            scale,
+        })
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(value: i64) -> Self {
+        Self {
+            units: value as i128,
+            scale: 0,
+        }
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = DecimalError;
+
+    /// Digits with an optional leading `-` and an optional `.` followed by
+    /// at least one more digit, as in `12`, `-0.5` or `12.50`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_error = || DecimalError::Parse(s.to_string());
+        let unsigned = s.strip_prefix('-').unwrap_or(s);
+        let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        let all_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if whole.is_empty()
+            || !all_digits(whole)
+            || !all_digits(fraction)
+            || (fraction.is_empty() && unsigned.contains('.'))
+        {
+            return Err(parse_error());
+        }
+        let scale = fraction.len() as u32;
+        if scale > MAX_SCALE {
+            return Err(DecimalError::Scale(scale));
+        }
+        // The digits alone can only fail to parse by overflowing
+        let digits = format!("{}{}{}", &s[..s.len() - unsigned.len()], whole, fraction);
+        let units = digits.parse().map_err(|_| DecimalError::Overflow)?;
+        Ok(Self { units, scale })
+    }
+}
+
+impl fmt::Display for Decimal {
+    /// Every digit of the scale, so `12.50d` shows as `12.50`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.units < 0 { "-" } else { "" };
+        let magnitude = self.units.unsigned_abs();
+        if self.scale == 0 {
+            return write!(f, "{}{}", sign, magnitude);
+        }
+        let one = 10u128.pow(self.scale);
+        write!(
+            f,
+            "{}{}.{:0width$}",
+            sign,
+            magnitude / one,
+            magnitude % one,
+            width = self.scale as usize
+        )
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let scale = self.scale.max(other.scale);
+        match (
+            rescale(self.units, self.scale, scale),
+            rescale(other.units, other.scale, scale),
+        ) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            // Only the side with the smaller scale is scaled up, and if
+            // that overflows it outweighs the other whatever its sign
+            (Err(_), _) => self.units.cmp(&0),
+            (_, Err(_)) => 0.cmp(&other.units),
+        }
+    }
+}
+
+fn pow10(exponent: u32) -> Result<i128, DecimalError> {
+    10i128.checked_pow(exponent).ok_or(DecimalError::Overflow)
+}
+
+/// `units` at scale `from` re-expressed at scale `to`, rounding half away
+/// from zero when that drops digits.
+fn rescale(units: i128, from: u32, to: u32) -> Result<i128, DecimalError> {
+    match to.cmp(&from) {
+        Ordering::Equal => Ok(units),
+        Ordering::Greater => units
+            .checked_mul(pow10(to - from)?)
+            .ok_or(DecimalError::Overflow),
+        Ordering::Less => div_round(units, pow10(from - to)?),
+    }
+}
+
+/// `numerator / denominator`, rounding half away from zero.
+fn div_round(numerator: i128, denominator: i128) -> Result<i128, DecimalError> {
+    let quotient = numerator
+        .checked_div(denominator)
+        .ok_or(DecimalError::Overflow)?;
+    let remainder = numerator % denominator;
+    if remainder.unsigned_abs() * 2 < denominator.unsigned_abs() {
+        return Ok(quotient);
+    }
+    let away = if (numerator < 0) == (denominator < 0) {
+        1
+    } else {
+        -1
+    };
+    quotient.checked_add(away).ok_or(DecimalError::Overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(s: &str) -> Decimal {
+        s.parse().expect("valid decimal")
+    }
+
+    #[test]
+    fn test_parse_and_display_keep_the_scale() {
+        for text in ["0", "12.50", "-0.05", "1000000.000001"] {
+            assert_eq!(dec(text).to_string(), text);
+        }
+        assert_eq!(dec("12.50").units(), 1250);
+        assert_eq!(dec("12.50").scale(), 2);
+        for bad in ["", "-", ".5", "1.", "1.2.3", "+1", "1e3", "12.5x"] {
+            assert_eq!(
+                bad.parse::<Decimal>(),
+                Err(DecimalError::Parse(bad.to_string()))
+            );
+        }
+        assert_eq!(
+            "0.12345678901234567890123456789".parse::<Decimal>(),
+            Err(DecimalError::Scale(29))
+        );
+        assert_eq!(
+            "999999999999999999999999999999999999999".parse::<Decimal>(),
+            Err(DecimalError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_is_exact_until_division() {
+        let price = dec("19.99");
+        let sum = price.checked_add(&dec("0.01")).unwrap();
+        assert_eq!(sum.to_string(), "20.00");
+        assert_eq!(dec("0.1").checked_add(&dec("0.2")).unwrap(), dec("0.3"));
+        assert_eq!(price.checked_sub(&dec("20")).unwrap().to_string(), "-0.01");
+        assert_eq!(
+            price.checked_mul(&Decimal::from(3)).unwrap().to_string(),
+            "59.97"
+        );
+        assert_eq!(
+            dec("1.25").checked_mul(&dec("1.25")).unwrap().to_string(),
+            "1.5625"
+        );
+
+        let third = dec("10.00").checked_div(&Decimal::from(3), 2).unwrap();
+        assert_eq!(third.to_string(), "3.33");
+        let two_thirds = dec("-2").checked_div(&Decimal::from(3), 4).unwrap();
+        assert_eq!(two_thirds.to_string(), "-0.6667");
+        assert_eq!(
+            dec("1").checked_div(&dec("0.00"), 2),
+            Err(DecimalError::DivisionByZero)
+        );
+
+        let max = Decimal::new(i128::MAX, 0).unwrap();
+        assert_eq!(
+            max.checked_add(&Decimal::from(1)),
+            Err(DecimalError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_round_half_away_from_zero() {
+        assert_eq!(dec("2.345").round(2).unwrap().to_string(), "2.35");
+        assert_eq!(dec("-2.345").round(2).unwrap().to_string(), "-2.35");
+        assert_eq!(dec("2.344").round(2).unwrap().to_string(), "2.34");
+        assert_eq!(dec("2.5").round(0).unwrap().to_string(), "3");
+        assert_eq!(dec("7").round(2).unwrap().to_string(), "7.00");
+        assert_eq!(dec("1.0"), dec("1.00"));
+        assert!(dec("-0.5") < dec("0.25"));
+        assert!(Decimal::new(i128::MAX, 0).unwrap() > dec("1.5"));
+    }
+}
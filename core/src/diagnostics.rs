@@ -0,0 +1,273 @@
+/*
+ * Copyright (c) 2026 Mohamad Al-Zawahreh (dba Sovereign Systems).
+ *
+ * This file is part of the Ark Sovereign Compiler.
+ *
+ * LICENSE: DUAL-LICENSED (AGPLv3 or COMMERCIAL).
+ *
+ * 1. OPEN SOURCE: You may use this file under the terms of the GNU Affero
+ * General Public License v3.0. If you link to this code, your ENTIRE
+ * application must be open-sourced under AGPLv3.
+ *
+ * 2. COMMERCIAL: For proprietary use, you must obtain a Commercial License
+ * from Sovereign Systems.
+ *
+ * PATENT NOTICE: Protected by US Patent App #63/935,467.
+ * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
+ */
+
+//! Compiler diagnostics shared by the parser, checker, bytecode compiler
+//! and WASM codegen: one `Diagnostic` shape with a span, severity, stable
+//! code and optional help, rendered either rustc-style against the source
+//! or as one JSON object per line for editors.
+//!
+//! (Unrelated to `crate::diagnostic`, the runtime proof suite.)
+//!
+//! Codes by stage:
+//!
+//! | Range | Stage |
+//! |---|---|
+//! | `E00xx` | parser |
+//! | `E01xx` | type and linearity checker |
+//! | `E02xx` | bytecode compiler |
+//! | `E03xx` | WASM codegen |
+
+use crate::ast::Span;
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// A single compiler message.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Stable identifier such as `E0107`, for tooling and documentation.
+    pub code: String,
+    pub message: String,
+    /// Where the problem is; `None` when the stage has no location for it.
+    pub span: Option<Span>,
+    /// A suggestion for fixing it.
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &str, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code: code.to_string(),
+            message: message.into(),
+            span: None,
+            help: None,
+        }
+    }
+
+    pub fn warning(code: &str, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Self::error(code, message)
+        }
+    }
+
+    pub fn with_span(mut self, span: Option<Span>) -> Self {
+        self.span = span;
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Render rustc-style, quoting the spanned line of `source` with a caret
+    /// under the span. A span that runs past its first line is underlined
+    /// to the end of that line.
+    pub fn render(&self, source: &str) -> String {
+        let mut out = format!("{}[{}]: {}", self.severity, self.code, self.message);
+        let quoted = self.span.as_ref().and_then(|span| {
+            let line = source
+                .lines()
+                .nth((span.start_line as usize).checked_sub(1)?)?;
+            Some((span, line))
+        });
+        let gutter = match quoted {
+            Some((span, _)) => span.start_line.to_string().len(),
+            None => 0,
+        };
+        let pad = " ".repeat(gutter);
+
+        if let Some(span) = &self.span {
+            out.push_str(&format!(
+                "\n{}--> {}:{}:{}",
+                pad, span.file, span.start_line, span.start_col
+            ));
+        }
+        if let Some((span, line)) = quoted {
+            // Columns are 1-based and the end column is inclusive
+            let start = (span.start_col as usize).saturating_sub(1);
+            let end = if span.end_line == span.start_line {
+                span.end_col as usize
+            } else {
+                line.chars().count()
+            }
+            .max(start + 1);
+            out.push_str(&format!("\n{} |", pad));
+            out.push_str(&format!("\n{} | {}", span.start_line, line));
+            out.push_str(&format!(
+                "\n{} | {}{}",
+                pad,
+                " ".repeat(start),
+                "^".repeat(end - start)
+            ));
+        }
+        if let Some(help) = &self.help {
+            out.push_str(&format!("\n{} = help: {}", pad, help));
+        }
+        out
+    }
+
+    /// The diagnostic as a single-line JSON object.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)?;
+        if let Some(span) = &self.span {
+            write!(
+                f,
+                " at {}:{}:{}",
+                span.file, span.start_line, span.start_col
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// How the command-line tools print diagnostics (`--error-format`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    /// Rendered against the source, for people.
+    #[default]
+    Human,
+    /// One JSON object per line, for editors.
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(ErrorFormat::Human),
+            "json" => Ok(ErrorFormat::Json),
+            other => Err(format!(
+                "unknown error format '{}' (expected human or json)",
+                other
+            )),
+        }
+    }
+}
+
+impl ErrorFormat {
+    /// Format `diagnostics` for output, one per block (human) or line (json).
+    pub fn emit(self, diagnostics: &[Diagnostic], source: &str) -> String {
+        let rendered: Vec<String> = diagnostics
+            .iter()
+            .map(|d| match self {
+                ErrorFormat::Human => d.render(source),
+                ErrorFormat::Json => d.to_json(),
+            })
+            .collect();
+        match self {
+            ErrorFormat::Human => rendered.join("\n\n"),
+            ErrorFormat::Json => rendered.join("\n"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(line: u32, start: u32, end: u32) -> Option<Span> {
+        Some(Span {
+            start_line: line,
+            start_col: start,
+            end_line: line,
+            end_col: end,
+            file: "app.ark".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_render_points_a_caret_at_the_span() {
+        let source = "x := 1\ny := x + \"a\"\n";
+        let diagnostic = Diagnostic::error("E0107", "mismatched operands")
+            .with_span(span(2, 6, 12))
+            .with_help("convert one side first");
+        assert_eq!(
+            diagnostic.render(source),
+            "error[E0107]: mismatched operands\n \
+             --> app.ark:2:6\n  \
+             |\n\
+             2 | y := x + \"a\"\n  \
+             |      ^^^^^^^\n  \
+             = help: convert one side first"
+        );
+    }
+
+    #[test]
+    fn test_render_without_span_or_source_line() {
+        let diagnostic = Diagnostic::warning("E0301", "unsupported");
+        assert_eq!(diagnostic.render(""), "warning[E0301]: unsupported");
+
+        // A span past the end of the source still names the location
+        let diagnostic = Diagnostic::error("E0001", "bad").with_span(span(9, 1, 1));
+        assert_eq!(
+            diagnostic.render("x := 1"),
+            "error[E0001]: bad\n--> app.ark:9:1"
+        );
+    }
+
+    #[test]
+    fn test_json_format_is_one_object_per_line() {
+        let diagnostics = vec![
+            Diagnostic::error("E0001", "first").with_span(span(1, 1, 2)),
+            Diagnostic::warning("E0101", "second"),
+        ];
+        let out = ErrorFormat::Json.emit(&diagnostics, "");
+        let lines: Vec<serde_json::Value> = out
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("valid json"))
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["severity"], "error");
+        assert_eq!(lines[0]["code"], "E0001");
+        assert_eq!(lines[0]["span"]["start_line"], 1);
+        assert_eq!(lines[1]["severity"], "warning");
+        assert!(lines[1]["span"].is_null());
+        assert_eq!("json".parse::<ErrorFormat>(), Ok(ErrorFormat::Json));
+        assert!("xml".parse::<ErrorFormat>().is_err());
+    }
+}
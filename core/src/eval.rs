@@ -17,13 +17,18 @@
  */
 
 use crate::ast::{ArkNode, Expression, Pattern, Statement};
+use crate::persistent::PMap;
 use crate::runtime::{RuntimeError, Scope, Value};
 use std::collections::HashSet;
+use std::ops::ControlFlow;
 
 pub struct Interpreter {
     recursion_limit: usize,
     current_depth: usize,
     imported_files: HashSet<String>,
+    /// Failure a `?` is unwinding to the enclosing function boundary with
+    /// (see [`RETURN_SIGNAL`])
+    propagating: Option<Value>,
 }
 
 impl Default for Interpreter {
@@ -38,6 +43,7 @@ impl Interpreter {
             recursion_limit: 500,
             current_depth: 0,
             imported_files: HashSet::new(),
+            propagating: None,
         }
     }
 
@@ -60,9 +66,20 @@ impl Interpreter {
         };
 
         self.current_depth -= 1;
+        self.function_result(result)
+    }
 
+    /// Finish a function body: a `return`, or a `?` failure unwinding out of
+    /// it, becomes its value.
+    fn function_result(
+        &mut self,
+        result: Result<Value, RuntimeError>,
+    ) -> Result<Value, RuntimeError> {
         match result {
             Ok(Value::Return(val)) => Ok(*val),
+            Err(RuntimeError::InvalidOperation(msg)) if msg == RETURN_SIGNAL => {
+                Ok(self.propagating.take().unwrap_or(Value::Unit))
+            }
             other => other,
         }
     }
@@ -74,13 +91,48 @@ impl Interpreter {
     ) -> Result<Value, RuntimeError> {
         match stmt {
             Statement::Let { name, ty: _, value } => {
-                let val = self.eval_expression(value, scope)?;
+                let val = storable(self.eval_expression(value, scope)?)?;
                 scope.set(name.clone(), val);
                 Ok(Value::Unit)
             }
-            Statement::LetDestructure { names, value } => {
-                let result = self.eval_expression(value, scope)?;
+            Statement::LetDestructure {
+                names,
+                value,
+                by_field,
+                rest,
+            } => {
+                let result = storable(self.eval_expression(value, scope)?)?;
                 match result {
+                    Value::Struct(mut data) if *by_field => {
+                        for name in names {
+                            let val = data
+                                .remove(name)
+                                .ok_or_else(|| RuntimeError::VariableNotFound(name.clone()))?;
+                            scope.set(name.clone(), val);
+                        }
+                        if let Some(rest) = rest {
+                            scope.set(rest.clone(), Value::Struct(data));
+                        }
+                        Ok(Value::Unit)
+                    }
+                    _ if *by_field => Err(RuntimeError::TypeMismatch("Struct".to_string(), result)),
+                    Value::List(mut items) if rest.is_some() => {
+                        if items.len() < names.len() {
+                            return Err(RuntimeError::InvalidOperation(format!(
+                                "Destructuring mismatch: expected at least {} items, got {}",
+                                names.len(),
+                                items.len()
+                            )));
+                        }
+                        let tail = items.split_off(names.len());
+                        for (name, val) in names.iter().zip(items) {
+                            scope.set(name.clone(), val);
+                        }
+                        if let Some(rest) = rest {
+                            scope.set(rest.clone(), Value::List(tail));
+                        }
+                        Ok(Value::Unit)
+                    }
                     Value::List(items) => {
                         if items.len() != names.len() {
                             println!(
@@ -119,12 +171,7 @@ impl Interpreter {
                 then_block,
                 else_block,
             } => {
-                let cond_val = self.eval_expression(condition, scope)?;
-                let is_true = match cond_val {
-                    Value::Integer(i) => i != 0,
-                    Value::Boolean(b) => b,
-                    _ => false,
-                };
+                let is_true = self.eval_expression(condition, scope)?.as_condition()?;
 
                 let mut result = Value::Unit;
                 if is_true {
@@ -144,14 +191,13 @@ impl Interpreter {
                 }
                 Ok(result)
             }
-            Statement::While { condition, body } => {
+            Statement::While {
+                condition,
+                body,
+                label,
+            } => {
                 loop {
-                    let cond_val = self.eval_expression(condition, scope)?;
-                    let is_true = match cond_val {
-                        Value::Integer(i) => i != 0,
-                        Value::Boolean(b) => b,
-                        _ => false,
-                    };
+                    let is_true = self.eval_expression(condition, scope)?.as_condition()?;
 
                     if !is_true {
                         break;
@@ -162,10 +208,16 @@ impl Interpreter {
                         match val {
                             Ok(Value::Return(v)) => return Ok(Value::Return(v)),
                             Ok(_) => {}
-                            Err(RuntimeError::InvalidOperation(msg)) if msg == "BREAK" => {
+                            Err(RuntimeError::InvalidOperation(msg))
+                                if targets_loop(&msg, "BREAK", label.as_deref()) =>
+                            {
                                 return Ok(Value::Unit);
                             }
-                            Err(RuntimeError::InvalidOperation(msg)) if msg == "CONTINUE" => break, // Break inner loop to continue while
+                            Err(RuntimeError::InvalidOperation(msg))
+                                if targets_loop(&msg, "CONTINUE", label.as_deref()) =>
+                            {
+                                break; // Break inner loop to continue while
+                            }
                             Err(e) => return Err(e),
                         }
                     }
@@ -176,6 +228,7 @@ impl Interpreter {
                 variable,
                 iterable,
                 body,
+                label,
             } => {
                 let iterable_val = self.eval_expression(iterable, scope)?;
                 let items = match iterable_val {
@@ -191,11 +244,17 @@ impl Interpreter {
                         match result {
                             Ok(Value::Return(v)) => return Ok(Value::Return(v)),
                             Ok(_) => {}
-                            Err(RuntimeError::InvalidOperation(msg)) if msg == "BREAK" => {
+                            Err(RuntimeError::InvalidOperation(msg))
+                                if targets_loop(&msg, "BREAK", label.as_deref()) =>
+                            {
                                 broken = true;
                                 break;
                             }
-                            Err(RuntimeError::InvalidOperation(msg)) if msg == "CONTINUE" => break, // Break inner loop (stmt loop) to continue outer loop (item loop)
+                            Err(RuntimeError::InvalidOperation(msg))
+                                if targets_loop(&msg, "CONTINUE", label.as_deref()) =>
+                            {
+                                break; // Break inner loop (stmt loop) to continue outer loop (item loop)
+                            }
                             Err(e) => return Err(e),
                         }
                     }
@@ -205,8 +264,19 @@ impl Interpreter {
                 }
                 Ok(Value::Unit)
             }
-            Statement::Break => Err(RuntimeError::InvalidOperation("BREAK".to_string())),
-            Statement::Continue => Err(RuntimeError::InvalidOperation("CONTINUE".to_string())),
+            Statement::TryCatch {
+                body,
+                catch_var,
+                handler,
+            } => match self.eval_block(body, scope) {
+                Err(err) if err.is_catchable() && !is_control_signal(&err) => {
+                    scope.set(catch_var.clone(), err.to_value());
+                    self.eval_block(handler, scope)
+                }
+                result => result,
+            },
+            Statement::Break { label } => Err(loop_signal("BREAK", label.as_deref())),
+            Statement::Continue { label } => Err(loop_signal("CONTINUE", label.as_deref())),
             Statement::Import(import_node) => {
                 let path = &import_node.path;
                 // Security Check: No path traversal
@@ -235,8 +305,15 @@ impl Interpreter {
                 obj_name,
                 field,
                 value,
+                obj: target,
             } => {
-                let val = self.eval_expression(value, scope)?;
+                if target.is_some() {
+                    return Err(RuntimeError::InvalidOperation(format!(
+                        "nested field assignment to '{}.{}' is not supported by the interpreter",
+                        obj_name, field
+                    )));
+                }
+                let val = storable(self.eval_expression(value, scope)?)?;
                 let mut obj = scope
                     .take(obj_name)
                     .ok_or_else(|| RuntimeError::VariableNotFound(obj_name.clone()))?;
@@ -254,6 +331,7 @@ impl Interpreter {
                 scope.set(func_def.name.clone(), Value::Unit);
                 Ok(Value::Unit)
             }
+            Statement::Export(inner) => self.eval_statement(inner, scope),
             Statement::StructDecl(_)
             | Statement::EnumDecl(_)
             | Statement::TraitDecl(_)
@@ -264,6 +342,22 @@ impl Interpreter {
         }
     }
 
+    /// Run `stmts` in order, stopping early at a `return`.
+    fn eval_block(
+        &mut self,
+        stmts: &[Statement],
+        scope: &mut Scope,
+    ) -> Result<Value, RuntimeError> {
+        let mut last_val = Value::Unit;
+        for stmt in stmts {
+            last_val = self.eval_statement(stmt, scope)?;
+            if let Value::Return(_) = last_val {
+                break;
+            }
+        }
+        Ok(last_val)
+    }
+
     fn eval_expression(
         &mut self,
         expr: &Expression,
@@ -289,11 +383,25 @@ impl Interpreter {
             Expression::StructInit { fields } => {
                 let mut data = std::collections::HashMap::new();
                 for (name, expr) in fields {
-                    let val = self.eval_expression(expr, scope)?;
+                    let val = storable(self.eval_expression(expr, scope)?)?;
                     data.insert(name.clone(), val);
                 }
                 Ok(Value::Struct(data))
             }
+            Expression::MapInit { entries } => {
+                let mut map = PMap::new();
+                for (key, expr) in entries {
+                    let key = match self.eval_expression(key, scope)? {
+                        Value::String(key) => key,
+                        other => {
+                            return Err(RuntimeError::TypeMismatch("String".to_string(), other));
+                        }
+                    };
+                    let val = storable(self.eval_expression(expr, scope)?)?;
+                    map = map.assoc(key, val);
+                }
+                Ok(Value::PMap(map))
+            }
             Expression::GetField { obj, field } => {
                 let obj_val = self.eval_expression(obj, scope)?;
                 match obj_val {
@@ -419,11 +527,17 @@ impl Interpreter {
             } => {
                 let mut evaluated_args = Vec::new();
                 for arg in args {
-                    evaluated_args.push(self.eval_expression(arg, scope)?);
+                    evaluated_args.push(storable(self.eval_expression(arg, scope)?)?);
                 }
 
+                // Operators desugar to bare names (`x * y` → `mul`)
                 if let Some(native_fn) =
-                    crate::intrinsics::IntrinsicRegistry::resolve(function_hash)
+                    crate::intrinsics::IntrinsicRegistry::resolve(function_hash).or_else(|| {
+                        crate::intrinsics::IntrinsicRegistry::resolve(&format!(
+                            "intrinsic_{}",
+                            function_hash
+                        ))
+                    })
                 {
                     return native_fn(evaluated_args);
                 }
@@ -447,26 +561,22 @@ impl Interpreter {
                     println!("Function '{}' not found in scope.", function_hash);
                 }
 
-                Err(RuntimeError::NotExecutable)
+                Err(RuntimeError::FunctionNotFound(function_hash.clone()))
             }
             Expression::List(items) => {
                 let mut values = Vec::new();
                 for item in items {
-                    values.push(self.eval_expression(item, scope)?);
+                    values.push(storable(self.eval_expression(item, scope)?)?);
                 }
                 Ok(Value::List(values))
             }
             Expression::Integer(i) => Ok(Value::Integer(*i)),
             Expression::Lambda { params: _, body } => {
-                // Evaluate lambda body inline (basic interpreter support)
-                let mut last_val = Value::Unit;
-                for stmt in body {
-                    last_val = self.eval_statement(stmt, scope)?;
-                    if let Value::Return(_) = last_val {
-                        return Ok(last_val);
-                    }
-                }
-                Ok(last_val)
+                // Evaluate lambda body inline (basic interpreter support).
+                // The lambda body is a function boundary: `return` and `?`
+                // stop here.
+                let result = self.eval_block(body, scope);
+                self.function_result(result)
             }
             Expression::EnumInit {
                 enum_name,
@@ -475,7 +585,7 @@ impl Interpreter {
             } => {
                 let mut fields = Vec::new();
                 for arg in args {
-                    fields.push(self.eval_expression(arg, scope)?);
+                    fields.push(storable(self.eval_expression(arg, scope)?)?);
                 }
                 Ok(Value::EnumValue {
                     enum_name: enum_name.clone(),
@@ -483,6 +593,18 @@ impl Interpreter {
                     fields,
                 })
             }
+            Expression::Propagate(inner) => {
+                let val = storable(self.eval_expression(inner, scope)?)?;
+                match val.propagate()? {
+                    ControlFlow::Continue(payload) => Ok(payload),
+                    // Returns the failure from wherever the `?` sits in the
+                    // enclosing expression, as the VM does
+                    ControlFlow::Break(failure) => {
+                        self.propagating = Some(failure);
+                        Err(RuntimeError::InvalidOperation(RETURN_SIGNAL.to_string()))
+                    }
+                }
+            }
         }
     }
 
@@ -537,6 +659,61 @@ impl Interpreter {
     }
 }
 
+/// The error a `break` or `continue` (`kind` "BREAK" or "CONTINUE") raises
+/// to unwind to its loop: `kind` alone, or `kind 'name` when it names one.
+fn loop_signal(kind: &str, label: Option<&str>) -> RuntimeError {
+    RuntimeError::InvalidOperation(match label {
+        Some(label) => format!("{} '{}", kind, label),
+        None => kind.to_string(),
+    })
+}
+
+/// Whether `msg` is a `kind` signal (see [`loop_signal`]) for the loop
+/// labelled `label`: an unlabelled one stops at the innermost loop, a
+/// labelled one unwinds past every loop with another label.
+fn targets_loop(msg: &str, kind: &str, label: Option<&str>) -> bool {
+    match msg.strip_prefix(kind) {
+        Some("") => true,
+        Some(rest) => rest
+            .strip_prefix(" '")
+            .is_some_and(|name| Some(name) == label),
+        None => false,
+    }
+}
+
+/// The error a failing `?` raises to unwind to the enclosing function
+/// boundary, which returns the failure stashed in
+/// `Interpreter::propagating`.
+const RETURN_SIGNAL: &str = "RETURN";
+
+/// Whether `err` is a `break` or `continue` on its way to its loop, or a
+/// `?` failure on its way to its function boundary, which `catch` must let
+/// through.
+fn is_control_signal(err: &RuntimeError) -> bool {
+    let RuntimeError::InvalidOperation(msg) = err else {
+        return false;
+    };
+    msg == RETURN_SIGNAL
+        || ["BREAK", "CONTINUE"]
+            .iter()
+            .any(|kind| match msg.strip_prefix(kind) {
+                Some(rest) => rest.is_empty() || rest.starts_with(" '"),
+                None => false,
+            })
+}
+
+/// `Value::Return` is a control-flow signal between statements and the
+/// enclosing function boundary. It must never be bound to a variable or stored
+/// inside a list, struct, enum, or argument list.
+fn storable(val: Value) -> Result<Value, RuntimeError> {
+    match val {
+        Value::Return(_) => Err(RuntimeError::InvalidOperation(
+            "return value escaped its function boundary".to_string(),
+        )),
+        other => Ok(other),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -588,6 +765,8 @@ mod tests {
         let stmt = Statement::LetDestructure {
             names: vec!["a".to_string(), "b".to_string()],
             value: Expression::List(vec![Expression::Literal("1".to_string())]),
+            by_field: false,
+            rest: None,
         };
 
         let result = interpreter.eval_statement(&stmt, &mut scope);
@@ -606,6 +785,8 @@ mod tests {
                 Expression::Literal("1".to_string()),
                 Expression::Literal("2".to_string()),
             ]),
+            by_field: false,
+            rest: None,
         };
 
         let result = interpreter.eval_statement(&stmt, &mut scope);
@@ -624,6 +805,8 @@ mod tests {
                 Expression::Literal("1".to_string()),
                 Expression::Literal("2".to_string()),
             ]),
+            by_field: false,
+            rest: None,
         };
 
         let result = interpreter.eval_statement(&stmt, &mut scope);
@@ -636,6 +819,62 @@ mod tests {
         assert_eq!(b, Value::Integer(2));
     }
 
+    fn int_list(values: &[i64]) -> Expression {
+        Expression::List(values.iter().map(|v| Expression::Integer(*v)).collect())
+    }
+
+    fn rest_destructure(names: &[&str], rest: &str, value: Expression) -> Statement {
+        Statement::LetDestructure {
+            names: names.iter().map(|n| n.to_string()).collect(),
+            value,
+            by_field: false,
+            rest: Some(rest.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_destructuring_head_and_rest() {
+        let mut scope = Scope::new();
+        let mut interpreter = Interpreter::new();
+
+        // let [head, ...tail] = [1, 2, 3];
+        let stmt = rest_destructure(&["head"], "tail", int_list(&[1, 2, 3]));
+        let result = interpreter.eval_statement(&stmt, &mut scope);
+        assert!(matches!(result, Ok(Value::Unit)));
+
+        let head = scope.get_or_move(&"head".to_string()).expect("head bound");
+        let tail = scope.get_or_move(&"tail".to_string()).expect("tail bound");
+        assert_eq!(head, Value::Integer(1));
+        assert_eq!(tail, Value::List(vec![Value::Integer(2), Value::Integer(3)]));
+    }
+
+    #[test]
+    fn test_destructuring_rest_exact_match_is_empty() {
+        let mut scope = Scope::new();
+        let mut interpreter = Interpreter::new();
+
+        // let [a, b, ...rest] = [1, 2];
+        let stmt = rest_destructure(&["a", "b"], "rest", int_list(&[1, 2]));
+        let result = interpreter.eval_statement(&stmt, &mut scope);
+        assert!(matches!(result, Ok(Value::Unit)));
+
+        let b = scope.get_or_move(&"b".to_string()).expect("b bound");
+        let rest = scope.get_or_move(&"rest".to_string()).expect("rest bound");
+        assert_eq!(b, Value::Integer(2));
+        assert_eq!(rest, Value::List(vec![]));
+    }
+
+    #[test]
+    fn test_destructuring_rest_list_too_short() {
+        let mut scope = Scope::new();
+        let mut interpreter = Interpreter::new();
+
+        // let [a, b, ...rest] = [1];
+        let stmt = rest_destructure(&["a", "b"], "rest", int_list(&[1]));
+        let result = interpreter.eval_statement(&stmt, &mut scope);
+        assert!(matches!(result, Err(RuntimeError::InvalidOperation(_))));
+    }
+
     #[test]
     fn test_match_literal_patterns() {
         let mut scope = Scope::new();
@@ -728,6 +967,7 @@ mod tests {
                     ],
                 },
             }],
+            label: None,
         };
 
         interpreter.eval_statement(&stmt, &mut scope).expect("operation failed");
@@ -759,7 +999,7 @@ mod tests {
                             Expression::Literal("2".to_string()),
                         ],
                     },
-                    then_block: vec![Statement::Break],
+                    then_block: vec![Statement::Break { label: None }],
                     else_block: None,
                 },
                 Statement::Let {
@@ -774,12 +1014,132 @@ mod tests {
                     },
                 },
             ],
+            label: None,
         };
         interpreter.eval_statement(&stmt, &mut scope).expect("operation failed");
         let sum = scope.get("sum").expect("operation failed");
         assert_eq!(sum, Value::Integer(1));
     }
 
+    #[test]
+    fn test_labeled_break_and_continue() {
+        let mut scope = Scope::new();
+        let mut interpreter = Interpreter::new();
+
+        // 'outer: for x in [1, 2, 3] {
+        //     for y in [1, 2, 3] {
+        //         if y == 2 { continue 'outer }
+        //         if x == 3 { break 'outer }
+        //         sum := sum + x * 10 + y
+        //     }
+        // }
+        // Only (1, 1) and (2, 1) are added: sum is 32.
+        scope.set("sum".to_string(), Value::Integer(0));
+        let var = |name: &str| Expression::Variable(name.to_string());
+        let call = |f: &str, args: Vec<Expression>| Expression::Call {
+            function_hash: f.to_string(),
+            args,
+        };
+        let list = || {
+            Expression::List(
+                ["1", "2", "3"]
+                    .map(|n| Expression::Literal(n.to_string()))
+                    .to_vec(),
+            )
+        };
+        let when = |name: &str, value: &str, then: Statement| Statement::If {
+            condition: call(
+                "intrinsic_eq",
+                vec![var(name), Expression::Literal(value.to_string())],
+            ),
+            then_block: vec![then],
+            else_block: None,
+        };
+        let outer = Some("outer".to_string());
+        let stmt = Statement::For {
+            variable: "x".to_string(),
+            iterable: list(),
+            body: vec![Statement::For {
+                variable: "y".to_string(),
+                iterable: list(),
+                body: vec![
+                    when(
+                        "y",
+                        "2",
+                        Statement::Continue {
+                            label: outer.clone(),
+                        },
+                    ),
+                    when(
+                        "x",
+                        "3",
+                        Statement::Break {
+                            label: outer.clone(),
+                        },
+                    ),
+                    Statement::Let {
+                        name: "sum".to_string(),
+                        ty: None,
+                        value: call(
+                            "intrinsic_add",
+                            vec![
+                                call(
+                                    "intrinsic_add",
+                                    vec![
+                                        var("sum"),
+                                        call(
+                                            "intrinsic_mul",
+                                            vec![var("x"), Expression::Literal("10".to_string())],
+                                        ),
+                                    ],
+                                ),
+                                var("y"),
+                            ],
+                        ),
+                    },
+                ],
+                label: None,
+            }],
+            label: outer,
+        };
+        interpreter.eval_statement(&stmt, &mut scope).expect("operation failed");
+        let sum = scope.get("sum").expect("operation failed");
+        assert_eq!(sum, Value::Integer(32));
+    }
+
+    #[test]
+    fn test_map_literal_get_set_keys() {
+        let mut scope = Scope::new();
+        let mut interpreter = Interpreter::new();
+        let lit = |s: &str| Expression::Literal(s.to_string());
+        let call = |f: &str, args: Vec<Expression>| Expression::Call {
+            function_hash: f.to_string(),
+            args,
+        };
+
+        // m := map.set({"b": 2, "a": 1}, "c", 3)
+        let map = Expression::MapInit {
+            entries: vec![(lit("b"), lit("2")), (lit("a"), lit("1"))],
+        };
+        let set = call("map.set", vec![map, lit("c"), lit("3")]);
+        let m = interpreter.eval_expression(&set, &mut scope).expect("operation failed");
+        scope.set("m".to_string(), m);
+
+        let m = || Expression::Variable("m".to_string());
+        let get = call("map.get", vec![m(), lit("a")]);
+        let result = interpreter.eval_expression(&get, &mut scope).expect("operation failed");
+        assert_eq!(result, Value::Integer(1));
+        let missing = call("map.get", vec![m(), lit("z")]);
+        let result = interpreter.eval_expression(&missing, &mut scope).expect("operation failed");
+        assert_eq!(result, Value::Unit);
+        let keys = call("map.keys", vec![m()]);
+        let result = interpreter.eval_expression(&keys, &mut scope).expect("operation failed");
+        assert_eq!(
+            result,
+            Value::List(["a", "b", "c"].map(|k| Value::String(k.to_string())).to_vec())
+        );
+    }
+
     #[test]
     fn test_string_interpolation() {
         let mut scope = Scope::new();
@@ -799,4 +1159,225 @@ mod tests {
         let result = interpreter.eval_expression(&expr, &mut scope);
         assert!(matches!(result, Err(RuntimeError::VariableNotFound(_))));
     }
+
+    #[test]
+    fn test_returned_value_stored_in_list_is_plain() {
+        let mut scope = Scope::new();
+        let mut interpreter = Interpreter::new();
+
+        // results := [func() { return 42 }(), 7]
+        let returns_42 = Expression::Lambda {
+            params: vec![],
+            body: vec![
+                Statement::Return(Expression::Integer(42)),
+                Statement::Expression(Expression::Integer(0)),
+            ],
+        };
+        let stmt = Statement::Let {
+            name: "results".to_string(),
+            ty: None,
+            value: Expression::List(vec![returns_42, Expression::Integer(7)]),
+        };
+        interpreter.eval_statement(&stmt, &mut scope).expect("operation failed");
+
+        let results = scope.get("results").expect("operation failed");
+        assert_eq!(
+            results,
+            Value::List(vec![Value::Integer(42), Value::Integer(7)])
+        );
+    }
+
+    #[test]
+    fn test_string_condition_is_type_error() {
+        let mut scope = Scope::new();
+        let mut interpreter = Interpreter::new();
+
+        // if "false" { ... } — strings are not conditions on any backend
+        let stmt = Statement::If {
+            condition: Expression::Literal("no".to_string()),
+            then_block: vec![Statement::Expression(Expression::Integer(1))],
+            else_block: None,
+        };
+        let err = interpreter
+            .eval_statement(&stmt, &mut scope)
+            .expect_err("string condition accepted");
+        assert!(matches!(err, RuntimeError::TypeMismatch(expected, _) if expected == "Boolean"));
+
+        // `true` still works
+        let stmt = Statement::If {
+            condition: Expression::Literal("true".to_string()),
+            then_block: vec![Statement::Expression(Expression::Integer(1))],
+            else_block: None,
+        };
+        assert_eq!(
+            interpreter.eval_statement(&stmt, &mut scope).expect("if failed"),
+            Value::Integer(1)
+        );
+    }
+
+    #[test]
+    fn test_try_catch_binds_error_and_lets_break_through() {
+        let mut scope = Scope::new();
+        let mut interpreter = Interpreter::new();
+
+        // try { 1 / 0 } catch err { err.code }
+        let stmt = Statement::TryCatch {
+            body: vec![Statement::Expression(Expression::Call {
+                function_hash: "div".to_string(),
+                args: vec![Expression::Integer(1), Expression::Integer(0)],
+            })],
+            catch_var: "err".to_string(),
+            handler: vec![Statement::Expression(Expression::GetField {
+                obj: Box::new(Expression::Variable("err".to_string())),
+                field: "code".to_string(),
+            })],
+        };
+        assert_eq!(
+            interpreter.eval_statement(&stmt, &mut scope).expect("try failed"),
+            Value::String("DivisionByZero".to_string())
+        );
+
+        // while true { try { break } catch err { caught := 1 } }
+        let stmt = Statement::While {
+            condition: Expression::Literal("true".to_string()),
+            body: vec![Statement::TryCatch {
+                body: vec![Statement::Break { label: None }],
+                catch_var: "err".to_string(),
+                handler: vec![Statement::Let {
+                    name: "caught".to_string(),
+                    ty: None,
+                    value: Expression::Integer(1),
+                }],
+            }],
+            label: None,
+        };
+        interpreter.eval_statement(&stmt, &mut scope).expect("loop failed");
+        assert!(scope.get("caught").is_none());
+    }
+
+    #[test]
+    fn test_propagate_returns_failure_from_lambda() {
+        let mut scope = Scope::new();
+        let mut interpreter = Interpreter::new();
+
+        // func() { x := <value>?; return x + 1 }
+        let body = |value: Expression| Expression::Lambda {
+            params: vec![],
+            body: vec![
+                Statement::Let {
+                    name: "x".to_string(),
+                    ty: None,
+                    value: Expression::Propagate(Box::new(value)),
+                },
+                Statement::Return(Expression::Call {
+                    function_hash: "add".to_string(),
+                    args: vec![
+                        Expression::Variable("x".to_string()),
+                        Expression::Integer(1),
+                    ],
+                }),
+            ],
+        };
+        let ok = Expression::EnumInit {
+            enum_name: "Result".to_string(),
+            variant: "Ok".to_string(),
+            args: vec![Expression::Integer(41)],
+        };
+        assert_eq!(
+            interpreter.eval_expression(&body(ok), &mut scope).expect("ok failed"),
+            Value::Integer(42)
+        );
+
+        let err = Expression::EnumInit {
+            enum_name: "Result".to_string(),
+            variant: "Err".to_string(),
+            args: vec![Expression::Literal("bad".to_string())],
+        };
+        assert_eq!(
+            interpreter.eval_expression(&body(err), &mut scope).expect("err failed"),
+            Value::err(Value::String("bad".to_string()))
+        );
+    }
+
+    /// Run `body` as the body of `func f(r)` with `r` bound to `Ok(1)` and
+    /// then `Err("bad")`, on the interpreter (as an inline lambda) and on
+    /// the VM, and return both lists of results.
+    fn propagate_on_both(body: &str) -> (Value, Value) {
+        let source = format!(
+            "func f(r) {{\n    {}\n}}\n[f(Ok(1)), f(Err(\"bad\"))]",
+            body
+        );
+        let ast = crate::parser::parse_source(&source, "test.ark").expect("parse failed");
+        let chunk = crate::compiler::Compiler::new().compile(&ast);
+        let on_vm = crate::vm::VM::new(chunk, "HASH", 0)
+            .expect("vm init failed")
+            .run()
+            .expect("vm run failed");
+
+        let ArkNode::Statement(Statement::Block(stmts)) = &ast else {
+            panic!("Expected Block, got {:?}", ast);
+        };
+        let Some(Statement::Function(func)) = stmts.first() else {
+            panic!("Expected Function, got {:?}", stmts.first());
+        };
+        let ArkNode::Statement(Statement::Block(body)) = &func.body.content else {
+            panic!("Expected Block, got {:?}", func.body.content);
+        };
+        let lambda = Expression::Lambda {
+            params: vec![],
+            body: body.clone(),
+        };
+        let mut interpreter = Interpreter::new();
+        let mut results = Vec::new();
+        for r in [
+            Value::ok(Value::Integer(1)),
+            Value::err(Value::String("bad".to_string())),
+        ] {
+            let mut scope = Scope::new();
+            scope.set("r".to_string(), r);
+            results.push(
+                interpreter
+                    .eval_expression(&lambda, &mut scope)
+                    .expect("eval failed"),
+            );
+        }
+        (Value::List(results), on_vm)
+    }
+
+    #[test]
+    fn test_propagate_inside_expressions_matches_vm() {
+        let bad = || Value::err(Value::String("bad".to_string()));
+        for (body, on_ok) in [
+            // Call argument (`+` desugars to `add`)
+            ("return r? + 1", Value::Integer(2)),
+            // List element
+            (
+                "return [r?, 2]",
+                Value::List(vec![Value::Integer(1), Value::Integer(2)]),
+            ),
+            // Constructor argument
+            ("return Ok(r?)", Value::ok(Value::Integer(1))),
+            // `return` operand
+            ("return r?", Value::Integer(1)),
+        ] {
+            let (on_interpreter, on_vm) = propagate_on_both(body);
+            assert_eq!(on_interpreter, Value::List(vec![on_ok, bad()]), "{}", body);
+            assert_eq!(on_interpreter, on_vm, "{}", body);
+        }
+    }
+
+    #[test]
+    fn test_catch_lets_propagate_through() {
+        let (on_interpreter, on_vm) = propagate_on_both(
+            "try {\n        x := r?\n    } catch e {\n        return 0\n    }\n    return 1",
+        );
+        assert_eq!(
+            on_interpreter,
+            Value::List(vec![
+                Value::Integer(1),
+                Value::err(Value::String("bad".to_string())),
+            ])
+        );
+        assert_eq!(on_interpreter, on_vm);
+    }
 }
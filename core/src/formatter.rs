@@ -0,0 +1,296 @@
+/*
+ * Copyright (c) 2026 Mohamad Al-Zawahreh (dba Sovereign Systems).
+ *
+ * This file is part of the Ark Sovereign Compiler.
+ *
+ * LICENSE: DUAL-LICENSED (AGPLv3 or COMMERCIAL).
+ *
+ * 1. OPEN SOURCE: You may use this file under the terms of the GNU Affero
+ * General Public License v3.0. If you link to this code, your ENTIRE
+ * application must be open-sourced under AGPLv3.
+ *
+ * 2. COMMERCIAL: For proprietary use, you must obtain a Commercial License
+ * from Sovereign Systems.
+ *
+ * PATENT NOTICE: Protected by US Patent App #63/935,467.
+ * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
+ */
+
+//! Canonical source formatting (`ark fmt`).
+//!
+//! The AST does not keep everything a formatter must reproduce (comments,
+//! lambda bodies, the spelling of literals), so the formatter lays out the
+//! token stream instead and uses the AST to prove it changed nothing: the
+//! formatted source must parse to exactly the same tree as the original.
+//!
+//! Line breaks are the author's. Everything else is canonical:
+//!
+//! - four spaces of indentation per open `{`, `(` or `[`
+//! - single spaces around binary operators, `:=` and `=>`, and just
+//!   inside braces; none inside `()` and `[]`, before `,` `:` `.` `?` or
+//!   after unary `-` `!`
+//! - at most one blank line in a row, none just inside a block
+//! - comments kept where they were; trailing whitespace removed
+
+use crate::parser::{self, Comment, Lexer, ParseError, SourceToken, TokenKind};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum FormatError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    /// The formatted source no longer parses to the same program. This is
+    /// a formatter bug; the source is left as it was.
+    #[error("formatting {0} would change its meaning; leaving it unchanged")]
+    Unstable(String),
+}
+
+const INDENT: &str = "    ";
+
+/// A token or comment and where it sits in the source.
+struct Piece {
+    kind: Option<TokenKind>,
+    text: String,
+    line: u32,
+    col: u32,
+    end_line: u32,
+    /// Column just past the last character
+    end_col: u32,
+}
+
+impl Piece {
+    fn new(kind: Option<TokenKind>, text: String, line: u32, col: u32) -> Self {
+        let (end_line, end_col) = match text.rsplit_once('\n') {
+            Some((before, last)) => (
+                line + before.matches('\n').count() as u32 + 1,
+                last.chars().count() as u32 + 1,
+            ),
+            None => (line, col + text.chars().count() as u32),
+        };
+        Piece {
+            kind,
+            text,
+            line,
+            col,
+            end_line,
+            end_col,
+        }
+    }
+
+    fn opens(&self) -> bool {
+        matches!(
+            self.kind,
+            Some(TokenKind::LBrace | TokenKind::LParen | TokenKind::LBracket)
+        )
+    }
+
+    fn closes(&self) -> bool {
+        matches!(
+            self.kind,
+            Some(TokenKind::RBrace | TokenKind::RParen | TokenKind::RBracket)
+        )
+    }
+
+    /// Whether `next` starts exactly where this piece ends.
+    fn touches(&self, next: &Piece) -> bool {
+        self.end_line == next.line && self.end_col == next.col
+    }
+}
+
+/// Whether a token can end an operand, making a following `-` binary.
+fn ends_operand(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Identifier(_)
+            | TokenKind::Integer(_)
+            | TokenKind::Float(_)
+            | TokenKind::Decimal(_)
+            | TokenKind::StringLit(_)
+            | TokenKind::FString(_)
+            | TokenKind::MultiString(_)
+            | TokenKind::True
+            | TokenKind::False
+            | TokenKind::Nil
+            | TokenKind::RParen
+            | TokenKind::RBracket
+            | TokenKind::RBrace
+            | TokenKind::Question
+    )
+}
+
+/// Spacing and generic-bracket state carried along one output line.
+struct Spacer {
+    /// `<` opened as a type argument list and not yet closed
+    generic_depth: usize,
+}
+
+impl Spacer {
+    /// Whether to put a space between `prev` and `next` on the same line;
+    /// `before` is the token ahead of `prev`, if it is on this line.
+    fn space(&mut self, before: Option<&Piece>, prev: &Piece, next: &Piece) -> bool {
+        let (Some(p), Some(n)) = (&prev.kind, &next.kind) else {
+            // Comments are always set off by a space
+            return true;
+        };
+        use TokenKind::*;
+
+        // `List<Int>`, `Map<Str, Int>`: written touching a type name
+        if matches!(n, Lt) && prev.touches(next) {
+            if let Identifier(name) = p {
+                if name.starts_with(|c: char| c.is_ascii_uppercase()) {
+                    self.generic_depth += 1;
+                    return false;
+                }
+            }
+        }
+        if matches!(p, Lt) && self.generic_depth > 0 && prev.touches(next) {
+            return false;
+        }
+        if matches!(n, Gt) && self.generic_depth > 0 {
+            self.generic_depth -= 1;
+            return false;
+        }
+
+        match (p, n) {
+            (LBrace, RBrace) => false,
+            (_, RParen | RBracket | Comma | Semicolon | Colon | Dot | OptChain | Question) => false,
+            (_, DotDot | DotDotEq) | (DotDot | DotDotEq, _) => false,
+            (LParen | LBracket | Dot | OptChain | Ellipsis | Bang | Tilde, _) => false,
+            (Identifier(_) | RParen | RBracket | Func, LParen) => false,
+            (Identifier(_) | RParen | RBracket | StringLit(_), LBracket) => false,
+            (Minus, _) => before
+                .and_then(|b| b.kind.as_ref())
+                .is_some_and(ends_operand),
+            _ => true,
+        }
+    }
+}
+
+/// Lay out `source` canonically. The result is verified to parse to the
+/// same program as `source`.
+pub fn format_source(source: &str, file: &str) -> Result<String, FormatError> {
+    let original = parser::parse_source(source, file)?;
+    let (tokens, comments) = Lexer::new(source).tokenize_with_comments()?;
+    let formatted = layout(tokens, comments);
+    match parser::parse_source(&formatted, file) {
+        Ok(reparsed) if reparsed == original => Ok(formatted),
+        _ => Err(FormatError::Unstable(file.to_string())),
+    }
+}
+
+fn layout(tokens: Vec<SourceToken>, comments: Vec<Comment>) -> String {
+    let mut pieces: Vec<Piece> = tokens
+        .into_iter()
+        .map(|t| Piece::new(Some(t.token.kind), t.text, t.token.line, t.token.col))
+        .chain(
+            comments
+                .into_iter()
+                .map(|c| Piece::new(None, c.text.trim_end().to_string(), c.line, c.col)),
+        )
+        .collect();
+    pieces.sort_by_key(|p| (p.line, p.col));
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut line_indent = 0;
+    // Indentation of the contents of each open bracket
+    let mut open: Vec<usize> = Vec::new();
+    let mut spacer = Spacer { generic_depth: 0 };
+
+    for (i, piece) in pieces.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|j| &pieces[j]);
+        match prev {
+            Some(prev) if piece.line == prev.end_line => {
+                let before = i
+                    .checked_sub(2)
+                    .map(|j| &pieces[j])
+                    .filter(|b| b.end_line == prev.line);
+                if spacer.space(before, prev, piece) {
+                    current.push(' ');
+                }
+            }
+            _ => {
+                if let Some(prev) = prev {
+                    lines.push(current.trim_end().to_string());
+                    let blank = piece.line > prev.end_line + 1;
+                    if blank && !prev.opens() && !piece.closes() {
+                        lines.push(String::new());
+                    }
+                }
+                line_indent = match open.last() {
+                    Some(&inner) if piece.closes() => inner - 1,
+                    Some(&inner) => inner,
+                    None => 0,
+                };
+                current = INDENT.repeat(line_indent);
+                spacer.generic_depth = 0;
+            }
+        }
+        current.push_str(&piece.text);
+
+        if piece.opens() {
+            open.push(line_indent + 1);
+        } else if piece.closes() {
+            open.pop();
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current.trim_end().to_string());
+    }
+    if lines.is_empty() {
+        return String::new();
+    }
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fmt(source: &str) -> String {
+        format_source(source, "test.ark").expect("format failed")
+    }
+
+    #[test]
+    fn test_reindents_and_respaces() {
+        let source = "func  area( w:Int,h :Int )=>Int{\n\t  x:=w*h\n  if x>10 {\n return -x\n      }\nreturn x\n}\n";
+        assert_eq!(
+            fmt(source),
+            "func area(w: Int, h: Int) => Int {\n    x := w * h\n    if x > 10 {\n        return -x\n    }\n    return x\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_keeps_comments_and_collapses_blank_lines() {
+        let source = "// header\n\n\n\nx := 1   // trailing\n/* block */ y := [1,2]\nfunc f() {\n\n    // inside\n    return x\n\n}\n";
+        assert_eq!(
+            fmt(source),
+            "// header\n\nx := 1 // trailing\n/* block */ y := [1, 2]\nfunc f() {\n    // inside\n    return x\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_literals_and_multiline_calls_are_preserved() {
+        let source = "m := {\"k\":  f\"a {b}\",\n\"n\": \"\"\"\n  raw\n\"\"\"}\nlet xs: List<Int> := sys.list.append(\nxs,\n  2.50)\nys := xs[0]\n";
+        assert_eq!(
+            fmt(source),
+            "m := { \"k\": f\"a {b}\",\n    \"n\": \"\"\"\n  raw\n\"\"\" }\nlet xs: List<Int> := sys.list.append(\n    xs,\n    2.50)\nys := xs[0]\n"
+        );
+    }
+
+    #[test]
+    fn test_formatting_is_idempotent() {
+        let source = "func f(a,b){\nreturn a+b}\n'outer: while true {\n  break 'outer\n}\n";
+        let once = fmt(source);
+        assert_eq!(fmt(&once), once);
+        assert!(once.contains("'outer: while true {"));
+    }
+
+    #[test]
+    fn test_invalid_source_is_a_parse_error() {
+        assert!(matches!(
+            format_source("func f( {", "bad.ark"),
+            Err(FormatError::Parse(_))
+        ));
+    }
+}
@@ -7,8 +7,14 @@
  */
 
 use crate::adn;
+use crate::bigint::BigInt;
+use crate::capability::Capabilities;
+use crate::decimal::{Decimal, DecimalError};
 use crate::persistent::{PMap, PVec};
-use crate::runtime::{NativeFn, RuntimeError, Scope, Value};
+use crate::runtime::{
+    NativeFn, NetworkErrorKind, Resource, ResourceLimits, RuntimeError, Scope, Value,
+};
+use crate::tensor::{Tensor, TensorError};
 use regex::Regex;
 #[cfg(not(target_arch = "wasm32"))]
 use reqwest::blocking::Client;
@@ -20,7 +26,8 @@ use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::sync::{Mutex, OnceLock};
+use std::sync::atomic::AtomicUsize;
+use std::sync::{Arc, Condvar, Mutex, OnceLock, RwLock, mpsc};
 use std::thread;
 
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
@@ -33,12 +40,27 @@ use ed25519_dalek::{Signer, SigningKey, Verifier, VerifyingKey};
 use hmac::{Hmac, Mac};
 use pbkdf2::pbkdf2;
 use rand::RngCore;
-use rand::rngs::OsRng;
-use sha2::{Digest, Sha512};
+use rand::SeedableRng;
+use rand::rngs::{OsRng, StdRng};
+use sha2::{Digest, Sha256, Sha512};
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::websocket;
+#[cfg(not(target_arch = "wasm32"))]
+use polling::{Event, Events, Poller};
+#[cfg(not(target_arch = "wasm32"))]
+use rustls::pki_types::{CertificateDer, ServerName};
+#[cfg(not(target_arch = "wasm32"))]
+use rustls::{
+    ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection, StreamOwned,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use std::collections::HashSet;
 #[cfg(not(target_arch = "wasm32"))]
 use std::io::Read;
 #[cfg(not(target_arch = "wasm32"))]
+use std::io::{Seek, SeekFrom};
+#[cfg(not(target_arch = "wasm32"))]
 use std::net::{TcpListener, TcpStream};
 #[cfg(not(target_arch = "wasm32"))]
 use std::sync::atomic::{AtomicI64, Ordering};
@@ -47,11 +69,149 @@ use std::sync::atomic::{AtomicI64, Ordering};
 pub enum SocketResource {
     Listener(TcpListener),
     Stream(TcpStream),
+    /// A listener whose accepted connections complete a TLS handshake.
+    TlsListener(TcpListener, Arc<ServerConfig>),
+    /// Server side of a TLS connection accepted from a `TlsListener`.
+    TlsServerStream(Box<StreamOwned<ServerConnection, TcpStream>>),
+    /// Client side of a TLS connection opened by `net.tls.connect`.
+    TlsClientStream(Box<StreamOwned<ClientConnection, TcpStream>>),
+    /// Certificate chain and key loaded by `net.tls.load_identity`.
+    TlsIdentity(Arc<ServerConfig>),
+    /// A plain or TLS connection that completed a WebSocket handshake.
+    /// `client` connections mask their frames; `closed` is set once a
+    /// close frame has been exchanged.
+    WebSocket {
+        conn: Box<SocketResource>,
+        client: bool,
+        closed: bool,
+    },
+    /// Sockets watched for readability by `net.poll.wait`.
+    Poll(Arc<PollSet>),
+}
+
+/// An OS poller plus the ids of the sockets registered with it. Each
+/// socket is registered with its id as the event key.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct PollSet {
+    poller: Poller,
+    members: Mutex<HashSet<i64>>,
+}
+
+#[cfg(unix)]
+type PollSource<'a> = std::os::fd::BorrowedFd<'a>;
+#[cfg(windows)]
+type PollSource<'a> = std::os::windows::io::BorrowedSocket<'a>;
+
+#[cfg(unix)]
+fn borrow_source<T: std::os::fd::AsFd>(socket: &T) -> PollSource<'_> {
+    socket.as_fd()
+}
+#[cfg(windows)]
+fn borrow_source<T: std::os::windows::io::AsSocket>(socket: &T) -> PollSource<'_> {
+    socket.as_socket()
+}
+
+/// A connection `net.socket.send`/`recv` can use, plain or TLS.
+#[cfg(not(target_arch = "wasm32"))]
+trait SocketStream: Read + Write {}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Read + Write> SocketStream for T {}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SocketResource {
+    /// The byte stream of a plain or TLS connection.
+    fn stream(&mut self) -> Option<&mut dyn SocketStream> {
+        match self {
+            SocketResource::Stream(s) => Some(s),
+            SocketResource::TlsServerStream(s) => Some(s.as_mut()),
+            SocketResource::TlsClientStream(s) => Some(s.as_mut()),
+            _ => None,
+        }
+    }
+
+    /// The TCP socket under a plain or TLS connection.
+    fn tcp(&self) -> Option<&TcpStream> {
+        match self {
+            SocketResource::Stream(s) => Some(s),
+            SocketResource::TlsServerStream(s) => Some(&s.sock),
+            SocketResource::TlsClientStream(s) => Some(&s.sock),
+            SocketResource::WebSocket { conn, .. } => conn.tcp(),
+            _ => None,
+        }
+    }
+
+    /// The OS socket `net.poll` watches: a listener itself, or the TCP
+    /// socket under a connection.
+    fn poll_source(&self) -> Option<PollSource<'_>> {
+        match self {
+            SocketResource::Listener(l) | SocketResource::TlsListener(l, _) => {
+                Some(borrow_source(l))
+            }
+            _ => self.tcp().map(borrow_source),
+        }
+    }
+
+    /// Tell the peer of a TLS or WebSocket connection that no more data
+    /// follows. Best effort: the socket is being dropped either way.
+    fn close_notify(&mut self) {
+        match self {
+            SocketResource::WebSocket {
+                conn,
+                client,
+                closed,
+            } => {
+                if !*closed {
+                    if let Some(stream) = conn.stream() {
+                        let _ = websocket::write_message(
+                            stream,
+                            websocket::OP_CLOSE,
+                            &1000u16.to_be_bytes(),
+                            *client,
+                        );
+                    }
+                }
+                conn.close_notify();
+            }
+            SocketResource::TlsServerStream(s) => {
+                s.conn.send_close_notify();
+                let _ = s.flush();
+            }
+            SocketResource::TlsClientStream(s) => {
+                s.conn.send_close_notify();
+                let _ = s.flush();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl PollSet {
+    fn members(&self) -> Result<std::sync::MutexGuard<'_, HashSet<i64>>, RuntimeError> {
+        self.members
+            .lock()
+            .map_err(|e| RuntimeError::InvalidOperation(format!("poll mutex poisoned: {}", e)))
+    }
+
+    /// Stop watching socket `id`; false if it was not registered.
+    fn remove(&self, id: i64, source: PollSource<'_>) -> Result<bool, RuntimeError> {
+        if !self.members()?.remove(&id) {
+            return Ok(false);
+        }
+        self.poller
+            .delete(source)
+            .map_err(|e| poll_error("net.poll.remove", e))?;
+        Ok(true)
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
 static AI_CLIENT: OnceLock<Client> = OnceLock::new();
 
+#[cfg(not(target_arch = "wasm32"))]
+static HTTP_AGENT: OnceLock<ureq::Agent> = OnceLock::new();
+
 #[cfg(not(target_arch = "wasm32"))]
 static AI_CACHE: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
 
@@ -66,14 +226,224 @@ fn get_sockets() -> &'static Mutex<HashMap<i64, SocketResource>> {
     SOCKETS.get_or_init(|| Mutex::new(HashMap::new()))
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+static PROCESS_ID_COUNTER: AtomicI64 = AtomicI64::new(1);
+
+#[cfg(not(target_arch = "wasm32"))]
+static PROCESSES: OnceLock<Mutex<HashMap<i64, std::process::Child>>> = OnceLock::new();
+
+#[cfg(not(target_arch = "wasm32"))]
+fn get_processes() -> &'static Mutex<HashMap<i64, std::process::Child>> {
+    PROCESSES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// Files opened by sys.fs.open, by handle id
+#[cfg(not(target_arch = "wasm32"))]
+static FILE_ID_COUNTER: AtomicI64 = AtomicI64::new(1);
+
+#[cfg(not(target_arch = "wasm32"))]
+static FILES: OnceLock<Mutex<HashMap<i64, fs::File>>> = OnceLock::new();
+
+#[cfg(not(target_arch = "wasm32"))]
+fn get_files() -> &'static Mutex<HashMap<i64, fs::File>> {
+    FILES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// Routes registered with net.http.route, in registration order
+#[cfg(not(target_arch = "wasm32"))]
+struct HttpRoute {
+    method: String,
+    path: String,
+    handler: Value,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+static HTTP_ROUTES: OnceLock<Mutex<Vec<HttpRoute>>> = OnceLock::new();
+
+#[cfg(not(target_arch = "wasm32"))]
+fn get_http_routes() -> &'static Mutex<Vec<HttpRoute>> {
+    HTTP_ROUTES.get_or_init(|| Mutex::new(Vec::new()))
+}
+
 // Threading & Events Globals
-static THREADS: OnceLock<Mutex<HashMap<i64, thread::JoinHandle<()>>>> = OnceLock::new();
+type ThreadHandle = thread::JoinHandle<Result<Value, RuntimeError>>;
+static THREADS: OnceLock<Mutex<HashMap<i64, ThreadHandle>>> = OnceLock::new();
 static EVENTS: OnceLock<Mutex<VecDeque<Value>>> = OnceLock::new();
 static NEXT_THREAD_ID: OnceLock<Mutex<i64>> = OnceLock::new();
 
+/// A `sys.chan.new` channel. `sender` is dropped by `sys.chan.close`; the
+/// receiver is shared so a blocking receive need not hold the registry.
+struct Channel {
+    sender: Option<mpsc::SyncSender<Value>>,
+    receiver: Arc<Mutex<mpsc::Receiver<Value>>>,
+}
+
+static CHANNEL_ID_COUNTER: std::sync::atomic::AtomicI64 = std::sync::atomic::AtomicI64::new(1);
+static CHANNELS: OnceLock<Mutex<HashMap<i64, Channel>>> = OnceLock::new();
+
+fn get_channels() -> &'static Mutex<HashMap<i64, Channel>> {
+    CHANNELS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// A `sys.sync.*` primitive. Locks stay held across intrinsic calls, so
+/// they track their holders by thread instead of handing out guards.
+#[derive(Clone)]
+enum SyncObject {
+    Mutex(Arc<SyncLock>),
+    RwLock(Arc<SyncLock>),
+    Atomic(Arc<std::sync::atomic::AtomicI64>),
+}
+
+/// A mutex (exclusive holds only) or readers-writer lock guarding a value.
+struct SyncLock {
+    state: Mutex<LockState>,
+    released: Condvar,
+}
+
+struct LockState {
+    writer: Option<thread::ThreadId>,
+    readers: Vec<thread::ThreadId>,
+    value: Value,
+}
+
+static SYNC_ID_COUNTER: std::sync::atomic::AtomicI64 = std::sync::atomic::AtomicI64::new(1);
+static SYNC_OBJECTS: OnceLock<Mutex<HashMap<i64, SyncObject>>> = OnceLock::new();
+
+fn get_sync_objects() -> &'static Mutex<HashMap<i64, SyncObject>> {
+    SYNC_OBJECTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// A `sys.timer.after`/`every` timer. `interval` is set for repeating
+/// timers, which are rescheduled each time they fire.
+#[cfg(not(target_arch = "wasm32"))]
+struct Timer {
+    due: std::time::Instant,
+    interval: Option<Duration>,
+    handler: Value,
+}
+
+/// Pending timers, watched by one background thread that queues a
+/// `[handler, []]` event as each fires. `changed` wakes it when a timer
+/// is added.
+#[cfg(not(target_arch = "wasm32"))]
+struct TimerQueue {
+    timers: Mutex<HashMap<i64, Timer>>,
+    changed: Condvar,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+static TIMER_ID_COUNTER: AtomicI64 = AtomicI64::new(1);
+#[cfg(not(target_arch = "wasm32"))]
+static TIMERS: OnceLock<Arc<TimerQueue>> = OnceLock::new();
+
+#[cfg(not(target_arch = "wasm32"))]
+fn get_timers() -> &'static Arc<TimerQueue> {
+    TIMERS.get_or_init(|| {
+        let queue = Arc::new(TimerQueue {
+            timers: Mutex::new(HashMap::new()),
+            changed: Condvar::new(),
+        });
+        let worker = queue.clone();
+        thread::spawn(move || run_timers(&worker));
+        queue
+    })
+}
+
+// Program arguments reported by sys.args (set by the loader)
+static SCRIPT_ARGS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Set the arguments `sys.args` reports, program path first as in `argv`.
+/// Only the first call takes effect; until then the process's own
+/// arguments are used.
+pub fn set_script_args(args: Vec<String>) {
+    let _ = SCRIPT_ARGS.set(args);
+}
+
+fn script_args() -> Vec<String> {
+    SCRIPT_ARGS
+        .get()
+        .cloned()
+        .unwrap_or_else(|| std::env::args().collect())
+}
+
+thread_local! {
+    /// Whether a deterministic run is in progress, set by
+    /// `set_deterministic`.
+    static DETERMINISTIC: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// Make intrinsics the program names at run time refuse the ones a
+/// deterministic VM cannot run, or allow them again with `false`.
+pub fn set_deterministic(on: bool) {
+    DETERMINISTIC.with(|flag| flag.set(on));
+}
+
 pub struct IntrinsicRegistry;
 
 impl IntrinsicRegistry {
+    /// Whether `name` is an intrinsic whose result depends on more than its
+    /// arguments (the network, the file system, other threads, the host)
+    /// and has no seeded stand-in, so a deterministic VM refuses it. The
+    /// clock and `sys.random.*` are not listed: they are replaced instead.
+    pub fn is_nondeterministic(name: &str) -> bool {
+        const PREFIXES: &[&str] = &[
+            "net.",
+            "sys.net.",
+            "intrinsic_http_",
+            "intrinsic_socket_",
+            "intrinsic_poll_",
+            "intrinsic_tls_",
+            "intrinsic_ws_",
+            "sys.thread.",
+            "sys.task.",
+            "sys.timer.",
+            "sys.event.",
+            "sys.chan.",
+            "sys.sync.",
+            "sys.proc.",
+            "sys.process.",
+            "intrinsic_proc",
+            "sys.exec",
+            "sys_exec",
+            "intrinsic_exec",
+            "sys.fs.",
+            "sys_fs_",
+            "intrinsic_fs_",
+            "sys.io.read",
+            "intrinsic_io_read",
+            "sys.ai.",
+            "ai.",
+            "intrinsic_ask_ai",
+            "sys.env.",
+            "intrinsic_env_",
+            "sys.resource.",
+            "intrinsic_resource_",
+            "sys.info",
+            "intrinsic_sys_info",
+            "sys.wasm.",
+            "wasm.",
+            "intrinsic_wasm_",
+            "sys.vm.eval",
+            "sys.z3.",
+            "intrinsic_z3_",
+            // Key generation always draws from the OS
+            "sys.crypto.ed25519_generate",
+            "sys.crypto.ed25519.gen",
+            "intrinsic_crypto_ed25519_generate",
+        ];
+        PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+    }
+
+    /// [`resolve`](Self::resolve) for a name the program computed, which
+    /// the VM's scan before a deterministic run cannot see: fails with
+    /// `NonDeterministic` if such a run is in progress and may not call it.
+    pub fn dispatch(name: &str) -> Result<Option<NativeFn>, RuntimeError> {
+        if DETERMINISTIC.with(|flag| flag.get()) && Self::is_nondeterministic(name) {
+            return Err(RuntimeError::NonDeterministic(name.to_string()));
+        }
+        Ok(Self::resolve(name))
+    }
+
     pub fn resolve(hash: &str) -> Option<NativeFn> {
         match hash {
             "intrinsic_add" => Some(intrinsic_add),
@@ -97,15 +467,47 @@ impl IntrinsicRegistry {
             "get_item" => Some(core_get),
             "intrinsic_ask_ai" | "sys.ai.ask" | "ai.ask" => Some(intrinsic_ask_ai),
             "sys_exec" | "intrinsic_exec" => Some(intrinsic_exec),
+            "intrinsic_process_spawn" | "sys.process.spawn" => Some(intrinsic_process_spawn),
+            "intrinsic_process_write_stdin" | "sys.process.write_stdin" => {
+                Some(intrinsic_process_write_stdin)
+            }
+            "intrinsic_process_read_stdout" | "sys.process.read_stdout" => {
+                Some(intrinsic_process_read_stdout)
+            }
+            "intrinsic_process_wait" | "sys.process.wait" => Some(intrinsic_process_wait),
+            "intrinsic_proc_spawn" | "sys.proc.spawn" => Some(intrinsic_proc_spawn),
+            "sys.proc.stdin_write" => Some(intrinsic_process_write_stdin),
+            "sys.proc.stdout_read" => Some(intrinsic_process_read_stdout),
+            "intrinsic_proc_stderr_read" | "sys.proc.stderr_read" => {
+                Some(intrinsic_proc_stderr_read)
+            }
+            "intrinsic_proc_wait" | "sys.proc.wait" => Some(intrinsic_proc_wait),
+            "intrinsic_proc_kill" | "sys.proc.kill" => Some(intrinsic_proc_kill),
             "sys_fs_write" | "intrinsic_fs_write" | "sys.fs.write" => Some(intrinsic_fs_write),
             "sys_fs_read" | "intrinsic_fs_read" | "sys.fs.read" => Some(intrinsic_fs_read),
+            "sys.fs.read_json" => Some(intrinsic_fs_read_json),
+            "sys.fs.open" | "intrinsic_fs_open" => Some(intrinsic_fs_open),
+            "sys.fs.read_chunk" | "intrinsic_fs_read_chunk" => Some(intrinsic_fs_read_chunk),
+            "sys.fs.seek" | "intrinsic_fs_seek" => Some(intrinsic_fs_seek),
+            "sys.fs.close" | "intrinsic_fs_close" => Some(intrinsic_fs_close),
+            "sys.fs.write_json" => Some(intrinsic_fs_write_json),
+            "sys.fs.try_read" => Some(intrinsic_fs_try_read),
+            "sys.fs.try_write" => Some(intrinsic_fs_try_write),
             "intrinsic_crypto_hash" | "sys.crypto.hash" => Some(intrinsic_crypto_hash),
             "intrinsic_crypto_verify" | "sys.crypto.verify" => Some(intrinsic_crypto_verify),
             "intrinsic_crypto_sha512" | "sys.crypto.sha512" => Some(intrinsic_crypto_sha512),
+            "intrinsic_crypto_sha256" | "sys.crypto.sha256" => Some(intrinsic_crypto_sha256),
+            "intrinsic_crypto_keccak256" | "sys.crypto.keccak256" => {
+                Some(intrinsic_crypto_keccak256)
+            }
+            "intrinsic_crypto_blake3" | "sys.crypto.blake3" => Some(intrinsic_crypto_blake3),
             "intrinsic_crypto_hmac_sha512" | "sys.crypto.hmac_sha512" => {
                 Some(intrinsic_crypto_hmac_sha512)
             }
             "intrinsic_crypto_pbkdf2" | "sys.crypto.pbkdf2" => Some(intrinsic_crypto_pbkdf2),
+            "intrinsic_crypto_constant_time_eq" | "sys.crypto.constant_time_eq" => {
+                Some(intrinsic_crypto_constant_time_eq)
+            }
             "intrinsic_crypto_aes_gcm_encrypt" | "sys.crypto.aes_gcm_encrypt" => {
                 Some(intrinsic_crypto_aes_gcm_encrypt)
             }
@@ -115,6 +517,19 @@ impl IntrinsicRegistry {
             "intrinsic_crypto_random_bytes" | "sys.crypto.random_bytes" => {
                 Some(intrinsic_crypto_random_bytes)
             }
+            "sys.random.int" => Some(intrinsic_random_int),
+            "sys.random.float" => Some(intrinsic_random_float),
+            "sys.random.choice" => Some(intrinsic_random_choice),
+            "intrinsic_uuid_v4" | "sys.uuid.v4" => Some(intrinsic_uuid_v4),
+            "intrinsic_uuid_parse" | "sys.uuid.parse" => Some(intrinsic_uuid_parse),
+            "intrinsic_encode_base58" | "sys.encode.base58" => Some(intrinsic_encode_base58),
+            "intrinsic_decode_base58" | "sys.decode.base58" => Some(intrinsic_decode_base58),
+            "intrinsic_encode_base58check" | "sys.encode.base58check" => {
+                Some(intrinsic_encode_base58check)
+            }
+            "intrinsic_decode_base58check" | "sys.decode.base58check" => {
+                Some(intrinsic_decode_base58check)
+            }
             "intrinsic_crypto_ed25519_generate"
             | "sys.crypto.ed25519_generate"
             | "sys.crypto.ed25519.gen" => Some(intrinsic_crypto_ed25519_generate),
@@ -130,11 +545,17 @@ impl IntrinsicRegistry {
             "intrinsic_buffer_inspect" | "sys.mem.inspect" => Some(intrinsic_buffer_inspect),
             "intrinsic_buffer_read" | "sys.mem.read" => Some(intrinsic_buffer_read),
             "intrinsic_buffer_write" | "sys.mem.write" => Some(intrinsic_buffer_write),
+            "intrinsic_buffer_view" | "sys.mem.view" => Some(intrinsic_buffer_view),
             "intrinsic_list_get" | "sys.list.get" | "sys.str.get" => Some(intrinsic_list_get),
             "intrinsic_list_append" | "sys.list.append" => Some(intrinsic_list_append),
             "intrinsic_list_pop" | "sys.list.pop" => Some(intrinsic_list_pop),
             "intrinsic_list_delete" | "sys.list.delete" => Some(intrinsic_list_delete),
+            "intrinsic_list_zip" | "sys.list.zip" => Some(intrinsic_list_zip),
+            "intrinsic_list_enumerate" | "sys.list.enumerate" => Some(intrinsic_list_enumerate),
+            "intrinsic_list_chunk" | "sys.list.chunk" => Some(intrinsic_list_chunk),
+            "intrinsic_list_window" | "sys.list.window" => Some(intrinsic_list_window),
             "intrinsic_len" | "sys.len" => Some(intrinsic_len),
+            "intrinsic_type_of" | "sys.type_of" => Some(intrinsic_type_of),
             "intrinsic_struct_get" | "sys.struct.get" => Some(intrinsic_struct_get),
             "intrinsic_struct_set" | "sys.struct.set" => Some(intrinsic_struct_set),
             "intrinsic_struct_has" | "sys.struct.has" => Some(intrinsic_struct_has),
@@ -152,6 +573,13 @@ impl IntrinsicRegistry {
             "intrinsic_math_ln" | "math.ln" => Some(intrinsic_math_ln),
             "intrinsic_math_exp" | "math.exp" => Some(intrinsic_math_exp),
             "intrinsic_math_abs" | "math.abs" => Some(intrinsic_math_abs),
+            "intrinsic_math_round" | "math.round" | "sys.math.round" => Some(intrinsic_math_round),
+            "intrinsic_math_floor" | "math.floor" | "sys.math.floor" => Some(intrinsic_math_floor),
+            "intrinsic_math_ceil" | "math.ceil" | "sys.math.ceil" => Some(intrinsic_math_ceil),
+            "intrinsic_math_trunc" | "math.trunc" | "sys.math.trunc" => Some(intrinsic_math_trunc),
+            "intrinsic_math_round_to" | "math.round_to" | "sys.math.round_to" => {
+                Some(intrinsic_math_round_to)
+            }
             "intrinsic_gcd_normalize" | "gcd.normalize" => Some(intrinsic_gcd_normalize),
             "intrinsic_io_cls" | "io.cls" => Some(intrinsic_io_cls),
             "intrinsic_list_set" | "sys.list.set" => Some(intrinsic_list_set),
@@ -167,6 +595,13 @@ impl IntrinsicRegistry {
             "math.cos_scaled" => Some(intrinsic_math_cos_scaled),
             "math.pi_scaled" => Some(intrinsic_math_pi_scaled),
             "sys.str.from_code" => Some(intrinsic_str_from_code),
+            "string_char_len" | "intrinsic_string_char_len" => Some(intrinsic_string_char_len),
+            "string_char_at" | "intrinsic_string_char_at" => Some(intrinsic_string_char_at),
+            "string_slice_chars" | "intrinsic_string_slice_chars" => {
+                Some(intrinsic_string_slice_chars)
+            }
+            "string_to_upper" | "intrinsic_string_to_upper" => Some(intrinsic_string_to_upper),
+            "string_to_lower" | "intrinsic_string_to_lower" => Some(intrinsic_string_to_lower),
             "sys.time.sleep" | "intrinsic_time_sleep" => Some(intrinsic_time_sleep),
             "sys.io.read_bytes" | "intrinsic_io_read_bytes" => Some(intrinsic_io_read_bytes),
             "sys.io.read_line" | "intrinsic_io_read_line" => Some(intrinsic_io_read_line),
@@ -177,12 +612,22 @@ impl IntrinsicRegistry {
             "sys.extract_code" | "intrinsic_extract_code" => Some(intrinsic_extract_code),
             "sys.resource.usage" | "intrinsic_resource_usage" => Some(intrinsic_resource_usage),
             // Networking Intrinsics
+            "net.http.try_request" | "sys.net.http.try_request" => Some(intrinsic_http_try_request),
             "net.http.request" | "intrinsic_http_request" | "sys.net.http.request" => {
                 Some(intrinsic_http_request)
             }
             "net.http.serve" | "intrinsic_http_serve" | "sys.net.http.serve" => {
                 Some(intrinsic_http_serve)
             }
+            "net.http.route" | "intrinsic_http_route" | "sys.net.http.route" => {
+                Some(intrinsic_http_route)
+            }
+            "net.http.listen" | "intrinsic_http_listen" | "sys.net.http.listen" => {
+                Some(intrinsic_http_listen)
+            }
+            "net.http.listen_tls" | "intrinsic_http_listen_tls" | "sys.net.http.listen_tls" => {
+                Some(intrinsic_http_listen_tls)
+            }
             "net.socket.bind" | "intrinsic_socket_bind" | "sys.net.socket.bind" => {
                 Some(intrinsic_socket_bind)
             }
@@ -204,18 +649,85 @@ impl IntrinsicRegistry {
             "net.socket.set_timeout"
             | "intrinsic_socket_set_timeout"
             | "sys.net.socket.set_timeout" => Some(intrinsic_socket_set_timeout),
+            "net.socket.set_nonblocking"
+            | "intrinsic_socket_set_nonblocking"
+            | "sys.net.socket.set_nonblocking" => Some(intrinsic_socket_set_nonblocking),
+            "net.poll.create" | "intrinsic_poll_create" | "sys.net.poll.create" => {
+                Some(intrinsic_poll_create)
+            }
+            "net.poll.add" | "intrinsic_poll_add" | "sys.net.poll.add" => Some(intrinsic_poll_add),
+            "net.poll.remove" | "intrinsic_poll_remove" | "sys.net.poll.remove" => {
+                Some(intrinsic_poll_remove)
+            }
+            "net.poll.wait" | "intrinsic_poll_wait" | "sys.net.poll.wait" => {
+                Some(intrinsic_poll_wait)
+            }
+            "net.tls.load_identity"
+            | "intrinsic_tls_load_identity"
+            | "sys.net.tls.load_identity" => Some(intrinsic_tls_load_identity),
+            "net.tls.wrap_listener"
+            | "intrinsic_tls_wrap_listener"
+            | "sys.net.tls.wrap_listener" => Some(intrinsic_tls_wrap_listener),
+            "net.tls.connect" | "intrinsic_tls_connect" | "sys.net.tls.connect" => {
+                Some(intrinsic_tls_connect)
+            }
+            "net.ws.connect" | "intrinsic_ws_connect" | "sys.net.ws.connect" => {
+                Some(intrinsic_ws_connect)
+            }
+            "net.ws.accept" | "intrinsic_ws_accept" | "sys.net.ws.accept" => {
+                Some(intrinsic_ws_accept)
+            }
+            "net.ws.send" | "intrinsic_ws_send" | "sys.net.ws.send" => Some(intrinsic_ws_send),
+            "net.ws.recv" | "intrinsic_ws_recv" | "sys.net.ws.recv" => Some(intrinsic_ws_recv),
+            // Sends the close frame, then drops the socket
+            "net.ws.close" | "sys.net.ws.close" => Some(intrinsic_socket_close),
             // Advanced Runtime
             "sys.thread.spawn" => Some(intrinsic_thread_spawn),
             "sys.thread.join" => Some(intrinsic_thread_join),
             "sys.event.poll" => Some(intrinsic_event_poll),
             "sys.event.push" => Some(intrinsic_event_push),
+            "sys.timer.after" => Some(intrinsic_timer_after),
+            "sys.timer.every" => Some(intrinsic_timer_every),
+            "sys.timer.cancel" => Some(intrinsic_timer_cancel),
+            "sys.chan.new" => Some(intrinsic_chan_new),
+            "sys.chan.send" => Some(intrinsic_chan_send),
+            "sys.chan.recv" => Some(intrinsic_chan_recv),
+            "sys.chan.try_recv" => Some(intrinsic_chan_try_recv),
+            "sys.chan.close" => Some(intrinsic_chan_close),
+            "sys.task.spawn" => Some(intrinsic_task_spawn),
+            "sys.task.yield" => Some(intrinsic_task_yield),
+            "sys.task.sleep" => Some(intrinsic_task_sleep),
+            "sys.task.recv" => Some(intrinsic_task_recv),
+            "sys.task.accept" => Some(intrinsic_task_accept),
+            "sys.error.trace" => Some(intrinsic_error_trace),
+            "sys.sync.mutex_new" => Some(intrinsic_sync_mutex_new),
+            "sys.sync.mutex_lock" => Some(intrinsic_sync_mutex_lock),
+            "sys.sync.mutex_unlock" => Some(intrinsic_sync_mutex_unlock),
+            "sys.sync.rwlock_new" => Some(intrinsic_sync_rwlock_new),
+            "sys.sync.rwlock_read" => Some(intrinsic_sync_rwlock_read),
+            "sys.sync.rwlock_write" => Some(intrinsic_sync_rwlock_write),
+            "sys.sync.rwlock_unlock" => Some(intrinsic_sync_rwlock_unlock),
+            "sys.sync.atomic_new" => Some(intrinsic_sync_atomic_new),
+            "sys.sync.atomic_add" => Some(intrinsic_sync_atomic_add),
+            "sys.sync.atomic_load" => Some(intrinsic_sync_atomic_load),
+            "sys.sync.atomic_store" => Some(intrinsic_sync_atomic_store),
             "sys.func.apply" => Some(intrinsic_func_apply),
+            "intrinsic_assert_throws" | "sys.assert_throws" => Some(intrinsic_assert_throws),
+            "assert" => Some(intrinsic_assert),
+            "assert_eq" => Some(intrinsic_assert_eq),
+            "assert_err" => Some(intrinsic_assert_err),
+            "intrinsic_bench_black_box" | "sys.bench.black_box" => Some(intrinsic_bench_black_box),
+            "intrinsic_debug_break" | "sys.debug.break" => Some(intrinsic_debug_break),
             "sys.vm.eval" => Some(intrinsic_vm_eval),
             // Phase 78: Final 12 Parity Intrinsics
             "sys.json.parse" | "intrinsic_json_parse" => Some(intrinsic_json_parse),
             "sys.json.stringify" | "intrinsic_json_stringify" => Some(intrinsic_json_stringify),
+            "sys.json.try_parse" => Some(intrinsic_json_try_parse),
             "sys.log" | "intrinsic_log" => Some(intrinsic_log),
             "sys.exit" | "exit" | "quit" | "intrinsic_exit" => Some(intrinsic_exit),
+            "sys.args.count" | "intrinsic_args_count" => Some(intrinsic_args_count),
+            "sys.args.get" | "intrinsic_args_get" => Some(intrinsic_args_get),
+            "sys.env.get" | "intrinsic_env_get" => Some(intrinsic_env_get),
             "sys.html_escape" | "intrinsic_html_escape" => Some(intrinsic_html_escape),
             "sys.z3.verify" | "intrinsic_z3_verify" => Some(intrinsic_z3_verify),
             "sys.vm.source" | "intrinsic_vm_source" => Some(intrinsic_vm_source),
@@ -242,6 +754,52 @@ impl IntrinsicRegistry {
             "pmap.get" | "sys.pmap.get" | "intrinsic_pmap_get" => Some(intrinsic_pmap_get),
             "pmap.keys" | "sys.pmap.keys" | "intrinsic_pmap_keys" => Some(intrinsic_pmap_keys),
             "pmap.merge" | "sys.pmap.merge" | "intrinsic_pmap_merge" => Some(intrinsic_pmap_merge),
+            // Big integer intrinsics
+            "bigint.from_string" | "sys.bigint.from_string" | "intrinsic_bigint_from_string" => {
+                Some(intrinsic_bigint_from_string)
+            }
+            "bigint.add" | "sys.bigint.add" | "intrinsic_bigint_add" => Some(intrinsic_bigint_add),
+            "bigint.mul" | "sys.bigint.mul" | "intrinsic_bigint_mul" => Some(intrinsic_bigint_mul),
+            "bigint.mod" | "sys.bigint.mod" | "intrinsic_bigint_mod" => Some(intrinsic_bigint_mod),
+            "bigint.pow_mod" | "sys.bigint.pow_mod" | "intrinsic_bigint_pow_mod" => {
+                Some(intrinsic_bigint_pow_mod)
+            }
+            "bigint.cmp" | "sys.bigint.cmp" | "intrinsic_bigint_cmp" => Some(intrinsic_bigint_cmp),
+            "bigint.to_string" | "sys.bigint.to_string" | "intrinsic_bigint_to_string" => {
+                Some(intrinsic_bigint_to_string)
+            }
+            // Fixed-point decimal intrinsics; `12.50d` is dec.from_string("12.50")
+            "dec.from_string" | "sys.dec.from_string" | "intrinsic_dec_from_string" => {
+                Some(intrinsic_dec_from_string)
+            }
+            "dec.add" | "sys.dec.add" | "intrinsic_dec_add" => Some(intrinsic_dec_add),
+            "dec.sub" | "sys.dec.sub" | "intrinsic_dec_sub" => Some(intrinsic_dec_sub),
+            "dec.mul" | "sys.dec.mul" | "intrinsic_dec_mul" => Some(intrinsic_dec_mul),
+            "dec.div" | "sys.dec.div" | "intrinsic_dec_div" => Some(intrinsic_dec_div),
+            "dec.round" | "sys.dec.round" | "intrinsic_dec_round" => Some(intrinsic_dec_round),
+            // Strided tensors with broadcasting arithmetic
+            "tensor.new" | "sys.tensor.new" | "intrinsic_tensor_new" => Some(intrinsic_tensor_new),
+            "tensor.shape" | "sys.tensor.shape" | "intrinsic_tensor_shape" => {
+                Some(intrinsic_tensor_shape)
+            }
+            "tensor.data" | "sys.tensor.data" | "intrinsic_tensor_data" => {
+                Some(intrinsic_tensor_data)
+            }
+            "tensor.get" | "sys.tensor.get" | "intrinsic_tensor_get" => Some(intrinsic_tensor_get),
+            "tensor.add" | "sys.tensor.add" | "intrinsic_tensor_add" => Some(intrinsic_tensor_add),
+            "tensor.sub" | "sys.tensor.sub" | "intrinsic_tensor_sub" => Some(intrinsic_tensor_sub),
+            "tensor.mul" | "sys.tensor.mul" | "intrinsic_tensor_mul" => Some(intrinsic_tensor_mul),
+            "tensor.reshape" | "sys.tensor.reshape" | "intrinsic_tensor_reshape" => {
+                Some(intrinsic_tensor_reshape)
+            }
+            "tensor.slice" | "sys.tensor.slice" | "intrinsic_tensor_slice" => {
+                Some(intrinsic_tensor_slice)
+            }
+            "tensor.sum" | "sys.tensor.sum" | "intrinsic_tensor_sum" => Some(intrinsic_tensor_sum),
+            // Map literal intrinsics: `{ "k": v }` evaluates to a PMap
+            "map.get" | "intrinsic_map_get" => Some(intrinsic_pmap_get),
+            "map.set" | "intrinsic_map_set" => Some(intrinsic_pmap_assoc),
+            "map.keys" | "intrinsic_map_keys" => Some(intrinsic_pmap_keys),
             // ADN Data Notation Intrinsics
             "data.to_adn" | "sys.data.to_adn" | "intrinsic_data_to_adn" => {
                 Some(intrinsic_data_to_adn)
@@ -353,10 +911,54 @@ impl IntrinsicRegistry {
         scope.set("get".to_string(), Value::NativeFunction(core_get));
         scope.set("get_item".to_string(), Value::NativeFunction(core_get));
         scope.set("sys.len".to_string(), Value::NativeFunction(intrinsic_len));
+        scope.set(
+            "sys.type_of".to_string(),
+            Value::NativeFunction(intrinsic_type_of),
+        );
         scope.set(
             "sys.exec".to_string(),
             Value::NativeFunction(intrinsic_exec),
         );
+        scope.set(
+            "sys.process.spawn".to_string(),
+            Value::NativeFunction(intrinsic_process_spawn),
+        );
+        scope.set(
+            "sys.process.write_stdin".to_string(),
+            Value::NativeFunction(intrinsic_process_write_stdin),
+        );
+        scope.set(
+            "sys.process.read_stdout".to_string(),
+            Value::NativeFunction(intrinsic_process_read_stdout),
+        );
+        scope.set(
+            "sys.process.wait".to_string(),
+            Value::NativeFunction(intrinsic_process_wait),
+        );
+        scope.set(
+            "sys.proc.spawn".to_string(),
+            Value::NativeFunction(intrinsic_proc_spawn),
+        );
+        scope.set(
+            "sys.proc.stdin_write".to_string(),
+            Value::NativeFunction(intrinsic_process_write_stdin),
+        );
+        scope.set(
+            "sys.proc.stdout_read".to_string(),
+            Value::NativeFunction(intrinsic_process_read_stdout),
+        );
+        scope.set(
+            "sys.proc.stderr_read".to_string(),
+            Value::NativeFunction(intrinsic_proc_stderr_read),
+        );
+        scope.set(
+            "sys.proc.wait".to_string(),
+            Value::NativeFunction(intrinsic_proc_wait),
+        );
+        scope.set(
+            "sys.proc.kill".to_string(),
+            Value::NativeFunction(intrinsic_proc_kill),
+        );
         scope.set(
             "sys.fs.write".to_string(),
             Value::NativeFunction(intrinsic_fs_write),
@@ -365,6 +967,58 @@ impl IntrinsicRegistry {
             "sys.fs.read".to_string(),
             Value::NativeFunction(intrinsic_fs_read),
         );
+        scope.set(
+            "sys.fs.read_json".to_string(),
+            Value::NativeFunction(intrinsic_fs_read_json),
+        );
+        scope.set(
+            "sys.fs.try_read".to_string(),
+            Value::NativeFunction(intrinsic_fs_try_read),
+        );
+        scope.set(
+            "sys.fs.try_write".to_string(),
+            Value::NativeFunction(intrinsic_fs_try_write),
+        );
+        scope.set(
+            "sys.fs.open".to_string(),
+            Value::NativeFunction(intrinsic_fs_open),
+        );
+        scope.set(
+            "sys.fs.read_chunk".to_string(),
+            Value::NativeFunction(intrinsic_fs_read_chunk),
+        );
+        scope.set(
+            "sys.fs.seek".to_string(),
+            Value::NativeFunction(intrinsic_fs_seek),
+        );
+        scope.set(
+            "sys.fs.close".to_string(),
+            Value::NativeFunction(intrinsic_fs_close),
+        );
+        scope.set(
+            "string_char_len".to_string(),
+            Value::NativeFunction(intrinsic_string_char_len),
+        );
+        scope.set(
+            "string_char_at".to_string(),
+            Value::NativeFunction(intrinsic_string_char_at),
+        );
+        scope.set(
+            "string_slice_chars".to_string(),
+            Value::NativeFunction(intrinsic_string_slice_chars),
+        );
+        scope.set(
+            "string_to_upper".to_string(),
+            Value::NativeFunction(intrinsic_string_to_upper),
+        );
+        scope.set(
+            "string_to_lower".to_string(),
+            Value::NativeFunction(intrinsic_string_to_lower),
+        );
+        scope.set(
+            "sys.fs.write_json".to_string(),
+            Value::NativeFunction(intrinsic_fs_write_json),
+        );
         scope.set(
             "sys.crypto.hash".to_string(),
             Value::NativeFunction(intrinsic_crypto_hash),
@@ -377,6 +1031,18 @@ impl IntrinsicRegistry {
             "sys.crypto.sha512".to_string(),
             Value::NativeFunction(intrinsic_crypto_sha512),
         );
+        scope.set(
+            "sys.crypto.sha256".to_string(),
+            Value::NativeFunction(intrinsic_crypto_sha256),
+        );
+        scope.set(
+            "sys.crypto.keccak256".to_string(),
+            Value::NativeFunction(intrinsic_crypto_keccak256),
+        );
+        scope.set(
+            "sys.crypto.blake3".to_string(),
+            Value::NativeFunction(intrinsic_crypto_blake3),
+        );
         scope.set(
             "sys.crypto.hmac_sha512".to_string(),
             Value::NativeFunction(intrinsic_crypto_hmac_sha512),
@@ -385,6 +1051,10 @@ impl IntrinsicRegistry {
             "sys.crypto.pbkdf2".to_string(),
             Value::NativeFunction(intrinsic_crypto_pbkdf2),
         );
+        scope.set(
+            "sys.crypto.constant_time_eq".to_string(),
+            Value::NativeFunction(intrinsic_crypto_constant_time_eq),
+        );
         scope.set(
             "sys.crypto.aes_gcm_encrypt".to_string(),
             Value::NativeFunction(intrinsic_crypto_aes_gcm_encrypt),
@@ -397,6 +1067,42 @@ impl IntrinsicRegistry {
             "sys.crypto.random_bytes".to_string(),
             Value::NativeFunction(intrinsic_crypto_random_bytes),
         );
+        scope.set(
+            "sys.random.int".to_string(),
+            Value::NativeFunction(intrinsic_random_int),
+        );
+        scope.set(
+            "sys.random.float".to_string(),
+            Value::NativeFunction(intrinsic_random_float),
+        );
+        scope.set(
+            "sys.random.choice".to_string(),
+            Value::NativeFunction(intrinsic_random_choice),
+        );
+        scope.set(
+            "sys.uuid.v4".to_string(),
+            Value::NativeFunction(intrinsic_uuid_v4),
+        );
+        scope.set(
+            "sys.uuid.parse".to_string(),
+            Value::NativeFunction(intrinsic_uuid_parse),
+        );
+        scope.set(
+            "sys.encode.base58".to_string(),
+            Value::NativeFunction(intrinsic_encode_base58),
+        );
+        scope.set(
+            "sys.decode.base58".to_string(),
+            Value::NativeFunction(intrinsic_decode_base58),
+        );
+        scope.set(
+            "sys.encode.base58check".to_string(),
+            Value::NativeFunction(intrinsic_encode_base58check),
+        );
+        scope.set(
+            "sys.decode.base58check".to_string(),
+            Value::NativeFunction(intrinsic_decode_base58check),
+        );
         scope.set(
             "sys.crypto.ed25519_generate".to_string(),
             Value::NativeFunction(intrinsic_crypto_ed25519_generate),
@@ -431,6 +1137,22 @@ impl IntrinsicRegistry {
             "sys.list.delete".to_string(),
             Value::NativeFunction(intrinsic_list_delete),
         );
+        scope.set(
+            "sys.list.zip".to_string(),
+            Value::NativeFunction(intrinsic_list_zip),
+        );
+        scope.set(
+            "sys.list.enumerate".to_string(),
+            Value::NativeFunction(intrinsic_list_enumerate),
+        );
+        scope.set(
+            "sys.list.chunk".to_string(),
+            Value::NativeFunction(intrinsic_list_chunk),
+        );
+        scope.set(
+            "sys.list.window".to_string(),
+            Value::NativeFunction(intrinsic_list_window),
+        );
         scope.set(
             "sys.struct.get".to_string(),
             Value::NativeFunction(intrinsic_struct_get),
@@ -556,6 +1278,20 @@ impl IntrinsicRegistry {
             "math.abs".to_string(),
             Value::NativeFunction(intrinsic_math_abs),
         );
+        for (name, func) in [
+            ("round", intrinsic_math_round as NativeFn),
+            ("floor", intrinsic_math_floor),
+            ("ceil", intrinsic_math_ceil),
+            ("trunc", intrinsic_math_trunc),
+            ("round_to", intrinsic_math_round_to),
+        ] {
+            scope.set(
+                format!("intrinsic_math_{}", name),
+                Value::NativeFunction(func),
+            );
+            scope.set(format!("math.{}", name), Value::NativeFunction(func));
+            scope.set(format!("sys.math.{}", name), Value::NativeFunction(func));
+        }
         scope.set(
             "intrinsic_gcd_normalize".to_string(),
             Value::NativeFunction(intrinsic_gcd_normalize),
@@ -626,84 +1362,276 @@ impl IntrinsicRegistry {
             Value::NativeFunction(intrinsic_event_push),
         );
         scope.set(
-            "sys.func.apply".to_string(),
-            Value::NativeFunction(intrinsic_func_apply),
+            "sys.timer.after".to_string(),
+            Value::NativeFunction(intrinsic_timer_after),
         );
         scope.set(
-            "sys.vm.eval".to_string(),
-            Value::NativeFunction(intrinsic_vm_eval),
+            "sys.timer.every".to_string(),
+            Value::NativeFunction(intrinsic_timer_every),
         );
         scope.set(
-            "sys.time.sleep".to_string(),
-            Value::NativeFunction(intrinsic_time_sleep),
+            "sys.timer.cancel".to_string(),
+            Value::NativeFunction(intrinsic_timer_cancel),
         );
         scope.set(
-            "sys.io.read_bytes".to_string(),
-            Value::NativeFunction(intrinsic_io_read_bytes),
+            "sys.chan.new".to_string(),
+            Value::NativeFunction(intrinsic_chan_new),
         );
         scope.set(
-            "sys.io.read_line".to_string(),
-            Value::NativeFunction(intrinsic_io_read_line),
+            "sys.chan.send".to_string(),
+            Value::NativeFunction(intrinsic_chan_send),
         );
         scope.set(
-            "sys.io.write".to_string(),
-            Value::NativeFunction(intrinsic_io_write),
+            "sys.chan.recv".to_string(),
+            Value::NativeFunction(intrinsic_chan_recv),
         );
         scope.set(
-            "sys.io.read_file_async".to_string(),
-            Value::NativeFunction(intrinsic_io_read_file_async),
+            "sys.chan.try_recv".to_string(),
+            Value::NativeFunction(intrinsic_chan_try_recv),
         );
         scope.set(
-            "sys.extract_code".to_string(),
-            Value::NativeFunction(intrinsic_extract_code),
+            "sys.chan.close".to_string(),
+            Value::NativeFunction(intrinsic_chan_close),
         );
         scope.set(
-            "sys.resource.usage".to_string(),
-            Value::NativeFunction(intrinsic_resource_usage),
+            "sys.task.spawn".to_string(),
+            Value::NativeFunction(intrinsic_task_spawn),
         );
         scope.set(
-            "intrinsic_resource_usage".to_string(),
-            Value::NativeFunction(intrinsic_resource_usage),
+            "sys.task.yield".to_string(),
+            Value::NativeFunction(intrinsic_task_yield),
         );
-        /*
-         */
-
-        // Networking Intrinsics
         scope.set(
-            "net.http.request".to_string(),
-            Value::NativeFunction(intrinsic_http_request),
+            "sys.task.sleep".to_string(),
+            Value::NativeFunction(intrinsic_task_sleep),
         );
         scope.set(
-            "net.http.serve".to_string(),
-            Value::NativeFunction(intrinsic_http_serve),
+            "sys.task.recv".to_string(),
+            Value::NativeFunction(intrinsic_task_recv),
         );
         scope.set(
-            "net.socket.bind".to_string(),
-            Value::NativeFunction(intrinsic_socket_bind),
+            "sys.task.accept".to_string(),
+            Value::NativeFunction(intrinsic_task_accept),
         );
         scope.set(
-            "net.socket.accept".to_string(),
-            Value::NativeFunction(intrinsic_socket_accept),
+            "sys.error.trace".to_string(),
+            Value::NativeFunction(intrinsic_error_trace),
         );
         scope.set(
-            "net.socket.connect".to_string(),
-            Value::NativeFunction(intrinsic_socket_connect),
+            "sys.sync.mutex_new".to_string(),
+            Value::NativeFunction(intrinsic_sync_mutex_new),
         );
         scope.set(
-            "net.socket.send".to_string(),
-            Value::NativeFunction(intrinsic_socket_send),
+            "sys.sync.mutex_lock".to_string(),
+            Value::NativeFunction(intrinsic_sync_mutex_lock),
         );
         scope.set(
-            "net.socket.recv".to_string(),
-            Value::NativeFunction(intrinsic_socket_recv),
+            "sys.sync.mutex_unlock".to_string(),
+            Value::NativeFunction(intrinsic_sync_mutex_unlock),
         );
         scope.set(
-            "net.socket.close".to_string(),
-            Value::NativeFunction(intrinsic_socket_close),
+            "sys.sync.rwlock_new".to_string(),
+            Value::NativeFunction(intrinsic_sync_rwlock_new),
         );
         scope.set(
-            "net.socket.set_timeout".to_string(),
-            Value::NativeFunction(intrinsic_socket_set_timeout),
+            "sys.sync.rwlock_read".to_string(),
+            Value::NativeFunction(intrinsic_sync_rwlock_read),
+        );
+        scope.set(
+            "sys.sync.rwlock_write".to_string(),
+            Value::NativeFunction(intrinsic_sync_rwlock_write),
+        );
+        scope.set(
+            "sys.sync.rwlock_unlock".to_string(),
+            Value::NativeFunction(intrinsic_sync_rwlock_unlock),
+        );
+        scope.set(
+            "sys.sync.atomic_new".to_string(),
+            Value::NativeFunction(intrinsic_sync_atomic_new),
+        );
+        scope.set(
+            "sys.sync.atomic_add".to_string(),
+            Value::NativeFunction(intrinsic_sync_atomic_add),
+        );
+        scope.set(
+            "sys.sync.atomic_load".to_string(),
+            Value::NativeFunction(intrinsic_sync_atomic_load),
+        );
+        scope.set(
+            "sys.sync.atomic_store".to_string(),
+            Value::NativeFunction(intrinsic_sync_atomic_store),
+        );
+        scope.set(
+            "sys.func.apply".to_string(),
+            Value::NativeFunction(intrinsic_func_apply),
+        );
+        scope.set(
+            "sys.assert_throws".to_string(),
+            Value::NativeFunction(intrinsic_assert_throws),
+        );
+        scope.set(
+            "assert".to_string(),
+            Value::NativeFunction(intrinsic_assert),
+        );
+        scope.set(
+            "assert_eq".to_string(),
+            Value::NativeFunction(intrinsic_assert_eq),
+        );
+        scope.set(
+            "assert_err".to_string(),
+            Value::NativeFunction(intrinsic_assert_err),
+        );
+        scope.set(
+            "sys.bench.black_box".to_string(),
+            Value::NativeFunction(intrinsic_bench_black_box),
+        );
+        scope.set(
+            "sys.debug.break".to_string(),
+            Value::NativeFunction(intrinsic_debug_break),
+        );
+        scope.set(
+            "sys.vm.eval".to_string(),
+            Value::NativeFunction(intrinsic_vm_eval),
+        );
+        scope.set(
+            "sys.time.sleep".to_string(),
+            Value::NativeFunction(intrinsic_time_sleep),
+        );
+        scope.set(
+            "sys.io.read_bytes".to_string(),
+            Value::NativeFunction(intrinsic_io_read_bytes),
+        );
+        scope.set(
+            "sys.io.read_line".to_string(),
+            Value::NativeFunction(intrinsic_io_read_line),
+        );
+        scope.set(
+            "sys.io.write".to_string(),
+            Value::NativeFunction(intrinsic_io_write),
+        );
+        scope.set(
+            "sys.io.read_file_async".to_string(),
+            Value::NativeFunction(intrinsic_io_read_file_async),
+        );
+        scope.set(
+            "sys.extract_code".to_string(),
+            Value::NativeFunction(intrinsic_extract_code),
+        );
+        scope.set(
+            "sys.resource.usage".to_string(),
+            Value::NativeFunction(intrinsic_resource_usage),
+        );
+        scope.set(
+            "intrinsic_resource_usage".to_string(),
+            Value::NativeFunction(intrinsic_resource_usage),
+        );
+        /*
+         */
+
+        // Networking Intrinsics
+        scope.set(
+            "net.http.request".to_string(),
+            Value::NativeFunction(intrinsic_http_request),
+        );
+        scope.set(
+            "net.http.try_request".to_string(),
+            Value::NativeFunction(intrinsic_http_try_request),
+        );
+        scope.set(
+            "net.http.serve".to_string(),
+            Value::NativeFunction(intrinsic_http_serve),
+        );
+        scope.set(
+            "net.http.route".to_string(),
+            Value::NativeFunction(intrinsic_http_route),
+        );
+        scope.set(
+            "net.http.listen".to_string(),
+            Value::NativeFunction(intrinsic_http_listen),
+        );
+        scope.set(
+            "net.http.listen_tls".to_string(),
+            Value::NativeFunction(intrinsic_http_listen_tls),
+        );
+        scope.set(
+            "net.socket.bind".to_string(),
+            Value::NativeFunction(intrinsic_socket_bind),
+        );
+        scope.set(
+            "net.socket.accept".to_string(),
+            Value::NativeFunction(intrinsic_socket_accept),
+        );
+        scope.set(
+            "net.socket.connect".to_string(),
+            Value::NativeFunction(intrinsic_socket_connect),
+        );
+        scope.set(
+            "net.socket.send".to_string(),
+            Value::NativeFunction(intrinsic_socket_send),
+        );
+        scope.set(
+            "net.socket.recv".to_string(),
+            Value::NativeFunction(intrinsic_socket_recv),
+        );
+        scope.set(
+            "net.socket.close".to_string(),
+            Value::NativeFunction(intrinsic_socket_close),
+        );
+        scope.set(
+            "net.socket.set_timeout".to_string(),
+            Value::NativeFunction(intrinsic_socket_set_timeout),
+        );
+        scope.set(
+            "net.socket.set_nonblocking".to_string(),
+            Value::NativeFunction(intrinsic_socket_set_nonblocking),
+        );
+        scope.set(
+            "net.poll.create".to_string(),
+            Value::NativeFunction(intrinsic_poll_create),
+        );
+        scope.set(
+            "net.poll.add".to_string(),
+            Value::NativeFunction(intrinsic_poll_add),
+        );
+        scope.set(
+            "net.poll.remove".to_string(),
+            Value::NativeFunction(intrinsic_poll_remove),
+        );
+        scope.set(
+            "net.poll.wait".to_string(),
+            Value::NativeFunction(intrinsic_poll_wait),
+        );
+        scope.set(
+            "net.tls.load_identity".to_string(),
+            Value::NativeFunction(intrinsic_tls_load_identity),
+        );
+        scope.set(
+            "net.tls.wrap_listener".to_string(),
+            Value::NativeFunction(intrinsic_tls_wrap_listener),
+        );
+        scope.set(
+            "net.tls.connect".to_string(),
+            Value::NativeFunction(intrinsic_tls_connect),
+        );
+        scope.set(
+            "net.ws.connect".to_string(),
+            Value::NativeFunction(intrinsic_ws_connect),
+        );
+        scope.set(
+            "net.ws.accept".to_string(),
+            Value::NativeFunction(intrinsic_ws_accept),
+        );
+        scope.set(
+            "net.ws.send".to_string(),
+            Value::NativeFunction(intrinsic_ws_send),
+        );
+        scope.set(
+            "net.ws.recv".to_string(),
+            Value::NativeFunction(intrinsic_ws_recv),
+        );
+        scope.set(
+            "net.ws.close".to_string(),
+            Value::NativeFunction(intrinsic_socket_close),
         );
 
         // Phase 78: Final 12 Parity Intrinsics
@@ -715,6 +1643,10 @@ impl IntrinsicRegistry {
             "sys.json.stringify".to_string(),
             Value::NativeFunction(intrinsic_json_stringify),
         );
+        scope.set(
+            "sys.json.try_parse".to_string(),
+            Value::NativeFunction(intrinsic_json_try_parse),
+        );
         scope.set("sys.log".to_string(), Value::NativeFunction(intrinsic_log));
         scope.set(
             "sys.exit".to_string(),
@@ -722,6 +1654,18 @@ impl IntrinsicRegistry {
         );
         scope.set("exit".to_string(), Value::NativeFunction(intrinsic_exit));
         scope.set("quit".to_string(), Value::NativeFunction(intrinsic_exit));
+        scope.set(
+            "sys.args.count".to_string(),
+            Value::NativeFunction(intrinsic_args_count),
+        );
+        scope.set(
+            "sys.args.get".to_string(),
+            Value::NativeFunction(intrinsic_args_get),
+        );
+        scope.set(
+            "sys.env.get".to_string(),
+            Value::NativeFunction(intrinsic_env_get),
+        );
         scope.set(
             "sys.html_escape".to_string(),
             Value::NativeFunction(intrinsic_html_escape),
@@ -809,6 +1753,122 @@ impl IntrinsicRegistry {
     }
 }
 
+thread_local! {
+    /// What the program on this thread may do, set by `set_capabilities`.
+    static CAPABILITIES: std::cell::RefCell<Option<Capabilities>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Restrict the intrinsics run on this thread to `capabilities`, or go
+/// back to [`Capabilities::host_default`] with `None`. Returns what was
+/// set before, so a caller can restore it. Threads spawned with
+/// `sys.thread.spawn` inherit the setting.
+pub fn set_capabilities(capabilities: Option<Capabilities>) -> Option<Capabilities> {
+    CAPABILITIES.with(|current| current.replace(capabilities))
+}
+
+/// What the program on this thread may do.
+pub fn capabilities() -> Capabilities {
+    CAPABILITIES.with(|current| {
+        current
+            .borrow()
+            .clone()
+            .unwrap_or_else(Capabilities::host_default)
+    })
+}
+
+thread_local! {
+    /// What the program on this thread may hold, set by `set_resource_limits`.
+    static RESOURCE_LIMITS: std::cell::RefCell<Option<ResourceLimits>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Bound what the program on this thread may hold, or lift the bounds
+/// with `None`. Returns what was set before, so a caller can restore it.
+/// Threads spawned with `sys.thread.spawn` inherit the setting.
+pub fn set_resource_limits(limits: Option<ResourceLimits>) -> Option<ResourceLimits> {
+    RESOURCE_LIMITS.with(|current| current.replace(limits))
+}
+
+/// What the program on this thread may hold.
+pub fn resource_limits() -> ResourceLimits {
+    RESOURCE_LIMITS.with(|current| current.borrow().clone().unwrap_or_default())
+}
+
+thread_local! {
+    /// Elements allocated so far by the program on this thread, set by
+    /// `set_values_allocated`.
+    static VALUES_ALLOCATED: std::cell::RefCell<Option<Arc<AtomicUsize>>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Make VMs started on this thread without limits of their own add what
+/// they allocate to `allocated`, or start their own count with `None`.
+/// Returns what was set before, so a caller can restore it. Threads
+/// spawned with `sys.thread.spawn` inherit the setting.
+pub fn set_values_allocated(allocated: Option<Arc<AtomicUsize>>) -> Option<Arc<AtomicUsize>> {
+    VALUES_ALLOCATED.with(|current| current.replace(allocated))
+}
+
+/// Elements allocated so far by the program on this thread, if it is
+/// counting them.
+pub fn values_allocated() -> Option<Arc<AtomicUsize>> {
+    VALUES_ALLOCATED.with(|current| current.borrow().clone())
+}
+
+/// Fail with `LimitExceeded` if one more handle of `resource` can't be
+/// opened while `open` are.
+fn admit_handle(resource: Resource, open: usize) -> Result<(), RuntimeError> {
+    resource_limits().check(resource, open + 1)
+}
+
+/// Fail with `NotExecutable` unless `granted`, saying which capability is
+/// missing.
+fn require_capability(granted: bool, missing: &str) -> Result<(), RuntimeError> {
+    if granted {
+        return Ok(());
+    }
+    println!("[Ark:Sandbox] Access Denied: {}.", missing);
+    Err(RuntimeError::NotExecutable)
+}
+
+/// Fail unless the program may reach (or listen on) `host:port`.
+#[cfg(not(target_arch = "wasm32"))]
+fn check_net_capability(host: &str, port: u16) -> Result<(), RuntimeError> {
+    require_capability(
+        capabilities().may_connect(host, port),
+        &format!("no net capability for {}:{}", host, port),
+    )
+}
+
+/// Host and port a URL connects to; the port defaults by scheme.
+#[cfg(not(target_arch = "wasm32"))]
+fn url_host_port(url: &str) -> Option<(String, u16)> {
+    let (scheme, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, a)| a);
+    let default_port = match scheme.to_ascii_lowercase().as_str() {
+        "https" | "wss" => 443,
+        _ => 80,
+    };
+    // `[::1]:8080`: the port follows the closing bracket
+    let (host, port) = match authority.rfind(']') {
+        Some(end) => match authority[end + 1..].strip_prefix(':') {
+            Some(port) => (&authority[..=end], Some(port)),
+            None => (authority, None),
+        },
+        None => match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+    let port = match port {
+        Some(port) => port.parse().ok()?,
+        None => default_port,
+    };
+    Some((host.to_string(), port))
+}
+
 fn check_path_security(path: &str, is_write: bool) -> Result<(), RuntimeError> {
     #[cfg(target_arch = "wasm32")]
     return Ok(());
@@ -818,7 +1878,7 @@ fn check_path_security(path: &str, is_write: bool) -> Result<(), RuntimeError> {
         use std::env;
         use std::path::Path;
 
-        let cwd = env::current_dir().map_err(|_| RuntimeError::NotExecutable)?;
+        let cwd = env::current_dir().map_err(RuntimeError::from)?;
         // Canonicalize CWD too — on Windows, canonicalize returns UNC paths (\\?\C:\...)
         // but current_dir() returns normal paths. Both must match for starts_with.
         let cwd = std::fs::canonicalize(&cwd).unwrap_or(cwd);
@@ -845,15 +1905,19 @@ fn check_path_security(path: &str, is_write: bool) -> Result<(), RuntimeError> {
         };
 
         // If parent doesn't exist, canonicalize fails.
-        let canonical_path =
-            std::fs::canonicalize(&path_to_check).map_err(|_| RuntimeError::NotExecutable)?;
+        let canonical_path = std::fs::canonicalize(&path_to_check).map_err(RuntimeError::from)?;
 
-        if !canonical_path.starts_with(&cwd) {
-            println!(
-                "[Ark:Sandbox] Access Denied: Path '{}' resolves outside CWD.",
-                path
-            );
-            return Err(RuntimeError::NotExecutable);
+        let capabilities = capabilities();
+        if is_write {
+            require_capability(
+                capabilities.may_write(&canonical_path),
+                &format!("no fs_write capability for '{}'", path),
+            )?;
+        } else {
+            require_capability(
+                capabilities.may_read(&canonical_path),
+                &format!("no fs_read capability for '{}'", path),
+            )?;
         }
 
         // Sovereign Security: Protected Paths (Write Only)
@@ -919,6 +1983,7 @@ pub fn intrinsic_ask_ai(args: Vec<Value>) -> Result<Value, RuntimeError> {
 
     #[cfg(not(target_arch = "wasm32"))]
     {
+        require_capability(capabilities().ai, "no ai capability")?;
         let api_key = std::env::var("GOOGLE_API_KEY").map_err(|_| {
             println!("[Ark:AI] Error: GOOGLE_API_KEY not set.");
             RuntimeError::NotExecutable
@@ -953,6 +2018,7 @@ pub fn intrinsic_ask_ai(args: Vec<Value>) -> Result<Value, RuntimeError> {
 
         // Optimization: Direct Blocking Call (No Tokio Runtime Overhead)
         // Simple Retry Logic
+        let mut network_error = None;
         for attempt in 0..3 {
             match client
                 .post(&url)
@@ -961,6 +2027,7 @@ pub fn intrinsic_ask_ai(args: Vec<Value>) -> Result<Value, RuntimeError> {
                 .send()
             {
                 Ok(resp) => {
+                    network_error = None;
                     if resp.status().is_success() {
                         let json_resp = match resp.json::<serde_json::Value>() {
                             Ok(v) => v,
@@ -990,10 +2057,19 @@ pub fn intrinsic_ask_ai(args: Vec<Value>) -> Result<Value, RuntimeError> {
                         println!("[Ark:AI] HTTP Error: {}", resp.status());
                     }
                 }
-                Err(e) => println!("[Ark:AI] Network Error: {}", e),
+                Err(e) => {
+                    println!("[Ark:AI] Network Error: {}", e);
+                    network_error = Some(network_error_from_reqwest(&e));
+                }
             }
         }
 
+        // Transport failures are surfaced so callers can tell why the model was
+        // unreachable; the mock below only covers API-level failures.
+        if let Some(err) = network_error {
+            return Err(err);
+        }
+
         // Fallback Mock
         println!("[Ark:AI] WARNING: API Failed. Using Fallback Mock.");
         let start = "```python\n";
@@ -1017,7 +2093,9 @@ pub fn intrinsic_exec(args: Vec<Value>) -> Result<Value, RuntimeError> {
             );
             #[cfg(not(target_arch = "wasm32"))]
             {
-                let parts = shell_words::split(s).map_err(|_| RuntimeError::NotExecutable)?;
+                let parts = shell_words::split(s).map_err(|e| {
+                    RuntimeError::InvalidOperation(format!("Invalid command: {}", e))
+                })?;
                 if parts.is_empty() {
                     return Err(RuntimeError::NotExecutable);
                 }
@@ -1061,81 +2139,331 @@ pub fn intrinsic_exec(args: Vec<Value>) -> Result<Value, RuntimeError> {
 
     #[cfg(not(target_arch = "wasm32"))]
     {
-        // Sovereign Security: Command Whitelist
-        // Unless ARK_UNSAFE_EXEC=true is strictly set, we block arbitrary execution.
-        let allow_unsafe =
-            std::env::var("ARK_UNSAFE_EXEC").unwrap_or_else(|_| "false".to_string()) == "true";
-
-        if !allow_unsafe {
-            // Allowed binaries (safe-ish subset)
-            // HARDENED: Removed python, node, cargo, rustc, git to prevent arbitrary code execution
-            let whitelist = ["ls", "grep", "cat", "echo", "date", "whoami", "clear"];
-
-            // Check strictly against whitelist (exact match on binary name)
-            // If program is a path (e.g. /bin/ls), extract file_name.
-            let prog_path = std::path::Path::new(&program);
-            let prog_name = prog_path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-
-            if !whitelist.contains(&prog_name) {
-                println!(
-                    "[Ark:Exec] Security Violation: Command '{}' is not in the whitelist.",
-                    program
-                );
-                println!("[Ark:Exec] To bypass, set ARK_UNSAFE_EXEC=true (NOT RECOMMENDED).");
-                return Err(RuntimeError::NotExecutable);
-            }
-        }
+        check_exec_capability(&program)?;
 
         println!("[Ark:Exec] {} {:?}", program, args_list);
 
         let mut cmd = Command::new(&program);
         cmd.args(&args_list);
 
-        let output = cmd.output().map_err(|_| RuntimeError::NotExecutable)?;
+        let output = cmd.output().map_err(RuntimeError::from)?;
 
         let stdout = String::from_utf8_lossy(&output.stdout).to_string();
         Ok(Value::String(stdout))
     }
 }
 
-pub fn intrinsic_fs_write(args: Vec<Value>) -> Result<Value, RuntimeError> {
-    if args.len() != 2 {
+/// Sovereign Security: only commands the exec capability names may run.
+/// By default that is `capability::DEFAULT_EXEC`, unless
+/// ARK_UNSAFE_EXEC=true is strictly set.
+#[cfg(not(target_arch = "wasm32"))]
+fn check_exec_capability(program: &str) -> Result<(), RuntimeError> {
+    require_capability(
+        capabilities().may_exec(program),
+        &format!("no exec capability for command '{}'", program),
+    )
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn process_handle(val: &Value) -> Result<i64, RuntimeError> {
+    match val {
+        Value::Integer(i) => Ok(*i),
+        _ => Err(RuntimeError::TypeMismatch(
+            "Integer".to_string(),
+            val.clone(),
+        )),
+    }
+}
+
+/// sys.process.spawn([cmd, arg1, ...]) → Integer handle
+/// Starts the command without waiting, with piped stdin/stdout. Subject to
+/// the same exec capability as `sys.exec`.
+pub fn intrinsic_process_spawn(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    spawn_process(&args, false)
+}
+
+/// sys.proc.spawn([cmd, arg1, ...]) → Integer handle
+/// Like `sys.process.spawn`, but stderr is piped as well, for
+/// `sys.proc.stderr_read`. Output nobody reads fills its pipe and
+/// eventually stalls the process.
+pub fn intrinsic_proc_spawn(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    spawn_process(&args, true)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn spawn_process(args: &[Value], pipe_stderr: bool) -> Result<Value, RuntimeError> {
+    if args.len() != 1 {
         return Err(RuntimeError::NotExecutable);
     }
-    let path_str = match &args[0] {
-        Value::String(s) => s,
+    let parts = match &args[0] {
+        Value::List(l) => l
+            .iter()
+            .map(|item| match item {
+                Value::String(s) => Ok(s.clone()),
+                _ => Err(RuntimeError::TypeMismatch(
+                    "String".to_string(),
+                    item.clone(),
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
         _ => {
             return Err(RuntimeError::TypeMismatch(
-                "String".to_string(),
+                "List".to_string(),
                 args[0].clone(),
             ));
         }
     };
-    let content = match &args[1] {
-        Value::String(s) => s,
-        _ => {
-            return Err(RuntimeError::TypeMismatch(
-                "String".to_string(),
-                args[1].clone(),
-            ));
-        }
-    };
+    let (program, rest) = parts.split_first().ok_or(RuntimeError::NotExecutable)?;
+    check_exec_capability(program)?;
 
-    check_path_security(path_str, true)?;
+    let mut command = Command::new(program);
+    command
+        .args(rest)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped());
+    if pipe_stderr {
+        command.stderr(std::process::Stdio::piped());
+    }
+    let child = command.spawn().map_err(RuntimeError::from)?;
+
+    let id = PROCESS_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
+    let mut processes = get_processes()
+        .lock()
+        .map_err(|e| RuntimeError::InvalidOperation(format!("process mutex poisoned: {}", e)))?;
+    processes.insert(id, child);
 
+    Ok(Value::Integer(id))
+}
+
+/// sys.process.write_stdin(handle, data) → Integer bytes written
+pub fn intrinsic_process_write_stdin(args: Vec<Value>) -> Result<Value, RuntimeError> {
     #[cfg(target_arch = "wasm32")]
-    {
-        println!(
-            "[Ark:VFS] Write to '{}': (Simulated) [Content Size: {}]",
-            path_str,
-            content.len()
-        );
-        Ok(Value::Unit)
-    }
+    return Err(RuntimeError::NotExecutable);
 
     #[cfg(not(target_arch = "wasm32"))]
     {
-        // NTS Protocol: Intentional Friction (Level 1)
+        if args.len() != 2 {
+            return Err(RuntimeError::NotExecutable);
+        }
+        let id = process_handle(&args[0])?;
+        let data = match &args[1] {
+            Value::String(s) => s.as_bytes().to_vec(),
+            Value::Buffer(b) => b.clone(),
+            _ => {
+                return Err(RuntimeError::TypeMismatch(
+                    "String or Buffer".to_string(),
+                    args[1].clone(),
+                ));
+            }
+        };
+
+        let mut processes = get_processes().lock().map_err(|e| {
+            RuntimeError::InvalidOperation(format!("process mutex poisoned: {}", e))
+        })?;
+        match processes.get_mut(&id).and_then(|c| c.stdin.as_mut()) {
+            Some(stdin) => {
+                stdin
+                    .write_all(&data)
+                    .and_then(|_| stdin.flush())
+                    .map_err(RuntimeError::from)?;
+                Ok(Value::Integer(data.len() as i64))
+            }
+            None => Err(RuntimeError::InvalidOperation(
+                "Invalid process handle or stdin closed".to_string(),
+            )),
+        }
+    }
+}
+
+/// sys.process.read_stdout(handle, n) → String
+/// Blocks until some output is available and returns at most `n` bytes;
+/// an empty string means the process closed its stdout.
+pub fn intrinsic_process_read_stdout(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    read_process_pipe(&args, false)
+}
+
+/// sys.proc.stderr_read(handle, n) → String
+/// `sys.process.read_stdout` for the stderr of a `sys.proc.spawn` process.
+pub fn intrinsic_proc_stderr_read(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    read_process_pipe(&args, true)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_process_pipe(args: &[Value], stderr: bool) -> Result<Value, RuntimeError> {
+    if args.len() != 2 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let id = process_handle(&args[0])?;
+    let max_bytes = match &args[1] {
+        Value::Integer(n) if *n >= 0 => *n as usize,
+        _ => {
+            return Err(RuntimeError::TypeMismatch(
+                "Integer".to_string(),
+                args[1].clone(),
+            ));
+        }
+    };
+
+    let mut processes = get_processes()
+        .lock()
+        .map_err(|e| RuntimeError::InvalidOperation(format!("process mutex poisoned: {}", e)))?;
+    let pipe: Option<&mut dyn Read> = match processes.get_mut(&id) {
+        Some(c) if stderr => c.stderr.as_mut().map(|p| p as &mut dyn Read),
+        Some(c) => c.stdout.as_mut().map(|p| p as &mut dyn Read),
+        None => None,
+    };
+    match pipe {
+        Some(pipe) => {
+            let mut buf = vec![0u8; max_bytes];
+            let n = pipe.read(&mut buf).map_err(RuntimeError::from)?;
+            buf.truncate(n);
+            Ok(Value::String(String::from_utf8_lossy(&buf).to_string()))
+        }
+        None if stderr => Err(RuntimeError::InvalidOperation(
+            "Invalid process handle or stderr not piped".to_string(),
+        )),
+        None => Err(RuntimeError::InvalidOperation(
+            "Invalid process handle".to_string(),
+        )),
+    }
+}
+
+/// sys.process.wait(handle) → Integer exit code
+/// Closes the process's stdin, waits for it to exit and releases the
+/// handle. A process killed by a signal reports -1.
+pub fn intrinsic_process_wait(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let status = wait_process(&args)?;
+        Ok(Value::Integer(status.code().map_or(-1, i64::from)))
+    }
+}
+
+/// sys.proc.wait(handle) → { code, signal }
+/// `sys.process.wait` that tells an exit code from a killing signal: one of
+/// `code` and `signal` is an Integer and the other is Unit. Signals are
+/// only reported on Unix.
+pub fn intrinsic_proc_wait(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let status = wait_process(&args)?;
+        #[cfg(unix)]
+        let signal = std::os::unix::process::ExitStatusExt::signal(&status);
+        #[cfg(not(unix))]
+        let signal: Option<i32> = None;
+
+        let optional = |n: Option<i32>| n.map_or(Value::Unit, |n| Value::Integer(n.into()));
+        let mut fields = HashMap::new();
+        fields.insert("code".to_string(), optional(status.code()));
+        fields.insert("signal".to_string(), optional(signal));
+        Ok(Value::Struct(fields))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn wait_process(args: &[Value]) -> Result<std::process::ExitStatus, RuntimeError> {
+    if args.len() != 1 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let id = process_handle(&args[0])?;
+    let child = get_processes()
+        .lock()
+        .map_err(|e| RuntimeError::InvalidOperation(format!("process mutex poisoned: {}", e)))?
+        .remove(&id);
+    let mut child = child
+        .ok_or_else(|| RuntimeError::InvalidOperation("Invalid process handle".to_string()))?;
+
+    drop(child.stdin.take());
+    child.wait().map_err(RuntimeError::from)
+}
+
+/// sys.proc.kill(handle) → Unit
+/// Forcibly stops the process. The handle stays valid so `sys.proc.wait`
+/// can collect the exit status; killing an exited process is a no-op.
+pub fn intrinsic_proc_kill(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if args.len() != 1 {
+            return Err(RuntimeError::NotExecutable);
+        }
+        let id = process_handle(&args[0])?;
+        let mut processes = get_processes().lock().map_err(|e| {
+            RuntimeError::InvalidOperation(format!("process mutex poisoned: {}", e))
+        })?;
+        let child = processes
+            .get_mut(&id)
+            .ok_or_else(|| RuntimeError::InvalidOperation("Invalid process handle".to_string()))?;
+        // An exited process has nothing left to kill
+        if child.try_wait().map_err(RuntimeError::from)?.is_none() {
+            child.kill().map_err(RuntimeError::from)?;
+        }
+        Ok(Value::Unit)
+    }
+}
+
+pub fn intrinsic_fs_write(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 2 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let path_str = match &args[0] {
+        Value::String(s) => s,
+        _ => {
+            return Err(RuntimeError::TypeMismatch(
+                "String".to_string(),
+                args[0].clone(),
+            ));
+        }
+    };
+    let content = match &args[1] {
+        Value::String(s) => s,
+        _ => {
+            return Err(RuntimeError::TypeMismatch(
+                "String".to_string(),
+                args[1].clone(),
+            ));
+        }
+    };
+
+    check_path_security(path_str, true)?;
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        println!(
+            "[Ark:VFS] Write to '{}': (Simulated) [Content Size: {}]",
+            path_str,
+            content.len()
+        );
+        Ok(Value::Unit)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        // NTS Protocol: Intentional Friction (Level 1)
         if std::path::Path::new(path_str).exists() {
             println!(
                 "[Ark:NTS] WARNING: Overwriting existing file '{}' without explicit lock (LAT).",
@@ -1144,7 +2472,7 @@ pub fn intrinsic_fs_write(args: Vec<Value>) -> Result<Value, RuntimeError> {
         }
 
         println!("[Ark:FS] Writing to {}", path_str);
-        fs::write(path_str, content).map_err(|_| RuntimeError::NotExecutable)?;
+        fs::write(path_str, content).map_err(RuntimeError::from)?;
         Ok(Value::Unit)
     }
 }
@@ -1244,7 +2572,7 @@ pub fn intrinsic_div(args: Vec<Value>) -> Result<Value, RuntimeError> {
     match (&args[0], &args[1]) {
         (Value::Integer(a), Value::Integer(b)) => {
             if *b == 0 {
-                return Err(RuntimeError::NotExecutable); // Div by zero
+                return Err(RuntimeError::DivisionByZero);
             }
             Ok(Value::Integer(a / b))
         }
@@ -1262,7 +2590,7 @@ pub fn intrinsic_mod(args: Vec<Value>) -> Result<Value, RuntimeError> {
     match (&args[0], &args[1]) {
         (Value::Integer(a), Value::Integer(b)) => {
             if *b == 0 {
-                return Err(RuntimeError::NotExecutable); // Mod by zero
+                return Err(RuntimeError::DivisionByZero);
             }
             Ok(Value::Integer(a % b))
         }
@@ -1307,15 +2635,7 @@ pub fn intrinsic_not(args: Vec<Value>) -> Result<Value, RuntimeError> {
     if args.len() != 1 {
         return Err(RuntimeError::NotExecutable);
     }
-    match &args[0] {
-        Value::Boolean(b) => Ok(Value::Boolean(!b)),
-        Value::Integer(n) => Ok(Value::Boolean(*n == 0)),
-        Value::String(s) => Ok(Value::Boolean(s.is_empty() || s == "0" || s == "false")),
-        _ => Err(RuntimeError::TypeMismatch(
-            "Boolean, Integer, or String".to_string(),
-            args[0].clone(),
-        )),
-    }
+    Ok(Value::Boolean(!args[0].as_condition()?))
 }
 
 pub fn intrinsic_ge(args: Vec<Value>) -> Result<Value, RuntimeError> {
@@ -1358,6 +2678,9 @@ pub fn intrinsic_eq(args: Vec<Value>) -> Result<Value, RuntimeError> {
         (Value::Integer(a), Value::Integer(b)) => Ok(Value::Boolean(a == b)),
         (Value::String(a), Value::String(b)) => Ok(Value::Boolean(a == b)),
         (Value::Boolean(a), Value::Boolean(b)) => Ok(Value::Boolean(a == b)),
+        // Decimals compare by value, whatever their scales
+        (Value::Decimal(a), Value::Decimal(b)) => Ok(Value::Boolean(a == b)),
+        (Value::Tensor(a), Value::Tensor(b)) => Ok(Value::Boolean(a == b)),
         // Cross-type: Integer vs String (common in Ark scripts)
         (Value::Integer(a), Value::String(b)) => Ok(Value::Boolean(&a.to_string() == b)),
         (Value::String(a), Value::Integer(b)) => Ok(Value::Boolean(a == &b.to_string())),
@@ -1369,16 +2692,9 @@ pub fn intrinsic_and(args: Vec<Value>) -> Result<Value, RuntimeError> {
     if args.len() != 2 {
         return Err(RuntimeError::NotExecutable);
     }
-    // Truthy check: Integer != 0, Boolean == true, String != "0" and != ""
-    let is_truthy = |v: &Value| match v {
-        Value::Integer(n) => *n != 0,
-        Value::Boolean(b) => *b,
-        Value::String(s) => s != "0" && !s.is_empty() && s != "false",
-        _ => false,
-    };
-
-    let left = is_truthy(&args[0]);
-    let right = is_truthy(&args[1]);
+    // Both operands are evaluated, so both must be valid conditions
+    let left = args[0].as_condition()?;
+    let right = args[1].as_condition()?;
 
     Ok(Value::Boolean(left && right))
 }
@@ -1387,15 +2703,8 @@ pub fn intrinsic_or(args: Vec<Value>) -> Result<Value, RuntimeError> {
     if args.len() != 2 {
         return Err(RuntimeError::NotExecutable);
     }
-    let is_truthy = |v: &Value| match v {
-        Value::Integer(n) => *n != 0,
-        Value::Boolean(b) => *b,
-        Value::String(s) => s != "0" && !s.is_empty() && s != "false",
-        _ => false,
-    };
-
-    let left = is_truthy(&args[0]);
-    let right = is_truthy(&args[1]);
+    let left = args[0].as_condition()?;
+    let right = args[1].as_condition()?;
 
     Ok(Value::Boolean(left || right))
 }
@@ -1428,6 +2737,9 @@ fn print_value(v: &Value) {
             print!("]");
         }
         Value::Buffer(b) => print!("<Buffer: len={}, ptr={:p}>", b.len(), b.as_ptr()),
+        Value::BufferView { offset, len, .. } => {
+            print!("<BufferView: len={}, offset={}>", len, offset)
+        }
         Value::Struct(fields) => {
             print!("{{");
             for (i, (k, v)) in fields.iter().enumerate() {
@@ -1441,6 +2753,9 @@ fn print_value(v: &Value) {
         }
         Value::PVec(pv) => print!("{}", pv),
         Value::PMap(pm) => print!("{}", pm),
+        Value::BigInt(n) => print!("{}", n),
+        Value::Decimal(d) => print!("{}", d),
+        Value::Tensor(t) => print!("{}", t),
         Value::Return(val) => print_value(val),
         Value::EnumValue {
             enum_name,
@@ -1468,14 +2783,10 @@ fn validate_safe_path(path_str: &str) -> Result<PathBuf, RuntimeError> {
 
     // 1. Canonicalize the requested path (resolves symlinks and ..)
     // If the file does not exist, canonicalize fails. For read, this is fine (file must exist).
-    let canonical_path = fs::canonicalize(path).map_err(|_| RuntimeError::NotExecutable)?;
-
-    // 2. Canonicalize the current working directory (sandbox root)
-    let current_dir = env::current_dir().map_err(|_| RuntimeError::NotExecutable)?;
-    let canonical_cwd = fs::canonicalize(current_dir).map_err(|_| RuntimeError::NotExecutable)?;
+    let canonical_path = fs::canonicalize(path).map_err(RuntimeError::from)?;
 
-    // 3. Verify that the requested path starts with the sandbox root
-    if canonical_path.starts_with(&canonical_cwd) {
+    // 2. Verify that it lies under a directory the program may read
+    if capabilities().may_read(&canonical_path) {
         Ok(canonical_path)
     } else {
         println!(
@@ -1513,7 +2824,7 @@ pub fn intrinsic_fs_read(args: Vec<Value>) -> Result<Value, RuntimeError> {
         println!("[Ark:FS] Reading from {}", path_str);
         // Security: Path Traversal Check
         let safe_path = validate_safe_path(path_str)?;
-        let content = fs::read_to_string(safe_path).map_err(|_| RuntimeError::NotExecutable)?;
+        let content = fs::read_to_string(safe_path).map_err(RuntimeError::from)?;
         Ok(Value::String(content))
     }
 }
@@ -1607,6 +2918,40 @@ pub fn intrinsic_crypto_sha512(args: Vec<Value>) -> Result<Value, RuntimeError>
     Ok(Value::String(hex::encode(result)))
 }
 
+/// Hex `digest` of the String or Buffer argument of a 32-byte hash intrinsic.
+fn crypto_digest(args: &[Value], digest: fn(&[u8]) -> [u8; 32]) -> Result<Value, RuntimeError> {
+    if args.len() != 1 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let data = match &args[0] {
+        Value::String(s) => s.as_bytes(),
+        Value::Buffer(b) => b.as_slice(),
+        _ => {
+            return Err(RuntimeError::TypeMismatch(
+                "String or Buffer".into(),
+                args[0].clone(),
+            ));
+        }
+    };
+    Ok(Value::String(hex::encode(digest(data))))
+}
+
+/// sys.crypto.sha256(data) → String, the hex SHA-256 digest.
+pub fn intrinsic_crypto_sha256(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    crypto_digest(&args, crate::crypto::hash_sha256)
+}
+
+/// sys.crypto.keccak256(data) → String, the hex Keccak-256 digest as
+/// Ethereum computes it (not SHA3-256).
+pub fn intrinsic_crypto_keccak256(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    crypto_digest(&args, crate::crypto::hash_keccak256)
+}
+
+/// sys.crypto.blake3(data) → String, the hex 32-byte BLAKE3 digest.
+pub fn intrinsic_crypto_blake3(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    crypto_digest(&args, crate::crypto::hash_blake3)
+}
+
 pub fn intrinsic_crypto_hmac_sha512(args: Vec<Value>) -> Result<Value, RuntimeError> {
     if args.len() != 2 {
         return Err(RuntimeError::NotExecutable);
@@ -1633,6 +2978,28 @@ pub fn intrinsic_crypto_hmac_sha512(args: Vec<Value>) -> Result<Value, RuntimeEr
     Ok(Value::String(hex::encode(result)))
 }
 
+/// sys.crypto.constant_time_eq(a, b) → Boolean
+/// Compares two Strings/Buffers byte-for-byte without short-circuiting, for
+/// MACs and tokens. Strings are compared as raw UTF-8, not hex-decoded.
+pub fn intrinsic_crypto_constant_time_eq(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 2 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let as_bytes = |v: &Value| -> Result<Vec<u8>, RuntimeError> {
+        match v {
+            Value::String(s) => Ok(s.as_bytes().to_vec()),
+            Value::Buffer(b) => Ok(b.clone()),
+            _ => Err(RuntimeError::TypeMismatch(
+                "String or Buffer".into(),
+                v.clone(),
+            )),
+        }
+    };
+    let a = as_bytes(&args[0])?;
+    let b = as_bytes(&args[1])?;
+    Ok(Value::Boolean(crate::crypto::constant_time_eq(&a, &b)))
+}
+
 pub fn intrinsic_crypto_pbkdf2(args: Vec<Value>) -> Result<Value, RuntimeError> {
     if args.len() != 4 {
         return Err(RuntimeError::NotExecutable);
@@ -1757,6 +3124,27 @@ pub fn intrinsic_crypto_aes_gcm_decrypt(args: Vec<Value>) -> Result<Value, Runti
     Ok(Value::Buffer(plaintext))
 }
 
+thread_local! {
+    /// Seeded replacement for `OsRng`, enabled by `set_deterministic_rng`.
+    static DETERMINISTIC_RNG: std::cell::RefCell<Option<StdRng>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Switch the current thread's random intrinsics (`sys.crypto.random_bytes`,
+/// `sys.uuid.v4`, `sys.random.*`) to a seeded generator so runs are reproducible, or back to
+/// `OsRng` with `None`. Intended for tests; key generation always uses
+/// `OsRng`.
+pub fn set_deterministic_rng(seed: Option<u64>) {
+    DETERMINISTIC_RNG.with(|rng| *rng.borrow_mut() = seed.map(StdRng::seed_from_u64));
+}
+
+fn random_fill(bytes: &mut [u8]) {
+    DETERMINISTIC_RNG.with(|rng| match rng.borrow_mut().as_mut() {
+        Some(seeded) => seeded.fill_bytes(bytes),
+        None => OsRng.fill_bytes(bytes),
+    });
+}
+
 pub fn intrinsic_crypto_random_bytes(args: Vec<Value>) -> Result<Value, RuntimeError> {
     if args.len() != 1 {
         return Err(RuntimeError::NotExecutable);
@@ -1776,72 +3164,360 @@ pub fn intrinsic_crypto_random_bytes(args: Vec<Value>) -> Result<Value, RuntimeE
         ));
     }
     let mut bytes = vec![0u8; n];
-    OsRng.fill_bytes(&mut bytes);
+    random_fill(&mut bytes);
     Ok(Value::String(hex::encode(bytes)))
 }
 
-pub fn intrinsic_crypto_ed25519_generate(args: Vec<Value>) -> Result<Value, RuntimeError> {
-    if !args.is_empty() {
-        return Err(RuntimeError::NotExecutable);
+/// Uniform draw from `0..bound` (`bound > 0`). Draws at or above the largest
+/// multiple of `bound` are rejected so the result has no modulo bias.
+fn random_below(bound: u64) -> u64 {
+    let zone = (u64::MAX / bound) * bound;
+    loop {
+        let mut bytes = [0u8; 8];
+        random_fill(&mut bytes);
+        let x = u64::from_le_bytes(bytes);
+        if x < zone {
+            return x % bound;
+        }
     }
-    let mut csprng = OsRng;
-    let signing_key = SigningKey::generate(&mut csprng);
-    let verifying_key = signing_key.verifying_key();
-
-    let mut map = std::collections::HashMap::new();
-    map.insert(
-        "private_key".to_string(),
-        Value::String(hex::encode(signing_key.to_bytes())),
-    );
-    map.insert(
-        "public_key".to_string(),
-        Value::String(hex::encode(verifying_key.to_bytes())),
-    );
+}
 
-    Ok(Value::Struct(map))
+/// sys.random.int(lo, hi) → Integer
+/// Uniform integer in the inclusive range `[lo, hi]`. Not for secrets; use
+/// `sys.crypto.random_bytes` there.
+pub fn intrinsic_random_int(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let (lo, hi) = match args.as_slice() {
+        [Value::Integer(lo), Value::Integer(hi)] => (*lo, *hi),
+        [Value::Integer(_), other] | [other, _] => {
+            return Err(RuntimeError::TypeMismatch(
+                "Integer".to_string(),
+                other.clone(),
+            ));
+        }
+        _ => {
+            return Err(RuntimeError::InvalidOperation(
+                "sys.random.int expects 2 arguments (lo, hi)".into(),
+            ));
+        }
+    };
+    if lo > hi {
+        return Err(RuntimeError::InvalidOperation(format!(
+            "sys.random.int: lo ({}) > hi ({})",
+            lo, hi
+        )));
+    }
+    let offset = match (hi.wrapping_sub(lo) as u64).checked_add(1) {
+        Some(span) => random_below(span),
+        // [i64::MIN, i64::MAX]: every u64 maps to a distinct value
+        None => {
+            let mut bytes = [0u8; 8];
+            random_fill(&mut bytes);
+            u64::from_le_bytes(bytes)
+        }
+    };
+    Ok(Value::Integer(lo.wrapping_add(offset as i64)))
 }
 
-pub fn intrinsic_crypto_ed25519_sign(args: Vec<Value>) -> Result<Value, RuntimeError> {
-    if args.len() != 2 {
-        return Err(RuntimeError::NotExecutable);
+/// sys.random.float() → Integer
+/// Uniform real in [0, 1) as Ark fixed-point (×10000), i.e. `0..=9999`.
+pub fn intrinsic_random_float(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if !args.is_empty() {
+        return Err(RuntimeError::InvalidOperation(
+            "sys.random.float expects no arguments".into(),
+        ));
     }
+    Ok(Value::Integer(random_below(FIXED_POINT_SCALE as u64) as i64))
+}
 
-    let msg_bytes = match &args[0] {
-        Value::String(s) => s.as_bytes(),
-        Value::Buffer(b) => b.as_slice(),
-        _ => {
+/// sys.random.choice(list) → Value
+/// A uniformly chosen element of a non-empty list.
+pub fn intrinsic_random_choice(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let items = match args.as_slice() {
+        [Value::List(items)] => items,
+        [other] => {
             return Err(RuntimeError::TypeMismatch(
-                "String or Buffer".into(),
-                args[0].clone(),
+                "List".to_string(),
+                other.clone(),
+            ));
+        }
+        _ => {
+            return Err(RuntimeError::InvalidOperation(
+                "sys.random.choice expects 1 argument (list)".into(),
             ));
         }
     };
-
-    let key_bytes = get_bytes_from_value(&args[1], "private_key")?;
-
-    if key_bytes.len() != 32 {
+    if items.is_empty() {
         return Err(RuntimeError::InvalidOperation(
-            "Private Key must be 32 bytes".into(),
+            "sys.random.choice: list is empty".into(),
         ));
     }
+    let idx = random_below(items.len() as u64) as usize;
+    Ok(items[idx].clone())
+}
 
-    let signing_key = SigningKey::from_bytes(
-        key_bytes
-            .as_slice()
-            .try_into()
-            .expect("byte array conversion"),
-    );
-    let signature = signing_key.sign(msg_bytes);
-
-    Ok(Value::String(hex::encode(signature.to_bytes())))
+fn format_uuid(bytes: &[u8; 16]) -> String {
+    let hex = hex::encode(bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
 }
 
-pub fn intrinsic_crypto_ed25519_verify(args: Vec<Value>) -> Result<Value, RuntimeError> {
-    if args.len() != 3 {
+/// Random (version 4) UUID as a lowercase hyphenated string.
+pub fn intrinsic_uuid_v4(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if !args.is_empty() {
         return Err(RuntimeError::NotExecutable);
     }
+    let mut bytes = [0u8; 16];
+    random_fill(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40; // version 4
+    bytes[8] = (bytes[8] & 0x3f) | 0x80; // RFC 4122 variant
+    Ok(Value::String(format_uuid(&bytes)))
+}
 
-    let msg_bytes = match &args[0] {
+/// Validate a UUID and normalize it to lowercase hyphenated form. Accepts
+/// the hyphenated or bare 32-digit form, optionally wrapped in braces.
+pub fn intrinsic_uuid_parse(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 1 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let input = match &args[0] {
+        Value::String(s) => s,
+        other => {
+            return Err(RuntimeError::TypeMismatch(
+                "String".to_string(),
+                other.clone(),
+            ));
+        }
+    };
+    let invalid = || RuntimeError::InvalidOperation(format!("Invalid UUID: '{}'", input));
+
+    let trimmed = input.trim();
+    let body = trimmed
+        .strip_prefix('{')
+        .and_then(|s| s.strip_suffix('}'))
+        .unwrap_or(trimmed);
+    let digits = if body.len() == 36 {
+        let groups: Vec<&str> = body.split('-').collect();
+        let lengths: Vec<usize> = groups.iter().map(|g| g.len()).collect();
+        if lengths != [8, 4, 4, 4, 12] {
+            return Err(invalid());
+        }
+        groups.concat()
+    } else if body.len() == 32 {
+        body.to_string()
+    } else {
+        return Err(invalid());
+    };
+
+    let decoded = hex::decode(&digits).map_err(|_| invalid())?;
+    let bytes: [u8; 16] = decoded.try_into().map_err(|_| invalid())?;
+    Ok(Value::String(format_uuid(&bytes)))
+}
+
+// Base58 (Bitcoin alphabet: no 0, O, I or l)
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+fn base58_encode(bytes: &[u8]) -> String {
+    // Each leading zero byte is written as a leading '1'
+    let zeros = bytes.iter().take_while(|&&b| b == 0).count();
+    // Little-endian base-58 digits of the remaining big-endian number
+    let mut digits: Vec<u8> = Vec::with_capacity(bytes.len() * 138 / 100 + 1);
+    for &byte in &bytes[zeros..] {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let mut out = "1".repeat(zeros);
+    out.extend(
+        digits
+            .iter()
+            .rev()
+            .map(|&d| BASE58_ALPHABET[d as usize] as char),
+    );
+    out
+}
+
+fn base58_decode(text: &str) -> Result<Vec<u8>, RuntimeError> {
+    let zeros = text.bytes().take_while(|&c| c == b'1').count();
+    // Little-endian base-256 bytes of the number
+    let mut bytes: Vec<u8> = Vec::with_capacity(text.len());
+    for (pos, c) in text.bytes().enumerate().skip(zeros) {
+        let value = BASE58_ALPHABET
+            .iter()
+            .position(|&a| a == c)
+            .ok_or_else(|| {
+                RuntimeError::InvalidOperation(format!(
+                    "Invalid base58 character '{}' at position {}",
+                    c as char, pos
+                ))
+            })?;
+        let mut carry = value as u32;
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = (carry & 0xff) as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push((carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+    let mut out = vec![0u8; zeros];
+    out.extend(bytes.iter().rev());
+    Ok(out)
+}
+
+/// First 4 bytes of SHA-256(SHA-256(payload)).
+fn base58check_checksum(payload: &[u8]) -> [u8; 4] {
+    let digest = Sha256::digest(Sha256::digest(payload));
+    [digest[0], digest[1], digest[2], digest[3]]
+}
+
+/// Bytes of a Buffer argument (a String is taken as its UTF-8 bytes).
+fn bytes_arg(args: &[Value]) -> Result<Vec<u8>, RuntimeError> {
+    match args.first() {
+        Some(Value::Buffer(b)) => Ok(b.clone()),
+        Some(Value::String(s)) => Ok(s.as_bytes().to_vec()),
+        Some(other) => Err(RuntimeError::TypeMismatch(
+            "Buffer".to_string(),
+            other.clone(),
+        )),
+        None => Err(RuntimeError::NotExecutable),
+    }
+}
+
+fn string_arg(args: &[Value]) -> Result<&str, RuntimeError> {
+    match args.first() {
+        Some(Value::String(s)) => Ok(s),
+        Some(other) => Err(RuntimeError::TypeMismatch(
+            "String".to_string(),
+            other.clone(),
+        )),
+        None => Err(RuntimeError::NotExecutable),
+    }
+}
+
+/// Encode a Buffer as a base58 string.
+pub fn intrinsic_encode_base58(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 1 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    Ok(Value::String(base58_encode(&bytes_arg(&args)?)))
+}
+
+/// Decode a base58 string into a Buffer.
+pub fn intrinsic_decode_base58(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 1 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    Ok(Value::Buffer(base58_decode(string_arg(&args)?)?))
+}
+
+/// Encode a Buffer as base58 with a trailing 4-byte double-SHA-256 checksum.
+pub fn intrinsic_encode_base58check(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 1 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let mut payload = bytes_arg(&args)?;
+    let checksum = base58check_checksum(&payload);
+    payload.extend_from_slice(&checksum);
+    Ok(Value::String(base58_encode(&payload)))
+}
+
+/// Decode a base58check string, verifying and stripping its checksum.
+pub fn intrinsic_decode_base58check(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 1 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let mut payload = base58_decode(string_arg(&args)?)?;
+    if payload.len() < 4 {
+        return Err(RuntimeError::InvalidOperation(
+            "base58check input is too short to hold a checksum".to_string(),
+        ));
+    }
+    let checksum = payload.split_off(payload.len() - 4);
+    if checksum != base58check_checksum(&payload) {
+        return Err(RuntimeError::InvalidOperation(
+            "base58check checksum mismatch".to_string(),
+        ));
+    }
+    Ok(Value::Buffer(payload))
+}
+
+pub fn intrinsic_crypto_ed25519_generate(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if !args.is_empty() {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let mut csprng = OsRng;
+    let signing_key = SigningKey::generate(&mut csprng);
+    let verifying_key = signing_key.verifying_key();
+
+    let mut map = std::collections::HashMap::new();
+    map.insert(
+        "private_key".to_string(),
+        Value::String(hex::encode(signing_key.to_bytes())),
+    );
+    map.insert(
+        "public_key".to_string(),
+        Value::String(hex::encode(verifying_key.to_bytes())),
+    );
+
+    Ok(Value::Struct(map))
+}
+
+pub fn intrinsic_crypto_ed25519_sign(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 2 {
+        return Err(RuntimeError::NotExecutable);
+    }
+
+    let msg_bytes = match &args[0] {
+        Value::String(s) => s.as_bytes(),
+        Value::Buffer(b) => b.as_slice(),
+        _ => {
+            return Err(RuntimeError::TypeMismatch(
+                "String or Buffer".into(),
+                args[0].clone(),
+            ));
+        }
+    };
+
+    let key_bytes = get_bytes_from_value(&args[1], "private_key")?;
+
+    if key_bytes.len() != 32 {
+        return Err(RuntimeError::InvalidOperation(
+            "Private Key must be 32 bytes".into(),
+        ));
+    }
+
+    let signing_key = SigningKey::from_bytes(
+        key_bytes
+            .as_slice()
+            .try_into()
+            .expect("byte array conversion"),
+    );
+    let signature = signing_key.sign(msg_bytes);
+
+    Ok(Value::String(hex::encode(signature.to_bytes())))
+}
+
+pub fn intrinsic_crypto_ed25519_verify(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 3 {
+        return Err(RuntimeError::NotExecutable);
+    }
+
+    let msg_bytes = match &args[0] {
         Value::String(s) => s.as_bytes(),
         Value::Buffer(b) => b.as_slice(),
         _ => {
@@ -1973,12 +3649,34 @@ pub fn intrinsic_buffer_read(args: Vec<Value>) -> Result<Value, RuntimeError> {
     match buf_val {
         Value::Buffer(b) => {
             if index < 0 || index >= b.len() as i64 {
-                return Err(RuntimeError::NotExecutable);
+                return Err(RuntimeError::IndexOutOfBounds {
+                    index: index,
+                    len: b.len(),
+                });
             }
             let val = b[index as usize] as i64;
             let list = vec![Value::Integer(val), Value::Buffer(b)];
             Ok(Value::List(list))
         }
+        Value::BufferView {
+            backing,
+            offset,
+            len,
+        } => {
+            if index < 0 || index >= len as i64 {
+                return Err(RuntimeError::IndexOutOfBounds {
+                    index: index,
+                    len: len,
+                });
+            }
+            let val = backing.read().unwrap_or_else(|e| e.into_inner())[offset + index as usize];
+            let view = Value::BufferView {
+                backing,
+                offset,
+                len,
+            };
+            Ok(Value::List(vec![Value::Integer(val as i64), view]))
+        }
         v => Err(RuntimeError::TypeMismatch("Buffer".to_string(), v)),
     }
 }
@@ -2021,15 +3719,100 @@ pub fn intrinsic_buffer_write(args: Vec<Value>) -> Result<Value, RuntimeError> {
     match buf_val {
         Value::Buffer(mut b) => {
             if index >= b.len() {
-                return Err(RuntimeError::NotExecutable);
+                return Err(RuntimeError::IndexOutOfBounds {
+                    index: index as i64,
+                    len: b.len(),
+                });
             }
             b[index] = byte_val;
             Ok(Value::Buffer(b)) // Return modified buffer (Linear Threading)
         }
+        // Writes land in the shared backing, so other views of it see them
+        Value::BufferView {
+            backing,
+            offset,
+            len,
+        } => {
+            if index >= len {
+                return Err(RuntimeError::IndexOutOfBounds {
+                    index: index as i64,
+                    len: len,
+                });
+            }
+            backing.write().unwrap_or_else(|e| e.into_inner())[offset + index] = byte_val;
+            Ok(Value::BufferView {
+                backing,
+                offset,
+                len,
+            })
+        }
         _ => Err(RuntimeError::TypeMismatch("Buffer".to_string(), buf_val)),
     }
 }
 
+/// sys.mem.view(buf, offset, len) → BufferView
+/// O(1) window over `buf` without copying. A plain buffer is moved into
+/// shared storage; viewing a view narrows it over the same backing. Every
+/// view of one backing aliases, so writes through any of them are visible
+/// to all.
+pub fn intrinsic_buffer_view(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 3 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let mut args = args;
+    let len_val = args
+        .pop()
+        .ok_or_else(|| RuntimeError::TypeMismatch("missing argument".into(), Value::Unit))?;
+    let offset_val = args
+        .pop()
+        .ok_or_else(|| RuntimeError::TypeMismatch("missing argument".into(), Value::Unit))?;
+    let buf_val = args
+        .pop()
+        .ok_or_else(|| RuntimeError::TypeMismatch("missing argument".into(), Value::Unit))?;
+
+    let (start, count) = match (&offset_val, &len_val) {
+        (Value::Integer(o), Value::Integer(l)) if *o >= 0 && *l >= 0 => (*o as usize, *l as usize),
+        (Value::Integer(_), Value::Integer(_)) => {
+            return Err(RuntimeError::InvalidOperation(
+                "sys.mem.view: offset and len must be non-negative".into(),
+            ));
+        }
+        (Value::Integer(_), _) => {
+            return Err(RuntimeError::TypeMismatch("Integer".to_string(), len_val));
+        }
+        _ => {
+            return Err(RuntimeError::TypeMismatch(
+                "Integer".to_string(),
+                offset_val,
+            ));
+        }
+    };
+
+    let (backing, base, available) = match buf_val {
+        Value::Buffer(b) => {
+            let available = b.len();
+            (Arc::new(RwLock::new(b)), 0, available)
+        }
+        Value::BufferView {
+            backing,
+            offset,
+            len,
+        } => (backing, offset, len),
+        v => return Err(RuntimeError::TypeMismatch("Buffer".to_string(), v)),
+    };
+    if !start.checked_add(count).is_some_and(|end| end <= available) {
+        return Err(RuntimeError::InvalidOperation(format!(
+            "sys.mem.view: range {}..{}+{} exceeds buffer of {} bytes",
+            start, start, count, available
+        )));
+    }
+    Ok(Value::BufferView {
+        backing,
+        offset: base + start,
+        len: count,
+    })
+}
+
 pub fn intrinsic_list_get(args: Vec<Value>) -> Result<Value, RuntimeError> {
     if args.len() != 2 {
         return Err(RuntimeError::NotExecutable);
@@ -2050,7 +3833,10 @@ pub fn intrinsic_list_get(args: Vec<Value>) -> Result<Value, RuntimeError> {
     match list_val {
         Value::List(list) => {
             if index < 0 || index >= list.len() as i64 {
-                return Err(RuntimeError::NotExecutable);
+                return Err(RuntimeError::IndexOutOfBounds {
+                    index: index,
+                    len: list.len(),
+                });
             }
             let val = list[index as usize].clone();
             let new_list_val = Value::List(list);
@@ -2059,7 +3845,10 @@ pub fn intrinsic_list_get(args: Vec<Value>) -> Result<Value, RuntimeError> {
         }
         Value::String(s) => {
             if index < 0 || index >= s.len() as i64 {
-                return Err(RuntimeError::NotExecutable);
+                return Err(RuntimeError::IndexOutOfBounds {
+                    index: index,
+                    len: s.len(),
+                });
             }
             // Unicode safety: chars().nth() is O(N). optimized: as_bytes?
             // Ark strings are UTF-8. Indexing by byte or char?
@@ -2143,7 +3932,10 @@ pub fn intrinsic_list_pop(args: Vec<Value>) -> Result<Value, RuntimeError> {
             };
 
             if index < 0 || index >= list.len() as i64 {
-                return Err(RuntimeError::NotExecutable);
+                return Err(RuntimeError::IndexOutOfBounds {
+                    index: index,
+                    len: list.len(),
+                });
             }
 
             // Linear Pop: Remove element.
@@ -2174,7 +3966,10 @@ pub fn intrinsic_list_delete(args: Vec<Value>) -> Result<Value, RuntimeError> {
     match list_val {
         Value::List(mut list) => {
             if index < 0 || index >= list.len() as i64 {
-                return Err(RuntimeError::NotExecutable);
+                return Err(RuntimeError::IndexOutOfBounds {
+                    index: index,
+                    len: list.len(),
+                });
             }
             list.remove(index as usize);
             Ok(Value::List(list))
@@ -2183,6 +3978,90 @@ pub fn intrinsic_list_delete(args: Vec<Value>) -> Result<Value, RuntimeError> {
     }
 }
 
+/// Pair up two lists element-wise: `zip([1, 2, 3], ["a", "b"])` is
+/// `[[1, "a"], [2, "b"]]`. The result is as long as the shorter list.
+pub fn intrinsic_list_zip(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 2 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let mut args = args;
+    let right = args
+        .pop()
+        .ok_or_else(|| RuntimeError::TypeMismatch("missing argument".into(), Value::Unit))?;
+    let left = args
+        .pop()
+        .ok_or_else(|| RuntimeError::TypeMismatch("missing argument".into(), Value::Unit))?;
+
+    match (left, right) {
+        (Value::List(a), Value::List(b)) => Ok(Value::List(
+            a.into_iter()
+                .zip(b)
+                .map(|(x, y)| Value::List(vec![x, y]))
+                .collect(),
+        )),
+        (Value::List(_), other) | (other, _) => {
+            Err(RuntimeError::TypeMismatch("List".to_string(), other))
+        }
+    }
+}
+
+/// Pair each element with its index: `enumerate(["a", "b"])` is
+/// `[[0, "a"], [1, "b"]]`.
+pub fn intrinsic_list_enumerate(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 1 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    match args.into_iter().next() {
+        Some(Value::List(list)) => Ok(Value::List(
+            list.into_iter()
+                .enumerate()
+                .map(|(i, v)| Value::List(vec![Value::Integer(i as i64), v]))
+                .collect(),
+        )),
+        Some(other) => Err(RuntimeError::TypeMismatch("List".to_string(), other)),
+        None => Err(RuntimeError::NotExecutable),
+    }
+}
+
+/// Shared argument handling for `chunk` / `window`: a list and a size >= 1.
+fn list_and_size(args: Vec<Value>, op: &str) -> Result<(Vec<Value>, usize), RuntimeError> {
+    if args.len() != 2 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let mut args = args.into_iter();
+    let list = match args.next() {
+        Some(Value::List(list)) => list,
+        Some(other) => return Err(RuntimeError::TypeMismatch("List".to_string(), other)),
+        None => return Err(RuntimeError::NotExecutable),
+    };
+    match args.next() {
+        Some(Value::Integer(n)) if n > 0 => Ok((list, n as usize)),
+        Some(Value::Integer(n)) => Err(RuntimeError::InvalidOperation(format!(
+            "{}: size must be positive, got {}",
+            op, n
+        ))),
+        Some(other) => Err(RuntimeError::TypeMismatch("Integer".to_string(), other)),
+        None => Err(RuntimeError::NotExecutable),
+    }
+}
+
+/// `sys.list.chunk(list, n)`: consecutive n-sized sublists; the last one may
+/// be shorter.
+pub fn intrinsic_list_chunk(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let (list, n) = list_and_size(args, "sys.list.chunk")?;
+    Ok(Value::List(
+        list.chunks(n).map(|c| Value::List(c.to_vec())).collect(),
+    ))
+}
+
+/// `sys.list.window(list, n)`: every sliding window of exactly n elements.
+pub fn intrinsic_list_window(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let (list, n) = list_and_size(args, "sys.list.window")?;
+    Ok(Value::List(
+        list.windows(n).map(|w| Value::List(w.to_vec())).collect(),
+    ))
+}
+
 pub fn intrinsic_struct_has(args: Vec<Value>) -> Result<Value, RuntimeError> {
     if args.len() != 2 {
         return Err(RuntimeError::NotExecutable);
@@ -2298,12 +4177,43 @@ pub fn intrinsic_len(args: Vec<Value>) -> Result<Value, RuntimeError> {
         Value::String(s) => s.len() as i64,
         Value::List(l) => l.len() as i64,
         Value::Buffer(b) => b.len() as i64,
+        Value::BufferView { len, .. } => *len as i64,
         _ => return Err(RuntimeError::TypeMismatch("Sequence".to_string(), val)),
     };
 
     Ok(Value::List(vec![Value::Integer(len), val]))
 }
 
+/// sys.type_of(value) -> String
+/// Returns the runtime type tag of `value`.
+pub fn intrinsic_type_of(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 1 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    Ok(Value::String(type_tag(&args[0]).to_string()))
+}
+
+fn type_tag(val: &Value) -> &'static str {
+    match val {
+        Value::Integer(_) => "integer",
+        Value::String(_) => "string",
+        Value::Boolean(_) => "boolean",
+        Value::Unit => "unit",
+        Value::LinearObject { .. } => "linear",
+        Value::Function(_) | Value::NativeFunction(_) => "function",
+        Value::List(_) => "list",
+        Value::Buffer(_) | Value::BufferView { .. } => "buffer",
+        Value::Struct(_) => "struct",
+        Value::PVec(_) => "pvec",
+        Value::PMap(_) => "pmap",
+        Value::BigInt(_) => "bigint",
+        Value::Decimal(_) => "decimal",
+        Value::Tensor(_) => "tensor",
+        Value::Return(inner) => type_tag(inner),
+        Value::EnumValue { .. } => "enum",
+    }
+}
+
 pub fn intrinsic_struct_get(args: Vec<Value>) -> Result<Value, RuntimeError> {
     if args.len() != 2 {
         return Err(RuntimeError::NotExecutable);
@@ -2364,7 +4274,10 @@ pub fn intrinsic_list_set(args: Vec<Value>) -> Result<Value, RuntimeError> {
     match list_val {
         Value::List(mut list) => {
             if index < 0 || index >= list.len() as i64 {
-                return Err(RuntimeError::NotExecutable);
+                return Err(RuntimeError::IndexOutOfBounds {
+                    index: index,
+                    len: list.len(),
+                });
             }
             list[index as usize] = val;
             Ok(Value::List(list))
@@ -2409,7 +4322,24 @@ pub fn intrinsic_struct_set(args: Vec<Value>) -> Result<Value, RuntimeError> {
     }
 }
 
+thread_local! {
+    /// Logical clock replacing the wall clock, enabled by
+    /// `set_deterministic_clock`.
+    static DETERMINISTIC_CLOCK: std::cell::Cell<Option<i64>> =
+        const { std::cell::Cell::new(None) };
+}
+
+/// Switch the current thread's `sys.time.now` to a logical clock that
+/// starts at `start` (ms since the epoch) and that only `sys.time.sleep`
+/// advances, without sleeping; or back to the wall clock with `None`.
+pub fn set_deterministic_clock(start: Option<i64>) {
+    DETERMINISTIC_CLOCK.with(|clock| clock.set(start));
+}
+
 pub fn intrinsic_time_now(_args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if let Some(now) = DETERMINISTIC_CLOCK.with(|clock| clock.get()) {
+        return Ok(Value::Integer(now));
+    }
     let start = SystemTime::now();
     let since_the_epoch = start
         .duration_since(UNIX_EPOCH)
@@ -2439,6 +4369,12 @@ pub fn intrinsic_time_sleep(args: Vec<Value>) -> Result<Value, RuntimeError> {
         }
     };
 
+    if let Some(now) = DETERMINISTIC_CLOCK.with(|clock| clock.get()) {
+        let later = now.saturating_add(duration_ms as i64);
+        DETERMINISTIC_CLOCK.with(|clock| clock.set(Some(later)));
+        return Ok(Value::Unit);
+    }
+
     #[cfg(target_arch = "wasm32")]
     {
         // In WASM, blocking sleep is generally not supported or freezes the browser.
@@ -2553,29 +4489,127 @@ pub fn intrinsic_math_abs(args: Vec<Value>) -> Result<Value, RuntimeError> {
     }
 }
 
-/// GCD normalize: clamp each value in a list to [epsilon, 10000-epsilon].
-/// Args: (list: List<Integer>, epsilon: Integer)
-/// Returns: new List with clamped values.
-pub fn intrinsic_gcd_normalize(args: Vec<Value>) -> Result<Value, RuntimeError> {
-    if args.len() != 2 {
+/// Scale of Ark's fixed-point reals (4 decimal places).
+const FIXED_POINT_SCALE: i64 = 10000;
+
+fn fixed_point_arg(args: &[Value]) -> Result<i64, RuntimeError> {
+    match args.first() {
+        Some(Value::Integer(n)) => Ok(*n),
+        Some(other) => Err(RuntimeError::TypeMismatch(
+            "Integer".to_string(),
+            other.clone(),
+        )),
+        None => Err(RuntimeError::NotExecutable),
+    }
+}
+
+/// Divide `n` by `step`, rounding halfway cases away from zero.
+fn div_round_half_away(n: i64, step: i64) -> i64 {
+    let (q, r) = (n / step, n % step);
+    if r.unsigned_abs() * 2 >= step.unsigned_abs() {
+        q + n.signum()
+    } else {
+        q
+    }
+}
+
+/// Round to the nearest whole number. Input is Ark fixed-point (×10000);
+/// returns a plain integer. Midpoints round away from zero, so
+/// `round(25000) == 3` and `round(-25000) == -3`.
+pub fn intrinsic_math_round(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 1 {
         return Err(RuntimeError::NotExecutable);
     }
-    let epsilon = match &args[1] {
-        Value::Integer(e) => *e,
-        _ => {
-            return Err(RuntimeError::TypeMismatch(
-                "Integer".to_string(),
-                args[1].clone(),
-            ));
-        }
-    };
-    match &args[0] {
-        Value::List(items) => {
-            let clamped: Vec<Value> = items
-                .iter()
-                .map(|v| match v {
-                    Value::Integer(n) => {
-                        let c = (*n).max(epsilon).min(10000 - epsilon);
+    let n = fixed_point_arg(&args)?;
+    Ok(Value::Integer(div_round_half_away(n, FIXED_POINT_SCALE)))
+}
+
+/// Largest whole number <= x. Input is Ark fixed-point (×10000); returns a
+/// plain integer.
+pub fn intrinsic_math_floor(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 1 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let n = fixed_point_arg(&args)?;
+    Ok(Value::Integer(n.div_euclid(FIXED_POINT_SCALE)))
+}
+
+/// Smallest whole number >= x. Input is Ark fixed-point (×10000); returns a
+/// plain integer.
+pub fn intrinsic_math_ceil(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 1 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let n = fixed_point_arg(&args)?;
+    let floor = n.div_euclid(FIXED_POINT_SCALE);
+    let ceil = if n.rem_euclid(FIXED_POINT_SCALE) == 0 {
+        floor
+    } else {
+        floor + 1
+    };
+    Ok(Value::Integer(ceil))
+}
+
+/// Whole part of x, rounding toward zero. Input is Ark fixed-point
+/// (×10000); returns a plain integer.
+pub fn intrinsic_math_trunc(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 1 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let n = fixed_point_arg(&args)?;
+    Ok(Value::Integer(n / FIXED_POINT_SCALE))
+}
+
+/// Round x to `decimals` places (0..=4). Input and result are Ark
+/// fixed-point (×10000), so `round_to(31416, 2) == 31400`. Midpoints round
+/// away from zero, as in `math.round`.
+pub fn intrinsic_math_round_to(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 2 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let n = fixed_point_arg(&args)?;
+    let decimals = match &args[1] {
+        Value::Integer(d) if (0..=4).contains(d) => *d as u32,
+        Value::Integer(d) => {
+            return Err(RuntimeError::InvalidOperation(format!(
+                "math.round_to: decimals must be between 0 and 4, got {}",
+                d
+            )));
+        }
+        other => {
+            return Err(RuntimeError::TypeMismatch(
+                "Integer".to_string(),
+                other.clone(),
+            ));
+        }
+    };
+    let step = 10i64.pow(4 - decimals);
+    Ok(Value::Integer(div_round_half_away(n, step) * step))
+}
+
+/// GCD normalize: clamp each value in a list to [epsilon, 10000-epsilon].
+/// Args: (list: List<Integer>, epsilon: Integer)
+/// Returns: new List with clamped values.
+pub fn intrinsic_gcd_normalize(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 2 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let epsilon = match &args[1] {
+        Value::Integer(e) => *e,
+        _ => {
+            return Err(RuntimeError::TypeMismatch(
+                "Integer".to_string(),
+                args[1].clone(),
+            ));
+        }
+    };
+    match &args[0] {
+        Value::List(items) => {
+            let clamped: Vec<Value> = items
+                .iter()
+                .map(|v| match v {
+                    Value::Integer(n) => {
+                        let c = (*n).max(epsilon).min(10000 - epsilon);
                         Value::Integer(c)
                     }
                     other => other.clone(),
@@ -2751,7 +4785,7 @@ pub fn intrinsic_io_read_bytes(args: Vec<Value>) -> Result<Value, RuntimeError>
     {
         // Security: Path Traversal Check
         let safe_path = validate_safe_path(path_str)?;
-        let content = fs::read(safe_path).map_err(|_| RuntimeError::NotExecutable)?;
+        let content = fs::read(safe_path).map_err(RuntimeError::from)?;
 
         // Convert Vec<u8> to Vec<Value> (List of Integers)
         let list = content
@@ -2775,7 +4809,7 @@ pub fn intrinsic_io_read_line(args: Vec<Value>) -> Result<Value, RuntimeError> {
         let mut input = String::new();
         io::stdin()
             .read_line(&mut input)
-            .map_err(|_| RuntimeError::NotExecutable)?;
+            .map_err(RuntimeError::from)?;
         // Trim newline
         if input.ends_with('\n') {
             input.pop();
@@ -2804,9 +4838,7 @@ pub fn intrinsic_io_write(args: Vec<Value>) -> Result<Value, RuntimeError> {
     print!("{}", s);
     #[cfg(not(target_arch = "wasm32"))]
     {
-        io::stdout()
-            .flush()
-            .map_err(|_| RuntimeError::NotExecutable)?;
+        io::stdout().flush().map_err(RuntimeError::from)?;
     }
     Ok(Value::Unit)
 }
@@ -2860,6 +4892,159 @@ pub fn intrinsic_chain_verify_tx(args: Vec<Value>) -> Result<Value, RuntimeError
     }
 }
 
+/// The handle id of a `sys.fs.open` file handle.
+#[cfg(not(target_arch = "wasm32"))]
+fn file_handle_id(name: &str, handle: &Value) -> Result<i64, RuntimeError> {
+    match handle {
+        Value::LinearObject { id, typename, .. } if typename == "File" => id
+            .parse()
+            .map_err(|_| RuntimeError::InvalidOperation(format!("{}: bad file handle", name))),
+        other => Err(RuntimeError::TypeMismatch(
+            "File".to_string(),
+            other.clone(),
+        )),
+    }
+}
+
+/// A non-negative integer argument of a `sys.fs` streaming call.
+#[cfg(not(target_arch = "wasm32"))]
+fn file_offset_arg(name: &str, val: &Value) -> Result<u64, RuntimeError> {
+    match val {
+        Value::Integer(n) if *n >= 0 => Ok(*n as u64),
+        Value::Integer(n) => Err(RuntimeError::InvalidOperation(format!(
+            "{}: expected a non-negative integer, got {}",
+            name, n
+        ))),
+        other => Err(RuntimeError::TypeMismatch(
+            "Integer".to_string(),
+            other.clone(),
+        )),
+    }
+}
+
+/// sys.fs.open(path) → File
+/// Opens a file for streaming reads (same jail as `sys.fs.read`). The
+/// handle is a linear `File` object: `sys.fs.read_chunk` and `sys.fs.seek`
+/// hand it back, and `sys.fs.close` consumes it.
+pub fn intrinsic_fs_open(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let path = match args.as_slice() {
+            [Value::String(s)] => s.clone(),
+            [other] => {
+                return Err(RuntimeError::TypeMismatch(
+                    "String".to_string(),
+                    other.clone(),
+                ));
+            }
+            _ => {
+                return Err(RuntimeError::InvalidOperation(
+                    "sys.fs.open expects 1 argument (path)".into(),
+                ));
+            }
+        };
+        check_path_security(&path, false)?;
+        let safe_path = validate_safe_path(&path)?;
+        let file = fs::File::open(safe_path)
+            .map_err(|e| RuntimeError::InvalidOperation(format!("{}: {}", path, e)))?;
+        let id = FILE_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
+        get_files()
+            .lock()
+            .map_err(|e| RuntimeError::InvalidOperation(format!("file mutex poisoned: {}", e)))?
+            .insert(id, file);
+        Ok(Value::LinearObject {
+            id: id.to_string(),
+            typename: "File".to_string(),
+            payload: path,
+        })
+    }
+}
+
+/// sys.fs.read_chunk(file, max_bytes) → [chunk, file]
+/// Reads up to `max_bytes` from the current position; the chunk is shorter
+/// only at end of file, and empty once there. Bytes that are not valid
+/// UTF-8 (including a character split across chunks) read as U+FFFD.
+pub fn intrinsic_fs_read_chunk(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let [handle, max] = args.as_slice() else {
+            return Err(RuntimeError::InvalidOperation(
+                "sys.fs.read_chunk expects 2 arguments (file, max_bytes)".into(),
+            ));
+        };
+        let id = file_handle_id("sys.fs.read_chunk", handle)?;
+        let max = file_offset_arg("sys.fs.read_chunk", max)?;
+        let mut files = get_files()
+            .lock()
+            .map_err(|e| RuntimeError::InvalidOperation(format!("file mutex poisoned: {}", e)))?;
+        let file = files.get_mut(&id).ok_or_else(|| {
+            RuntimeError::InvalidOperation("sys.fs.read_chunk: file is closed".into())
+        })?;
+        let mut buf = Vec::new();
+        file.take(max)
+            .read_to_end(&mut buf)
+            .map_err(|e| RuntimeError::InvalidOperation(format!("sys.fs.read_chunk: {}", e)))?;
+        let chunk = String::from_utf8_lossy(&buf).into_owned();
+        Ok(Value::List(vec![Value::String(chunk), handle.clone()]))
+    }
+}
+
+/// sys.fs.seek(file, offset) → file
+/// Moves the read position to byte `offset` from the start of the file.
+pub fn intrinsic_fs_seek(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let [handle, offset] = args.as_slice() else {
+            return Err(RuntimeError::InvalidOperation(
+                "sys.fs.seek expects 2 arguments (file, offset)".into(),
+            ));
+        };
+        let id = file_handle_id("sys.fs.seek", handle)?;
+        let offset = file_offset_arg("sys.fs.seek", offset)?;
+        let mut files = get_files()
+            .lock()
+            .map_err(|e| RuntimeError::InvalidOperation(format!("file mutex poisoned: {}", e)))?;
+        let file = files
+            .get_mut(&id)
+            .ok_or_else(|| RuntimeError::InvalidOperation("sys.fs.seek: file is closed".into()))?;
+        file.seek(SeekFrom::Start(offset))
+            .map_err(|e| RuntimeError::InvalidOperation(format!("sys.fs.seek: {}", e)))?;
+        Ok(handle.clone())
+    }
+}
+
+/// sys.fs.close(file) → Unit
+/// Closes a file opened with `sys.fs.open`.
+pub fn intrinsic_fs_close(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let [handle] = args.as_slice() else {
+            return Err(RuntimeError::InvalidOperation(
+                "sys.fs.close expects 1 argument (file)".into(),
+            ));
+        };
+        let id = file_handle_id("sys.fs.close", handle)?;
+        get_files()
+            .lock()
+            .map_err(|e| RuntimeError::InvalidOperation(format!("file mutex poisoned: {}", e)))?
+            .remove(&id)
+            .ok_or_else(|| RuntimeError::InvalidOperation("sys.fs.close: file is closed".into()))?;
+        Ok(Value::Unit)
+    }
+}
+
 pub fn intrinsic_fs_write_buffer(args: Vec<Value>) -> Result<Value, RuntimeError> {
     if args.len() != 2 {
         return Err(RuntimeError::NotExecutable);
@@ -2896,7 +5081,7 @@ pub fn intrinsic_fs_write_buffer(args: Vec<Value>) -> Result<Value, RuntimeError
                     );
                 }
                 println!("[Ark:FS] Writing buffer to {}", path_str);
-                fs::write(path_str, buf).map_err(|_| RuntimeError::NotExecutable)?;
+                fs::write(path_str, buf).map_err(RuntimeError::from)?;
                 Ok(Value::Unit)
             }
         }
@@ -2933,7 +5118,7 @@ pub fn intrinsic_fs_read_buffer(args: Vec<Value>) -> Result<Value, RuntimeError>
         println!("[Ark:FS] Reading buffer from {}", path_str);
         // Security: Path Traversal Check
         let safe_path = validate_safe_path(path_str)?;
-        let content = fs::read(safe_path).map_err(|_| RuntimeError::NotExecutable)?;
+        let content = fs::read(safe_path).map_err(RuntimeError::from)?;
         Ok(Value::Buffer(content))
     }
 }
@@ -3058,6 +5243,109 @@ pub fn intrinsic_str_from_code(args: Vec<Value>) -> Result<Value, RuntimeError>
     }
 }
 
+/// The single `String` argument of the UTF-8 string intrinsic `name`.
+fn string_arg<'a>(name: &str, args: &'a [Value]) -> Result<&'a str, RuntimeError> {
+    match args.first() {
+        Some(Value::String(s)) => Ok(s),
+        Some(other) => Err(RuntimeError::TypeMismatch(
+            "String".to_string(),
+            other.clone(),
+        )),
+        None => Err(RuntimeError::InvalidOperation(format!(
+            "{}: missing string argument",
+            name
+        ))),
+    }
+}
+
+/// Character index argument `i` of `name`, checked against `0..=limit`.
+fn char_index_arg(
+    name: &str,
+    args: &[Value],
+    i: usize,
+    limit: usize,
+) -> Result<usize, RuntimeError> {
+    match args.get(i) {
+        Some(Value::Integer(n)) if *n >= 0 && *n as usize <= limit => Ok(*n as usize),
+        Some(Value::Integer(n)) => Err(RuntimeError::InvalidOperation(format!(
+            "{}: index {} out of range for {} characters",
+            name, n, limit
+        ))),
+        Some(other) => Err(RuntimeError::TypeMismatch(
+            "Integer".to_string(),
+            other.clone(),
+        )),
+        None => Err(RuntimeError::NotExecutable),
+    }
+}
+
+/// `string_char_len(s)`: the number of characters (not bytes) in `s`.
+pub fn intrinsic_string_char_len(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 1 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let s = string_arg("string_char_len", &args)?;
+    Ok(Value::Integer(s.chars().count() as i64))
+}
+
+/// `string_char_at(s, i)`: character `i` of `s` as a one-character string.
+pub fn intrinsic_string_char_at(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 2 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let s = string_arg("string_char_at", &args)?;
+    let count = s.chars().count();
+    let index = char_index_arg("string_char_at", &args, 1, count)?;
+    match s.chars().nth(index) {
+        Some(c) => Ok(Value::String(c.to_string())),
+        None => Err(RuntimeError::InvalidOperation(format!(
+            "string_char_at: index {} out of range for {} characters",
+            index, count
+        ))),
+    }
+}
+
+/// `string_slice_chars(s, start, end)`: the characters of `s` in
+/// `start..end`, counted in characters rather than bytes.
+pub fn intrinsic_string_slice_chars(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 3 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let s = string_arg("string_slice_chars", &args)?;
+    let count = s.chars().count();
+    let start = char_index_arg("string_slice_chars", &args, 1, count)?;
+    let end = char_index_arg("string_slice_chars", &args, 2, count)?;
+    if start > end {
+        return Err(RuntimeError::InvalidOperation(format!(
+            "string_slice_chars: start {} is after end {}",
+            start, end
+        )));
+    }
+    Ok(Value::String(
+        s.chars().skip(start).take(end - start).collect(),
+    ))
+}
+
+/// `string_to_upper(s)`: `s` with every character upper-cased.
+pub fn intrinsic_string_to_upper(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 1 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    Ok(Value::String(
+        string_arg("string_to_upper", &args)?.to_uppercase(),
+    ))
+}
+
+/// `string_to_lower(s)`: `s` with every character lower-cased.
+pub fn intrinsic_string_to_lower(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 1 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    Ok(Value::String(
+        string_arg("string_to_lower", &args)?.to_lowercase(),
+    ))
+}
+
 pub fn intrinsic_extract_code(args: Vec<Value>) -> Result<Value, RuntimeError> {
     if args.len() != 1 {
         return Err(RuntimeError::NotExecutable);
@@ -3160,7 +5448,21 @@ pub fn intrinsic_http_request(args: Vec<Value>) -> Result<Value, RuntimeError> {
             }
         };
 
-        let request = ureq::request(method, url);
+        if let Some((host, port)) = url_host_port(url) {
+            check_net_capability(&host, port)?;
+        }
+
+        // ureq's native-tls feature does not install a connector by itself;
+        // without one every https:// request fails before reaching the wire.
+        let agent = HTTP_AGENT.get_or_init(|| {
+            let builder = ureq::AgentBuilder::new();
+            match native_tls::TlsConnector::new() {
+                Ok(tls) => builder.tls_connector(std::sync::Arc::new(tls)),
+                Err(_) => builder,
+            }
+            .build()
+        });
+        let request = agent.request(method, url);
 
         let result = if args.len() > 2 {
             match &args[2] {
@@ -3184,9 +5486,7 @@ pub fn intrinsic_http_request(args: Vec<Value>) -> Result<Value, RuntimeError> {
                     headers_map.insert(name, Value::String(value.to_string()));
                 }
             }
-            let body = response
-                .into_string()
-                .map_err(|_| RuntimeError::NotExecutable)?;
+            let body = response.into_string().map_err(RuntimeError::from)?;
             let mut resp_struct = HashMap::new();
             resp_struct.insert("status".to_string(), Value::Integer(status));
             resp_struct.insert("body".to_string(), Value::String(body));
@@ -3197,12 +5497,65 @@ pub fn intrinsic_http_request(args: Vec<Value>) -> Result<Value, RuntimeError> {
         match result {
             Ok(response) => handle_response(response),
             Err(ureq::Error::Status(_code, response)) => handle_response(response),
-            Err(e) => {
-                // Transport error
-                Err(RuntimeError::InvalidOperation(format!("HTTP Error: {}", e)))
+            Err(ureq::Error::Transport(t)) => Err(network_error_from_ureq(&t)),
+        }
+    }
+}
+
+/// Walk an error's source chain looking for a recognisable network cause.
+#[cfg(not(target_arch = "wasm32"))]
+fn classify_network_error(err: &(dyn std::error::Error + 'static)) -> NetworkErrorKind {
+    let mut current = Some(err);
+    while let Some(e) = current {
+        if let Some(io_err) = e.downcast_ref::<io::Error>() {
+            match io_err.kind() {
+                io::ErrorKind::ConnectionRefused => return NetworkErrorKind::ConnectionRefused,
+                io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => {
+                    return NetworkErrorKind::Timeout;
+                }
+                _ => {}
             }
         }
+        let msg = e.to_string().to_lowercase();
+        if msg.contains("dns") || msg.contains("lookup address") {
+            return NetworkErrorKind::DnsError;
+        }
+        if msg.contains("tls") || msg.contains("ssl") || msg.contains("certificate") {
+            return NetworkErrorKind::TlsError;
+        }
+        if msg.contains("timed out") {
+            return NetworkErrorKind::Timeout;
+        }
+        if msg.contains("connection refused") {
+            return NetworkErrorKind::ConnectionRefused;
+        }
+        current = e.source();
     }
+    NetworkErrorKind::Other
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn network_error_from_ureq(err: &ureq::Transport) -> RuntimeError {
+    let kind = match err.kind() {
+        ureq::ErrorKind::Dns => NetworkErrorKind::DnsError,
+        ureq::ErrorKind::InvalidUrl | ureq::ErrorKind::UnknownScheme => {
+            NetworkErrorKind::InvalidUrl
+        }
+        _ => classify_network_error(err),
+    };
+    RuntimeError::NetworkError(kind, err.to_string())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn network_error_from_reqwest(err: &reqwest::Error) -> RuntimeError {
+    let kind = if err.is_timeout() {
+        NetworkErrorKind::Timeout
+    } else if err.is_builder() {
+        NetworkErrorKind::InvalidUrl
+    } else {
+        classify_network_error(err)
+    };
+    RuntimeError::NetworkError(kind, err.to_string())
 }
 
 pub fn intrinsic_http_serve(args: Vec<Value>) -> Result<Value, RuntimeError> {
@@ -3224,11 +5577,14 @@ pub fn intrinsic_http_serve(args: Vec<Value>) -> Result<Value, RuntimeError> {
             }
         };
 
+        check_net_capability("0.0.0.0", port)?;
         let listener = TcpListener::bind(format!("0.0.0.0:{}", port))
-            .map_err(|_| RuntimeError::NotExecutable)?;
+            .map_err(|e| net_io_error("net.http.serve", e))?;
 
         // Accept ONE connection
-        let (mut stream, _) = listener.accept().map_err(|_| RuntimeError::NotExecutable)?;
+        let (mut stream, _) = listener
+            .accept()
+            .map_err(|e| net_io_error("net.http.serve", e))?;
 
         // Read request
         // We'll read what's available or set a timeout.
@@ -3244,88 +5600,512 @@ pub fn intrinsic_http_serve(args: Vec<Value>) -> Result<Value, RuntimeError> {
     }
 }
 
-pub fn intrinsic_socket_bind(args: Vec<Value>) -> Result<Value, RuntimeError> {
+/// `net.http.route(method, path, handler)`: register `handler` for requests
+/// whose method and path (without the query string) match exactly.
+/// Registering the same method and path again replaces the handler.
+pub fn intrinsic_http_route(args: Vec<Value>) -> Result<Value, RuntimeError> {
     #[cfg(target_arch = "wasm32")]
     return Err(RuntimeError::NotExecutable);
 
     #[cfg(not(target_arch = "wasm32"))]
     {
-        if args.len() != 1 {
-            return Err(RuntimeError::NotExecutable);
-        }
-        let port = match &args[0] {
-            Value::Integer(i) => *i as u16,
-            _ => {
+        let (method, path, handler) = match args.as_slice() {
+            [Value::String(m), Value::String(p), h] => (m.to_uppercase(), p.clone(), h.clone()),
+            [Value::String(_), other, _] | [other, _, _] => {
                 return Err(RuntimeError::TypeMismatch(
-                    "Integer".to_string(),
-                    args[0].clone(),
+                    "String".to_string(),
+                    other.clone(),
                 ));
             }
+            _ => return Err(RuntimeError::NotExecutable),
         };
+        if !matches!(
+            handler,
+            Value::Function(_) | Value::NativeFunction(_) | Value::String(_)
+        ) {
+            return Err(RuntimeError::TypeMismatch("Function".to_string(), handler));
+        }
 
-        let listener = TcpListener::bind(format!("0.0.0.0:{}", port))
-            .map_err(|_| RuntimeError::NotExecutable)?;
+        let mut routes = get_http_routes().lock().map_err(|e| {
+            RuntimeError::InvalidOperation(format!("route table mutex poisoned: {}", e))
+        })?;
+        match routes
+            .iter_mut()
+            .find(|route| route.method == method && route.path == path)
+        {
+            Some(route) => route.handler = handler,
+            None => routes.push(HttpRoute {
+                method,
+                path,
+                handler,
+            }),
+        }
+        Ok(Value::Unit)
+    }
+}
 
-        let id = SOCKET_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
-        let mut sockets = get_sockets()
-            .lock()
-            .map_err(|e| RuntimeError::InvalidOperation(format!("socket mutex poisoned: {}", e)))?;
-        sockets.insert(id, SocketResource::Listener(listener));
+/// `net.http.listen(port, max_requests?)`: serve the routes registered with
+/// `net.http.route` on `port`, one connection at a time. Each request is
+/// passed to its handler as a struct with `method`, `path`, `query`,
+/// `headers` (lower-cased names) and `body`; the handler returns a string
+/// body or a struct with optional `status`, `headers` and `body`. Serves
+/// forever unless `max_requests` is given, and returns the number served.
+pub fn intrinsic_http_listen(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
 
-        Ok(Value::Integer(id))
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        match args.as_slice() {
+            [port] => serve_http_routes("net.http.listen", port, None, None),
+            [port, max_requests] => {
+                serve_http_routes("net.http.listen", port, Some(max_requests), None)
+            }
+            _ => Err(RuntimeError::NotExecutable),
+        }
     }
 }
 
-pub fn intrinsic_socket_accept(args: Vec<Value>) -> Result<Value, RuntimeError> {
+/// `net.http.listen_tls(port, identity, max_requests?)`: `net.http.listen`
+/// over TLS, using an identity from `net.tls.load_identity`. Connections
+/// that fail the handshake are dropped but still count towards
+/// `max_requests`.
+pub fn intrinsic_http_listen_tls(args: Vec<Value>) -> Result<Value, RuntimeError> {
     #[cfg(target_arch = "wasm32")]
     return Err(RuntimeError::NotExecutable);
 
     #[cfg(not(target_arch = "wasm32"))]
     {
-        if args.len() != 1 {
-            return Err(RuntimeError::NotExecutable);
-        }
-        let id = match &args[0] {
-            Value::Integer(i) => *i,
-            _ => {
-                return Err(RuntimeError::TypeMismatch(
-                    "Integer".to_string(),
-                    args[0].clone(),
-                ));
-            }
+        let (port, identity, max_requests) = match args.as_slice() {
+            [port, identity] => (port, identity, None),
+            [port, identity, max_requests] => (port, identity, Some(max_requests)),
+            _ => return Err(RuntimeError::NotExecutable),
         };
-
-        // We need to release the lock while accepting, otherwise we block all network ops.
-        // BUT we can't easily clone TcpListener.
-        // Rust TcpListener `try_clone` exists.
-        let listener_clone = {
+        let config = {
             let sockets = get_sockets().lock().map_err(|e| {
                 RuntimeError::InvalidOperation(format!("socket mutex poisoned: {}", e))
             })?;
-            match sockets.get(&id) {
-                Some(SocketResource::Listener(l)) => {
-                    l.try_clone().map_err(|_| RuntimeError::NotExecutable)?
-                }
-                _ => return Err(RuntimeError::InvalidOperation("Not a listener".to_string())),
-            }
+            tls_identity(&sockets, identity)?
         };
+        serve_http_routes("net.http.listen_tls", port, max_requests, Some(config))
+    }
+}
 
-        let (stream, _) = listener_clone
-            .accept()
-            .map_err(|_| RuntimeError::NotExecutable)?;
+/// Accept connections on `port` and answer each with the routed handler,
+/// completing a TLS handshake first when `tls` is set.
+#[cfg(not(target_arch = "wasm32"))]
+fn serve_http_routes(
+    name: &str,
+    port: &Value,
+    max_requests: Option<&Value>,
+    tls: Option<Arc<ServerConfig>>,
+) -> Result<Value, RuntimeError> {
+    let port = match port {
+        Value::Integer(p) => u16::try_from(*p)
+            .map_err(|_| RuntimeError::InvalidOperation(format!("{}: invalid port {}", name, p)))?,
+        other => {
+            return Err(RuntimeError::TypeMismatch(
+                "Integer".to_string(),
+                other.clone(),
+            ));
+        }
+    };
+    let max_requests = match max_requests {
+        None => None,
+        Some(Value::Integer(n)) if *n >= 0 => Some(*n),
+        Some(Value::Integer(n)) => {
+            return Err(RuntimeError::InvalidOperation(format!(
+                "{}: max_requests must be non-negative, got {}",
+                name, n
+            )));
+        }
+        Some(other) => {
+            return Err(RuntimeError::TypeMismatch(
+                "Integer".to_string(),
+                other.clone(),
+            ));
+        }
+    };
 
-        let new_id = SOCKET_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
-        let mut sockets = get_sockets()
-            .lock()
-            .map_err(|e| RuntimeError::InvalidOperation(format!("socket mutex poisoned: {}", e)))?;
-        sockets.insert(new_id, SocketResource::Stream(stream));
+    check_net_capability("0.0.0.0", port)?;
+    let listener = TcpListener::bind(format!("0.0.0.0:{}", port)).map_err(|e| {
+        RuntimeError::NetworkError(NetworkErrorKind::Other, format!("{}: {}", name, e))
+    })?;
 
-        Ok(Value::Integer(new_id))
+    let mut served = 0;
+    while max_requests != Some(served) {
+        let (mut stream, _) = listener.accept().map_err(|e| {
+            RuntimeError::NetworkError(NetworkErrorKind::Other, format!("{}: {}", name, e))
+        })?;
+        stream.set_read_timeout(Some(Duration::from_secs(5))).ok();
+        match &tls {
+            None => handle_http_connection(&mut stream),
+            Some(config) => {
+                if let Ok(mut tls_stream) = tls_accept(name, config.clone(), stream) {
+                    handle_http_connection(&mut tls_stream);
+                }
+            }
+        }
+        served += 1;
     }
+    Ok(Value::Integer(served))
 }
 
-pub fn intrinsic_socket_connect(args: Vec<Value>) -> Result<Value, RuntimeError> {
-    #[cfg(target_arch = "wasm32")]
+/// Answer the single request on `stream`.
+#[cfg(not(target_arch = "wasm32"))]
+fn handle_http_connection<S: Read + Write>(stream: &mut S) {
+    let (status, headers, body) = match read_http_request(stream) {
+        Ok(request) => dispatch_http_request(request),
+        Err((status, msg)) => (status, Vec::new(), msg),
+    };
+    // A client that hung up early only loses its own response; headers
+    // that would split it are refused before anything is written
+    if let Err(e) = write_http_response(stream, status, &headers, &body) {
+        if e.kind() == io::ErrorKind::InvalidInput {
+            let msg = format!("bad handler response: {}", e);
+            let _ = write_http_response(stream, 500, &[], &msg);
+        }
+    }
+}
+
+/// Largest request body the server reads; a longer Content-Length is
+/// answered with 413 before any of the body is read.
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_HTTP_BODY_BYTES: usize = 1024 * 1024;
+
+/// Read one HTTP/1.x request from `stream` as an Ark request struct, or
+/// the status and message to reject it with.
+#[cfg(not(target_arch = "wasm32"))]
+fn read_http_request(stream: &mut impl Read) -> Result<Value, (i64, String)> {
+    const MAX_HEADER_BYTES: usize = 64 * 1024;
+    let bad_request = |msg: String| (400, msg);
+
+    let mut data = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        if data.len() > MAX_HEADER_BYTES {
+            return Err(bad_request("request headers too large".to_string()));
+        }
+        let n = stream
+            .read(&mut chunk)
+            .map_err(|e| bad_request(e.to_string()))?;
+        if n == 0 {
+            return Err(bad_request("incomplete request".to_string()));
+        }
+        data.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let request_line = lines.next().unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target), Some(_version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(bad_request(format!(
+            "malformed request line '{}'",
+            request_line
+        )));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let mut headers = HashMap::new();
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(bad_request(format!("malformed header '{}'", line)));
+        };
+        headers.insert(
+            name.trim().to_lowercase(),
+            Value::String(value.trim().to_string()),
+        );
+    }
+
+    let content_length = match headers.get("content-length") {
+        Some(Value::String(len)) => len
+            .parse::<usize>()
+            .map_err(|_| bad_request(format!("invalid Content-Length '{}'", len)))?,
+        _ => 0,
+    };
+    if content_length > MAX_HTTP_BODY_BYTES {
+        return Err((
+            413,
+            format!(
+                "request body of {} bytes exceeds the {} byte limit",
+                content_length, MAX_HTTP_BODY_BYTES
+            ),
+        ));
+    }
+    let mut body = data[header_end + 4..].to_vec();
+    while body.len() < content_length {
+        let n = stream
+            .read(&mut chunk)
+            .map_err(|e| bad_request(e.to_string()))?;
+        if n == 0 {
+            return Err(bad_request("incomplete request body".to_string()));
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    body.truncate(content_length);
+
+    let mut request = HashMap::new();
+    request.insert("method".to_string(), Value::String(method.to_uppercase()));
+    request.insert("path".to_string(), Value::String(path.to_string()));
+    request.insert("query".to_string(), Value::String(query.to_string()));
+    request.insert("headers".to_string(), Value::Struct(headers));
+    request.insert(
+        "body".to_string(),
+        Value::String(String::from_utf8_lossy(&body).to_string()),
+    );
+    Ok(Value::Struct(request))
+}
+
+/// Run the handler routed for `request` and turn its result into
+/// `(status, headers, body)`. Unknown paths get 404, a known path with
+/// another method 405, and a failing handler 500.
+#[cfg(not(target_arch = "wasm32"))]
+fn dispatch_http_request(request: Value) -> (i64, Vec<(String, String)>, String) {
+    let field = |name: &str| match &request {
+        Value::Struct(fields) => match fields.get(name) {
+            Some(Value::String(s)) => s.clone(),
+            _ => String::new(),
+        },
+        _ => String::new(),
+    };
+    let (method, path) = (field("method"), field("path"));
+
+    let handler = {
+        let routes = match get_http_routes().lock() {
+            Ok(routes) => routes,
+            Err(e) => return (500, Vec::new(), format!("route table poisoned: {}", e)),
+        };
+        let mut on_path = routes.iter().filter(|route| route.path == path).peekable();
+        if on_path.peek().is_none() {
+            return (404, Vec::new(), "Not Found".to_string());
+        }
+        match on_path.find(|route| route.method == method) {
+            Some(route) => route.handler.clone(),
+            None => return (405, Vec::new(), "Method Not Allowed".to_string()),
+        }
+    };
+
+    match intrinsic_func_apply(vec![handler, Value::List(vec![request])]) {
+        Ok(response) => http_response_parts(response)
+            .unwrap_or_else(|msg| (500, Vec::new(), format!("bad handler response: {}", msg))),
+        Err(e) => (500, Vec::new(), e.to_string()),
+    }
+}
+
+/// Split a handler's return value into `(status, headers, body)`.
+#[cfg(not(target_arch = "wasm32"))]
+fn http_response_parts(response: Value) -> Result<(i64, Vec<(String, String)>, String), String> {
+    let mut fields = match response {
+        Value::Return(inner) => return http_response_parts(*inner),
+        Value::String(body) => return Ok((200, Vec::new(), body)),
+        Value::Struct(fields) => fields,
+        other => return Err(format!("expected a String or Struct, got {:?}", other)),
+    };
+
+    let status = match fields.remove("status") {
+        None => 200,
+        Some(Value::Integer(code)) if (100..=999).contains(&code) => code,
+        Some(other) => return Err(format!("invalid status {:?}", other)),
+    };
+    let mut headers = Vec::new();
+    match fields.remove("headers") {
+        None => {}
+        Some(Value::Struct(map)) => {
+            for (name, value) in map {
+                match value {
+                    Value::String(v) => headers.push((name, v)),
+                    other => return Err(format!("header '{}' is not a String: {:?}", name, other)),
+                }
+            }
+            // HashMap order is arbitrary; keep responses reproducible
+            headers.sort();
+        }
+        Some(other) => return Err(format!("headers must be a Struct, got {:?}", other)),
+    }
+    let body = match fields.remove("body") {
+        None => String::new(),
+        Some(Value::String(body)) => body,
+        Some(other) => return Err(format!("body must be a String, got {:?}", other)),
+    };
+    Ok((status, headers, body))
+}
+
+/// Write an HTTP/1.1 response and close the connection. A header name or
+/// value containing CR or LF fails with `InvalidInput` before anything is
+/// written, so a handler cannot inject headers or split the response.
+#[cfg(not(target_arch = "wasm32"))]
+fn write_http_response(
+    stream: &mut impl Write,
+    status: i64,
+    headers: &[(String, String)],
+    body: &str,
+) -> io::Result<()> {
+    let line_break = |s: &str| s.contains(['\r', '\n']);
+    if let Some((name, _)) = headers
+        .iter()
+        .find(|(name, value)| line_break(name) || line_break(value))
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("header {:?} contains a line break", name),
+        ));
+    }
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        500 => "Internal Server Error",
+        _ => "",
+    };
+    let mut response = format!("HTTP/1.1 {} {}\r\n", status, reason);
+    for (name, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    if !headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+    {
+        response.push_str("Content-Type: text/plain; charset=utf-8\r\n");
+    }
+    response.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    ));
+    stream.write_all(response.as_bytes())?;
+    stream.flush()
+}
+
+pub fn intrinsic_socket_bind(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if args.len() != 1 {
+            return Err(RuntimeError::NotExecutable);
+        }
+        let port = match &args[0] {
+            Value::Integer(i) => *i as u16,
+            _ => {
+                return Err(RuntimeError::TypeMismatch(
+                    "Integer".to_string(),
+                    args[0].clone(),
+                ));
+            }
+        };
+
+        check_net_capability("0.0.0.0", port)?;
+        let listener = TcpListener::bind(format!("0.0.0.0:{}", port))
+            .map_err(|e| net_io_error("net.socket.bind", e))?;
+
+        insert_socket(SocketResource::Listener(listener))
+    }
+}
+
+pub fn intrinsic_socket_accept(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if args.len() != 1 {
+            return Err(RuntimeError::NotExecutable);
+        }
+        let id = match &args[0] {
+            Value::Integer(i) => *i,
+            _ => {
+                return Err(RuntimeError::TypeMismatch(
+                    "Integer".to_string(),
+                    args[0].clone(),
+                ));
+            }
+        };
+
+        match accept_connection("net.socket.accept", id)? {
+            Some(resource) => insert_socket(resource),
+            // Non-blocking listener with no pending connection
+            None => Ok(Value::Boolean(false)),
+        }
+    }
+}
+
+/// Accept the next connection on listener `id`, completing the TLS
+/// handshake for a wrapped listener. `None` means a non-blocking listener
+/// has no connection waiting.
+#[cfg(not(target_arch = "wasm32"))]
+fn accept_connection(name: &str, id: i64) -> Result<Option<SocketResource>, RuntimeError> {
+    // We need to release the lock while accepting, otherwise we block all network ops.
+    // BUT we can't easily clone TcpListener.
+    // Rust TcpListener `try_clone` exists.
+    let (listener_clone, tls_config) = {
+        let sockets = get_sockets()
+            .lock()
+            .map_err(|e| RuntimeError::InvalidOperation(format!("socket mutex poisoned: {}", e)))?;
+        match sockets.get(&id) {
+            Some(SocketResource::Listener(l)) => (
+                l.try_clone()
+                    .map_err(|e| net_io_error("net.socket.accept", e))?,
+                None,
+            ),
+            Some(SocketResource::TlsListener(l, config)) => (
+                l.try_clone()
+                    .map_err(|e| net_io_error("net.socket.accept", e))?,
+                Some(config.clone()),
+            ),
+            _ => return Err(RuntimeError::InvalidOperation("Not a listener".to_string())),
+        }
+    };
+
+    let stream = match listener_clone.accept() {
+        Ok((stream, _)) => stream,
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+        Err(_) => return Err(RuntimeError::NotExecutable),
+    };
+    // Some platforms hand out connections that inherit the listener's
+    // non-blocking mode; connections always start out blocking
+    stream
+        .set_nonblocking(false)
+        .map_err(|e| net_io_error("net.socket.accept", e))?;
+    // The handshake runs before the registry is locked again, so a slow
+    // client does not stall other sockets
+    Ok(Some(match tls_config {
+        None => SocketResource::Stream(stream),
+        Some(config) => {
+            SocketResource::TlsServerStream(Box::new(tls_accept(name, config, stream)?))
+        }
+    }))
+}
+
+/// Register `resource` in the socket registry and return its id. Over the
+/// socket limit, `resource` is closed instead.
+#[cfg(not(target_arch = "wasm32"))]
+fn insert_socket(resource: SocketResource) -> Result<Value, RuntimeError> {
+    let mut sockets = get_sockets()
+        .lock()
+        .map_err(|e| RuntimeError::InvalidOperation(format!("socket mutex poisoned: {}", e)))?;
+    admit_handle(Resource::Sockets, sockets.len())?;
+    let id = SOCKET_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
+    sockets.insert(id, resource);
+    Ok(Value::Integer(id))
+}
+
+pub fn intrinsic_socket_connect(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
     return Err(RuntimeError::NotExecutable);
 
     #[cfg(not(target_arch = "wasm32"))]
@@ -3352,16 +6132,11 @@ pub fn intrinsic_socket_connect(args: Vec<Value>) -> Result<Value, RuntimeError>
             }
         };
 
+        check_net_capability(&host, port)?;
         let stream = TcpStream::connect(format!("{}:{}", host, port))
-            .map_err(|_| RuntimeError::NotExecutable)?;
-
-        let id = SOCKET_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
-        let mut sockets = get_sockets()
-            .lock()
-            .map_err(|e| RuntimeError::InvalidOperation(format!("socket mutex poisoned: {}", e)))?;
-        sockets.insert(id, SocketResource::Stream(stream));
+            .map_err(|e| net_io_error("net.socket.connect", e))?;
 
-        Ok(Value::Integer(id))
+        insert_socket(SocketResource::Stream(stream))
     }
 }
 
@@ -3397,10 +6172,11 @@ pub fn intrinsic_socket_send(args: Vec<Value>) -> Result<Value, RuntimeError> {
         let mut sockets = get_sockets()
             .lock()
             .map_err(|e| RuntimeError::InvalidOperation(format!("socket mutex poisoned: {}", e)))?;
-        match sockets.get_mut(&id) {
-            Some(SocketResource::Stream(s)) => {
+        match sockets.get_mut(&id).and_then(SocketResource::stream) {
+            Some(s) => {
                 s.write_all(&data)
-                    .map_err(|_| RuntimeError::NotExecutable)?;
+                    .and_then(|_| s.flush())
+                    .map_err(|e| net_io_error("net.socket.send", e))?;
                 Ok(Value::Integer(data.len() as i64))
             }
             _ => Err(RuntimeError::InvalidOperation(
@@ -3440,10 +6216,23 @@ pub fn intrinsic_socket_recv(args: Vec<Value>) -> Result<Value, RuntimeError> {
         let mut sockets = get_sockets()
             .lock()
             .map_err(|e| RuntimeError::InvalidOperation(format!("socket mutex poisoned: {}", e)))?;
-        match sockets.get_mut(&id) {
-            Some(SocketResource::Stream(s)) => {
+        match sockets.get_mut(&id).and_then(SocketResource::stream) {
+            Some(s) => {
                 let mut buf = vec![0u8; max_bytes];
-                let n = s.read(&mut buf).map_err(|_| RuntimeError::NotExecutable)?;
+                let n = match s.read(&mut buf) {
+                    Ok(n) => n,
+                    // Nothing arrived yet on a non-blocking socket or
+                    // within the timeout
+                    Err(e)
+                        if matches!(
+                            e.kind(),
+                            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                        ) =>
+                    {
+                        return Ok(Value::Boolean(false));
+                    }
+                    Err(_) => return Err(RuntimeError::NotExecutable),
+                };
                 // Truncate to actual size
                 buf.truncate(n);
                 // Convert to string (lossy) or return buffer?
@@ -3479,164 +6268,1556 @@ pub fn intrinsic_socket_close(args: Vec<Value>) -> Result<Value, RuntimeError> {
         let mut sockets = get_sockets()
             .lock()
             .map_err(|e| RuntimeError::InvalidOperation(format!("socket mutex poisoned: {}", e)))?;
-        if sockets.remove(&id).is_some() {
-            Ok(Value::Boolean(true))
-        } else {
-            Ok(Value::Boolean(false))
+        match sockets.remove(&id) {
+            Some(mut resource) => {
+                // The OS socket must leave every poll set before it closes
+                if let Some(source) = resource.poll_source() {
+                    for other in sockets.values() {
+                        if let SocketResource::Poll(set) = other {
+                            let _ = set.remove(id, source);
+                        }
+                    }
+                }
+                resource.close_notify();
+                Ok(Value::Boolean(true))
+            }
+            None => Ok(Value::Boolean(false)),
         }
     }
 }
-pub fn intrinsic_thread_spawn(args: Vec<Value>) -> Result<Value, RuntimeError> {
-    if args.len() != 1 {
-        return Err(RuntimeError::NotExecutable);
-    }
-    let callable = args[0].clone();
 
-    // Get Next ID
-    let thread_id = {
-        let mut id_guard = NEXT_THREAD_ID
-            .get_or_init(|| Mutex::new(1))
-            .lock()
-            .map_err(|e| {
-                RuntimeError::InvalidOperation(format!("thread id mutex poisoned: {}", e))
-            })?;
-        let id = *id_guard;
-        *id_guard += 1;
-        id
-    };
+/// `net.tls.load_identity(cert_path, key_path)`: load a PEM certificate
+/// chain and private key for TLS servers. Returns an id for
+/// `net.tls.wrap_listener` and `net.http.listen_tls`.
+pub fn intrinsic_tls_load_identity(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
 
-    let handle = thread::spawn(move || {
-        match callable {
-            Value::NativeFunction(f) => {
-                let _ = f(vec![]);
-            }
-            Value::Function(chunk) => {
-                // Create VM and run
-                // Assumes 0-arg function in chunk
-                if let Ok(mut vm) = crate::vm::VM::new((*chunk).clone(), "THREAD", 0) {
-                    let _ = vm.run();
-                }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        const NAME: &str = "net.tls.load_identity";
+        let (cert_path, key_path) = match args.as_slice() {
+            [Value::String(cert), Value::String(key)] => (cert, key),
+            [Value::String(_), other] | [other, _] => {
+                return Err(RuntimeError::TypeMismatch(
+                    "String".to_string(),
+                    other.clone(),
+                ));
             }
-            _ => {}
-        }
-    });
+            _ => return Err(RuntimeError::NotExecutable),
+        };
 
-    THREADS
-        .get_or_init(|| Mutex::new(HashMap::new()))
-        .lock()
-        .expect("unexpected failure")
-        .insert(thread_id, handle);
+        let certs = pem_certificates(NAME, cert_path)?;
+        let key_pem = read_pem_file(NAME, key_path)?;
+        let key = rustls_pemfile::private_key(&mut key_pem.as_slice())
+            .map_err(|e| tls_error(NAME, format!("{}: {}", key_path, e)))?
+            .ok_or_else(|| tls_error(NAME, format!("no private key in {}", key_path)))?;
+        let config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(|e| tls_error(NAME, e))?;
 
-    Ok(Value::Integer(thread_id))
+        insert_socket(SocketResource::TlsIdentity(Arc::new(config)))
+    }
 }
 
-pub fn intrinsic_thread_join(args: Vec<Value>) -> Result<Value, RuntimeError> {
-    if args.len() != 1 {
-        return Err(RuntimeError::NotExecutable);
-    }
-    let thread_id = match args[0] {
-        Value::Integer(i) => i,
-        _ => {
-            return Err(RuntimeError::TypeMismatch(
-                "Integer".to_string(),
-                args[0].clone(),
-            ));
-        }
-    };
+/// `net.tls.wrap_listener(listener, identity)`: make `net.socket.accept` on
+/// `listener` complete a TLS handshake with `identity`, so the accepted
+/// streams are encrypted. Returns `listener`.
+pub fn intrinsic_tls_wrap_listener(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
 
-    let handle_opt = THREADS
-        .get_or_init(|| Mutex::new(HashMap::new()))
-        .lock()
-        .expect("unexpected failure")
-        .remove(&thread_id);
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let (id, identity) = match args.as_slice() {
+            [Value::Integer(id), identity] => (*id, identity),
+            [other, _] => {
+                return Err(RuntimeError::TypeMismatch(
+                    "Integer".to_string(),
+                    other.clone(),
+                ));
+            }
+            _ => return Err(RuntimeError::NotExecutable),
+        };
 
-    if let Some(handle) = handle_opt {
-        if handle.join().is_ok() {
-            Ok(Value::Boolean(true))
-        } else {
-            Ok(Value::Boolean(false))
+        let mut sockets = get_sockets()
+            .lock()
+            .map_err(|e| RuntimeError::InvalidOperation(format!("socket mutex poisoned: {}", e)))?;
+        let config = tls_identity(&sockets, identity)?;
+        match sockets.remove(&id) {
+            Some(SocketResource::Listener(listener)) => {
+                sockets.insert(id, SocketResource::TlsListener(listener, config));
+                Ok(Value::Integer(id))
+            }
+            Some(other) => {
+                sockets.insert(id, other);
+                Err(RuntimeError::InvalidOperation("Not a listener".to_string()))
+            }
+            None => Err(RuntimeError::InvalidOperation("Not a listener".to_string())),
         }
-    } else {
-        Ok(Value::Boolean(false)) // Thread not found
     }
 }
 
-pub fn intrinsic_socket_set_timeout(args: Vec<Value>) -> Result<Value, RuntimeError> {
+/// `net.tls.connect(host, port, ca_path?)`: open a TLS connection, verifying
+/// the server against the bundled web PKI roots plus any certificates in
+/// the PEM file `ca_path`. The stream works with the `net.socket.*`
+/// send/recv/close intrinsics.
+pub fn intrinsic_tls_connect(args: Vec<Value>) -> Result<Value, RuntimeError> {
     #[cfg(target_arch = "wasm32")]
     return Err(RuntimeError::NotExecutable);
 
     #[cfg(not(target_arch = "wasm32"))]
     {
-        if args.len() != 2 {
-            return Err(RuntimeError::NotExecutable);
-        }
-        let id = match &args[0] {
-            Value::Integer(i) => *i,
-            _ => {
+        const NAME: &str = "net.tls.connect";
+        let (host, port, ca_path) = match args.as_slice() {
+            [Value::String(host), Value::Integer(port)] => (host, *port, None),
+            [Value::String(host), Value::Integer(port), Value::String(ca)] => {
+                (host, *port, Some(ca))
+            }
+            [Value::String(_), Value::Integer(_), other] => {
                 return Err(RuntimeError::TypeMismatch(
-                    "Integer".to_string(),
-                    args[0].clone(),
+                    "String".to_string(),
+                    other.clone(),
                 ));
             }
-        };
-        let timeout_ms = match &args[1] {
-            Value::Integer(i) => *i as u64,
-            _ => {
+            [Value::String(_), other] | [Value::String(_), other, _] => {
                 return Err(RuntimeError::TypeMismatch(
                     "Integer".to_string(),
-                    args[1].clone(),
+                    other.clone(),
+                ));
+            }
+            [other, _] | [other, _, _] => {
+                return Err(RuntimeError::TypeMismatch(
+                    "String".to_string(),
+                    other.clone(),
                 ));
             }
+            _ => return Err(RuntimeError::NotExecutable),
         };
+        let port = u16::try_from(port).map_err(|_| {
+            RuntimeError::InvalidOperation(format!("{}: invalid port {}", NAME, port))
+        })?;
 
-        let sockets = get_sockets()
-            .lock()
-            .map_err(|e| RuntimeError::InvalidOperation(format!("socket mutex poisoned: {}", e)))?;
-        match sockets.get(&id) {
-            Some(SocketResource::Stream(s)) => {
-                let dur = if timeout_ms == 0 {
-                    None
-                } else {
-                    Some(Duration::from_millis(timeout_ms))
-                };
-                s.set_read_timeout(dur)
-                    .map_err(|_| RuntimeError::NotExecutable)?;
-                s.set_write_timeout(dur)
-                    .map_err(|_| RuntimeError::NotExecutable)?;
-                Ok(Value::Unit)
-            }
-            _ => Err(RuntimeError::InvalidOperation(
-                "Invalid socket or not a stream".to_string(),
-            )),
-        }
+        check_net_capability(host, port)?;
+        let tls = tls_client(NAME, host, port, ca_path.map(String::as_str))?;
+        insert_socket(SocketResource::TlsClientStream(Box::new(tls)))
     }
 }
 
-pub fn intrinsic_event_poll(_args: Vec<Value>) -> Result<Value, RuntimeError> {
-    let mut events = EVENTS
-        .get_or_init(|| Mutex::new(VecDeque::new()))
-        .lock()
-        .expect("operation failed");
-    if let Some(val) = events.pop_front() {
-        Ok(val)
-    } else {
-        Ok(Value::Unit)
+/// Connect to `host:port` and complete a TLS handshake, trusting the web
+/// PKI roots plus the certificates in the PEM file `ca_path`.
+#[cfg(not(target_arch = "wasm32"))]
+fn tls_client(
+    name: &str,
+    host: &str,
+    port: u16,
+    ca_path: Option<&str>,
+) -> Result<StreamOwned<ClientConnection, TcpStream>, RuntimeError> {
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    if let Some(ca_path) = ca_path {
+        for cert in pem_certificates(name, ca_path)? {
+            roots.add(cert).map_err(|e| tls_error(name, e))?;
+        }
+    }
+    let config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let server_name = ServerName::try_from(host.to_string()).map_err(|_| {
+        RuntimeError::NetworkError(
+            NetworkErrorKind::InvalidUrl,
+            format!("{}: invalid server name '{}'", name, host),
+        )
+    })?;
+
+    let stream = TcpStream::connect(format!("{}:{}", host, port)).map_err(|e| {
+        RuntimeError::NetworkError(classify_network_error(&e), format!("{}: {}", name, e))
+    })?;
+    let conn =
+        ClientConnection::new(Arc::new(config), server_name).map_err(|e| tls_error(name, e))?;
+    let mut tls = StreamOwned::new(conn, stream);
+    while tls.conn.is_handshaking() {
+        tls.conn
+            .complete_io(&mut tls.sock)
+            .map_err(|e| tls_error(name, e))?;
     }
+    Ok(tls)
 }
 
-pub fn intrinsic_event_push(args: Vec<Value>) -> Result<Value, RuntimeError> {
+#[cfg(not(target_arch = "wasm32"))]
+fn tls_error(name: &str, err: impl std::fmt::Display) -> RuntimeError {
+    RuntimeError::NetworkError(NetworkErrorKind::TlsError, format!("{}: {}", name, err))
+}
+
+/// Complete the server side of a TLS handshake on an accepted `stream`.
+#[cfg(not(target_arch = "wasm32"))]
+fn tls_accept(
+    name: &str,
+    config: Arc<ServerConfig>,
+    stream: TcpStream,
+) -> Result<StreamOwned<ServerConnection, TcpStream>, RuntimeError> {
+    let conn = ServerConnection::new(config).map_err(|e| tls_error(name, e))?;
+    let mut tls = StreamOwned::new(conn, stream);
+    while tls.conn.is_handshaking() {
+        tls.conn
+            .complete_io(&mut tls.sock)
+            .map_err(|e| tls_error(name, e))?;
+    }
+    Ok(tls)
+}
+
+/// The server config behind the `net.tls.load_identity` id in `identity`.
+#[cfg(not(target_arch = "wasm32"))]
+fn tls_identity(
+    sockets: &HashMap<i64, SocketResource>,
+    identity: &Value,
+) -> Result<Arc<ServerConfig>, RuntimeError> {
+    match identity {
+        Value::Integer(id) => match sockets.get(id) {
+            Some(SocketResource::TlsIdentity(config)) => Ok(config.clone()),
+            _ => Err(RuntimeError::InvalidOperation(
+                "Not a TLS identity".to_string(),
+            )),
+        },
+        other => Err(RuntimeError::TypeMismatch(
+            "Integer".to_string(),
+            other.clone(),
+        )),
+    }
+}
+
+/// Read a PEM file, confined to the working directory like `sys.fs.read`.
+#[cfg(not(target_arch = "wasm32"))]
+fn read_pem_file(name: &str, path: &str) -> Result<Vec<u8>, RuntimeError> {
+    check_path_security(path, false)?;
+    let safe_path = validate_safe_path(path)?;
+    fs::read(safe_path)
+        .map_err(|e| RuntimeError::ResourceError(format!("{}: {}: {}", name, path, e)))
+}
+
+/// Every certificate in the PEM file at `path`; at least one is required.
+#[cfg(not(target_arch = "wasm32"))]
+fn pem_certificates(name: &str, path: &str) -> Result<Vec<CertificateDer<'static>>, RuntimeError> {
+    let pem = read_pem_file(name, path)?;
+    let certs = rustls_pemfile::certs(&mut pem.as_slice())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| tls_error(name, format!("{}: {}", path, e)))?;
+    if certs.is_empty() {
+        return Err(tls_error(name, format!("no certificates in {}", path)));
+    }
+    Ok(certs)
+}
+
+/// `net.ws.connect(url, ca_path?)`: open a WebSocket to a `ws://` or
+/// `wss://` URL. `ca_path` adds trusted CA certificates for `wss://`, as in
+/// `net.tls.connect`. Returns a socket id for `net.ws.send`/`recv`.
+pub fn intrinsic_ws_connect(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        const NAME: &str = "net.ws.connect";
+        let (url, ca_path) = match args.as_slice() {
+            [Value::String(url)] => (url, None),
+            [Value::String(url), Value::String(ca)] => (url, Some(ca)),
+            [Value::String(_), other] | [other] | [other, _] => {
+                return Err(RuntimeError::TypeMismatch(
+                    "String".to_string(),
+                    other.clone(),
+                ));
+            }
+            _ => return Err(RuntimeError::NotExecutable),
+        };
+
+        let invalid_url = || {
+            RuntimeError::NetworkError(
+                NetworkErrorKind::InvalidUrl,
+                format!("{}: invalid WebSocket URL '{}'", NAME, url),
+            )
+        };
+        let (secure, rest) = if let Some(rest) = url.strip_prefix("wss://") {
+            (true, rest)
+        } else if let Some(rest) = url.strip_prefix("ws://") {
+            (false, rest)
+        } else {
+            return Err(invalid_url());
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse::<u16>().map_err(|_| invalid_url())?),
+            None => (authority, if secure { 443 } else { 80 }),
+        };
+        if host.is_empty() {
+            return Err(invalid_url());
+        }
+        check_net_capability(host, port)?;
+
+        let mut conn = if secure {
+            let tls = tls_client(NAME, host, port, ca_path.map(String::as_str))?;
+            SocketResource::TlsClientStream(Box::new(tls))
+        } else {
+            let stream = TcpStream::connect((host, port)).map_err(|e| {
+                RuntimeError::NetworkError(classify_network_error(&e), format!("{}: {}", NAME, e))
+            })?;
+            SocketResource::Stream(stream)
+        };
+        let stream = conn.stream().ok_or(RuntimeError::NotExecutable)?;
+        websocket::client_handshake(stream, &format!("{}:{}", host, port), path)
+            .map_err(|e| net_io_error(NAME, e))?;
+
+        insert_socket(SocketResource::WebSocket {
+            conn: Box::new(conn),
+            client: true,
+            closed: false,
+        })
+    }
+}
+
+/// `net.ws.accept(listener)`: accept the next connection on a listener from
+/// `net.socket.bind` or `net.tls.wrap_listener` and complete the WebSocket
+/// upgrade. Returns a socket id for `net.ws.send`/`recv`, or false when a
+/// non-blocking listener has no connection waiting.
+pub fn intrinsic_ws_accept(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        const NAME: &str = "net.ws.accept";
+        let id = match args.as_slice() {
+            [Value::Integer(id)] => *id,
+            [other] => {
+                return Err(RuntimeError::TypeMismatch(
+                    "Integer".to_string(),
+                    other.clone(),
+                ));
+            }
+            _ => return Err(RuntimeError::NotExecutable),
+        };
+
+        let Some(mut conn) = accept_connection(NAME, id)? else {
+            return Ok(Value::Boolean(false));
+        };
+        let stream = conn.stream().ok_or(RuntimeError::NotExecutable)?;
+        websocket::server_handshake(stream).map_err(|e| net_io_error(NAME, e))?;
+
+        insert_socket(SocketResource::WebSocket {
+            conn: Box::new(conn),
+            client: false,
+            closed: false,
+        })
+    }
+}
+
+/// `net.ws.send(id, data)`: send a String as a text message or a Buffer as
+/// a binary message. Returns the payload length.
+pub fn intrinsic_ws_send(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        const NAME: &str = "net.ws.send";
+        let (id, opcode, data) = match args.as_slice() {
+            [Value::Integer(id), Value::String(s)] => (*id, websocket::OP_TEXT, s.as_bytes()),
+            [Value::Integer(id), Value::Buffer(b)] => (*id, websocket::OP_BINARY, b.as_slice()),
+            [Value::Integer(_), other] => {
+                return Err(RuntimeError::TypeMismatch(
+                    "String or Buffer".to_string(),
+                    other.clone(),
+                ));
+            }
+            [other, _] => {
+                return Err(RuntimeError::TypeMismatch(
+                    "Integer".to_string(),
+                    other.clone(),
+                ));
+            }
+            _ => return Err(RuntimeError::NotExecutable),
+        };
+
+        let mut sockets = get_sockets()
+            .lock()
+            .map_err(|e| RuntimeError::InvalidOperation(format!("socket mutex poisoned: {}", e)))?;
+        match sockets.get_mut(&id) {
+            Some(SocketResource::WebSocket {
+                conn,
+                client,
+                closed: false,
+            }) => {
+                let stream = conn.stream().ok_or(RuntimeError::NotExecutable)?;
+                websocket::write_message(stream, opcode, data, *client)
+                    .map_err(|e| net_io_error(NAME, e))?;
+                Ok(Value::Integer(data.len() as i64))
+            }
+            Some(SocketResource::WebSocket { .. }) => Err(RuntimeError::InvalidOperation(format!(
+                "{}: WebSocket is closed",
+                NAME
+            ))),
+            _ => Err(RuntimeError::InvalidOperation(
+                "Invalid socket or not a WebSocket".to_string(),
+            )),
+        }
+    }
+}
+
+/// `net.ws.recv(id)`: block for the next message. Text arrives as a String
+/// and binary as a Buffer; Unit means the peer closed the connection.
+pub fn intrinsic_ws_recv(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        const NAME: &str = "net.ws.recv";
+        let id = match args.as_slice() {
+            [Value::Integer(id)] => *id,
+            [other] => {
+                return Err(RuntimeError::TypeMismatch(
+                    "Integer".to_string(),
+                    other.clone(),
+                ));
+            }
+            _ => return Err(RuntimeError::NotExecutable),
+        };
+
+        let mut sockets = get_sockets()
+            .lock()
+            .map_err(|e| RuntimeError::InvalidOperation(format!("socket mutex poisoned: {}", e)))?;
+        match sockets.get_mut(&id) {
+            Some(SocketResource::WebSocket {
+                conn,
+                client,
+                closed,
+            }) => {
+                if *closed {
+                    return Ok(Value::Unit);
+                }
+                let stream = conn.stream().ok_or(RuntimeError::NotExecutable)?;
+                match websocket::read_message(stream, *client).map_err(|e| net_io_error(NAME, e))? {
+                    websocket::Message::Text(text) => Ok(Value::String(text)),
+                    websocket::Message::Binary(data) => Ok(Value::Buffer(data)),
+                    websocket::Message::Close => {
+                        *closed = true;
+                        Ok(Value::Unit)
+                    }
+                }
+            }
+            _ => Err(RuntimeError::InvalidOperation(
+                "Invalid socket or not a WebSocket".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn net_io_error(name: &str, err: io::Error) -> RuntimeError {
+    RuntimeError::NetworkError(classify_network_error(&err), format!("{}: {}", name, err))
+}
+
+/// sys.thread.spawn(fn, args?) → Integer thread id
+/// Runs `fn` (a function, native function or intrinsic name) on a new
+/// thread with copies of `args`. `sys.thread.join` collects its result.
+pub fn intrinsic_thread_spawn(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let (callable, call_args) = match args.as_slice() {
+        [callable] => (callable.clone(), Vec::new()),
+        [callable, Value::List(call_args)] => (
+            callable.clone(),
+            call_args.iter().map(detach_value).collect(),
+        ),
+        [_, other] => {
+            return Err(RuntimeError::TypeMismatch(
+                "List".to_string(),
+                other.clone(),
+            ));
+        }
+        _ => return Err(RuntimeError::NotExecutable),
+    };
+    if !matches!(
+        callable,
+        Value::Function(_) | Value::NativeFunction(_) | Value::String(_)
+    ) {
+        return Err(RuntimeError::TypeMismatch(
+            "Function or String".to_string(),
+            callable,
+        ));
+    }
+    require_capability(capabilities().threads, "no threads capability")?;
+    let granted = CAPABILITIES.with(|current| current.borrow().clone());
+    let limits = RESOURCE_LIMITS.with(|current| current.borrow().clone());
+    let gas = crate::vm::gas();
+    let allocated = values_allocated();
+
+    // Get Next ID
+    let thread_id = {
+        let mut id_guard = NEXT_THREAD_ID
+            .get_or_init(|| Mutex::new(1))
+            .lock()
+            .map_err(|e| {
+                RuntimeError::InvalidOperation(format!("thread id mutex poisoned: {}", e))
+            })?;
+        let id = *id_guard;
+        *id_guard += 1;
+        id
+    };
+
+    let mut threads = THREADS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .map_err(|e| RuntimeError::InvalidOperation(format!("thread mutex poisoned: {}", e)))?;
+    admit_handle(Resource::Threads, threads.len())?;
+
+    // The thread runs under the same restrictions as its parent
+    let handle = thread::spawn(move || {
+        set_capabilities(granted);
+        set_resource_limits(limits);
+        crate::vm::set_gas(gas);
+        set_values_allocated(allocated);
+        intrinsic_func_apply(vec![callable, Value::List(call_args)])
+    });
+    threads.insert(thread_id, handle);
+
+    Ok(Value::Integer(thread_id))
+}
+
+/// sys.thread.join(thread_id) → { is_err, val, err }
+/// Waits for the thread and reports its outcome in the shape of
+/// `lib/std/result.ark`: the function's return value in `val`, or the
+/// error or panic message in `err`.
+pub fn intrinsic_thread_join(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 1 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let thread_id = match args[0] {
+        Value::Integer(i) => i,
+        _ => {
+            return Err(RuntimeError::TypeMismatch(
+                "Integer".to_string(),
+                args[0].clone(),
+            ));
+        }
+    };
+
+    let handle = THREADS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .map_err(|e| RuntimeError::InvalidOperation(format!("thread mutex poisoned: {}", e)))?
+        .remove(&thread_id)
+        .ok_or_else(|| RuntimeError::InvalidOperation("Invalid thread handle".to_string()))?;
+
+    let outcome = match handle.join() {
+        Ok(result) => result.map_err(|e| e.to_string()),
+        Err(panic) => Err(match panic.downcast_ref::<&str>() {
+            Some(msg) => format!("thread panicked: {}", msg),
+            None => match panic.downcast_ref::<String>() {
+                Some(msg) => format!("thread panicked: {}", msg),
+                None => "thread panicked".to_string(),
+            },
+        }),
+    };
+    let mut fields = HashMap::new();
+    let (is_err, val, err) = match outcome {
+        Ok(val) => (false, val, String::new()),
+        Err(err) => (true, Value::String(String::new()), err),
+    };
+    fields.insert("is_err".to_string(), Value::Boolean(is_err));
+    fields.insert("val".to_string(), val);
+    fields.insert("err".to_string(), Value::String(err));
+    Ok(Value::Struct(fields))
+}
+
+/// sys.error.trace() → List
+/// The Ark call stack at the most recent runtime error, innermost call
+/// first, as `{function, file, line, col}` structs. Inside a `catch` block
+/// this is the trace of the error being handled.
+pub fn intrinsic_error_trace(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if !args.is_empty() {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let trace = crate::vm::last_error_trace();
+    Ok(Value::List(
+        trace.iter().map(|frame| frame.to_value()).collect(),
+    ))
+}
+
+/// sys.task.spawn(fn, args?) → Integer task id
+/// Runs `fn(args...)` as a lightweight task on the calling thread. Tasks
+/// take turns whenever the running one yields, sleeps or waits for I/O;
+/// the program's `run` finishes them all before returning.
+pub fn intrinsic_task_spawn(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let (callable, call_args) = match args.as_slice() {
+        [callable] => (callable.clone(), Vec::new()),
+        [callable, Value::List(call_args)] => (callable.clone(), call_args.clone()),
+        [_, other] => {
+            return Err(RuntimeError::TypeMismatch(
+                "List".to_string(),
+                other.clone(),
+            ));
+        }
+        _ => return Err(RuntimeError::NotExecutable),
+    };
+    if !matches!(callable, Value::Function(_) | Value::NativeFunction(_)) {
+        return Err(RuntimeError::TypeMismatch("Function".to_string(), callable));
+    }
+    Ok(Value::Integer(crate::vm::spawn_task(callable, call_args)))
+}
+
+/// sys.task.yield() → Unit
+/// Lets every other runnable task take a turn.
+pub fn intrinsic_task_yield(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if !args.is_empty() {
+        return Err(RuntimeError::NotExecutable);
+    }
+    crate::vm::request_suspend(crate::vm::TaskRequest::Yield);
+    Ok(Value::Unit)
+}
+
+/// sys.task.sleep(ms) → Unit
+/// Suspends the task for `ms` milliseconds while the others keep running,
+/// unlike `sys.time.sleep`, which blocks the whole thread.
+pub fn intrinsic_task_sleep(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let ms = match args.as_slice() {
+            [Value::Integer(ms)] if *ms >= 0 => *ms as u64,
+            [Value::Integer(_)] => {
+                return Err(RuntimeError::InvalidOperation(
+                    "Negative sleep duration".to_string(),
+                ));
+            }
+            [other] => {
+                return Err(RuntimeError::TypeMismatch(
+                    "Integer".to_string(),
+                    other.clone(),
+                ));
+            }
+            _ => return Err(RuntimeError::NotExecutable),
+        };
+        let until = std::time::Instant::now() + Duration::from_millis(ms);
+        crate::vm::request_suspend(crate::vm::TaskRequest::Sleep(until));
+        Ok(Value::Unit)
+    }
+}
+
+/// sys.task.recv(socket, n?) → String
+/// `net.socket.recv` that lets other tasks run while no data has arrived.
+/// Switches the socket to non-blocking mode.
+pub fn intrinsic_task_recv(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    retry_until_ready(intrinsic_task_recv, intrinsic_socket_recv, args)
+}
+
+/// sys.task.accept(listener) → Integer
+/// `net.socket.accept` that lets other tasks run while no connection is
+/// waiting. Switches the listener to non-blocking mode.
+pub fn intrinsic_task_accept(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    retry_until_ready(intrinsic_task_accept, intrinsic_socket_accept, args)
+}
+
+/// Run the non-blocking form of `op`; if it reports false (would block),
+/// ask the VM to suspend the task and call `retry` again later.
+#[cfg(not(target_arch = "wasm32"))]
+fn retry_until_ready(
+    retry: NativeFn,
+    op: NativeFn,
+    args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    let socket = args.first().cloned().ok_or(RuntimeError::NotExecutable)?;
+    intrinsic_socket_set_nonblocking(vec![socket, Value::Boolean(true)])?;
+    match op(args.clone())? {
+        Value::Boolean(false) => {
+            crate::vm::request_suspend(crate::vm::TaskRequest::Retry(retry, args));
+            Ok(Value::Unit)
+        }
+        ready => Ok(ready),
+    }
+}
+
+pub fn intrinsic_socket_set_timeout(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if args.len() != 2 {
+            return Err(RuntimeError::NotExecutable);
+        }
+        let id = match &args[0] {
+            Value::Integer(i) => *i,
+            _ => {
+                return Err(RuntimeError::TypeMismatch(
+                    "Integer".to_string(),
+                    args[0].clone(),
+                ));
+            }
+        };
+        let timeout_ms = match &args[1] {
+            Value::Integer(i) => *i as u64,
+            _ => {
+                return Err(RuntimeError::TypeMismatch(
+                    "Integer".to_string(),
+                    args[1].clone(),
+                ));
+            }
+        };
+
+        let sockets = get_sockets()
+            .lock()
+            .map_err(|e| RuntimeError::InvalidOperation(format!("socket mutex poisoned: {}", e)))?;
+        match sockets.get(&id).and_then(SocketResource::tcp) {
+            Some(s) => {
+                let dur = if timeout_ms == 0 {
+                    None
+                } else {
+                    Some(Duration::from_millis(timeout_ms))
+                };
+                s.set_read_timeout(dur)
+                    .map_err(|e| net_io_error("net.socket.set_timeout", e))?;
+                s.set_write_timeout(dur)
+                    .map_err(|e| net_io_error("net.socket.set_timeout", e))?;
+                Ok(Value::Unit)
+            }
+            _ => Err(RuntimeError::InvalidOperation(
+                "Invalid socket or not a stream".to_string(),
+            )),
+        }
+    }
+}
+
+/// `net.socket.set_nonblocking(id, flag)`: switch a listener or connection
+/// in or out of non-blocking mode. A non-blocking `net.socket.accept` or
+/// `net.socket.recv` returns false instead of waiting.
+pub fn intrinsic_socket_set_nonblocking(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let (id, nonblocking) = match args.as_slice() {
+            [Value::Integer(id), Value::Boolean(flag)] => (*id, *flag),
+            [Value::Integer(_), other] => {
+                return Err(RuntimeError::TypeMismatch(
+                    "Boolean".to_string(),
+                    other.clone(),
+                ));
+            }
+            [other, _] => {
+                return Err(RuntimeError::TypeMismatch(
+                    "Integer".to_string(),
+                    other.clone(),
+                ));
+            }
+            _ => return Err(RuntimeError::NotExecutable),
+        };
+
+        let sockets = get_sockets()
+            .lock()
+            .map_err(|e| RuntimeError::InvalidOperation(format!("socket mutex poisoned: {}", e)))?;
+        let result = match sockets.get(&id) {
+            Some(SocketResource::Listener(l)) | Some(SocketResource::TlsListener(l, _)) => {
+                l.set_nonblocking(nonblocking)
+            }
+            Some(resource) => match resource.tcp() {
+                Some(s) => s.set_nonblocking(nonblocking),
+                None => {
+                    return Err(RuntimeError::InvalidOperation(
+                        "Invalid socket or not a stream".to_string(),
+                    ));
+                }
+            },
+            None => {
+                return Err(RuntimeError::InvalidOperation(
+                    "Invalid socket or not a stream".to_string(),
+                ));
+            }
+        };
+        result.map_err(|e| net_io_error("net.socket.set_nonblocking", e))?;
+        Ok(Value::Unit)
+    }
+}
+
+/// `net.poll.create()`: a new, empty poll set. Returns its id.
+pub fn intrinsic_poll_create(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if !args.is_empty() {
+            return Err(RuntimeError::NotExecutable);
+        }
+        let poller = Poller::new().map_err(|e| poll_error("net.poll.create", e))?;
+        insert_socket(SocketResource::Poll(Arc::new(PollSet {
+            poller,
+            members: Mutex::new(HashSet::new()),
+        })))
+    }
+}
+
+/// `net.poll.add(poll, socket)`: watch a listener or connection for
+/// incoming data or connections. Adding a socket twice is harmless.
+pub fn intrinsic_poll_add(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        const NAME: &str = "net.poll.add";
+        let (poll, socket) = poll_socket_args(&args)?;
+        let sockets = get_sockets()
+            .lock()
+            .map_err(|e| RuntimeError::InvalidOperation(format!("socket mutex poisoned: {}", e)))?;
+        let set = poll_set(&sockets, poll)?;
+        let source = sockets
+            .get(&socket)
+            .and_then(SocketResource::poll_source)
+            .ok_or_else(|| {
+                RuntimeError::InvalidOperation("Invalid socket or not pollable".to_string())
+            })?;
+
+        let mut members = set.members()?;
+        let interest = Event::readable(socket as usize);
+        let registered = if members.contains(&socket) {
+            set.poller.modify(source, interest)
+        } else {
+            // SAFETY: the socket is deleted from the poller before it is
+            // dropped, either by `net.poll.remove` or by `net.socket.close`.
+            unsafe { set.poller.add(&source, interest) }
+        };
+        registered.map_err(|e| poll_error(NAME, e))?;
+        members.insert(socket);
+        Ok(Value::Unit)
+    }
+}
+
+/// `net.poll.remove(poll, socket)`: stop watching a socket. Returns whether
+/// it was being watched.
+pub fn intrinsic_poll_remove(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let (poll, socket) = poll_socket_args(&args)?;
+        let sockets = get_sockets()
+            .lock()
+            .map_err(|e| RuntimeError::InvalidOperation(format!("socket mutex poisoned: {}", e)))?;
+        let set = poll_set(&sockets, poll)?;
+        match sockets.get(&socket).and_then(SocketResource::poll_source) {
+            Some(source) => Ok(Value::Boolean(set.remove(socket, source)?)),
+            None => Ok(Value::Boolean(false)),
+        }
+    }
+}
+
+/// `net.poll.wait(poll, timeout_ms?)`: block until a watched socket is
+/// readable and return the ids of the ready sockets. Without a timeout, or
+/// with a negative one, waits indefinitely; an empty list means the timeout
+/// expired.
+pub fn intrinsic_poll_wait(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        const NAME: &str = "net.poll.wait";
+        let (poll, timeout) = match args.as_slice() {
+            [Value::Integer(poll)] => (*poll, None),
+            [Value::Integer(poll), Value::Integer(ms)] => {
+                (*poll, u64::try_from(*ms).ok().map(Duration::from_millis))
+            }
+            [Value::Integer(_), other] | [other] | [other, _] => {
+                return Err(RuntimeError::TypeMismatch(
+                    "Integer".to_string(),
+                    other.clone(),
+                ));
+            }
+            _ => return Err(RuntimeError::NotExecutable),
+        };
+
+        // Wait without holding the registry, so other threads can keep using
+        // their sockets (and add or remove members) in the meantime
+        let set = poll_set(
+            &*get_sockets().lock().map_err(|e| {
+                RuntimeError::InvalidOperation(format!("socket mutex poisoned: {}", e))
+            })?,
+            poll,
+        )?;
+        let mut events = Events::new();
+        set.poller
+            .wait(&mut events, timeout)
+            .map_err(|e| poll_error(NAME, e))?;
+
+        // Events are one-shot: re-arm every reported socket that is still
+        // open and registered
+        let sockets = get_sockets()
+            .lock()
+            .map_err(|e| RuntimeError::InvalidOperation(format!("socket mutex poisoned: {}", e)))?;
+        let members = set.members()?;
+        let mut ready = Vec::new();
+        for event in events.iter() {
+            let id = event.key as i64;
+            let Some(source) = sockets.get(&id).and_then(SocketResource::poll_source) else {
+                continue;
+            };
+            if !members.contains(&id) {
+                continue;
+            }
+            set.poller
+                .modify(source, Event::readable(event.key))
+                .map_err(|e| poll_error(NAME, e))?;
+            ready.push(Value::Integer(id));
+        }
+        Ok(Value::List(ready))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn poll_socket_args(args: &[Value]) -> Result<(i64, i64), RuntimeError> {
+    match args {
+        [Value::Integer(poll), Value::Integer(socket)] => Ok((*poll, *socket)),
+        [Value::Integer(_), other] | [other, _] => Err(RuntimeError::TypeMismatch(
+            "Integer".to_string(),
+            other.clone(),
+        )),
+        _ => Err(RuntimeError::NotExecutable),
+    }
+}
+
+/// The poll set behind the `net.poll.create` id `poll`.
+#[cfg(not(target_arch = "wasm32"))]
+fn poll_set(
+    sockets: &HashMap<i64, SocketResource>,
+    poll: i64,
+) -> Result<Arc<PollSet>, RuntimeError> {
+    match sockets.get(&poll) {
+        Some(SocketResource::Poll(set)) => Ok(set.clone()),
+        _ => Err(RuntimeError::InvalidOperation("Not a poll set".to_string())),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn poll_error(name: &str, err: io::Error) -> RuntimeError {
+    RuntimeError::NetworkError(NetworkErrorKind::Other, format!("{}: {}", name, err))
+}
+
+pub fn intrinsic_event_poll(_args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let mut events = EVENTS
+        .get_or_init(|| Mutex::new(VecDeque::new()))
+        .lock()
+        .expect("operation failed");
+    if let Some(val) = events.pop_front() {
+        Ok(val)
+    } else {
+        Ok(Value::Unit)
+    }
+}
+
+pub fn intrinsic_event_push(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 1 {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let val = args[0].clone();
+    EVENTS
+        .get_or_init(|| Mutex::new(VecDeque::new()))
+        .lock()
+        .expect("unexpected failure")
+        .push_back(val);
+    Ok(Value::Unit)
+}
+
+/// sys.timer.after(ms, handler) → Integer timer id
+/// Queues `[handler, []]` on the `sys.event` queue once `ms` milliseconds
+/// have passed, for `event.loop` to call.
+pub fn intrinsic_timer_after(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    add_timer(args, false)
+}
+
+/// sys.timer.every(ms, handler) → Integer timer id
+/// Queues `[handler, []]` on the `sys.event` queue every `ms` milliseconds
+/// until the timer is cancelled.
+pub fn intrinsic_timer_every(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    add_timer(args, true)
+}
+
+fn add_timer(args: Vec<Value>, repeat: bool) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let (ms, handler) = match args.as_slice() {
+            [Value::Integer(ms), handler] => (*ms, handler.clone()),
+            [other, _] => {
+                return Err(RuntimeError::TypeMismatch(
+                    "Integer".to_string(),
+                    other.clone(),
+                ));
+            }
+            _ => return Err(RuntimeError::NotExecutable),
+        };
+        if ms < 0 || (repeat && ms == 0) {
+            return Err(RuntimeError::InvalidOperation(format!(
+                "Invalid timer interval: {}ms",
+                ms
+            )));
+        }
+        if !matches!(
+            handler,
+            Value::Function(_) | Value::NativeFunction(_) | Value::String(_)
+        ) {
+            return Err(RuntimeError::TypeMismatch(
+                "Function or String".to_string(),
+                handler,
+            ));
+        }
+
+        let delay = Duration::from_millis(ms as u64);
+        let id = TIMER_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let queue = get_timers();
+        queue
+            .timers
+            .lock()
+            .map_err(|_| RuntimeError::InvalidOperation("Timer lock poisoned".to_string()))?
+            .insert(
+                id,
+                Timer {
+                    due: std::time::Instant::now() + delay,
+                    interval: if repeat { Some(delay) } else { None },
+                    handler,
+                },
+            );
+        queue.changed.notify_one();
+        Ok(Value::Integer(id))
+    }
+}
+
+/// sys.timer.cancel(id) → Boolean
+/// Stops a timer; false if it had already fired or been cancelled.
+/// Events it queued before are not withdrawn.
+pub fn intrinsic_timer_cancel(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    #[cfg(target_arch = "wasm32")]
+    return Err(RuntimeError::NotExecutable);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let id = match args.as_slice() {
+            [Value::Integer(id)] => *id,
+            [other] => {
+                return Err(RuntimeError::TypeMismatch(
+                    "Integer".to_string(),
+                    other.clone(),
+                ));
+            }
+            _ => return Err(RuntimeError::NotExecutable),
+        };
+        let removed = get_timers()
+            .timers
+            .lock()
+            .map_err(|_| RuntimeError::InvalidOperation("Timer lock poisoned".to_string()))?
+            .remove(&id)
+            .is_some();
+        Ok(Value::Boolean(removed))
+    }
+}
+
+/// Body of the timer thread: queue the events of due timers, then sleep
+/// until the next one is due or a timer is added.
+#[cfg(not(target_arch = "wasm32"))]
+fn run_timers(queue: &TimerQueue) {
+    let Ok(mut timers) = queue.timers.lock() else {
+        return;
+    };
+    loop {
+        let now = std::time::Instant::now();
+        let (fired, next_due) = fire_due_timers(&mut timers, now);
+        if !fired.is_empty() {
+            let Ok(mut events) = EVENTS.get_or_init(|| Mutex::new(VecDeque::new())).lock() else {
+                return;
+            };
+            events.extend(fired);
+        }
+        let waited = match next_due {
+            Some(due) => queue
+                .changed
+                .wait_timeout(timers, due.saturating_duration_since(now))
+                .map(|(guard, _)| guard)
+                .ok(),
+            None => queue.changed.wait(timers).ok(),
+        };
+        match waited {
+            Some(guard) => timers = guard,
+            None => return,
+        }
+    }
+}
+
+/// Take the events of the timers due at `now`, earliest first, dropping
+/// one-shot timers and rescheduling repeating ones. Also returns when the
+/// next remaining timer is due.
+#[cfg(not(target_arch = "wasm32"))]
+fn fire_due_timers(
+    timers: &mut HashMap<i64, Timer>,
+    now: std::time::Instant,
+) -> (Vec<Value>, Option<std::time::Instant>) {
+    let mut due: Vec<(std::time::Instant, i64)> = timers
+        .iter()
+        .filter(|(_, timer)| timer.due <= now)
+        .map(|(id, timer)| (timer.due, *id))
+        .collect();
+    due.sort();
+
+    let mut fired = Vec::new();
+    for (_, id) in due {
+        let Some(timer) = timers.get_mut(&id) else {
+            continue;
+        };
+        fired.push(Value::List(vec![
+            timer.handler.clone(),
+            Value::List(vec![]),
+        ]));
+        match timer.interval {
+            Some(interval) => {
+                timer.due += interval;
+                // A timer that fell behind skips the missed ticks
+                if timer.due <= now {
+                    timer.due = now + interval;
+                }
+            }
+            None => {
+                timers.remove(&id);
+            }
+        }
+    }
+    let next_due = timers.values().map(|timer| timer.due).min();
+    (fired, next_due)
+}
+
+/// sys.chan.new(cap) → Integer handle
+/// A bounded channel between threads: `sys.chan.send` blocks while `cap`
+/// values are waiting. A capacity of 0 makes every send wait for a
+/// matching `sys.chan.recv`.
+pub fn intrinsic_chan_new(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let cap = match args.as_slice() {
+        [Value::Integer(cap)] if *cap >= 0 => *cap as usize,
+        [other] => {
+            return Err(RuntimeError::TypeMismatch(
+                "Integer".to_string(),
+                other.clone(),
+            ));
+        }
+        _ => return Err(RuntimeError::NotExecutable),
+    };
+
+    let (sender, receiver) = mpsc::sync_channel(cap);
+    let id = CHANNEL_ID_COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    get_channels()
+        .lock()
+        .map_err(|e| RuntimeError::InvalidOperation(format!("channel mutex poisoned: {}", e)))?
+        .insert(
+            id,
+            Channel {
+                sender: Some(sender),
+                receiver: Arc::new(Mutex::new(receiver)),
+            },
+        );
+    Ok(Value::Integer(id))
+}
+
+/// sys.chan.send(chan, value) → Unit
+/// Queues a copy of `value`, blocking while the channel is full. Sending on
+/// a closed channel is an error.
+pub fn intrinsic_chan_send(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let (id, value) = match args.as_slice() {
+        [Value::Integer(id), value] => (*id, value),
+        [other, _] => {
+            return Err(RuntimeError::TypeMismatch(
+                "Integer".to_string(),
+                other.clone(),
+            ));
+        }
+        _ => return Err(RuntimeError::NotExecutable),
+    };
+
+    // Block on a clone of the sender, not while holding the registry
+    let sender = match get_channels()
+        .lock()
+        .map_err(|e| RuntimeError::InvalidOperation(format!("channel mutex poisoned: {}", e)))?
+        .get(&id)
+    {
+        Some(channel) => channel.sender.clone(),
+        None => {
+            return Err(RuntimeError::InvalidOperation(
+                "Invalid channel handle".to_string(),
+            ));
+        }
+    };
+    sender
+        .ok_or_else(|| RuntimeError::InvalidOperation("Channel is closed".to_string()))?
+        .send(detach_value(value))
+        .map_err(|_| RuntimeError::InvalidOperation("Channel is closed".to_string()))?;
+    Ok(Value::Unit)
+}
+
+/// sys.chan.recv(chan) → [Boolean, value]
+/// Blocks for the next value and returns `[true, value]`, or `[false, ()]`
+/// once the channel is closed and drained.
+pub fn intrinsic_chan_recv(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let receiver = channel_receiver(&args)?;
+    let receiver = receiver
+        .lock()
+        .map_err(|e| RuntimeError::InvalidOperation(format!("channel mutex poisoned: {}", e)))?;
+    Ok(received(receiver.recv().ok()))
+}
+
+/// sys.chan.try_recv(chan) → [Boolean, value]
+/// `sys.chan.recv` that never blocks: `[false, ()]` also means nothing is
+/// waiting yet.
+pub fn intrinsic_chan_try_recv(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let receiver = channel_receiver(&args)?;
+    let receiver = receiver
+        .lock()
+        .map_err(|e| RuntimeError::InvalidOperation(format!("channel mutex poisoned: {}", e)))?;
+    Ok(received(receiver.try_recv().ok()))
+}
+
+/// sys.chan.close(chan) → Boolean
+/// Refuses further sends. Values already queued can still be received.
+/// Returns false if the channel was already closed.
+pub fn intrinsic_chan_close(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let id = match args.as_slice() {
+        [Value::Integer(id)] => *id,
+        [other] => {
+            return Err(RuntimeError::TypeMismatch(
+                "Integer".to_string(),
+                other.clone(),
+            ));
+        }
+        _ => return Err(RuntimeError::NotExecutable),
+    };
+
+    let mut channels = get_channels()
+        .lock()
+        .map_err(|e| RuntimeError::InvalidOperation(format!("channel mutex poisoned: {}", e)))?;
+    match channels.get_mut(&id) {
+        Some(channel) => Ok(Value::Boolean(channel.sender.take().is_some())),
+        None => Err(RuntimeError::InvalidOperation(
+            "Invalid channel handle".to_string(),
+        )),
+    }
+}
+
+fn channel_receiver(args: &[Value]) -> Result<Arc<Mutex<mpsc::Receiver<Value>>>, RuntimeError> {
+    let id = match args {
+        [Value::Integer(id)] => *id,
+        [other] => {
+            return Err(RuntimeError::TypeMismatch(
+                "Integer".to_string(),
+                other.clone(),
+            ));
+        }
+        _ => return Err(RuntimeError::NotExecutable),
+    };
+    get_channels()
+        .lock()
+        .map_err(|e| RuntimeError::InvalidOperation(format!("channel mutex poisoned: {}", e)))?
+        .get(&id)
+        .map(|channel| channel.receiver.clone())
+        .ok_or_else(|| RuntimeError::InvalidOperation("Invalid channel handle".to_string()))
+}
+
+fn received(value: Option<Value>) -> Value {
+    match value {
+        Some(value) => Value::List(vec![Value::Boolean(true), value]),
+        None => Value::List(vec![Value::Boolean(false), Value::Unit]),
+    }
+}
+
+/// A copy of `value` that shares no mutable state with the original.
+/// Buffer views get their own backing; persistent collections and
+/// functions are immutable and stay shared.
+fn detach_value(value: &Value) -> Value {
+    match value {
+        Value::BufferView {
+            backing,
+            offset,
+            len,
+        } => {
+            let bytes =
+                backing.read().unwrap_or_else(|e| e.into_inner())[*offset..*offset + *len].to_vec();
+            Value::BufferView {
+                backing: Arc::new(RwLock::new(bytes)),
+                offset: 0,
+                len: *len,
+            }
+        }
+        Value::List(items) => Value::List(items.iter().map(detach_value).collect()),
+        Value::Struct(fields) => Value::Struct(
+            fields
+                .iter()
+                .map(|(name, value)| (name.clone(), detach_value(value)))
+                .collect(),
+        ),
+        Value::Return(inner) => Value::Return(Box::new(detach_value(inner))),
+        Value::EnumValue {
+            enum_name,
+            variant,
+            fields,
+        } => Value::EnumValue {
+            enum_name: enum_name.clone(),
+            variant: variant.clone(),
+            fields: fields.iter().map(detach_value).collect(),
+        },
+        other => other.clone(),
+    }
+}
+
+/// sys.sync.mutex_new(value?) → Integer handle
+/// A mutex guarding `value` (Unit if omitted).
+pub fn intrinsic_sync_mutex_new(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let lock = SyncLock::new(optional_value(&args)?);
+    insert_sync_object(SyncObject::Mutex(Arc::new(lock)))
+}
+
+/// sys.sync.mutex_lock(mutex) → value
+/// Blocks until the mutex is free, takes it for the calling thread and
+/// returns the guarded value.
+pub fn intrinsic_sync_mutex_lock(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    match sync_object(&args, 1)? {
+        SyncObject::Mutex(lock) => lock.acquire(true),
+        _ => Err(RuntimeError::InvalidOperation("Not a mutex".to_string())),
+    }
+}
+
+/// sys.sync.mutex_unlock(mutex, value?) → Unit
+/// Releases a mutex held by the calling thread, replacing the guarded
+/// value if one is given.
+pub fn intrinsic_sync_mutex_unlock(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    match sync_object(&args, 2)? {
+        SyncObject::Mutex(lock) => lock.release(args.get(1)),
+        _ => Err(RuntimeError::InvalidOperation("Not a mutex".to_string())),
+    }
+}
+
+/// sys.sync.rwlock_new(value?) → Integer handle
+/// A readers-writer lock guarding `value` (Unit if omitted).
+pub fn intrinsic_sync_rwlock_new(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let lock = SyncLock::new(optional_value(&args)?);
+    insert_sync_object(SyncObject::RwLock(Arc::new(lock)))
+}
+
+/// sys.sync.rwlock_read(rwlock) → value
+/// Blocks while a writer holds the lock, then shares it with other readers.
+pub fn intrinsic_sync_rwlock_read(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    match sync_object(&args, 1)? {
+        SyncObject::RwLock(lock) => lock.acquire(false),
+        _ => Err(RuntimeError::InvalidOperation("Not a rwlock".to_string())),
+    }
+}
+
+/// sys.sync.rwlock_write(rwlock) → value
+/// Blocks until no reader or writer holds the lock, then takes it alone.
+pub fn intrinsic_sync_rwlock_write(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    match sync_object(&args, 1)? {
+        SyncObject::RwLock(lock) => lock.acquire(true),
+        _ => Err(RuntimeError::InvalidOperation("Not a rwlock".to_string())),
+    }
+}
+
+/// sys.sync.rwlock_unlock(rwlock, value?) → Unit
+/// Releases the calling thread's read or write hold. Only a writer may
+/// replace the guarded value.
+pub fn intrinsic_sync_rwlock_unlock(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    match sync_object(&args, 2)? {
+        SyncObject::RwLock(lock) => lock.release(args.get(1)),
+        _ => Err(RuntimeError::InvalidOperation("Not a rwlock".to_string())),
+    }
+}
+
+/// sys.sync.atomic_new(n?) → Integer handle
+/// An atomic integer starting at `n` (0 if omitted).
+pub fn intrinsic_sync_atomic_new(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let initial = match optional_value(&args)? {
+        Value::Unit => 0,
+        Value::Integer(n) => n,
+        other => return Err(RuntimeError::TypeMismatch("Integer".to_string(), other)),
+    };
+    insert_sync_object(SyncObject::Atomic(Arc::new(
+        std::sync::atomic::AtomicI64::new(initial),
+    )))
+}
+
+/// sys.sync.atomic_add(atomic, delta) → Integer
+/// Adds `delta` (wrapping on overflow) and returns the new value.
+pub fn intrinsic_sync_atomic_add(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let delta = atomic_operand(&args)?;
+    let atomic = sync_atomic(&args)?;
+    let previous = atomic.fetch_add(delta, std::sync::atomic::Ordering::SeqCst);
+    Ok(Value::Integer(previous.wrapping_add(delta)))
+}
+
+/// sys.sync.atomic_load(atomic) → Integer
+pub fn intrinsic_sync_atomic_load(args: Vec<Value>) -> Result<Value, RuntimeError> {
     if args.len() != 1 {
         return Err(RuntimeError::NotExecutable);
     }
-    let val = args[0].clone();
-    EVENTS
-        .get_or_init(|| Mutex::new(VecDeque::new()))
-        .lock()
-        .expect("unexpected failure")
-        .push_back(val);
+    let atomic = sync_atomic(&args)?;
+    Ok(Value::Integer(
+        atomic.load(std::sync::atomic::Ordering::SeqCst),
+    ))
+}
+
+/// sys.sync.atomic_store(atomic, n) → Unit
+pub fn intrinsic_sync_atomic_store(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let value = atomic_operand(&args)?;
+    let atomic = sync_atomic(&args)?;
+    atomic.store(value, std::sync::atomic::Ordering::SeqCst);
     Ok(Value::Unit)
 }
 
+impl SyncLock {
+    fn new(value: Value) -> Self {
+        SyncLock {
+            state: Mutex::new(LockState {
+                writer: None,
+                readers: Vec::new(),
+                value,
+            }),
+            released: Condvar::new(),
+        }
+    }
+
+    fn state(&self) -> Result<std::sync::MutexGuard<'_, LockState>, RuntimeError> {
+        self.state
+            .lock()
+            .map_err(|e| RuntimeError::InvalidOperation(format!("lock state poisoned: {}", e)))
+    }
+
+    /// Take the lock for the calling thread, alone if `exclusive`, and
+    /// return a copy of the guarded value.
+    fn acquire(&self, exclusive: bool) -> Result<Value, RuntimeError> {
+        let me = thread::current().id();
+        let mut state = self.state()?;
+        // Waiting on our own hold would never finish
+        if state.writer == Some(me) || (exclusive && state.readers.contains(&me)) {
+            return Err(RuntimeError::InvalidOperation(
+                "Lock is already held by this thread".to_string(),
+            ));
+        }
+        while state.writer.is_some() || (exclusive && !state.readers.is_empty()) {
+            state = self.released.wait(state).map_err(|e| {
+                RuntimeError::InvalidOperation(format!("lock state poisoned: {}", e))
+            })?;
+        }
+        if exclusive {
+            state.writer = Some(me);
+        } else {
+            state.readers.push(me);
+        }
+        Ok(detach_value(&state.value))
+    }
+
+    /// Drop the calling thread's hold, storing `value` if it was the writer.
+    fn release(&self, value: Option<&Value>) -> Result<Value, RuntimeError> {
+        let me = thread::current().id();
+        let mut state = self.state()?;
+        if state.writer == Some(me) {
+            state.writer = None;
+            if let Some(value) = value {
+                state.value = detach_value(value);
+            }
+        } else if let Some(i) = state.readers.iter().position(|t| *t == me) {
+            if value.is_some() {
+                return Err(RuntimeError::InvalidOperation(
+                    "A read lock cannot replace the value".to_string(),
+                ));
+            }
+            state.readers.swap_remove(i);
+        } else {
+            return Err(RuntimeError::InvalidOperation(
+                "Lock is not held by this thread".to_string(),
+            ));
+        }
+        drop(state);
+        self.released.notify_all();
+        Ok(Value::Unit)
+    }
+}
+
+fn optional_value(args: &[Value]) -> Result<Value, RuntimeError> {
+    match args {
+        [] => Ok(Value::Unit),
+        [value] => Ok(value.clone()),
+        _ => Err(RuntimeError::NotExecutable),
+    }
+}
+
+fn insert_sync_object(object: SyncObject) -> Result<Value, RuntimeError> {
+    let id = SYNC_ID_COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    get_sync_objects()
+        .lock()
+        .map_err(|e| RuntimeError::InvalidOperation(format!("sync mutex poisoned: {}", e)))?
+        .insert(id, object);
+    Ok(Value::Integer(id))
+}
+
+/// The object behind the handle in `args[0]`, for calls taking at most
+/// `max_args` arguments.
+fn sync_object(args: &[Value], max_args: usize) -> Result<SyncObject, RuntimeError> {
+    if args.is_empty() || args.len() > max_args {
+        return Err(RuntimeError::NotExecutable);
+    }
+    let id = match &args[0] {
+        Value::Integer(id) => *id,
+        other => {
+            return Err(RuntimeError::TypeMismatch(
+                "Integer".to_string(),
+                other.clone(),
+            ));
+        }
+    };
+    get_sync_objects()
+        .lock()
+        .map_err(|e| RuntimeError::InvalidOperation(format!("sync mutex poisoned: {}", e)))?
+        .get(&id)
+        .cloned()
+        .ok_or_else(|| RuntimeError::InvalidOperation("Invalid sync handle".to_string()))
+}
+
+fn sync_atomic(args: &[Value]) -> Result<Arc<std::sync::atomic::AtomicI64>, RuntimeError> {
+    match sync_object(args, 2)? {
+        SyncObject::Atomic(atomic) => Ok(atomic),
+        _ => Err(RuntimeError::InvalidOperation("Not an atomic".to_string())),
+    }
+}
+
+/// The Integer second argument of `atomic_add`/`atomic_store`.
+fn atomic_operand(args: &[Value]) -> Result<i64, RuntimeError> {
+    match args {
+        [_, Value::Integer(n)] => Ok(*n),
+        [_, other] => Err(RuntimeError::TypeMismatch(
+            "Integer".to_string(),
+            other.clone(),
+        )),
+        _ => Err(RuntimeError::NotExecutable),
+    }
+}
+
 pub fn intrinsic_func_apply(args: Vec<Value>) -> Result<Value, RuntimeError> {
     if args.len() != 2 {
         return Err(RuntimeError::NotExecutable);
@@ -3654,20 +7835,23 @@ pub fn intrinsic_func_apply(args: Vec<Value>) -> Result<Value, RuntimeError> {
 
     match func {
         Value::String(name) => {
-            if let Some(native_fn) = IntrinsicRegistry::resolve(&name) {
+            if let Some(native_fn) = IntrinsicRegistry::dispatch(&name)? {
                 native_fn(func_args)
             } else {
                 Err(RuntimeError::FunctionNotFound(name))
             }
         }
         Value::Function(chunk) => {
-            // Run VM for function
+            // Run VM for function, on the gas of the program calling it
             if let Ok(mut vm) = crate::vm::VM::new((*chunk).clone(), "APPLY", 0) {
                 for arg in func_args {
                     vm.stack.push(arg);
                 }
-                vm.run()
-                    .map_err(|e| RuntimeError::InvalidOperation(e.to_string()))
+                vm.run().map_err(|e| match e {
+                    // Running out of gas or past a limit stays uncatchable
+                    crate::vm::ArkError::Runtime(e) if !e.is_catchable() => e,
+                    other => RuntimeError::InvalidOperation(other.to_string()),
+                })
             } else {
                 Err(RuntimeError::NotExecutable)
             }
@@ -3680,6 +7864,227 @@ pub fn intrinsic_func_apply(args: Vec<Value>) -> Result<Value, RuntimeError> {
     }
 }
 
+/// sys.assert_throws(fn, pattern?) → String
+/// Calls the zero-argument `fn` and passes only if it errors, returning the
+/// error message. With `pattern`, the message must also match that regex.
+/// Returning normally, or a non-matching error, is an `AssertionFailed`.
+pub fn intrinsic_assert_throws(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let (func, pattern) = match args.as_slice() {
+        [func] => (func.clone(), None),
+        [func, Value::String(p)] => (func.clone(), Some(p.clone())),
+        [_, other] => {
+            return Err(RuntimeError::TypeMismatch(
+                "String".to_string(),
+                other.clone(),
+            ));
+        }
+        _ => return Err(RuntimeError::NotExecutable),
+    };
+
+    let message = match intrinsic_func_apply(vec![func, Value::List(vec![])]) {
+        Ok(val) => {
+            return Err(RuntimeError::AssertionFailed(format!(
+                "expected an error, but the function returned {:?}",
+                val
+            )));
+        }
+        Err(e) => e.to_string(),
+    };
+
+    if let Some(pattern) = pattern {
+        let re = Regex::new(&pattern).map_err(|e| {
+            RuntimeError::InvalidOperation(format!("sys.assert_throws: bad pattern: {}", e))
+        })?;
+        if !re.is_match(&message) {
+            return Err(RuntimeError::AssertionFailed(format!(
+                "error '{}' does not match '{}'",
+                message, pattern
+            )));
+        }
+    }
+    Ok(Value::String(message))
+}
+
+thread_local! {
+    /// Failures recorded by `assert`, `assert_eq` and `assert_err` while a
+    /// test runs on this thread; `None` outside one.
+    static TEST_FAILURES: std::cell::RefCell<Option<Vec<String>>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Record failed assertions on this thread from now on instead of failing
+/// with them, until `end_test`.
+pub fn begin_test() {
+    TEST_FAILURES.with(|failures| *failures.borrow_mut() = Some(Vec::new()));
+}
+
+/// Stop recording, returning the failures recorded since `begin_test`.
+pub fn end_test() -> Vec<String> {
+    TEST_FAILURES
+        .with(|failures| failures.borrow_mut().take())
+        .unwrap_or_default()
+}
+
+/// A failed assertion: recorded if a test is running, so it carries on and
+/// the assertion returns false, and an `AssertionFailed` error otherwise.
+fn assertion_failed(message: String) -> Result<Value, RuntimeError> {
+    let unrecorded = TEST_FAILURES.with(|failures| match failures.borrow_mut().as_mut() {
+        Some(failures) => {
+            failures.push(message);
+            None
+        }
+        None => Some(message),
+    });
+    match unrecorded {
+        Some(message) => Err(RuntimeError::AssertionFailed(message)),
+        None => Ok(Value::Boolean(false)),
+    }
+}
+
+/// "assertion failed", with the caller's message if it gave one.
+fn assertion_message(message: Option<&Value>) -> Result<String, RuntimeError> {
+    match message {
+        None => Ok("assertion failed".to_string()),
+        Some(Value::String(m)) => Ok(format!("assertion failed: {}", m)),
+        Some(other) => Err(RuntimeError::TypeMismatch(
+            "String".to_string(),
+            other.clone(),
+        )),
+    }
+}
+
+/// How `left` and `right` differ: both values on one line each, or a line
+/// diff of their pretty-printed forms when either spans several lines.
+fn describe_mismatch(left: &Value, right: &Value) -> String {
+    let left = crate::adn::to_adn_pretty(left);
+    let right = crate::adn::to_adn_pretty(right);
+    if !left.contains('\n') && !right.contains('\n') {
+        return format!("  left:  {}\n  right: {}", left, right);
+    }
+    let left: Vec<&str> = left.lines().collect();
+    let right: Vec<&str> = right.lines().collect();
+    // Longest common subsequence of lines, filled from the end
+    let mut common = vec![vec![0usize; right.len() + 1]; left.len() + 1];
+    for i in (0..left.len()).rev() {
+        for j in (0..right.len()).rev() {
+            common[i][j] = if left[i] == right[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let mut lines = vec!["  --- left".to_string(), "  +++ right".to_string()];
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() || j < right.len() {
+        if i < left.len() && j < right.len() && left[i] == right[j] {
+            lines.push(format!("    {}", left[i]));
+            i += 1;
+            j += 1;
+        } else if j == right.len() || (i < left.len() && common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(format!("  - {}", left[i]));
+            i += 1;
+        } else {
+            lines.push(format!("  + {}", right[j]));
+            j += 1;
+        }
+    }
+    lines.join("\n")
+}
+
+/// assert(cond, message?) → Bool
+/// Passes when `cond` is true. Under `ark test` a failure is recorded and
+/// the test goes on; anywhere else it is an `AssertionFailed` error.
+pub fn intrinsic_assert(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let (cond, message) = match args.as_slice() {
+        [cond] => (cond, None),
+        [cond, message] => (cond, Some(message)),
+        _ => return Err(RuntimeError::NotExecutable),
+    };
+    let message = assertion_message(message)?;
+    match cond {
+        Value::Boolean(true) => Ok(Value::Boolean(true)),
+        Value::Boolean(false) => assertion_failed(message),
+        other => Err(RuntimeError::TypeMismatch(
+            "Boolean".to_string(),
+            other.clone(),
+        )),
+    }
+}
+
+/// assert_eq(left, right, message?) → Bool
+/// Passes when the values are equal; a failure shows how they differ.
+pub fn intrinsic_assert_eq(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let (left, right, message) = match args.as_slice() {
+        [left, right] => (left, right, None),
+        [left, right, message] => (left, right, Some(message)),
+        _ => return Err(RuntimeError::NotExecutable),
+    };
+    let message = assertion_message(message)?;
+    if left == right {
+        return Ok(Value::Boolean(true));
+    }
+    assertion_failed(format!("{}\n{}", message, describe_mismatch(left, right)))
+}
+
+/// assert_err(value, message?) → Bool
+/// Passes when `value` is an error: an `Err(..)` result, a result struct
+/// with `is_err` set, or a zero-argument function that raises an error or
+/// returns one of those.
+pub fn intrinsic_assert_err(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let (value, message) = match args.as_slice() {
+        [value] => (value.clone(), None),
+        [value, message] => (value.clone(), Some(message)),
+        _ => return Err(RuntimeError::NotExecutable),
+    };
+    let message = assertion_message(message)?;
+    let value = match value {
+        Value::Function(_) | Value::NativeFunction(_) => {
+            match intrinsic_func_apply(vec![value, Value::List(vec![])]) {
+                Ok(returned) => returned,
+                Err(_) => return Ok(Value::Boolean(true)),
+            }
+        }
+        value => value,
+    };
+    let is_err = match &value {
+        Value::EnumValue {
+            enum_name, variant, ..
+        } => enum_name == "Result" && variant == "Err",
+        Value::Struct(fields) => fields.get("is_err") == Some(&Value::Boolean(true)),
+        _ => false,
+    };
+    if is_err {
+        return Ok(Value::Boolean(true));
+    }
+    assertion_failed(format!(
+        "{}\n  expected an error, got {}",
+        message,
+        crate::adn::to_adn(&value)
+    ))
+}
+
+/// sys.bench.black_box(value) → value
+/// Returns its argument, in a way the compiler cannot see through, so a
+/// benchmark's result is not optimized away.
+pub fn intrinsic_bench_black_box(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    match args.as_slice() {
+        [value] => Ok(std::hint::black_box(value.clone())),
+        _ => Err(RuntimeError::NotExecutable),
+    }
+}
+
+/// sys.debug.break() → Unit
+/// Pauses an attached debugger before the next instruction and marks the
+/// call in a recorded trace; does nothing otherwise.
+pub fn intrinsic_debug_break(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if !args.is_empty() {
+        return Err(RuntimeError::NotExecutable);
+    }
+    crate::debugger::request_break();
+    Ok(Value::Unit)
+}
+
 pub fn intrinsic_vm_eval(args: Vec<Value>) -> Result<Value, RuntimeError> {
     if args.len() != 1 {
         return Err(RuntimeError::NotExecutable);
@@ -3703,14 +8108,14 @@ pub fn intrinsic_vm_eval(args: Vec<Value>) -> Result<Value, RuntimeError> {
     {
         // Write to temp file
         let temp_path = "temp_eval.ark";
-        fs::write(temp_path, source).map_err(|_| RuntimeError::NotExecutable)?;
+        fs::write(temp_path, source).map_err(RuntimeError::from)?;
 
         // Execute python meta/ark.py
         let output = Command::new("python3")
             .arg("meta/ark.py")
             .arg(temp_path)
             .output()
-            .map_err(|_| RuntimeError::NotExecutable)?;
+            .map_err(RuntimeError::from)?;
 
         let _ = fs::remove_file(temp_path);
 
@@ -3896,6 +8301,95 @@ fn value_to_json(val: &Value) -> String {
     }
 }
 
+/// Strict counterpart of `value_to_json` for file output. Values with no
+/// JSON form (functions, buffers, ...) become `null`, as in `json.stringify`.
+fn value_to_serde_json(val: &Value) -> serde_json::Value {
+    match val {
+        Value::Integer(n) => serde_json::Value::from(*n),
+        Value::String(s) => serde_json::Value::String(s.clone()),
+        Value::Boolean(b) => serde_json::Value::Bool(*b),
+        Value::List(items) => {
+            serde_json::Value::Array(items.iter().map(value_to_serde_json).collect())
+        }
+        Value::Struct(map) => serde_json::Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), value_to_serde_json(v)))
+                .collect(),
+        ),
+        _ => serde_json::Value::Null,
+    }
+}
+
+/// Strict counterpart of `json_to_value`. Numbers follow `json.parse`:
+/// non-integers are truncated to Integer.
+fn serde_json_to_value(json: serde_json::Value) -> Value {
+    match json {
+        serde_json::Value::Null => Value::Unit,
+        serde_json::Value::Bool(b) => Value::Boolean(b),
+        serde_json::Value::Number(n) => Value::Integer(
+            n.as_i64()
+                .unwrap_or_else(|| n.as_f64().map_or(0, |f| f as i64)),
+        ),
+        serde_json::Value::String(s) => Value::String(s),
+        serde_json::Value::Array(items) => {
+            Value::List(items.into_iter().map(serde_json_to_value).collect())
+        }
+        serde_json::Value::Object(map) => Value::Struct(
+            map.into_iter()
+                .map(|(k, v)| (k, serde_json_to_value(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// sys.fs.read_json(path) → Value
+/// Reads a file (same jail as `sys.fs.read`) and parses it as JSON. Parse
+/// errors name the file.
+pub fn intrinsic_fs_read_json(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let path = match args.first() {
+        Some(Value::String(s)) if args.len() == 1 => s.clone(),
+        Some(other) if args.len() == 1 => {
+            return Err(RuntimeError::TypeMismatch("String".to_string(), other.clone()));
+        }
+        _ => {
+            return Err(RuntimeError::InvalidOperation(
+                "sys.fs.read_json expects 1 argument (path)".into(),
+            ));
+        }
+    };
+    let content = match intrinsic_fs_read(args)? {
+        Value::String(s) => s,
+        other => return Err(RuntimeError::TypeMismatch("String".to_string(), other)),
+    };
+    let json = serde_json::from_str(&content).map_err(|e| {
+        RuntimeError::InvalidOperation(format!("{}: JSON Parse Error: {}", path, e))
+    })?;
+    Ok(serde_json_to_value(json))
+}
+
+/// sys.fs.write_json(path, value, pretty?) → Unit
+/// Serializes `value` as JSON (indented when `pretty` is true) and writes it
+/// through `sys.fs.write`.
+pub fn intrinsic_fs_write_json(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 2 && args.len() != 3 {
+        return Err(RuntimeError::InvalidOperation(
+            "sys.fs.write_json expects 2 or 3 arguments (path, value, pretty?)".into(),
+        ));
+    }
+    let pretty = match args.get(2) {
+        Some(flag) => flag.as_condition()?,
+        None => false,
+    };
+    let json = value_to_serde_json(&args[1]);
+    let text = if pretty {
+        serde_json::to_string_pretty(&json)
+    } else {
+        serde_json::to_string(&json)
+    }
+    .map_err(|e| RuntimeError::InvalidOperation(format!("JSON Serialize Error: {}", e)))?;
+    intrinsic_fs_write(vec![args[0].clone(), Value::String(text)])
+}
+
 // =============================================================================
 // ADN Data Notation Intrinsics (Phase 80)
 // =============================================================================
@@ -3932,22 +8426,128 @@ fn intrinsic_data_from_adn(args: Vec<Value>) -> Result<Value, RuntimeError> {
         .map_err(|e| RuntimeError::InvalidOperation(format!("ADN Parse Error: {}", e)))
 }
 
-/// sys.log(args...) → Unit
-/// Prints a log message to stderr with [LOG] prefix.
-fn intrinsic_log(args: Vec<Value>) -> Result<Value, RuntimeError> {
-    let parts: Vec<String> = args
+/// Severity accepted as the first argument of `sys.log`, lowest first.
+const LOG_LEVELS: [&str; 4] = ["debug", "info", "warn", "error"];
+
+thread_local! {
+    /// Lines `sys.log` would write to stderr, collected instead while set.
+    static LOG_CAPTURE: std::cell::RefCell<Option<Vec<String>>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+fn log_level_rank(level: &str) -> Option<usize> {
+    LOG_LEVELS
         .iter()
-        .map(|a| match a {
-            Value::Integer(n) => n.to_string(),
-            Value::String(s) => s.clone(),
-            Value::Boolean(b) => b.to_string(),
-            Value::Unit => "null".into(),
-            Value::List(_) => "[List]".into(),
-            Value::Struct(_) => "{Struct}".into(),
-            _ => format!("{:?}", a),
+        .position(|l| l.eq_ignore_ascii_case(level))
+}
+
+thread_local! {
+    /// Minimum level set by `set_log_level`, in place of `ARK_LOG_LEVEL`.
+    static LOG_LEVEL: std::cell::Cell<Option<usize>> = const { std::cell::Cell::new(None) };
+}
+
+/// Make `sys.log` on this thread drop lines below `level` whatever
+/// `ARK_LOG_LEVEL` says, or go back to reading it with `None`. An unknown
+/// level means `info`, as it does in the variable.
+pub fn set_log_level(level: Option<&str>) {
+    let rank = level.map(|l| log_level_rank(l.trim()).unwrap_or(1));
+    LOG_LEVEL.with(|current| current.set(rank));
+}
+
+/// Minimum level from `set_log_level`, or else `ARK_LOG_LEVEL`; unset or
+/// unknown means `info`.
+fn log_threshold() -> usize {
+    LOG_LEVEL.with(|current| current.get()).unwrap_or_else(|| {
+        env::var("ARK_LOG_LEVEL")
+            .ok()
+            .and_then(|l| log_level_rank(l.trim()))
+            .unwrap_or(1)
+    })
+}
+
+fn log_text(val: &Value) -> String {
+    match val {
+        Value::Integer(n) => n.to_string(),
+        Value::String(s) => s.clone(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Unit => "null".into(),
+        Value::List(_) => "[List]".into(),
+        Value::Struct(_) => "{Struct}".into(),
+        _ => format!("{:?}", val),
+    }
+}
+
+/// Render structured fields as `key=value`, sorted by key. Values containing
+/// spaces, `=` or quotes are quoted.
+fn log_fields(fields: &HashMap<String, Value>) -> String {
+    let mut keys: Vec<&String> = fields.keys().collect();
+    keys.sort();
+    keys.iter()
+        .map(|k| {
+            let text = log_text(&fields[*k]);
+            if text.is_empty() || text.contains([' ', '=', '"']) {
+                format!("{}={:?}", k, text)
+            } else {
+                format!("{}={}", k, text)
+            }
         })
-        .collect();
-    eprintln!("[LOG] {}", parts.join(" "));
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn emit_log_line(line: String) {
+    LOG_CAPTURE.with(|capture| match capture.borrow_mut().as_mut() {
+        Some(lines) => lines.push(line),
+        None => eprintln!("{}", line),
+    });
+}
+
+/// sys.log(level, message, fields?) → Unit
+/// sys.log(args...) → Unit
+/// Writes to stderr. With a leading `debug`/`info`/`warn`/`error` level the
+/// line is `[LEVEL] message k=v ...` and is dropped below `ARK_LOG_LEVEL`;
+/// otherwise the arguments are joined under a `[LOG]` prefix at info level.
+fn intrinsic_log(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let level = match args.first() {
+        Some(Value::String(l)) if args.len() >= 2 => log_level_rank(l),
+        _ => None,
+    };
+    let Some(rank) = level else {
+        if log_threshold() <= 1 {
+            let parts: Vec<String> = args.iter().map(log_text).collect();
+            emit_log_line(format!("[LOG] {}", parts.join(" ")));
+        }
+        return Ok(Value::Unit);
+    };
+
+    let fields = match args.get(2) {
+        None => None,
+        Some(Value::Struct(map)) if args.len() == 3 => Some(map),
+        Some(other) if args.len() == 3 => {
+            return Err(RuntimeError::TypeMismatch(
+                "Struct".to_string(),
+                other.clone(),
+            ));
+        }
+        _ => {
+            return Err(RuntimeError::InvalidOperation(
+                "sys.log expects (level, message, fields?)".into(),
+            ));
+        }
+    };
+    if rank < log_threshold() {
+        return Ok(Value::Unit);
+    }
+    let mut line = format!(
+        "[{}] {}",
+        LOG_LEVELS[rank].to_uppercase(),
+        log_text(&args[1])
+    );
+    if let Some(fields) = fields.filter(|f| !f.is_empty()) {
+        line.push(' ');
+        line.push_str(&log_fields(fields));
+    }
+    emit_log_line(line);
     Ok(Value::Unit)
 }
 
@@ -3965,6 +8565,68 @@ fn intrinsic_exit(args: Vec<Value>) -> Result<Value, RuntimeError> {
     std::process::exit(code);
 }
 
+/// sys.args.count() → Integer
+/// Number of program arguments, counting the program path at index 0.
+fn intrinsic_args_count(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if !args.is_empty() {
+        return Err(RuntimeError::InvalidOperation(
+            "sys.args.count takes no arguments".into(),
+        ));
+    }
+    Ok(Value::Integer(script_args().len() as i64))
+}
+
+/// sys.args.get(index) → String
+/// The program argument at `index`; 0 is the program path.
+fn intrinsic_args_get(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let index = match args.as_slice() {
+        [Value::Integer(i)] => *i,
+        [other] => {
+            return Err(RuntimeError::TypeMismatch(
+                "Integer".to_string(),
+                other.clone(),
+            ));
+        }
+        _ => {
+            return Err(RuntimeError::InvalidOperation(
+                "sys.args.get expects 1 integer argument".into(),
+            ));
+        }
+    };
+    let program_args = script_args();
+    usize::try_from(index)
+        .ok()
+        .and_then(|i| program_args.get(i))
+        .map(|arg| Value::String(arg.clone()))
+        .ok_or_else(|| {
+            RuntimeError::InvalidOperation(format!(
+                "sys.args.get: index {} out of range ({} arguments)",
+                index,
+                program_args.len()
+            ))
+        })
+}
+
+/// sys.env.get(name) → String
+/// Value of environment variable `name`, or "" when it is unset.
+fn intrinsic_env_get(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let name = match args.as_slice() {
+        [Value::String(name)] => name,
+        [other] => {
+            return Err(RuntimeError::TypeMismatch(
+                "String".to_string(),
+                other.clone(),
+            ));
+        }
+        _ => {
+            return Err(RuntimeError::InvalidOperation(
+                "sys.env.get expects 1 string argument".into(),
+            ));
+        }
+    };
+    Ok(Value::String(std::env::var(name).unwrap_or_default()))
+}
+
 /// sys.html_escape(string) → String
 /// Escapes HTML special characters: & < > " '
 fn intrinsic_html_escape(args: Vec<Value>) -> Result<Value, RuntimeError> {
@@ -4029,8 +8691,8 @@ fn intrinsic_z3_verify(args: Vec<Value>) -> Result<Value, RuntimeError> {
 }
 
 /// sys.vm.source(path) → String
-/// Reads a source file and returns its contents as a string.
-/// (Full eval requires parser access; Rust runtime returns the raw source.)
+/// Parses a source file and returns it re-rendered by `ast::to_source`, so
+/// the result is normalized and known to parse. Syntax errors name the file.
 fn intrinsic_vm_source(args: Vec<Value>) -> Result<Value, RuntimeError> {
     if args.len() != 1 {
         return Err(RuntimeError::InvalidOperation(
@@ -4055,10 +8717,11 @@ fn intrinsic_vm_source(args: Vec<Value>) -> Result<Value, RuntimeError> {
             path
         )));
     }
-    match fs::read_to_string(&path) {
-        Ok(contents) => Ok(Value::String(contents)),
-        Err(e) => Err(RuntimeError::ResourceError(format!("Source Error: {}", e))),
-    }
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| RuntimeError::ResourceError(format!("Source Error: {}", e)))?;
+    let ast = crate::parser::parse_source(&contents, &path)
+        .map_err(|e| RuntimeError::InvalidOperation(format!("{}: Parse Error: {}", path, e)))?;
+    Ok(Value::String(crate::ast::to_source(&ast)))
 }
 
 /// sys.info() → Struct
@@ -4101,6 +8764,10 @@ fn make_tensor(flat_data: Vec<i64>, shape: Vec<i64>) -> Value {
 fn extract_tensor(val: &Value) -> Result<(Vec<i64>, Vec<i64>), RuntimeError> {
     let fields = match val {
         Value::Struct(f) => f,
+        Value::Tensor(t) => {
+            let shape = t.shape().iter().map(|&dim| dim as i64).collect();
+            return Ok((t.to_vec(), shape));
+        }
         _ => {
             return Err(RuntimeError::InvalidOperation(format!(
                 "Expected tensor (Struct), got {:?}",
@@ -4199,25 +8866,61 @@ fn intrinsic_math_matmul(args: Vec<Value>) -> Result<Value, RuntimeError> {
             "math.matmul expects 2D tensors".into(),
         ));
     }
-    let (m, k) = (a_shape[0] as usize, a_shape[1] as usize);
-    let (k2, n) = (b_shape[0] as usize, b_shape[1] as usize);
+    let (m, k) = matrix_dims(&a_data, &a_shape)?;
+    let (k2, n) = matrix_dims(&b_data, &b_shape)?;
     if k != k2 {
         return Err(RuntimeError::InvalidOperation(format!(
             "math.matmul dimension mismatch: {} vs {}",
             k, k2
         )));
     }
-    let mut result = vec![0i64; m * n];
-    for i in 0..m {
-        for j in 0..n {
-            let mut s = 0i64;
-            for p in 0..k {
-                s += a_data[i * k + p] * b_data[p * n + j];
+    let result = matmul_kernel(&a_data, &b_data, m, k, n);
+    Ok(make_tensor(result, vec![m as i64, n as i64]))
+}
+
+/// Rows and columns of a 2D tensor, checked against its data length.
+fn matrix_dims(data: &[i64], shape: &[i64]) -> Result<(usize, usize), RuntimeError> {
+    let dims = usize::try_from(shape[0])
+        .ok()
+        .zip(usize::try_from(shape[1]).ok());
+    match dims {
+        Some((rows, cols)) if rows.checked_mul(cols) == Some(data.len()) => Ok((rows, cols)),
+        _ => Err(RuntimeError::InvalidOperation(format!(
+            "Tensor shape {:?} does not fit {} elements",
+            shape,
+            data.len()
+        ))),
+    }
+}
+
+/// Side of the square tiles `matmul_kernel` works through, small enough
+/// that a tile of each operand stays in cache.
+const MATMUL_BLOCK: usize = 64;
+
+/// C = A×B for row-major A=[m,k] and B=[k,n], wrapping on overflow as the
+/// WASM lowering does. Tiles of A, B and C are multiplied in i-p-j order,
+/// so the innermost loop is a scaled row add over contiguous slices that
+/// the compiler turns into SIMD.
+fn matmul_kernel(a: &[i64], b: &[i64], m: usize, k: usize, n: usize) -> Vec<i64> {
+    let mut c = vec![0i64; m * n];
+    for i0 in (0..m).step_by(MATMUL_BLOCK) {
+        for p0 in (0..k).step_by(MATMUL_BLOCK) {
+            for j0 in (0..n).step_by(MATMUL_BLOCK) {
+                let j1 = (j0 + MATMUL_BLOCK).min(n);
+                for i in i0..(i0 + MATMUL_BLOCK).min(m) {
+                    let c_row = &mut c[i * n + j0..i * n + j1];
+                    for p in p0..(p0 + MATMUL_BLOCK).min(k) {
+                        let a_ip = a[i * k + p];
+                        let b_row = &b[p * n + j0..p * n + j1];
+                        for (c_ij, b_pj) in c_row.iter_mut().zip(b_row) {
+                            *c_ij = c_ij.wrapping_add(a_ip.wrapping_mul(*b_pj));
+                        }
+                    }
+                }
             }
-            result[i * n + j] = s;
         }
     }
-    Ok(make_tensor(result, vec![m as i64, n as i64]))
+    c
 }
 
 /// math.transpose(T) → Tensor. T=[m,n] → T'=[n,m]
@@ -4233,7 +8936,7 @@ fn intrinsic_math_transpose(args: Vec<Value>) -> Result<Value, RuntimeError> {
             "math.transpose expects a 2D tensor".into(),
         ));
     }
-    let (m, n) = (shape[0] as usize, shape[1] as usize);
+    let (m, n) = matrix_dims(&data, &shape)?;
     let mut result = vec![0i64; m * n];
     for j in 0..n {
         for i in 0..m {
@@ -4259,8 +8962,26 @@ fn intrinsic_math_dot(args: Vec<Value>) -> Result<Value, RuntimeError> {
             b_data.len()
         )));
     }
-    let s: i64 = a_data.iter().zip(b_data.iter()).map(|(a, b)| a * b).sum();
-    Ok(Value::Integer(s))
+    Ok(Value::Integer(dot_kernel(&a_data, &b_data)))
+}
+
+/// Sum of the products of `a` and `b`, wrapping on overflow as the WASM
+/// lowering does. Four independent accumulators keep the multiplies out
+/// of each other's way so they run in SIMD lanes.
+fn dot_kernel(a: &[i64], b: &[i64]) -> i64 {
+    let mut lanes = [0i64; 4];
+    let (a_chunks, b_chunks) = (a.chunks_exact(4), b.chunks_exact(4));
+    let tail = a_chunks
+        .remainder()
+        .iter()
+        .zip(b_chunks.remainder())
+        .fold(0i64, |acc, (x, y)| acc.wrapping_add(x.wrapping_mul(*y)));
+    for (xs, ys) in a_chunks.zip(b_chunks) {
+        for ((lane, x), y) in lanes.iter_mut().zip(xs).zip(ys) {
+            *lane = lane.wrapping_add(x.wrapping_mul(*y));
+        }
+    }
+    lanes.iter().fold(tail, |acc, lane| acc.wrapping_add(*lane))
 }
 
 /// math.add(a, b) → Tensor. Element-wise addition.
@@ -4699,6 +9420,436 @@ fn intrinsic_pmap_merge(args: Vec<Value>) -> Result<Value, RuntimeError> {
     }
 }
 
+// ============================================================================
+// BIG INTEGER INTRINSICS
+// ============================================================================
+
+/// The `count` operands of a `sys.bigint.*` call, each a BigInt or an
+/// Integer taken as one.
+fn bigint_args(intrinsic: &str, args: &[Value], count: usize) -> Result<Vec<BigInt>, RuntimeError> {
+    if args.len() != count {
+        return Err(RuntimeError::InvalidOperation(format!(
+            "{} expects {} argument(s)",
+            intrinsic, count
+        )));
+    }
+    args.iter()
+        .map(|arg| match arg {
+            Value::BigInt(n) => Ok(n.clone()),
+            Value::Integer(i) => Ok(BigInt::from(*i)),
+            _ => Err(RuntimeError::InvalidOperation(format!(
+                "{}: expects BigInt or Integer arguments",
+                intrinsic
+            ))),
+        })
+        .collect()
+}
+
+/// sys.bigint.from_string(s) → BigInt. Parse decimal digits with an
+/// optional leading `-`.
+fn intrinsic_bigint_from_string(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 1 {
+        return Err(RuntimeError::InvalidOperation(
+            "sys.bigint.from_string expects 1 argument: (string)".to_string(),
+        ));
+    }
+    match &args[0] {
+        Value::String(s) => s
+            .parse::<BigInt>()
+            .map(Value::BigInt)
+            .map_err(|e| RuntimeError::InvalidOperation(format!("sys.bigint.from_string: {}", e))),
+        _ => Err(RuntimeError::InvalidOperation(
+            "sys.bigint.from_string: argument must be a String".to_string(),
+        )),
+    }
+}
+
+/// sys.bigint.add(a, b) → BigInt.
+fn intrinsic_bigint_add(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let n = bigint_args("sys.bigint.add", &args, 2)?;
+    Ok(Value::BigInt(&n[0] + &n[1]))
+}
+
+/// sys.bigint.mul(a, b) → BigInt.
+fn intrinsic_bigint_mul(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let n = bigint_args("sys.bigint.mul", &args, 2)?;
+    Ok(Value::BigInt(&n[0] * &n[1]))
+}
+
+/// sys.bigint.mod(a, m) → BigInt. `a` modulo `m`, from 0 up to `|m|`
+/// whatever the signs.
+fn intrinsic_bigint_mod(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let n = bigint_args("sys.bigint.mod", &args, 2)?;
+    n[0].modulo(&n[1]).map(Value::BigInt).ok_or_else(|| {
+        RuntimeError::InvalidOperation("sys.bigint.mod: modulus is zero".to_string())
+    })
+}
+
+/// sys.bigint.pow_mod(base, exponent, m) → BigInt. `base` to the power
+/// `exponent`, modulo `m`; the exponent must not be negative.
+fn intrinsic_bigint_pow_mod(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let n = bigint_args("sys.bigint.pow_mod", &args, 3)?;
+    n[0].pow_mod(&n[1], &n[2])
+        .map(Value::BigInt)
+        .ok_or_else(|| {
+            RuntimeError::InvalidOperation(
+                "sys.bigint.pow_mod: modulus is zero or exponent negative".to_string(),
+            )
+        })
+}
+
+/// sys.bigint.cmp(a, b) → Integer. -1, 0 or 1 as `a` is less than, equal
+/// to or greater than `b`.
+fn intrinsic_bigint_cmp(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let n = bigint_args("sys.bigint.cmp", &args, 2)?;
+    Ok(Value::Integer(n[0].cmp(&n[1]) as i64))
+}
+
+/// sys.bigint.to_string(n) → String. Decimal digits, `-` first when negative.
+fn intrinsic_bigint_to_string(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let n = bigint_args("sys.bigint.to_string", &args, 1)?;
+    Ok(Value::String(n[0].to_string()))
+}
+
+// ============================================================================
+// DECIMAL INTRINSICS
+// ============================================================================
+
+/// The operand at `index` of a `dec.*` call, a Decimal or an Integer taken
+/// as one.
+fn decimal_arg(intrinsic: &str, args: &[Value], index: usize) -> Result<Decimal, RuntimeError> {
+    match args.get(index) {
+        Some(Value::Decimal(d)) => Ok(*d),
+        Some(Value::Integer(i)) => Ok(Decimal::from(*i)),
+        _ => Err(RuntimeError::InvalidOperation(format!(
+            "{}: expects Decimal or Integer arguments",
+            intrinsic
+        ))),
+    }
+}
+
+/// The digits-after-the-point operand at `index` of a `dec.*` call.
+fn decimal_places(intrinsic: &str, args: &[Value], index: usize) -> Result<u32, RuntimeError> {
+    match args.get(index) {
+        Some(Value::Integer(places)) if *places >= 0 => {
+            Ok(u32::try_from(*places).unwrap_or(u32::MAX))
+        }
+        _ => Err(RuntimeError::InvalidOperation(format!(
+            "{}: places must be a non-negative Integer",
+            intrinsic
+        ))),
+    }
+}
+
+fn decimal_result(
+    intrinsic: &str,
+    result: Result<Decimal, DecimalError>,
+) -> Result<Value, RuntimeError> {
+    result
+        .map(Value::Decimal)
+        .map_err(|e| RuntimeError::InvalidOperation(format!("{}: {}", intrinsic, e)))
+}
+
+/// Apply a two-operand `dec.*` operation.
+fn decimal_binary(
+    intrinsic: &str,
+    args: &[Value],
+    op: fn(&Decimal, &Decimal) -> Result<Decimal, DecimalError>,
+) -> Result<Value, RuntimeError> {
+    if args.len() != 2 {
+        return Err(RuntimeError::InvalidOperation(format!(
+            "{} expects 2 arguments",
+            intrinsic
+        )));
+    }
+    let a = decimal_arg(intrinsic, args, 0)?;
+    let b = decimal_arg(intrinsic, args, 1)?;
+    decimal_result(intrinsic, op(&a, &b))
+}
+
+/// dec.from_string(s) → Decimal. Digits with an optional leading `-` and
+/// fraction, keeping every fraction digit as scale: "12.50" is 12.50.
+fn intrinsic_dec_from_string(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    match args.as_slice() {
+        [Value::String(s)] => decimal_result("dec.from_string", s.parse()),
+        _ => Err(RuntimeError::InvalidOperation(
+            "dec.from_string expects 1 argument: (string)".to_string(),
+        )),
+    }
+}
+
+/// dec.add(a, b) → Decimal, at the larger scale of `a` and `b`.
+fn intrinsic_dec_add(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    decimal_binary("dec.add", &args, Decimal::checked_add)
+}
+
+/// dec.sub(a, b) → Decimal, at the larger scale of `a` and `b`.
+fn intrinsic_dec_sub(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    decimal_binary("dec.sub", &args, Decimal::checked_sub)
+}
+
+/// dec.mul(a, b) → Decimal, at the sum of the scales of `a` and `b`.
+fn intrinsic_dec_mul(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    decimal_binary("dec.mul", &args, Decimal::checked_mul)
+}
+
+/// dec.div(a, b[, places]) → Decimal. `a / b` rounded half away from zero
+/// to `places` digits, by default the larger scale of `a` and `b`.
+fn intrinsic_dec_div(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 2 && args.len() != 3 {
+        return Err(RuntimeError::InvalidOperation(
+            "dec.div expects 2 or 3 arguments: (a, b[, places])".to_string(),
+        ));
+    }
+    let a = decimal_arg("dec.div", &args, 0)?;
+    let b = decimal_arg("dec.div", &args, 1)?;
+    let places = if args.len() == 3 {
+        decimal_places("dec.div", &args, 2)?
+    } else {
+        a.scale().max(b.scale())
+    };
+    decimal_result("dec.div", a.checked_div(&b, places))
+}
+
+/// dec.round(d, places) → Decimal. `d` with exactly `places` digits after
+/// the point, rounded half away from zero.
+fn intrinsic_dec_round(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 2 {
+        return Err(RuntimeError::InvalidOperation(
+            "dec.round expects 2 arguments: (decimal, places)".to_string(),
+        ));
+    }
+    let d = decimal_arg("dec.round", &args, 0)?;
+    let places = decimal_places("dec.round", &args, 1)?;
+    decimal_result("dec.round", d.round(places))
+}
+
+// ============================================================================
+// TENSOR INTRINSICS
+// ============================================================================
+
+/// The operand at `index` of a `tensor.*` call: a Tensor, or a
+/// `math.Tensor` struct, which is converted.
+fn tensor_arg(intrinsic: &str, args: &[Value], index: usize) -> Result<Tensor, RuntimeError> {
+    match args.get(index) {
+        Some(Value::Tensor(t)) => Ok(t.clone()),
+        Some(v @ Value::Struct(_)) => {
+            let (data, shape) = extract_tensor(v)?;
+            let shape = tensor_dims(intrinsic, &shape)?;
+            tensor_result(intrinsic, Tensor::new(data, shape))
+        }
+        _ => Err(RuntimeError::InvalidOperation(format!(
+            "{}: expects Tensor arguments",
+            intrinsic
+        ))),
+    }
+}
+
+/// The Integers of the List operand at `index` of a `tensor.*` call.
+fn tensor_ints(intrinsic: &str, args: &[Value], index: usize) -> Result<Vec<i64>, RuntimeError> {
+    let invalid =
+        || RuntimeError::InvalidOperation(format!("{}: expects a List of Integers", intrinsic));
+    match args.get(index) {
+        Some(Value::List(items)) => items
+            .iter()
+            .map(|v| match v {
+                Value::Integer(n) => Ok(*n),
+                _ => Err(invalid()),
+            })
+            .collect(),
+        _ => Err(invalid()),
+    }
+}
+
+fn tensor_dims(intrinsic: &str, dims: &[i64]) -> Result<Vec<usize>, RuntimeError> {
+    dims.iter()
+        .map(|&dim| {
+            usize::try_from(dim).map_err(|_| {
+                RuntimeError::InvalidOperation(format!(
+                    "{}: {}",
+                    intrinsic,
+                    TensorError::InvalidShape(dims.to_vec())
+                ))
+            })
+        })
+        .collect()
+}
+
+fn tensor_result<T>(intrinsic: &str, result: Result<T, TensorError>) -> Result<T, RuntimeError> {
+    result.map_err(|e| RuntimeError::InvalidOperation(format!("{}: {}", intrinsic, e)))
+}
+
+fn int_list(items: impl IntoIterator<Item = i64>) -> Value {
+    Value::List(items.into_iter().map(Value::Integer).collect())
+}
+
+/// Apply an element-wise `tensor.*` operation, broadcasting the operands
+/// against each other. Either may be an Integer, taken as a scalar tensor.
+fn tensor_binary(
+    intrinsic: &str,
+    args: &[Value],
+    op: fn(i64, i64) -> i64,
+) -> Result<Value, RuntimeError> {
+    if args.len() != 2 {
+        return Err(RuntimeError::InvalidOperation(format!(
+            "{} expects 2 arguments",
+            intrinsic
+        )));
+    }
+    let operand = |index: usize| match &args[index] {
+        Value::Integer(n) => tensor_result(intrinsic, Tensor::new(vec![*n], Vec::new())),
+        _ => tensor_arg(intrinsic, args, index),
+    };
+    let (a, b) = (operand(0)?, operand(1)?);
+    tensor_result(intrinsic, a.zip_with(&b, op)).map(Value::Tensor)
+}
+
+/// tensor.new(data, shape) → Tensor. `data` is a List of Integers in
+/// row-major order whose length is the product of `shape`.
+fn intrinsic_tensor_new(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 2 {
+        return Err(RuntimeError::InvalidOperation(
+            "tensor.new expects 2 arguments: (data, shape)".to_string(),
+        ));
+    }
+    let data = tensor_ints("tensor.new", &args, 0)?;
+    let shape = tensor_dims("tensor.new", &tensor_ints("tensor.new", &args, 1)?)?;
+    tensor_result("tensor.new", Tensor::new(data, shape)).map(Value::Tensor)
+}
+
+/// tensor.shape(t) → List of the length of each axis.
+fn intrinsic_tensor_shape(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let t = tensor_arg("tensor.shape", &args, 0)?;
+    Ok(int_list(t.shape().iter().map(|&dim| dim as i64)))
+}
+
+/// tensor.data(t) → List of the elements in row-major order.
+fn intrinsic_tensor_data(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let t = tensor_arg("tensor.data", &args, 0)?;
+    Ok(int_list(t.to_vec()))
+}
+
+/// tensor.get(t, index) → Integer. `index` holds one coordinate per axis.
+fn intrinsic_tensor_get(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 2 {
+        return Err(RuntimeError::InvalidOperation(
+            "tensor.get expects 2 arguments: (tensor, index)".to_string(),
+        ));
+    }
+    let t = tensor_arg("tensor.get", &args, 0)?;
+    let index = tensor_ints("tensor.get", &args, 1)?;
+    index
+        .iter()
+        .map(|&i| usize::try_from(i).ok())
+        .collect::<Option<Vec<_>>>()
+        .and_then(|index| t.get(&index))
+        .map(Value::Integer)
+        .ok_or_else(|| {
+            RuntimeError::InvalidOperation(format!(
+                "tensor.get: index {:?} is out of range for shape {:?}",
+                index,
+                t.shape()
+            ))
+        })
+}
+
+/// tensor.add(a, b) → Tensor, broadcasting `a` and `b`.
+fn intrinsic_tensor_add(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    tensor_binary("tensor.add", &args, i64::wrapping_add)
+}
+
+/// tensor.sub(a, b) → Tensor, broadcasting `a` and `b`.
+fn intrinsic_tensor_sub(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    tensor_binary("tensor.sub", &args, i64::wrapping_sub)
+}
+
+/// tensor.mul(a, b) → Tensor. The element-wise product, broadcasting `a`
+/// and `b`; `math.matmul` is the matrix product.
+fn intrinsic_tensor_mul(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    tensor_binary("tensor.mul", &args, i64::wrapping_mul)
+}
+
+/// tensor.reshape(t, shape) → Tensor. One dimension of `shape` may be -1
+/// to take up whatever the others leave.
+fn intrinsic_tensor_reshape(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args.len() != 2 {
+        return Err(RuntimeError::InvalidOperation(
+            "tensor.reshape expects 2 arguments: (tensor, shape)".to_string(),
+        ));
+    }
+    let t = tensor_arg("tensor.reshape", &args, 0)?;
+    let shape = tensor_ints("tensor.reshape", &args, 1)?;
+    tensor_result("tensor.reshape", t.reshape(&shape)).map(Value::Tensor)
+}
+
+/// tensor.slice(t, axis, start, end) → Tensor. Indices `start..end` along
+/// `axis`; negative axes and bounds count back from the end.
+fn intrinsic_tensor_slice(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let t = tensor_arg("tensor.slice", &args, 0)?;
+    match &args[1..] {
+        [
+            Value::Integer(axis),
+            Value::Integer(start),
+            Value::Integer(end),
+        ] => tensor_result("tensor.slice", t.slice(*axis, *start, *end)).map(Value::Tensor),
+        _ => Err(RuntimeError::InvalidOperation(
+            "tensor.slice expects 4 arguments: (tensor, axis, start, end)".to_string(),
+        )),
+    }
+}
+
+/// tensor.sum(t[, axis]) → Integer, the sum of every element, or with an
+/// `axis` the Tensor of sums along it.
+fn intrinsic_tensor_sum(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let t = tensor_arg("tensor.sum", &args, 0)?;
+    match &args[1..] {
+        [] => Ok(Value::Integer(t.sum())),
+        [Value::Integer(axis)] => tensor_result("tensor.sum", t.sum_axis(*axis)).map(Value::Tensor),
+        _ => Err(RuntimeError::InvalidOperation(
+            "tensor.sum expects 1 or 2 arguments: (tensor[, axis])".to_string(),
+        )),
+    }
+}
+
+// ============================================================================
+// RESULT-RETURNING INTRINSICS
+// ============================================================================
+
+/// Wrap a fallible intrinsic's outcome in the built-in `Result` enum:
+/// `Ok(value)` on success, or `Err(error)` (see `RuntimeError::to_value`)
+/// for a failure Ark code may recover from. Security lockouts still abort.
+fn into_result(outcome: Result<Value, RuntimeError>) -> Result<Value, RuntimeError> {
+    match outcome {
+        Ok(value) => Ok(Value::ok(value)),
+        Err(err) if err.is_catchable() => Ok(Value::err(err.to_value())),
+        Err(err) => Err(err),
+    }
+}
+
+/// sys.fs.try_read(path) → Result
+/// `sys.fs.read` that returns `Err(error)` instead of failing.
+pub fn intrinsic_fs_try_read(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    into_result(intrinsic_fs_read(args))
+}
+
+/// sys.fs.try_write(path, content) → Result
+/// `sys.fs.write` that returns `Err(error)` instead of failing.
+pub fn intrinsic_fs_try_write(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    into_result(intrinsic_fs_write(args))
+}
+
+/// sys.json.try_parse(json_string) → Result
+/// `sys.json.parse` that returns `Err(error)` for malformed JSON.
+fn intrinsic_json_try_parse(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    into_result(intrinsic_json_parse(args))
+}
+
+/// net.http.try_request(method, url, ...) → Result
+/// `net.http.request` that returns `Err(error)` when the request fails.
+pub fn intrinsic_http_try_request(args: Vec<Value>) -> Result<Value, RuntimeError> {
+    into_result(intrinsic_http_request(args))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -4721,7 +9872,146 @@ mod tests {
 
         let args_join = vec![Value::Integer(id)];
         let join_res = intrinsic_thread_join(args_join).expect("operation failed");
-        assert_eq!(join_res, Value::Boolean(true));
+        match join_res {
+            Value::Struct(fields) => {
+                assert_eq!(fields.get("is_err"), Some(&Value::Boolean(false)));
+            }
+            other => panic!("Expected a result struct, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_thread_join_returns_value_or_error() {
+        let join = |args: Vec<Value>| {
+            let id = intrinsic_thread_spawn(args).expect("spawn failed");
+            match intrinsic_thread_join(vec![id]).expect("join failed") {
+                Value::Struct(fields) => (
+                    fields.get("is_err").cloned(),
+                    fields.get("val").cloned(),
+                    fields.get("err").cloned(),
+                ),
+                other => panic!("Expected a result struct, got {:?}", other),
+            }
+        };
+
+        let (is_err, val, _) = join(vec![
+            Value::String("intrinsic_add".to_string()),
+            Value::List(vec![Value::Integer(2), Value::Integer(3)]),
+        ]);
+        assert_eq!(is_err, Some(Value::Boolean(false)));
+        assert_eq!(val, Some(Value::Integer(5)));
+
+        let (is_err, _, err) = join(vec![Value::String("no_such_intrinsic".to_string())]);
+        assert_eq!(is_err, Some(Value::Boolean(true)));
+        assert!(matches!(err, Some(Value::String(e)) if e.contains("no_such_intrinsic")));
+
+        let id = intrinsic_thread_spawn(vec![Value::NativeFunction(|_| panic!("boom"))])
+            .expect("spawn failed");
+        match intrinsic_thread_join(vec![id.clone()]).expect("join failed") {
+            Value::Struct(fields) => {
+                assert_eq!(fields.get("is_err"), Some(&Value::Boolean(true)));
+                assert_eq!(
+                    fields.get("err"),
+                    Some(&Value::String("thread panicked: boom".to_string()))
+                );
+            }
+            other => panic!("Expected a result struct, got {:?}", other),
+        }
+        assert!(intrinsic_thread_join(vec![id]).is_err());
+        assert!(intrinsic_thread_spawn(vec![Value::Integer(1)]).is_err());
+    }
+
+    #[test]
+    fn test_capabilities_restrict_intrinsics_and_threads() {
+        let read_in_thread = || {
+            let id = intrinsic_thread_spawn(vec![
+                Value::NativeFunction(intrinsic_fs_read),
+                Value::List(vec![Value::String("Cargo.toml".to_string())]),
+            ])
+            .expect("spawn failed");
+            match intrinsic_thread_join(vec![id]).expect("join failed") {
+                Value::Struct(fields) => fields.get("is_err").cloned(),
+                other => panic!("Expected a result struct, got {:?}", other),
+            }
+        };
+        assert_eq!(read_in_thread(), Some(Value::Boolean(false)));
+
+        // A thread inherits its parent's restrictions
+        set_capabilities(Some(Capabilities {
+            threads: true,
+            ..Capabilities::none()
+        }));
+        assert_eq!(read_in_thread(), Some(Value::Boolean(true)));
+        assert!(matches!(
+            intrinsic_socket_connect(vec![
+                Value::String("127.0.0.1".to_string()),
+                Value::Integer(9),
+            ]),
+            Err(RuntimeError::NotExecutable)
+        ));
+        assert!(intrinsic_exec(vec![Value::String("echo hi".to_string())]).is_err());
+
+        set_capabilities(Some(Capabilities::none()));
+        assert!(intrinsic_thread_spawn(vec![Value::String("intrinsic_add".to_string())]).is_err());
+        set_capabilities(None);
+        assert_eq!(capabilities(), Capabilities::host_default());
+    }
+
+    #[test]
+    fn test_resource_limits_bound_open_handles() {
+        let limit = |resource, limit| RuntimeError::LimitExceeded { resource, limit };
+        set_resource_limits(Some(ResourceLimits {
+            max_sockets: Some(0),
+            ..ResourceLimits::default()
+        }));
+        assert_eq!(
+            intrinsic_socket_bind(vec![Value::Integer(0)]).map_err(|e| e.to_string()),
+            Err(limit(Resource::Sockets, 0).to_string())
+        );
+
+        // A thread inherits its parent's limits
+        let id = intrinsic_thread_spawn(vec![
+            Value::NativeFunction(intrinsic_socket_bind),
+            Value::List(vec![Value::Integer(0)]),
+        ])
+        .expect("spawn failed");
+        match intrinsic_thread_join(vec![id]).expect("join failed") {
+            Value::Struct(fields) => {
+                assert_eq!(fields.get("is_err"), Some(&Value::Boolean(true)));
+                assert_eq!(
+                    fields.get("err"),
+                    Some(&Value::String(limit(Resource::Sockets, 0).to_string()))
+                );
+            }
+            other => panic!("Expected a result struct, got {:?}", other),
+        }
+
+        set_resource_limits(Some(ResourceLimits {
+            max_threads: Some(0),
+            ..ResourceLimits::default()
+        }));
+        assert_eq!(
+            intrinsic_thread_spawn(vec![Value::String("intrinsic_add".to_string())])
+                .map_err(|e| e.to_string()),
+            Err(limit(Resource::Threads, 0).to_string())
+        );
+        set_resource_limits(None);
+        assert_eq!(resource_limits(), ResourceLimits::default());
+    }
+
+    #[test]
+    fn test_url_host_port() {
+        let parse = url_host_port;
+        assert_eq!(
+            parse("https://api.example.com/v1?q=1"),
+            Some(("api.example.com".to_string(), 443))
+        );
+        assert_eq!(
+            parse("http://user:pw@localhost:8080"),
+            Some(("localhost".to_string(), 8080))
+        );
+        assert_eq!(parse("http://[::1]:81/"), Some(("[::1]".to_string(), 81)));
+        assert_eq!(parse("localhost:8080"), None);
     }
 
     #[test]
@@ -4739,6 +10029,228 @@ mod tests {
         assert_eq!(res_empty, Value::Unit);
     }
 
+    #[test]
+    fn test_try_intrinsics_return_result_values() {
+        let code = |value: &Value| match value {
+            Value::EnumValue {
+                enum_name,
+                variant,
+                fields,
+            } if enum_name == "Result" && variant == "Err" => match &fields[0] {
+                Value::Struct(error) => error.get("code").cloned(),
+                other => panic!("Expected an error struct, got {:?}", other),
+            },
+            other => panic!("Expected Err, got {:?}", other),
+        };
+
+        assert_eq!(
+            intrinsic_json_try_parse(vec![Value::String("[1, 2]".to_string())])
+                .expect("try_parse failed"),
+            Value::ok(Value::List(vec![Value::Integer(1), Value::Integer(2)]))
+        );
+        let malformed = intrinsic_json_try_parse(vec![Value::String("".to_string())])
+            .expect("try_parse failed");
+        assert_eq!(
+            code(&malformed),
+            Some(Value::String("InvalidOperation".to_string()))
+        );
+
+        let missing = intrinsic_fs_try_read(vec![Value::String(
+            "no_such_file_for_try_read.txt".to_string(),
+        )])
+        .expect("try_read failed");
+        assert_eq!(code(&missing), Some(Value::String("IoError".to_string())));
+    }
+
+    #[test]
+    fn test_timer_cancel_and_argument_checks() {
+        let handler = Value::NativeFunction(intrinsic_event_poll);
+        let id = intrinsic_timer_after(vec![Value::Integer(60_000), handler.clone()])
+            .expect("after failed");
+        assert_eq!(
+            intrinsic_timer_cancel(vec![id.clone()]).expect("cancel failed"),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            intrinsic_timer_cancel(vec![id]).expect("cancel failed"),
+            Value::Boolean(false)
+        );
+        assert!(intrinsic_timer_every(vec![Value::Integer(0), handler.clone()]).is_err());
+        assert!(intrinsic_timer_after(vec![Value::Integer(-1), handler]).is_err());
+        assert!(intrinsic_timer_after(vec![Value::Integer(10), Value::Integer(1)]).is_err());
+    }
+
+    #[test]
+    fn test_fire_due_timers_reschedules_repeating_timers() {
+        let start = std::time::Instant::now();
+        let ms = Duration::from_millis;
+        let handler = |name: &str| Value::String(name.to_string());
+        let mut timers = HashMap::new();
+        timers.insert(
+            1,
+            Timer {
+                due: start + ms(20),
+                interval: None,
+                handler: handler("once"),
+            },
+        );
+        timers.insert(
+            2,
+            Timer {
+                due: start + ms(10),
+                interval: Some(ms(10)),
+                handler: handler("tick"),
+            },
+        );
+
+        let (fired, next) = fire_due_timers(&mut timers, start + ms(5));
+        assert!(fired.is_empty());
+        assert_eq!(next, Some(start + ms(10)));
+
+        let event = |name: &str| Value::List(vec![handler(name), Value::List(vec![])]);
+        let (fired, next) = fire_due_timers(&mut timers, start + ms(20));
+        assert_eq!(fired, vec![event("tick"), event("once")]);
+        assert_eq!(next, Some(start + ms(30)));
+        assert_eq!(timers.len(), 1);
+
+        // Missed ticks are skipped rather than delivered in a burst
+        let (fired, next) = fire_due_timers(&mut timers, start + ms(95));
+        assert_eq!(fired, vec![event("tick")]);
+        assert_eq!(next, Some(start + ms(105)));
+    }
+
+    #[test]
+    fn test_chan_send_recv_across_threads() {
+        let chan = intrinsic_chan_new(vec![Value::Integer(1)]).expect("new failed");
+        assert_eq!(
+            intrinsic_chan_try_recv(vec![chan.clone()]).expect("try_recv failed"),
+            Value::List(vec![Value::Boolean(false), Value::Unit])
+        );
+
+        // With room for one value the producer has to wait for the consumer
+        let producer_chan = chan.clone();
+        let producer = thread::spawn(move || {
+            for i in 1..=3 {
+                intrinsic_chan_send(vec![producer_chan.clone(), Value::Integer(i)])
+                    .expect("send failed");
+            }
+            intrinsic_chan_close(vec![producer_chan]).expect("close failed")
+        });
+        for i in 1..=3 {
+            assert_eq!(
+                intrinsic_chan_recv(vec![chan.clone()]).expect("recv failed"),
+                Value::List(vec![Value::Boolean(true), Value::Integer(i)])
+            );
+        }
+        assert_eq!(
+            producer.join().expect("producer panicked"),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            intrinsic_chan_recv(vec![chan.clone()]).expect("recv failed"),
+            Value::List(vec![Value::Boolean(false), Value::Unit])
+        );
+        assert!(intrinsic_chan_send(vec![chan.clone(), Value::Integer(4)]).is_err());
+        assert_eq!(
+            intrinsic_chan_close(vec![chan]).expect("close failed"),
+            Value::Boolean(false)
+        );
+
+        // Buffer views are copied, not shared with the receiver
+        let chan = intrinsic_chan_new(vec![Value::Integer(1)]).expect("new failed");
+        let backing = Arc::new(RwLock::new(vec![1u8, 2, 3]));
+        let view = Value::BufferView {
+            backing: backing.clone(),
+            offset: 1,
+            len: 2,
+        };
+        intrinsic_chan_send(vec![chan.clone(), view]).expect("send failed");
+        backing.write().expect("lock poisoned")[1] = 99;
+        match intrinsic_chan_recv(vec![chan]).expect("recv failed") {
+            Value::List(pair) => match &pair[1] {
+                Value::BufferView {
+                    backing, offset, ..
+                } => {
+                    assert_eq!(*offset, 0);
+                    assert_eq!(*backing.read().expect("lock poisoned"), vec![2, 3]);
+                }
+                other => panic!("Expected a BufferView, got {:?}", other),
+            },
+            other => panic!("Expected a pair, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_sync_primitives_share_state_between_threads() {
+        let counter = intrinsic_sync_atomic_new(vec![]).expect("atomic_new failed");
+        let total = intrinsic_sync_mutex_new(vec![Value::Integer(0)]).expect("mutex_new failed");
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let (counter, total) = (counter.clone(), total.clone());
+                thread::spawn(move || {
+                    for _ in 0..50 {
+                        intrinsic_sync_atomic_add(vec![counter.clone(), Value::Integer(2)])
+                            .expect("atomic_add failed");
+                        let n = match intrinsic_sync_mutex_lock(vec![total.clone()]) {
+                            Ok(Value::Integer(n)) => n,
+                            other => panic!("Expected Integer, got {:?}", other),
+                        };
+                        intrinsic_sync_mutex_unlock(vec![total.clone(), Value::Integer(n + 1)])
+                            .expect("mutex_unlock failed");
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().expect("worker panicked");
+        }
+        assert_eq!(
+            intrinsic_sync_atomic_load(vec![counter.clone()]).expect("atomic_load failed"),
+            Value::Integer(400)
+        );
+        intrinsic_sync_atomic_store(vec![counter.clone(), Value::Integer(5)])
+            .expect("atomic_store failed");
+        assert_eq!(
+            intrinsic_sync_atomic_add(vec![counter, Value::Integer(-1)]).expect("add failed"),
+            Value::Integer(4)
+        );
+
+        assert_eq!(
+            intrinsic_sync_mutex_lock(vec![total.clone()]).expect("mutex_lock failed"),
+            Value::Integer(200)
+        );
+        // Relocking on the holding thread would deadlock
+        assert!(intrinsic_sync_mutex_lock(vec![total.clone()]).is_err());
+        intrinsic_sync_mutex_unlock(vec![total.clone()]).expect("mutex_unlock failed");
+        assert!(intrinsic_sync_mutex_unlock(vec![total.clone()]).is_err());
+        assert!(intrinsic_sync_rwlock_read(vec![total]).is_err());
+
+        let cache = intrinsic_sync_rwlock_new(vec![Value::String("old".to_string())])
+            .expect("rwlock_new failed");
+        assert_eq!(
+            intrinsic_sync_rwlock_read(vec![cache.clone()]).expect("read failed"),
+            Value::String("old".to_string())
+        );
+        assert!(
+            intrinsic_sync_rwlock_unlock(vec![cache.clone(), Value::String("x".to_string())])
+                .is_err()
+        );
+        intrinsic_sync_rwlock_unlock(vec![cache.clone()]).expect("unlock failed");
+        let writer_cache = cache.clone();
+        thread::spawn(move || {
+            intrinsic_sync_rwlock_write(vec![writer_cache.clone()]).expect("write failed");
+            intrinsic_sync_rwlock_unlock(vec![writer_cache, Value::String("new".to_string())])
+                .expect("unlock failed");
+        })
+        .join()
+        .expect("writer panicked");
+        assert_eq!(
+            intrinsic_sync_rwlock_read(vec![cache.clone()]).expect("read failed"),
+            Value::String("new".to_string())
+        );
+        intrinsic_sync_rwlock_unlock(vec![cache]).expect("unlock failed");
+    }
+
     #[test]
     fn test_func_apply_native() {
         // Apply "intrinsic_add" with [1, 2]
@@ -4750,6 +10262,95 @@ mod tests {
         assert_eq!(res, Value::Integer(3));
     }
 
+    #[test]
+    fn test_assert_throws() {
+        fn fails(_: Vec<Value>) -> Result<Value, RuntimeError> {
+            Err(RuntimeError::InvalidOperation("disk full".to_string()))
+        }
+        fn succeeds(_: Vec<Value>) -> Result<Value, RuntimeError> {
+            Ok(Value::Integer(1))
+        }
+
+        let res = intrinsic_assert_throws(vec![Value::NativeFunction(fails)])
+            .expect("erroring fn should pass");
+        assert_eq!(
+            res,
+            Value::String("Invalid operation: disk full".to_string())
+        );
+        intrinsic_assert_throws(vec![
+            Value::NativeFunction(fails),
+            Value::String("disk \\w+".to_string()),
+        ])
+        .expect("matching error should pass");
+
+        assert!(matches!(
+            intrinsic_assert_throws(vec![Value::NativeFunction(succeeds)]),
+            Err(RuntimeError::AssertionFailed(_))
+        ));
+        assert!(matches!(
+            intrinsic_assert_throws(vec![
+                Value::NativeFunction(fails),
+                Value::String("timeout".to_string()),
+            ]),
+            Err(RuntimeError::AssertionFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_assertions_are_recorded_during_a_test() {
+        fn fails(_: Vec<Value>) -> Result<Value, RuntimeError> {
+            Err(RuntimeError::InvalidOperation("disk full".to_string()))
+        }
+
+        // Outside a test a failed assertion is an error
+        assert!(matches!(
+            intrinsic_assert(vec![Value::Boolean(false)]),
+            Err(RuntimeError::AssertionFailed(_))
+        ));
+
+        begin_test();
+        assert_eq!(
+            intrinsic_assert(vec![Value::Boolean(true)]).expect("assert failed"),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            intrinsic_assert(vec![
+                Value::Boolean(false),
+                Value::String("must hold".to_string()),
+            ])
+            .expect("assert failed"),
+            Value::Boolean(false)
+        );
+        intrinsic_assert_eq(vec![Value::Integer(1), Value::Integer(2)]).expect("assert_eq failed");
+        let left: Vec<Value> = (1..=6).map(Value::Integer).collect();
+        let mut right = left.clone();
+        right[2] = Value::Integer(30);
+        intrinsic_assert_eq(vec![Value::List(left), Value::List(right)]).expect("assert_eq failed");
+        intrinsic_assert_err(vec![Value::err(Value::String("no".to_string()))])
+            .expect("assert_err failed");
+        intrinsic_assert_err(vec![Value::NativeFunction(fails)]).expect("assert_err failed");
+        intrinsic_assert_err(vec![Value::ok(Value::Integer(1))]).expect("assert_err failed");
+        let failures = end_test();
+
+        assert_eq!(failures.len(), 4, "{:?}", failures);
+        assert_eq!(failures[0], "assertion failed: must hold");
+        assert_eq!(failures[1], "assertion failed\n  left:  1\n  right: 2");
+        assert!(failures[2].contains("  - "), "{}", failures[2]);
+        assert!(failures[2].contains("  + "), "{}", failures[2]);
+        assert!(failures[3].contains("expected an error"), "{}", failures[3]);
+        assert!(end_test().is_empty());
+    }
+
+    #[test]
+    fn test_bench_black_box_returns_its_argument() {
+        let value = Value::List(vec![Value::Integer(1), Value::String("x".to_string())]);
+        assert_eq!(
+            intrinsic_bench_black_box(vec![value.clone()]).expect("black_box failed"),
+            value
+        );
+        assert!(intrinsic_bench_black_box(vec![]).is_err());
+    }
+
     #[test]
     fn test_time_now() {
         let res = intrinsic_time_now(vec![]);
@@ -4783,6 +10384,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_math_rounding_positive_and_negative() {
+        let call = |f: NativeFn, n: i64| f(vec![Value::Integer(n)]).expect("rounding failed");
+        // 2.5 / -2.5: midpoints round away from zero
+        assert_eq!(call(intrinsic_math_round, 25000), Value::Integer(3));
+        assert_eq!(call(intrinsic_math_round, -25000), Value::Integer(-3));
+        assert_eq!(call(intrinsic_math_round, 24999), Value::Integer(2));
+        assert_eq!(call(intrinsic_math_round, -24999), Value::Integer(-2));
+        // 2.7 / -2.7
+        assert_eq!(call(intrinsic_math_floor, 27000), Value::Integer(2));
+        assert_eq!(call(intrinsic_math_floor, -27000), Value::Integer(-3));
+        assert_eq!(call(intrinsic_math_ceil, 27000), Value::Integer(3));
+        assert_eq!(call(intrinsic_math_ceil, -27000), Value::Integer(-2));
+        assert_eq!(call(intrinsic_math_trunc, 27000), Value::Integer(2));
+        assert_eq!(call(intrinsic_math_trunc, -27000), Value::Integer(-2));
+        // Whole numbers are unchanged
+        assert_eq!(call(intrinsic_math_ceil, 30000), Value::Integer(3));
+        assert_eq!(call(intrinsic_math_floor, -30000), Value::Integer(-3));
+    }
+
+    #[test]
+    fn test_math_round_to_two_decimals() {
+        let round_to =
+            |n: i64, d: i64| intrinsic_math_round_to(vec![Value::Integer(n), Value::Integer(d)]);
+        // 3.1416 -> 3.14, -3.1456 -> -3.15, 2.675 -> 2.68
+        assert_eq!(round_to(31416, 2).expect("round_to"), Value::Integer(31400));
+        assert_eq!(
+            round_to(-31456, 2).expect("round_to"),
+            Value::Integer(-31500)
+        );
+        assert_eq!(round_to(26750, 2).expect("round_to"), Value::Integer(26800));
+        assert_eq!(round_to(31416, 4).expect("round_to"), Value::Integer(31416));
+        assert!(round_to(31416, 5).is_err());
+    }
+
     #[test]
     fn test_math_sqrt() {
         // sqrt(16) = 4
@@ -4888,6 +10524,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_buffer_view_aliases_backing() {
+        let whole = intrinsic_buffer_view(vec![
+            Value::Buffer(vec![10, 11, 12, 13, 14, 15]),
+            Value::Integer(0),
+            Value::Integer(6),
+        ])
+        .expect("view failed");
+        let window =
+            intrinsic_buffer_view(vec![whole.clone(), Value::Integer(2), Value::Integer(3)])
+                .expect("view failed");
+
+        assert_eq!(
+            intrinsic_len(vec![window.clone()]).expect("len failed"),
+            Value::List(vec![Value::Integer(3), window.clone()])
+        );
+        match intrinsic_buffer_read(vec![window.clone(), Value::Integer(0)]) {
+            Ok(Value::List(items)) => assert_eq!(items[0], Value::Integer(12)),
+            other => panic!("Expected List, got {:?}", other),
+        }
+        assert!(intrinsic_buffer_read(vec![window.clone(), Value::Integer(3)]).is_err());
+
+        // A write through the window lands in the shared backing
+        intrinsic_buffer_write(vec![window, Value::Integer(1), Value::Integer(99)])
+            .expect("write failed");
+        assert_eq!(whole.view_bytes(), Some(vec![10, 11, 12, 99, 14, 15]));
+        match &whole {
+            Value::BufferView { backing, .. } => {
+                assert_eq!(backing.read().expect("lock poisoned")[3], 99)
+            }
+            other => panic!("Expected BufferView, got {:?}", other),
+        }
+
+        assert!(intrinsic_buffer_view(vec![whole, Value::Integer(4), Value::Integer(3)]).is_err());
+    }
+
     #[test]
     fn test_security_fs_write_traversal() {
         // [MODE: KINETIC_EXECUTION]
@@ -4938,6 +10610,87 @@ mod tests {
         std::fs::remove_file(file_name).expect("operation failed");
     }
 
+    #[test]
+    fn test_fs_json_round_trip() {
+        let file_name = "intrinsics_test_round_trip.json";
+        let _ = std::fs::remove_file(file_name);
+
+        let mut fields = HashMap::new();
+        fields.insert("name".to_string(), Value::String("ark \"core\"\n".to_string()));
+        fields.insert(
+            "tags".to_string(),
+            Value::List(vec![Value::Integer(1), Value::Boolean(true), Value::Unit]),
+        );
+        let original = Value::Struct(fields);
+
+        for pretty in [false, true] {
+            intrinsic_fs_write_json(vec![
+                Value::String(file_name.to_string()),
+                original.clone(),
+                Value::Boolean(pretty),
+            ])
+            .expect("write_json failed");
+            let read = intrinsic_fs_read_json(vec![Value::String(file_name.to_string())])
+                .expect("read_json failed");
+            assert_eq!(read, original);
+        }
+        std::fs::remove_file(file_name).expect("operation failed");
+    }
+
+    #[test]
+    fn test_fs_read_json_malformed_names_file() {
+        let file_name = "intrinsics_test_malformed.json";
+        std::fs::write(file_name, "{\"a\": 1,").expect("operation failed");
+
+        let res = intrinsic_fs_read_json(vec![Value::String(file_name.to_string())]);
+        std::fs::remove_file(file_name).expect("operation failed");
+        match res {
+            Err(RuntimeError::InvalidOperation(msg)) => {
+                assert!(msg.starts_with(file_name), "{}", msg)
+            }
+            other => panic!("Expected path-tagged parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_fs_streaming_read() {
+        let file_name = "intrinsics_test_stream.txt";
+        std::fs::write(file_name, "hello, world").expect("operation failed");
+
+        let file =
+            intrinsic_fs_open(vec![Value::String(file_name.to_string())]).expect("open failed");
+        let read_chunk = |file: Value, max: i64| match intrinsic_fs_read_chunk(vec![
+            file,
+            Value::Integer(max),
+        ]) {
+            Ok(Value::List(mut pair)) if pair.len() == 2 => {
+                let file = pair.pop().expect("file");
+                (pair.pop().expect("chunk"), file)
+            }
+            other => panic!("Expected [chunk, file], got {:?}", other),
+        };
+        let (chunk, file) = read_chunk(file, 5);
+        assert_eq!(chunk, Value::String("hello".to_string()));
+        let (chunk, file) = read_chunk(file, 100);
+        assert_eq!(chunk, Value::String(", world".to_string()));
+        let (chunk, file) = read_chunk(file, 100);
+        assert_eq!(chunk, Value::String(String::new()));
+
+        let file = intrinsic_fs_seek(vec![file, Value::Integer(7)]).expect("seek failed");
+        let (chunk, file) = read_chunk(file, 3);
+        assert_eq!(chunk, Value::String("wor".to_string()));
+
+        assert!(matches!(
+            intrinsic_fs_close(vec![file.clone()]),
+            Ok(Value::Unit)
+        ));
+        std::fs::remove_file(file_name).expect("operation failed");
+        match intrinsic_fs_read_chunk(vec![file, Value::Integer(1)]) {
+            Err(RuntimeError::InvalidOperation(msg)) => assert!(msg.contains("closed"), "{}", msg),
+            other => panic!("Expected a closed-file error, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_security_fs_read_traversal() {
         let file_name = "../Cargo.toml";
@@ -4984,6 +10737,114 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_list_zip_equal_length() {
+        let a = Value::List(vec![Value::Integer(1), Value::Integer(2)]);
+        let b = Value::List(vec![
+            Value::String("x".to_string()),
+            Value::String("y".to_string()),
+        ]);
+        let res = intrinsic_list_zip(vec![a, b]).expect("zip failed");
+        assert_eq!(
+            res,
+            Value::List(vec![
+                Value::List(vec![Value::Integer(1), Value::String("x".to_string())]),
+                Value::List(vec![Value::Integer(2), Value::String("y".to_string())]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_list_zip_truncates_to_shorter() {
+        let a = Value::List(vec![
+            Value::Integer(1),
+            Value::Integer(2),
+            Value::Integer(3),
+        ]);
+        let b = Value::List(vec![Value::Integer(10)]);
+        let res = intrinsic_list_zip(vec![a.clone(), b.clone()]).expect("zip failed");
+        assert_eq!(
+            res,
+            Value::List(vec![Value::List(vec![
+                Value::Integer(1),
+                Value::Integer(10)
+            ])])
+        );
+        let res = intrinsic_list_zip(vec![b, a]).expect("zip failed");
+        assert_eq!(
+            res,
+            Value::List(vec![Value::List(vec![
+                Value::Integer(10),
+                Value::Integer(1)
+            ])])
+        );
+        assert!(intrinsic_list_zip(vec![Value::Integer(1), Value::List(vec![])]).is_err());
+    }
+
+    #[test]
+    fn test_list_enumerate() {
+        let list = Value::List(vec![
+            Value::String("a".to_string()),
+            Value::String("b".to_string()),
+            Value::String("c".to_string()),
+        ]);
+        let res = intrinsic_list_enumerate(vec![list]).expect("enumerate failed");
+        match res {
+            Value::List(pairs) => {
+                assert_eq!(pairs.len(), 3);
+                assert_eq!(
+                    pairs[2],
+                    Value::List(vec![Value::Integer(2), Value::String("c".to_string())])
+                );
+                assert_eq!(
+                    pairs[0],
+                    Value::List(vec![Value::Integer(0), Value::String("a".to_string())])
+                );
+            }
+            _ => panic!("Expected List result"),
+        }
+    }
+
+    fn ints(values: &[i64]) -> Value {
+        Value::List(values.iter().map(|v| Value::Integer(*v)).collect())
+    }
+
+    #[test]
+    fn test_list_chunk_last_is_shorter() {
+        let list = ints(&[1, 2, 3, 4, 5, 6, 7]);
+        let res = intrinsic_list_chunk(vec![list, Value::Integer(3)]).expect("chunk failed");
+        assert_eq!(
+            res,
+            Value::List(vec![ints(&[1, 2, 3]), ints(&[4, 5, 6]), ints(&[7])])
+        );
+    }
+
+    #[test]
+    fn test_list_window_pairs() {
+        let list = ints(&[1, 2, 3, 4]);
+        let res = intrinsic_list_window(vec![list, Value::Integer(2)]).expect("window failed");
+        assert_eq!(
+            res,
+            Value::List(vec![ints(&[1, 2]), ints(&[2, 3]), ints(&[3, 4])])
+        );
+        // A window larger than the list yields no windows
+        let res =
+            intrinsic_list_window(vec![ints(&[1]), Value::Integer(2)]).expect("window failed");
+        assert_eq!(res, Value::List(vec![]));
+    }
+
+    #[test]
+    fn test_list_chunk_and_window_reject_non_positive_size() {
+        assert!(matches!(
+            intrinsic_list_chunk(vec![ints(&[1, 2]), Value::Integer(0)]),
+            Err(RuntimeError::InvalidOperation(_))
+        ));
+        assert!(matches!(
+            intrinsic_list_window(vec![ints(&[1, 2]), Value::Integer(-1)]),
+            Err(RuntimeError::InvalidOperation(_))
+        ));
+    }
+
     #[test]
     fn test_time_sleep() {
         let args = vec![Value::Integer(10)];
@@ -5023,6 +10884,42 @@ mod tests {
         let _ = std::fs::remove_file(filename);
     }
 
+    #[test]
+    fn test_string_utf8_intrinsics() {
+        let s = || Value::String("héllo, мир".to_string());
+        assert!(matches!(
+            intrinsic_string_char_len(vec![s()]),
+            Ok(Value::Integer(10))
+        ));
+        match intrinsic_string_char_at(vec![s(), Value::Integer(1)]) {
+            Ok(Value::String(c)) => assert_eq!(c, "é"),
+            other => panic!("Expected a character, got {:?}", other),
+        }
+        match intrinsic_string_slice_chars(vec![s(), Value::Integer(7), Value::Integer(10)]) {
+            Ok(Value::String(sub)) => assert_eq!(sub, "мир"),
+            other => panic!("Expected a substring, got {:?}", other),
+        }
+        match intrinsic_string_to_upper(vec![s()]) {
+            Ok(Value::String(up)) => assert_eq!(up, "HÉLLO, МИР"),
+            other => panic!("Expected a string, got {:?}", other),
+        }
+        match intrinsic_string_to_lower(vec![Value::String("HÉLLO, МИР".to_string())]) {
+            Ok(Value::String(low)) => assert_eq!(low, "héllo, мир"),
+            other => panic!("Expected a string, got {:?}", other),
+        }
+
+        // Indices count characters, so the end of the string is in range
+        // for a slice but not for a single character
+        assert!(intrinsic_string_char_at(vec![s(), Value::Integer(10)]).is_err());
+        assert!(
+            intrinsic_string_slice_chars(vec![s(), Value::Integer(10), Value::Integer(10)]).is_ok()
+        );
+        assert!(
+            intrinsic_string_slice_chars(vec![s(), Value::Integer(3), Value::Integer(2)]).is_err()
+        );
+        assert!(intrinsic_string_char_at(vec![s(), Value::Integer(-1)]).is_err());
+    }
+
     #[test]
     fn test_extract_code_blocks() {
         let md = "Start\n```rust\nfn main() {}\n```\nMid\n```\nraw\n```\nEnd";
@@ -5060,6 +10957,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_crypto_sha256_keccak256_blake3() {
+        let digest = |f: fn(Vec<Value>) -> Result<Value, RuntimeError>, data: Value| {
+            f(vec![data]).expect("digest failed")
+        };
+        let abc = || Value::String("abc".to_string());
+        let hex = |h: &str| Value::String(h.to_string());
+        assert_eq!(
+            digest(intrinsic_crypto_sha256, abc()),
+            hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+        // Keccak-256, not SHA3-256 ("a7ffc6f8...")
+        assert_eq!(
+            digest(intrinsic_crypto_keccak256, Value::String(String::new())),
+            hex("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+        );
+        assert_eq!(
+            digest(intrinsic_crypto_keccak256, abc()),
+            hex("4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45")
+        );
+        assert_eq!(
+            digest(intrinsic_crypto_blake3, Value::Buffer(b"abc".to_vec())),
+            hex("6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85")
+        );
+        assert!(intrinsic_crypto_sha256(vec![Value::Integer(1)]).is_err());
+        assert!(intrinsic_crypto_blake3(vec![]).is_err());
+    }
+
     #[test]
     fn test_extract_code_empty() {
         let md = "No code blocks here.";
@@ -5087,6 +11012,100 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_args_and_env_intrinsics() {
+        set_script_args(vec!["prog.ark".to_string(), "--fast".to_string()]);
+        let count = intrinsic_args_count(vec![]).expect("count");
+        assert_eq!(count, Value::Integer(script_args().len() as i64));
+        let first = intrinsic_args_get(vec![Value::Integer(0)]).expect("argv[0]");
+        assert_eq!(first, Value::String(script_args()[0].clone()));
+        assert!(intrinsic_args_get(vec![Value::Integer(-1)]).is_err());
+        assert!(intrinsic_args_get(vec![Value::Integer(1 << 40)]).is_err());
+
+        // PATH is set wherever the tests run; the other name never is
+        let path = intrinsic_env_get(vec![Value::String("PATH".to_string())]).expect("PATH");
+        assert_eq!(
+            path,
+            Value::String(std::env::var("PATH").unwrap_or_default())
+        );
+        let unset = intrinsic_env_get(vec![Value::String("ARK_SURELY_UNSET_VAR".to_string())])
+            .expect("unset var");
+        assert_eq!(unset, Value::String(String::new()));
+        assert!(intrinsic_env_get(vec![Value::Integer(1)]).is_err());
+    }
+
+    #[test]
+    fn test_log_levels_and_fields() {
+        let captured = |args: Vec<Value>| {
+            LOG_CAPTURE.with(|c| *c.borrow_mut() = Some(Vec::new()));
+            intrinsic_log(args).expect("sys.log failed");
+            LOG_CAPTURE.with(|c| c.borrow_mut().take().unwrap_or_default())
+        };
+        let text = |s: &str| Value::String(s.to_string());
+
+        set_log_level(Some("warn"));
+        assert!(captured(vec![text("info"), text("starting")]).is_empty());
+        assert!(captured(vec![text("legacy message")]).is_empty());
+        assert_eq!(
+            captured(vec![text("error"), text("disk full")]),
+            vec!["[ERROR] disk full".to_string()]
+        );
+
+        let mut fields = HashMap::new();
+        fields.insert("path".to_string(), text("/tmp/a b"));
+        fields.insert("code".to_string(), Value::Integer(28));
+        assert_eq!(
+            captured(vec![text("WARN"), text("retrying"), Value::Struct(fields)]),
+            vec!["[WARN] retrying code=28 path=\"/tmp/a b\"".to_string()]
+        );
+
+        set_log_level(Some("debug"));
+        assert_eq!(
+            captured(vec![text("legacy"), Value::Integer(1)]),
+            vec!["[LOG] legacy 1".to_string()]
+        );
+        set_log_level(None);
+        assert!(intrinsic_log(vec![text("info"), text("m"), Value::Integer(1)]).is_err());
+    }
+
+    #[test]
+    fn test_crypto_constant_time_eq() {
+        let eq = |a: Value, b: Value| intrinsic_crypto_constant_time_eq(vec![a, b]);
+        let tag = "b42af09057bac1e2".to_string();
+
+        assert_eq!(
+            eq(Value::String(tag.clone()), Value::String(tag.clone())).expect("operation failed"),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            eq(
+                Value::Buffer(tag.as_bytes().to_vec()),
+                Value::String(tag.clone())
+            )
+            .expect("operation failed"),
+            Value::Boolean(true)
+        );
+        // Same length, last byte differs
+        assert_eq!(
+            eq(
+                Value::String(tag.clone()),
+                Value::String("b42af09057bac1e3".to_string())
+            )
+            .expect("operation failed"),
+            Value::Boolean(false)
+        );
+        // Different lengths, one a prefix of the other
+        assert_eq!(
+            eq(
+                Value::Buffer(vec![1, 2, 3]),
+                Value::Buffer(vec![1, 2, 3, 4])
+            )
+            .expect("operation failed"),
+            Value::Boolean(false)
+        );
+        assert!(eq(Value::Integer(1), Value::String(tag)).is_err());
+    }
+
     #[test]
     fn test_crypto_aes_gcm_roundtrip() {
         let key = Value::String(hex::encode("01234567890123456789012345678901")); // 32 bytes
@@ -5165,6 +11184,155 @@ mod tests {
         }
     }
 
+    fn uuid_v4() -> String {
+        match intrinsic_uuid_v4(vec![]).expect("uuid.v4 failed") {
+            Value::String(s) => s,
+            other => panic!("Expected String, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_uuid_v4_distinct_and_well_formed() {
+        let a = uuid_v4();
+        let b = uuid_v4();
+        assert_ne!(a, b);
+        for id in [&a, &b] {
+            assert_eq!(id.len(), 36);
+            assert_eq!(&id[14..15], "4", "version nibble in {}", id);
+            assert!("89ab".contains(&id[19..20]), "variant nibble in {}", id);
+            // Well-formed output round-trips through parse unchanged
+            assert_eq!(
+                intrinsic_uuid_parse(vec![Value::String(id.clone())]).expect("parse failed"),
+                Value::String(id.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn test_uuid_parse_normalizes_and_rejects_garbage() {
+        let parsed = intrinsic_uuid_parse(vec![Value::String(
+            "{550E8400E29B41D4A716446655440000}".to_string(),
+        )])
+        .expect("parse failed");
+        assert_eq!(
+            parsed,
+            Value::String("550e8400-e29b-41d4-a716-446655440000".to_string())
+        );
+        for bad in [
+            "not-a-uuid",
+            "550e8400-e29b-41d4-a716-44665544000g",
+            "550e8400e-29b-41d4-a716-446655440000",
+            "",
+        ] {
+            assert!(
+                intrinsic_uuid_parse(vec![Value::String(bad.to_string())]).is_err(),
+                "accepted {:?}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_uuid_v4_deterministic_mode() {
+        set_deterministic_rng(Some(42));
+        let first = (uuid_v4(), uuid_v4());
+        set_deterministic_rng(Some(42));
+        let second = (uuid_v4(), uuid_v4());
+        set_deterministic_rng(None);
+        assert_eq!(first, second);
+        assert_ne!(first.0, first.1);
+    }
+
+    #[test]
+    fn test_random_int_float_choice() {
+        for _ in 0..200 {
+            match intrinsic_random_int(vec![Value::Integer(-3), Value::Integer(3)]) {
+                Ok(Value::Integer(n)) => assert!((-3..=3).contains(&n), "{}", n),
+                other => panic!("Expected Integer, got {:?}", other),
+            }
+            match intrinsic_random_float(vec![]) {
+                Ok(Value::Integer(n)) => assert!((0..FIXED_POINT_SCALE).contains(&n), "{}", n),
+                other => panic!("Expected Integer, got {:?}", other),
+            }
+        }
+        assert_eq!(
+            intrinsic_random_int(vec![Value::Integer(5), Value::Integer(5)])
+                .expect("random.int failed"),
+            Value::Integer(5)
+        );
+        assert!(intrinsic_random_int(vec![Value::Integer(2), Value::Integer(1)]).is_err());
+
+        let only = Value::String("only".to_string());
+        assert_eq!(
+            intrinsic_random_choice(vec![Value::List(vec![only.clone()])])
+                .expect("random.choice failed"),
+            only
+        );
+        assert!(intrinsic_random_choice(vec![Value::List(vec![])]).is_err());
+    }
+
+    #[test]
+    fn test_random_deterministic_mode() {
+        let draw = || {
+            let choices = Value::List((0..10).map(Value::Integer).collect());
+            (
+                intrinsic_random_int(vec![Value::Integer(0), Value::Integer(1_000_000)])
+                    .expect("random.int failed"),
+                intrinsic_random_float(vec![]).expect("random.float failed"),
+                intrinsic_random_choice(vec![choices]).expect("random.choice failed"),
+            )
+        };
+        set_deterministic_rng(Some(7));
+        let first = draw();
+        set_deterministic_rng(Some(7));
+        let second = draw();
+        set_deterministic_rng(None);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_base58_round_trip() {
+        // Known vector, plus leading zero bytes (encoded as leading '1's)
+        let encoded = intrinsic_encode_base58(vec![Value::Buffer(b"hello world".to_vec())])
+            .expect("encode failed");
+        assert_eq!(encoded, Value::String("StV1DL6CwTryKyV".to_string()));
+
+        for bytes in [vec![], vec![0, 0, 1, 2, 255], vec![0xff; 32]] {
+            let encoded =
+                intrinsic_encode_base58(vec![Value::Buffer(bytes.clone())]).expect("encode failed");
+            let decoded = intrinsic_decode_base58(vec![encoded]).expect("decode failed");
+            assert_eq!(decoded, Value::Buffer(bytes));
+        }
+
+        // '0' is not in the alphabet
+        assert!(intrinsic_decode_base58(vec![Value::String("abc0".to_string())]).is_err());
+    }
+
+    #[test]
+    fn test_base58check_detects_corruption() {
+        // Version byte 0x00 + 20-byte hash: a Bitcoin-style address
+        let mut payload = vec![0u8];
+        payload.extend(1..=20u8);
+        let encoded = intrinsic_encode_base58check(vec![Value::Buffer(payload.clone())])
+            .expect("encode failed");
+        let Value::String(address) = encoded.clone() else {
+            panic!("Expected String");
+        };
+        assert!(address.starts_with('1'));
+        assert_eq!(
+            intrinsic_decode_base58check(vec![encoded]).expect("decode failed"),
+            Value::Buffer(payload)
+        );
+
+        // Flip one checksum byte and re-encode
+        let mut raw = base58_decode(&address).expect("decode failed");
+        let last = raw.len() - 1;
+        raw[last] ^= 0x01;
+        let corrupted = Value::String(base58_encode(&raw));
+        let err = intrinsic_decode_base58check(vec![corrupted]).expect_err("corruption accepted");
+        assert!(err.to_string().contains("checksum"));
+    }
+
     // Networking Tests
     #[test]
     fn test_socket_bind_close() {
@@ -5183,6 +11351,412 @@ mod tests {
         assert_eq!(res_close, Value::Boolean(true));
     }
 
+    #[test]
+    fn test_tls_socket_round_trip() {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])
+            .expect("cert generation failed");
+        let cert_path = "intrinsics_test_tls_cert.pem";
+        let key_path = "intrinsics_test_tls_key.pem";
+        fs::write(cert_path, cert.cert.pem()).expect("write cert failed");
+        fs::write(key_path, cert.key_pair.serialize_pem()).expect("write key failed");
+        let id = |result: Result<Value, RuntimeError>| match result {
+            Ok(Value::Integer(i)) => i,
+            other => panic!("Expected an Integer id, got {:?}", other),
+        };
+
+        let identity = id(intrinsic_tls_load_identity(vec![
+            Value::String(cert_path.to_string()),
+            Value::String(key_path.to_string()),
+        ]));
+        let listener = id(intrinsic_socket_bind(vec![Value::Integer(0)]));
+        let port = match get_sockets().lock().expect("lock").get(&listener) {
+            Some(SocketResource::Listener(l)) => l.local_addr().expect("addr").port(),
+            _ => panic!("Expected a listener"),
+        };
+        intrinsic_tls_wrap_listener(vec![Value::Integer(listener), Value::Integer(identity)])
+            .expect("wrap_listener failed");
+        // Only the handshake needs both ends running at once
+        let server = thread::spawn(move || intrinsic_socket_accept(vec![Value::Integer(listener)]));
+        let client = id(intrinsic_tls_connect(vec![
+            Value::String("localhost".to_string()),
+            Value::Integer(port as i64),
+            Value::String(cert_path.to_string()),
+        ]));
+        let server = id(server.join().expect("accept panicked"));
+
+        let send = |socket: i64, text: &str| {
+            intrinsic_socket_send(vec![
+                Value::Integer(socket),
+                Value::String(text.to_string()),
+            ])
+            .expect("send failed");
+        };
+        let recv = |socket: i64| match intrinsic_socket_recv(vec![Value::Integer(socket)]) {
+            Ok(Value::String(s)) => s,
+            other => panic!("Expected String, got {:?}", other),
+        };
+        send(client, "ping");
+        assert_eq!(recv(server), "ping");
+        send(server, "pong");
+        assert_eq!(recv(client), "pong");
+
+        // Without the self-signed CA the server is not trusted
+        let rejected =
+            thread::spawn(move || intrinsic_socket_accept(vec![Value::Integer(listener)]));
+        let untrusted = intrinsic_tls_connect(vec![
+            Value::String("localhost".to_string()),
+            Value::Integer(port as i64),
+        ]);
+        assert!(
+            matches!(
+                untrusted,
+                Err(RuntimeError::NetworkError(NetworkErrorKind::TlsError, _))
+            ),
+            "{:?}",
+            untrusted
+        );
+        assert!(rejected.join().expect("accept panicked").is_err());
+
+        for socket in [client, server, listener, identity] {
+            assert_eq!(
+                intrinsic_socket_close(vec![Value::Integer(socket)]).expect("close failed"),
+                Value::Boolean(true)
+            );
+        }
+        let _ = fs::remove_file(cert_path);
+        let _ = fs::remove_file(key_path);
+    }
+
+    #[test]
+    fn test_websocket_round_trip() {
+        let id = |result: Result<Value, RuntimeError>| match result {
+            Ok(Value::Integer(i)) => i,
+            other => panic!("Expected an Integer id, got {:?}", other),
+        };
+        let listener = id(intrinsic_socket_bind(vec![Value::Integer(0)]));
+        let port = match get_sockets().lock().expect("lock").get(&listener) {
+            Some(SocketResource::Listener(l)) => l.local_addr().expect("addr").port(),
+            _ => panic!("Expected a listener"),
+        };
+        // Only the upgrade needs both ends running at once
+        let server = thread::spawn(move || intrinsic_ws_accept(vec![Value::Integer(listener)]));
+        let client = id(intrinsic_ws_connect(vec![Value::String(format!(
+            "ws://127.0.0.1:{}/chat",
+            port
+        ))]));
+        let server = id(server.join().expect("accept panicked"));
+
+        let send = |socket: i64, data: Value| {
+            intrinsic_ws_send(vec![Value::Integer(socket), data]).expect("send failed")
+        };
+        let recv =
+            |socket: i64| intrinsic_ws_recv(vec![Value::Integer(socket)]).expect("recv failed");
+        assert_eq!(
+            send(client, Value::String("héllo".to_string())),
+            Value::Integer(6)
+        );
+        assert_eq!(recv(server), Value::String("héllo".to_string()));
+        send(server, Value::Buffer(vec![0, 1, 255]));
+        assert_eq!(recv(client), Value::Buffer(vec![0, 1, 255]));
+
+        // Raw socket reads would bypass the framing
+        assert!(intrinsic_socket_recv(vec![Value::Integer(server)]).is_err());
+
+        // Closing the client sends a close frame; the server sees Unit
+        assert_eq!(
+            intrinsic_socket_close(vec![Value::Integer(client)]).expect("close failed"),
+            Value::Boolean(true)
+        );
+        assert_eq!(recv(server), Value::Unit);
+        assert!(
+            intrinsic_ws_send(vec![
+                Value::Integer(server),
+                Value::String("late".to_string())
+            ])
+            .is_err()
+        );
+
+        assert!(matches!(
+            intrinsic_ws_connect(vec![Value::String("http://127.0.0.1/".to_string())]),
+            Err(RuntimeError::NetworkError(NetworkErrorKind::InvalidUrl, _))
+        ));
+        for socket in [server, listener] {
+            assert_eq!(
+                intrinsic_socket_close(vec![Value::Integer(socket)]).expect("close failed"),
+                Value::Boolean(true)
+            );
+        }
+    }
+
+    #[test]
+    fn test_socket_poll_reports_ready_sockets() {
+        let id = |result: Result<Value, RuntimeError>| match result {
+            Ok(Value::Integer(i)) => i,
+            other => panic!("Expected an Integer id, got {:?}", other),
+        };
+        let wait = |poll: i64, ms: i64| {
+            intrinsic_poll_wait(vec![Value::Integer(poll), Value::Integer(ms)])
+                .expect("wait failed")
+        };
+        let listener = id(intrinsic_socket_bind(vec![Value::Integer(0)]));
+        let port = match get_sockets().lock().expect("lock").get(&listener) {
+            Some(SocketResource::Listener(l)) => l.local_addr().expect("addr").port(),
+            _ => panic!("Expected a listener"),
+        };
+        intrinsic_socket_set_nonblocking(vec![Value::Integer(listener), Value::Boolean(true)])
+            .expect("set_nonblocking failed");
+        assert_eq!(
+            intrinsic_socket_accept(vec![Value::Integer(listener)]).expect("accept failed"),
+            Value::Boolean(false)
+        );
+
+        let poll = id(intrinsic_poll_create(vec![]));
+        intrinsic_poll_add(vec![Value::Integer(poll), Value::Integer(listener)])
+            .expect("poll add failed");
+        assert_eq!(wait(poll, 0), Value::List(vec![]));
+
+        let client = id(intrinsic_socket_connect(vec![
+            Value::String("127.0.0.1".to_string()),
+            Value::Integer(port as i64),
+        ]));
+        assert_eq!(
+            wait(poll, 5000),
+            Value::List(vec![Value::Integer(listener)])
+        );
+        let server = id(intrinsic_socket_accept(vec![Value::Integer(listener)]));
+
+        intrinsic_poll_add(vec![Value::Integer(poll), Value::Integer(server)])
+            .expect("poll add failed");
+        assert_eq!(wait(poll, 0), Value::List(vec![]));
+        intrinsic_socket_send(vec![
+            Value::Integer(client),
+            Value::String("hi".to_string()),
+        ])
+        .expect("send failed");
+        assert_eq!(wait(poll, 5000), Value::List(vec![Value::Integer(server)]));
+        assert_eq!(
+            intrinsic_socket_recv(vec![Value::Integer(server)]).expect("recv failed"),
+            Value::String("hi".to_string())
+        );
+
+        // A drained non-blocking connection reports false rather than waiting
+        intrinsic_socket_set_nonblocking(vec![Value::Integer(server), Value::Boolean(true)])
+            .expect("set_nonblocking failed");
+        assert_eq!(
+            intrinsic_socket_recv(vec![Value::Integer(server)]).expect("recv failed"),
+            Value::Boolean(false)
+        );
+
+        let remove = |socket: i64| {
+            intrinsic_poll_remove(vec![Value::Integer(poll), Value::Integer(socket)])
+                .expect("poll remove failed")
+        };
+        assert_eq!(remove(server), Value::Boolean(true));
+        assert_eq!(remove(server), Value::Boolean(false));
+        // Closing a watched socket takes it out of the poll set
+        for socket in [client, server, listener, poll] {
+            assert_eq!(
+                intrinsic_socket_close(vec![Value::Integer(socket)]).expect("close failed"),
+                Value::Boolean(true)
+            );
+        }
+        assert!(intrinsic_poll_wait(vec![Value::Integer(poll), Value::Integer(0)]).is_err());
+    }
+
+    fn echo_route_handler(args: Vec<Value>) -> Result<Value, RuntimeError> {
+        let Some(Value::Struct(request)) = args.first() else {
+            return Err(RuntimeError::NotExecutable);
+        };
+        let field = |name: &str| match request.get(name) {
+            Some(Value::String(s)) => s.clone(),
+            other => panic!("request.{} missing: {:?}", name, other),
+        };
+        let mut headers = HashMap::new();
+        headers.insert("X-Ark".to_string(), Value::String("yes".to_string()));
+        let mut response = HashMap::new();
+        response.insert("status".to_string(), Value::Integer(201));
+        response.insert("headers".to_string(), Value::Struct(headers));
+        response.insert(
+            "body".to_string(),
+            Value::String(format!(
+                "{} {}?{} {}",
+                field("method"),
+                field("path"),
+                field("query"),
+                field("body")
+            )),
+        );
+        Ok(Value::Struct(response))
+    }
+
+    #[test]
+    fn test_http_route_listen_dispatches_to_handler() {
+        intrinsic_http_route(vec![
+            Value::String("post".to_string()),
+            Value::String("/echo".to_string()),
+            Value::NativeFunction(echo_route_handler),
+        ])
+        .expect("route failed");
+        let port = TcpListener::bind("127.0.0.1:0")
+            .and_then(|l| l.local_addr())
+            .expect("no free port")
+            .port();
+        let server = thread::spawn(move || {
+            intrinsic_http_listen(vec![Value::Integer(port as i64), Value::Integer(3)])
+        });
+
+        let send = |raw: &str| -> String {
+            for _ in 0..100 {
+                if let Ok(mut stream) = TcpStream::connect(("127.0.0.1", port)) {
+                    stream.write_all(raw.as_bytes()).expect("write failed");
+                    let mut response = String::new();
+                    stream.read_to_string(&mut response).expect("read failed");
+                    return response;
+                }
+                thread::sleep(Duration::from_millis(20));
+            }
+            panic!("server never started listening");
+        };
+
+        let ok = send("POST /echo?x=1 HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello");
+        assert!(ok.starts_with("HTTP/1.1 201 Created\r\n"), "{}", ok);
+        assert!(ok.contains("X-Ark: yes\r\n"), "{}", ok);
+        assert!(ok.ends_with("\r\n\r\nPOST /echo?x=1 hello"), "{}", ok);
+
+        let missing = send("GET /missing HTTP/1.1\r\n\r\n");
+        assert!(missing.starts_with("HTTP/1.1 404 Not Found"), "{}", missing);
+        let wrong_method = send("GET /echo HTTP/1.1\r\n\r\n");
+        assert!(
+            wrong_method.starts_with("HTTP/1.1 405 Method Not Allowed"),
+            "{}",
+            wrong_method
+        );
+
+        let served = server.join().expect("server panicked");
+        assert!(matches!(served, Ok(Value::Integer(3))), "{:?}", served);
+    }
+
+    #[test]
+    fn test_http_request_body_over_limit_is_413() {
+        let head = |len: usize| format!("POST /up HTTP/1.1\r\nContent-Length: {}\r\n\r\n", len);
+        let raw = head(MAX_HTTP_BODY_BYTES + 1);
+        match read_http_request(&mut raw.as_bytes()) {
+            Err((413, msg)) => assert!(msg.contains("exceeds"), "{}", msg),
+            other => panic!("Expected 413, got {:?}", other),
+        }
+
+        let raw = head(MAX_HTTP_BODY_BYTES) + &"x".repeat(MAX_HTTP_BODY_BYTES);
+        match read_http_request(&mut raw.as_bytes()) {
+            Ok(Value::Struct(request)) => match request.get("body") {
+                Some(Value::String(body)) => assert_eq!(body.len(), MAX_HTTP_BODY_BYTES),
+                other => panic!("Expected a body, got {:?}", other),
+            },
+            other => panic!("Expected a request, got {:?}", other),
+        }
+
+        let mut response = Vec::new();
+        write_http_response(&mut response, 413, &[], "too big").expect("write failed");
+        let response = String::from_utf8(response).expect("utf-8");
+        assert!(
+            response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"),
+            "{}",
+            response
+        );
+    }
+
+    #[test]
+    fn test_http_response_rejects_line_breaks_in_headers() {
+        for (name, value) in [
+            ("X-Ark", "yes\r\nSet-Cookie: session=stolen"),
+            ("X-Ark\nSet-Cookie", "session=stolen"),
+            ("X-Ark", "yes\r"),
+        ] {
+            let mut response = Vec::new();
+            let headers = [(name.to_string(), value.to_string())];
+            let err = write_http_response(&mut response, 200, &headers, "body")
+                .expect_err("line break accepted");
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            assert!(response.is_empty(), "wrote {:?}", response);
+        }
+
+        let mut response = Vec::new();
+        let headers = [("X-Ark".to_string(), "yes".to_string())];
+        write_http_response(&mut response, 200, &headers, "body").expect("write failed");
+        let response = String::from_utf8(response).expect("utf-8");
+        assert!(response.contains("\r\nX-Ark: yes\r\n"), "{}", response);
+    }
+
+    #[test]
+    fn test_process_spawn_pipes_stdin_to_stdout() {
+        let handle =
+            intrinsic_process_spawn(vec![Value::List(vec![Value::String("cat".to_string())])])
+                .expect("spawn failed");
+
+        let written =
+            intrinsic_process_write_stdin(vec![handle.clone(), Value::String("ping\n".into())])
+                .expect("write failed");
+        assert_eq!(written, Value::Integer(5));
+
+        let mut echoed = String::new();
+        while echoed.len() < 5 {
+            match intrinsic_process_read_stdout(vec![handle.clone(), Value::Integer(64)]) {
+                Ok(Value::String(chunk)) if !chunk.is_empty() => echoed.push_str(&chunk),
+                other => panic!("Expected output, got {:?}", other),
+            }
+        }
+        assert_eq!(echoed, "ping\n");
+
+        assert_eq!(
+            intrinsic_process_wait(vec![handle.clone()]).expect("wait failed"),
+            Value::Integer(0)
+        );
+        assert!(intrinsic_process_wait(vec![handle]).is_err());
+
+        assert!(
+            intrinsic_process_spawn(vec![Value::List(vec![Value::String("rm".into())])]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_proc_reports_stderr_exit_code_and_kill_signal() {
+        let field = |status: &Value, name: &str| match status {
+            Value::Struct(fields) => fields.get(name).cloned().expect("missing field"),
+            other => panic!("Expected a Struct, got {:?}", other),
+        };
+
+        let handle = intrinsic_proc_spawn(vec![Value::List(vec![
+            Value::String("ls".to_string()),
+            Value::String("ark_no_such_file".to_string()),
+        ])])
+        .expect("spawn failed");
+        let mut stderr = String::new();
+        loop {
+            match intrinsic_proc_stderr_read(vec![handle.clone(), Value::Integer(256)]) {
+                Ok(Value::String(chunk)) if chunk.is_empty() => break,
+                Ok(Value::String(chunk)) => stderr.push_str(&chunk),
+                other => panic!("Expected output, got {:?}", other),
+            }
+        }
+        assert!(stderr.contains("ark_no_such_file"), "{}", stderr);
+        let status = intrinsic_proc_wait(vec![handle]).expect("wait failed");
+        assert!(matches!(field(&status, "code"), Value::Integer(code) if code != 0));
+        assert_eq!(field(&status, "signal"), Value::Unit);
+
+        // `cat` blocks on its open stdin until it is killed
+        let handle =
+            intrinsic_proc_spawn(vec![Value::List(vec![Value::String("cat".to_string())])])
+                .expect("spawn failed");
+        intrinsic_proc_kill(vec![handle.clone()]).expect("kill failed");
+        intrinsic_proc_kill(vec![handle.clone()]).expect("second kill failed");
+        let status = intrinsic_proc_wait(vec![handle.clone()]).expect("wait failed");
+        #[cfg(unix)]
+        {
+            assert_eq!(field(&status, "code"), Value::Unit);
+            assert_eq!(field(&status, "signal"), Value::Integer(9));
+        }
+        assert!(intrinsic_proc_kill(vec![handle]).is_err());
+    }
+
     #[test]
     fn test_close_nonexistent() {
         let args_close = vec![Value::Integer(999999)];
@@ -5200,4 +11774,348 @@ mod tests {
         // Should return Error, not panic
         assert!(res.is_err());
     }
+
+    #[test]
+    fn test_type_of_every_variant() {
+        let cases = vec![
+            (Value::Integer(1), "integer"),
+            (Value::String("s".to_string()), "string"),
+            (Value::Boolean(true), "boolean"),
+            (Value::Unit, "unit"),
+            (
+                Value::LinearObject {
+                    id: "1".to_string(),
+                    typename: "Token".to_string(),
+                    payload: String::new(),
+                },
+                "linear",
+            ),
+            (
+                Value::Function(std::sync::Arc::new(crate::bytecode::Chunk::new())),
+                "function",
+            ),
+            (Value::NativeFunction(intrinsic_type_of), "function"),
+            (Value::List(vec![]), "list"),
+            (Value::Buffer(vec![0u8]), "buffer"),
+            (Value::Struct(HashMap::new()), "struct"),
+            (Value::PVec(PVec::new()), "pvec"),
+            (Value::PMap(PMap::new()), "pmap"),
+            (Value::BigInt(BigInt::from(1)), "bigint"),
+            (Value::Decimal(Decimal::from(1)), "decimal"),
+            (Value::Return(Box::new(Value::Integer(1))), "integer"),
+            (
+                Value::EnumValue {
+                    enum_name: "Option".to_string(),
+                    variant: "None".to_string(),
+                    fields: vec![],
+                },
+                "enum",
+            ),
+        ];
+        for (value, tag) in cases {
+            let res = intrinsic_type_of(vec![value]).expect("operation failed");
+            assert_eq!(res, Value::String(tag.to_string()));
+        }
+        assert!(IntrinsicRegistry::resolve("sys.type_of").is_some());
+    }
+
+    #[test]
+    fn test_bigint_intrinsics() {
+        let call = |name: &str, args: Vec<Value>| {
+            IntrinsicRegistry::resolve(name).expect("bigint intrinsic")(args)
+        };
+        let big = |s: &str| {
+            call("sys.bigint.from_string", vec![Value::String(s.to_string())])
+                .expect("from_string failed")
+        };
+        let text = |v: Value| call("sys.bigint.to_string", vec![v]).expect("to_string failed");
+
+        // i64::MAX + 1 no longer wraps
+        let sum = call(
+            "sys.bigint.add",
+            vec![Value::Integer(i64::MAX), Value::Integer(1)],
+        )
+        .expect("add failed");
+        assert_eq!(text(sum), Value::String("9223372036854775808".to_string()));
+        let square = call(
+            "sys.bigint.mul",
+            vec![big("-4294967296"), big("4294967296")],
+        )
+        .expect("mul failed");
+        assert_eq!(
+            text(square.clone()),
+            Value::String("-18446744073709551616".to_string())
+        );
+        assert_eq!(
+            call("sys.bigint.mod", vec![square, Value::Integer(7)]).expect("mod failed"),
+            Value::BigInt(BigInt::from(5))
+        );
+        assert_eq!(
+            call(
+                "sys.bigint.pow_mod",
+                vec![Value::Integer(4), Value::Integer(13), Value::Integer(497)]
+            )
+            .expect("pow_mod failed"),
+            Value::BigInt(BigInt::from(445))
+        );
+        assert_eq!(
+            call("sys.bigint.cmp", vec![big("-10"), Value::Integer(-9)]).expect("cmp failed"),
+            Value::Integer(-1)
+        );
+
+        let not_decimal = Value::String("1e9".to_string());
+        assert!(call("sys.bigint.from_string", vec![not_decimal]).is_err());
+        assert!(call("sys.bigint.mod", vec![big("5"), big("0")]).is_err());
+        assert!(call("sys.bigint.add", vec![big("5"), Value::Boolean(true)]).is_err());
+    }
+
+    /// The display text of a decimal or tensor result, as `print` shows it.
+    fn text(v: &Value) -> String {
+        match v {
+            Value::Decimal(d) => d.to_string(),
+            Value::Tensor(t) => t.to_string(),
+            other => panic!("expected a Decimal or Tensor, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decimal_intrinsics() {
+        let call = |name: &str, args: Vec<Value>| {
+            IntrinsicRegistry::resolve(name).expect("dec intrinsic")(args)
+        };
+        let dec = |s: &str| {
+            call("dec.from_string", vec![Value::String(s.to_string())]).expect("from_string failed")
+        };
+
+        let total = call("dec.add", vec![dec("19.99"), dec("0.01")]).expect("add failed");
+        assert_eq!(text(&total), "20.00");
+        let change = call("dec.sub", vec![dec("20"), dec("19.99")]).expect("sub failed");
+        assert_eq!(text(&change), "0.01");
+        let subtotal = call("dec.mul", vec![dec("19.99"), Value::Integer(3)]).expect("mul failed");
+        assert_eq!(text(&subtotal), "59.97");
+        let share = call("dec.div", vec![dec("10.00"), Value::Integer(3)]).expect("div failed");
+        assert_eq!(text(&share), "3.33");
+        let places = vec![dec("10"), Value::Integer(3), Value::Integer(4)];
+        let precise = call("dec.div", places).expect("div failed");
+        assert_eq!(text(&precise), "3.3333");
+        let tax = call("dec.round", vec![dec("2.345"), Value::Integer(2)]).expect("round failed");
+        assert_eq!(text(&tax), "2.35");
+        assert_eq!(
+            intrinsic_eq(vec![dec("1.0"), dec("1.00")]).expect("eq failed"),
+            Value::Boolean(true)
+        );
+
+        assert!(call("dec.from_string", vec![Value::String("1.2.3".to_string())]).is_err());
+        assert!(call("dec.div", vec![dec("1.00"), dec("0.0")]).is_err());
+        assert!(call("dec.round", vec![dec("1.5"), Value::Integer(-1)]).is_err());
+        assert!(call("dec.add", vec![dec("1.5"), Value::String("2".to_string())]).is_err());
+    }
+
+    #[test]
+    fn test_tensor_kernels_match_naive_loops() {
+        let tensor = make_tensor;
+        // Sizes on both sides of a block edge, with an odd tail
+        for (m, k, n) in [(1, 1, 1), (2, 3, 5), (67, 65, 70)] {
+            let a: Vec<i64> = (0..m * k).map(|x| (x as i64 * 7) % 13 - 6).collect();
+            let b: Vec<i64> = (0..k * n).map(|x| (x as i64 * 5) % 11 - 5).collect();
+            let mut expected = vec![0i64; m * n];
+            for i in 0..m {
+                for j in 0..n {
+                    expected[i * n + j] = (0..k).map(|p| a[i * k + p] * b[p * n + j]).sum();
+                }
+            }
+            let args = vec![
+                tensor(a.clone(), vec![m as i64, k as i64]),
+                tensor(b.clone(), vec![k as i64, n as i64]),
+            ];
+            let product = intrinsic_math_matmul(args).expect("matmul failed");
+            assert_eq!(
+                extract_tensor(&product).expect("tensor"),
+                (expected, vec![m as i64, n as i64])
+            );
+
+            let dot = a.iter().zip(&a).map(|(x, y)| x * y).sum::<i64>();
+            let args = vec![tensor(a.clone(), vec![a.len() as i64]); 2];
+            assert_eq!(
+                intrinsic_math_dot(args).expect("dot failed"),
+                Value::Integer(dot)
+            );
+        }
+
+        // Wraps like the WASM lowering instead of panicking
+        assert_eq!(dot_kernel(&[i64::MAX, 1], &[2, 2]), 0);
+        let ragged = tensor(vec![1, 2, 3], vec![2, 2]);
+        assert!(intrinsic_math_matmul(vec![ragged.clone(), ragged.clone()]).is_err());
+        assert!(intrinsic_math_transpose(vec![ragged]).is_err());
+        let negative = tensor(vec![], vec![-1, 0]);
+        assert!(intrinsic_math_transpose(vec![negative]).is_err());
+    }
+
+    #[test]
+    fn test_tensor_intrinsics_broadcast_and_reduce() {
+        let call = |name: &str, args: Vec<Value>| {
+            IntrinsicRegistry::resolve(name).expect("tensor intrinsic")(args)
+        };
+        let ints = |xs: &[i64]| Value::List(xs.iter().copied().map(Value::Integer).collect());
+        let grid =
+            call("tensor.new", vec![ints(&[1, 2, 3, 4, 5, 6]), ints(&[2, 3])]).expect("new failed");
+        assert_eq!(text(&grid), "[[1, 2, 3], [4, 5, 6]]");
+
+        // A row broadcasts down the grid; a scalar across it
+        let shifted = call("tensor.add", vec![grid.clone(), ints(&[10, 20, 30])]);
+        assert!(shifted.is_err(), "a List is not a tensor");
+        let row = call("tensor.new", vec![ints(&[10, 20, 30]), ints(&[3])]).expect("new failed");
+        let shifted = call("tensor.add", vec![grid.clone(), row]).expect("add failed");
+        assert_eq!(text(&shifted), "[[11, 22, 33], [14, 25, 36]]");
+        let scaled = call("tensor.mul", vec![Value::Integer(2), grid.clone()]).expect("mul failed");
+        assert_eq!(text(&scaled), "[[2, 4, 6], [8, 10, 12]]");
+        // The math.Tensor struct form is accepted and interoperates
+        let column = make_tensor(vec![1, 2], vec![2, 1]);
+        let centred = call("tensor.sub", vec![grid.clone(), column]).expect("sub failed");
+        assert_eq!(text(&centred), "[[0, 1, 2], [2, 3, 4]]");
+        let product =
+            intrinsic_math_matmul(vec![grid.clone(), make_tensor(vec![1, 1, 1], vec![3, 1])])
+                .expect("matmul failed");
+        assert_eq!(
+            extract_tensor(&product).expect("tensor"),
+            (vec![6, 15], vec![2, 1])
+        );
+
+        let tail = call(
+            "tensor.slice",
+            vec![
+                grid.clone(),
+                Value::Integer(1),
+                Value::Integer(-2),
+                Value::Integer(3),
+            ],
+        )
+        .expect("slice failed");
+        assert_eq!(
+            call("tensor.shape", vec![tail.clone()]).expect("shape"),
+            ints(&[2, 2])
+        );
+        assert_eq!(
+            call("tensor.data", vec![tail.clone()]).expect("data"),
+            ints(&[2, 3, 5, 6])
+        );
+        assert_eq!(
+            call("tensor.get", vec![tail.clone(), ints(&[1, 0])]).expect("get failed"),
+            Value::Integer(5)
+        );
+        let flat = call("tensor.reshape", vec![tail, ints(&[-1])]).expect("reshape failed");
+        assert_eq!(text(&flat), "[2, 3, 5, 6]");
+
+        assert_eq!(
+            call("tensor.sum", vec![grid.clone()]).expect("sum"),
+            Value::Integer(21)
+        );
+        let columns = call("tensor.sum", vec![grid.clone(), Value::Integer(0)]).expect("sum");
+        assert_eq!(text(&columns), "[5, 7, 9]");
+        assert_eq!(type_tag(&columns), "tensor");
+
+        let pair = call("tensor.new", vec![ints(&[1, 2]), ints(&[2])]).expect("new failed");
+        assert!(call("tensor.add", vec![grid.clone(), pair]).is_err());
+        assert!(call("tensor.new", vec![ints(&[1, 2, 3]), ints(&[2, 2])]).is_err());
+        assert!(call("tensor.reshape", vec![grid.clone(), ints(&[4, -1])]).is_err());
+        assert!(call("tensor.get", vec![grid.clone(), ints(&[2, 0])]).is_err());
+        assert!(call("tensor.sum", vec![grid, Value::Integer(2)]).is_err());
+    }
+    fn network_kind(res: Result<Value, RuntimeError>) -> NetworkErrorKind {
+        match res {
+            Err(RuntimeError::NetworkError(kind, _)) => kind,
+            other => panic!("expected NetworkError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_http_request_connection_refused() {
+        // Bind then drop a listener so the port is known to be closed.
+        let port = TcpListener::bind("127.0.0.1:0")
+            .expect("bind failed")
+            .local_addr()
+            .expect("local addr")
+            .port();
+        let args = vec![
+            Value::String("GET".to_string()),
+            Value::String(format!("http://127.0.0.1:{}/", port)),
+        ];
+        assert_eq!(
+            network_kind(intrinsic_http_request(args)),
+            NetworkErrorKind::ConnectionRefused
+        );
+    }
+
+    #[test]
+    fn test_http_request_dns_failure() {
+        // `.invalid` is reserved and never resolves (RFC 6761).
+        let args = vec![
+            Value::String("GET".to_string()),
+            Value::String("http://ark-test.invalid/".to_string()),
+        ];
+        assert_eq!(
+            network_kind(intrinsic_http_request(args)),
+            NetworkErrorKind::DnsError
+        );
+    }
+
+    #[test]
+    fn test_http_request_invalid_url_kind() {
+        let args = vec![
+            Value::String("GET".to_string()),
+            Value::String("not a url".to_string()),
+        ];
+        assert_eq!(
+            network_kind(intrinsic_http_request(args)),
+            NetworkErrorKind::InvalidUrl
+        );
+    }
+
+    #[test]
+    fn test_http_request_tls_failure() {
+        // A plain TCP server that answers the TLS ClientHello with garbage.
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind failed");
+        let port = listener.local_addr().expect("local addr").port();
+        let server = thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let mut buf = [0u8; 512];
+                let _ = stream.read(&mut buf);
+                let _ = stream.write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n");
+            }
+        });
+        let args = vec![
+            Value::String("GET".to_string()),
+            Value::String(format!("https://127.0.0.1:{}/", port)),
+        ];
+        assert_eq!(
+            network_kind(intrinsic_http_request(args)),
+            NetworkErrorKind::TlsError
+        );
+        let _ = server.join();
+    }
+
+    #[test]
+    fn test_reqwest_timeout_kind() {
+        // Accept the connection but never answer.
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind failed");
+        let port = listener.local_addr().expect("local addr").port();
+        let server = thread::spawn(move || {
+            let conn = listener.accept();
+            thread::sleep(Duration::from_millis(500));
+            drop(conn);
+        });
+        let client = Client::builder()
+            .timeout(Duration::from_millis(100))
+            .build()
+            .expect("client");
+        let err = client
+            .get(format!("http://127.0.0.1:{}/", port))
+            .send()
+            .expect_err("request should time out");
+        assert!(matches!(
+            network_error_from_reqwest(&err),
+            RuntimeError::NetworkError(NetworkErrorKind::Timeout, _)
+        ));
+        let _ = server.join();
+    }
 }
@@ -22,6 +22,7 @@ pub mod a2a;
 pub mod adn;
 pub mod agent_pipeline;
 pub mod approval;
+pub mod artifact;
 pub mod ast;
 pub mod audit;
 pub mod capability;
@@ -33,6 +34,8 @@ pub mod context_overflow;
 pub mod csnp;
 pub mod desktop_ffi;
 
+pub mod bench;
+pub mod bigint;
 pub mod blockchain;
 #[cfg(feature = "ipc")]
 #[allow(dead_code)]
@@ -44,9 +47,12 @@ pub mod compiler;
 pub mod consensus;
 pub mod crypto;
 pub mod debugger;
+pub mod decimal;
 pub mod diagnostic;
+pub mod diagnostics;
 pub mod embedding;
 pub mod engine;
+pub mod formatter;
 #[cfg(test)]
 pub mod eval; // Deprecated by VM, enabled for tests
 pub mod ffi;
@@ -62,14 +68,19 @@ pub mod macros;
 pub mod manifest_signing;
 pub mod metering;
 pub mod model_catalog;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod native_codegen;
 pub mod ois;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod package;
 pub mod parser;
 pub mod persistent;
+pub mod profiler;
 pub mod proprioception;
 pub mod provider_health;
 pub mod qdma;
 pub mod research;
-// pub mod repl; // Deprecated interpreter REPL
+pub mod repl;
 #[cfg(test)]
 pub mod bench_intrinsics;
 pub mod retry;
@@ -80,8 +91,12 @@ pub mod shell_bleed;
 pub mod signal_gate;
 #[cfg(test)]
 pub mod snapshot_tests;
+#[cfg(test)]
+pub mod test_support;
 pub mod taint;
+pub mod tensor;
 pub mod tool_policy;
+pub mod trace;
 pub mod triggers;
 pub mod types;
 pub mod veto_circuit;
@@ -95,6 +110,8 @@ pub mod wasm_interop;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod wasm_runner;
 pub mod wasserstein;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod websocket;
 pub mod wit_gen;
 pub mod yggdrasil;
 pub use wasm::*;
@@ -16,8 +16,18 @@
  * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
  */
 
-use crate::ast::{ArkNode, AstError, MastNode};
+//! Loading programs: JSON MAST files, and Ark sources linked together with
+//! the modules they import.
+
+use crate::artifact::{ArtifactError, CompiledProgram, TrustPolicy};
+use crate::ast::{
+    ArkNode, AstError, Expression, FunctionDef, Import, MastNode, Statement, Visitor,
+};
+use crate::diagnostics::Diagnostic;
+use crate::parser::{self, ParseError};
 use serde_json::from_str;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -28,6 +38,8 @@ pub enum LoadError {
     AstError(#[from] AstError),
     #[error("Integrity Error: Hash Mismatch. Expected {expected}, computed {computed}.")]
     HashMismatch { expected: String, computed: String },
+    #[error("Signature Error: {0}")]
+    Signature(#[from] ArtifactError),
 }
 
 pub fn load_ark_program(json: &str) -> Result<MastNode, LoadError> {
@@ -40,6 +52,22 @@ pub fn load_ark_program(json: &str) -> Result<MastNode, LoadError> {
     Ok(mast)
 }
 
+/// Load a compiled program (`.arkc`). Its MAST hash must match its
+/// content, and its signature (or lack of one) must satisfy `policy`.
+pub fn load_compiled_program(json: &str, policy: &TrustPolicy) -> Result<MastNode, LoadError> {
+    let program: CompiledProgram = from_str(json)?;
+    verify_mast_integrity(&program.mast)?;
+    policy.verify(program.mast.hash.as_bytes(), program.signature.as_ref())?;
+    Ok(program.mast)
+}
+
+/// Load a JSON MAST, which has no signature to check: `policy` must
+/// allow unsigned programs.
+pub fn load_unsigned_program(json: &str, policy: &TrustPolicy) -> Result<MastNode, LoadError> {
+    policy.allow_unsigned()?;
+    load_ark_program(json)
+}
+
 fn verify_mast_integrity(mast: &MastNode) -> Result<(), LoadError> {
     // Compute Hash using canonical JSON (matches Python compiler and MastNode::new)
     let computed_hash = crate::ast::calculate_hash(&mast.content).map_err(LoadError::AstError)?;
@@ -56,3 +84,785 @@ fn verify_mast_integrity(mast: &MastNode) -> Result<(), LoadError> {
     // For now, checks top-level signature which covers the content.
     Ok(())
 }
+
+// =============================================================================
+// Module Linking
+// =============================================================================
+
+#[derive(Error, Debug)]
+pub enum LinkError {
+    #[error("cannot find module '{module}' imported by {importer}")]
+    NotFound { module: String, importer: String },
+    #[error("cannot read {file}: {error}")]
+    Io {
+        file: String,
+        #[source]
+        error: std::io::Error,
+    },
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("import cycle: {0}")]
+    Cycle(String),
+    #[error("{first} and {second} are both imported as '{namespace}'")]
+    NamespaceClash {
+        namespace: String,
+        first: String,
+        second: String,
+    },
+    #[error("call to '{name}' in {file} is ambiguous: it is defined in {}", .modules.join(" and "))]
+    Ambiguous {
+        name: String,
+        file: String,
+        modules: Vec<String>,
+    },
+    #[error(transparent)]
+    Ast(#[from] AstError),
+}
+
+impl LinkError {
+    /// This error as a shared [`Diagnostic`]. Imports carry no spans, so
+    /// only parse errors inside a module point at a location.
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        match self {
+            LinkError::Parse(e) => e.to_diagnostic(),
+            LinkError::NotFound { .. } => Diagnostic::error("E0401", self.to_string()).with_help(
+                "paths are searched relative to the importing file, then in ARK_PATH, ./ and ./lib",
+            ),
+            LinkError::Io { .. } => Diagnostic::error("E0402", self.to_string()),
+            LinkError::Cycle(_) => Diagnostic::error("E0403", self.to_string()),
+            LinkError::NamespaceClash { .. } => Diagnostic::error("E0404", self.to_string())
+                .with_help("import one of them under another name with `as`"),
+            LinkError::Ambiguous { name, modules, .. } => {
+                Diagnostic::error("E0405", self.to_string())
+                    .with_help(format!("qualify the call, e.g. `{}.{}`", modules[0], name))
+            }
+            LinkError::Ast(_) => Diagnostic::error("E0406", self.to_string()),
+        }
+    }
+}
+
+/// A module that has been linked: the namespace its functions were moved
+/// into and their names before the move.
+#[derive(Clone)]
+struct Module {
+    namespace: String,
+    functions: HashSet<String>,
+}
+
+/// Links an Ark program with the modules it imports into one program that
+/// any backend can compile.
+///
+/// `import "utils.ark"` names a file relative to the importing file;
+/// `import a.b` names `a/b.ark`, looked up next to the importing file, then
+/// in installed packages (`import json` loads the `json` package's entry
+/// file, `import json.pretty` its `pretty.ark`), then in each directory of
+/// the search path. Either form takes `as name`.
+///
+/// Every module is read and linked once however often it is imported. Its
+/// functions are renamed into its namespace (`utils::parse`), which is the
+/// alias it was first imported under, or its file name. Callers write
+/// `utils.parse(x)`; a bare `parse(x)` works as well while no local
+/// definition and no other import has that name. A module's top-level
+/// statements run once, before those of the modules importing it.
+pub struct Linker {
+    search_path: Vec<PathBuf>,
+    /// Root directory and entry file of each package, by name
+    packages: HashMap<String, (PathBuf, PathBuf)>,
+    /// Linked modules by canonical path
+    modules: HashMap<PathBuf, Module>,
+    /// Display name of the module holding each namespace
+    namespaces: HashMap<String, String>,
+    /// Files being linked, outermost first, for cycle detection
+    stack: Vec<(PathBuf, String)>,
+    /// Source of every file read, under the name its spans use
+    sources: HashMap<String, String>,
+    /// Top-level statements of every linked module, dependencies first
+    linked: Vec<Statement>,
+}
+
+impl Linker {
+    pub fn new(search_path: Vec<PathBuf>) -> Self {
+        Linker {
+            search_path,
+            packages: HashMap::new(),
+            modules: HashMap::new(),
+            namespaces: HashMap::new(),
+            stack: Vec::new(),
+            sources: HashMap::new(),
+            linked: Vec::new(),
+        }
+    }
+
+    /// A linker searching the directories in `ARK_PATH`, then the working
+    /// directory and its `lib/`.
+    pub fn from_env() -> Self {
+        let mut search_path: Vec<PathBuf> = std::env::var_os("ARK_PATH")
+            .map(|paths| std::env::split_paths(&paths).collect())
+            .unwrap_or_default();
+        search_path.push(PathBuf::from("."));
+        search_path.push(PathBuf::from("lib"));
+        Self::new(search_path)
+    }
+
+    /// Make the package `name`, installed in `root`, importable by name.
+    pub fn add_package(&mut self, name: &str, root: PathBuf, entry: PathBuf) {
+        self.packages.insert(name.to_string(), (root, entry));
+    }
+
+    /// Source of a file read while linking, for rendering diagnostics
+    /// whose span points into it.
+    pub fn source(&self, file: &str) -> Option<&str> {
+        self.sources.get(file).map(String::as_str)
+    }
+
+    /// Parse `source` (read from `file`) and link it with everything it
+    /// imports. The result is a single top-level block: the modules'
+    /// statements in dependency order, then the program's own, without its
+    /// imports.
+    pub fn link(&mut self, source: &str, file: &str) -> Result<ArkNode, LinkError> {
+        self.sources.insert(file.to_string(), source.to_string());
+        let ArkNode::Statement(Statement::Block(mut stmts)) =
+            parser::parse_source_with_spans(source, file)?
+        else {
+            unreachable!("parse_program always yields a block");
+        };
+        if let Ok(canonical) = Path::new(file).canonicalize() {
+            self.stack.push((canonical, file.to_string()));
+        }
+
+        let names = self.link_imports(&stmts, file)?;
+        let own = top_level_functions(&stmts);
+        let renamer = Renamer::new(names, &own, None, file);
+        renamer.rename_block(&mut stmts)?;
+        self.stack.clear();
+
+        let mut program = std::mem::take(&mut self.linked);
+        program.extend(
+            stmts
+                .into_iter()
+                .filter(|s| !matches!(s, Statement::Import(_))),
+        );
+        Ok(ArkNode::Statement(Statement::Block(program)))
+    }
+
+    /// Link every module `stmts` imports. Returns the names the importing
+    /// file may call them by: `alias.f` and bare `f`, each with the linked
+    /// names it could mean.
+    fn link_imports(
+        &mut self,
+        stmts: &[Statement],
+        importer: &str,
+    ) -> Result<HashMap<String, Vec<String>>, LinkError> {
+        let mut names: HashMap<String, Vec<String>> = HashMap::new();
+        for stmt in stmts {
+            let Statement::Import(import) = stmt else {
+                continue;
+            };
+            let module = self.link_module(import, importer)?;
+            let alias = import
+                .alias
+                .clone()
+                .unwrap_or_else(|| module_stem(&import.path));
+            for function in &module.functions {
+                let linked = format!("{}::{}", module.namespace, function);
+                names.insert(format!("{}.{}", alias, function), vec![linked.clone()]);
+                let candidates = names.entry(function.clone()).or_default();
+                if !candidates.contains(&linked) {
+                    candidates.push(linked);
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    fn link_module(&mut self, import: &Import, importer: &str) -> Result<Module, LinkError> {
+        let path = self.resolve(&import.path, importer)?;
+        let file = path.to_string_lossy().into_owned();
+        let canonical = path.canonicalize().map_err(|error| LinkError::Io {
+            file: file.clone(),
+            error,
+        })?;
+
+        if let Some(start) = self.stack.iter().position(|(p, _)| *p == canonical) {
+            let mut chain: Vec<&str> = self.stack[start..]
+                .iter()
+                .map(|(_, f)| f.as_str())
+                .collect();
+            chain.push(&file);
+            return Err(LinkError::Cycle(chain.join(" -> ")));
+        }
+        if let Some(module) = self.modules.get(&canonical) {
+            return Ok(module.clone());
+        }
+
+        let namespace = import
+            .alias
+            .clone()
+            .unwrap_or_else(|| module_stem(&import.path));
+        if let Some(first) = self.namespaces.get(&namespace) {
+            return Err(LinkError::NamespaceClash {
+                namespace,
+                first: first.clone(),
+                second: file,
+            });
+        }
+        self.namespaces.insert(namespace.clone(), file.clone());
+
+        let source = std::fs::read_to_string(&path).map_err(|error| LinkError::Io {
+            file: file.clone(),
+            error,
+        })?;
+        self.sources.insert(file.clone(), source);
+        let ArkNode::Statement(Statement::Block(mut stmts)) =
+            parser::parse_source_with_spans(&self.sources[&file], &file)?
+        else {
+            unreachable!("parse_program always yields a block");
+        };
+
+        self.stack.push((canonical.clone(), file.clone()));
+        let names = self.link_imports(&stmts, &file)?;
+        self.stack.pop();
+
+        let functions = top_level_functions(&stmts);
+        let renamer = Renamer::new(names, &functions, Some(namespace.as_str()), &file);
+        renamer.rename_block(&mut stmts)?;
+        self.linked.extend(
+            stmts
+                .into_iter()
+                .filter(|s| !matches!(s, Statement::Import(_))),
+        );
+
+        let module = Module {
+            namespace,
+            functions,
+        };
+        self.modules.insert(canonical, module.clone());
+        Ok(module)
+    }
+
+    /// The file an import names: a path ending in `.ark` (or containing
+    /// `/`) as written, a dotted path as nested directories.
+    fn resolve(&self, import: &str, importer: &str) -> Result<PathBuf, LinkError> {
+        let is_file = import.ends_with(".ark") || import.contains('/');
+        let relative = if is_file {
+            PathBuf::from(import)
+        } else {
+            import.split('.').collect::<PathBuf>().with_extension("ark")
+        };
+        let here = Path::new(importer).parent().unwrap_or(Path::new(""));
+        let package = match import.split_once('.') {
+            _ if is_file => None,
+            None => self.packages.get(import).map(|(_, entry)| entry.clone()),
+            Some((name, rest)) => self.packages.get(name).map(|(root, _)| {
+                root.join(rest.split('.').collect::<PathBuf>().with_extension("ark"))
+            }),
+        };
+        std::iter::once(here.join(&relative))
+            .chain(package)
+            .chain(self.search_path.iter().map(|dir| dir.join(&relative)))
+            .find(|candidate| candidate.is_file())
+            .map(|found| {
+                found
+                    .strip_prefix(".")
+                    .map(Path::to_path_buf)
+                    .unwrap_or(found)
+            })
+            .ok_or_else(|| LinkError::NotFound {
+                module: import.to_string(),
+                importer: importer.to_string(),
+            })
+    }
+}
+
+/// The `#[test]` functions of a linked program, in source order. Tests in
+/// imported modules are left out: they are namespaced, and belong to
+/// whoever runs `ark test` on the module itself.
+pub fn test_functions(program: &ArkNode) -> Vec<String> {
+    functions_with_attribute(program, "test")
+}
+
+/// The `#[bench]` functions of a linked program, chosen as for
+/// [`test_functions`].
+pub fn bench_functions(program: &ArkNode) -> Vec<String> {
+    functions_with_attribute(program, "bench")
+}
+
+fn functions_with_attribute(program: &ArkNode, attribute: &str) -> Vec<String> {
+    let ArkNode::Statement(Statement::Block(stmts)) = program else {
+        return Vec::new();
+    };
+    stmts
+        .iter()
+        .filter_map(|s| match s {
+            Statement::Function(func)
+                if !func.name.contains("::") && func.attributes.iter().any(|a| a == attribute) =>
+            {
+                Some(func.name.clone())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The default namespace of an import: its file name without `.ark`.
+fn module_stem(import: &str) -> String {
+    let last = if import.ends_with(".ark") || import.contains('/') {
+        Path::new(import)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+    } else {
+        import.rsplit('.').next().map(str::to_string)
+    };
+    last.unwrap_or_else(|| import.to_string())
+}
+
+fn top_level_functions(stmts: &[Statement]) -> HashSet<String> {
+    stmts
+        .iter()
+        .filter_map(|s| match s {
+            Statement::Function(func) => Some(func.name.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Names bound in a scope: `let`s, loop variables, caught errors and lambda
+/// parameters. Nested functions are scopes of their own.
+#[derive(Default)]
+struct Bindings(HashSet<String>);
+
+impl Visitor for Bindings {
+    fn visit_function(&mut self, _func: &FunctionDef) {}
+
+    fn visit_statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::Let { name, .. } => {
+                self.0.insert(name.clone());
+            }
+            Statement::LetDestructure { names, rest, .. } => {
+                self.0.extend(names.iter().chain(rest).cloned());
+            }
+            Statement::For { variable, .. } => {
+                self.0.insert(variable.clone());
+            }
+            Statement::TryCatch { catch_var, .. } => {
+                self.0.insert(catch_var.clone());
+            }
+            _ => {}
+        }
+        crate::ast::walk_statement(self, stmt);
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        if let Expression::Lambda { params, .. } = expr {
+            self.0.extend(params.iter().cloned());
+        }
+        crate::ast::walk_expression(self, expr);
+    }
+}
+
+/// Rewrites the calls and function references in one file to the linked
+/// names of what they refer to.
+struct Renamer<'a> {
+    /// `alias.f` or `f` → every linked function it could mean
+    names: HashMap<String, Vec<String>>,
+    /// The namespace the file's own functions move into, if it is a module
+    namespace: Option<&'a str>,
+    file: &'a str,
+}
+
+impl<'a> Renamer<'a> {
+    fn new(
+        mut names: HashMap<String, Vec<String>>,
+        own: &HashSet<String>,
+        namespace: Option<&'a str>,
+        file: &'a str,
+    ) -> Self {
+        // A file's own functions shadow anything it imports
+        for function in own {
+            match namespace {
+                Some(ns) => {
+                    names.insert(function.clone(), vec![format!("{}::{}", ns, function)]);
+                }
+                None => {
+                    names.remove(function);
+                }
+            }
+        }
+        Renamer {
+            names,
+            namespace,
+            file,
+        }
+    }
+
+    /// The linked name `name` refers to, unless a local binding shadows it.
+    fn lookup(&self, name: &str, locals: &HashSet<String>) -> Result<Option<String>, LinkError> {
+        // `u.f()` on a local `u` is a method call, not a module function
+        let root = name.split('.').next().unwrap_or(name);
+        if locals.contains(name) || locals.contains(root) {
+            return Ok(None);
+        }
+        match self.names.get(name).map(Vec::as_slice) {
+            None | Some([]) => Ok(None),
+            Some([linked]) => Ok(Some(linked.clone())),
+            Some(candidates) => Err(LinkError::Ambiguous {
+                name: name.to_string(),
+                file: self.file.to_string(),
+                modules: candidates
+                    .iter()
+                    .map(|c| c.split("::").next().unwrap_or(c).to_string())
+                    .collect(),
+            }),
+        }
+    }
+
+    /// Rename the top level of a file, including its own function
+    /// definitions when it is a module.
+    fn rename_block(&self, stmts: &mut [Statement]) -> Result<(), LinkError> {
+        if self.names.is_empty() {
+            return Ok(());
+        }
+        let mut locals = Bindings::default();
+        for stmt in stmts.iter() {
+            locals.visit_statement(stmt);
+        }
+        for stmt in stmts.iter_mut() {
+            if let (Statement::Function(func), Some(ns)) = (&mut *stmt, self.namespace) {
+                func.name = format!("{}::{}", ns, func.name);
+            }
+            self.rename_statement(stmt, &locals.0)?;
+        }
+        Ok(())
+    }
+
+    fn rename_function(&self, func: &mut FunctionDef) -> Result<(), LinkError> {
+        let mut locals = Bindings::default();
+        locals.visit_node(&func.body.content);
+        locals
+            .0
+            .extend(func.inputs.iter().map(|(name, _)| name.clone()));
+
+        let span = func.body.span.take();
+        let mut content = func.body.content.clone();
+        match &mut content {
+            ArkNode::Function(inner) => self.rename_function(inner)?,
+            ArkNode::Statement(stmt) => self.rename_statement(stmt, &locals.0)?,
+            ArkNode::Expression(expr) => self.rename_expression(expr, &locals.0)?,
+            ArkNode::Type(_) => {}
+        }
+        *func.body = MastNode::new(content)?;
+        func.body.span = span;
+        Ok(())
+    }
+
+    fn rename_statements(
+        &self,
+        stmts: &mut [Statement],
+        locals: &HashSet<String>,
+    ) -> Result<(), LinkError> {
+        stmts
+            .iter_mut()
+            .try_for_each(|s| self.rename_statement(s, locals))
+    }
+
+    fn rename_statement(
+        &self,
+        stmt: &mut Statement,
+        locals: &HashSet<String>,
+    ) -> Result<(), LinkError> {
+        match stmt {
+            Statement::SetField { obj, value, .. } => {
+                if let Some(obj) = obj {
+                    self.rename_expression(obj, locals)?;
+                }
+                self.rename_expression(value, locals)
+            }
+            Statement::Let { value, .. }
+            | Statement::LetDestructure { value, .. }
+            | Statement::Return(value)
+            | Statement::Expression(value) => self.rename_expression(value, locals),
+            Statement::Block(stmts) => self.rename_statements(stmts, locals),
+            Statement::If {
+                condition,
+                then_block,
+                else_block,
+            } => {
+                self.rename_expression(condition, locals)?;
+                self.rename_statements(then_block, locals)?;
+                match else_block {
+                    Some(stmts) => self.rename_statements(stmts, locals),
+                    None => Ok(()),
+                }
+            }
+            Statement::While {
+                condition, body, ..
+            } => {
+                self.rename_expression(condition, locals)?;
+                self.rename_statements(body, locals)
+            }
+            Statement::For { iterable, body, .. } => {
+                self.rename_expression(iterable, locals)?;
+                self.rename_statements(body, locals)
+            }
+            Statement::TryCatch { body, handler, .. } => {
+                self.rename_statements(body, locals)?;
+                self.rename_statements(handler, locals)
+            }
+            Statement::Function(func) => self.rename_function(func),
+            Statement::Export(inner) => self.rename_statement(inner, locals),
+            Statement::ImplBlock(block) => block
+                .methods
+                .iter_mut()
+                .try_for_each(|method| self.rename_function(method)),
+            Statement::Break { .. }
+            | Statement::Continue { .. }
+            | Statement::Import(_)
+            | Statement::StructDecl(_)
+            | Statement::EnumDecl(_)
+            | Statement::TraitDecl(_) => Ok(()),
+        }
+    }
+
+    fn rename_expression(
+        &self,
+        expr: &mut Expression,
+        locals: &HashSet<String>,
+    ) -> Result<(), LinkError> {
+        match expr {
+            Expression::Call {
+                function_hash,
+                args,
+            } => {
+                if let Some(linked) = self.lookup(function_hash, locals)? {
+                    *function_hash = linked;
+                }
+                args.iter_mut()
+                    .try_for_each(|arg| self.rename_expression(arg, locals))
+            }
+            Expression::Variable(name) => {
+                if let Some(linked) = self.lookup(name, locals)? {
+                    *name = linked;
+                }
+                Ok(())
+            }
+            Expression::EnumInit { args: items, .. } | Expression::List(items) => items
+                .iter_mut()
+                .try_for_each(|item| self.rename_expression(item, locals)),
+            Expression::StructInit { fields } => fields
+                .iter_mut()
+                .try_for_each(|(_, value)| self.rename_expression(value, locals)),
+            Expression::MapInit { entries } => entries.iter_mut().try_for_each(|(key, value)| {
+                self.rename_expression(key, locals)?;
+                self.rename_expression(value, locals)
+            }),
+            Expression::GetField { obj, .. } | Expression::Propagate(obj) => {
+                self.rename_expression(obj, locals)
+            }
+            Expression::Match { scrutinee, arms } => {
+                self.rename_expression(scrutinee, locals)?;
+                arms.iter_mut()
+                    .try_for_each(|(_, arm)| self.rename_expression(arm, locals))
+            }
+            Expression::Lambda { params, body } => {
+                let mut inner = locals.clone();
+                inner.extend(params.iter().cloned());
+                self.rename_statements(body, &inner)
+            }
+            Expression::Literal(_) | Expression::Integer(_) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::Compiler;
+    use crate::runtime::Value;
+    use crate::test_support::project;
+    use crate::vm::VM;
+    use crate::wasm_codegen::WasmCodegen;
+
+    fn link(dir: &Path, search_path: Vec<PathBuf>) -> Result<ArkNode, LinkError> {
+        let main = dir.join("main.ark");
+        let source = std::fs::read_to_string(&main).expect("read failed");
+        Linker::new(search_path).link(&source, &main.to_string_lossy())
+    }
+
+    #[derive(Default)]
+    struct FunctionNames(Vec<String>);
+
+    impl Visitor for FunctionNames {
+        fn visit_function(&mut self, func: &FunctionDef) {
+            self.0.push(func.name.clone());
+        }
+    }
+
+    #[test]
+    fn test_modules_are_namespaced_and_linked_once() {
+        let dir = project(
+            "namespaces",
+            &[
+                (
+                    "utils.ark",
+                    "func double(n: Int) => Int {\n    return n * 2\n}\nfunc quad(n: Int) => Int {\n    return double(double(n))\n}\n",
+                ),
+                (
+                    "shapes.ark",
+                    "import \"utils.ark\"\nfunc area(n: Int) => Int {\n    return utils.double(n) + 1\n}\n",
+                ),
+                (
+                    "main.ark",
+                    "import \"utils.ark\"\nimport \"shapes.ark\" as s\n#[export]\nfunc answer() => Int {\n    return utils.quad(s.area(1))\n}\nanswer()\n",
+                ),
+            ],
+        );
+        let program = link(&dir, Vec::new()).expect("link failed");
+
+        let mut names = FunctionNames::default();
+        names.visit_node(&program);
+        assert_eq!(
+            names.0,
+            ["utils::double", "utils::quad", "s::area", "answer"]
+        );
+
+        let chunk = Compiler::new().compile(&program);
+        let result = VM::new(chunk, "HASH", 0).expect("vm init failed").run();
+        assert_eq!(result.expect("run failed"), Value::Integer(12));
+
+        let bytes = WasmCodegen::compile_to_bytes(&program).expect("wasm compile failed");
+        let answer = crate::wasm_runner::call_exported(&bytes, "answer", &[]).expect("call failed");
+        assert_eq!(answer, Some(12));
+    }
+
+    #[test]
+    fn test_dotted_imports_use_the_search_path_and_allow_bare_calls() {
+        let dir = project(
+            "search_path",
+            &[
+                (
+                    "app/main.ark",
+                    "import text.pad\nfunc local() {\n    return 1\n}\nw := width(3) + local()\nw\n",
+                ),
+                ("std/text/pad.ark", "func width(n) {\n    return n * 5\n}\n"),
+            ],
+        );
+        let app = dir.join("app");
+        assert!(matches!(
+            link(&app, Vec::new()),
+            Err(LinkError::NotFound { module, .. }) if module == "text.pad"
+        ));
+
+        let program = link(&app, vec![dir.join("std")]).expect("link failed");
+        let chunk = Compiler::new().compile(&program);
+        let result = VM::new(chunk, "HASH", 0).expect("vm init failed").run();
+        assert_eq!(result.expect("run failed"), Value::Integer(16));
+    }
+
+    #[test]
+    fn test_import_cycles_and_ambiguous_calls_are_errors() {
+        let dir = project(
+            "cycle",
+            &[
+                ("a.ark", "import \"b.ark\"\nfunc f() {\n    return 1\n}\n"),
+                ("b.ark", "import \"a.ark\"\nfunc g() {\n    return 2\n}\n"),
+                ("main.ark", "import \"a.ark\"\n"),
+            ],
+        );
+        match link(&dir, Vec::new()) {
+            Err(LinkError::Cycle(chain)) => {
+                let files: Vec<&str> = chain
+                    .split(" -> ")
+                    .map(|f| f.rsplit('/').next().unwrap_or(f))
+                    .collect();
+                assert_eq!(files, ["a.ark", "b.ark", "a.ark"]);
+            }
+            other => panic!("expected a cycle, got {:?}", other),
+        }
+
+        let dir = project(
+            "ambiguous",
+            &[
+                ("a.ark", "func f() {\n    return 1\n}\n"),
+                ("b.ark", "func f() {\n    return 2\n}\n"),
+                (
+                    "main.ark",
+                    "import \"a.ark\"\nimport \"b.ark\"\nx := a.f() + b.f()\ny := f()\n",
+                ),
+            ],
+        );
+        let err = link(&dir, Vec::new()).expect_err("bare f is ambiguous");
+        assert!(matches!(&err, LinkError::Ambiguous { name, .. } if name == "f"));
+        assert_eq!(err.to_diagnostic().code, "E0405");
+    }
+
+    #[test]
+    fn test_only_the_programs_own_tests_are_discovered() {
+        let dir = project(
+            "tests",
+            &[
+                (
+                    "util.ark",
+                    "func one() {\n    return 1\n}\n#[test]\nfunc test_one() {\n    assert(one() == 1)\n}\n",
+                ),
+                (
+                    "main.ark",
+                    "import \"util.ark\"\n#[test]\nfunc test_b() {\n    assert_eq(util.one(), 1)\n}\nfunc helper() {\n    return 2\n}\n#[test]\nfunc test_a() {\n    assert(helper() == 2)\n}\n",
+                ),
+            ],
+        );
+        let program = link(&dir, Vec::new()).expect("link failed");
+        assert_eq!(test_functions(&program), ["test_b", "test_a"]);
+        assert!(bench_functions(&program).is_empty());
+    }
+
+    #[test]
+    fn test_compiled_programs_are_hash_and_signature_checked() {
+        use ed25519_dalek::SigningKey;
+        use rand::rngs::OsRng;
+
+        let key = SigningKey::generate(&mut OsRng);
+        let program = parser::parse_source("x := 20 + 1\nx\n", "main.ark").expect("parse failed");
+        let json = CompiledProgram::new(program.clone(), Some(&key))
+            .expect("hash failed")
+            .to_json();
+
+        let trusting = TrustPolicy::new(vec![key.verifying_key()]);
+        let mast = load_compiled_program(&json, &trusting).expect("load failed");
+        assert_eq!(mast.content, program);
+        let chunk = Compiler::new().compile(&mast.content);
+        let result = VM::new(chunk, &mast.hash, 0).expect("vm init failed").run();
+        assert_eq!(result.expect("run failed"), Value::Integer(21));
+
+        // Editing the program breaks its hash; re-hashing it breaks the signature
+        let tampered = json.replace("20", "99");
+        assert!(matches!(
+            load_compiled_program(&tampered, &trusting),
+            Err(LoadError::HashMismatch { .. })
+        ));
+        let edited = parser::parse_source("x := 99 + 1\nx\n", "main.ark").expect("parse failed");
+        let mut forged: CompiledProgram = serde_json::from_str(&json).expect("bad json");
+        forged.mast = MastNode::new(edited).expect("hash failed");
+        assert!(matches!(
+            load_compiled_program(&forged.to_json(), &trusting),
+            Err(LoadError::Signature(ArtifactError::HashMismatch { .. }))
+        ));
+
+        let unsigned = CompiledProgram::new(program.clone(), None)
+            .expect("hash failed")
+            .to_json();
+        assert!(load_compiled_program(&unsigned, &TrustPolicy::default()).is_ok());
+        assert!(matches!(
+            load_compiled_program(&unsigned, &trusting),
+            Err(LoadError::Signature(ArtifactError::Unsigned))
+        ));
+
+        // A JSON MAST is never signed, so trust roots refuse it outright
+        let legacy = serde_json::to_string(&program).expect("serialize failed");
+        assert!(load_unsigned_program(&legacy, &TrustPolicy::default()).is_ok());
+        assert!(matches!(
+            load_unsigned_program(&legacy, &trusting),
+            Err(LoadError::Signature(ArtifactError::Unsigned))
+        ));
+    }
+}
@@ -384,6 +384,10 @@ fn expand_expr(expr: &Expression, registry: &mut MacroRegistry) -> Result<Expres
             Ok(Expression::List(expanded?))
         }
 
+        Expression::Propagate(inner) => Ok(Expression::Propagate(Box::new(expand_expr(
+            inner, registry,
+        )?))),
+
         // Leaf nodes — no expansion needed
         _ => Ok(expr.clone()),
     }
@@ -432,19 +436,25 @@ fn expand_stmt(stmt: &Statement, registry: &mut MacroRegistry) -> Result<Stateme
                 else_block: expanded_else,
             })
         }
-        Statement::While { condition, body } => {
+        Statement::While {
+            condition,
+            body,
+            label,
+        } => {
             let expanded_cond = expand_expr(condition, registry)?;
             let expanded_body: Result<Vec<Statement>, _> =
                 body.iter().map(|s| expand_stmt(s, registry)).collect();
             Ok(Statement::While {
                 condition: expanded_cond,
                 body: expanded_body?,
+                label: label.clone(),
             })
         }
         Statement::For {
             variable,
             iterable,
             body,
+            label,
         } => {
             let expanded_iter = expand_expr(iterable, registry)?;
             let expanded_body: Result<Vec<Statement>, _> =
@@ -453,12 +463,28 @@ fn expand_stmt(stmt: &Statement, registry: &mut MacroRegistry) -> Result<Stateme
                 variable: variable.clone(),
                 iterable: expanded_iter,
                 body: expanded_body?,
+                label: label.clone(),
             })
         }
         Statement::Return(e) => {
             let expanded = expand_expr(e, registry)?;
             Ok(Statement::Return(expanded))
         }
+        Statement::TryCatch {
+            body,
+            catch_var,
+            handler,
+        } => {
+            let expanded_body: Result<Vec<Statement>, _> =
+                body.iter().map(|s| expand_stmt(s, registry)).collect();
+            let expanded_handler: Result<Vec<Statement>, _> =
+                handler.iter().map(|s| expand_stmt(s, registry)).collect();
+            Ok(Statement::TryCatch {
+                body: expanded_body?,
+                catch_var: catch_var.clone(),
+                handler: expanded_handler?,
+            })
+        }
         // Pass-through statements
         _ => Ok(stmt.clone()),
     }
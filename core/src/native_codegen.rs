@@ -0,0 +1,970 @@
+/*
+ * Copyright (c) 2026 Mohamad Al-Zawahreh (dba Sovereign Systems).
+ *
+ * Native Code Generator for the Ark Language.
+ *
+ * Compiles ArkNode AST to machine code for the host CPU with Cranelift and
+ * runs it in-process (JIT). This is the third backend, next to the bytecode
+ * compiler (compiler.rs) and the WASM code generator (wasm_codegen.rs), for
+ * long-running integer workloads where the VM's dispatch overhead dominates.
+ *
+ * LICENSE: DUAL-LICENSED (AGPLv3 or COMMERCIAL).
+ *
+ * PATENT NOTICE: Protected by US Patent App #63/935,467.
+ * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
+ */
+
+use crate::ast::{ArkNode, Expression, FunctionDef, Statement};
+use crate::compiler::optimize;
+use cranelift_codegen::ir::condcodes::IntCC;
+use cranelift_codegen::ir::{types, AbiParam, Block, InstBuilder, MemFlags, Signature, Value};
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, FuncId, Linkage, Module};
+use std::collections::HashMap;
+use std::fmt;
+
+// =============================================================================
+// Error Types
+// =============================================================================
+
+#[derive(Debug, Clone)]
+pub struct NativeCompileError {
+    pub message: String,
+    pub context: String,
+}
+
+impl fmt::Display for NativeCompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "native compile error: {} ({})",
+            self.message, self.context
+        )
+    }
+}
+
+impl std::error::Error for NativeCompileError {}
+
+fn error(message: impl Into<String>, context: &str) -> NativeCompileError {
+    NativeCompileError {
+        message: message.into(),
+        context: context.to_string(),
+    }
+}
+
+// =============================================================================
+// Native Value Representation
+// =============================================================================
+
+/// Every Ark value is an i64 in a register, as in the WASM backend:
+///
+/// - Integers: raw i64 value
+/// - Booleans: 0 or 1
+///
+/// Strings, lists, structs, floats and closures need a heap and a runtime
+/// that this backend does not have yet; programs using them are rejected
+/// at compile time, so run them on the VM or the WASM backend instead.
+const VALUE: types::Type = types::I64;
+
+/// Same optimization level as the WASM backend runs the AST through.
+const OPT_LEVEL: u8 = 2;
+
+/// Symbol the compiled code calls for `print`.
+const PRINT_SYMBOL: &str = "ark_native_print";
+
+extern "C" fn ark_native_print(value: i64) -> i64 {
+    println!("{}", value);
+    0
+}
+
+/// Deepest chain of Ark calls compiled code may make before it stops with a
+/// recursion error, well inside the native stack of a test thread.
+pub const MAX_CALL_DEPTH: i64 = 10_000;
+
+/// Fault codes compiled code leaves in [`NativeState`] when it stops early.
+const FAULT_DIVISION_BY_ZERO: i64 = 1;
+const FAULT_DIVISION_OVERFLOW: i64 = 2;
+const FAULT_RECURSION_LIMIT: i64 = 3;
+
+/// Per-run state that compiled code reads and writes through the pointer
+/// every compiled function takes as its first parameter.
+///
+/// Runtime errors do not trap: the failing function records a fault code
+/// here and returns, and every caller checks the code after each call and
+/// returns in turn, so the error unwinds back to the host instead of
+/// killing it with a signal.
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct NativeState {
+    fault: i64,
+    depth: i64,
+}
+
+const FAULT_OFFSET: i32 = std::mem::offset_of!(NativeState, fault) as i32;
+const DEPTH_OFFSET: i32 = std::mem::offset_of!(NativeState, depth) as i32;
+
+impl NativeState {
+    /// The error the last run stopped with, if any.
+    pub fn error(&self) -> Option<NativeCompileError> {
+        let message = match self.fault {
+            0 => return None,
+            FAULT_DIVISION_BY_ZERO => "division by zero".to_string(),
+            FAULT_DIVISION_OVERFLOW => "integer overflow in division".to_string(),
+            FAULT_RECURSION_LIMIT => {
+                format!("recursion limit exceeded ({} calls deep)", MAX_CALL_DEPTH)
+            }
+            code => format!("unknown fault {}", code),
+        };
+        Some(error(message, "run"))
+    }
+}
+
+// =============================================================================
+// Native Code Generator
+// =============================================================================
+
+/// JIT-compiles Ark programs to native code.
+///
+/// The code lives in memory owned by the generator: a pointer returned by
+/// [`compile`](Self::compile) stays valid for as long as the generator does.
+pub struct NativeCodegen {
+    module: JITModule,
+    /// Programs compiled so far, used to keep symbol names apart
+    generation: u32,
+}
+
+impl NativeCodegen {
+    /// Create a generator targeting the host CPU.
+    pub fn new() -> Result<Self, NativeCompileError> {
+        let mut flags = settings::builder();
+        for (flag, value) in [
+            ("use_colocated_libcalls", "false"),
+            ("is_pic", "false"),
+            ("opt_level", "speed"),
+        ] {
+            flags
+                .set(flag, value)
+                .map_err(|e| error(e.to_string(), "NativeCodegen::new"))?;
+        }
+        let isa = cranelift_native::builder()
+            .map_err(|e| error(e, "NativeCodegen::new"))?
+            .finish(settings::Flags::new(flags))
+            .map_err(|e| error(e.to_string(), "NativeCodegen::new"))?;
+
+        let mut builder = JITBuilder::with_isa(isa, default_libcall_names());
+        builder.symbol(PRINT_SYMBOL, ark_native_print as *const u8);
+        Ok(Self {
+            module: JITModule::new(builder),
+            generation: 0,
+        })
+    }
+
+    /// Compile `node` and return a pointer to its entry point, an
+    /// `extern "C" fn(*mut NativeState) -> i64` that runs the top-level code
+    /// and returns the value of its last expression statement (0 if there is
+    /// none). Check [`NativeState::error`] afterwards: the value is 0 when the
+    /// run stopped with an error.
+    pub fn compile(&mut self, node: &ArkNode) -> Result<*const u8, NativeCompileError> {
+        let optimized = optimize(node.clone(), OPT_LEVEL);
+        let (func_defs, top_level) = split_top_level(&optimized);
+        let generation = self.generation;
+        self.generation += 1;
+
+        let mut print_signature = self.module.make_signature();
+        print_signature.params.push(AbiParam::new(VALUE));
+        print_signature.returns.push(AbiParam::new(VALUE));
+        let print = self
+            .module
+            .declare_function(PRINT_SYMBOL, Linkage::Import, &print_signature)
+            .map_err(|e| error(e.to_string(), "print"))?;
+
+        // Declare every function first so that bodies can call each other
+        let mut functions = HashMap::new();
+        for func_def in &func_defs {
+            if functions.contains_key(&func_def.name) {
+                return Err(error(
+                    format!("function '{}' is defined more than once", func_def.name),
+                    "NativeCodegen::compile",
+                ));
+            }
+            let arity = func_def.inputs.len();
+            let id = self
+                .module
+                .declare_function(
+                    &format!("ark{}_{}", generation, func_def.name),
+                    Linkage::Local,
+                    &self.signature(arity),
+                )
+                .map_err(|e| error(e.to_string(), &func_def.name))?;
+            functions.insert(func_def.name.clone(), (id, arity));
+        }
+        let entry = self
+            .module
+            .declare_function(
+                &format!("ark{}_main", generation),
+                Linkage::Export,
+                &self.signature(0),
+            )
+            .map_err(|e| error(e.to_string(), "entry"))?;
+
+        let mut builder_ctx = FunctionBuilderContext::new();
+        for func_def in &func_defs {
+            let params: Vec<&str> = func_def.inputs.iter().map(|(n, _)| n.as_str()).collect();
+            let (id, _) = functions[&func_def.name];
+            self.define(
+                id,
+                &params,
+                &mut builder_ctx,
+                &functions,
+                print,
+                |lowering| lowering.lower_node(&func_def.body.content),
+            )
+            .map_err(|e| NativeCompileError {
+                context: format!("{} in '{}'", e.context, func_def.name),
+                ..e
+            })?;
+        }
+        self.define(
+            entry,
+            &[],
+            &mut builder_ctx,
+            &functions,
+            print,
+            |lowering| lowering.lower_block(&top_level),
+        )?;
+
+        self.module
+            .finalize_definitions()
+            .map_err(|e| error(e.to_string(), "finalize"))?;
+        Ok(self.module.get_finalized_function(entry))
+    }
+
+    /// `(*mut NativeState, i64 × arity) -> i64`
+    fn signature(&self, arity: usize) -> Signature {
+        let mut sig = self.module.make_signature();
+        let pointer = self.module.target_config().pointer_type();
+        sig.params.push(AbiParam::new(pointer));
+        sig.params.extend((0..arity).map(|_| AbiParam::new(VALUE)));
+        sig.returns.push(AbiParam::new(VALUE));
+        sig
+    }
+
+    /// Build the body of function `id` with `lower`, which returns the value
+    /// the body falls through with.
+    fn define(
+        &mut self,
+        id: FuncId,
+        params: &[&str],
+        builder_ctx: &mut FunctionBuilderContext,
+        functions: &HashMap<String, (FuncId, usize)>,
+        print: FuncId,
+        lower: impl FnOnce(&mut FunctionLowering) -> Result<Value, NativeCompileError>,
+    ) -> Result<(), NativeCompileError> {
+        let mut ctx = self.module.make_context();
+        ctx.func.signature = self.signature(params.len());
+
+        let mut builder = FunctionBuilder::new(&mut ctx.func, builder_ctx);
+        let entry_block = builder.create_block();
+        builder.append_block_params_for_function_params(entry_block);
+        builder.switch_to_block(entry_block);
+        builder.seal_block(entry_block);
+
+        let state = builder.block_params(entry_block)[0];
+        let mut vars = HashMap::new();
+        for (i, name) in params.iter().enumerate() {
+            let var = builder.declare_var(VALUE);
+            let value = builder.block_params(entry_block)[i + 1];
+            builder.def_var(var, value);
+            vars.insert(name.to_string(), var);
+        }
+
+        let mut lowering = FunctionLowering {
+            builder,
+            module: &mut self.module,
+            functions,
+            print,
+            state,
+            vars,
+            loops: Vec::new(),
+        };
+        let result = lower(&mut lowering)?;
+        lowering.builder.ins().return_(&[result]);
+        lowering.builder.seal_all_blocks();
+        lowering.builder.finalize();
+
+        self.module
+            .define_function(id, &mut ctx)
+            .map_err(|e| error(e.to_string(), "define_function"))?;
+        self.module.clear_context(&mut ctx);
+        Ok(())
+    }
+}
+
+/// Compile `node` and run it, returning the value of its last top-level
+/// expression statement.
+pub fn run(node: &ArkNode) -> Result<i64, NativeCompileError> {
+    let mut codegen = NativeCodegen::new()?;
+    let entry = codegen.compile(node)?;
+    // SAFETY: `compile` returns the entry point of a function it just
+    // finalized with the signature `(*mut NativeState) -> i64` in the default
+    // C calling convention, and `codegen` keeps its code mapped until after
+    // the call.
+    let main: extern "C" fn(*mut NativeState) -> i64 = unsafe { std::mem::transmute(entry) };
+    let mut state = NativeState::default();
+    let value = main(&mut state);
+    match state.error() {
+        Some(e) => Err(e),
+        None => Ok(value),
+    }
+}
+
+/// Top-level function definitions, and the remaining top-level statements.
+fn split_top_level(node: &ArkNode) -> (Vec<FunctionDef>, Vec<Statement>) {
+    let stmts = match node {
+        ArkNode::Function(func_def) => vec![Statement::Function(func_def.clone())],
+        ArkNode::Statement(Statement::Block(stmts)) => stmts.clone(),
+        ArkNode::Statement(stmt) => vec![stmt.clone()],
+        ArkNode::Expression(expr) => vec![Statement::Expression(expr.clone())],
+        ArkNode::Type(_) => Vec::new(),
+    };
+    let mut func_defs = Vec::new();
+    let mut top_level = Vec::new();
+    for stmt in stmts {
+        match stmt {
+            Statement::Function(func_def) => func_defs.push(func_def),
+            other => top_level.push(other),
+        }
+    }
+    (func_defs, top_level)
+}
+
+// =============================================================================
+// Function Body Lowering
+// =============================================================================
+
+/// State for lowering one function body to Cranelift IR.
+struct FunctionLowering<'a, 'b> {
+    builder: FunctionBuilder<'a>,
+    module: &'b mut JITModule,
+    /// Ark function name → (declared function, arity)
+    functions: &'b HashMap<String, (FuncId, usize)>,
+    print: FuncId,
+    /// The `*mut NativeState` this function was called with
+    state: Value,
+    /// Ark variable → Cranelift variable; one flat scope per function, as in
+    /// the WASM backend
+    vars: HashMap<String, Variable>,
+    /// Enclosing loops, innermost last: (label, break block, continue block)
+    loops: Vec<(Option<String>, Block, Block)>,
+}
+
+impl FunctionLowering<'_, '_> {
+    fn lower_node(&mut self, node: &ArkNode) -> Result<Value, NativeCompileError> {
+        match node {
+            ArkNode::Statement(Statement::Block(stmts)) => self.lower_block(stmts),
+            ArkNode::Statement(stmt) => self.lower_block(std::slice::from_ref(stmt)),
+            ArkNode::Expression(expr) => self.lower_expr(expr),
+            ArkNode::Function(_) | ArkNode::Type(_) => Ok(self.builder.ins().iconst(VALUE, 0)),
+        }
+    }
+
+    /// Lower `stmts`; the block's value is that of its last statement if it
+    /// is an expression, otherwise 0.
+    fn lower_block(&mut self, stmts: &[Statement]) -> Result<Value, NativeCompileError> {
+        let mut value = None;
+        for stmt in stmts {
+            value = self.lower_stmt(stmt)?;
+        }
+        Ok(value.unwrap_or_else(|| self.builder.ins().iconst(VALUE, 0)))
+    }
+
+    /// After a terminator, carry on in a fresh block that nothing branches
+    /// to, so that any statements left in the Ark block still have a home.
+    fn start_unreachable_block(&mut self) {
+        let block = self.builder.create_block();
+        self.builder.switch_to_block(block);
+    }
+
+    /// Lower a statement; an expression statement yields its value.
+    fn lower_stmt(&mut self, stmt: &Statement) -> Result<Option<Value>, NativeCompileError> {
+        match stmt {
+            Statement::Expression(expr) => return self.lower_expr(expr).map(Some),
+            Statement::Let { name, value, .. } => {
+                let value = self.lower_expr(value)?;
+                let var = match self.vars.get(name) {
+                    Some(&var) => var,
+                    None => {
+                        let var = self.builder.declare_var(VALUE);
+                        self.vars.insert(name.clone(), var);
+                        var
+                    }
+                };
+                self.builder.def_var(var, value);
+            }
+            Statement::Export(inner) => return self.lower_stmt(inner),
+            Statement::Block(stmts) => {
+                self.lower_block(stmts)?;
+            }
+            // Faults unwind straight back to the host, so the handler never
+            // runs: as in the WASM backend, a failing body aborts the program
+            Statement::TryCatch { body, .. } => {
+                self.lower_block(body)?;
+            }
+            Statement::Return(expr) => {
+                let value = self.lower_expr(expr)?;
+                self.builder.ins().return_(&[value]);
+                self.start_unreachable_block();
+            }
+            Statement::If {
+                condition,
+                then_block,
+                else_block,
+            } => {
+                let condition = self.lower_expr(condition)?;
+                let then_label = self.builder.create_block();
+                let else_label = self.builder.create_block();
+                let merge = self.builder.create_block();
+                self.builder
+                    .ins()
+                    .brif(condition, then_label, &[], else_label, &[]);
+
+                self.builder.switch_to_block(then_label);
+                self.lower_block(then_block)?;
+                self.builder.ins().jump(merge, &[]);
+
+                self.builder.switch_to_block(else_label);
+                if let Some(else_block) = else_block {
+                    self.lower_block(else_block)?;
+                }
+                self.builder.ins().jump(merge, &[]);
+
+                self.builder.switch_to_block(merge);
+            }
+            Statement::While {
+                condition,
+                body,
+                label,
+            } => {
+                //   jump header
+                // header:                      ; continue target
+                //   brif condition, body, exit
+                // body:
+                //   body...
+                //   jump header
+                // exit:                        ; break target
+                let header = self.builder.create_block();
+                let body_label = self.builder.create_block();
+                let exit = self.builder.create_block();
+                self.builder.ins().jump(header, &[]);
+
+                self.builder.switch_to_block(header);
+                let condition = self.lower_expr(condition)?;
+                self.builder
+                    .ins()
+                    .brif(condition, body_label, &[], exit, &[]);
+
+                self.builder.switch_to_block(body_label);
+                self.loops.push((label.clone(), exit, header));
+                self.lower_block(body)?;
+                self.loops.pop();
+                self.builder.ins().jump(header, &[]);
+
+                self.builder.switch_to_block(exit);
+            }
+            Statement::For {
+                variable,
+                iterable,
+                body,
+                label,
+            } => self.lower_for(variable, iterable, body, label.clone())?,
+            Statement::Break { label } => {
+                let (exit, _) = self.target_loop(label.as_deref())?;
+                self.builder.ins().jump(exit, &[]);
+                self.start_unreachable_block();
+            }
+            Statement::Continue { label } => {
+                let (_, next) = self.target_loop(label.as_deref())?;
+                self.builder.ins().jump(next, &[]);
+                self.start_unreachable_block();
+            }
+            // Declarations carry no code of their own; nested functions are
+            // not lifted
+            Statement::Import(_)
+            | Statement::StructDecl(_)
+            | Statement::EnumDecl(_)
+            | Statement::TraitDecl(_)
+            | Statement::ImplBlock(_) => {}
+            Statement::Function(func_def) => {
+                return Err(error(
+                    format!(
+                        "nested function '{}' is not supported by the native backend",
+                        func_def.name
+                    ),
+                    "lower_stmt",
+                ));
+            }
+            Statement::LetDestructure { .. } | Statement::SetField { .. } => {
+                return Err(error(
+                    "destructuring and field assignment are not supported by the native backend",
+                    "lower_stmt",
+                ));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Lower `for variable in a..b` (or `a..=b`) to a counted loop. Like the
+    /// other backends, `variable` is rebound from a hidden index each time
+    /// round, so assigning to it in the body does not change the iteration.
+    fn lower_for(
+        &mut self,
+        variable: &str,
+        iterable: &Expression,
+        body: &[Statement],
+        label: Option<String>,
+    ) -> Result<(), NativeCompileError> {
+        let (start, end, inclusive) = match iterable {
+            Expression::Call {
+                function_hash,
+                args,
+            } if args.len() == 2
+                && matches!(
+                    function_hash.as_str(),
+                    "range_exclusive" | "range_inclusive"
+                ) =>
+            {
+                (&args[0], &args[1], function_hash == "range_inclusive")
+            }
+            _ => {
+                return Err(error(
+                    "the native backend can only iterate over integer ranges",
+                    "lower_for",
+                ));
+            }
+        };
+
+        let start = self.lower_expr(start)?;
+        let end = self.lower_expr(end)?;
+        let index = self.builder.declare_var(VALUE);
+        self.builder.def_var(index, start);
+        let end_var = self.builder.declare_var(VALUE);
+        self.builder.def_var(end_var, end);
+        let var = match self.vars.get(variable) {
+            Some(&var) => var,
+            None => {
+                let var = self.builder.declare_var(VALUE);
+                self.vars.insert(variable.to_string(), var);
+                var
+            }
+        };
+
+        let header = self.builder.create_block();
+        let body_label = self.builder.create_block();
+        let step = self.builder.create_block();
+        let exit = self.builder.create_block();
+        self.builder.ins().jump(header, &[]);
+
+        self.builder.switch_to_block(header);
+        let current = self.builder.use_var(index);
+        let end = self.builder.use_var(end_var);
+        let cc = if inclusive {
+            IntCC::SignedLessThanOrEqual
+        } else {
+            IntCC::SignedLessThan
+        };
+        let in_range = self.builder.ins().icmp(cc, current, end);
+        self.builder
+            .ins()
+            .brif(in_range, body_label, &[], exit, &[]);
+
+        self.builder.switch_to_block(body_label);
+        self.builder.def_var(var, current);
+        self.loops.push((label, exit, step));
+        self.lower_block(body)?;
+        self.loops.pop();
+        self.builder.ins().jump(step, &[]);
+
+        self.builder.switch_to_block(step);
+        let current = self.builder.use_var(index);
+        let next = self.builder.ins().iadd_imm(current, 1);
+        self.builder.def_var(index, next);
+        self.builder.ins().jump(header, &[]);
+
+        self.builder.switch_to_block(exit);
+        Ok(())
+    }
+
+    /// (break, continue) blocks of the innermost loop, or of the nearest
+    /// enclosing loop carrying `label`.
+    fn target_loop(&self, label: Option<&str>) -> Result<(Block, Block), NativeCompileError> {
+        let target = match label {
+            None => self.loops.last(),
+            Some(name) => self
+                .loops
+                .iter()
+                .rfind(|(l, _, _)| l.as_deref() == Some(name)),
+        };
+        match (target, label) {
+            (Some(&(_, exit, next)), _) => Ok((exit, next)),
+            (None, None) => Err(error("break/continue outside of a loop", "lower_stmt")),
+            (None, Some(name)) => Err(error(
+                format!("no enclosing loop labelled '{}'", name),
+                "lower_stmt",
+            )),
+        }
+    }
+
+    fn lower_expr(&mut self, expr: &Expression) -> Result<Value, NativeCompileError> {
+        match expr {
+            Expression::Integer(n) => Ok(self.builder.ins().iconst(VALUE, *n)),
+            Expression::Literal(s) => {
+                let value = match s.as_str() {
+                    "true" => 1,
+                    "false" => 0,
+                    _ => s.parse::<i64>().map_err(|_| {
+                        error(
+                            format!(
+                                "literal '{}' is not supported by the native backend, \
+                                 which only handles integers and booleans",
+                                s
+                            ),
+                            "lower_expr",
+                        )
+                    })?,
+                };
+                Ok(self.builder.ins().iconst(VALUE, value))
+            }
+            Expression::Variable(name) => match self.vars.get(name) {
+                Some(&var) => Ok(self.builder.use_var(var)),
+                None => Err(error(
+                    format!("undefined variable '{}'", name),
+                    "lower_expr",
+                )),
+            },
+            Expression::Call {
+                function_hash,
+                args,
+            } => self.lower_call(function_hash, args),
+            Expression::List(_)
+            | Expression::StructInit { .. }
+            | Expression::MapInit { .. }
+            | Expression::GetField { .. }
+            | Expression::Match { .. }
+            | Expression::Lambda { .. }
+            | Expression::EnumInit { .. }
+            | Expression::Propagate(_) => Err(error(
+                "lists, structs, maps, matches, lambdas, enums and '?' are not supported by \
+                 the native backend",
+                "lower_expr",
+            )),
+        }
+    }
+
+    fn lower_call(
+        &mut self,
+        function_hash: &str,
+        args: &[Expression],
+    ) -> Result<Value, NativeCompileError> {
+        let expect_args = |n: usize| {
+            if args.len() == n {
+                Ok(())
+            } else {
+                Err(error(
+                    format!(
+                        "'{}' takes {} argument(s), got {}",
+                        function_hash,
+                        n,
+                        args.len()
+                    ),
+                    "lower_call",
+                ))
+            }
+        };
+        let cc = match function_hash {
+            "intrinsic_eq" | "eq" => Some(IntCC::Equal),
+            "intrinsic_neq" | "neq" => Some(IntCC::NotEqual),
+            "intrinsic_lt" | "lt" => Some(IntCC::SignedLessThan),
+            "intrinsic_gt" | "gt" => Some(IntCC::SignedGreaterThan),
+            "intrinsic_le" | "le" => Some(IntCC::SignedLessThanOrEqual),
+            "intrinsic_ge" | "ge" => Some(IntCC::SignedGreaterThanOrEqual),
+            _ => None,
+        };
+        if let Some(cc) = cc {
+            expect_args(2)?;
+            let (a, b) = self.lower_pair(args)?;
+            let flag = self.builder.ins().icmp(cc, a, b);
+            return Ok(self.builder.ins().uextend(VALUE, flag));
+        }
+
+        match function_hash {
+            "intrinsic_add" | "add" => {
+                expect_args(2)?;
+                let (a, b) = self.lower_pair(args)?;
+                Ok(self.builder.ins().iadd(a, b))
+            }
+            "intrinsic_sub" | "sub" => {
+                expect_args(2)?;
+                let (a, b) = self.lower_pair(args)?;
+                Ok(self.builder.ins().isub(a, b))
+            }
+            "intrinsic_mul" | "mul" => {
+                expect_args(2)?;
+                let (a, b) = self.lower_pair(args)?;
+                Ok(self.builder.ins().imul(a, b))
+            }
+            // sdiv/srem would raise SIGFPE in the host on a zero divisor or
+            // on i64::MIN / -1, so both are checked and fault first
+            "intrinsic_div" | "div" => {
+                expect_args(2)?;
+                let (a, b) = self.lower_pair(args)?;
+                self.check_divisor(a, b);
+                Ok(self.builder.ins().sdiv(a, b))
+            }
+            "intrinsic_mod" | "modulo" | "mod" => {
+                expect_args(2)?;
+                let (a, b) = self.lower_pair(args)?;
+                self.check_divisor(a, b);
+                Ok(self.builder.ins().srem(a, b))
+            }
+            "neg" => {
+                expect_args(1)?;
+                let a = self.lower_expr(&args[0])?;
+                Ok(self.builder.ins().ineg(a))
+            }
+            // Logical operators evaluate both sides and yield 0 or 1
+            "intrinsic_and" | "and" | "intrinsic_or" | "or" => {
+                expect_args(2)?;
+                let (a, b) = self.lower_pair(args)?;
+                let a = self.builder.ins().icmp_imm(IntCC::NotEqual, a, 0);
+                let b = self.builder.ins().icmp_imm(IntCC::NotEqual, b, 0);
+                let flag = if function_hash.ends_with("and") {
+                    self.builder.ins().band(a, b)
+                } else {
+                    self.builder.ins().bor(a, b)
+                };
+                Ok(self.builder.ins().uextend(VALUE, flag))
+            }
+            "intrinsic_not" | "not" => {
+                expect_args(1)?;
+                let a = self.lower_expr(&args[0])?;
+                let flag = self.builder.ins().icmp_imm(IntCC::Equal, a, 0);
+                Ok(self.builder.ins().uextend(VALUE, flag))
+            }
+            "print" | "intrinsic_print" => {
+                expect_args(1)?;
+                let value = self.lower_expr(&args[0])?;
+                let print = self.print;
+                self.call(print, &[value])
+            }
+            name => {
+                let Some(&(id, arity)) = self.functions.get(name) else {
+                    return Err(error(
+                        format!("'{}' is not supported by the native backend", name),
+                        "lower_call",
+                    ));
+                };
+                expect_args(arity)?;
+                let mut values = vec![self.state];
+                for arg in args {
+                    values.push(self.lower_expr(arg)?);
+                }
+                self.call_ark(id, &values)
+            }
+        }
+    }
+
+    fn lower_pair(&mut self, args: &[Expression]) -> Result<(Value, Value), NativeCompileError> {
+        let a = self.lower_expr(&args[0])?;
+        let b = self.lower_expr(&args[1])?;
+        Ok((a, b))
+    }
+
+    /// Fault unless `a / b` and `a % b` are defined.
+    fn check_divisor(&mut self, a: Value, b: Value) {
+        let zero = self.builder.ins().icmp_imm(IntCC::Equal, b, 0);
+        let code = self.builder.ins().iconst(VALUE, FAULT_DIVISION_BY_ZERO);
+        self.fault_if(zero, code);
+
+        let min = self.builder.ins().icmp_imm(IntCC::Equal, a, i64::MIN);
+        let minus_one = self.builder.ins().icmp_imm(IntCC::Equal, b, -1);
+        let overflow = self.builder.ins().band(min, minus_one);
+        let code = self.builder.ins().iconst(VALUE, FAULT_DIVISION_OVERFLOW);
+        self.fault_if(overflow, code);
+    }
+
+    /// If `condition` is nonzero, record fault `code` and return 0;
+    /// otherwise carry on in a fresh block.
+    fn fault_if(&mut self, condition: Value, code: Value) {
+        let fail = self.builder.create_block();
+        let ok = self.builder.create_block();
+        self.builder.ins().brif(condition, fail, &[], ok, &[]);
+
+        self.builder.switch_to_block(fail);
+        self.builder
+            .ins()
+            .store(MemFlags::trusted(), code, self.state, FAULT_OFFSET);
+        let zero = self.builder.ins().iconst(VALUE, 0);
+        self.builder.ins().return_(&[zero]);
+
+        self.builder.switch_to_block(ok);
+    }
+
+    /// Call Ark function `id` one level deeper, faulting instead once
+    /// [`MAX_CALL_DEPTH`] is reached, and return on if the callee faulted.
+    fn call_ark(&mut self, id: FuncId, args: &[Value]) -> Result<Value, NativeCompileError> {
+        let flags = MemFlags::trusted();
+        let depth = self
+            .builder
+            .ins()
+            .load(VALUE, flags, self.state, DEPTH_OFFSET);
+        let deeper = self.builder.ins().iadd_imm(depth, 1);
+        let too_deep =
+            self.builder
+                .ins()
+                .icmp_imm(IntCC::SignedGreaterThan, deeper, MAX_CALL_DEPTH);
+        let code = self.builder.ins().iconst(VALUE, FAULT_RECURSION_LIMIT);
+        self.fault_if(too_deep, code);
+
+        self.builder
+            .ins()
+            .store(flags, deeper, self.state, DEPTH_OFFSET);
+        let result = self.call(id, args)?;
+        self.builder
+            .ins()
+            .store(flags, depth, self.state, DEPTH_OFFSET);
+
+        let fault = self
+            .builder
+            .ins()
+            .load(VALUE, flags, self.state, FAULT_OFFSET);
+        self.fault_if(fault, fault);
+        Ok(result)
+    }
+
+    /// Call function `id` and return its single result.
+    fn call(&mut self, id: FuncId, args: &[Value]) -> Result<Value, NativeCompileError> {
+        let callee = self.module.declare_func_in_func(id, self.builder.func);
+        let call = self.builder.ins().call(callee, args);
+        Ok(self.builder.inst_results(call)[0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    fn run_source(source: &str) -> Result<i64, NativeCompileError> {
+        let ast = parser::parse_source(source, "test.ark").expect("parse failed");
+        run(&ast)
+    }
+
+    #[test]
+    fn test_recursive_functions() {
+        let source = "func fib(n) {\n    if n < 2 {\n        return n\n    }\n    \
+                      return fib(n - 1) + fib(n - 2)\n}\nfib(25)";
+        assert_eq!(run_source(source).expect("run failed"), 75025);
+    }
+
+    #[test]
+    fn test_loops_with_labels() {
+        let source = r#"
+total := 0
+'outer: for i in 0..10 {
+    for j in 0..10 {
+        if j > i {
+            continue 'outer
+        }
+        if i == 6 {
+            break 'outer
+        }
+        total := total + j
+    }
+}
+k := 0
+while k < 5 {
+    k := k + 1
+    total := total + k * k
+}
+total
+"#;
+        assert_eq!(run_source(source).expect("run failed"), 35 + 55);
+    }
+
+    #[test]
+    fn test_logic_and_comparisons() {
+        let source = "a := 7\nb := 3\n(a > b and !(a == b)) + (a % b) * 10 - -a / b";
+        assert_eq!(run_source(source).expect("run failed"), 1 + 10 + 2);
+    }
+
+    #[test]
+    fn test_unsupported_programs_are_rejected() {
+        let err = run_source("func f(x) {\n    return x\n}\nf(1, 2)").expect_err("bad arity");
+        assert!(
+            err.message.contains("takes 1 argument(s), got 2"),
+            "{}",
+            err.message
+        );
+
+        let err = run_source("xs := [1, 2]").expect_err("lists compiled");
+        assert!(err.message.contains("not supported"), "{}", err.message);
+
+        let err = run_source("print(\"hi\")").expect_err("strings compiled");
+        assert!(
+            err.message.contains("integers and booleans"),
+            "{}",
+            err.message
+        );
+    }
+
+    #[test]
+    fn test_division_faults_return_errors() {
+        let err = run_source("func f(x) {\n    return 10 / x\n}\nf(0)").expect_err("div by zero");
+        assert_eq!(err.message, "division by zero");
+        let err = run_source("x := 0\n7 % x").expect_err("mod by zero");
+        assert_eq!(err.message, "division by zero");
+
+        let min = "m := 0 - 9223372036854775807 - 1\nd := 0 - 1\n";
+        let err = run_source(&format!("{}m / d", min)).expect_err("MIN / -1");
+        assert_eq!(err.message, "integer overflow in division");
+        let err = run_source(&format!("{}m % d", min)).expect_err("MIN % -1");
+        assert_eq!(err.message, "integer overflow in division");
+
+        // A fault skips the rest of the program, including later prints
+        let err = run_source("func f(x) {\n    return 1 / x\n}\nf(0)\nprint(1)\n5")
+            .expect_err("fault ignored");
+        assert_eq!(err.message, "division by zero");
+        assert_eq!(run_source("0 - 7 / 2 + 7 % 3").expect("run failed"), -2);
+    }
+
+    #[test]
+    fn test_deep_recursion_is_stopped() {
+        let source = "func down(n) {\n    return down(n + 1)\n}\ndown(0)";
+        let err = run_source(source).expect_err("unbounded recursion");
+        assert!(err.message.contains("recursion limit"), "{}", err.message);
+
+        // The depth count unwinds with each return, so a deep but bounded
+        // chain still runs, repeatedly
+        let source = "func sum(n) {\n    if n == 0 {\n        return 0\n    }\n    \
+                      return n + sum(n - 1)\n}\nsum(5000) + sum(5000)";
+        assert_eq!(run_source(source).expect("run failed"), 2 * 12_502_500);
+    }
+
+    #[test]
+    fn test_compile_twice_with_one_generator() {
+        let mut codegen = NativeCodegen::new().expect("host ISA");
+        for n in [3, 4] {
+            let source = format!("func sq(x) {{\n    return x * x\n}}\nsq({})", n);
+            let ast = parser::parse_source(&source, "test.ark").expect("parse failed");
+            let entry = codegen.compile(&ast).expect("compile failed");
+            // SAFETY: see `run`
+            let main: extern "C" fn(*mut NativeState) -> i64 =
+                unsafe { std::mem::transmute(entry) };
+            let mut state = NativeState::default();
+            assert_eq!(main(&mut state), n * n);
+            assert!(state.error().is_none());
+        }
+    }
+}
@@ -0,0 +1,1476 @@
+/*
+ * Copyright (c) 2026 Mohamad Al-Zawahreh (dba Sovereign Systems).
+ *
+ * This file is part of the Ark Sovereign Compiler.
+ *
+ * LICENSE: DUAL-LICENSED (AGPLv3 or COMMERCIAL).
+ *
+ * 1. OPEN SOURCE: You may use this file under the terms of the GNU Affero
+ * General Public License v3.0. If you link to this code, your ENTIRE
+ * application must be open-sourced under AGPLv3.
+ *
+ * 2. COMMERCIAL: For proprietary use, you must obtain a Commercial License
+ * from Sovereign Systems.
+ *
+ * PATENT NOTICE: Protected by US Patent App #63/935,467.
+ * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
+ */
+
+//! Packages (`ark pkg`): the `ark.toml` manifest, the `ark.lock` lockfile,
+//! and fetching dependencies into the cache under `~/.ark/packages`.
+//!
+//! ```toml
+//! [package]
+//! name = "wallet"
+//! version = "0.3.0"
+//! entry = "lib.ark"          # what `import wallet` loads
+//!
+//! [dependencies]
+//! json_utils = "1.2"         # registry, semver-compatible with 1.2.0
+//! geo = { git = "https://example.com/geo.git", rev = "v0.4.1" }
+//! shared = { path = "../shared" }
+//! ```
+//!
+//! Registry dependencies come from the HTTP registry named by
+//! `ARK_REGISTRY` or `[registry] url`. It serves
+//!
+//! - `GET  {url}/api/v1/packages/{name}`: `{"versions": [{"version", "checksum"}]}`
+//! - `GET  {url}/api/v1/packages/{name}/{version}/download`: the archive
+//! - `PUT  {url}/api/v1/packages/{name}/{version}`: publish an archive
+//!
+//! An archive is gzipped JSON, `{"files": {"path": "contents"}}`, and its
+//! checksum is the SHA-256 of the gzipped bytes. Git dependencies are
+//! cloned with the `git` command; the lockfile pins the commit.
+//!
+//! Package names become directories in the cache and parts of registry
+//! URLs, so they are limited to lowercase letters, digits, `_` and `-`.
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use thiserror::Error;
+
+pub const MANIFEST: &str = "ark.toml";
+pub const LOCKFILE: &str = "ark.lock";
+
+#[derive(Error, Debug)]
+pub enum PackageError {
+    #[error("{file}:{line}: {message}")]
+    Syntax {
+        file: String,
+        line: usize,
+        message: String,
+    },
+    #[error("cannot access {path}: {error}")]
+    Io {
+        path: String,
+        #[source]
+        error: std::io::Error,
+    },
+    #[error("no ark.toml in {0} or any directory above it")]
+    NoManifest(String),
+    #[error("invalid version '{0}' (expected MAJOR.MINOR.PATCH)")]
+    Version(String),
+    #[error("no published version of '{name}' matches '{requirement}'")]
+    NoMatch { name: String, requirement: String },
+    #[error("'{name}' is required as both {first} and {second}")]
+    Conflict {
+        name: String,
+        first: String,
+        second: String,
+    },
+    #[error("'{0}' comes from the registry, but none is set: use ARK_REGISTRY or [registry] url")]
+    NoRegistry(String),
+    #[error("{url}: {message}")]
+    Http { url: String, message: String },
+    #[error("git {command} failed: {message}")]
+    Git { command: String, message: String },
+    #[error("checksum mismatch for {name} {version}: expected {expected}, got {actual}")]
+    Checksum {
+        name: String,
+        version: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("invalid package archive: {0}")]
+    Archive(String),
+    #[error("package '{0}' is not installed; run `ark pkg install`")]
+    NotInstalled(String),
+}
+
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> PackageError + '_ {
+    move |error| PackageError::Io {
+        path: path.display().to_string(),
+        error,
+    }
+}
+
+// =============================================================================
+// Versions
+// =============================================================================
+
+/// A `MAJOR.MINOR.PATCH` package version.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    pub fn parse(s: &str) -> Result<Self, PackageError> {
+        let parts: Vec<&str> = s.trim().split('.').collect();
+        let number = |part: &str| part.parse::<u64>().ok();
+        match parts.as_slice() {
+            [major, minor, patch] => match (number(major), number(minor), number(patch)) {
+                (Some(major), Some(minor), Some(patch)) => Ok(Version {
+                    major,
+                    minor,
+                    patch,
+                }),
+                _ => Err(PackageError::Version(s.to_string())),
+            },
+            _ => Err(PackageError::Version(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Which versions a registry dependency accepts: `*`, `=1.2.3`, or
+/// `1.2.3` / `1.2` / `1`, meaning any later version Cargo would call
+/// compatible (same major, or same minor below 1.0).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionReq {
+    Any,
+    Exact(Version),
+    Compatible(Version),
+}
+
+impl VersionReq {
+    pub fn parse(s: &str) -> Result<Self, PackageError> {
+        let s = s.trim();
+        if s == "*" {
+            return Ok(VersionReq::Any);
+        }
+        if let Some(exact) = s.strip_prefix('=') {
+            return Version::parse(exact).map(VersionReq::Exact);
+        }
+        let base = s.strip_prefix('^').unwrap_or(s);
+        let padded = match base.matches('.').count() {
+            0 => format!("{}.0.0", base),
+            1 => format!("{}.0", base),
+            _ => base.to_string(),
+        };
+        Version::parse(&padded)
+            .map(VersionReq::Compatible)
+            .map_err(|_| PackageError::Version(s.to_string()))
+    }
+
+    pub fn matches(&self, version: &Version) -> bool {
+        match self {
+            VersionReq::Any => true,
+            VersionReq::Exact(exact) => version == exact,
+            VersionReq::Compatible(base) => {
+                version >= base
+                    && match (base.major, base.minor) {
+                        (0, 0) => version == base,
+                        (0, minor) => version.major == 0 && version.minor == minor,
+                        (major, _) => version.major == major,
+                    }
+            }
+        }
+    }
+}
+
+impl fmt::Display for VersionReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionReq::Any => write!(f, "*"),
+            VersionReq::Exact(v) => write!(f, "={}", v),
+            VersionReq::Compatible(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+// =============================================================================
+// TOML
+// =============================================================================
+
+/// A value in the TOML subset manifests and lockfiles use: strings, and
+/// inline tables of strings.
+#[derive(Debug, Clone, PartialEq)]
+enum TomlValue {
+    Str(String),
+    Table(Vec<(String, String)>),
+}
+
+/// One `[name]` or `[[name]]` section; keys before the first header go in
+/// a section named "".
+#[derive(Debug)]
+struct TomlSection {
+    name: String,
+    entries: Vec<(String, TomlValue)>,
+}
+
+impl TomlSection {
+    fn get(&self, key: &str) -> Option<&TomlValue> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    fn string(&self, key: &str) -> Option<&str> {
+        match self.get(key) {
+            Some(TomlValue::Str(s)) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+fn parse_toml(text: &str, file: &str) -> Result<Vec<TomlSection>, PackageError> {
+    let mut sections = vec![TomlSection {
+        name: String::new(),
+        entries: Vec::new(),
+    }];
+    for (index, raw) in text.lines().enumerate() {
+        let error = |message: &str| PackageError::Syntax {
+            file: file.to_string(),
+            line: index + 1,
+            message: message.to_string(),
+        };
+        let mut cursor = TomlCursor {
+            chars: raw.chars().collect(),
+            pos: 0,
+        };
+        cursor.skip_space();
+        if cursor.at_end() {
+            continue;
+        }
+        if cursor.eat('[') {
+            let array = cursor.eat('[');
+            let name = cursor.key().ok_or_else(|| error("expected a table name"))?;
+            if !cursor.eat(']') || (array && !cursor.eat(']')) {
+                return Err(error("unclosed table header"));
+            }
+            if !cursor.at_end() {
+                return Err(error("unexpected text after table header"));
+            }
+            sections.push(TomlSection {
+                name,
+                entries: Vec::new(),
+            });
+            continue;
+        }
+        let key = cursor
+            .key()
+            .ok_or_else(|| error("expected `key = value`"))?;
+        if !cursor.eat('=') {
+            return Err(error("expected `=` after key"));
+        }
+        let value = if cursor.eat('{') {
+            let mut entries = Vec::new();
+            if !cursor.eat('}') {
+                loop {
+                    let k = cursor.key().ok_or_else(|| error("expected a key"))?;
+                    if !cursor.eat('=') {
+                        return Err(error("expected `=` after key"));
+                    }
+                    let v = cursor.string().ok_or_else(|| error("expected a string"))?;
+                    entries.push((k, v));
+                    if cursor.eat('}') {
+                        break;
+                    }
+                    if !cursor.eat(',') {
+                        return Err(error("expected `,` or `}` in inline table"));
+                    }
+                }
+            }
+            TomlValue::Table(entries)
+        } else {
+            TomlValue::Str(cursor.string().ok_or_else(|| error("expected a string"))?)
+        };
+        if !cursor.at_end() {
+            return Err(error("unexpected text after value"));
+        }
+        let section = sections.last_mut().expect("there is always a section");
+        section.entries.push((key, value));
+    }
+    Ok(sections)
+}
+
+struct TomlCursor {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl TomlCursor {
+    fn skip_space(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    /// Whether only whitespace and a comment are left.
+    fn at_end(&mut self) -> bool {
+        self.skip_space();
+        matches!(self.chars.get(self.pos), None | Some('#'))
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_space();
+        if self.chars.get(self.pos) == Some(&c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// A bare key (`a-z`, digits, `_`, `-`, dotted) or a quoted one.
+    fn key(&mut self) -> Option<String> {
+        self.skip_space();
+        if self.chars.get(self.pos) == Some(&'"') {
+            return self.string();
+        }
+        let start = self.pos;
+        while self
+            .chars
+            .get(self.pos)
+            .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        {
+            self.pos += 1;
+        }
+        (self.pos > start).then(|| self.chars[start..self.pos].iter().collect())
+    }
+
+    fn string(&mut self) -> Option<String> {
+        if !self.eat('"') {
+            return None;
+        }
+        let mut out = String::new();
+        loop {
+            let c = *self.chars.get(self.pos)?;
+            self.pos += 1;
+            match c {
+                '"' => return Some(out),
+                '\\' => {
+                    let escaped = *self.chars.get(self.pos)?;
+                    self.pos += 1;
+                    out.push(match escaped {
+                        'n' => '\n',
+                        't' => '\t',
+                        other => other,
+                    });
+                }
+                c => out.push(c),
+            }
+        }
+    }
+}
+
+fn toml_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// =============================================================================
+// Manifest
+// =============================================================================
+
+/// Where a dependency comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Registry(VersionReq),
+    Git {
+        url: String,
+        rev: Option<String>,
+    },
+    /// A directory, relative to the manifest that names it.
+    Path(PathBuf),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Registry(req) => write!(f, "version {}", req),
+            Source::Git { url, rev: None } => write!(f, "git {}", url),
+            Source::Git {
+                url,
+                rev: Some(rev),
+            } => write!(f, "git {} at {}", url, rev),
+            Source::Path(path) => write!(f, "path {}", path.display()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub name: String,
+    pub source: Source,
+}
+
+impl Dependency {
+    /// The dependency as a line of `[dependencies]`.
+    pub fn to_toml(&self) -> String {
+        let value = match &self.source {
+            Source::Registry(req) => toml_string(&req.to_string()),
+            Source::Git { url, rev } => match rev {
+                Some(rev) => format!(
+                    "{{ git = {}, rev = {} }}",
+                    toml_string(url),
+                    toml_string(rev)
+                ),
+                None => format!("{{ git = {} }}", toml_string(url)),
+            },
+            Source::Path(path) => format!("{{ path = {} }}", toml_string(&path.to_string_lossy())),
+        };
+        format!("{} = {}", self.name, value)
+    }
+}
+
+/// A parsed `ark.toml`.
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    pub name: String,
+    pub version: Version,
+    /// The file `import <name>` loads, relative to the package root.
+    pub entry: String,
+    pub dependencies: Vec<Dependency>,
+    /// `[registry] url`, for registry dependencies and `ark pkg publish`.
+    pub registry: Option<String>,
+}
+
+impl Manifest {
+    pub fn parse(text: &str, file: &str) -> Result<Self, PackageError> {
+        let sections = parse_toml(text, file)?;
+        let syntax = |message: String| PackageError::Syntax {
+            file: file.to_string(),
+            line: 0,
+            message,
+        };
+        let package = sections
+            .iter()
+            .find(|s| s.name == "package")
+            .ok_or_else(|| syntax("missing [package] section".to_string()))?;
+        let name = package
+            .string("name")
+            .ok_or_else(|| syntax("[package] needs a name".to_string()))?
+            .to_string();
+        if !is_package_name(&name) {
+            return Err(syntax(format!("invalid package name '{}'", name)));
+        }
+        let version = Version::parse(package.string("version").unwrap_or("0.1.0"))?;
+        let entry = package.string("entry").unwrap_or("lib.ark").to_string();
+        if !is_inside(Path::new(&entry)) {
+            return Err(syntax(format!(
+                "entry '{}' must be a path inside the package",
+                entry
+            )));
+        }
+        let registry = sections
+            .iter()
+            .find(|s| s.name == "registry")
+            .and_then(|s| s.string("url"))
+            .map(str::to_string);
+
+        let mut dependencies = Vec::new();
+        for section in sections.iter().filter(|s| s.name == "dependencies") {
+            for (dep, value) in &section.entries {
+                if !is_package_name(dep) {
+                    return Err(syntax(format!("invalid dependency name '{}'", dep)));
+                }
+                let source = match value {
+                    TomlValue::Str(req) => Source::Registry(VersionReq::parse(req)?),
+                    TomlValue::Table(fields) => {
+                        let field = |key: &str| {
+                            fields
+                                .iter()
+                                .find(|(k, _)| k == key)
+                                .map(|(_, v)| v.clone())
+                        };
+                        if let Some(url) = field("git") {
+                            let rev = field("rev");
+                            // Either would reach `git` as an option
+                            if url.starts_with('-')
+                                || rev.as_ref().is_some_and(|r| r.starts_with('-'))
+                            {
+                                return Err(syntax(format!(
+                                    "dependency '{}' has a git url or rev starting with '-'",
+                                    dep
+                                )));
+                            }
+                            Source::Git { url, rev }
+                        } else if let Some(path) = field("path") {
+                            Source::Path(PathBuf::from(path))
+                        } else if let Some(req) = field("version") {
+                            Source::Registry(VersionReq::parse(&req)?)
+                        } else {
+                            return Err(syntax(format!(
+                                "dependency '{}' needs a version, git or path",
+                                dep
+                            )));
+                        }
+                    }
+                };
+                dependencies.push(Dependency {
+                    name: dep.clone(),
+                    source,
+                });
+            }
+        }
+        Ok(Manifest {
+            name,
+            version,
+            entry,
+            dependencies,
+            registry,
+        })
+    }
+
+    /// Read `ark.toml` from a package directory.
+    pub fn load(dir: &Path) -> Result<Self, PackageError> {
+        let path = dir.join(MANIFEST);
+        let text = std::fs::read_to_string(&path).map_err(io_error(&path))?;
+        Self::parse(&text, &path.display().to_string())
+    }
+
+    /// A new manifest for a package with no dependencies.
+    pub fn template(name: &str) -> String {
+        format!(
+            "[package]\nname = {}\nversion = \"0.1.0\"\nentry = \"lib.ark\"\n\n[dependencies]\n",
+            toml_string(name)
+        )
+    }
+}
+
+/// Whether `name` may name a package: lowercase letters, digits, `_` and
+/// `-`.
+fn is_package_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| matches!(b, b'a'..=b'z' | b'0'..=b'9' | b'_' | b'-'))
+}
+
+/// Whether joining `path` to a directory stays inside it.
+fn is_inside(path: &Path) -> bool {
+    path.components().next().is_some()
+        && path.components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// The directory holding the `ark.toml` that governs `start` (a file or
+/// directory): the nearest one at or above it.
+pub fn find_project(start: &Path) -> Option<PathBuf> {
+    let start = start.canonicalize().ok()?;
+    let mut dir = if start.is_dir() {
+        start.as_path()
+    } else {
+        start.parent()?
+    };
+    loop {
+        if dir.join(MANIFEST).is_file() {
+            return Some(dir.to_path_buf());
+        }
+        dir = dir.parent()?;
+    }
+}
+
+/// Add `dep` to the `[dependencies]` of the `ark.toml` in `dir`, replacing
+/// any entry of the same name and keeping the rest of the file as written.
+pub fn add_dependency(dir: &Path, dep: &Dependency) -> Result<(), PackageError> {
+    let path = dir.join(MANIFEST);
+    let text = std::fs::read_to_string(&path).map_err(io_error(&path))?;
+    // Check the file parses before touching it
+    Manifest::parse(&text, &path.display().to_string())?;
+
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    let header = lines.iter().position(|l| l.trim() == "[dependencies]");
+    match header {
+        Some(header) => {
+            let end = lines[header + 1..]
+                .iter()
+                .position(|l| l.trim_start().starts_with('['))
+                .map_or(lines.len(), |i| header + 1 + i);
+            let existing = lines[header + 1..end].iter().position(|l| {
+                l.split('=')
+                    .next()
+                    .is_some_and(|key| key.trim().trim_matches('"') == dep.name)
+            });
+            match existing {
+                Some(i) => lines[header + 1 + i] = dep.to_toml(),
+                None => {
+                    // After the last entry, before any blank lines
+                    let mut at = end;
+                    while at > header + 1 && lines[at - 1].trim().is_empty() {
+                        at -= 1;
+                    }
+                    lines.insert(at, dep.to_toml());
+                }
+            }
+        }
+        None => {
+            if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push("[dependencies]".to_string());
+            lines.push(dep.to_toml());
+        }
+    }
+    let updated = lines.join("\n") + "\n";
+    // And that it still does with `dep` in it
+    Manifest::parse(&updated, &path.display().to_string())?;
+    std::fs::write(&path, updated).map_err(io_error(&path))
+}
+
+// =============================================================================
+// Lockfile
+// =============================================================================
+
+/// One resolved package in `ark.lock`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: Version,
+    /// `registry+<url>`, `git+<url>?rev=<rev>#<commit>` or `path+<dir>`
+    pub source: String,
+    /// SHA-256 of the registry archive.
+    pub checksum: Option<String>,
+}
+
+impl LockedPackage {
+    /// Whether this pin still satisfies `source` as declared in a manifest
+    /// (with path dependencies already made absolute).
+    fn satisfies(&self, source: &Source, registry: Option<&str>) -> bool {
+        match source {
+            Source::Registry(req) => {
+                registry.is_some_and(|url| self.source == format!("registry+{}", url))
+                    && req.matches(&self.version)
+            }
+            Source::Git { url, rev } => self
+                .source
+                .split_once('#')
+                .is_some_and(|(declared, _)| declared == git_source(url, rev.as_deref())),
+            Source::Path(path) => self.source == format!("path+{}", path.display()),
+        }
+    }
+
+    /// The commit a git package is pinned to, if it looks like one.
+    fn commit(&self) -> Option<&str> {
+        self.source
+            .strip_prefix("git+")
+            .and_then(|s| s.split_once('#'))
+            .map(|(_, commit)| commit)
+            .filter(|commit| !commit.is_empty() && commit.bytes().all(|b| b.is_ascii_hexdigit()))
+    }
+}
+
+fn git_source(url: &str, rev: Option<&str>) -> String {
+    match rev {
+        Some(rev) => format!("git+{}?rev={}", url, rev),
+        None => format!("git+{}", url),
+    }
+}
+
+pub fn read_lockfile(dir: &Path) -> Result<Option<Vec<LockedPackage>>, PackageError> {
+    let path = dir.join(LOCKFILE);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(io_error(&path)(e)),
+    };
+    let file = path.display().to_string();
+    let mut packages = Vec::new();
+    for section in parse_toml(&text, &file)?
+        .iter()
+        .filter(|s| s.name == "package")
+    {
+        let field = |key: &str| {
+            section.string(key).ok_or_else(|| PackageError::Syntax {
+                file: file.clone(),
+                line: 0,
+                message: format!("locked package without {}", key),
+            })
+        };
+        let name = field("name")?;
+        if !is_package_name(name) {
+            return Err(PackageError::Syntax {
+                file: file.clone(),
+                line: 0,
+                message: format!("invalid package name '{}'", name),
+            });
+        }
+        packages.push(LockedPackage {
+            name: name.to_string(),
+            version: Version::parse(field("version")?)?,
+            source: field("source")?.to_string(),
+            checksum: section.string("checksum").map(str::to_string),
+        });
+    }
+    Ok(Some(packages))
+}
+
+pub fn write_lockfile(dir: &Path, packages: &[LockedPackage]) -> Result<(), PackageError> {
+    let mut out = String::from("# Written by `ark pkg`. Do not edit.\n");
+    for package in packages {
+        out.push_str(&format!(
+            "\n[[package]]\nname = {}\nversion = {}\nsource = {}\n",
+            toml_string(&package.name),
+            toml_string(&package.version.to_string()),
+            toml_string(&package.source),
+        ));
+        if let Some(checksum) = &package.checksum {
+            out.push_str(&format!("checksum = {}\n", toml_string(checksum)));
+        }
+    }
+    let path = dir.join(LOCKFILE);
+    std::fs::write(&path, out).map_err(io_error(&path))
+}
+
+// =============================================================================
+// Archives
+// =============================================================================
+
+#[derive(Serialize, Deserialize)]
+struct Archive {
+    files: BTreeMap<String, String>,
+}
+
+/// Pack `ark.toml` and every `.ark` file under `dir` (skipping hidden
+/// directories) into a registry archive.
+pub fn pack(dir: &Path) -> Result<Vec<u8>, PackageError> {
+    fn add_files(
+        root: &Path,
+        dir: &Path,
+        files: &mut BTreeMap<String, String>,
+    ) -> Result<(), PackageError> {
+        let entries = std::fs::read_dir(dir).map_err(io_error(dir))?;
+        for entry in entries {
+            let path = entry.map_err(io_error(dir))?.path();
+            let hidden = path
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+            if hidden {
+                continue;
+            }
+            if path.is_dir() {
+                add_files(root, &path, files)?;
+            } else if path.extension().is_some_and(|e| e == "ark")
+                || path.file_name().is_some_and(|n| n == MANIFEST)
+            {
+                let contents = std::fs::read_to_string(&path).map_err(io_error(&path))?;
+                let relative = path.strip_prefix(root).unwrap_or(&path);
+                let key = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.insert(key, contents);
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = BTreeMap::new();
+    add_files(dir, dir, &mut files)?;
+    let json =
+        serde_json::to_vec(&Archive { files }).map_err(|e| PackageError::Archive(e.to_string()))?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(&json)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| PackageError::Archive(e.to_string()))
+}
+
+/// Unpack a registry archive into `dir`, refusing paths that leave it.
+fn unpack(bytes: &[u8], dir: &Path) -> Result<(), PackageError> {
+    let mut json = Vec::new();
+    GzDecoder::new(bytes)
+        .read_to_end(&mut json)
+        .map_err(|e| PackageError::Archive(e.to_string()))?;
+    let archive: Archive =
+        serde_json::from_slice(&json).map_err(|e| PackageError::Archive(e.to_string()))?;
+    for (name, contents) in &archive.files {
+        let relative = Path::new(name);
+        if !is_inside(relative) {
+            return Err(PackageError::Archive(format!("unsafe path '{}'", name)));
+        }
+        let path = dir.join(relative);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(io_error(parent))?;
+        }
+        std::fs::write(&path, contents).map_err(io_error(&path))?;
+    }
+    Ok(())
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+// =============================================================================
+// Installing
+// =============================================================================
+
+/// `$ARK_HOME/packages`, or `~/.ark/packages`.
+pub fn cache_dir() -> PathBuf {
+    if let Some(home) = std::env::var_os("ARK_HOME") {
+        return PathBuf::from(home).join("packages");
+    }
+    let home = std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    home.join(".ark").join("packages")
+}
+
+/// An installed package: where it lives and the file `import <name>`
+/// loads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub root: PathBuf,
+    pub entry: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct RegistryIndex {
+    versions: Vec<RegistryVersion>,
+}
+
+#[derive(Deserialize)]
+struct RegistryVersion {
+    version: String,
+    checksum: String,
+}
+
+/// Resolves, fetches and caches the dependencies of a project.
+pub struct Installer {
+    cache: PathBuf,
+    /// Registry URL overriding the manifest's (`ARK_REGISTRY`)
+    registry: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl Installer {
+    pub fn new(cache: PathBuf, registry: Option<String>) -> Self {
+        let builder = ureq::AgentBuilder::new();
+        let agent = match native_tls::TlsConnector::new() {
+            Ok(tls) => builder.tls_connector(std::sync::Arc::new(tls)),
+            Err(_) => builder,
+        }
+        .build();
+        Installer {
+            cache,
+            registry: registry.map(|url| url.trim_end_matches('/').to_string()),
+            agent,
+        }
+    }
+
+    /// An installer using [`cache_dir`] and `ARK_REGISTRY`.
+    pub fn from_env() -> Self {
+        Self::new(cache_dir(), std::env::var("ARK_REGISTRY").ok())
+    }
+
+    fn registry_for(&self, manifest: &Manifest) -> Option<String> {
+        self.registry.clone().or_else(|| {
+            manifest
+                .registry
+                .as_ref()
+                .map(|url| url.trim_end_matches('/').to_string())
+        })
+    }
+
+    /// Install every dependency of the project in `dir`, transitively,
+    /// and write `ark.lock`. Versions and commits already in the lockfile
+    /// are kept while the manifest still allows them.
+    pub fn install(&self, dir: &Path) -> Result<Vec<LockedPackage>, PackageError> {
+        let manifest = Manifest::load(dir)?;
+        let registry = self.registry_for(&manifest);
+        let previous: HashMap<String, LockedPackage> = read_lockfile(dir)?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|p| (p.name.clone(), p))
+            .collect();
+
+        // (dependency, directory of the manifest that declared it)
+        let mut queue: Vec<(Dependency, PathBuf)> = manifest
+            .dependencies
+            .iter()
+            .rev()
+            .map(|d| (d.clone(), dir.to_path_buf()))
+            .collect();
+        let mut resolved: BTreeMap<String, (LockedPackage, Source)> = BTreeMap::new();
+
+        while let Some((dep, base)) = queue.pop() {
+            let source = match &dep.source {
+                Source::Path(path) => Source::Path(
+                    base.join(path)
+                        .canonicalize()
+                        .map_err(io_error(&base.join(path)))?,
+                ),
+                other => other.clone(),
+            };
+            if let Some((locked, first)) = resolved.get(&dep.name) {
+                if locked.satisfies(&source, registry.as_deref()) {
+                    continue;
+                }
+                return Err(PackageError::Conflict {
+                    name: dep.name.clone(),
+                    first: first.to_string(),
+                    second: source.to_string(),
+                });
+            }
+
+            let pinned = previous
+                .get(&dep.name)
+                .filter(|p| p.satisfies(&source, registry.as_deref()));
+            let (locked, root) = self.fetch(&dep.name, &source, registry.as_deref(), pinned)?;
+
+            // Whatever the package itself depends on
+            if root.join(MANIFEST).is_file() {
+                for d in Manifest::load(&root)?.dependencies.iter().rev() {
+                    queue.push((d.clone(), root.clone()));
+                }
+            }
+            resolved.insert(dep.name.clone(), (locked, source));
+        }
+
+        let packages: Vec<LockedPackage> = resolved.into_values().map(|(p, _)| p).collect();
+        write_lockfile(dir, &packages)?;
+        Ok(packages)
+    }
+
+    /// Make `name` available in the cache (or find it on disk, for path
+    /// dependencies). Returns its lock entry and root directory.
+    fn fetch(
+        &self,
+        name: &str,
+        source: &Source,
+        registry: Option<&str>,
+        pinned: Option<&LockedPackage>,
+    ) -> Result<(LockedPackage, PathBuf), PackageError> {
+        match source {
+            Source::Path(path) => {
+                let version = Manifest::load(path).map_or(Version::default(), |m| m.version);
+                let locked = LockedPackage {
+                    name: name.to_string(),
+                    version,
+                    source: format!("path+{}", path.display()),
+                    checksum: None,
+                };
+                Ok((locked, path.clone()))
+            }
+            Source::Registry(req) => {
+                let url = registry.ok_or_else(|| PackageError::NoRegistry(name.to_string()))?;
+                let pin = pinned.and_then(|p| Some((p.version, p.checksum.clone()?)));
+                let (version, checksum) = match pin {
+                    Some(pin) => pin,
+                    None => self.pick_version(url, name, req)?,
+                };
+                let root = self.cache.join(name).join(version.to_string());
+                if !root.is_dir() {
+                    self.download(url, name, &version, &checksum, &root)?;
+                }
+                let locked = LockedPackage {
+                    name: name.to_string(),
+                    version,
+                    source: format!("registry+{}", url),
+                    checksum: Some(checksum),
+                };
+                Ok((locked, root))
+            }
+            Source::Git { url, rev } => {
+                let commit = match pinned.and_then(LockedPackage::commit) {
+                    Some(commit) => commit.to_string(),
+                    None => self.git_clone(name, url, rev.as_deref())?,
+                };
+                let root = self.cache.join(name).join(format!("git-{}", commit));
+                if !root.is_dir() {
+                    self.git_clone(name, url, Some(&commit))?;
+                }
+                let version = Manifest::load(&root).map_or(Version::default(), |m| m.version);
+                let locked = LockedPackage {
+                    name: name.to_string(),
+                    version,
+                    source: format!("{}#{}", git_source(url, rev.as_deref()), commit),
+                    checksum: None,
+                };
+                Ok((locked, root))
+            }
+        }
+    }
+
+    fn get(&self, url: &str) -> Result<ureq::Response, PackageError> {
+        self.agent.get(url).call().map_err(|e| PackageError::Http {
+            url: url.to_string(),
+            message: e.to_string(),
+        })
+    }
+
+    /// The newest published version matching `req`, and its checksum.
+    fn pick_version(
+        &self,
+        registry: &str,
+        name: &str,
+        req: &VersionReq,
+    ) -> Result<(Version, String), PackageError> {
+        let url = format!("{}/api/v1/packages/{}", registry, name);
+        let index: RegistryIndex = self
+            .get(&url)?
+            .into_json()
+            .map_err(|e| PackageError::Http {
+                url: url.clone(),
+                message: e.to_string(),
+            })?;
+        index
+            .versions
+            .into_iter()
+            .filter_map(|v| Some((Version::parse(&v.version).ok()?, v.checksum)))
+            .filter(|(version, _)| req.matches(version))
+            .max_by(|a, b| a.0.cmp(&b.0))
+            .ok_or_else(|| PackageError::NoMatch {
+                name: name.to_string(),
+                requirement: req.to_string(),
+            })
+    }
+
+    fn download(
+        &self,
+        registry: &str,
+        name: &str,
+        version: &Version,
+        checksum: &str,
+        root: &Path,
+    ) -> Result<(), PackageError> {
+        let url = format!("{}/api/v1/packages/{}/{}/download", registry, name, version);
+        let mut bytes = Vec::new();
+        self.get(&url)?
+            .into_reader()
+            .read_to_end(&mut bytes)
+            .map_err(|e| PackageError::Http {
+                url: url.clone(),
+                message: e.to_string(),
+            })?;
+        let actual = sha256_hex(&bytes);
+        if actual != checksum {
+            return Err(PackageError::Checksum {
+                name: name.to_string(),
+                version: version.to_string(),
+                expected: checksum.to_string(),
+                actual,
+            });
+        }
+        // Unpack beside the final directory so a failure leaves no
+        // half-installed package behind
+        let staging = root.with_extension("partial");
+        let _ = std::fs::remove_dir_all(&staging);
+        unpack(&bytes, &staging)?;
+        std::fs::rename(&staging, root).map_err(io_error(root))
+    }
+
+    /// Clone `url` at `rev` (or its default branch) into the cache and
+    /// return the commit it is at.
+    fn git_clone(&self, name: &str, url: &str, rev: Option<&str>) -> Result<String, PackageError> {
+        let parent = self.cache.join(name);
+        std::fs::create_dir_all(&parent).map_err(io_error(&parent))?;
+        let staging = parent.join("git-partial");
+        let _ = std::fs::remove_dir_all(&staging);
+
+        let staging_arg = staging.to_string_lossy().into_owned();
+        git(&parent, &["clone", "--quiet", "--", url, &staging_arg])?;
+        if let Some(rev) = rev {
+            git(&staging, &["checkout", "--quiet", "--detach", rev, "--"])?;
+        }
+        let commit = git(&staging, &["rev-parse", "HEAD"])?;
+
+        let root = parent.join(format!("git-{}", commit));
+        if root.is_dir() {
+            let _ = std::fs::remove_dir_all(&staging);
+        } else {
+            std::fs::rename(&staging, &root).map_err(io_error(&root))?;
+        }
+        Ok(commit)
+    }
+
+    /// Upload the package in `dir` to its registry.
+    pub fn publish(&self, dir: &Path, token: Option<&str>) -> Result<Manifest, PackageError> {
+        let manifest = Manifest::load(dir)?;
+        let registry = self
+            .registry_for(&manifest)
+            .ok_or_else(|| PackageError::NoRegistry(manifest.name.clone()))?;
+        let archive = pack(dir)?;
+        let url = format!(
+            "{}/api/v1/packages/{}/{}",
+            registry, manifest.name, manifest.version
+        );
+        let mut request = self
+            .agent
+            .put(&url)
+            .set("Content-Type", "application/gzip")
+            .set("X-Checksum-Sha256", &sha256_hex(&archive));
+        if let Some(token) = token {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+        request
+            .send_bytes(&archive)
+            .map_err(|e| PackageError::Http {
+                url,
+                message: e.to_string(),
+            })?;
+        Ok(manifest)
+    }
+}
+
+/// Run `git` in `dir` and return its trimmed stdout.
+fn git(dir: &Path, args: &[&str]) -> Result<String, PackageError> {
+    let failed = |message: String| PackageError::Git {
+        command: args.first().copied().unwrap_or_default().to_string(),
+        message,
+    };
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| failed(e.to_string()))?;
+    if !output.status.success() {
+        return Err(failed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The packages locked for the project in `dir`, as installed in `cache`.
+/// A project whose manifest declares dependencies must have been
+/// installed first.
+pub fn installed(dir: &Path, cache: &Path) -> Result<Vec<InstalledPackage>, PackageError> {
+    let manifest = Manifest::load(dir)?;
+    let Some(locked) = read_lockfile(dir)? else {
+        return match manifest.dependencies.first() {
+            Some(dep) => Err(PackageError::NotInstalled(dep.name.clone())),
+            None => Ok(Vec::new()),
+        };
+    };
+    if let Some(missing) = manifest
+        .dependencies
+        .iter()
+        .find(|d| !locked.iter().any(|p| p.name == d.name))
+    {
+        return Err(PackageError::NotInstalled(missing.name.clone()));
+    }
+
+    locked
+        .iter()
+        .map(|package| {
+            let root = if let Some(path) = package.source.strip_prefix("path+") {
+                PathBuf::from(path)
+            } else if let Some(commit) = package.commit() {
+                cache.join(&package.name).join(format!("git-{}", commit))
+            } else {
+                cache.join(&package.name).join(package.version.to_string())
+            };
+            if !root.is_dir() {
+                return Err(PackageError::NotInstalled(package.name.clone()));
+            }
+            let entry = Manifest::load(&root).map_or_else(|_| "lib.ark".to_string(), |m| m.entry);
+            Ok(InstalledPackage {
+                name: package.name.clone(),
+                entry: root.join(entry),
+                root,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loader::Linker;
+    use crate::test_support::project;
+    use std::net::TcpListener;
+
+    /// Serve `routes` (path -> body) over HTTP on a local port until the
+    /// test exits. Returns the registry URL.
+    fn registry(routes: Vec<(String, Vec<u8>)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind failed");
+        let url = format!("http://{}", listener.local_addr().expect("no address"));
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buffer) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buffer[..n]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let path = request.split(' ').nth(1).unwrap_or("");
+                let response = match routes.iter().find(|(route, _)| route == path) {
+                    Some((_, body)) => {
+                        let mut response = format!(
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                            body.len()
+                        )
+                        .into_bytes();
+                        response.extend_from_slice(body);
+                        response
+                    }
+                    None => {
+                        b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_vec()
+                    }
+                };
+                let _ = stream.write_all(&response);
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn test_version_requirements() {
+        let v = |s: &str| Version::parse(s).expect("bad version");
+        let req = |s: &str| VersionReq::parse(s).expect("bad requirement");
+
+        assert!(req("1.2").matches(&v("1.9.0")));
+        assert!(!req("1.2").matches(&v("1.1.9")));
+        assert!(!req("1.2").matches(&v("2.0.0")));
+        assert!(req("0.3.1").matches(&v("0.3.4")));
+        assert!(!req("0.3.1").matches(&v("0.4.0")));
+        assert!(!req("0.0.1").matches(&v("0.0.2")));
+        assert!(req("=1.0.0").matches(&v("1.0.0")));
+        assert!(!req("=1.0.0").matches(&v("1.0.1")));
+        assert!(req("*").matches(&v("7.0.0")));
+        assert_eq!(req("^2").to_string(), "2.0.0");
+        assert!(matches!(
+            Version::parse("1.x.0"),
+            Err(PackageError::Version(_))
+        ));
+    }
+
+    #[test]
+    fn test_manifest_parsing_and_adding_dependencies() {
+        let dir = project(
+            "manifest",
+            &[(
+                "ark.toml",
+                "# app\n[package]\nname = \"app\"\nversion = \"0.2.0\"\n\n[dependencies]\njson_utils = \"1.2\"\ngeo = { git = \"https://example.com/geo.git\", rev = \"v0.4.1\" }\n\n[registry]\nurl = \"https://registry.example.com\"\n",
+            )],
+        );
+        let manifest = Manifest::load(&dir).expect("load failed");
+        assert_eq!(manifest.name, "app");
+        assert_eq!(manifest.version.to_string(), "0.2.0");
+        assert_eq!(manifest.entry, "lib.ark");
+        assert_eq!(
+            manifest.registry.as_deref(),
+            Some("https://registry.example.com")
+        );
+        assert_eq!(
+            manifest.dependencies[1].source,
+            Source::Git {
+                url: "https://example.com/geo.git".to_string(),
+                rev: Some("v0.4.1".to_string()),
+            }
+        );
+
+        let shared = Dependency {
+            name: "shared".to_string(),
+            source: Source::Path(PathBuf::from("../shared")),
+        };
+        add_dependency(&dir, &shared).expect("add failed");
+        let json = Dependency {
+            name: "json_utils".to_string(),
+            source: Source::Registry(VersionReq::parse("=1.3.0").expect("bad requirement")),
+        };
+        add_dependency(&dir, &json).expect("add failed");
+
+        let text = std::fs::read_to_string(dir.join(MANIFEST)).expect("read failed");
+        assert!(text.starts_with("# app\n"));
+        assert!(text.contains(
+            "json_utils = \"=1.3.0\"\ngeo = { git = \"https://example.com/geo.git\", rev = \"v0.4.1\" }\nshared = { path = \"../shared\" }\n\n[registry]"
+        ));
+        let manifest = Manifest::load(&dir).expect("reload failed");
+        assert_eq!(manifest.dependencies.len(), 3);
+        assert_eq!(manifest.dependencies[2], shared);
+
+        assert!(matches!(
+            Manifest::parse("[package]\nname = app\n", "bad.toml"),
+            Err(PackageError::Syntax { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_manifests_that_escape_the_cache_are_refused() {
+        let refused = [
+            // Read by git as options
+            "[package]\nname = \"app\"\n\n[dependencies]\ngeo = { git = \"--upload-pack=touch /tmp/pwned\" }\n",
+            "[package]\nname = \"app\"\n\n[dependencies]\ngeo = { git = \"https://example.com/geo.git\", rev = \"--orphan=x\" }\n",
+            // Joined onto the cache directory
+            "[package]\nname = \"app\"\n\n[dependencies]\n\"../../.ssh\" = \"1.0\"\n",
+            "[package]\nname = \"../app\"\n",
+            "[package]\nname = \"app\"\nentry = \"../../outside.ark\"\n",
+            "[package]\nname = \"app\"\nentry = \"/etc/passwd\"\n",
+        ];
+        for text in refused {
+            assert!(
+                matches!(
+                    Manifest::parse(text, "ark.toml"),
+                    Err(PackageError::Syntax { .. })
+                ),
+                "accepted {:?}",
+                text
+            );
+        }
+
+        let dir = project(
+            "refused",
+            &[("ark.toml", "[package]\nname = \"app\"\n\n[dependencies]\n")],
+        );
+        let dependency = Dependency {
+            name: "geo".to_string(),
+            source: Source::Git {
+                url: "-uevil".to_string(),
+                rev: None,
+            },
+        };
+        assert!(add_dependency(&dir, &dependency).is_err());
+        assert!(
+            Manifest::load(&dir)
+                .expect("load failed")
+                .dependencies
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_install_fetches_locks_and_links_dependencies() {
+        let dir = project(
+            "install",
+            &[
+                (
+                    "app/ark.toml",
+                    "[package]\nname = \"app\"\n\n[dependencies]\nshared = { path = \"../shared\" }\n",
+                ),
+                (
+                    "app/main.ark",
+                    "import shared\nimport json_utils\nx := shared.twice(json_utils.width(2))\nx\n",
+                ),
+                (
+                    "shared/ark.toml",
+                    "[package]\nname = \"shared\"\nversion = \"0.4.0\"\nentry = \"src/shared.ark\"\n\n[dependencies]\njson_utils = \"1.2\"\n",
+                ),
+                (
+                    "shared/src/shared.ark",
+                    "func twice(n) {\n    return n * 2\n}\n",
+                ),
+                (
+                    "json_utils/ark.toml",
+                    "[package]\nname = \"json_utils\"\nversion = \"1.4.0\"\n",
+                ),
+                (
+                    "json_utils/lib.ark",
+                    "func width(n) {\n    return n + 1\n}\n",
+                ),
+            ],
+        );
+        let archive = pack(&dir.join("json_utils")).expect("pack failed");
+        let checksum = sha256_hex(&archive);
+        let index = format!(
+            "{{\"versions\": [{{\"version\": \"1.4.0\", \"checksum\": \"{c}\"}}, {{\"version\": \"2.0.0\", \"checksum\": \"{c}\"}}, {{\"version\": \"1.1.0\", \"checksum\": \"{c}\"}}]}}",
+            c = checksum
+        );
+        let url = registry(vec![
+            (
+                "/api/v1/packages/json_utils".to_string(),
+                index.into_bytes(),
+            ),
+            (
+                "/api/v1/packages/json_utils/1.4.0/download".to_string(),
+                archive,
+            ),
+        ]);
+
+        let app = dir.join("app");
+        let cache = dir.join("cache");
+        assert!(matches!(
+            installed(&app, &cache),
+            Err(PackageError::NotInstalled(name)) if name == "shared"
+        ));
+
+        let locked = Installer::new(cache.clone(), Some(url.clone()))
+            .install(&app)
+            .expect("install failed");
+        let names: Vec<&str> = locked.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["json_utils", "shared"]);
+        assert_eq!(locked[0].version.to_string(), "1.4.0");
+        assert_eq!(locked[0].source, format!("registry+{}", url));
+        assert_eq!(locked[0].checksum.as_deref(), Some(checksum.as_str()));
+        assert!(cache.join("json_utils/1.4.0/lib.ark").is_file());
+        assert_eq!(read_lockfile(&app).expect("read failed"), Some(locked));
+
+        let mut linker = Linker::new(Vec::new());
+        for package in installed(&app, &cache).expect("not installed") {
+            linker.add_package(&package.name, package.root, package.entry);
+        }
+        let main = app.join("main.ark");
+        let source = std::fs::read_to_string(&main).expect("read failed");
+        let program = linker
+            .link(&source, &main.to_string_lossy())
+            .expect("link failed");
+        let chunk = crate::compiler::Compiler::new().compile(&program);
+        let result = crate::vm::VM::new(chunk, "HASH", 0)
+            .expect("vm init failed")
+            .run();
+        assert_eq!(
+            result.expect("run failed"),
+            crate::runtime::Value::Integer(6)
+        );
+    }
+
+    #[test]
+    fn test_tampered_archives_are_rejected() {
+        let dir = project(
+            "tampered",
+            &[(
+                "app/ark.toml",
+                "[package]\nname = \"app\"\n\n[dependencies]\nlib = \"*\"\n",
+            )],
+        );
+        let url = registry(vec![
+            (
+                "/api/v1/packages/lib".to_string(),
+                b"{\"versions\": [{\"version\": \"1.0.0\", \"checksum\": \"00\"}]}".to_vec(),
+            ),
+            (
+                "/api/v1/packages/lib/1.0.0/download".to_string(),
+                b"not the archive".to_vec(),
+            ),
+        ]);
+        let err = Installer::new(dir.join("cache"), Some(url))
+            .install(&dir.join("app"))
+            .expect_err("checksum must not match");
+        assert!(matches!(err, PackageError::Checksum { .. }));
+        assert!(!dir.join("cache/lib/1.0.0").exists());
+
+        let mut bytes = Vec::new();
+        let mut encoder = GzEncoder::new(&mut bytes, Compression::default());
+        encoder
+            .write_all(b"{\"files\": {\"../escape.ark\": \"\"}}")
+            .expect("write failed");
+        encoder.finish().expect("gzip failed");
+        assert!(matches!(
+            unpack(&bytes, &dir.join("out")),
+            Err(PackageError::Archive(_))
+        ));
+    }
+}
@@ -26,8 +26,9 @@
 
 use crate::ast::{
     ArkNode, EnumDecl, EnumVariantDef, Expression, FunctionDef, ImplBlock, Import, MastNode,
-    Pattern, Statement, StructDecl, TraitDecl, TraitMethodSig,
+    Pattern, Span, Statement, StructDecl, TraitDecl, TraitMethodSig,
 };
+use crate::diagnostics::Diagnostic;
 use crate::types::ArkType;
 use thiserror::Error;
 
@@ -73,6 +74,38 @@ impl ParseError {
             file: file.to_string(),
         }
     }
+
+    /// This error as a shared [`Diagnostic`], pointing at the offending
+    /// token.
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let at = |line: u32, col: u32, file: &str| {
+            Some(Span {
+                start_line: line,
+                start_col: col,
+                end_line: line,
+                end_col: col,
+                file: file.to_string(),
+            })
+        };
+        match self {
+            ParseError::Syntax {
+                message,
+                line,
+                col,
+                file,
+            } => Diagnostic::error("E0001", message.clone()).with_span(at(*line, *col, file)),
+            ParseError::UnexpectedToken {
+                expected,
+                found,
+                line,
+                col,
+                file,
+            } => Diagnostic::error("E0002", format!("expected {}, found {}", expected, found))
+                .with_span(at(*line, *col, file)),
+            ParseError::UnexpectedEof => Diagnostic::error("E0003", "unexpected end of file")
+                .with_help("check for an unclosed `{`, `(` or `[`"),
+        }
+    }
 }
 
 // ─── Token Types ─────────────────────────────────────────────────────────────
@@ -82,10 +115,13 @@ pub enum TokenKind {
     // Literals
     Integer(i64),
     Float(f64),
+    /// Fixed-point literal such as `12.50d`, holding its digits `12.50`
+    Decimal(String),
     StringLit(String),
     FString(String),
     MultiString(String),
     Identifier(String),
+    Label(String), // 'name, on a loop or after break/continue
     DocComment(String),
 
     // Keywords
@@ -142,10 +178,12 @@ pub enum TokenKind {
     Pipe,     // |>
     DotDot,   // ..
     DotDotEq, // ..=
+    Ellipsis, // ...
     AndAnd,   // &&
     OrOr,     // ||
     Arrow,    // =>
     OptChain, // ?.
+    Question, // ?
 
     // Delimiters
     LParen,
@@ -176,6 +214,23 @@ impl Token {
     }
 }
 
+/// A `//` or `/* */` comment. The parser never sees these;
+/// `Lexer::tokenize_with_comments` keeps them for tools that rewrite source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    /// The comment exactly as written, delimiters included.
+    pub text: String,
+    pub line: u32,
+    pub col: u32,
+}
+
+/// A token together with its exact spelling in the source.
+#[derive(Debug, Clone)]
+pub struct SourceToken {
+    pub token: Token,
+    pub text: String,
+}
+
 // ─── Lexer ───────────────────────────────────────────────────────────────────
 
 pub struct Lexer {
@@ -183,6 +238,8 @@ pub struct Lexer {
     pos: usize,
     line: u32,
     col: u32,
+    /// Comments seen so far, when the caller asked to keep them
+    comments: Option<Vec<Comment>>,
 }
 
 impl Lexer {
@@ -192,6 +249,39 @@ impl Lexer {
             pos: 0,
             line: 1,
             col: 1,
+            comments: None,
+        }
+    }
+
+    /// Tokenize keeping each token's source text and every comment, in
+    /// source order. Unlike `tokenize`, no `Eof` token is appended.
+    pub fn tokenize_with_comments(
+        &mut self,
+    ) -> Result<(Vec<SourceToken>, Vec<Comment>), ParseError> {
+        self.comments = Some(Vec::new());
+        let mut tokens = Vec::new();
+        loop {
+            self.skip_whitespace_and_comments();
+            if self.pos >= self.source.len() {
+                break;
+            }
+            let start = self.pos;
+            let token = self.next_token()?;
+            let text = self.source[start..self.pos].iter().collect();
+            tokens.push(SourceToken { token, text });
+        }
+        Ok((tokens, self.comments.take().unwrap_or_default()))
+    }
+
+    /// Keep the source from `start` to the current position as a comment,
+    /// if comments are being collected.
+    fn record_comment(&mut self, start: usize, line: u32, col: u32) {
+        if let Some(comments) = &mut self.comments {
+            comments.push(Comment {
+                text: self.source[start..self.pos].iter().collect(),
+                line,
+                col,
+            });
         }
     }
 
@@ -245,17 +335,20 @@ impl Lexer {
                 && self.peek_ahead(1) == Some('/')
                 && self.peek_ahead(2) != Some('/')
             {
+                let (start, line, col) = (self.pos, self.line, self.col);
                 while let Some(ch) = self.peek() {
                     if ch == '\n' {
                         break;
                     }
                     self.advance();
                 }
+                self.record_comment(start, line, col);
                 continue;
             }
 
             // Skip block comments /* ... */
             if self.peek() == Some('/') && self.peek_ahead(1) == Some('*') {
+                let (start, line, col) = (self.pos, self.line, self.col);
                 self.advance(); // /
                 self.advance(); // *
                 let mut depth = 1;
@@ -273,6 +366,7 @@ impl Lexer {
                         _ => {}
                     }
                 }
+                self.record_comment(start, line, col);
                 continue;
             }
 
@@ -389,6 +483,15 @@ impl Lexer {
                     break;
                 }
             }
+            // A `d` suffix makes a fixed-point decimal, keeping every digit
+            if self.peek() == Some('d')
+                && !self
+                    .peek_ahead(1)
+                    .is_some_and(|n| n.is_ascii_alphanumeric() || n == '_')
+            {
+                self.advance();
+                return Ok(Token::new(TokenKind::Decimal(num), start_line, start_col));
+            }
             if is_float {
                 let val: f64 = num.parse().unwrap_or(0.0);
                 return Ok(Token::new(TokenKind::Float(val), start_line, start_col));
@@ -442,6 +545,25 @@ impl Lexer {
             return Ok(Token::new(kind, start_line, start_col));
         }
 
+        // Loop labels: 'name
+        if ch == '\''
+            && self
+                .peek_ahead(1)
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        {
+            self.advance();
+            let mut label = String::new();
+            while let Some(c) = self.peek() {
+                if c.is_ascii_alphanumeric() || c == '_' {
+                    label.push(c);
+                    self.advance();
+                } else {
+                    break;
+                }
+            }
+            return Ok(Token::new(TokenKind::Label(label), start_line, start_col));
+        }
+
         // Operators and delimiters
         self.advance();
         let kind = match ch {
@@ -562,6 +684,9 @@ impl Lexer {
                     if self.peek() == Some('=') {
                         self.advance();
                         TokenKind::DotDotEq
+                    } else if self.peek() == Some('.') {
+                        self.advance();
+                        TokenKind::Ellipsis
                     } else {
                         TokenKind::DotDot
                     }
@@ -574,12 +699,7 @@ impl Lexer {
                     self.advance();
                     TokenKind::OptChain
                 } else {
-                    return Err(ParseError::Syntax {
-                        message: "Unexpected '?'".into(),
-                        line: start_line,
-                        col: start_col,
-                        file: String::new(),
-                    });
+                    TokenKind::Question
                 }
             }
             '(' => TokenKind::LParen,
@@ -642,6 +762,8 @@ pub struct Parser {
     tokens: Vec<Token>,
     pos: usize,
     file: String,
+    /// Attach a `Span` to each function body's `MastNode`
+    record_spans: bool,
 }
 
 impl Parser {
@@ -650,9 +772,17 @@ impl Parser {
             tokens,
             pos: 0,
             file: file.to_string(),
+            record_spans: false,
         }
     }
 
+    /// Record source spans on function bodies. Off by default: a span makes
+    /// otherwise identical ASTs compare (and hash) differently depending on
+    /// where the code sits in the file.
+    pub fn record_spans(&mut self, enabled: bool) {
+        self.record_spans = enabled;
+    }
+
     fn peek(&self) -> &Token {
         self.tokens.get(self.pos).unwrap_or(&Token {
             kind: TokenKind::Eof,
@@ -763,6 +893,19 @@ impl Parser {
                 }
                 self.parse_impl_block()
             }
+            _ if !attrs.is_empty() && attrs.iter().all(|a| a == "export") => {
+                let tok = self.peek().clone();
+                match self.parse_statement()? {
+                    stmt @ Statement::Let { .. } => Ok(Statement::Export(Box::new(stmt))),
+                    _ => Err(ParseError::Syntax {
+                        message: "#[export] applies to functions and top-level `let` bindings"
+                            .into(),
+                        line: tok.line,
+                        col: tok.col,
+                        file: self.file.clone(),
+                    }),
+                }
+            }
             _ => {
                 if !attrs.is_empty() {
                     let tok = self.peek().clone();
@@ -791,7 +934,7 @@ impl Parser {
         &mut self,
         attributes: Vec<String>,
     ) -> Result<Statement, ParseError> {
-        self.expect(&TokenKind::Func)?;
+        let func_tok = self.expect(&TokenKind::Func)?;
 
         let name_tok = self.peek().clone();
         let name = match &name_tok.kind {
@@ -817,7 +960,12 @@ impl Parser {
                     _ => return Err(ParseError::unexpected("parameter name", &p_tok, &self.file)),
                 };
                 self.advance();
-                params.push((p_name, ArkType::Any));
+                let ty = if self.match_tok(&TokenKind::Colon) {
+                    self.parse_type_annotation()?
+                } else {
+                    ArkType::Any
+                };
+                params.push((p_name, ty));
 
                 if !self.match_tok(&TokenKind::Comma) {
                     break;
@@ -826,20 +974,38 @@ impl Parser {
         }
         self.expect(&TokenKind::RParen)?;
 
+        // Optional return type
+        let output = if self.match_tok(&TokenKind::Arrow) {
+            self.parse_type_annotation()?
+        } else {
+            ArkType::Any
+        };
+
         // Body
         let body = self.parse_block()?;
         let body_node = ArkNode::Statement(Statement::Block(body));
-        let mast = MastNode::new(body_node).map_err(|e| ParseError::Syntax {
+        let mut mast = MastNode::new(body_node).map_err(|e| ParseError::Syntax {
             message: format!("MAST error: {}", e),
             line: name_tok.line,
             col: name_tok.col,
             file: self.file.clone(),
         })?;
+        if self.record_spans {
+            // From the `func` keyword to the closing brace
+            let close = &self.tokens[self.pos - 1];
+            mast.span = Some(Span {
+                start_line: func_tok.line,
+                start_col: func_tok.col,
+                end_line: close.line,
+                end_col: close.col,
+                file: self.file.clone(),
+            });
+        }
 
         Ok(Statement::Function(FunctionDef {
             name,
             inputs: params,
-            output: ArkType::Any,
+            output,
             body: Box::new(mast),
             attributes,
         }))
@@ -1131,13 +1297,14 @@ impl Parser {
             TokenKind::Import => self.parse_import(),
             TokenKind::Match => self.parse_match(),
             TokenKind::Try => self.parse_try(),
+            TokenKind::Label(_) => self.parse_labeled_loop(),
             TokenKind::Break => {
-                self.advance();
-                Ok(Statement::Break)
+                let label = self.parse_loop_exit_label();
+                Ok(Statement::Break { label })
             }
             TokenKind::Continue => {
-                self.advance();
-                Ok(Statement::Continue)
+                let label = self.parse_loop_exit_label();
+                Ok(Statement::Continue { label })
             }
             TokenKind::Func => self.parse_function_def(),
             TokenKind::Enum => self.parse_enum_def(),
@@ -1150,26 +1317,48 @@ impl Parser {
     fn parse_let(&mut self) -> Result<Statement, ParseError> {
         self.expect(&TokenKind::Let)?;
 
-        // Check for destructure: let (a, b) := expr
-        if self.check(&TokenKind::LParen) {
+        // Check for destructure: let (a, b) := expr / let [a, b] := expr,
+        // or by field: let {x, y} := expr. A trailing `...rest` takes the remainder.
+        if self.check(&TokenKind::LParen)
+            || self.check(&TokenKind::LBracket)
+            || self.check(&TokenKind::LBrace)
+        {
+            let by_field = self.check(&TokenKind::LBrace);
+            let close = match self.peek().kind {
+                TokenKind::LParen => TokenKind::RParen,
+                TokenKind::LBracket => TokenKind::RBracket,
+                _ => TokenKind::RBrace,
+            };
             self.advance();
             let mut names = Vec::new();
+            let mut rest = None;
             loop {
+                let is_rest = self.match_tok(&TokenKind::Ellipsis);
                 let n_tok = self.peek().clone();
                 let name = match &n_tok.kind {
                     TokenKind::Identifier(n) => n.clone(),
                     _ => return Err(ParseError::unexpected("identifier", &n_tok, &self.file)),
                 };
                 self.advance();
+                if is_rest {
+                    // The rest binding must come last.
+                    rest = Some(name);
+                    break;
+                }
                 names.push(name);
                 if !self.match_tok(&TokenKind::Comma) {
                     break;
                 }
             }
-            self.expect(&TokenKind::RParen)?;
+            self.expect(&close)?;
             self.expect(&TokenKind::Assign)?;
             let value = self.parse_expression()?;
-            return Ok(Statement::LetDestructure { names, value });
+            return Ok(Statement::LetDestructure {
+                names,
+                value,
+                by_field,
+                rest,
+            });
         }
 
         // Regular let
@@ -1186,14 +1375,17 @@ impl Parser {
         };
         self.advance();
 
+        // Optional annotation: let p: Point := {...}
+        let ty = if self.match_tok(&TokenKind::Colon) {
+            Some(self.parse_type_annotation()?)
+        } else {
+            None
+        };
+
         self.expect(&TokenKind::Assign)?;
         let value = self.parse_expression()?;
 
-        Ok(Statement::Let {
-            name,
-            ty: None,
-            value,
-        })
+        Ok(Statement::Let { name, ty, value })
     }
 
     fn parse_if(&mut self) -> Result<Statement, ParseError> {
@@ -1224,7 +1416,11 @@ impl Parser {
         self.expect(&TokenKind::While)?;
         let condition = self.parse_expression()?;
         let body = self.parse_block()?;
-        Ok(Statement::While { condition, body })
+        Ok(Statement::While {
+            condition,
+            body,
+            label: None,
+        })
     }
 
     fn parse_for(&mut self) -> Result<Statement, ParseError> {
@@ -1249,9 +1445,52 @@ impl Parser {
             variable,
             iterable,
             body,
+            label: None,
         })
     }
 
+    /// `'name: while ...` or `'name: for ...`.
+    fn parse_labeled_loop(&mut self) -> Result<Statement, ParseError> {
+        let label_tok = self.peek().clone();
+        let TokenKind::Label(name) = &label_tok.kind else {
+            return Err(ParseError::unexpected("loop label", &label_tok, &self.file));
+        };
+        let name = name.clone();
+        self.advance();
+        self.expect(&TokenKind::Colon)?;
+
+        let mut stmt = match self.peek().kind {
+            TokenKind::While => self.parse_while()?,
+            TokenKind::For => self.parse_for()?,
+            _ => {
+                return Err(ParseError::unexpected(
+                    "while or for after a loop label",
+                    self.peek(),
+                    &self.file,
+                ));
+            }
+        };
+        if let Statement::While { label, .. } | Statement::For { label, .. } = &mut stmt {
+            *label = Some(name);
+        }
+        Ok(stmt)
+    }
+
+    /// Consume a `break` or `continue` keyword and the label naming its
+    /// loop, if one follows on the same line.
+    fn parse_loop_exit_label(&mut self) -> Option<String> {
+        let keyword_line = self.peek().line;
+        self.advance();
+        match &self.peek().kind {
+            TokenKind::Label(name) if self.peek().line == keyword_line => {
+                let name = name.clone();
+                self.advance();
+                Some(name)
+            }
+            _ => None,
+        }
+    }
+
     fn parse_return(&mut self) -> Result<Statement, ParseError> {
         self.expect(&TokenKind::Return)?;
         // Return can have no value (returns Unit)
@@ -1265,13 +1504,31 @@ impl Parser {
         Ok(Statement::Return(value))
     }
 
+    /// `import a.b.c`, or `import "path/to/file.ark"`, optionally followed
+    /// by `as name`. Dotted paths are kept joined with `.`.
     fn parse_import(&mut self) -> Result<Statement, ParseError> {
         self.expect(&TokenKind::Import)?;
-        let mut path_parts = Vec::new();
 
         let first_tok = self.peek().clone();
-        let first = match &first_tok.kind {
-            TokenKind::Identifier(n) => n.clone(),
+        let path = match &first_tok.kind {
+            TokenKind::StringLit(file) => {
+                self.advance();
+                file.clone()
+            }
+            TokenKind::Identifier(first) => {
+                let mut path_parts = vec![first.clone()];
+                self.advance();
+                while self.match_tok(&TokenKind::Dot) {
+                    let p_tok = self.peek().clone();
+                    let part = match &p_tok.kind {
+                        TokenKind::Identifier(n) => n.clone(),
+                        _ => return Err(ParseError::unexpected("module name", &p_tok, &self.file)),
+                    };
+                    self.advance();
+                    path_parts.push(part);
+                }
+                path_parts.join(".")
+            }
             _ => {
                 return Err(ParseError::unexpected(
                     "module name",
@@ -1280,23 +1537,22 @@ impl Parser {
                 ));
             }
         };
-        self.advance();
-        path_parts.push(first);
 
-        while self.match_tok(&TokenKind::Dot) {
-            let p_tok = self.peek().clone();
-            let part = match &p_tok.kind {
-                TokenKind::Identifier(n) => n.clone(),
-                _ => return Err(ParseError::unexpected("module name", &p_tok, &self.file)),
-            };
-            self.advance();
-            path_parts.push(part);
-        }
+        // `as` is not a keyword: only `as <name>` right after the path counts
+        let alias = match (
+            &self.peek().kind,
+            self.tokens.get(self.pos + 1).map(|t| &t.kind),
+        ) {
+            (TokenKind::Identifier(kw), Some(TokenKind::Identifier(name))) if kw == "as" => {
+                let name = name.clone();
+                self.advance();
+                self.advance();
+                Some(name)
+            }
+            _ => None,
+        };
 
-        Ok(Statement::Import(Import {
-            path: path_parts.join("."),
-            alias: None,
-        }))
+        Ok(Statement::Import(Import { path, alias }))
     }
 
     fn parse_match(&mut self) -> Result<Statement, ParseError> {
@@ -1324,6 +1580,21 @@ impl Parser {
                 Expression::Variable(s) if s == "_" => Pattern::Wildcard,
                 Expression::Variable(s) => Pattern::Variable(s.clone()),
                 Expression::Integer(n) => Pattern::Literal(n.to_string()),
+                Expression::EnumInit {
+                    enum_name,
+                    variant,
+                    args,
+                } => Pattern::EnumVariant {
+                    enum_name: enum_name.clone(),
+                    variant: variant.clone(),
+                    bindings: args
+                        .iter()
+                        .map(|arg| match arg {
+                            Expression::Variable(name) => name.clone(),
+                            _ => "_".to_string(),
+                        })
+                        .collect(),
+                },
                 _ => Pattern::Wildcard,
             };
 
@@ -1344,7 +1615,7 @@ impl Parser {
 
         self.expect(&TokenKind::Catch)?;
         let var_tok = self.peek().clone();
-        let _catch_var = match &var_tok.kind {
+        let catch_var = match &var_tok.kind {
             TokenKind::Identifier(n) => n.clone(),
             _ => {
                 return Err(ParseError::unexpected(
@@ -1356,14 +1627,13 @@ impl Parser {
         };
         self.advance();
 
-        let _catch_block = self.parse_block()?;
+        let handler = self.parse_block()?;
 
-        // Wrap as nested if/block since AST TryCatch uses MastNode
-        // For the bytecode compiler, we emit as a Block with error handling semantics
-        let all_stmts = try_block;
-        // The catch_var and catch_block are encoded but not fully wired to VM yet
-        // This is a placeholder that preserves the structure
-        Ok(Statement::Block(all_stmts))
+        Ok(Statement::TryCatch {
+            body: try_block,
+            catch_var,
+            handler,
+        })
     }
 
     fn parse_expr_or_assign(&mut self) -> Result<Statement, ParseError> {
@@ -1388,6 +1658,15 @@ impl Parser {
                                 obj_name,
                                 field,
                                 value,
+                                obj: None,
+                            })
+                        } else if let Some(obj_name) = assignment_root(&obj) {
+                            // a.b.field := value / xs[0].field := value
+                            Ok(Statement::SetField {
+                                obj_name,
+                                field,
+                                value,
+                                obj: Some(obj),
                             })
                         } else {
                             Ok(Statement::Expression(value))
@@ -1606,6 +1885,12 @@ impl Parser {
             }
             TokenKind::Minus => {
                 self.advance();
+                // `-12.50d` is a negative literal, as decimals have no `neg`
+                if let TokenKind::Decimal(digits) = &self.peek().kind {
+                    let digits = format!("-{}", digits);
+                    self.advance();
+                    return Ok(decimal_literal(digits));
+                }
                 let expr = self.parse_unary()?;
                 Ok(Expression::Call {
                     function_hash: "neg".into(),
@@ -1624,7 +1909,7 @@ impl Parser {
         }
     }
 
-    // postfix: primary (.field | ?.field | (args) | [index])*
+    // postfix: primary (.field | ?.field | (args) | [index] | ?)*
     fn parse_postfix(&mut self) -> Result<Expression, ParseError> {
         let mut expr = self.parse_primary()?;
         loop {
@@ -1634,6 +1919,8 @@ impl Parser {
                     let field_tok = self.peek().clone();
                     let field = match &field_tok.kind {
                         TokenKind::Identifier(n) => n.clone(),
+                        // A plain name after a dot, as in sys.debug.break()
+                        TokenKind::Break => "break".to_string(),
                         _ => {
                             return Err(ParseError::unexpected(
                                 "field name",
@@ -1730,11 +2017,22 @@ impl Parser {
                         _ => "__call__".into(),
                     };
 
-                    expr = Expression::Call {
-                        function_hash: func_name,
-                        args,
+                    expr = match builtin_variant(&func_name) {
+                        Some((enum_name, variant)) => Expression::EnumInit {
+                            enum_name: enum_name.into(),
+                            variant: variant.into(),
+                            args,
+                        },
+                        None => Expression::Call {
+                            function_hash: func_name,
+                            args,
+                        },
                     };
                 }
+                TokenKind::Question => {
+                    self.advance();
+                    expr = Expression::Propagate(Box::new(expr));
+                }
                 TokenKind::LBracket => {
                     self.advance(); // [
                     let index = self.parse_expression()?;
@@ -1766,6 +2064,11 @@ impl Parser {
                 // Store as Literal since Expression doesn't have a Float variant
                 Ok(Expression::Literal(f.to_string()))
             }
+            TokenKind::Decimal(digits) => {
+                let digits = digits.clone();
+                self.advance();
+                Ok(decimal_literal(digits))
+            }
             TokenKind::StringLit(s) => {
                 let s = s.clone();
                 self.advance();
@@ -1782,6 +2085,14 @@ impl Parser {
                 self.advance();
                 Ok(Expression::Literal(s))
             }
+            TokenKind::Identifier(name) if name == "None" => {
+                self.advance();
+                Ok(Expression::EnumInit {
+                    enum_name: "Option".into(),
+                    variant: "None".into(),
+                    args: Vec::new(),
+                })
+            }
             TokenKind::Identifier(name) => {
                 let name = name.clone();
                 self.advance();
@@ -1821,6 +2132,22 @@ impl Parser {
             }
             TokenKind::LBrace => {
                 self.advance();
+                // A string key starts a map literal; identifier keys name
+                // struct fields.
+                if matches!(self.peek().kind, TokenKind::StringLit(_)) {
+                    let mut entries = Vec::new();
+                    loop {
+                        let key = self.parse_expression()?;
+                        self.expect(&TokenKind::Colon)?;
+                        let value = self.parse_expression()?;
+                        entries.push((key, value));
+                        if !self.match_tok(&TokenKind::Comma) {
+                            break;
+                        }
+                    }
+                    self.expect(&TokenKind::RBrace)?;
+                    return Ok(Expression::MapInit { entries });
+                }
                 let mut fields = Vec::new();
                 if !self.check(&TokenKind::RBrace) {
                     loop {
@@ -1878,6 +2205,39 @@ impl Parser {
     }
 }
 
+/// Root variable of an assignable object path such as `a.b` or `xs[0]`.
+fn assignment_root(expr: &Expression) -> Option<String> {
+    match expr {
+        Expression::Variable(name) => Some(name.clone()),
+        Expression::GetField { obj, .. } => assignment_root(obj),
+        Expression::Call {
+            function_hash,
+            args,
+        } if function_hash == "get_item" && !args.is_empty() => assignment_root(&args[0]),
+        _ => None,
+    }
+}
+
+/// The built-in enum and variant a call to `name` constructs: `Ok(x)` and
+/// `Err(e)` build a `Result`, `Some(x)` an `Option`.
+fn builtin_variant(name: &str) -> Option<(&'static str, &'static str)> {
+    match name {
+        "Ok" => Some(("Result", "Ok")),
+        "Err" => Some(("Result", "Err")),
+        "Some" => Some(("Option", "Some")),
+        _ => None,
+    }
+}
+
+/// The expression a decimal literal with these digits stands for: a call
+/// to `dec.from_string`, so every backend that has the intrinsic takes it.
+fn decimal_literal(digits: String) -> Expression {
+    Expression::Call {
+        function_hash: "dec.from_string".into(),
+        args: vec![Expression::Literal(digits)],
+    }
+}
+
 // ─── Public API ──────────────────────────────────────────────────────────────
 
 /// Parse Ark source code into an `ArkNode` AST.
@@ -1888,6 +2248,16 @@ pub fn parse_source(source: &str, file: &str) -> Result<ArkNode, ParseError> {
     parser.parse_program()
 }
 
+/// Like [`parse_source`], but every function body carries the source span
+/// of its definition, for debug info in compiled output.
+pub fn parse_source_with_spans(source: &str, file: &str) -> Result<ArkNode, ParseError> {
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer.tokenize()?;
+    let mut parser = Parser::new(tokens, file);
+    parser.record_spans(true);
+    parser.parse_program()
+}
+
 /// Parse Ark source code into a `MastNode` (content-addressed, hash-verified).
 pub fn parse_to_mast(source: &str, file: &str) -> Result<MastNode, ParseError> {
     let ast = parse_source(source, file)?;
@@ -1924,6 +2294,31 @@ mod tests {
         assert!(matches!(tokens[1].kind, TokenKind::Float(f) if (f - 3.14).abs() < 0.001));
     }
 
+    #[test]
+    fn test_decimal_literals() {
+        let mut lexer = Lexer::new("12.50d 3d 2dx");
+        let tokens = lexer.tokenize().expect("operation failed");
+        assert_eq!(tokens[0].kind, TokenKind::Decimal("12.50".to_string()));
+        assert_eq!(tokens[1].kind, TokenKind::Decimal("3".to_string()));
+        assert_eq!(tokens[2].kind, TokenKind::Integer(2));
+        assert_eq!(tokens[3].kind, TokenKind::Identifier("dx".to_string()));
+
+        let ast = parse_source("x := -12.50d", "test.ark").expect("operation failed");
+        let ArkNode::Statement(Statement::Block(stmts)) = ast else {
+            panic!("expected a block");
+        };
+        let Statement::Let { value, .. } = &stmts[0] else {
+            panic!("expected a let");
+        };
+        assert_eq!(
+            value,
+            &Expression::Call {
+                function_hash: "dec.from_string".to_string(),
+                args: vec![Expression::Literal("-12.50".to_string())],
+            }
+        );
+    }
+
     #[test]
     fn test_lex_operators() {
         let mut lexer = Lexer::new(":= == != <= >= |> .. ..=");
@@ -2046,6 +2441,114 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_try_catch() {
+        let source = r#"
+            try {
+                x := risky()
+            } catch err {
+                print(err.message)
+            }
+        "#;
+        let ast = parse_source(source, "test.ark").expect("operation failed");
+        let ArkNode::Statement(Statement::Block(stmts)) = ast else {
+            panic!("Expected Block");
+        };
+        let Statement::TryCatch {
+            body,
+            catch_var,
+            handler,
+        } = &stmts[0]
+        else {
+            panic!("Expected TryCatch, got {:?}", stmts[0]);
+        };
+        assert_eq!(catch_var, "err");
+        assert!(matches!(&body[..], [Statement::Let { name, .. }] if name == "x"));
+        assert_eq!(handler.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_result_option_and_propagation() {
+        let source = r#"
+            x := load(path)?
+            match x {
+                Ok(v) => v,
+                Err(_) => 0,
+                None => 1
+            }
+        "#;
+        let ast = parse_source(source, "test.ark").expect("operation failed");
+        let ArkNode::Statement(Statement::Block(stmts)) = ast else {
+            panic!("Expected Block");
+        };
+        let Statement::Let { value, .. } = &stmts[0] else {
+            panic!("Expected Let, got {:?}", stmts[0]);
+        };
+        assert!(matches!(
+            value,
+            Expression::Propagate(inner)
+                if matches!(inner.as_ref(), Expression::Call { function_hash, .. } if function_hash == "load")
+        ));
+        let Statement::Expression(Expression::Match { arms, .. }) = &stmts[1] else {
+            panic!("Expected Match, got {:?}", stmts[1]);
+        };
+        let patterns: Vec<Pattern> = arms.iter().map(|(p, _)| p.clone()).collect();
+        let variant = |enum_name: &str, variant: &str, bindings: &[&str]| Pattern::EnumVariant {
+            enum_name: enum_name.to_string(),
+            variant: variant.to_string(),
+            bindings: bindings.iter().map(|b| b.to_string()).collect(),
+        };
+        assert_eq!(
+            patterns,
+            vec![
+                variant("Result", "Ok", &["v"]),
+                variant("Result", "Err", &["_"]),
+                variant("Option", "None", &[]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_labeled_loops() {
+        let source = r#"
+            'rows: for r in rows {
+                'cells: while i < 10 {
+                    continue 'rows
+                    break 'cells
+                    break
+                }
+            }
+        "#;
+        let ast = parse_source(source, "test.ark").expect("operation failed");
+        let ArkNode::Statement(Statement::Block(stmts)) = ast else {
+            panic!("expected a block");
+        };
+        let Statement::For { label, body, .. } = &stmts[0] else {
+            panic!("expected a for loop, got {:?}", stmts[0]);
+        };
+        assert_eq!(label.as_deref(), Some("rows"));
+        let Statement::While { label, body, .. } = &body[0] else {
+            panic!("expected a while loop, got {:?}", body[0]);
+        };
+        assert_eq!(label.as_deref(), Some("cells"));
+        let label = |name: &str| Some(name.to_string());
+        assert_eq!(
+            body,
+            &vec![
+                Statement::Continue {
+                    label: label("rows")
+                },
+                Statement::Break {
+                    label: label("cells")
+                },
+                Statement::Break { label: None },
+            ]
+        );
+
+        let err = parse_source("'oops: x := 1", "test.ark").expect_err("label on a non-loop");
+        assert!(format!("{:?}", err).contains("while or for"), "{:?}", err);
+    }
+
     #[test]
     fn test_parse_method_call() {
         let ast = parse_source(r#"sys.ai.ask("prompt")"#, "test.ark").expect("operation failed");
@@ -2061,6 +2564,15 @@ mod tests {
                 panic!("Expected Call");
             }
         }
+
+        let ast = parse_source("sys.debug.break()", "test.ark").expect("operation failed");
+        if let ArkNode::Statement(Statement::Block(stmts)) = ast {
+            assert!(matches!(
+                &stmts[0],
+                Statement::Expression(Expression::Call { function_hash, .. })
+                    if function_hash == "sys.debug.break"
+            ));
+        }
     }
 
     #[test]
@@ -2089,6 +2601,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_map_init() {
+        let ast = parse_source(r#"m := { "a": 1, "b": x }"#, "test.ark").expect("operation failed");
+        let ArkNode::Statement(Statement::Block(stmts)) = ast else {
+            panic!("expected a block");
+        };
+        let Statement::Let { value, .. } = &stmts[0] else {
+            panic!("expected a let");
+        };
+        let Expression::MapInit { entries } = value else {
+            panic!("expected a map literal, got {:?}", value);
+        };
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].0, Expression::Literal("a".to_string()));
+        assert_eq!(entries[1].1, Expression::Variable("x".to_string()));
+    }
+
     #[test]
     fn test_parse_import() {
         let ast = parse_source("import std.crypto", "test.ark").expect("operation failed");
@@ -2099,16 +2628,91 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_file_import_with_alias() {
+        let ast = parse_source(
+            "import \"lib/utils.ark\" as u\nimport std.math as m\nimport io\nas := 1",
+            "test.ark",
+        )
+        .expect("operation failed");
+        let ArkNode::Statement(Statement::Block(stmts)) = ast else {
+            panic!("expected a block");
+        };
+        let imports: Vec<(&str, Option<&str>)> = stmts
+            .iter()
+            .filter_map(|s| match s {
+                Statement::Import(imp) => Some((imp.path.as_str(), imp.alias.as_deref())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            imports,
+            [
+                ("lib/utils.ark", Some("u")),
+                ("std.math", Some("m")),
+                ("io", None)
+            ]
+        );
+        assert_eq!(stmts.len(), 4);
+    }
+
     #[test]
     fn test_parse_destructure() {
         let ast = parse_source("let (a, b) := get_pair()", "test.ark").expect("operation failed");
         if let ArkNode::Statement(Statement::Block(stmts)) = ast {
-            if let Statement::LetDestructure { names, value } = &stmts[0] {
+            if let Statement::LetDestructure { names, value, .. } = &stmts[0] {
                 assert_eq!(names, &["a", "b"]);
             }
         }
     }
 
+    #[test]
+    fn test_parse_typed_let() {
+        let ast = parse_source("let p: Point := { x: 1 }", "test.ark").expect("operation failed");
+        let ArkNode::Statement(Statement::Block(stmts)) = ast else {
+            panic!("expected block");
+        };
+        match &stmts[0] {
+            Statement::Let { name, ty, .. } => {
+                assert_eq!(name, "p");
+                assert_eq!(ty, &Some(ArkType::Struct("Point".to_string(), vec![])));
+            }
+            other => panic!("expected Let, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_struct_destructure() {
+        let ast = parse_source("let {x, y} := point", "test.ark").expect("operation failed");
+        let ArkNode::Statement(Statement::Block(stmts)) = ast else {
+            panic!("expected block");
+        };
+        match &stmts[0] {
+            Statement::LetDestructure {
+                names, by_field, ..
+            } => {
+                assert_eq!(names, &["x", "y"]);
+                assert!(*by_field);
+            }
+            other => panic!("expected LetDestructure, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_rest_destructure() {
+        let ast = parse_source("let [head, ...tail] := xs", "test.ark").expect("operation failed");
+        let ArkNode::Statement(Statement::Block(stmts)) = ast else {
+            panic!("expected block");
+        };
+        match &stmts[0] {
+            Statement::LetDestructure { names, rest, .. } => {
+                assert_eq!(names, &["head"]);
+                assert_eq!(rest.as_deref(), Some("tail"));
+            }
+            other => panic!("expected LetDestructure, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_comments_skipped() {
         let source = r#"
@@ -2149,6 +2753,69 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_export_let() {
+        let ast = parse_source("#[export]\nlet LIMIT := 42", "test.ark").expect("parse failed");
+        match ast {
+            ArkNode::Statement(Statement::Block(stmts)) => match &stmts[0] {
+                Statement::Export(inner) => assert!(
+                    matches!(**inner, Statement::Let { ref name, .. } if name == "LIMIT"),
+                    "{:?}",
+                    inner
+                ),
+                other => panic!("Expected Export, got {:?}", other),
+            },
+            other => panic!("Expected Block, got {:?}", other),
+        }
+        assert!(parse_source("#[export]\nprint(1)", "test.ark").is_err());
+    }
+
+    #[test]
+    fn test_parse_function_spans() {
+        let source = "x := 1\n\n  func double(n) {\n    return n * 2\n  }\n";
+        let span_of = |ast: ArkNode| match ast {
+            ArkNode::Statement(Statement::Block(stmts)) => match &stmts[1] {
+                Statement::Function(func) => func.body.span.clone(),
+                other => panic!("Expected Function, got {:?}", other),
+            },
+            other => panic!("Expected Block, got {:?}", other),
+        };
+
+        let span = span_of(parse_source_with_spans(source, "spans.ark").expect("parse failed"));
+        assert_eq!(
+            span,
+            Some(Span {
+                start_line: 3,
+                start_col: 3,
+                end_line: 5,
+                end_col: 3,
+                file: "spans.ark".to_string(),
+            })
+        );
+        // Plain parsing stays position-independent
+        assert_eq!(
+            span_of(parse_source(source, "spans.ark").expect("parse failed")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_typed_function_signature() {
+        let source = "func area(r: Float, n) => Float {\n    return r * r\n}";
+        let ast = parse_source(source, "test.ark").expect("parse failed");
+        match ast {
+            ArkNode::Statement(Statement::Block(stmts)) => match &stmts[0] {
+                Statement::Function(func) => {
+                    assert_eq!(func.inputs[0], ("r".to_string(), ArkType::Float));
+                    assert_eq!(func.inputs[1], ("n".to_string(), ArkType::Any));
+                    assert_eq!(func.output, ArkType::Float);
+                }
+                other => panic!("Expected Function, got {:?}", other),
+            },
+            other => panic!("Expected Block, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_parse_multiple_attributes() {
         let source = r#"
@@ -2197,4 +2864,14 @@ mod tests {
             panic!("Expected Block");
         }
     }
+
+    #[test]
+    fn test_parse_error_to_diagnostic() {
+        let err = parse_source("func f( {", "bad.ark").expect_err("should fail");
+        let diagnostic = err.to_diagnostic();
+        assert!(diagnostic.code.starts_with("E000"));
+        let span = diagnostic.span.expect("parse errors carry a position");
+        assert_eq!(span.file, "bad.ark");
+        assert_eq!(span.start_line, 1);
+    }
 }
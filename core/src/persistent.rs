@@ -687,9 +687,13 @@ pub fn format_value_adn(v: &Value) -> String {
         }
         Value::PVec(pv) => format!("{}", pv),
         Value::PMap(pm) => format!("{}", pm),
+        Value::BigInt(n) => n.to_string(),
+        Value::Decimal(d) => format!("{}d", d),
+        Value::Tensor(t) => format!("#tensor{}", t),
         Value::Function(_) => "#<fn>".to_string(),
         Value::NativeFunction(_) => "#<native-fn>".to_string(),
         Value::Buffer(b) => format!("#buf[{} bytes]", b.len()),
+        Value::BufferView { len, .. } => format!("#buf[{} bytes]", len),
         Value::LinearObject { typename, id, .. } => format!("#<linear:{} {}>", typename, id),
         Value::Return(v) => format_value_adn(v),
         Value::EnumValue {
@@ -0,0 +1,219 @@
+/*
+ * Copyright (c) 2026 Mohamad Al-Zawahreh (dba Sovereign Systems).
+ *
+ * This file is part of the Ark Sovereign Compiler.
+ *
+ * LICENSE: DUAL-LICENSED (AGPLv3 or COMMERCIAL).
+ *
+ * 1. OPEN SOURCE: You may use this file under the terms of the GNU Affero
+ * General Public License v3.0. If you link to this code, your ENTIRE
+ * application must be open-sourced under AGPLv3.
+ *
+ * 2. COMMERCIAL: For proprietary use, you must obtain a Commercial License
+ * from Sovereign Systems.
+ *
+ * PATENT NOTICE: Protected by US Patent App #63/935,467.
+ * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
+ */
+
+//! Per-function profiles of VM runs (`VmConfig::profile`).
+//!
+//! The VM tells the profile when an Ark function is entered and left and
+//! when a value is allocated, so the numbers are exact rather than
+//! sampled. Time spent in intrinsics counts towards the function that
+//! called them. A function's inclusive time is counted once however
+//! deeply it recurses; its exclusive time leaves out the functions it
+//! called.
+
+use crate::runtime::Value;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// What one function did over a run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FunctionStats {
+    pub calls: u64,
+    pub inclusive: Duration,
+    pub exclusive: Duration,
+    /// Values allocated while it was innermost: every string, list,
+    /// struct, buffer or enum with fields it built or copied
+    pub allocations: u64,
+}
+
+/// A call still running.
+#[derive(Debug)]
+struct Open {
+    function: String,
+    /// Names from the outermost call down to this one, `;`-separated
+    path: String,
+    started: Instant,
+    /// Time spent in the calls it made
+    children: Duration,
+}
+
+#[derive(Debug, Default)]
+pub struct Profile {
+    functions: HashMap<String, FunctionStats>,
+    /// Exclusive time per call path
+    stacks: HashMap<String, Duration>,
+    open: Vec<Open>,
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether no call is running.
+    pub fn is_idle(&self) -> bool {
+        self.open.is_empty()
+    }
+
+    /// `function` was called.
+    pub fn enter(&mut self, function: &str) {
+        let path = match self.open.last() {
+            Some(caller) => format!("{};{}", caller.path, function),
+            None => function.to_string(),
+        };
+        self.functions
+            .entry(function.to_string())
+            .or_default()
+            .calls += 1;
+        self.open.push(Open {
+            function: function.to_string(),
+            path,
+            started: Instant::now(),
+            children: Duration::ZERO,
+        });
+    }
+
+    /// The innermost running call returned, or was unwound by an error.
+    pub fn exit(&mut self) {
+        let Some(call) = self.open.pop() else {
+            return;
+        };
+        let elapsed = call.started.elapsed();
+        let exclusive = elapsed.saturating_sub(call.children);
+        if let Some(caller) = self.open.last_mut() {
+            caller.children += elapsed;
+        }
+        let recursing = self.open.iter().any(|o| o.function == call.function);
+        let stats = self.functions.entry(call.function).or_default();
+        stats.exclusive += exclusive;
+        if !recursing {
+            stats.inclusive += elapsed;
+        }
+        *self.stacks.entry(call.path).or_default() += exclusive;
+    }
+
+    /// Leave every running call, as when the run ends.
+    pub fn exit_all(&mut self) {
+        while !self.open.is_empty() {
+            self.exit();
+        }
+    }
+
+    /// Count `value` against the innermost running call if making it
+    /// allocated.
+    pub fn record_value(&mut self, value: &Value) {
+        let allocates = match value {
+            Value::String(_) | Value::List(_) | Value::Struct(_) | Value::Buffer(_) => true,
+            Value::EnumValue { fields, .. } => !fields.is_empty(),
+            _ => false,
+        };
+        let Some(call) = self.open.last().filter(|_| allocates) else {
+            return;
+        };
+        if let Some(stats) = self.functions.get_mut(&call.function) {
+            stats.allocations += 1;
+        }
+    }
+
+    /// The functions called, most exclusive time first.
+    pub fn functions(&self) -> Vec<(&str, &FunctionStats)> {
+        let mut functions: Vec<(&str, &FunctionStats)> = self
+            .functions
+            .iter()
+            .map(|(name, stats)| (name.as_str(), stats))
+            .collect();
+        functions.sort_by(|a, b| b.1.exclusive.cmp(&a.1.exclusive).then(a.0.cmp(b.0)));
+        functions
+    }
+
+    /// The profile in the folded-stack format flamegraph tools read: one
+    /// line per call path, outermost function first, weighted by its
+    /// exclusive time in microseconds.
+    pub fn folded(&self) -> String {
+        let mut lines: Vec<String> = self
+            .stacks
+            .iter()
+            .map(|(path, time)| format!("{} {}", path, time.as_micros()))
+            .collect();
+        lines.sort();
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    /// A table of calls, times and allocations per function.
+    pub fn summary(&self) -> String {
+        let functions = self.functions();
+        let width = functions
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(std::iter::once("function".len()))
+            .max()
+            .unwrap_or(0);
+        let mut out = format!(
+            "{:<width$} {:>10} {:>12} {:>12} {:>10}\n",
+            "function", "calls", "inclusive", "exclusive", "allocs"
+        );
+        for (name, stats) in functions {
+            out.push_str(&format!(
+                "{:<width$} {:>10} {:>12} {:>12} {:>10}\n",
+                name,
+                stats.calls,
+                crate::bench::format_ns(stats.inclusive.as_nanos() as f64),
+                crate::bench::format_ns(stats.exclusive.as_nanos() as f64),
+                stats.allocations
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recursion_counts_inclusive_time_once() {
+        let mut profile = Profile::new();
+        profile.enter("<main>");
+        profile.enter("fact");
+        profile.enter("fact");
+        std::thread::sleep(Duration::from_millis(5));
+        profile.record_value(&Value::String("x".to_string()));
+        profile.record_value(&Value::Integer(1));
+        profile.exit();
+        profile.exit();
+        profile.exit_all();
+        assert!(profile.is_idle());
+
+        let functions: HashMap<&str, &FunctionStats> = profile.functions().into_iter().collect();
+        let fact = functions["fact"];
+        assert_eq!(fact.calls, 2);
+        assert_eq!(fact.allocations, 1);
+        assert!(fact.inclusive >= Duration::from_millis(5));
+        // Only the outer call's time, not the inner one's again
+        assert!(fact.inclusive <= functions["<main>"].inclusive);
+        assert!(fact.exclusive <= fact.inclusive);
+        assert_eq!(profile.functions()[0].0, "fact");
+
+        let folded = profile.folded();
+        let paths: Vec<&str> = folded
+            .lines()
+            .map(|l| l.rsplit_once(' ').expect("weighted line").0)
+            .collect();
+        assert_eq!(paths, ["<main>", "<main>;fact", "<main>;fact;fact"]);
+        assert!(profile.summary().starts_with("function"));
+    }
+}
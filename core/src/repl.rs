@@ -16,61 +16,170 @@
  * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
  */
 
-use crate::ast::{ArkNode, Expression};
-use crate::eval::Interpreter;
-use crate::runtime::Scope;
-use std::io::{self, Write};
+//! Interactive sessions on the bytecode VM (`ark repl`).
+//!
+//! Every input is compiled on its own and run on a fresh VM that is handed
+//! the session's global scope, so variables and functions defined by one
+//! input are there for the next. The session also keeps the source of each
+//! input that ran, which is what `:type` and `:wasm` see besides the
+//! expression they are given.
 
-pub fn start() {
-    println!("Ark-0 (Zheng) Sovereign Shell v0.1");
-    println!("Type 'ADD x y' to test intrinsics, or 'exit' to quit.");
+use crate::checker::LinearChecker;
+use crate::compiler::{self, CompileError, Compiler};
+use crate::parser::{self, Lexer, ParseError, TokenKind};
+use crate::runtime::{RuntimeError, Scope, Value};
+use crate::vm::{ArkError, VM};
+use crate::wasm_codegen::{WasmCodegen, WasmCompileError};
+use thiserror::Error;
 
-    let mut scope = Scope::new();
+const FILE: &str = "<repl>";
 
-    loop {
-        print!("> ");
-        io::stdout().flush().expect("stdout flush failed");
+/// Binding `:type` asks the checker about
+const TYPE_PROBE: &str = "__repl_type";
 
-        let mut input = String::new();
-        if io::stdin().read_line(&mut input).is_err() {
-            println!("Error reading input");
-            continue;
-        }
+#[derive(Error, Debug)]
+pub enum ReplError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error(transparent)]
+    Compile(#[from] CompileError),
+    #[error(transparent)]
+    Wasm(#[from] WasmCompileError),
+    #[error("VM error: {0}")]
+    Vm(#[from] RuntimeError),
+    #[error("Runtime error: {0}")]
+    Runtime(#[from] ArkError),
+    #[error("Cannot print the module: {0}")]
+    Print(String),
+}
 
-        let input = input.trim();
-        if input == "exit" {
-            break;
-        }
+#[derive(Default)]
+pub struct Session {
+    /// Global scope left by the last input; `None` before the first
+    globals: Option<Scope<'static>>,
+    /// Source of every input that ran, in order
+    source: String,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `input` in the session and return the value it produced.
+    /// Definitions made before a runtime error are kept; the input is
+    /// not, as far as `:type` and `:wasm` are concerned.
+    pub fn eval(&mut self, input: &str) -> Result<Value, ReplError> {
+        let ast = parser::parse_source(input, FILE)?;
+        let mut compiler = Compiler::new();
+        compiler.compile_safe(&compiler::optimize(ast, 2))?;
 
-        if input.is_empty() {
-            continue;
+        let mut vm: VM<'static> = VM::new(compiler.chunk, "REPL", 0)?;
+        if let Some(globals) = self.globals.take() {
+            vm.scopes[0] = globals;
         }
+        let result = vm.run();
+        self.globals = Some(std::mem::take(&mut vm.scopes[0]));
+
+        let value = result?;
+        self.source.push_str(input);
+        self.source.push('\n');
+        Ok(value)
+    }
+
+    /// The type the checker gives `expr`, given everything defined so far.
+    pub fn type_of(&self, expr: &str) -> Result<String, ReplError> {
+        parser::parse_source(expr, FILE)?;
+        let program = format!("{}{} := {}\n", self.source, TYPE_PROBE, expr);
+        let ast = parser::parse_source(&program, FILE)?;
+        let ty = LinearChecker::binding_types(&ast)
+            .into_iter()
+            .rfind(|b| b.name == TYPE_PROBE && b.function.is_none())
+            .map(|b| b.ty.to_string());
+        Ok(ty.unwrap_or_else(|| "Unknown".to_string()))
+    }
+
+    /// The WebAssembly text of `input` compiled after everything defined
+    /// so far.
+    pub fn wasm(&self, input: &str) -> Result<String, ReplError> {
+        parser::parse_source(input, FILE)?;
+        let program = format!("{}{}\n", self.source, input);
+        let ast = parser::parse_source(&program, FILE)?;
+        let bytes = WasmCodegen::compile_to_bytes(&ast)?;
+        wasmprinter::print_bytes(&bytes).map_err(|e| ReplError::Print(e.to_string()))
+    }
+}
+
+/// The AST of `input`, as JSON.
+pub fn ast(input: &str) -> Result<String, ReplError> {
+    let ast = parser::parse_source(input, FILE)?;
+    serde_json::to_string_pretty(&ast).map_err(|e| ReplError::Print(e.to_string()))
+}
 
-        // Extremely naive 'parser' for Phase 2 prototype
-        // Support: ADD 5 10
-        let parts: Vec<&str> = input.split_whitespace().collect();
-        let expr = if parts[0] == "ADD" && parts.len() == 3 {
-            Expression::Call {
-                function_hash: "intrinsic_add".to_string(),
-                args: vec![
-                    Expression::Literal(parts[1].to_string()),
-                    Expression::Literal(parts[2].to_string()),
-                ],
-            }
-        } else if parts[0] == "PRINT" && parts.len() > 1 {
-            Expression::Call {
-                function_hash: "intrinsic_print".to_string(),
-                args: vec![Expression::Literal(parts[1].to_string())],
-            }
-        } else {
-            // Default: Literal evaluation
-            Expression::Literal(input.to_string())
-        };
-
-        let mut interpreter = Interpreter::new();
-        match interpreter.eval(&ArkNode::Expression(expr), &mut scope) {
-            Ok(val) => println!("= {:?}", val),
-            Err(e) => println!("Error: {}", e),
+/// Whether `input` stops partway: inside a string or with a bracket still
+/// open. The REPL reads more lines before running such input.
+pub fn is_incomplete(input: &str) -> bool {
+    let tokens = match Lexer::new(input).tokenize() {
+        Ok(tokens) => tokens,
+        Err(ParseError::UnexpectedEof) => return true,
+        Err(_) => return false,
+    };
+    let mut depth = 0i32;
+    for token in &tokens {
+        match token.kind {
+            TokenKind::LBrace | TokenKind::LParen | TokenKind::LBracket => depth += 1,
+            TokenKind::RBrace | TokenKind::RParen | TokenKind::RBracket => depth -= 1,
+            _ => {}
         }
     }
+    depth > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_definitions_persist_across_inputs() {
+        let mut session = Session::new();
+        session.eval("x := 20").expect("eval failed");
+        session
+            .eval("func double(n: Int) => Int {\n    return n * 2\n}")
+            .expect("eval failed");
+        assert_eq!(
+            session.eval("double(x) + 2").expect("eval failed"),
+            Value::Integer(42)
+        );
+
+        assert!(matches!(
+            session.eval("missing + 1"),
+            Err(ReplError::Runtime(_))
+        ));
+        assert!(matches!(session.eval("x :="), Err(ReplError::Parse(_))));
+        assert_eq!(session.eval("x").expect("eval failed"), Value::Integer(20));
+    }
+
+    #[test]
+    fn test_inspection_commands() {
+        let mut session = Session::new();
+        session
+            .eval("func double(n: Int) => Int {\n    return n * 2\n}")
+            .expect("eval failed");
+        assert_eq!(session.type_of("double(4)").expect("type failed"), "Int");
+        assert_eq!(session.type_of("\"hi\"").expect("type failed"), "Str");
+
+        assert!(ast("1 + 2").expect("ast failed").contains("Expression"));
+        let wat = session.wasm("double(4)").expect("wasm failed");
+        assert!(wat.starts_with("(module"), "{}", wat);
+    }
+
+    #[test]
+    fn test_incomplete_input_waits_for_more() {
+        assert!(is_incomplete("func f(n) {"));
+        assert!(is_incomplete("xs := [1,\n2"));
+        assert!(is_incomplete("s := \"unterminated"));
+        assert!(!is_incomplete("func f(n) {\n    return n\n}"));
+        assert!(!is_incomplete("x := 1"));
+        assert!(!is_incomplete("}"));
+    }
 }
@@ -16,15 +16,20 @@
  * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
  */
 
+use crate::bigint::BigInt;
 use crate::bytecode::Chunk;
+use crate::decimal::Decimal;
 use crate::persistent::{PMap, PVec};
+use crate::tensor::Tensor;
 
 use lazy_static::lazy_static;
 use std::collections::HashMap;
+use std::fmt;
+use std::ops::ControlFlow;
 use std::sync::Arc;
 use std::sync::{
     atomic::{AtomicBool, AtomicUsize, Ordering},
-    Mutex,
+    Mutex, RwLock,
 };
 use thiserror::Error;
 
@@ -260,11 +265,25 @@ pub enum Value {
     NativeFunction(NativeFn),
     List(Vec<Value>),
     Buffer(Vec<u8>),
+    /// Zero-copy window `[offset, offset + len)` over a shared byte buffer.
+    /// Views made from the same buffer alias: a write through one is seen by
+    /// every other view of that backing.
+    BufferView {
+        backing: Arc<RwLock<Vec<u8>>>,
+        offset: usize,
+        len: usize,
+    },
     Struct(HashMap<String, Value>),
     /// Persistent (immutable) vector with structural sharing
     PVec(PVec),
     /// Persistent (immutable) map with structural sharing
     PMap(PMap),
+    /// Arbitrary-precision integer (`sys.bigint.*`)
+    BigInt(BigInt),
+    /// Fixed-point decimal (`12.50d`, `dec.*`)
+    Decimal(Decimal),
+    /// N-dimensional integer tensor (`tensor.*`)
+    Tensor(Tensor),
     /// Control Flow: Return value wrapper
     Return(Box<Value>),
     /// Enum variant with optional fields
@@ -289,9 +308,15 @@ impl PartialEq for Value {
             (Value::NativeFunction(a), Value::NativeFunction(b)) => *a as usize == *b as usize,
             (Value::List(a), Value::List(b)) => a == b,
             (Value::Buffer(a), Value::Buffer(b)) => a == b,
+            (Value::BufferView { .. }, Value::BufferView { .. }) => {
+                self.view_bytes() == other.view_bytes()
+            }
             (Value::Struct(a), Value::Struct(b)) => a == b,
             (Value::PVec(a), Value::PVec(b)) => a == b,
             (Value::PMap(a), Value::PMap(b)) => a == b,
+            (Value::BigInt(a), Value::BigInt(b)) => a == b,
+            (Value::Decimal(a), Value::Decimal(b)) => a == b,
+            (Value::Tensor(a), Value::Tensor(b)) => a == b,
             (Value::Return(a), Value::Return(b)) => a == b,
             (
                 Value::EnumValue {
@@ -311,6 +336,93 @@ impl PartialEq for Value {
 }
 
 impl Value {
+    /// Truth value of `self` used as a condition (`if`, `while`, `and`, `or`,
+    /// `not`). This is the one truthiness rule for every backend: a Boolean is
+    /// itself and an Integer is true when nonzero. Anything else is a type
+    /// error rather than being coerced, since strings and heap values have no
+    /// meaning that the WASM backend (which tests the raw i64) could share.
+    pub fn as_condition(&self) -> Result<bool, RuntimeError> {
+        match self {
+            Value::Boolean(b) => Ok(*b),
+            Value::Integer(n) => Ok(*n != 0),
+            other => Err(RuntimeError::TypeMismatch(
+                "Boolean".to_string(),
+                other.clone(),
+            )),
+        }
+    }
+
+    /// Copy of the bytes a `BufferView` covers; `None` for any other value.
+    pub fn view_bytes(&self) -> Option<Vec<u8>> {
+        match self {
+            Value::BufferView {
+                backing,
+                offset,
+                len,
+            } => {
+                let bytes = backing.read().unwrap_or_else(|e| e.into_inner());
+                Some(bytes[*offset..*offset + *len].to_vec())
+            }
+            _ => None,
+        }
+    }
+
+    /// `Ok(value)` of the built-in `Result` enum.
+    pub fn ok(value: Value) -> Value {
+        Value::builtin_variant("Result", "Ok", vec![value])
+    }
+
+    /// `Err(error)` of the built-in `Result` enum.
+    pub fn err(error: Value) -> Value {
+        Value::builtin_variant("Result", "Err", vec![error])
+    }
+
+    /// `Some(value)` of the built-in `Option` enum.
+    pub fn some(value: Value) -> Value {
+        Value::builtin_variant("Option", "Some", vec![value])
+    }
+
+    /// `None` of the built-in `Option` enum.
+    pub fn none() -> Value {
+        Value::builtin_variant("Option", "None", Vec::new())
+    }
+
+    fn builtin_variant(enum_name: &str, variant: &str, fields: Vec<Value>) -> Value {
+        Value::EnumValue {
+            enum_name: enum_name.to_string(),
+            variant: variant.to_string(),
+            fields,
+        }
+    }
+
+    /// What `self?` does: `Continue` with the payload of an `Ok` or `Some`,
+    /// or `Break` with an `Err` or `None` for the enclosing function to
+    /// return. Anything that is not a `Result` or `Option` is a type error.
+    pub fn propagate(self) -> Result<ControlFlow<Value, Value>, RuntimeError> {
+        let succeeded = match &self {
+            Value::EnumValue {
+                enum_name,
+                variant,
+                fields,
+            } => match (enum_name.as_str(), variant.as_str()) {
+                ("Result", "Ok") | ("Option", "Some") if fields.len() == 1 => Some(true),
+                ("Result", "Err") | ("Option", "None") => Some(false),
+                _ => None,
+            },
+            _ => None,
+        };
+        match (succeeded, self) {
+            (Some(true), Value::EnumValue { mut fields, .. }) => {
+                Ok(ControlFlow::Continue(fields.remove(0)))
+            }
+            (Some(_), failure) => Ok(ControlFlow::Break(failure)),
+            (None, other) => Err(RuntimeError::TypeMismatch(
+                "Result or Option".to_string(),
+                other,
+            )),
+        }
+    }
+
     pub fn is_linear(&self) -> bool {
         match self {
             Value::Integer(_)
@@ -320,10 +432,15 @@ impl Value {
             | Value::NativeFunction(_)
             | Value::PVec(_)
             | Value::PMap(_)
+            | Value::BigInt(_)
+            | Value::Decimal(_)
+            | Value::Tensor(_)
             | Value::String(_) => false,
-            Value::List(_) | Value::LinearObject { .. } | Value::Buffer(_) | Value::Struct(_) => {
-                true
-            }
+            Value::List(_)
+            | Value::LinearObject { .. }
+            | Value::Buffer(_)
+            | Value::BufferView { .. }
+            | Value::Struct(_) => true,
             Value::Return(val) => val.is_linear(), // Recursive check
             Value::EnumValue { .. } => false,
         }
@@ -367,6 +484,11 @@ impl<'a> Scope<'a> {
         }
     }
 
+    /// Names bound in this scope itself, not in its parents.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.variables.keys().map(String::as_str)
+    }
+
     pub fn get_or_move(&mut self, name: &str) -> Option<Value> {
         // 1. Try Local
         if let Some(v) = self.variables.get(name) {
@@ -397,6 +519,33 @@ impl<'a> Scope<'a> {
     }
 }
 
+/// Canonical text form of a float, shared by printing and JSON output so both
+/// stay reproducible across platforms.
+///
+/// Digits are the shortest representation that round-trips; integral values
+/// keep a trailing `.0`; magnitudes outside `[1e-5, 1e16)` use exponent form
+/// (`1e300`); both zeros print as `0.0`.
+pub fn format_float(x: f64) -> String {
+    if x.is_nan() {
+        return "NaN".to_string();
+    }
+    if x.is_infinite() {
+        return if x > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+    if x == 0.0 {
+        return "0.0".to_string();
+    }
+    if !(1e-5..1e16).contains(&x.abs()) {
+        return format!("{:e}", x);
+    }
+    let text = x.to_string();
+    if text.contains('.') {
+        text
+    } else {
+        text + ".0"
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum RuntimeError {
     #[error("Variable not found: {0}")]
@@ -415,15 +564,235 @@ pub enum RuntimeError {
     RecursionLimitExceeded,
     #[error("System Lockout: Untrusted Code Hash")]
     UntrustedCode,
+    #[error("Non-deterministic intrinsic in deterministic mode: {0}")]
+    NonDeterministic(String),
+    #[error("Out of gas")]
+    OutOfGas,
+    #[error("Resource limit exceeded: {resource} (limit {limit})")]
+    LimitExceeded { resource: Resource, limit: usize },
     #[error("Allocation failed: {0}")]
     AllocationError(String),
     #[error("Resource error: {0}")]
     ResourceError(String),
+    #[error("{0}: {1}")]
+    NetworkError(NetworkErrorKind, String),
+    #[error("Assertion failed: {0}")]
+    AssertionFailed(String),
+    #[error("I/O error: {0}")]
+    IoError(String),
+    #[error("Index {index} out of bounds for length {len}")]
+    IndexOutOfBounds { index: i64, len: usize },
+    #[error("Division by zero")]
+    DivisionByZero,
+}
+
+impl RuntimeError {
+    /// Stable name of the error's kind, as seen by an Ark `catch` block.
+    pub fn code(&self) -> &'static str {
+        match self {
+            RuntimeError::VariableNotFound(_) => "VariableNotFound",
+            RuntimeError::TypeMismatch(_, _) => "TypeError",
+            RuntimeError::NotExecutable => "NotExecutable",
+            RuntimeError::FunctionNotFound(_) => "FunctionNotFound",
+            RuntimeError::StackUnderflow => "StackUnderflow",
+            RuntimeError::InvalidOperation(_) => "InvalidOperation",
+            RuntimeError::RecursionLimitExceeded => "RecursionLimitExceeded",
+            RuntimeError::UntrustedCode => "UntrustedCode",
+            RuntimeError::NonDeterministic(_) => "NonDeterministic",
+            RuntimeError::OutOfGas => "OutOfGas",
+            RuntimeError::LimitExceeded { .. } => "LimitExceeded",
+            RuntimeError::AllocationError(_) => "AllocationError",
+            RuntimeError::ResourceError(_) => "ResourceError",
+            RuntimeError::NetworkError(_, _) => "NetworkError",
+            RuntimeError::AssertionFailed(_) => "AssertionFailed",
+            RuntimeError::IoError(_) => "IoError",
+            RuntimeError::IndexOutOfBounds { .. } => "IndexOutOfBounds",
+            RuntimeError::DivisionByZero => "DivisionByZero",
+        }
+    }
+
+    /// Whether Ark code may recover from the error with `try`/`catch`.
+    /// Security lockouts, running out of gas and exceeding a resource limit
+    /// always abort the program.
+    pub fn is_catchable(&self) -> bool {
+        !matches!(
+            self,
+            RuntimeError::RecursionLimitExceeded
+                | RuntimeError::UntrustedCode
+                | RuntimeError::NonDeterministic(_)
+                | RuntimeError::OutOfGas
+                | RuntimeError::LimitExceeded { .. }
+        )
+    }
+
+    /// The value a `catch` block binds: a struct with the error's `code`
+    /// and `message`, plus `expected`/`found` for type errors,
+    /// `index`/`len` for bad indices, `kind` for network errors and
+    /// `resource`/`limit` for exceeded limits.
+    pub fn to_value(&self) -> Value {
+        let mut fields = HashMap::new();
+        match self {
+            RuntimeError::TypeMismatch(expected, found) => {
+                fields.insert("expected".to_string(), Value::String(expected.clone()));
+                fields.insert("found".to_string(), found.clone());
+            }
+            RuntimeError::IndexOutOfBounds { index, len } => {
+                fields.insert("index".to_string(), Value::Integer(*index));
+                fields.insert("len".to_string(), Value::Integer(*len as i64));
+            }
+            RuntimeError::NetworkError(kind, _) => {
+                fields.insert("kind".to_string(), Value::String(kind.to_string()));
+            }
+            RuntimeError::LimitExceeded { resource, limit } => {
+                fields.insert("resource".to_string(), Value::String(resource.to_string()));
+                fields.insert("limit".to_string(), Value::Integer(*limit as i64));
+            }
+            _ => {}
+        }
+        fields.insert("code".to_string(), Value::String(self.code().to_string()));
+        fields.insert("message".to_string(), Value::String(self.to_string()));
+        Value::Struct(fields)
+    }
+}
+
+/// Cause of a failed network operation, so Ark code can tell a bad hostname
+/// from a refused port or an expired certificate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkErrorKind {
+    DnsError,
+    TlsError,
+    Timeout,
+    ConnectionRefused,
+    InvalidUrl,
+    Other,
+}
+
+impl fmt::Display for NetworkErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            NetworkErrorKind::DnsError => "DnsError",
+            NetworkErrorKind::TlsError => "TlsError",
+            NetworkErrorKind::Timeout => "Timeout",
+            NetworkErrorKind::ConnectionRefused => "ConnectionRefused",
+            NetworkErrorKind::InvalidUrl => "InvalidUrl",
+            NetworkErrorKind::Other => "NetworkError",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Something a program can run out of under [`ResourceLimits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    CallDepth,
+    Values,
+    Allocations,
+    BufferBytes,
+    Sockets,
+    Threads,
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Resource::CallDepth => "call depth",
+            Resource::Values => "values in one collection",
+            Resource::Allocations => "values allocated over the run",
+            Resource::BufferBytes => "buffer bytes",
+            Resource::Sockets => "open sockets",
+            Resource::Threads => "open threads",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Bounds on what one program may hold at once. `None` leaves a resource
+/// unbounded, which is the default for all of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// Function calls in progress at once
+    pub max_call_depth: Option<usize>,
+    /// Elements in one list, struct, map or enum payload
+    pub max_values: Option<usize>,
+    /// Elements the program may allocate over the whole run, in all the
+    /// lists, structs, maps and enum payloads it makes, nested VMs and
+    /// spawned threads included. A budget, not a bound on live memory:
+    /// dropping a value gives nothing back.
+    pub max_allocations: Option<usize>,
+    /// Bytes in one buffer
+    pub max_buffer_bytes: Option<usize>,
+    /// Sockets, listeners and TLS identities open at once
+    pub max_sockets: Option<usize>,
+    /// Threads spawned and not yet joined
+    pub max_threads: Option<usize>,
+}
+
+impl ResourceLimits {
+    pub fn limit(&self, resource: Resource) -> Option<usize> {
+        match resource {
+            Resource::CallDepth => self.max_call_depth,
+            Resource::Values => self.max_values,
+            Resource::Allocations => self.max_allocations,
+            Resource::BufferBytes => self.max_buffer_bytes,
+            Resource::Sockets => self.max_sockets,
+            Resource::Threads => self.max_threads,
+        }
+    }
+
+    /// Fail with [`RuntimeError::LimitExceeded`] if holding `amount` of
+    /// `resource` would go over its limit.
+    pub fn check(&self, resource: Resource, amount: usize) -> Result<(), RuntimeError> {
+        match self.limit(resource) {
+            Some(limit) if amount > limit => Err(RuntimeError::LimitExceeded { resource, limit }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the size of a value about to be held against `max_values`
+    /// and `max_buffer_bytes`. Only the value itself is counted, not what
+    /// it contains, so the check costs the same however large it is.
+    pub fn check_value(&self, value: &Value) -> Result<(), RuntimeError> {
+        match value {
+            Value::Buffer(bytes) => self.check(Resource::BufferBytes, bytes.len()),
+            Value::BufferView { len, .. } => self.check(Resource::BufferBytes, *len),
+            _ => self.check(Resource::Values, elements(value)),
+        }
+    }
+
+    /// Spend the elements of a value the program has just made from the
+    /// allocation budget, counted in `allocated`, and fail once the run has
+    /// allocated more than `max_allocations` in all. Many small
+    /// collections add up where [`check_value`](Self::check_value) sees
+    /// each on its own.
+    pub fn check_allocation(
+        &self,
+        value: &Value,
+        allocated: &AtomicUsize,
+    ) -> Result<(), RuntimeError> {
+        if self.max_allocations.is_none() {
+            return Ok(());
+        }
+        let values = elements(value);
+        let total = allocated.fetch_add(values, Ordering::Relaxed) + values;
+        self.check(Resource::Allocations, total)
+    }
+}
+
+/// Elements held directly by a collection, 0 for anything else.
+fn elements(value: &Value) -> usize {
+    match value {
+        Value::List(items) => items.len(),
+        Value::Struct(fields) => fields.len(),
+        Value::PVec(items) => items.len(),
+        Value::PMap(entries) => entries.len(),
+        Value::EnumValue { fields, .. } => fields.len(),
+        _ => 0,
+    }
 }
 
 impl From<std::io::Error> for RuntimeError {
     fn from(err: std::io::Error) -> Self {
-        RuntimeError::ResourceError(err.to_string())
+        RuntimeError::IoError(err.to_string())
     }
 }
 
@@ -529,6 +898,87 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_format_float_is_stable() {
+        assert_eq!(format_float(1.0), "1.0");
+        assert_eq!(format_float(1.5), "1.5");
+        assert_eq!(format_float(0.1), "0.1");
+        assert_eq!(format_float(-2.25), "-2.25");
+        assert_eq!(format_float(123456789.0), "123456789.0");
+        assert_eq!(format_float(1e300), "1e300");
+        assert_eq!(format_float(-1.5e-7), "-1.5e-7");
+        assert_eq!(format_float(-0.0), "0.0");
+        assert_eq!(format_float(0.0), "0.0");
+        assert_eq!(format_float(f64::NAN), "NaN");
+    }
+
+    #[test]
+    fn test_format_float_round_trips() {
+        for x in [0.1, 1.0 / 3.0, 2.5e-5, 9.999e15, 6.02214076e23] {
+            let parsed: f64 = format_float(x).parse().expect("parse failed");
+            assert_eq!(parsed, x);
+        }
+    }
+
+    #[test]
+    fn test_error_value_carries_code_and_details() {
+        let field = |value: &Value, name: &str| match value {
+            Value::Struct(fields) => fields.get(name).cloned(),
+            other => panic!("Expected a struct, got {:?}", other),
+        };
+        let err = RuntimeError::IndexOutOfBounds { index: 5, len: 2 };
+        let value = err.to_value();
+        assert_eq!(
+            field(&value, "code"),
+            Some(Value::String("IndexOutOfBounds".to_string()))
+        );
+        assert_eq!(
+            field(&value, "message"),
+            Some(Value::String(
+                "Index 5 out of bounds for length 2".to_string()
+            ))
+        );
+        assert_eq!(field(&value, "index"), Some(Value::Integer(5)));
+        assert_eq!(field(&value, "len"), Some(Value::Integer(2)));
+
+        let value =
+            RuntimeError::TypeMismatch("Integer".to_string(), Value::Boolean(true)).to_value();
+        assert_eq!(
+            field(&value, "code"),
+            Some(Value::String("TypeError".to_string()))
+        );
+        assert_eq!(field(&value, "found"), Some(Value::Boolean(true)));
+
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "missing.txt");
+        assert_eq!(RuntimeError::from(io).code(), "IoError");
+        assert!(!RuntimeError::UntrustedCode.is_catchable());
+    }
+
+    #[test]
+    fn test_propagate_unwraps_success_and_breaks_on_failure() {
+        assert_eq!(
+            Value::ok(Value::Integer(1)).propagate().ok(),
+            Some(ControlFlow::Continue(Value::Integer(1)))
+        );
+        assert_eq!(
+            Value::some(Value::Unit).propagate().ok(),
+            Some(ControlFlow::Continue(Value::Unit))
+        );
+        let failure = Value::err(Value::String("bad".to_string()));
+        assert_eq!(
+            failure.clone().propagate().ok(),
+            Some(ControlFlow::Break(failure))
+        );
+        assert_eq!(
+            Value::none().propagate().ok(),
+            Some(ControlFlow::Break(Value::none()))
+        );
+        assert!(matches!(
+            Value::Integer(3).propagate(),
+            Err(RuntimeError::TypeMismatch(_, Value::Integer(3)))
+        ));
+    }
+
     #[test]
     fn test_runtime_stats_counting() {
         // Reset
@@ -0,0 +1,420 @@
+/*
+ * Copyright (c) 2026 Mohamad Al-Zawahreh (dba Sovereign Systems).
+ *
+ * This file is part of the Ark Sovereign Compiler.
+ *
+ * LICENSE: DUAL-LICENSED (AGPLv3 or COMMERCIAL).
+ *
+ * 1. OPEN SOURCE: You may use this file under the terms of the GNU Affero
+ * General Public License v3.0. If you link to this code, your ENTIRE
+ * application must be open-sourced under AGPLv3.
+ *
+ * 2. COMMERCIAL: For proprietary use, you must obtain a Commercial License
+ * from Sovereign Systems.
+ *
+ * PATENT NOTICE: Protected by US Patent App #63/935,467.
+ * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
+ */
+
+//! N-dimensional integer tensors for the `tensor.*` intrinsics.
+//!
+//! A [`Tensor`] is a view of a shared, immutable buffer: a shape, the
+//! stride of each axis in elements, and the offset of its first element.
+//! Slicing only narrows the view and reshaping a contiguous view only
+//! changes its shape, so neither copies. Element-wise arithmetic follows
+//! NumPy's broadcasting rules: shapes are aligned from their last axis and
+//! each pair of dimensions must be equal or contain a 1, which is
+//! stretched to match. Arithmetic wraps on overflow, as `math.matmul` does.
+
+use std::fmt;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Debug, Clone)]
+pub struct Tensor {
+    data: Arc<[i64]>,
+    shape: Vec<usize>,
+    strides: Vec<usize>,
+    offset: usize,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum TensorError {
+    #[error("shape {shape:?} does not fit {elements} elements")]
+    Shape { shape: Vec<usize>, elements: usize },
+    #[error("invalid shape {0:?}")]
+    InvalidShape(Vec<i64>),
+    #[error("shapes {0:?} and {1:?} do not broadcast")]
+    Broadcast(Vec<usize>, Vec<usize>),
+    #[error("axis {axis} is out of range for {rank} dimensions")]
+    Axis { axis: i64, rank: usize },
+    #[error("slice {start}..{end} is out of range for a dimension of {len}")]
+    Slice { start: i64, end: i64, len: usize },
+}
+
+impl Tensor {
+    /// `data` in row-major order, laid out as `shape`.
+    pub fn new(data: Vec<i64>, shape: Vec<usize>) -> Result<Self, TensorError> {
+        let fits = shape
+            .iter()
+            .try_fold(1usize, |n, &dim| n.checked_mul(dim))
+            .is_some_and(|n| n == data.len());
+        if !fits {
+            return Err(TensorError::Shape {
+                shape,
+                elements: data.len(),
+            });
+        }
+        let strides = row_major_strides(&shape);
+        Ok(Self {
+            data: data.into(),
+            shape,
+            strides,
+            offset: 0,
+        })
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    /// Elements to step over for one step along each axis.
+    pub fn strides(&self) -> &[usize] {
+        &self.strides
+    }
+
+    pub fn len(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The elements in row-major order.
+    pub fn to_vec(&self) -> Vec<i64> {
+        offsets(&self.shape, &self.strides, self.offset)
+            .into_iter()
+            .map(|i| self.data[i])
+            .collect()
+    }
+
+    /// The element at `index`, one coordinate per axis.
+    pub fn get(&self, index: &[usize]) -> Option<i64> {
+        if index.len() != self.shape.len() || index.iter().zip(&self.shape).any(|(i, n)| i >= n) {
+            return None;
+        }
+        let at = index
+            .iter()
+            .zip(&self.strides)
+            .fold(self.offset, |at, (i, stride)| at + i * stride);
+        Some(self.data[at])
+    }
+
+    /// The same elements laid out as `shape`, where one dimension may be
+    /// -1 to stand for whatever the others leave. Shares the buffer unless
+    /// this is a non-contiguous slice, which is copied first.
+    pub fn reshape(&self, shape: &[i64]) -> Result<Tensor, TensorError> {
+        let invalid = || TensorError::InvalidShape(shape.to_vec());
+        let mut inferred = None;
+        let mut known = 1usize;
+        let mut dims = Vec::with_capacity(shape.len());
+        for (axis, &dim) in shape.iter().enumerate() {
+            if dim == -1 && inferred.is_none() {
+                inferred = Some(axis);
+                dims.push(0);
+                continue;
+            }
+            let dim = usize::try_from(dim).map_err(|_| invalid())?;
+            known = known.checked_mul(dim).ok_or_else(invalid)?;
+            dims.push(dim);
+        }
+        let len = self.len();
+        if let Some(axis) = inferred {
+            if known == 0 || !len.is_multiple_of(known) {
+                return Err(invalid());
+            }
+            dims[axis] = len / known;
+        } else if known != len {
+            return Err(TensorError::Shape {
+                shape: dims,
+                elements: len,
+            });
+        }
+        if !self.is_contiguous() {
+            return Tensor::new(self.to_vec(), dims);
+        }
+        Ok(Self {
+            data: Arc::clone(&self.data),
+            strides: row_major_strides(&dims),
+            shape: dims,
+            offset: self.offset,
+        })
+    }
+
+    /// The view of indices `start..end` along `axis`. Negative axes and
+    /// bounds count back from the end.
+    pub fn slice(&self, axis: i64, start: i64, end: i64) -> Result<Tensor, TensorError> {
+        let axis = self.axis(axis)?;
+        let len = self.shape[axis];
+        let from_end = |i: i64| if i < 0 { i + len as i64 } else { i };
+        let (first, last) = (from_end(start), from_end(end));
+        if first < 0 || first > last || last > len as i64 {
+            return Err(TensorError::Slice { start, end, len });
+        }
+        let mut shape = self.shape.clone();
+        shape[axis] = (last - first) as usize;
+        Ok(Self {
+            data: Arc::clone(&self.data),
+            shape,
+            strides: self.strides.clone(),
+            offset: self.offset + first as usize * self.strides[axis],
+        })
+    }
+
+    /// `op` applied to each pair of elements of `self` and `other` once
+    /// both are broadcast to a common shape.
+    pub fn zip_with(&self, other: &Tensor, op: fn(i64, i64) -> i64) -> Result<Tensor, TensorError> {
+        let rank = self.shape.len().max(other.shape.len());
+        let mut shape = Vec::with_capacity(rank);
+        for axis in 0..rank {
+            let (a, b) = (
+                self.dim_from_end(rank - axis),
+                other.dim_from_end(rank - axis),
+            );
+            if a != b && a != 1 && b != 1 {
+                return Err(TensorError::Broadcast(
+                    self.shape.clone(),
+                    other.shape.clone(),
+                ));
+            }
+            shape.push(if a == 1 { b } else { a });
+        }
+        let lhs = offsets(&shape, &self.broadcast_strides(&shape), self.offset);
+        let rhs = offsets(&shape, &other.broadcast_strides(&shape), other.offset);
+        let data = lhs
+            .into_iter()
+            .zip(rhs)
+            .map(|(i, j)| op(self.data[i], other.data[j]))
+            .collect();
+        Tensor::new(data, shape)
+    }
+
+    /// The sum of every element.
+    pub fn sum(&self) -> i64 {
+        self.to_vec()
+            .into_iter()
+            .fold(0i64, |total, x| total.wrapping_add(x))
+    }
+
+    /// The sums along `axis`, which the result drops.
+    pub fn sum_axis(&self, axis: i64) -> Result<Tensor, TensorError> {
+        let axis = self.axis(axis)?;
+        let (mut shape, mut strides) = (self.shape.clone(), self.strides.clone());
+        let (len, stride) = (shape.remove(axis), strides.remove(axis));
+        let data = offsets(&shape, &strides, self.offset)
+            .into_iter()
+            .map(|start| {
+                (0..len).fold(0i64, |total, i| {
+                    total.wrapping_add(self.data[start + i * stride])
+                })
+            })
+            .collect();
+        Tensor::new(data, shape)
+    }
+
+    fn axis(&self, axis: i64) -> Result<usize, TensorError> {
+        let rank = self.shape.len();
+        let resolved = if axis < 0 { axis + rank as i64 } else { axis };
+        if resolved < 0 || resolved >= rank as i64 {
+            return Err(TensorError::Axis { axis, rank });
+        }
+        Ok(resolved as usize)
+    }
+
+    /// Dimension `n` counting from 1 at the last axis, or 1 past the first.
+    fn dim_from_end(&self, n: usize) -> usize {
+        self.shape
+            .len()
+            .checked_sub(n)
+            .map_or(1, |axis| self.shape[axis])
+    }
+
+    /// Strides that read this tensor as if it had `shape`, which it
+    /// broadcasts to: stretched axes step by 0.
+    fn broadcast_strides(&self, shape: &[usize]) -> Vec<usize> {
+        let padding = shape.len() - self.shape.len();
+        (0..shape.len())
+            .map(|axis| match axis.checked_sub(padding) {
+                Some(own) if self.shape[own] != 1 => self.strides[own],
+                _ => 0,
+            })
+            .collect()
+    }
+
+    fn is_contiguous(&self) -> bool {
+        self.shape
+            .iter()
+            .zip(&self.strides)
+            .zip(row_major_strides(&self.shape))
+            .all(|((&dim, &stride), packed)| dim <= 1 || stride == packed)
+    }
+}
+
+impl PartialEq for Tensor {
+    fn eq(&self, other: &Self) -> bool {
+        self.shape == other.shape && self.to_vec() == other.to_vec()
+    }
+}
+
+impl Eq for Tensor {}
+
+impl fmt::Display for Tensor {
+    /// Nested lists, one level per axis: `[[1, 2], [3, 4]]`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn nest(f: &mut fmt::Formatter<'_>, shape: &[usize], items: &[i64]) -> fmt::Result {
+            let Some((&len, inner)) = shape.split_first() else {
+                return write!(f, "{}", items[0]);
+            };
+            let step = inner.iter().product::<usize>();
+            write!(f, "[")?;
+            for i in 0..len {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                nest(f, inner, &items[i * step..(i + 1) * step])?;
+            }
+            write!(f, "]")
+        }
+        nest(f, &self.shape, &self.to_vec())
+    }
+}
+
+fn row_major_strides(shape: &[usize]) -> Vec<usize> {
+    let mut strides = vec![1; shape.len()];
+    for axis in (1..shape.len()).rev() {
+        strides[axis - 1] = strides[axis] * shape[axis];
+    }
+    strides
+}
+
+/// Buffer positions of every element of the view at `offset` with `shape`
+/// and `strides`, in row-major order.
+fn offsets(shape: &[usize], strides: &[usize], offset: usize) -> Vec<usize> {
+    shape
+        .iter()
+        .zip(strides)
+        .fold(vec![offset], |outer, (&dim, &stride)| {
+            outer
+                .iter()
+                .flat_map(|&at| (0..dim).map(move |i| at + i * stride))
+                .collect()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn iota(shape: &[usize]) -> Tensor {
+        let len = shape.iter().product::<usize>() as i64;
+        Tensor::new((0..len).collect(), shape.to_vec()).expect("valid shape")
+    }
+
+    #[test]
+    fn test_reshape_and_slice_share_the_buffer() {
+        let t = iota(&[2, 3]);
+        assert_eq!(t.strides(), [3, 1]);
+        assert_eq!(t.to_string(), "[[0, 1, 2], [3, 4, 5]]");
+
+        let column = t.slice(1, 1, 2).unwrap();
+        assert_eq!(column.shape(), [2, 1]);
+        assert_eq!(column.to_vec(), [1, 4]);
+        assert!(Arc::ptr_eq(&column.data, &t.data));
+        assert_eq!(t.slice(-1, -2, 3).unwrap().to_vec(), [1, 2, 4, 5]);
+        assert_eq!(t.get(&[1, 2]), Some(5));
+        assert_eq!(t.get(&[2, 0]), None);
+
+        let flat = t.reshape(&[-1]).unwrap();
+        assert_eq!(flat.shape(), [6]);
+        assert!(Arc::ptr_eq(&flat.data, &t.data));
+        assert_eq!(
+            t.reshape(&[3, -1]).unwrap().to_string(),
+            "[[0, 1], [2, 3], [4, 5]]"
+        );
+        // A strided view is gathered before it is reshaped
+        let copied = t.slice(1, 0, 2).unwrap().reshape(&[4]).unwrap();
+        assert_eq!(copied.to_vec(), [0, 1, 3, 4]);
+
+        assert_eq!(
+            t.reshape(&[4, -1]),
+            Err(TensorError::InvalidShape(vec![4, -1]))
+        );
+        assert_eq!(
+            t.reshape(&[5]),
+            Err(TensorError::Shape {
+                shape: vec![5],
+                elements: 6
+            })
+        );
+        assert_eq!(
+            t.slice(0, 1, 3),
+            Err(TensorError::Slice {
+                start: 1,
+                end: 3,
+                len: 2
+            })
+        );
+        assert_eq!(
+            t.slice(2, 0, 1),
+            Err(TensorError::Axis { axis: 2, rank: 2 })
+        );
+    }
+
+    #[test]
+    fn test_broadcasting() {
+        let grid = iota(&[2, 3]);
+        let row = Tensor::new(vec![10, 20, 30], vec![3]).unwrap();
+        let column = Tensor::new(vec![100, 200], vec![2, 1]).unwrap();
+        let scalar = Tensor::new(vec![2], vec![]).unwrap();
+
+        let add = |a: i64, b: i64| a.wrapping_add(b);
+        assert_eq!(
+            grid.zip_with(&row, add).unwrap().to_string(),
+            "[[10, 21, 32], [13, 24, 35]]"
+        );
+        assert_eq!(
+            column.zip_with(&row, add).unwrap().to_string(),
+            "[[110, 120, 130], [210, 220, 230]]"
+        );
+        let doubled = grid.zip_with(&scalar, |a, b| a.wrapping_mul(b)).unwrap();
+        assert_eq!(doubled.to_vec(), [0, 2, 4, 6, 8, 10]);
+        // Broadcasting a strided view reads through its strides
+        let tail = grid.slice(1, 1, 3).unwrap();
+        assert_eq!(
+            tail.zip_with(&column, add).unwrap().to_vec(),
+            [101, 102, 204, 205]
+        );
+
+        assert_eq!(
+            grid.zip_with(&Tensor::new(vec![1, 2], vec![2]).unwrap(), add),
+            Err(TensorError::Broadcast(vec![2, 3], vec![2]))
+        );
+    }
+
+    #[test]
+    fn test_sums() {
+        let t = iota(&[2, 3]);
+        assert_eq!(t.sum(), 15);
+        assert_eq!(t.sum_axis(0).unwrap().to_vec(), [3, 5, 7]);
+        assert_eq!(t.sum_axis(-1).unwrap().to_vec(), [3, 12]);
+        let total = t.sum_axis(0).unwrap().sum_axis(0).unwrap();
+        assert_eq!(total.shape(), [] as [usize; 0]);
+        assert_eq!(total.to_string(), "15");
+        assert_eq!(
+            t.slice(0, 1, 2).unwrap().sum_axis(1).unwrap().to_vec(),
+            [12]
+        );
+        assert!(Tensor::new(vec![1, 2, 3], vec![2, 2]).is_err());
+    }
+}
@@ -0,0 +1,22 @@
+/*
+ * Copyright (c) 2026 Mohamad Al-Zawahreh (dba Sovereign Systems).
+ *
+ * This file is part of the Ark Sovereign Compiler.
+ *
+ * Fixtures shared by the unit tests of several modules.
+ */
+
+use std::path::PathBuf;
+
+/// A fresh directory holding `files` (path -> contents), for one test.
+/// `name` must be unique across the crate's tests.
+pub fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ark_test_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    for (file, contents) in files {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().expect("file has a parent")).expect("mkdir failed");
+        std::fs::write(path, contents).expect("write failed");
+    }
+    dir
+}
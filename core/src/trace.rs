@@ -0,0 +1,628 @@
+/*
+ * Copyright (c) 2026 Mohamad Al-Zawahreh (dba Sovereign Systems).
+ *
+ * This file is part of the Ark Sovereign Compiler.
+ *
+ * LICENSE: DUAL-LICENSED (AGPLv3 or COMMERCIAL).
+ *
+ * 1. OPEN SOURCE: You may use this file under the terms of the GNU Affero
+ * General Public License v3.0. If you link to this code, your ENTIRE
+ * application must be open-sourced under AGPLv3.
+ *
+ * 2. COMMERCIAL: For proprietary use, you must obtain a Commercial License
+ * from Sovereign Systems.
+ *
+ * PATENT NOTICE: Protected by US Patent App #63/935,467.
+ * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
+ */
+
+//! Execution traces of VM runs (`VmConfig::record_trace`), and stepping
+//! through them in either direction.
+//!
+//! A recording VM logs every instruction it executes, with the stack
+//! values the instruction works on, into a ring buffer that keeps the most
+//! recent ones. Entries hold no times or addresses, so a deterministic run
+//! records the same trace every time. Traces are written as JSON lines; a
+//! [`TraceCursor`] moves through one forwards and backwards, which lets a
+//! debugger step back from where a run failed.
+//!
+//! Debugger frontends drive a cursor, or a live VM, over a pipe or socket
+//! with the same protocol: one JSON [`Command`] per line in, one JSON
+//! [`Event`] per line out.
+
+use crate::bytecode::{Chunk, OpCode};
+use crate::debugger::{self, DebugAction, DebugState, StepMode};
+use crate::runtime::{Scope, Value};
+use crate::vm::DebugHookFn;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, VecDeque};
+use std::io::{self, BufRead, Write};
+
+/// Instructions `ark run --record` keeps.
+pub const DEFAULT_CAPACITY: usize = 100_000;
+
+/// One executed instruction, as the VM was about to run it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceEntry {
+    /// Instructions executed before this one, plus one
+    pub step: u64,
+    pub function: String,
+    pub ip: usize,
+    /// Source line, 0 if unknown
+    pub line: u32,
+    /// Calls in progress below the top level
+    pub depth: usize,
+    pub op: String,
+    /// Stack values the instruction takes, deepest first
+    pub operands: Vec<String>,
+    /// The instruction called `sys.debug.break()`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub breakpoint: bool,
+    /// The error the run stopped with here
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl TraceEntry {
+    /// The instruction at `ip` in `chunk`, given the VM's stack and scopes.
+    pub fn capture(step: u64, stack: &[Value], scopes: &[Scope], ip: usize, chunk: &Chunk) -> Self {
+        let op = &chunk.code[ip];
+        let taken = stack.len().min(operand_count(op, stack));
+        TraceEntry {
+            step,
+            function: chunk.name.clone().unwrap_or_else(|| "<main>".to_string()),
+            ip,
+            line: chunk.get_source_loc(ip).map_or(0, |loc| loc.line),
+            depth: scopes.len().saturating_sub(1),
+            op: describe(op),
+            operands: stack[stack.len() - taken..]
+                .iter()
+                .map(debugger::format_value)
+                .collect(),
+            breakpoint: false,
+            error: None,
+        }
+    }
+}
+
+/// How many stack values `op` pops.
+fn operand_count(op: &OpCode, stack: &[Value]) -> usize {
+    match op {
+        OpCode::Push(_)
+        | OpCode::Load(_)
+        | OpCode::Jmp(_)
+        | OpCode::SetupTry(_)
+        | OpCode::PopTry => 0,
+        OpCode::Add
+        | OpCode::Sub
+        | OpCode::Mul
+        | OpCode::Div
+        | OpCode::Mod
+        | OpCode::Eq
+        | OpCode::Neq
+        | OpCode::Gt
+        | OpCode::Lt
+        | OpCode::Ge
+        | OpCode::Le
+        | OpCode::And
+        | OpCode::Or
+        | OpCode::SetField(_) => 2,
+        OpCode::Call(args) => args + 1,
+        OpCode::MakeList(size) => *size,
+        OpCode::MakeStruct(size) => size * 2,
+        OpCode::MakeEnum(_, _, fields) => *fields,
+        // A function's return value, if it left one
+        OpCode::Ret => usize::from(!stack.is_empty()),
+        OpCode::Pop
+        | OpCode::Not
+        | OpCode::Store(_)
+        | OpCode::JmpIfFalse(_)
+        | OpCode::Print
+        | OpCode::Destructure
+        | OpCode::GetField(_)
+        | OpCode::Propagate => 1,
+    }
+}
+
+/// `op` as the trace shows it: pushed values compactly, since a pushed
+/// function would otherwise print its whole body.
+fn describe(op: &OpCode) -> String {
+    match op {
+        OpCode::Push(value) => format!("Push({})", debugger::format_value(value)),
+        _ => format!("{:?}", op),
+    }
+}
+
+/// The most recent instructions of a run.
+#[derive(Debug, Clone)]
+pub struct ExecutionTrace {
+    entries: VecDeque<TraceEntry>,
+    capacity: usize,
+    /// Entries pushed out of the ring
+    dropped: u64,
+}
+
+impl ExecutionTrace {
+    /// Keep the last `capacity` instructions (at least one).
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        ExecutionTrace {
+            entries: VecDeque::with_capacity(capacity.min(DEFAULT_CAPACITY)),
+            capacity,
+            dropped: 0,
+        }
+    }
+
+    pub fn record(&mut self, entry: TraceEntry) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// The last instruction recorded called `sys.debug.break()`.
+    pub fn mark_break(&mut self) {
+        if let Some(entry) = self.entries.back_mut() {
+            entry.breakpoint = true;
+        }
+    }
+
+    /// The run stopped with `error` at the last instruction recorded.
+    pub fn mark_error(&mut self, error: &str) {
+        if let Some(entry) = self.entries.back_mut() {
+            entry.error = Some(error.to_string());
+        }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &TraceEntry> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// How many earlier instructions no longer fit.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// One JSON object per line, oldest first.
+    pub fn to_json_lines(&self) -> String {
+        let mut out = String::new();
+        for entry in &self.entries {
+            out.push_str(&serde_json::to_string(entry).expect("entry serializes"));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Read back a trace written by [`ExecutionTrace::to_json_lines`].
+pub fn parse_json_lines(text: &str) -> Result<Vec<TraceEntry>, serde_json::Error> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(serde_json::from_str)
+        .collect()
+}
+
+/// Why stepping stopped where it did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    /// The session just began
+    Entry,
+    Step,
+    /// A line breakpoint or `sys.debug.break()`
+    Breakpoint,
+    /// The first recorded instruction
+    Start,
+    /// The last recorded instruction
+    End,
+}
+
+/// A position in a recorded trace, with line breakpoints.
+#[derive(Debug, Clone)]
+pub struct TraceCursor {
+    entries: Vec<TraceEntry>,
+    position: usize,
+    breakpoints: BTreeSet<u32>,
+}
+
+impl TraceCursor {
+    /// A cursor on the last entry: where a failed run stopped.
+    pub fn new(entries: Vec<TraceEntry>) -> Self {
+        let position = entries.len().saturating_sub(1);
+        TraceCursor {
+            entries,
+            position,
+            breakpoints: BTreeSet::new(),
+        }
+    }
+
+    pub fn current(&self) -> Option<&TraceEntry> {
+        self.entries.get(self.position)
+    }
+
+    /// One instruction forward.
+    pub fn step(&mut self) -> StopReason {
+        if self.position + 1 >= self.entries.len() {
+            return StopReason::End;
+        }
+        self.position += 1;
+        StopReason::Step
+    }
+
+    /// One instruction back.
+    pub fn back(&mut self) -> StopReason {
+        if self.position == 0 {
+            return StopReason::Start;
+        }
+        self.position -= 1;
+        StopReason::Step
+    }
+
+    /// Forward to the next breakpoint, or the end.
+    pub fn resume(&mut self) -> StopReason {
+        match (self.position + 1..self.entries.len()).find(|&i| self.stops_at(i)) {
+            Some(i) => {
+                self.position = i;
+                StopReason::Breakpoint
+            }
+            None => {
+                self.position = self.entries.len().saturating_sub(1);
+                StopReason::End
+            }
+        }
+    }
+
+    /// Back to the previous breakpoint, or the start.
+    pub fn reverse(&mut self) -> StopReason {
+        match (0..self.position).rev().find(|&i| self.stops_at(i)) {
+            Some(i) => {
+                self.position = i;
+                StopReason::Breakpoint
+            }
+            None => {
+                self.position = 0;
+                StopReason::Start
+            }
+        }
+    }
+
+    /// Move to the entry for `step`; false if it was not recorded.
+    pub fn goto(&mut self, step: u64) -> bool {
+        match self.entries.iter().position(|entry| entry.step == step) {
+            Some(i) => {
+                self.position = i;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Toggle a line breakpoint; returns whether it is now set.
+    pub fn toggle_breakpoint(&mut self, line: u32) -> bool {
+        if !self.breakpoints.remove(&line) {
+            self.breakpoints.insert(line);
+            return true;
+        }
+        false
+    }
+
+    pub fn breakpoints(&self) -> Vec<u32> {
+        self.breakpoints.iter().copied().collect()
+    }
+
+    /// Entry `i` called `sys.debug.break()`, or begins a visit to a line
+    /// with a breakpoint.
+    fn stops_at(&self, i: usize) -> bool {
+        let entry = &self.entries[i];
+        if entry.breakpoint {
+            return true;
+        }
+        let first_on_line = i == 0 || self.entries[i - 1].line != entry.line;
+        self.breakpoints.contains(&entry.line) && first_on_line
+    }
+}
+
+/// A request from a debugger frontend, one JSON object per line, e.g.
+/// `{"command": "break", "line": 12}`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+    Step,
+    Continue,
+    /// Recorded traces only
+    Back,
+    /// Recorded traces only
+    ReverseContinue,
+    /// Recorded traces only
+    Goto {
+        step: u64,
+    },
+    Break {
+        line: u32,
+    },
+    Quit,
+}
+
+/// What the debugger tells the frontend, one JSON object per line.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Stopped {
+        reason: StopReason,
+        entry: TraceEntry,
+    },
+    Breakpoints {
+        lines: Vec<u32>,
+    },
+    /// A live run ended
+    Finished {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        result: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Write `event` to the frontend as one line.
+pub fn send(output: &mut impl Write, event: &Event) -> io::Result<()> {
+    let line = serde_json::to_string(event).map_err(io::Error::other)?;
+    writeln!(output, "{}", line)?;
+    output.flush()
+}
+
+/// The next command from `input`, `None` at end of input. Lines that are
+/// not a command are answered with an error event and skipped.
+fn receive(input: &mut impl BufRead, output: &mut impl Write) -> io::Result<Option<Command>> {
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(command) => return Ok(Some(command)),
+            Err(e) => send(
+                output,
+                &Event::Error {
+                    message: format!("bad command: {}", e),
+                },
+            )?,
+        }
+    }
+}
+
+/// Step through a recorded trace as `input` commands, until `quit` or the
+/// end of input. Starts by reporting the entry the cursor is on.
+pub fn serve(
+    cursor: &mut TraceCursor,
+    mut input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<()> {
+    let Some(entry) = cursor.current().cloned() else {
+        return send(
+            &mut output,
+            &Event::Error {
+                message: "the trace is empty".to_string(),
+            },
+        );
+    };
+    send(
+        &mut output,
+        &Event::Stopped {
+            reason: StopReason::Entry,
+            entry,
+        },
+    )?;
+    while let Some(command) = receive(&mut input, &mut output)? {
+        let reason = match command {
+            Command::Step => cursor.step(),
+            Command::Back => cursor.back(),
+            Command::Continue => cursor.resume(),
+            Command::ReverseContinue => cursor.reverse(),
+            Command::Goto { step } if cursor.goto(step) => StopReason::Step,
+            Command::Goto { step } => {
+                send(
+                    &mut output,
+                    &Event::Error {
+                        message: format!("step {} is not in the trace", step),
+                    },
+                )?;
+                continue;
+            }
+            Command::Break { line } => {
+                cursor.toggle_breakpoint(line);
+                send(
+                    &mut output,
+                    &Event::Breakpoints {
+                        lines: cursor.breakpoints(),
+                    },
+                )?;
+                continue;
+            }
+            Command::Quit => return Ok(()),
+        };
+        let entry = cursor.current().cloned().expect("trace is not empty");
+        send(&mut output, &Event::Stopped { reason, entry })?;
+    }
+    Ok(())
+}
+
+/// A debug hook that lets a frontend step a live VM over the protocol.
+/// The VM stops on its first line, at line breakpoints and at
+/// `sys.debug.break()`; stepping backward needs a recorded trace. The hook
+/// quits the run when input ends or output fails.
+pub fn live_hook<'a>(
+    mut input: impl BufRead + 'a,
+    mut output: impl Write + 'a,
+) -> Box<DebugHookFn<'a>> {
+    let mut state = DebugState::new();
+    let mut step = 0u64;
+    let mut reason = StopReason::Entry;
+    Box::new(move |stack, scopes, ip, chunk| {
+        step += 1;
+        let depth = scopes.len().saturating_sub(1);
+        if !state.should_break(ip, chunk, depth) {
+            return DebugAction::Continue;
+        }
+        let entry = TraceEntry::capture(step, stack, scopes, ip, chunk);
+        state.last_line = entry.line;
+        let stopped = Event::Stopped { reason, entry };
+        if send(&mut output, &stopped).is_err() {
+            return DebugAction::Quit;
+        }
+        loop {
+            let command = match receive(&mut input, &mut output) {
+                Ok(Some(command)) => command,
+                Ok(None) | Err(_) => return DebugAction::Quit,
+            };
+            let reply = match command {
+                Command::Step => {
+                    state.step_mode = StepMode::StepInto;
+                    state.stepping = true;
+                    reason = StopReason::Step;
+                    return DebugAction::Continue;
+                }
+                Command::Continue => {
+                    state.step_mode = StepMode::Continue;
+                    state.stepping = false;
+                    reason = StopReason::Breakpoint;
+                    return DebugAction::Continue;
+                }
+                Command::Quit => return DebugAction::Quit,
+                Command::Break { line } => {
+                    state.toggle_breakpoint(line);
+                    let mut lines: Vec<u32> = state.breakpoints.iter().copied().collect();
+                    lines.sort();
+                    Event::Breakpoints { lines }
+                }
+                Command::Back | Command::ReverseContinue | Command::Goto { .. } => Event::Error {
+                    message: "stepping backward needs a recorded trace (ark run --record)"
+                        .to_string(),
+                },
+            };
+            if send(&mut output, &reply).is_err() {
+                return DebugAction::Quit;
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(step: u64, line: u32) -> TraceEntry {
+        TraceEntry {
+            step,
+            function: "<main>".to_string(),
+            ip: step as usize,
+            line,
+            depth: 0,
+            op: "Pop".to_string(),
+            operands: Vec::new(),
+            breakpoint: false,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_ring_keeps_the_latest_entries() {
+        let mut trace = ExecutionTrace::new(2);
+        for step in 1..=3 {
+            trace.record(entry(step, 1));
+        }
+        trace.mark_error("boom");
+        let steps: Vec<u64> = trace.entries().map(|e| e.step).collect();
+        assert_eq!(steps, [2, 3]);
+        assert_eq!(trace.dropped(), 1);
+
+        let lines = trace.to_json_lines();
+        assert!(!lines.lines().next().expect("a line").contains("error"));
+        let parsed = parse_json_lines(&lines).expect("trace parses");
+        assert_eq!(parsed[1].error.as_deref(), Some("boom"));
+        assert_eq!(parsed, trace.entries().cloned().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_cursor_steps_both_ways_between_breakpoints() {
+        let mut entries: Vec<TraceEntry> = [1, 2, 2, 3, 2, 4]
+            .iter()
+            .enumerate()
+            .map(|(i, &line)| entry(i as u64 + 1, line))
+            .collect();
+        entries[3].breakpoint = true;
+        let mut cursor = TraceCursor::new(entries);
+        assert_eq!(cursor.current().map(|e| e.step), Some(6));
+        assert_eq!(cursor.step(), StopReason::End);
+
+        assert_eq!(cursor.reverse(), StopReason::Breakpoint);
+        assert_eq!(cursor.current().map(|e| e.step), Some(4));
+        assert!(cursor.toggle_breakpoint(2));
+        // Each visit to line 2 stops once, on its first instruction
+        assert_eq!(cursor.reverse(), StopReason::Breakpoint);
+        assert_eq!(cursor.current().map(|e| e.step), Some(2));
+        assert_eq!(cursor.back(), StopReason::Step);
+        assert_eq!(cursor.back(), StopReason::Start);
+        assert_eq!(cursor.resume(), StopReason::Breakpoint);
+        assert_eq!(cursor.current().map(|e| e.step), Some(2));
+        assert_eq!(cursor.resume(), StopReason::Breakpoint);
+        assert_eq!(cursor.resume(), StopReason::Breakpoint);
+        assert_eq!(cursor.current().map(|e| e.step), Some(5));
+        assert_eq!(cursor.resume(), StopReason::End);
+        assert!(cursor.goto(3));
+        assert!(!cursor.goto(99));
+    }
+
+    #[test]
+    fn test_serve_answers_each_command_with_one_event() {
+        let mut cursor = TraceCursor::new(vec![entry(1, 1), entry(2, 2)]);
+        let input = concat!(
+            "{\"command\": \"back\"}\n",
+            "{\"command\": \"break\", \"line\": 2}\n",
+            "{\"command\": \"continue\"}\n",
+            "nonsense\n",
+            "{\"command\": \"goto\", \"step\": 9}\n",
+            "{\"command\": \"quit\"}\n",
+            "{\"command\": \"step\"}\n",
+        );
+        let mut output = Vec::new();
+        serve(&mut cursor, input.as_bytes(), &mut output).expect("serve failed");
+
+        let events: Vec<serde_json::Value> = String::from_utf8(output)
+            .expect("utf-8")
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("event is JSON"))
+            .collect();
+        let kinds: Vec<&str> = events
+            .iter()
+            .map(|e| e["event"].as_str().expect("event kind"))
+            .collect();
+        let expected = [
+            "stopped",
+            "stopped",
+            "breakpoints",
+            "stopped",
+            "error",
+            "error",
+        ];
+        assert_eq!(kinds, expected);
+        assert_eq!(events[0]["reason"], "entry");
+        assert_eq!(events[1]["entry"]["step"], 1);
+        assert_eq!(events[2]["lines"], serde_json::json!([2]));
+        assert_eq!(events[3]["reason"], "breakpoint");
+    }
+}
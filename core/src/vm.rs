@@ -1,8 +1,18 @@
+use crate::ast::Span;
 use crate::bytecode::{Chunk, OpCode};
-use crate::debugger::DebugAction;
+use crate::capability::Capabilities;
+use crate::debugger::{self, DebugAction};
 use crate::intrinsics;
-use crate::runtime::{RuntimeError, Scope, Value};
+use crate::profiler::Profile;
+use crate::runtime::{NativeFn, Resource, ResourceLimits, RuntimeError, Scope, Value};
+use crate::trace::{ExecutionTrace, TraceEntry};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::ops::ControlFlow;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 pub const MAX_STACK_DEPTH: usize = 10_000;
@@ -24,6 +34,20 @@ pub enum ArkError {
     Generic(String),
 }
 
+impl ArkError {
+    /// The value a `catch` block binds for this error, or `None` for
+    /// errors that always abort the program (resource limits, VM faults
+    /// and security lockouts).
+    pub fn catch_value(&self) -> Option<Value> {
+        match self {
+            ArkError::Runtime(err) if err.is_catchable() => Some(err.to_value()),
+            ArkError::DivisionByZero => Some(RuntimeError::DivisionByZero.to_value()),
+            ArkError::Generic(msg) => Some(RuntimeError::InvalidOperation(msg.clone()).to_value()),
+            _ => None,
+        }
+    }
+}
+
 // --- GraphArena Implementation ---
 
 #[derive(Debug)]
@@ -103,6 +127,445 @@ pub struct CallFrame {
     pub chunk: Arc<Chunk>,
 }
 
+/// An active `try` block: where its `catch` block starts, and the VM state
+/// to unwind to before running it.
+#[derive(Debug)]
+struct TryHandler {
+    catch_ip: usize,
+    chunk: Arc<Chunk>,
+    stack_len: usize,
+    frame_depth: usize,
+    scope_depth: usize,
+}
+
+// --- Backtraces ---
+
+/// One call in an Ark-level backtrace: the function running and where it
+/// is defined (`None` unless the source was parsed with spans).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceFrame {
+    pub function: String,
+    pub span: Option<Span>,
+}
+
+impl TraceFrame {
+    fn of(chunk: &Chunk) -> Self {
+        TraceFrame {
+            function: chunk.name.clone().unwrap_or_else(|| "<main>".to_string()),
+            span: chunk.span.clone(),
+        }
+    }
+
+    /// The frame as `sys.error.trace` returns it: a struct with the
+    /// `function` name and its `file`, `line` and `col` (Unit if unknown).
+    pub fn to_value(&self) -> Value {
+        let mut fields = HashMap::new();
+        fields.insert("function".to_string(), Value::String(self.function.clone()));
+        let (file, line, col) = match &self.span {
+            Some(span) => (
+                Value::String(span.file.clone()),
+                Value::Integer(span.start_line as i64),
+                Value::Integer(span.start_col as i64),
+            ),
+            None => (Value::Unit, Value::Unit, Value::Unit),
+        };
+        fields.insert("file".to_string(), file);
+        fields.insert("line".to_string(), line);
+        fields.insert("col".to_string(), col);
+        Value::Struct(fields)
+    }
+}
+
+impl fmt::Display for TraceFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.span {
+            Some(span) => write!(
+                f,
+                "at {} ({}:{}:{})",
+                self.function, span.file, span.start_line, span.start_col
+            ),
+            None => write!(f, "at {}", self.function),
+        }
+    }
+}
+
+/// Render a backtrace, innermost call first, one indented frame per line.
+pub fn format_backtrace(trace: &[TraceFrame]) -> String {
+    let mut out = String::from("Backtrace (most recent call first):");
+    for frame in trace {
+        out.push_str("\n    ");
+        out.push_str(&frame.to_string());
+    }
+    out
+}
+
+thread_local! {
+    static LAST_TRACE: RefCell<Vec<TraceFrame>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Call stack at the most recent error a VM on this thread raised, caught
+/// or not; empty before the first error.
+pub fn last_error_trace() -> Vec<TraceFrame> {
+    LAST_TRACE.with(|trace| trace.borrow().clone())
+}
+
+// --- Cooperative Tasks ---
+
+/// How long a task whose I/O would block waits before trying again.
+const TASK_RETRY_INTERVAL: Duration = Duration::from_millis(1);
+
+/// A request from a `sys.task.*` intrinsic to suspend the task running it.
+/// Native functions have no handle on their VM, so they leave the request
+/// with `request_suspend` and the VM acts on it when the call returns.
+pub enum TaskRequest {
+    /// Let the other tasks run, then continue.
+    Yield,
+    /// Let the other tasks run until the given instant.
+    Sleep(Instant),
+    /// The call would block: let the other tasks run, then make it again.
+    Retry(NativeFn, Vec<Value>),
+}
+
+thread_local! {
+    static TASK_REQUEST: RefCell<Option<TaskRequest>> = const { RefCell::new(None) };
+    static SPAWNED_TASKS: RefCell<Vec<(i64, Value, Vec<Value>)>> = const { RefCell::new(Vec::new()) };
+}
+
+static NEXT_TASK_ID: AtomicI64 = AtomicI64::new(1);
+
+/// Queue `callable(args)` as a task on this thread's scheduler. It starts
+/// the next time the running task suspends; returns the task id.
+pub fn spawn_task(callable: Value, args: Vec<Value>) -> i64 {
+    let id = NEXT_TASK_ID.fetch_add(1, Ordering::SeqCst);
+    SPAWNED_TASKS.with(|tasks| tasks.borrow_mut().push((id, callable, args)));
+    id
+}
+
+/// Ask the VM running the current native call to suspend once it returns.
+/// Outside a VM the request is dropped by the next call.
+pub fn request_suspend(request: TaskRequest) {
+    TASK_REQUEST.with(|slot| *slot.borrow_mut() = Some(request));
+}
+
+fn take_task_request() -> Option<TaskRequest> {
+    TASK_REQUEST.with(|slot| slot.borrow_mut().take())
+}
+
+/// Why `VM::execute` handed control back.
+enum Suspend {
+    Done(Value),
+    Yield,
+    Sleep(Instant),
+}
+
+struct Task {
+    id: i64,
+    vm: VM<'static>,
+    wake_at: Option<Instant>,
+}
+
+/// Round-robin scheduler for the tasks spawned while a `VM::run` is active.
+/// Every task is a VM of its own that runs until it suspends, on the gas
+/// of the VM that spawned it.
+#[derive(Default)]
+struct Scheduler {
+    tasks: VecDeque<Task>,
+}
+
+impl Scheduler {
+    /// Take over the tasks spawned since the last call.
+    fn adopt_spawned(&mut self) {
+        for (id, callable, args) in SPAWNED_TASKS.with(|tasks| tasks.take()) {
+            // A chunk that makes the call, so functions and native
+            // functions start the same way
+            let mut chunk = Chunk::new();
+            let arg_count = args.len();
+            for arg in args {
+                chunk.write(OpCode::Push(arg));
+            }
+            chunk.write(OpCode::Push(callable));
+            chunk.write(OpCode::Call(arg_count));
+            chunk.write(OpCode::Ret);
+            match VM::with_config(chunk, "TASK", VmConfig::default()) {
+                Ok(vm) => self.tasks.push_back(Task {
+                    id,
+                    vm,
+                    wake_at: None,
+                }),
+                Err(e) => eprintln!("[Ark:Task] task {} failed to start: {}", id, e),
+            }
+        }
+    }
+
+    /// Resume every task that is due once. Returns whether any task ran.
+    fn run_round(&mut self) -> bool {
+        self.adopt_spawned();
+        let now = if self.tasks.iter().any(|t| t.wake_at.is_some()) {
+            Some(Instant::now())
+        } else {
+            None
+        };
+        let mut ran = false;
+        for _ in 0..self.tasks.len() {
+            let Some(mut task) = self.tasks.pop_front() else {
+                break;
+            };
+            if matches!((task.wake_at, now), (Some(wake), Some(now)) if wake > now) {
+                self.tasks.push_back(task);
+                continue;
+            }
+            ran = true;
+            task.wake_at = None;
+            match task.vm.execute() {
+                Ok(Suspend::Done(_)) => {}
+                Ok(Suspend::Yield) => self.tasks.push_back(task),
+                Ok(Suspend::Sleep(until)) => {
+                    task.wake_at = Some(until);
+                    self.tasks.push_back(task);
+                }
+                Err(e) => eprintln!("[Ark:Task] task {} failed: {}", task.id, e),
+            }
+            self.adopt_spawned();
+        }
+        ran
+    }
+
+    /// Run tasks until `deadline` passes, or until all have finished when
+    /// there is none.
+    fn run_until(&mut self, deadline: Option<Instant>) {
+        loop {
+            self.adopt_spawned();
+            if self.tasks.is_empty() {
+                if let Some(deadline) = deadline {
+                    std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+                }
+                return;
+            }
+            if matches!(deadline, Some(d) if Instant::now() >= d) {
+                return;
+            }
+            if !self.run_round() {
+                // Everyone is asleep: wait for the first to wake
+                let next_wake = self.tasks.iter().filter_map(|t| t.wake_at).min();
+                let until = match (next_wake, deadline) {
+                    (Some(wake), Some(d)) => wake.min(d),
+                    (Some(wake), None) => wake,
+                    (None, Some(d)) => d,
+                    (None, None) => continue,
+                };
+                std::thread::sleep(until.saturating_duration_since(Instant::now()));
+            }
+        }
+    }
+}
+
+// --- Configuration ---
+
+/// How a VM runs. The default is an ordinary VM with the full host
+/// available.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VmConfig {
+    pub security_level: u8,
+    /// Replay-safe execution; see [`VmConfig::deterministic`].
+    pub deterministic: bool,
+    /// What `sys.random.*` is seeded with in deterministic mode.
+    pub seed: u64,
+    /// What `sys.time.now` reads when a deterministic run starts, in ms
+    /// since the epoch.
+    pub clock_start: i64,
+    /// Instructions the VM may execute before failing with
+    /// [`RuntimeError::OutOfGas`]. The tasks and threads the program
+    /// spawns, and the functions its native calls run (`sys.func.apply`,
+    /// HTTP route handlers), draw on the same allowance. `None` draws on
+    /// the gas of the program already running on the thread (see
+    /// [`set_gas`]), if any, and otherwise leaves only the `MAX_STEPS`
+    /// timeout.
+    pub gas_limit: Option<u64>,
+    /// What the program may do outside the VM while it runs. `None` keeps
+    /// whatever the thread already has (see
+    /// [`intrinsics::set_capabilities`]).
+    pub capabilities: Option<Capabilities>,
+    /// How deep the program may recurse, how large its collections and
+    /// buffers may grow and how many sockets and threads it may hold
+    /// open. Exceeding one fails with [`RuntimeError::LimitExceeded`].
+    /// `None` keeps whatever the thread already has (see
+    /// [`intrinsics::set_resource_limits`]).
+    pub limits: Option<ResourceLimits>,
+    /// Record calls, time and allocations per Ark function; read them
+    /// with [`VM::profile`].
+    pub profile: bool,
+    /// Record the last this many instructions executed, with their
+    /// operands; read them with [`VM::execution_trace`].
+    pub record_trace: Option<usize>,
+}
+
+impl VmConfig {
+    /// A VM whose result depends only on the program, `seed` and its
+    /// arguments, so every node replaying a program gets the same result.
+    ///
+    /// `sys.random.*`, `sys.uuid.v4` and `sys.crypto.random_bytes` draw
+    /// from a generator seeded with `seed`; `sys.time.now` is a logical
+    /// clock that only `sys.time.sleep` advances. A program that calls
+    /// anything else outside the VM (the network, files, processes,
+    /// threads, tasks, timers) is refused before it runs with
+    /// [`RuntimeError::NonDeterministic`].
+    pub fn deterministic(seed: u64) -> Self {
+        VmConfig {
+            deterministic: true,
+            seed,
+            ..Self::default()
+        }
+    }
+}
+
+/// The first call in `chunk`, or a function it defines, to an intrinsic
+/// a deterministic VM cannot run.
+fn nondeterministic_call(chunk: &Chunk) -> Option<String> {
+    chunk
+        .code
+        .iter()
+        .find_map(|op| match op {
+            OpCode::Load(name) if intrinsics::IntrinsicRegistry::is_nondeterministic(name) => {
+                Some(name.clone())
+            }
+            OpCode::Push(Value::Function(inner)) => nondeterministic_call(inner),
+            _ => None,
+        })
+        .or_else(|| {
+            chunk.constants.iter().find_map(|value| match value {
+                Value::Function(inner) => nondeterministic_call(inner),
+                _ => None,
+            })
+        })
+}
+
+/// Seeds the thread's random intrinsics and clock for a deterministic
+/// run, refuses host calls made by name, and puts the host's back when
+/// dropped.
+struct Replay;
+
+impl Replay {
+    fn start(config: &VmConfig) -> Self {
+        intrinsics::set_deterministic_rng(Some(config.seed));
+        intrinsics::set_deterministic_clock(Some(config.clock_start));
+        intrinsics::set_deterministic(true);
+        Replay
+    }
+}
+
+impl Drop for Replay {
+    fn drop(&mut self) {
+        intrinsics::set_deterministic_rng(None);
+        intrinsics::set_deterministic_clock(None);
+        intrinsics::set_deterministic(false);
+    }
+}
+
+/// Puts the program's capabilities in force on the thread, and the ones
+/// before them back when dropped.
+struct Sandbox {
+    previous: Option<Capabilities>,
+}
+
+impl Sandbox {
+    fn enter(capabilities: &Capabilities) -> Self {
+        Sandbox {
+            previous: intrinsics::set_capabilities(Some(capabilities.clone())),
+        }
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        intrinsics::set_capabilities(self.previous.take());
+    }
+}
+
+/// Puts the program's resource limits in force on the thread, and the
+/// ones before them back when dropped.
+struct Bounds {
+    previous: Option<ResourceLimits>,
+}
+
+impl Bounds {
+    fn enter(limits: &ResourceLimits) -> Self {
+        Bounds {
+            previous: intrinsics::set_resource_limits(Some(limits.clone())),
+        }
+    }
+}
+
+impl Drop for Bounds {
+    fn drop(&mut self) {
+        intrinsics::set_resource_limits(self.previous.take());
+    }
+}
+
+thread_local! {
+    /// Gas left for the program running on this thread, set by `set_gas`.
+    static GAS: RefCell<Option<Arc<AtomicU64>>> = const { RefCell::new(None) };
+}
+
+/// Make VMs started on this thread without a `gas_limit` of their own draw
+/// on `gas`, or run unmetered with `None`. Returns what was set before, so
+/// a caller can restore it. Threads spawned with `sys.thread.spawn` inherit
+/// the setting.
+pub fn set_gas(gas: Option<Arc<AtomicU64>>) -> Option<Arc<AtomicU64>> {
+    GAS.with(|current| current.replace(gas))
+}
+
+/// Gas left for the program running on this thread, if it has a limit.
+pub fn gas() -> Option<Arc<AtomicU64>> {
+    GAS.with(|current| current.borrow().clone())
+}
+
+/// Puts a VM's gas in force on the thread, so the VMs started while it
+/// runs draw on it, and the gas before it back when dropped.
+struct Meter {
+    previous: Option<Arc<AtomicU64>>,
+}
+
+impl Meter {
+    fn enter(gas: &Arc<AtomicU64>) -> Self {
+        Meter {
+            previous: set_gas(Some(gas.clone())),
+        }
+    }
+}
+
+impl Drop for Meter {
+    fn drop(&mut self) {
+        set_gas(self.previous.take());
+    }
+}
+
+/// Puts a VM's count of allocated values in force on the thread, so the
+/// VMs started while it runs add to it, and the count before it back
+/// when dropped.
+struct Tally {
+    previous: Option<Arc<AtomicUsize>>,
+}
+
+impl Tally {
+    fn enter(allocated: &Arc<AtomicUsize>) -> Self {
+        Tally {
+            previous: intrinsics::set_values_allocated(Some(allocated.clone())),
+        }
+    }
+}
+
+impl Drop for Tally {
+    fn drop(&mut self) {
+        intrinsics::set_values_allocated(self.previous.take());
+    }
+}
+
+thread_local! {
+    /// Calls in progress on this thread below the VM being made: those of
+    /// the VMs whose native calls started it.
+    static CALL_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
 /// Type alias for VM debug hook callback.
 pub type DebugHookFn<'a> = dyn FnMut(&[Value], &[Scope], usize, &Chunk) -> DebugAction + 'a;
 
@@ -118,15 +581,72 @@ pub struct VM<'a> {
     pub trace: bool,
     /// Optional debug hook: called before each opcode, receives (ip). Returns DebugAction.
     pub debug_hook: Option<Box<DebugHookFn<'a>>>,
+    /// A native call that would have blocked, made again on resume.
+    pending_call: Option<(NativeFn, Vec<Value>)>,
+    /// Set by a native call that asked the task to suspend.
+    suspended: Option<Suspend>,
+    /// Enclosing `try` blocks, innermost last.
+    handlers: Vec<TryHandler>,
+    config: VmConfig,
+    /// The limits in force: the config's, or else the thread's when the
+    /// VM was made
+    limits: ResourceLimits,
+    /// Gas left: the config's allowance, or else the thread's when the VM
+    /// was made, shared with every VM started while this one runs
+    gas: Option<Arc<AtomicU64>>,
+    /// Values allocated so far, spent from `max_allocations`: counted
+    /// afresh when the config sets limits, or else added to the thread's
+    /// count when the VM was made
+    allocated: Arc<AtomicUsize>,
+    /// Calls already in progress on the thread when the VM was made,
+    /// counted against `max_call_depth` with the VM's own
+    depth_base: usize,
+    /// What has run so far, if the config asked for a profile
+    profile: Option<Profile>,
+    /// The latest instructions, if the config asked for a trace
+    recording: Option<ExecutionTrace>,
 }
 
 impl<'a> VM<'a> {
     pub fn new(chunk: Chunk, hash: &str, security_level: u8) -> Result<Self, RuntimeError> {
+        Self::with_config(
+            chunk,
+            hash,
+            VmConfig {
+                security_level,
+                ..VmConfig::default()
+            },
+        )
+    }
+
+    pub fn with_config(chunk: Chunk, hash: &str, config: VmConfig) -> Result<Self, RuntimeError> {
+        let security_level = config.security_level;
+        if config.deterministic {
+            if let Some(name) = nondeterministic_call(&chunk) {
+                return Err(RuntimeError::NonDeterministic(name));
+            }
+        }
+
         // Security Check: Verify Code Hash on Blockchain
         if security_level > 0 && !crate::blockchain::verify_code_hash(hash) {
             return Err(RuntimeError::UntrustedCode);
         }
 
+        let limits = config
+            .limits
+            .clone()
+            .unwrap_or_else(intrinsics::resource_limits);
+        let gas = match config.gas_limit {
+            Some(limit) => Some(Arc::new(AtomicU64::new(limit))),
+            None => gas(),
+        };
+        let allocated = match config.limits {
+            Some(_) => Arc::default(),
+            None => intrinsics::values_allocated().unwrap_or_default(),
+        };
+        let depth_base = CALL_DEPTH.with(Cell::get);
+        let profile = config.profile.then(Profile::new);
+        let recording = config.record_trace.map(ExecutionTrace::new);
         let mut global_scope = Scope::new();
         crate::intrinsics::IntrinsicRegistry::register_all(&mut global_scope);
         Ok(Self {
@@ -140,14 +660,48 @@ impl<'a> VM<'a> {
             step_count: 0,
             trace: false,
             debug_hook: None,
+            pending_call: None,
+            suspended: None,
+            handlers: Vec::new(),
+            config,
+            limits,
+            gas,
+            allocated,
+            depth_base,
+            profile,
+            recording,
         })
     }
 
+    /// Calls, time and allocations per function over every run so far;
+    /// `None` unless [`VmConfig::profile`] is set.
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    /// The latest instructions executed, including those of a run that
+    /// failed; `None` unless [`VmConfig::record_trace`] is set.
+    pub fn execution_trace(&self) -> Option<&ExecutionTrace> {
+        self.recording.as_ref()
+    }
+
     #[inline]
     pub fn push(&mut self, val: Value) -> Result<(), ArkError> {
+        if let Some(profile) = &mut self.profile {
+            profile.record_value(&val);
+        }
+        self.push_unprofiled(val)
+    }
+
+    /// Push a value the profile has already seen allocated: a result
+    /// coming back from a call.
+    #[inline]
+    fn push_unprofiled(&mut self, val: Value) -> Result<(), ArkError> {
         if self.stack.len() >= MAX_STACK_DEPTH {
             return Err(ArkError::StackOverflow);
         }
+        // Every value a program holds passes through the stack
+        self.limits.check_value(&val)?;
         self.stack.push(val);
         Ok(())
     }
@@ -184,414 +738,561 @@ impl<'a> VM<'a> {
         self.run()
     }
 
+    /// Run the program to completion. Tasks it spawns with `sys.task.spawn`
+    /// run whenever it suspends, and finish before this returns.
     pub fn run(&mut self) -> Result<Value, ArkError> {
-        loop {
-            // Execution Timeout Check
-            self.step_count += 1;
-            if self.step_count > MAX_STEPS {
-                return Err(ArkError::ExecutionTimeout);
-            }
+        let _replay = self
+            .config
+            .deterministic
+            .then(|| Replay::start(&self.config));
+        let _sandbox = self.config.capabilities.as_ref().map(Sandbox::enter);
+        let _bounds = self.config.limits.as_ref().map(Bounds::enter);
+        let _meter = self.gas.as_ref().map(Meter::enter);
+        let _tally = Tally::enter(&self.allocated);
+        // A VM started by a native call runs inside the calls under it
+        self.limits.check(Resource::CallDepth, self.depth_base)?;
+        if let Some(profile) = self.profile.as_mut().filter(|p| p.is_idle()) {
+            profile.enter(&TraceFrame::of(&self.chunk).function);
+        }
+        let result = self.run_tasks();
+        if let Some(profile) = &mut self.profile {
+            profile.exit_all();
+        }
+        if let (Some(recording), Err(err)) = (&mut self.recording, &result) {
+            recording.mark_error(&err.to_string());
+        }
+        result
+    }
 
-            if self.ip >= self.chunk.code.len() {
-                if let Some(val) = self.op_return()? {
+    /// Execute, running spawned tasks whenever the program suspends.
+    fn run_tasks(&mut self) -> Result<Value, ArkError> {
+        let mut scheduler = Scheduler::default();
+        loop {
+            match self.execute()? {
+                Suspend::Done(val) => {
+                    scheduler.run_until(None);
                     return Ok(val);
                 }
-                continue;
+                Suspend::Yield => {
+                    scheduler.run_round();
+                }
+                Suspend::Sleep(until) => scheduler.run_until(Some(until)),
             }
+        }
+    }
 
-            let op = &self.chunk.code[self.ip];
-
-            // Debug hook — fire before execution
-            if self.debug_hook.is_some() {
-                // We need to temporarily take the hook to avoid borrow issues
-                let mut hook = self.debug_hook.take().expect("operation failed");
-                let action = hook(&self.stack, &self.scopes, self.ip, &self.chunk);
-                self.debug_hook = Some(hook);
-                match action {
-                    DebugAction::Quit => return Ok(Value::Unit),
-                    DebugAction::Continue => {}
+    /// Run until the program returns or a native call suspends it.
+    fn execute(&mut self) -> Result<Suspend, ArkError> {
+        if let Some((func, args)) = self.pending_call.take() {
+            match self.call_native(func, args) {
+                Ok(()) => {
+                    if let Some(suspend) = self.suspended.take() {
+                        return Ok(suspend);
+                    }
                 }
+                Err(err) => self.catch_error(err)?,
             }
-
-            if self.trace {
-                println!("IP: {:03} | Op: {:?}", self.ip, op);
+        }
+        loop {
+            match self.step() {
+                Ok(None) => {}
+                Ok(Some(suspend)) => return Ok(suspend),
+                Err(err) => self.catch_error(err)?,
             }
+        }
+    }
 
-            #[cfg(debug_assertions)]
+    /// Execute one instruction. Returns why execution stopped, if it did.
+    fn step(&mut self) -> Result<Option<Suspend>, ArkError> {
+        // Execution Timeout Check
+        self.step_count += 1;
+        if self.step_count > MAX_STEPS {
+            return Err(ArkError::ExecutionTimeout);
+        }
+        if let Some(gas) = &self.gas {
+            // Once empty, every VM sharing the gas fails on its next step
+            if gas
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+                    left.checked_sub(1)
+                })
+                .is_err()
             {
-                use std::io::Write;
-                println!(
-                    "IP: {:03} | Op: {:?} | Stack: {} | Frames: {}",
-                    self.ip,
-                    op,
-                    self.stack.len(),
-                    self.frames.len()
-                );
-                std::io::stdout().flush().expect("stdout flush failed");
-            }
-            self.ip += 1;
-
-            match op {
-                OpCode::Push(v) => self.push(v.clone())?,
-                OpCode::Pop => {
-                    self.stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Pop".to_string()))?;
-                }
+                return Err(RuntimeError::OutOfGas.into());
+            }
+        }
 
-                OpCode::Add => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Add".into()))?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Add".into()))?;
-                    match (&a, &b) {
-                        (Value::Integer(i1), Value::Integer(i2)) => {
-                            self.push(Value::Integer(i1 + i2))?
-                        }
-                        _ => self.push(intrinsics::intrinsic_add(vec![a, b])?)?,
+        if self.ip >= self.chunk.code.len() {
+            return Ok(self.op_return()?.map(Suspend::Done));
+        }
+
+        let op = &self.chunk.code[self.ip];
+
+        if let Some(recording) = &mut self.recording {
+            recording.record(TraceEntry::capture(
+                self.step_count,
+                &self.stack,
+                &self.scopes,
+                self.ip,
+                &self.chunk,
+            ));
+        }
+
+        // Debug hook — fire before execution
+        if self.debug_hook.is_some() {
+            // We need to temporarily take the hook to avoid borrow issues
+            let mut hook = self.debug_hook.take().expect("operation failed");
+            let action = hook(&self.stack, &self.scopes, self.ip, &self.chunk);
+            self.debug_hook = Some(hook);
+            match action {
+                DebugAction::Quit => return Ok(Some(Suspend::Done(Value::Unit))),
+                DebugAction::Continue => {}
+            }
+        }
+
+        if self.trace {
+            println!("IP: {:03} | Op: {:?}", self.ip, op);
+        }
+
+        #[cfg(debug_assertions)]
+        {
+            use std::io::Write;
+            println!(
+                "IP: {:03} | Op: {:?} | Stack: {} | Frames: {}",
+                self.ip,
+                op,
+                self.stack.len(),
+                self.frames.len()
+            );
+            std::io::stdout().flush().expect("stdout flush failed");
+        }
+        self.ip += 1;
+
+        match op {
+            OpCode::Push(v) => self.push(v.clone())?,
+            OpCode::Pop => {
+                self.stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Pop".to_string()))?;
+            }
+
+            OpCode::Add => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Add".into()))?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Add".into()))?;
+                match (&a, &b) {
+                    (Value::Integer(i1), Value::Integer(i2)) => {
+                        self.push(Value::Integer(i1 + i2))?
                     }
+                    _ => self.push(intrinsics::intrinsic_add(vec![a, b])?)?,
                 }
-                OpCode::Sub => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Sub".into()))?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Sub".into()))?;
-                    match (&a, &b) {
-                        (Value::Integer(i1), Value::Integer(i2)) => {
-                            self.push(Value::Integer(i1 - i2))?
-                        }
-                        _ => self.push(intrinsics::intrinsic_sub(vec![a, b])?)?,
+            }
+            OpCode::Sub => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Sub".into()))?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Sub".into()))?;
+                match (&a, &b) {
+                    (Value::Integer(i1), Value::Integer(i2)) => {
+                        self.push(Value::Integer(i1 - i2))?
                     }
+                    _ => self.push(intrinsics::intrinsic_sub(vec![a, b])?)?,
                 }
-                OpCode::Mul => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Mul".into()))?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Mul".into()))?;
-                    match (&a, &b) {
-                        (Value::Integer(i1), Value::Integer(i2)) => {
-                            self.push(Value::Integer(i1 * i2))?
-                        }
-                        _ => self.push(intrinsics::intrinsic_mul(vec![a, b])?)?,
+            }
+            OpCode::Mul => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Mul".into()))?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Mul".into()))?;
+                match (&a, &b) {
+                    (Value::Integer(i1), Value::Integer(i2)) => {
+                        self.push(Value::Integer(i1 * i2))?
                     }
+                    _ => self.push(intrinsics::intrinsic_mul(vec![a, b])?)?,
                 }
-                OpCode::Div => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Div".into()))?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Div".into()))?;
-                    match (&a, &b) {
-                        (Value::Integer(i1), Value::Integer(i2)) => {
-                            if *i2 == 0 {
-                                return Err(ArkError::DivisionByZero);
-                            }
-                            self.push(Value::Integer(i1 / i2))?
+            }
+            OpCode::Div => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Div".into()))?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Div".into()))?;
+                match (&a, &b) {
+                    (Value::Integer(i1), Value::Integer(i2)) => {
+                        if *i2 == 0 {
+                            return Err(ArkError::DivisionByZero);
                         }
-                        _ => self.push(intrinsics::intrinsic_div(vec![a, b])?)?,
+                        self.push(Value::Integer(i1 / i2))?
                     }
+                    _ => self.push(intrinsics::intrinsic_div(vec![a, b])?)?,
                 }
-                OpCode::Mod => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Mod".into()))?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Mod".into()))?;
-                    match (&a, &b) {
-                        (Value::Integer(i1), Value::Integer(i2)) => {
-                            if *i2 == 0 {
-                                return Err(ArkError::DivisionByZero);
-                            }
-                            self.push(Value::Integer(i1 % i2))?
+            }
+            OpCode::Mod => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Mod".into()))?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Mod".into()))?;
+                match (&a, &b) {
+                    (Value::Integer(i1), Value::Integer(i2)) => {
+                        if *i2 == 0 {
+                            return Err(ArkError::DivisionByZero);
                         }
-                        _ => self.push(intrinsics::intrinsic_mod(vec![a, b])?)?,
+                        self.push(Value::Integer(i1 % i2))?
                     }
+                    _ => self.push(intrinsics::intrinsic_mod(vec![a, b])?)?,
                 }
+            }
 
-                OpCode::Eq => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Eq".into()))?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Eq".into()))?;
-                    match (&a, &b) {
-                        (Value::Integer(i1), Value::Integer(i2)) => {
-                            self.push(Value::Integer(if i1 == i2 { 1 } else { 0 }))?
-                        }
-                        (Value::Boolean(b1), Value::Boolean(b2)) => {
-                            self.push(Value::Integer(if b1 == b2 { 1 } else { 0 }))?
-                        }
-                        _ => self.push(intrinsics::intrinsic_eq(vec![a, b])?)?,
+            OpCode::Eq => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Eq".into()))?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Eq".into()))?;
+                match (&a, &b) {
+                    (Value::Integer(i1), Value::Integer(i2)) => {
+                        self.push(Value::Integer(if i1 == i2 { 1 } else { 0 }))?
                     }
+                    (Value::Boolean(b1), Value::Boolean(b2)) => {
+                        self.push(Value::Integer(if b1 == b2 { 1 } else { 0 }))?
+                    }
+                    _ => self.push(intrinsics::intrinsic_eq(vec![a, b])?)?,
                 }
-                OpCode::Neq => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Neq".into()))?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Neq".into()))?;
-                    match (&a, &b) {
-                        (Value::Integer(i1), Value::Integer(i2)) => {
-                            self.push(Value::Integer(if i1 != i2 { 1 } else { 0 }))?
-                        }
-                        _ => {
-                            let res = intrinsics::intrinsic_eq(vec![a, b])?;
-                            if let Value::Integer(i) = res {
-                                self.push(Value::Integer(if i == 0 { 1 } else { 0 }))?;
-                            } else if let Value::Boolean(b) = res {
-                                self.push(Value::Integer(if !b { 1 } else { 0 }))?;
-                            } else {
-                                self.push(Value::Integer(1))?; // Assume not eq
-                            }
+            }
+            OpCode::Neq => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Neq".into()))?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Neq".into()))?;
+                match (&a, &b) {
+                    (Value::Integer(i1), Value::Integer(i2)) => {
+                        self.push(Value::Integer(if i1 != i2 { 1 } else { 0 }))?
+                    }
+                    _ => {
+                        let res = intrinsics::intrinsic_eq(vec![a, b])?;
+                        if let Value::Integer(i) = res {
+                            self.push(Value::Integer(if i == 0 { 1 } else { 0 }))?;
+                        } else if let Value::Boolean(b) = res {
+                            self.push(Value::Integer(if !b { 1 } else { 0 }))?;
+                        } else {
+                            self.push(Value::Integer(1))?; // Assume not eq
                         }
                     }
                 }
-                OpCode::Gt => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Gt".into()))?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Gt".into()))?;
-                    match (&a, &b) {
-                        (Value::Integer(i1), Value::Integer(i2)) => {
-                            self.push(Value::Integer(if i1 > i2 { 1 } else { 0 }))?
-                        }
-                        _ => self.push(intrinsics::intrinsic_gt(vec![a, b])?)?,
+            }
+            OpCode::Gt => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Gt".into()))?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Gt".into()))?;
+                match (&a, &b) {
+                    (Value::Integer(i1), Value::Integer(i2)) => {
+                        self.push(Value::Integer(if i1 > i2 { 1 } else { 0 }))?
                     }
+                    _ => self.push(intrinsics::intrinsic_gt(vec![a, b])?)?,
                 }
-                OpCode::Lt => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Lt".into()))?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Lt".into()))?;
-                    match (&a, &b) {
-                        (Value::Integer(i1), Value::Integer(i2)) => {
-                            self.push(Value::Boolean(i1 < i2))?
-                        }
-                        _ => self.push(intrinsics::intrinsic_lt(vec![a, b])?)?,
+            }
+            OpCode::Lt => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Lt".into()))?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Lt".into()))?;
+                match (&a, &b) {
+                    (Value::Integer(i1), Value::Integer(i2)) => {
+                        self.push(Value::Boolean(i1 < i2))?
                     }
+                    _ => self.push(intrinsics::intrinsic_lt(vec![a, b])?)?,
                 }
-                OpCode::Ge => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Ge".into()))?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Ge".into()))?;
-                    match (&a, &b) {
-                        (Value::Integer(i1), Value::Integer(i2)) => {
-                            self.push(Value::Integer(if i1 >= i2 { 1 } else { 0 }))?
-                        }
-                        _ => self.push(intrinsics::intrinsic_ge(vec![a, b])?)?,
+            }
+            OpCode::Ge => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Ge".into()))?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Ge".into()))?;
+                match (&a, &b) {
+                    (Value::Integer(i1), Value::Integer(i2)) => {
+                        self.push(Value::Integer(if i1 >= i2 { 1 } else { 0 }))?
                     }
+                    _ => self.push(intrinsics::intrinsic_ge(vec![a, b])?)?,
                 }
-                OpCode::Le => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Le".into()))?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Le".into()))?;
-                    match (&a, &b) {
-                        (Value::Integer(i1), Value::Integer(i2)) => {
-                            self.push(Value::Integer(if i1 <= i2 { 1 } else { 0 }))?
-                        }
-                        _ => self.push(intrinsics::intrinsic_le(vec![a, b])?)?,
+            }
+            OpCode::Le => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Le".into()))?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Le".into()))?;
+                match (&a, &b) {
+                    (Value::Integer(i1), Value::Integer(i2)) => {
+                        self.push(Value::Integer(if i1 <= i2 { 1 } else { 0 }))?
                     }
+                    _ => self.push(intrinsics::intrinsic_le(vec![a, b])?)?,
                 }
+            }
 
-                OpCode::And => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("And".to_string()))?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("And".to_string()))?;
-                    let res = intrinsics::intrinsic_and(vec![a, b])?;
-                    self.push(res)?;
-                }
-                OpCode::Or => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Or".to_string()))?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Or".to_string()))?;
-                    let res = intrinsics::intrinsic_or(vec![a, b])?;
-                    self.push(res)?;
-                }
-                OpCode::Not => {
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Not".to_string()))?;
-                    let res = intrinsics::intrinsic_not(vec![a])?;
-                    self.push(res)?;
-                }
+            OpCode::And => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("And".to_string()))?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("And".to_string()))?;
+                let res = intrinsics::intrinsic_and(vec![a, b])?;
+                self.push(res)?;
+            }
+            OpCode::Or => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Or".to_string()))?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Or".to_string()))?;
+                let res = intrinsics::intrinsic_or(vec![a, b])?;
+                self.push(res)?;
+            }
+            OpCode::Not => {
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Not".to_string()))?;
+                let res = intrinsics::intrinsic_not(vec![a])?;
+                self.push(res)?;
+            }
 
-                OpCode::Print => {
-                    let val = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("Print".to_string()))?;
-                    #[cfg(not(test))]
-                    println!("{:?}", val);
-                    #[cfg(test)]
-                    let _ = val;
-                }
-                OpCode::Destructure => self.op_destructure()?,
+            OpCode::Print => {
+                let val = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Print".to_string()))?;
+                #[cfg(not(test))]
+                println!("{:?}", val);
+                #[cfg(test)]
+                let _ = val;
+            }
+            OpCode::Destructure => self.op_destructure()?,
 
-                OpCode::MakeList(size) => self.op_make_list(*size)?,
+            OpCode::MakeList(size) => self.op_make_list(*size)?,
 
-                OpCode::MakeStruct(size) => self.op_make_struct(*size)?,
+            OpCode::MakeStruct(size) => self.op_make_struct(*size)?,
 
-                OpCode::GetField(field) => {
-                    let obj = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("GetField".to_string()))?;
-                    if let Value::Struct(mut fields) = obj {
-                        if let Some(val) = fields.remove(field) {
-                            self.push(val)?;
-                        } else {
-                            return Err(ArkError::Generic(format!(
-                                "Field '{}' not found in struct",
-                                field
-                            )));
-                        }
+            OpCode::GetField(field) => {
+                let obj = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("GetField".to_string()))?;
+                if let Value::Struct(mut fields) = obj {
+                    if let Some(val) = fields.remove(field) {
+                        self.push(val)?;
                     } else {
                         return Err(ArkError::Generic(format!(
-                            "GetField expected Struct, got {:?}",
-                            obj
+                            "Field '{}' not found in struct",
+                            field
                         )));
                     }
+                } else {
+                    return Err(ArkError::Generic(format!(
+                        "GetField expected Struct, got {:?}",
+                        obj
+                    )));
                 }
+            }
 
-                OpCode::SetField(field) => {
-                    let obj = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("SetField".to_string()))?;
-                    let val = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("SetField".to_string()))?;
+            OpCode::SetField(field) => {
+                let obj = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("SetField".to_string()))?;
+                let val = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("SetField".to_string()))?;
+
+                if let Value::Struct(mut fields) = obj {
+                    fields.insert(field.clone(), val);
+                    self.push_new(Value::Struct(fields))?;
+                } else {
+                    return Err(ArkError::Generic(format!(
+                        "SetField expected Struct, got {:?}",
+                        obj
+                    )));
+                }
+            }
 
-                    if let Value::Struct(mut fields) = obj {
-                        fields.insert(field.clone(), val);
-                        self.push(Value::Struct(fields))?;
-                    } else {
-                        return Err(ArkError::Generic(format!(
-                            "SetField expected Struct, got {:?}",
-                            obj
-                        )));
-                    }
+            OpCode::Load(name) => {
+                let val = self
+                    .find_var(name)
+                    .ok_or_else(|| ArkError::Generic(format!("Variable not found: {}", name)))?;
+                self.push(val)?;
+            }
+            OpCode::Store(name) => {
+                let val = self
+                    .stack
+                    .last()
+                    .ok_or_else(|| ArkError::StackUnderflow("Store".to_string()))?
+                    .clone();
+                // Store in current scope
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.set(name.clone(), val);
                 }
+                self.stack.pop(); // Store consumes value? Let's say yes for now.
+            }
 
-                OpCode::Load(name) => {
-                    let val = self.find_var(name).ok_or_else(|| {
-                        ArkError::Generic(format!("Variable not found: {}", name))
-                    })?;
-                    self.push(val)?;
+            OpCode::Jmp(offset) => {
+                self.ip = *offset;
+            }
+            OpCode::JmpIfFalse(offset) => {
+                let val = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("JmpIfFalse".to_string()))?;
+                if !val.as_condition()? {
+                    self.ip = *offset;
                 }
-                OpCode::Store(name) => {
-                    let val = self
-                        .stack
-                        .last()
-                        .ok_or_else(|| ArkError::StackUnderflow("Store".to_string()))?
-                        .clone();
-                    // Store in current scope
-                    if let Some(scope) = self.scopes.last_mut() {
-                        scope.set(name.clone(), val);
-                    }
-                    self.stack.pop(); // Store consumes value? Let's say yes for now.
+            }
+
+            OpCode::Call(arg_count) => {
+                self.op_call(*arg_count)?;
+                if let Some(suspend) = self.suspended.take() {
+                    return Ok(Some(suspend));
                 }
+            }
 
-                OpCode::Jmp(offset) => {
-                    self.ip = *offset;
+            OpCode::Ret => {
+                if let Some(val) = self.op_return()? {
+                    return Ok(Some(Suspend::Done(val)));
                 }
-                OpCode::JmpIfFalse(offset) => {
+            }
+
+            OpCode::MakeEnum(enum_name, variant, field_count) => {
+                let mut fields = Vec::with_capacity(*field_count);
+                for _ in 0..*field_count {
                     let val = self
                         .stack
                         .pop()
-                        .ok_or_else(|| ArkError::StackUnderflow("JmpIfFalse".to_string()))?;
-                    let is_true = match val {
-                        Value::Boolean(b) => b,
-                        Value::Integer(i) => i != 0,
-                        // String truthiness consistency
-                        Value::String(s) => !s.is_empty(),
-                        _ => false,
-                    };
-                    if !is_true {
-                        self.ip = *offset;
-                    }
+                        .ok_or_else(|| ArkError::StackUnderflow("MakeEnum".to_string()))?;
+                    fields.push(val);
                 }
+                fields.reverse(); // Fields were pushed left-to-right
+                self.push_new(Value::EnumValue {
+                    enum_name: enum_name.clone(),
+                    variant: variant.clone(),
+                    fields,
+                })?;
+            }
 
-                OpCode::Call(arg_count) => self.op_call(*arg_count)?,
+            OpCode::SetupTry(catch_ip) => self.handlers.push(TryHandler {
+                catch_ip: *catch_ip,
+                chunk: self.chunk.clone(),
+                stack_len: self.stack.len(),
+                frame_depth: self.frames.len(),
+                scope_depth: self.scopes.len(),
+            }),
+            OpCode::PopTry => {
+                self.handlers.pop();
+            }
 
-                OpCode::Ret => {
-                    if let Some(val) = self.op_return()? {
-                        return Ok(val);
+            OpCode::Propagate => {
+                let val = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| ArkError::StackUnderflow("Propagate".to_string()))?;
+                match val.propagate()? {
+                    ControlFlow::Continue(payload) => self.push(payload)?,
+                    // Return the failure, as `return` would
+                    ControlFlow::Break(failure) => {
+                        self.push(failure)?;
+                        if let Some(val) = self.op_return()? {
+                            return Ok(Some(Suspend::Done(val)));
+                        }
                     }
                 }
+            }
+        }
+        Ok(None)
+    }
 
-                OpCode::MakeEnum(enum_name, variant, field_count) => {
-                    let mut fields = Vec::with_capacity(*field_count);
-                    for _ in 0..*field_count {
-                        let val = self
-                            .stack
-                            .pop()
-                            .ok_or_else(|| ArkError::StackUnderflow("MakeEnum".to_string()))?;
-                        fields.push(val);
-                    }
-                    fields.reverse(); // Fields were pushed left-to-right
-                    self.push(Value::EnumValue {
-                        enum_name: enum_name.clone(),
-                        variant: variant.clone(),
-                        fields,
-                    })?;
-                }
+    /// Resume at the innermost `catch` block with the error bound, or
+    /// hand the error back if nothing catches it.
+    fn catch_error(&mut self, err: ArkError) -> Result<(), ArkError> {
+        let trace = self.backtrace();
+        LAST_TRACE.with(|last| *last.borrow_mut() = trace);
+        let Some(value) = err.catch_value() else {
+            return Err(err);
+        };
+        let Some(handler) = self.handlers.pop() else {
+            return Err(err);
+        };
+        while self.frames.len() > handler.frame_depth {
+            if let Some(frame_idx) = self.frames.pop() {
+                self.heap.decref(frame_idx);
+            }
+            if let Some(profile) = &mut self.profile {
+                profile.exit();
+            }
+        }
+        self.scopes.truncate(handler.scope_depth);
+        self.stack.truncate(handler.stack_len);
+        self.chunk = handler.chunk;
+        self.ip = handler.catch_ip;
+        self.push(value)
+    }
+
+    /// The Ark-level call stack, innermost call first.
+    pub fn backtrace(&self) -> Vec<TraceFrame> {
+        let mut trace = vec![TraceFrame::of(&self.chunk)];
+        // Each frame holds the chunk its caller returns to
+        for &frame_idx in self.frames.iter().rev() {
+            if let Some(node) = self.heap.get(frame_idx) {
+                let GraphData::Frame(ref frame) = node.data;
+                trace.push(TraceFrame::of(&frame.chunk));
             }
         }
+        trace
     }
 
     #[inline]
@@ -617,7 +1318,14 @@ impl<'a> VM<'a> {
             self.heap.decref(frame_idx);
 
             self.scopes.pop();
-            self.push(result)?;
+            // A `return` inside `try` leaves its handler behind
+            while matches!(self.handlers.last(), Some(h) if h.frame_depth > self.frames.len()) {
+                self.handlers.pop();
+            }
+            if let Some(profile) = &mut self.profile {
+                profile.exit();
+            }
+            self.push_unprofiled(result)?;
             Ok(None) // Continue loop
         } else {
             // Return from main
@@ -633,6 +1341,8 @@ impl<'a> VM<'a> {
             .ok_or_else(|| ArkError::StackUnderflow("Call".to_string()))?;
         match func_val {
             Value::Function(chunk) => {
+                self.limits
+                    .check(Resource::CallDepth, self.call_depth() + 1)?;
                 // Allocation on Heap (Zero-Copy Ref Count)
                 let frame = CallFrame {
                     ip: self.ip,
@@ -640,6 +1350,13 @@ impl<'a> VM<'a> {
                 };
                 let frame_idx = self.heap.alloc(GraphData::Frame(frame));
                 self.frames.push(frame_idx);
+                if let Some(profile) = &mut self.profile {
+                    // A call from the host starts the run it happens in
+                    if profile.is_idle() {
+                        profile.enter(&TraceFrame::of(&self.chunk).function);
+                    }
+                    profile.enter(chunk.name.as_deref().unwrap_or("<lambda>"));
+                }
 
                 // Switch Context
                 self.chunk = chunk;
@@ -661,9 +1378,7 @@ impl<'a> VM<'a> {
                 // We need them in forward order [arg1, arg2]
                 args.reverse();
 
-                let result = func(args).map_err(ArkError::from)?;
-                self.push(result)?;
-                Ok(())
+                self.call_native(func, args)
             }
             _ => Err(ArkError::Generic(format!(
                 "Calling non-function value: {:?}",
@@ -672,6 +1387,57 @@ impl<'a> VM<'a> {
         }
     }
 
+    /// Call a native function and push its result, unless it asked the
+    /// task to suspend first.
+    fn call_native(&mut self, func: NativeFn, args: Vec<Value>) -> Result<(), ArkError> {
+        // Drop any request left by a call made outside the VM
+        take_task_request();
+        // VMs the call starts, like `sys.func.apply`'s, run above this one
+        let depth = CALL_DEPTH.with(|current| current.replace(self.call_depth() + 1));
+        let result = func(args);
+        CALL_DEPTH.with(|current| current.set(depth));
+        let result = result.map_err(ArkError::from)?;
+        // Whatever a native call returns is new to the program
+        self.limits.check_allocation(&result, &self.allocated)?;
+        if debugger::break_requested() {
+            if let Some(recording) = &mut self.recording {
+                recording.mark_break();
+            }
+            // Nobody to pause for
+            if self.debug_hook.is_none() {
+                debugger::take_break_request();
+            }
+        }
+        match take_task_request() {
+            None => self.push(result)?,
+            Some(TaskRequest::Yield) => {
+                self.push(result)?;
+                self.suspended = Some(Suspend::Yield);
+            }
+            Some(TaskRequest::Sleep(until)) => {
+                self.push(result)?;
+                self.suspended = Some(Suspend::Sleep(until));
+            }
+            Some(TaskRequest::Retry(func, args)) => {
+                self.pending_call = Some((func, args));
+                self.suspended = Some(Suspend::Sleep(Instant::now() + TASK_RETRY_INTERVAL));
+            }
+        }
+        Ok(())
+    }
+
+    /// Calls in progress on the thread, this VM's and those below it.
+    fn call_depth(&self) -> usize {
+        self.depth_base + self.frames.len()
+    }
+
+    /// Push a value the program has just made, adding its elements to
+    /// the run's total.
+    fn push_new(&mut self, val: Value) -> Result<(), ArkError> {
+        self.limits.check_allocation(&val, &self.allocated)?;
+        self.push(val)
+    }
+
     #[inline]
     fn op_make_list(&mut self, size: usize) -> Result<(), ArkError> {
         let mut items = Vec::new();
@@ -683,7 +1449,7 @@ impl<'a> VM<'a> {
             );
         }
         items.reverse();
-        self.push(Value::List(items))?;
+        self.push_new(Value::List(items))?;
         Ok(())
     }
 
@@ -709,7 +1475,7 @@ impl<'a> VM<'a> {
                 )));
             }
         }
-        self.push(Value::Struct(fields))?;
+        self.push_new(Value::Struct(fields))?;
         Ok(())
     }
 
@@ -912,6 +1678,633 @@ mod tests {
         assert_eq!(result, Value::Integer(100));
     }
 
+    fn run_source(source: &str) -> Result<Value, ArkError> {
+        let ast = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+        let chunk = crate::compiler::Compiler::new().compile(&ast);
+        VM::new(chunk, "HASH", 0).expect("vm init failed").run()
+    }
+
+    #[test]
+    fn test_catch_unwinds_calls_and_binds_error() {
+        let source = r#"
+func risky(n) {
+    return 10 / n
+}
+result := 0
+try {
+    result := risky(0)
+} catch err {
+    result := err.code
+}
+result
+"#;
+        assert_eq!(
+            run_source(source).expect("run failed"),
+            Value::String("DivisionByZero".to_string())
+        );
+    }
+
+    #[test]
+    fn test_return_inside_try_drops_its_handler() {
+        let source = r#"
+func early() {
+    try {
+        return 1
+    } catch err {
+        return 2
+    }
+}
+x := early()
+y := 1 / 0
+"#;
+        assert!(matches!(run_source(source), Err(ArkError::DivisionByZero)));
+    }
+
+    #[test]
+    fn test_propagate_returns_failure_from_the_calling_function() {
+        let source = r#"
+func half(n) {
+    if n % 2 == 1 {
+        return Err("odd")
+    }
+    return Ok(n / 2)
+}
+func quarter(n) {
+    h := half(n)?
+    return Ok(half(h)?)
+}
+[quarter(8), quarter(6)]
+"#;
+        assert_eq!(
+            run_source(source).expect("run failed"),
+            Value::List(vec![
+                Value::ok(Value::Integer(2)),
+                Value::err(Value::String("odd".to_string())),
+            ])
+        );
+        assert!(matches!(
+            run_source("x := 5?"),
+            Err(ArkError::Runtime(RuntimeError::TypeMismatch(_, _)))
+        ));
+    }
+
+    #[test]
+    fn test_uncaught_error_records_backtrace_with_spans() {
+        let source = r#"
+func inner(n) {
+    return 10 / n
+}
+func outer(n) {
+    return inner(n) + 1
+}
+outer(0)
+"#;
+        let ast =
+            crate::parser::parse_source_with_spans(source, "trace.ark").expect("parse failed");
+        let chunk = crate::compiler::Compiler::new().compile(&ast);
+        let result = VM::new(chunk, "HASH", 0).expect("vm init failed").run();
+        assert!(matches!(result, Err(ArkError::DivisionByZero)));
+
+        let trace = last_error_trace();
+        let names: Vec<&str> = trace.iter().map(|f| f.function.as_str()).collect();
+        assert_eq!(names, vec!["inner", "outer", "<main>"]);
+        let span = trace[0].span.as_ref().expect("inner has a span");
+        assert_eq!(span.file, "trace.ark");
+        assert!(span.start_line >= 2);
+        assert!(trace[2].span.is_none());
+        assert!(
+            format_backtrace(&trace)
+                .starts_with("Backtrace (most recent call first):\n    at inner (trace.ark:")
+        );
+    }
+
+    fn run_deterministic(source: &str, seed: u64) -> Result<Value, ArkError> {
+        let ast = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+        let chunk = crate::compiler::Compiler::new().compile(&ast);
+        VM::with_config(chunk, "HASH", VmConfig::deterministic(seed))?.run()
+    }
+
+    #[test]
+    fn test_deterministic_runs_replay_exactly() {
+        let source = r#"
+start := sys.time.now()
+sys.time.sleep(250)
+[sys.random.int(0, 1000000), sys.random.int(0, 1000000), sys.time.now() - start, start]
+"#;
+        let first = run_deterministic(source, 42).expect("run failed");
+        assert_eq!(first, run_deterministic(source, 42).expect("run failed"));
+        let Value::List(items) = &first else {
+            panic!("expected a list, got {:?}", first);
+        };
+        assert_eq!(items[2], Value::Integer(250));
+        assert_eq!(items[3], Value::Integer(0));
+        assert_ne!(first, run_deterministic(source, 43).expect("run failed"));
+
+        // The host's clock is back once the run is over
+        let now = run_source("sys.time.now()").expect("run failed");
+        assert!(matches!(now, Value::Integer(ms) if ms > 0));
+    }
+
+    #[test]
+    fn test_deterministic_mode_rejects_host_calls() {
+        let source = r#"
+func fetch(url) {
+    return sys.net.http.request("GET", url)
+}
+1
+"#;
+        assert!(matches!(
+            run_deterministic(source, 1),
+            Err(ArkError::Runtime(RuntimeError::NonDeterministic(name)))
+                if name == "sys.net.http.request"
+        ));
+        assert!(matches!(
+            run_deterministic("sys.thread.spawn(1)", 1),
+            Err(ArkError::Runtime(RuntimeError::NonDeterministic(_)))
+        ));
+        assert!(!RuntimeError::NonDeterministic("sys.fs.read".into()).is_catchable());
+
+        // A name computed at run time is checked when it is called
+        let source = r#"
+name := "sys.fs." + "read"
+sys.func.apply(name, ["/etc/hostname"])
+"#;
+        assert!(matches!(
+            run_deterministic(source, 1),
+            Err(ArkError::Runtime(RuntimeError::NonDeterministic(name)))
+                if name == "sys.fs.read"
+        ));
+        let outside = run_source(r#"sys.func.apply("sys.type_of", [1])"#);
+        assert!(outside.is_ok(), "{:?}", outside);
+        assert!(intrinsics::IntrinsicRegistry::dispatch("sys.fs.read").is_ok());
+    }
+
+    #[test]
+    fn test_gas_limit_stops_runaway_programs() {
+        let run = |source: &str, gas_limit| {
+            let ast = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+            let chunk = crate::compiler::Compiler::new().compile(&ast);
+            let config = VmConfig {
+                gas_limit: Some(gas_limit),
+                ..VmConfig::default()
+            };
+            VM::with_config(chunk, "HASH", config)
+                .expect("vm init failed")
+                .run()
+        };
+        assert_eq!(run("1 + 2", 1_000).expect("run failed"), Value::Integer(3));
+
+        let spin = r#"
+n := 0
+try {
+    while true {
+        n := n + 1
+    }
+} catch err {
+    n := -1
+}
+"#;
+        let result = run(spin, 5_000);
+        assert!(matches!(
+            result,
+            Err(ArkError::Runtime(RuntimeError::OutOfGas))
+        ));
+        assert!(!RuntimeError::OutOfGas.is_catchable());
+    }
+
+    #[test]
+    fn test_gas_is_shared_with_tasks_and_nested_vms() {
+        let run = |source: &str, gas_limit| {
+            let ast = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+            let chunk = crate::compiler::Compiler::new().compile(&ast);
+            let config = VmConfig {
+                gas_limit: Some(gas_limit),
+                ..VmConfig::default()
+            };
+            VM::with_config(chunk, "HASH", config)
+                .expect("vm init failed")
+                .run()
+        };
+        // Each call is well within the allowance; all of them together
+        // are not
+        let work = r#"
+func work() {
+    i := 0
+    while i < 200 {
+        i := i + 1
+    }
+    return i
+}
+"#;
+        let spawn_loop = format!(
+            "{}k := 0\nwhile k < 20 {{\n    sys.task.spawn(work)\n    k := k + 1\n}}\n\
+             sys.task.yield()\nk",
+            work
+        );
+        let apply_loop = format!(
+            "{}total := 0\nk := 0\nwhile k < 20 {{\n    \
+             total := total + sys.func.apply(work, [])\n    k := k + 1\n}}\ntotal",
+            work
+        );
+        for (source, expected) in [(spawn_loop, 20), (apply_loop, 4_000)] {
+            assert_eq!(
+                run(&source, 1_000_000).expect("run failed"),
+                Value::Integer(expected)
+            );
+            let result = run(&source, 5_000);
+            assert!(
+                matches!(result, Err(ArkError::Runtime(RuntimeError::OutOfGas))),
+                "{:?}",
+                result
+            );
+        }
+        assert!(gas().is_none());
+    }
+
+    #[test]
+    fn test_resource_limits_stop_the_program() {
+        let run = |source: &str| {
+            let ast = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+            let chunk = crate::compiler::Compiler::new().compile(&ast);
+            let config = VmConfig {
+                limits: Some(ResourceLimits {
+                    max_call_depth: Some(50),
+                    max_values: Some(4),
+                    max_buffer_bytes: Some(16),
+                    ..ResourceLimits::default()
+                }),
+                ..VmConfig::default()
+            };
+            VM::with_config(chunk, "HASH", config)
+                .expect("vm init failed")
+                .run()
+        };
+        let within = r#"
+func build(n) {
+    if n == 0 {
+        return [1, 2, 3, 4]
+    }
+    return build(n - 1)
+}
+build(40)
+"#;
+        assert_eq!(
+            run(within).expect("run failed"),
+            Value::List((1..=4).map(Value::Integer).collect())
+        );
+
+        let deep = r#"
+func down(n) {
+    return down(n + 1)
+}
+down(0)
+"#;
+        assert!(matches!(
+            run(deep),
+            Err(ArkError::Runtime(RuntimeError::LimitExceeded {
+                resource: Resource::CallDepth,
+                limit: 50,
+            }))
+        ));
+        let wide = r#"
+r := 0
+try {
+    r := [1, 2, 3, 4, 5]
+} catch err {
+    r := err.code
+}
+r
+"#;
+        assert!(matches!(
+            run(wide),
+            Err(ArkError::Runtime(RuntimeError::LimitExceeded {
+                resource: Resource::Values,
+                limit: 4,
+            }))
+        ));
+        assert!(matches!(
+            run("sys.mem.alloc(64)"),
+            Err(ArkError::Runtime(RuntimeError::LimitExceeded {
+                resource: Resource::BufferBytes,
+                limit: 16,
+            }))
+        ));
+        assert_eq!(intrinsics::resource_limits(), ResourceLimits::default());
+    }
+
+    #[test]
+    fn test_allocation_budget_and_call_depth_span_nested_vms() {
+        let run = |source: &str| {
+            let ast = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+            let chunk = crate::compiler::Compiler::new().compile(&ast);
+            let config = VmConfig {
+                limits: Some(ResourceLimits {
+                    max_call_depth: Some(50),
+                    max_values: Some(4),
+                    max_allocations: Some(100),
+                    ..ResourceLimits::default()
+                }),
+                ..VmConfig::default()
+            };
+            VM::with_config(chunk, "HASH", config)
+                .expect("vm init failed")
+                .run()
+        };
+        // Only one small list is alive at a time, but every one of them is
+        // spent from the budget
+        let lists = |count: i64| {
+            format!(
+                "k := 0\nwhile k < {} {{\n    pair := [k, k]\n    k := k + 1\n}}\nk",
+                count
+            )
+        };
+        assert_eq!(run(&lists(20)).expect("run failed"), Value::Integer(20));
+        assert!(matches!(
+            run(&lists(200)),
+            Err(ArkError::Runtime(RuntimeError::LimitExceeded {
+                resource: Resource::Allocations,
+                limit: 100,
+            }))
+        ));
+
+        let apply = |stop: i64| {
+            format!(
+                "func down(f, n) {{\n    if n == {} {{\n        return n\n    }}\n    \
+                 return sys.func.apply(f, [f, n + 1])\n}}\ndown(down, 0)",
+                stop
+            )
+        };
+        assert_eq!(run(&apply(5)).expect("run failed"), Value::Integer(5));
+        assert!(matches!(
+            run(&apply(1_000)),
+            Err(ArkError::Runtime(RuntimeError::LimitExceeded {
+                resource: Resource::CallDepth,
+                limit: 50,
+            }))
+        ));
+        assert!(intrinsics::values_allocated().is_none());
+        assert_eq!(CALL_DEPTH.with(Cell::get), 0);
+    }
+
+    #[test]
+    fn test_profile_counts_calls_time_and_allocations() {
+        let source = r#"
+func fib(n) {
+    if n < 2 {
+        return n
+    }
+    return fib(n - 1) + fib(n - 2)
+}
+func words() {
+    return ["a", "b"]
+}
+func risky() {
+    return 1 / 0
+}
+try {
+    risky()
+} catch err {
+}
+words()
+fib(5)
+"#;
+        let ast = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+        let chunk = crate::compiler::Compiler::new().compile(&ast);
+        let config = VmConfig {
+            profile: true,
+            ..VmConfig::default()
+        };
+        let mut vm = VM::with_config(chunk, "HASH", config).expect("vm init failed");
+        assert_eq!(vm.run().expect("run failed"), Value::Integer(5));
+
+        let profile = vm.profile().expect("profiling was on");
+        assert!(profile.is_idle());
+        let functions: HashMap<&str, &crate::profiler::FunctionStats> =
+            profile.functions().into_iter().collect();
+        assert_eq!(functions["fib"].calls, 15);
+        assert_eq!(functions["risky"].calls, 1);
+        assert_eq!(functions["<main>"].calls, 1);
+        assert!(functions["words"].allocations >= 3);
+        assert_eq!(functions["fib"].allocations, 0);
+        assert!(functions["fib"].inclusive <= functions["<main>"].inclusive);
+
+        let folded = profile.folded();
+        assert!(folded.contains("<main>;fib;fib;fib;fib;fib "), "{}", folded);
+        assert!(folded.contains("<main>;risky "), "{}", folded);
+        assert!(!folded.contains("<main>;risky;"), "{}", folded);
+
+        let unprofiled = VM::new(Chunk::new(), "HASH", 0).expect("vm init failed");
+        assert!(unprofiled.profile().is_none());
+    }
+
+    #[test]
+    fn test_recorded_trace_ends_where_the_run_failed() {
+        let source = r#"
+func half(n) {
+    sys.debug.break()
+    return 10 / n
+}
+half(5)
+half(0)
+"#;
+        let record = |capacity| {
+            let ast = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+            let chunk = crate::compiler::Compiler::new().compile(&ast);
+            let config = VmConfig {
+                record_trace: Some(capacity),
+                ..VmConfig::default()
+            };
+            let mut vm = VM::with_config(chunk, "HASH", config).expect("vm init failed");
+            assert!(vm.run().is_err());
+            vm.execution_trace().expect("recording was on").clone()
+        };
+
+        let trace = record(10_000);
+        assert_eq!(trace.dropped(), 0);
+        let entries: Vec<&TraceEntry> = trace.entries().collect();
+        let last = entries.last().expect("instructions were recorded");
+        assert_eq!(last.op, "Div");
+        assert_eq!(last.operands, ["10", "0"]);
+        assert_eq!((last.function.as_str(), last.depth), ("half", 1));
+        assert!(last.error.is_some());
+        let breaks: Vec<&str> = entries
+            .iter()
+            .filter(|e| e.breakpoint)
+            .map(|e| e.op.as_str())
+            .collect();
+        assert_eq!(breaks, ["Call(0)", "Call(0)"]);
+        assert!(!debugger::break_requested());
+
+        // The same program records the same trace
+        assert_eq!(record(10_000).to_json_lines(), trace.to_json_lines());
+        let tail = record(3);
+        assert_eq!(tail.len(), 3);
+        assert_eq!(tail.dropped(), trace.len() as u64 - 3);
+        assert_eq!(tail.entries().last(), trace.entries().last());
+
+        let untraced = VM::new(Chunk::new(), "HASH", 0).expect("vm init failed");
+        assert!(untraced.execution_trace().is_none());
+    }
+
+    #[test]
+    fn test_capabilities_apply_while_the_program_runs() {
+        let source = r#"
+r := "unread"
+try {
+    r := sys.fs.read("Cargo.toml")
+} catch err {
+    r := err.code
+}
+r
+"#;
+        let ast = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+        let chunk = crate::compiler::Compiler::new().compile(&ast);
+        let config = VmConfig {
+            capabilities: Some(Capabilities::none()),
+            ..VmConfig::default()
+        };
+        let result = VM::with_config(chunk, "HASH", config)
+            .expect("vm init failed")
+            .run()
+            .expect("run failed");
+        assert_eq!(result, Value::String("NotExecutable".to_string()));
+        assert_eq!(intrinsics::capabilities(), Capabilities::host_default());
+        assert!(matches!(run_source(source), Ok(Value::String(s)) if s.contains("[package]")));
+    }
+
+    /// Everything sent so far on the `sys.chan` channel `chan`.
+    fn drain_channel(chan: &Value) -> Vec<Value> {
+        let mut received = Vec::new();
+        loop {
+            match intrinsics::intrinsic_chan_try_recv(vec![chan.clone()]) {
+                Ok(Value::List(pair)) if pair[0] == Value::Boolean(true) => {
+                    received.push(pair[1].clone())
+                }
+                _ => return received,
+            }
+        }
+    }
+
+    /// Emit `native(args...)` with the result discarded.
+    fn emit_native_call(chunk: &mut Chunk, native: NativeFn, args: Vec<OpCode>) {
+        let arg_count = args.len();
+        for arg in args {
+            chunk.write(arg);
+        }
+        chunk.write(OpCode::Push(Value::NativeFunction(native)));
+        chunk.write(OpCode::Call(arg_count));
+        chunk.write(OpCode::Pop);
+    }
+
+    fn emit_send(chunk: &mut Chunk, message: &str) {
+        emit_native_call(
+            chunk,
+            intrinsics::intrinsic_chan_send,
+            vec![
+                OpCode::Load("ch".to_string()),
+                OpCode::Push(Value::String(message.to_string())),
+            ],
+        );
+    }
+
+    fn emit_spawn(chunk: &mut Chunk, task: Chunk, chan: &Value) {
+        emit_native_call(
+            chunk,
+            intrinsics::intrinsic_task_spawn,
+            vec![
+                OpCode::Push(Value::Function(Arc::new(task))),
+                OpCode::Push(chan.clone()),
+                OpCode::MakeList(1),
+            ],
+        );
+    }
+
+    fn strings(items: &[&str]) -> Vec<Value> {
+        items.iter().map(|s| Value::String(s.to_string())).collect()
+    }
+
+    #[test]
+    fn test_tasks_interleave_at_yields() {
+        let chan = intrinsics::intrinsic_chan_new(vec![Value::Integer(16)]).expect("chan");
+
+        let mut task = Chunk::new();
+        task.write(OpCode::Store("ch".to_string()));
+        emit_send(&mut task, "t1");
+        emit_native_call(&mut task, intrinsics::intrinsic_task_yield, vec![]);
+        emit_send(&mut task, "t2");
+        task.write(OpCode::Ret);
+
+        let mut main = Chunk::new();
+        main.write(OpCode::Push(chan.clone()));
+        main.write(OpCode::Store("ch".to_string()));
+        emit_spawn(&mut main, task, &chan);
+        emit_send(&mut main, "m1");
+        emit_native_call(&mut main, intrinsics::intrinsic_task_yield, vec![]);
+        emit_send(&mut main, "m2");
+        emit_native_call(&mut main, intrinsics::intrinsic_task_yield, vec![]);
+        emit_send(&mut main, "m3");
+        main.write(OpCode::Push(Value::Integer(7)));
+        main.write(OpCode::Ret);
+
+        let mut vm = VM::new(main, "HASH", 0).expect("operation failed");
+        assert_eq!(vm.run().expect("run failed"), Value::Integer(7));
+        assert_eq!(
+            drain_channel(&chan),
+            strings(&["m1", "t1", "m2", "t2", "m3"])
+        );
+    }
+
+    #[test]
+    fn test_sleeping_task_lets_others_run_and_run_waits_for_it() {
+        let chan = intrinsics::intrinsic_chan_new(vec![Value::Integer(16)]).expect("chan");
+
+        let mut sleeper = Chunk::new();
+        sleeper.write(OpCode::Store("ch".to_string()));
+        emit_native_call(
+            &mut sleeper,
+            intrinsics::intrinsic_task_sleep,
+            vec![OpCode::Push(Value::Integer(30))],
+        );
+        emit_send(&mut sleeper, "slept");
+        sleeper.write(OpCode::Ret);
+
+        let mut quick = Chunk::new();
+        quick.write(OpCode::Store("ch".to_string()));
+        emit_send(&mut quick, "quick");
+        quick.write(OpCode::Ret);
+
+        let mut main = Chunk::new();
+        emit_spawn(&mut main, sleeper, &chan);
+        emit_spawn(&mut main, quick, &chan);
+        main.write(OpCode::Ret);
+
+        let started = Instant::now();
+        let mut vm = VM::new(main, "HASH", 0).expect("operation failed");
+        vm.run().expect("run failed");
+        assert!(started.elapsed() >= Duration::from_millis(30));
+        assert_eq!(drain_channel(&chan), strings(&["quick", "slept"]));
+    }
+
+    #[test]
+    fn test_blocked_native_call_is_retried() {
+        static CALLS: AtomicI64 = AtomicI64::new(0);
+        fn flaky(args: Vec<Value>) -> Result<Value, RuntimeError> {
+            let calls = CALLS.fetch_add(1, Ordering::SeqCst) + 1;
+            if calls < 3 {
+                request_suspend(TaskRequest::Retry(flaky, args));
+                return Ok(Value::Unit);
+            }
+            Ok(Value::Integer(calls))
+        }
+
+        let mut chunk = Chunk::new();
+        chunk.write(OpCode::Push(Value::NativeFunction(flaky)));
+        chunk.write(OpCode::Call(0));
+        chunk.write(OpCode::Ret);
+        let mut vm = VM::new(chunk, "HASH", 0).expect("operation failed");
+        assert_eq!(vm.run().expect("run failed"), Value::Integer(3));
+    }
+
     #[cfg(test)]
     mod hardening_tests {
         use super::*;
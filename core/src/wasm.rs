@@ -458,6 +458,11 @@ fn value_to_json(v: &Value) -> serde_json::Value {
                 .collect();
             serde_json::Value::Object(map)
         }
+        // Decimal text, as a JSON number would lose precision
+        Value::BigInt(n) => serde_json::Value::String(n.to_string()),
+        Value::Decimal(d) => serde_json::Value::String(d.to_string()),
+        // The same fields as the `math.Tensor` struct form
+        Value::Tensor(t) => serde_json::json!({ "data": t.to_vec(), "shape": t.shape() }),
         _ => serde_json::Value::String(format!("{:?}", v)),
     }
 }
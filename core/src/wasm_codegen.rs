@@ -13,14 +13,22 @@
  * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
  */
 
-use crate::ast::{ArkNode, Expression, FunctionDef, MastNode, Pattern, Statement};
+use crate::ast::{
+    calculate_hash, walk_expression, walk_function, walk_statement, ArkNode, EnumDecl,
+    Expression, FunctionDef, MastNode, Pattern, Span, Statement, Visitor,
+};
+use crate::checker::LinearChecker;
 use crate::compiler::optimize;
-use std::collections::HashMap;
+use crate::diagnostics::Diagnostic;
+use crate::types::ArkType;
+use crate::wit_gen::{to_wit_ident, WitInterface};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use wasm_encoder::{
-    BlockType, CodeSection, ElementSection, ExportKind, ExportSection, Function, FunctionSection,
-    GlobalSection, GlobalType, ImportSection, Instruction, MemorySection, MemoryType, Module,
-    TableSection, TableType, TypeSection, ValType,
+    BlockType, CodeSection, ElementSection, Encode, ExportKind, ExportSection, Function,
+    FunctionSection, GlobalSection, GlobalType, ImportSection, Instruction, MemorySection,
+    MemoryType, Module, TableSection, TableType, TypeSection, ValType,
 };
 
 // =============================================================================
@@ -31,6 +39,25 @@ use wasm_encoder::{
 pub struct WasmCompileError {
     pub message: String,
     pub context: String,
+    /// The function being compiled, if it was parsed with spans.
+    pub span: Option<Span>,
+}
+
+impl WasmCompileError {
+    /// Attribute an error raised while compiling a function's body to that
+    /// function, unless it already has a location.
+    fn within(mut self, span: &Option<Span>) -> Self {
+        if self.span.is_none() {
+            self.span = span.clone();
+        }
+        self
+    }
+
+    /// This error as a shared [`Diagnostic`].
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic::error("E0301", format!("{} (in {})", self.message, self.context))
+            .with_span(self.span.clone())
+    }
 }
 
 impl fmt::Display for WasmCompileError {
@@ -41,6 +68,23 @@ impl fmt::Display for WasmCompileError {
 
 impl std::error::Error for WasmCompileError {}
 
+// =============================================================================
+// Import Registry
+// =============================================================================
+
+/// Key prefix under which imported functions are published in the function
+/// index map. The colon cannot appear in an Ark identifier, so these entries
+/// never shadow (or get shadowed by) user functions.
+const IMPORT_KEY_PREFIX: &str = "import:";
+
+/// One entry of the module's import section.
+#[derive(Debug, Clone)]
+struct WasmImport {
+    module: String,
+    name: String,
+    type_idx: u32,
+}
+
 // =============================================================================
 // WASM Value Representation
 // =============================================================================
@@ -50,73 +94,885 @@ impl std::error::Error for WasmCompileError {}
 /// - Integers: raw i64 value
 /// - Booleans: 0 or 1 as i64
 /// - Strings: pointer to linear memory (upper 32 bits = ptr, lower 32 = len)
+/// - Floats: the IEEE 754 f64 bit pattern
 /// - Unit: 0i64
 ///
+/// A float carries no runtime tag, so integers keep the full i64 range.
+/// Instead the compiler tracks statically which values are floats (float
+/// literals, `Float` parameters and results, float arithmetic and the math
+/// intrinsics) and selects f64 opcodes for them. An integer meeting a float
+/// is converted; any value of unknown type meeting a float is taken to be a
+/// float already.
+///
 /// For the initial backend, we focus on Integer + Boolean + Unit (i64-only),
 /// which covers arithmetic, control flow, and function calls. String/List/Struct
 /// support uses linear memory and will be added incrementally.
-// WASI type indices (assigned in register_wasi_imports, must match push order)
-const WASI_FD_WRITE_TYPE_IDX: u32 = 0; // (i32,i32,i32,i32)->i32  (fd_write + fd_read)
-const WASI_CLOCK_TIME_GET_TYPE_IDX: u32 = 1; // (i32,i64,i32)->i32
-const WASI_RANDOM_GET_TYPE_IDX: u32 = 2; // (i32,i32)->i32           (random_get, args_*, environ_*)
-const WASI_PROC_EXIT_TYPE_IDX: u32 = 3; // (i32)->()
-const WASI_PATH_OPEN_TYPE_IDX: u32 = 4; // (i32,i32,i32,i32,i32,i64,i64,i32,i32)->i32
-const WASI_FD_CLOSE_TYPE_IDX: u32 = 5; // (i32)->i32
-
-// Import function indices (0..10)
-#[allow(dead_code)]
-const WASI_FD_WRITE_FUNC_IDX: u32 = 0;
-#[allow(dead_code)]
-const WASI_FD_READ_FUNC_IDX: u32 = 1;
-#[allow(dead_code)]
-const WASI_CLOCK_TIME_GET_FUNC_IDX: u32 = 2;
-#[allow(dead_code)]
-const WASI_RANDOM_GET_FUNC_IDX: u32 = 3;
-#[allow(dead_code)]
-const WASI_ARGS_GET_FUNC_IDX: u32 = 4;
-#[allow(dead_code)]
-const WASI_ARGS_SIZES_GET_FUNC_IDX: u32 = 5;
-#[allow(dead_code)]
-const WASI_ENVIRON_GET_FUNC_IDX: u32 = 6;
-#[allow(dead_code)]
-const WASI_ENVIRON_SIZES_GET_FUNC_IDX: u32 = 7;
-#[allow(dead_code)]
-const WASI_PROC_EXIT_FUNC_IDX: u32 = 8;
-#[allow(dead_code)]
-const WASI_PATH_OPEN_FUNC_IDX: u32 = 9;
-#[allow(dead_code)]
-const WASI_FD_CLOSE_FUNC_IDX: u32 = 10;
-#[allow(dead_code)]
-const WASI_IMPORT_COUNT: u32 = 11;
-
-// Ark Host type indices (extend after WASI types, pushed in register_wasi_imports)
-const ARK_HOST_UNARY_I64_TYPE_IDX: u32 = 6; // (i64) -> i64  (math unary)
-const ARK_HOST_BINARY_I64_TYPE_IDX: u32 = 7; // (i64,i64) -> i64  (math binary)
-const ARK_HOST_TERNARY_I64_TYPE_IDX: u32 = 8; // (i64,i64,i64) -> i64  (pow_mod)
-const ARK_HOST_MEM_3I32_TYPE_IDX: u32 = 9; // (i32,i32,i32) -> i32  (crypto_sha512, json_parse, json_stringify)
-const ARK_HOST_MEM_4I32_TYPE_IDX: u32 = 10; // (i32,i32,i32,i32) -> i32  (ask_ai)
-
-// Ark Host function indices (offset from WASI imports)
-const ARK_HOST_MATH_SIN_FUNC_IDX: u32 = 11;
-const ARK_HOST_MATH_COS_FUNC_IDX: u32 = 12;
-const ARK_HOST_MATH_TAN_FUNC_IDX: u32 = 13;
-const ARK_HOST_MATH_ASIN_FUNC_IDX: u32 = 14;
-const ARK_HOST_MATH_ACOS_FUNC_IDX: u32 = 15;
-const ARK_HOST_MATH_ATAN_FUNC_IDX: u32 = 16;
-const ARK_HOST_MATH_ATAN2_FUNC_IDX: u32 = 17;
-const ARK_HOST_MATH_SQRT_FUNC_IDX: u32 = 18;
-const ARK_HOST_MATH_POW_FUNC_IDX: u32 = 19;
-const ARK_HOST_MATH_POW_MOD_FUNC_IDX: u32 = 20;
-const ARK_HOST_CRYPTO_SHA512_FUNC_IDX: u32 = 21;
-const ARK_HOST_JSON_PARSE_FUNC_IDX: u32 = 22;
-const ARK_HOST_JSON_STRINGIFY_FUNC_IDX: u32 = 23;
-const ARK_HOST_ASK_AI_FUNC_IDX: u32 = 24;
-
-const ARK_HOST_IMPORT_COUNT: u32 = 14;
-const TOTAL_IMPORT_COUNT: u32 = WASI_IMPORT_COUNT + ARK_HOST_IMPORT_COUNT; // 25
+/// Optimizer level applied before lowering (recorded in `ark.build`).
+const OPT_LEVEL: u8 = 2;
+
+/// Name of the custom section holding build provenance.
+pub const BUILD_INFO_SECTION: &str = "ark.build";
+
+/// Exported global holding a metered module's remaining fuel (see
+/// [`WasmCodegenOptions::fuel`]).
+pub const FUEL_GLOBAL: &str = "__fuel";
+
+/// `ark_host` math imports: (import name, arity, intrinsic names lowering to
+/// it in `compile_expr`). An import is only emitted when one of its
+/// intrinsics is called somewhere in the program.
+const MATH_HOST_IMPORTS: &[(&str, usize, &[&str])] = &[
+    ("math_sin", 1, &["intrinsic_math_sin", "math.sin"]),
+    ("math_cos", 1, &["intrinsic_math_cos", "math.cos"]),
+    ("math_tan", 1, &["intrinsic_math_tan", "math.tan"]),
+    ("math_asin", 1, &["intrinsic_math_asin", "math.asin"]),
+    ("math_acos", 1, &["intrinsic_math_acos", "math.acos"]),
+    ("math_atan", 1, &["intrinsic_math_atan", "math.atan"]),
+    ("math_atan2", 2, &["intrinsic_math_atan2", "math.atan2"]),
+    ("math_sqrt", 1, &["intrinsic_math_sqrt", "math.sqrt"]),
+    ("math_pow", 2, &["intrinsic_math_pow", "math.pow"]),
+    (
+        "math_pow_mod",
+        3,
+        &["intrinsic_pow_mod", "math.pow_mod", "sys.math.pow_mod"],
+    ),
+];
 
 const STRING_MEMORY_START: i32 = 1024; // strings start at byte 1024 in linear memory
 
+/// The most 64 KiB pages a wasm32 memory can have (4 GiB)
+const WASM32_MAX_PAGES: u32 = 65536;
+
+// Allocator free-list heads: one i32 per size class just past the scratch
+// bytes 0–63, for payloads of 8..=FREE_LIST_MAX_SMALL bytes in 8-byte steps,
+// then a single first-fit list for anything larger.
+const FREE_LIST_BASE: i32 = 64;
+const FREE_LIST_MAX_SMALL: i64 = 256;
+const FREE_LIST_LARGE: i32 = FREE_LIST_BASE + 4 * (FREE_LIST_MAX_SMALL as i32 / 8);
+
+// Return area for the WASI 0.2 calls made by the Preview1-shaped shims (a
+// write result or a wall-clock datetime, 16 bytes at most), past the
+// free-list heads and clear of the scratch bytes their callers fill.
+const WASI_P2_RET_AREA: i32 = 256;
+
+/// `sys.log` levels, lowest first; the same names the interpreter accepts.
+const LOG_LEVELS: [&str; 4] = ["debug", "info", "warn", "error"];
+
+// Heap objects (lists, structs, closures) carry an i64 type tag in the word
+// just before their header: [tag @ ptr-8][len/field_count @ ptr][slots @ ptr+8 ...].
+// The magic prefix keeps stray integers from being mistaken for tags.
+const HEAP_TAG_MAGIC: i64 = 0x41524B00; // "ARK\0"
+const HEAP_TAG_LIST: i64 = HEAP_TAG_MAGIC | 1;
+const HEAP_TAG_STRUCT: i64 = HEAP_TAG_MAGIC | 2;
+// A closure's slots are its lifted function's table index followed by the
+// captured values, in `closure_captures` order.
+const HEAP_TAG_CLOSURE: i64 = HEAP_TAG_MAGIC | 3;
+// An enum value's header word is its variant's index in the `EnumDecl`
+// rather than a length; the variant's payload fields fill the slots.
+const HEAP_TAG_ENUM: i64 = HEAP_TAG_MAGIC | 4;
+// A map's header word is its entry count; slot 0 holds the table capacity (a
+// power of two) and slot 1 the table pointer. The table is a separate
+// open-addressing block of 16-byte `[key][value]` entries, reallocated as
+// the map grows so the map pointer itself stays stable. Keys are packed
+// strings, never 0, so a zero key marks an empty entry.
+const HEAP_TAG_MAP: i64 = HEAP_TAG_MAGIC | 5;
+const MAP_INITIAL_CAPACITY: i64 = 8;
+// A persistent vector's header word is its length; slot 0 holds its trie's
+// shift (5 per level above the leaves) and slot 1 the root. The trie is a
+// radix-balanced 32-way tree of untagged 256-byte nodes, branches holding
+// child pointers and leaves the elements, so element `i` sits in slot
+// `i & 31` of the leaf its higher 5-bit groups lead to. An update copies
+// the nodes on the path it changes and shares the rest; a pop only writes a
+// shorter header.
+const HEAP_TAG_PVEC: i64 = HEAP_TAG_MAGIC | 6;
+const PVEC_NODE_BYTES: i64 = 32 * 8;
+// A persistent map's header word is its entry count; slot 0 holds the root
+// of a hash array mapped trie, or 0 when the map is empty. A trie node is an
+// untagged `[bitmap][key][value]...` block with one 16-byte entry per set
+// bitmap bit, in bit order. A nonzero key is a packed string stored with its
+// value; a zero key marks an entry whose value word points at a child node.
+// Each level indexes by 5 bits of the key's FNV-1a hash, lowest first, and
+// once the hash is used up by 4 bits at a time of the key's length and then
+// its bytes, so two different keys always end up in different entries.
+const HEAP_TAG_PMAP: i64 = HEAP_TAG_MAGIC | 7;
+const HAMT_HASH_LEVELS: i64 = 13;
+// A big integer's header word is its limb count; slot 0 holds 1 when it is
+// negative, else 0, and the magnitude's 32-bit limbs follow from slot 1,
+// least significant first, with no zero limb on top. Zero has no limbs.
+const HEAP_TAG_BIGINT: i64 = HEAP_TAG_MAGIC | 8;
+
+// A match on integer literals dispatches through a br_table once it has
+// this many literal arms and they cover at least half of a value range of
+// at most `MATCH_TABLE_MAX_SPAN` entries.
+const MATCH_TABLE_MIN_ARMS: usize = 3;
+const MATCH_TABLE_MAX_SPAN: i64 = 1024;
+
+/// The value of a float literal. The parser keeps float literals as their
+/// decimal text, so this is any numeric literal that is not an integer
+/// (note that `2.0` reaches the backend as `2`, an integer).
+fn float_literal(s: &str) -> Option<f64> {
+    let numeric = s.bytes().any(|b| b.is_ascii_digit())
+        && s.bytes()
+            .all(|b| b.is_ascii_digit() || b == b'.' || b == b'-');
+    if !numeric || s.parse::<i64>().is_ok() {
+        return None;
+    }
+    s.parse().ok()
+}
+
+/// WASM type of a parameter or result declared as `ty`. Only floats leave
+/// the i64 convention: strings stay packed `ptr << 32 | len` pointers and
+/// every other value is already an i64.
+fn val_type_of(ty: &ArkType) -> ValType {
+    match ty {
+        ArkType::Float => ValType::F64,
+        _ => ValType::I64,
+    }
+}
+
+/// Static kind (as in `static_type_of`) that an argument for a parameter
+/// declared `ty` is checked against, for the types a call could otherwise
+/// silently reinterpret.
+fn checked_kind(ty: &ArkType) -> Option<&'static str> {
+    match ty {
+        ArkType::Integer => Some("integer"),
+        ArkType::Float => Some("float"),
+        ArkType::String => Some("string"),
+        _ => None,
+    }
+}
+
+/// Emits the body of a WASI-backed helper, given the function index map.
+type HelperEmitter = fn(&mut FuncContext, &HashMap<String, u32>) -> Result<(), WasmCompileError>;
+
+/// Helper functions behind the file, argument and environment intrinsics:
+/// (helper, arity, intrinsic names lowering to a call to it in
+/// `compile_expr`, body emitter). A helper is only emitted when one of its
+/// intrinsics is called somewhere in the program.
+const WASI_HELPERS: &[(&str, usize, &[&str], HelperEmitter)] = &[
+    (
+        "__fs_read",
+        1,
+        &["sys.fs.read", "sys_fs_read", "intrinsic_fs_read"],
+        WasmCodegen::emit_fs_read,
+    ),
+    (
+        "__fs_write",
+        2,
+        &["sys.fs.write", "sys_fs_write", "intrinsic_fs_write"],
+        WasmCodegen::emit_fs_overwrite,
+    ),
+    (
+        "__fs_append",
+        2,
+        &["sys.fs.append", "sys_fs_append", "intrinsic_fs_append"],
+        WasmCodegen::emit_fs_append,
+    ),
+    (
+        "__fs_open",
+        1,
+        &["sys.fs.open", "intrinsic_fs_open"],
+        WasmCodegen::emit_fs_open_stream,
+    ),
+    (
+        "__fs_read_chunk",
+        2,
+        &["sys.fs.read_chunk", "intrinsic_fs_read_chunk"],
+        WasmCodegen::emit_fs_read_chunk,
+    ),
+    (
+        "__fs_seek",
+        2,
+        &["sys.fs.seek", "intrinsic_fs_seek"],
+        WasmCodegen::emit_fs_seek,
+    ),
+    (
+        "__fs_close",
+        1,
+        &["sys.fs.close", "intrinsic_fs_close"],
+        WasmCodegen::emit_fs_close,
+    ),
+    (
+        "__args_get",
+        1,
+        &["sys.args.get", "intrinsic_args_get"],
+        WasmCodegen::emit_args_get,
+    ),
+    (
+        "__env_get",
+        1,
+        &["sys.env.get", "intrinsic_env_get"],
+        WasmCodegen::emit_env_get,
+    ),
+];
+
+/// Helper functions behind the map intrinsics, in the [`WASI_HELPERS`]
+/// format. Map literals are built with `__map_set`, so `called_names`
+/// reports `map.set` for each one.
+const MAP_HELPERS: &[(&str, usize, &[&str], HelperEmitter)] = &[
+    (
+        "__map_get",
+        2,
+        &["map.get", "intrinsic_map_get"],
+        WasmCodegen::emit_map_get,
+    ),
+    (
+        "__map_set",
+        3,
+        &["map.set", "intrinsic_map_set"],
+        WasmCodegen::emit_map_set,
+    ),
+    (
+        "__map_keys",
+        1,
+        &["map.keys", "intrinsic_map_keys"],
+        WasmCodegen::emit_map_keys,
+    ),
+];
+
+/// Helper functions behind the UTF-8 string intrinsics, in the
+/// [`WASI_HELPERS`] format.
+const STRING_HELPERS: &[(&str, usize, &[&str], HelperEmitter)] = &[
+    (
+        "__str_char_len",
+        1,
+        &["string_char_len", "intrinsic_string_char_len"],
+        WasmCodegen::emit_str_char_len,
+    ),
+    (
+        "__str_char_at",
+        2,
+        &["string_char_at", "intrinsic_string_char_at"],
+        WasmCodegen::emit_str_char_at,
+    ),
+    (
+        "__str_slice_chars",
+        3,
+        &["string_slice_chars", "intrinsic_string_slice_chars"],
+        WasmCodegen::emit_str_slice_chars,
+    ),
+    (
+        "__str_to_upper",
+        1,
+        &["string_to_upper", "intrinsic_string_to_upper"],
+        WasmCodegen::emit_str_to_upper,
+    ),
+    (
+        "__str_to_lower",
+        1,
+        &["string_to_lower", "intrinsic_string_to_lower"],
+        WasmCodegen::emit_str_to_lower,
+    ),
+];
+
+/// Helper functions behind the persistent vector and map intrinsics, in the
+/// [`WASI_HELPERS`] format.
+const PERSISTENT_HELPERS: &[(&str, usize, &[&str], HelperEmitter)] = &[
+    (
+        "__pvec_new",
+        0,
+        &["pvec.new", "sys.pvec.new", "intrinsic_pvec_new"],
+        WasmCodegen::emit_pvec_new,
+    ),
+    (
+        "__pvec_conj",
+        2,
+        &["pvec.conj", "sys.pvec.conj", "intrinsic_pvec_conj"],
+        WasmCodegen::emit_pvec_conj,
+    ),
+    (
+        "__pvec_get",
+        2,
+        &["pvec.get", "sys.pvec.get", "intrinsic_pvec_get"],
+        WasmCodegen::emit_pvec_get,
+    ),
+    (
+        "__pvec_assoc",
+        3,
+        &["pvec.assoc", "sys.pvec.assoc", "intrinsic_pvec_assoc"],
+        WasmCodegen::emit_pvec_assoc,
+    ),
+    (
+        "__pvec_pop",
+        1,
+        &["pvec.pop", "sys.pvec.pop", "intrinsic_pvec_pop"],
+        WasmCodegen::emit_pvec_pop,
+    ),
+    (
+        "__pvec_len",
+        1,
+        &["pvec.len", "sys.pvec.len", "intrinsic_pvec_len"],
+        WasmCodegen::emit_pvec_len,
+    ),
+    (
+        "__pmap_new",
+        0,
+        &["pmap.new", "sys.pmap.new", "intrinsic_pmap_new"],
+        WasmCodegen::emit_pmap_new,
+    ),
+    (
+        "__pmap_assoc",
+        3,
+        &["pmap.assoc", "sys.pmap.assoc", "intrinsic_pmap_assoc"],
+        WasmCodegen::emit_pmap_assoc,
+    ),
+    (
+        "__pmap_dissoc",
+        2,
+        &["pmap.dissoc", "sys.pmap.dissoc", "intrinsic_pmap_dissoc"],
+        WasmCodegen::emit_pmap_dissoc,
+    ),
+    (
+        "__pmap_get",
+        2,
+        &["pmap.get", "sys.pmap.get", "intrinsic_pmap_get"],
+        WasmCodegen::emit_pmap_get,
+    ),
+    (
+        "__pmap_keys",
+        1,
+        &["pmap.keys", "sys.pmap.keys", "intrinsic_pmap_keys"],
+        WasmCodegen::emit_pmap_keys,
+    ),
+    (
+        "__pmap_merge",
+        2,
+        &["pmap.merge", "sys.pmap.merge", "intrinsic_pmap_merge"],
+        WasmCodegen::emit_pmap_merge,
+    ),
+];
+
+/// Trie walks shared by the [`PERSISTENT_HELPERS`], each listed under the
+/// intrinsics that end up calling it. Ark code never calls these directly.
+const PERSISTENT_NODE_HELPERS: &[(&str, usize, &[&str], HelperEmitter)] = &[
+    (
+        "__pvec_set",
+        3,
+        &[
+            "pvec.conj",
+            "sys.pvec.conj",
+            "intrinsic_pvec_conj",
+            "pvec.assoc",
+            "sys.pvec.assoc",
+            "intrinsic_pvec_assoc",
+        ],
+        WasmCodegen::emit_pvec_set,
+    ),
+    (
+        "__pmap_find",
+        2,
+        &[
+            "pmap.get",
+            "sys.pmap.get",
+            "intrinsic_pmap_get",
+            "pmap.assoc",
+            "sys.pmap.assoc",
+            "intrinsic_pmap_assoc",
+            "pmap.dissoc",
+            "sys.pmap.dissoc",
+            "intrinsic_pmap_dissoc",
+            "pmap.merge",
+            "sys.pmap.merge",
+            "intrinsic_pmap_merge",
+        ],
+        WasmCodegen::emit_pmap_find,
+    ),
+    (
+        "__pmap_node_assoc",
+        5,
+        &[
+            "pmap.assoc",
+            "sys.pmap.assoc",
+            "intrinsic_pmap_assoc",
+            "pmap.merge",
+            "sys.pmap.merge",
+            "intrinsic_pmap_merge",
+        ],
+        WasmCodegen::emit_pmap_node_assoc,
+    ),
+    (
+        "__pmap_node_dissoc",
+        4,
+        &["pmap.dissoc", "sys.pmap.dissoc", "intrinsic_pmap_dissoc"],
+        WasmCodegen::emit_pmap_node_dissoc,
+    ),
+    (
+        "__pmap_collect",
+        3,
+        &["pmap.keys", "sys.pmap.keys", "intrinsic_pmap_keys"],
+        WasmCodegen::emit_pmap_collect,
+    ),
+    (
+        "__pmap_put",
+        3,
+        &[
+            "pmap.assoc",
+            "sys.pmap.assoc",
+            "intrinsic_pmap_assoc",
+            "pmap.merge",
+            "sys.pmap.merge",
+            "intrinsic_pmap_merge",
+        ],
+        WasmCodegen::emit_pmap_put,
+    ),
+    (
+        "__pmap_node_merge",
+        2,
+        &["pmap.merge", "sys.pmap.merge", "intrinsic_pmap_merge"],
+        WasmCodegen::emit_pmap_node_merge,
+    ),
+];
+
+/// Helper functions behind the `sys.bigint.*` intrinsics, in the
+/// [`WASI_HELPERS`] format.
+const BIGINT_HELPERS: &[(&str, usize, &[&str], HelperEmitter)] = &[
+    (
+        "__big_from_string",
+        1,
+        &[
+            "bigint.from_string",
+            "sys.bigint.from_string",
+            "intrinsic_bigint_from_string",
+        ],
+        WasmCodegen::emit_big_from_string,
+    ),
+    (
+        "__big_add",
+        2,
+        &["bigint.add", "sys.bigint.add", "intrinsic_bigint_add"],
+        WasmCodegen::emit_big_add,
+    ),
+    (
+        "__big_mul",
+        2,
+        &["bigint.mul", "sys.bigint.mul", "intrinsic_bigint_mul"],
+        WasmCodegen::emit_big_mul,
+    ),
+    (
+        "__big_mod",
+        2,
+        &["bigint.mod", "sys.bigint.mod", "intrinsic_bigint_mod"],
+        WasmCodegen::emit_big_mod,
+    ),
+    (
+        "__big_pow_mod",
+        3,
+        &[
+            "bigint.pow_mod",
+            "sys.bigint.pow_mod",
+            "intrinsic_bigint_pow_mod",
+        ],
+        WasmCodegen::emit_big_pow_mod,
+    ),
+    (
+        "__big_cmp",
+        2,
+        &["bigint.cmp", "sys.bigint.cmp", "intrinsic_bigint_cmp"],
+        WasmCodegen::emit_big_cmp,
+    ),
+    (
+        "__big_to_string",
+        1,
+        &[
+            "bigint.to_string",
+            "sys.bigint.to_string",
+            "intrinsic_bigint_to_string",
+        ],
+        WasmCodegen::emit_big_to_string,
+    ),
+];
+
+/// Limb arithmetic shared by the [`BIGINT_HELPERS`], each listed under the
+/// intrinsics that end up calling it. Ark code never calls these directly.
+const BIGINT_NODE_HELPERS: &[(&str, usize, &[&str], HelperEmitter)] = &[
+    (
+        "__big_alloc",
+        1,
+        &[
+            "bigint.from_string",
+            "sys.bigint.from_string",
+            "intrinsic_bigint_from_string",
+            "bigint.add",
+            "sys.bigint.add",
+            "intrinsic_bigint_add",
+            "bigint.mul",
+            "sys.bigint.mul",
+            "intrinsic_bigint_mul",
+            "bigint.mod",
+            "sys.bigint.mod",
+            "intrinsic_bigint_mod",
+            "bigint.pow_mod",
+            "sys.bigint.pow_mod",
+            "intrinsic_bigint_pow_mod",
+            "bigint.cmp",
+            "sys.bigint.cmp",
+            "intrinsic_bigint_cmp",
+            "bigint.to_string",
+            "sys.bigint.to_string",
+            "intrinsic_bigint_to_string",
+        ],
+        WasmCodegen::emit_big_alloc,
+    ),
+    (
+        "__big_trim",
+        1,
+        &[
+            "bigint.from_string",
+            "sys.bigint.from_string",
+            "intrinsic_bigint_from_string",
+            "bigint.add",
+            "sys.bigint.add",
+            "intrinsic_bigint_add",
+            "bigint.mul",
+            "sys.bigint.mul",
+            "intrinsic_bigint_mul",
+            "bigint.mod",
+            "sys.bigint.mod",
+            "intrinsic_bigint_mod",
+            "bigint.pow_mod",
+            "sys.bigint.pow_mod",
+            "intrinsic_bigint_pow_mod",
+            "bigint.cmp",
+            "sys.bigint.cmp",
+            "intrinsic_bigint_cmp",
+            "bigint.to_string",
+            "sys.bigint.to_string",
+            "intrinsic_bigint_to_string",
+        ],
+        WasmCodegen::emit_big_trim,
+    ),
+    (
+        "__big_of",
+        1,
+        &[
+            "bigint.add",
+            "sys.bigint.add",
+            "intrinsic_bigint_add",
+            "bigint.mul",
+            "sys.bigint.mul",
+            "intrinsic_bigint_mul",
+            "bigint.mod",
+            "sys.bigint.mod",
+            "intrinsic_bigint_mod",
+            "bigint.pow_mod",
+            "sys.bigint.pow_mod",
+            "intrinsic_bigint_pow_mod",
+            "bigint.cmp",
+            "sys.bigint.cmp",
+            "intrinsic_bigint_cmp",
+            "bigint.to_string",
+            "sys.bigint.to_string",
+            "intrinsic_bigint_to_string",
+        ],
+        WasmCodegen::emit_big_of,
+    ),
+    (
+        "__big_mag_cmp",
+        2,
+        &[
+            "bigint.add",
+            "sys.bigint.add",
+            "intrinsic_bigint_add",
+            "bigint.cmp",
+            "sys.bigint.cmp",
+            "intrinsic_bigint_cmp",
+        ],
+        WasmCodegen::emit_big_mag_cmp,
+    ),
+    (
+        "__big_mag_add",
+        2,
+        &["bigint.add", "sys.bigint.add", "intrinsic_bigint_add"],
+        WasmCodegen::emit_big_mag_add,
+    ),
+    (
+        "__big_mag_sub",
+        2,
+        &[
+            "bigint.add",
+            "sys.bigint.add",
+            "intrinsic_bigint_add",
+            "bigint.mod",
+            "sys.bigint.mod",
+            "intrinsic_bigint_mod",
+            "bigint.pow_mod",
+            "sys.bigint.pow_mod",
+            "intrinsic_bigint_pow_mod",
+        ],
+        WasmCodegen::emit_big_mag_sub,
+    ),
+    (
+        "__big_mag_rem",
+        2,
+        &[
+            "bigint.mod",
+            "sys.bigint.mod",
+            "intrinsic_bigint_mod",
+            "bigint.pow_mod",
+            "sys.bigint.pow_mod",
+            "intrinsic_bigint_pow_mod",
+        ],
+        WasmCodegen::emit_big_mag_rem,
+    ),
+    (
+        "__big_product",
+        2,
+        &[
+            "bigint.mul",
+            "sys.bigint.mul",
+            "intrinsic_bigint_mul",
+            "bigint.pow_mod",
+            "sys.bigint.pow_mod",
+            "intrinsic_bigint_pow_mod",
+        ],
+        WasmCodegen::emit_big_product,
+    ),
+    (
+        "__big_remainder",
+        2,
+        &[
+            "bigint.mod",
+            "sys.bigint.mod",
+            "intrinsic_bigint_mod",
+            "bigint.pow_mod",
+            "sys.bigint.pow_mod",
+            "intrinsic_bigint_pow_mod",
+        ],
+        WasmCodegen::emit_big_remainder,
+    ),
+];
+
+/// Helper functions behind the tensor intrinsics, in the [`WASI_HELPERS`]
+/// format. A tensor is a struct whose slots are its `data` and `shape`
+/// lists, as the interpreter's `math.Tensor` builds it.
+const TENSOR_HELPERS: &[(&str, usize, &[&str], HelperEmitter)] = &[
+    (
+        "__tensor_new",
+        2,
+        &["math.Tensor", "intrinsic_math_tensor"],
+        WasmCodegen::emit_tensor_new,
+    ),
+    (
+        "__tensor_dot",
+        2,
+        &["math.dot", "intrinsic_math_dot"],
+        WasmCodegen::emit_tensor_dot,
+    ),
+    (
+        "__tensor_matmul",
+        2,
+        &["math.matmul", "intrinsic_math_matmul"],
+        WasmCodegen::emit_tensor_matmul,
+    ),
+    (
+        "__tensor_transpose",
+        1,
+        &["math.transpose", "intrinsic_math_transpose"],
+        WasmCodegen::emit_tensor_transpose,
+    ),
+    (
+        "__tensor_add",
+        2,
+        &["math.add", "intrinsic_math_tensor_add"],
+        WasmCodegen::emit_tensor_add,
+    ),
+    (
+        "__tensor_sub",
+        2,
+        &["math.sub", "intrinsic_math_tensor_sub"],
+        WasmCodegen::emit_tensor_sub,
+    ),
+    (
+        "__tensor_mul_scalar",
+        2,
+        &["math.mul_scalar", "intrinsic_math_mul_scalar"],
+        WasmCodegen::emit_tensor_mul_scalar,
+    ),
+];
+
+/// [`TENSOR_HELPERS`] with the arithmetic kernels on v128 lanes, emitted
+/// instead of them under [`WasmCodegenOptions::simd`]. Names and arities
+/// match entry for entry.
+const TENSOR_SIMD_HELPERS: &[(&str, usize, &[&str], HelperEmitter)] = &[
+    (
+        "__tensor_new",
+        2,
+        &["math.Tensor", "intrinsic_math_tensor"],
+        WasmCodegen::emit_tensor_new,
+    ),
+    (
+        "__tensor_dot",
+        2,
+        &["math.dot", "intrinsic_math_dot"],
+        WasmCodegen::emit_tensor_dot_simd,
+    ),
+    (
+        "__tensor_matmul",
+        2,
+        &["math.matmul", "intrinsic_math_matmul"],
+        WasmCodegen::emit_tensor_matmul_simd,
+    ),
+    (
+        "__tensor_transpose",
+        1,
+        &["math.transpose", "intrinsic_math_transpose"],
+        WasmCodegen::emit_tensor_transpose,
+    ),
+    (
+        "__tensor_add",
+        2,
+        &["math.add", "intrinsic_math_tensor_add"],
+        WasmCodegen::emit_tensor_add_simd,
+    ),
+    (
+        "__tensor_sub",
+        2,
+        &["math.sub", "intrinsic_math_tensor_sub"],
+        WasmCodegen::emit_tensor_sub_simd,
+    ),
+    (
+        "__tensor_mul_scalar",
+        2,
+        &["math.mul_scalar", "intrinsic_math_mul_scalar"],
+        WasmCodegen::emit_tensor_mul_scalar_simd,
+    ),
+];
+
+/// Element-wise operation of the `math.add`, `math.sub` and
+/// `math.mul_scalar` helpers.
+#[derive(Clone, Copy)]
+enum TensorOp {
+    Add,
+    Sub,
+    /// Multiply by the scalar in the second parameter
+    Scale,
+}
+
+impl TensorOp {
+    fn scalar(self) -> Instruction<'static> {
+        match self {
+            TensorOp::Add => Instruction::I64Add,
+            TensorOp::Sub => Instruction::I64Sub,
+            TensorOp::Scale => Instruction::I64Mul,
+        }
+    }
+
+    fn lanes(self) -> Instruction<'static> {
+        match self {
+            TensorOp::Add => Instruction::I64x2Add,
+            TensorOp::Sub => Instruction::I64x2Sub,
+            TensorOp::Scale => Instruction::I64x2Mul,
+        }
+    }
+}
+
+/// Helper functions behind the `sys.crypto.*` 32-byte digests, in the
+/// [`WASI_HELPERS`] format.
+const DIGEST_HELPERS: &[(&str, usize, &[&str], HelperEmitter)] = &[
+    (
+        "__crypto_sha256",
+        1,
+        &["intrinsic_crypto_sha256", "sys.crypto.sha256"],
+        WasmCodegen::emit_crypto_sha256,
+    ),
+    (
+        "__crypto_keccak256",
+        1,
+        &["intrinsic_crypto_keccak256", "sys.crypto.keccak256"],
+        WasmCodegen::emit_crypto_keccak256,
+    ),
+    (
+        "__crypto_blake3",
+        1,
+        &["intrinsic_crypto_blake3", "sys.crypto.blake3"],
+        WasmCodegen::emit_crypto_blake3,
+    ),
+];
+
+/// `ark_host` digest imports: (import name, intrinsic names whose
+/// [`DIGEST_HELPERS`] helper calls it). Each has the `crypto_sha512`
+/// signature and writes 32 bytes; an import is only emitted when one of
+/// its intrinsics is called somewhere in the program.
+const DIGEST_HOST_IMPORTS: &[(&str, &[&str])] = &[
+    (
+        "crypto_sha256",
+        &["intrinsic_crypto_sha256", "sys.crypto.sha256"],
+    ),
+    (
+        "crypto_keccak256",
+        &["intrinsic_crypto_keccak256", "sys.crypto.keccak256"],
+    ),
+    (
+        "crypto_blake3",
+        &["intrinsic_crypto_blake3", "sys.crypto.blake3"],
+    ),
+];
+
+// Case mappings for `string_to_upper`/`string_to_lower` as (first, last,
+// skipped code point or -1, delta). They cover ASCII, Latin-1, Greek and
+// Cyrillic, whose cased pairs encode to the same number of bytes; other
+// characters are left as they are.
+const STRING_UPPER_RANGES: &[(i64, i64, i64, i64)] = &[
+    (0x61, 0x7A, -1, -32),
+    (0xE0, 0xFE, 0xF7, -32),
+    (0x3B1, 0x3C9, 0x3C2, -32),
+    (0x430, 0x44F, -1, -32),
+    (0x450, 0x45F, -1, -80),
+];
+const STRING_LOWER_RANGES: &[(i64, i64, i64, i64)] = &[
+    (0x41, 0x5A, -1, 32),
+    (0xC0, 0xDE, 0xD7, 32),
+    (0x391, 0x3A9, 0x3A2, 32),
+    (0x410, 0x42F, -1, 32),
+    (0x400, 0x40F, -1, 80),
+];
+
+// WASI Preview1 file constants used by the `sys.fs` helpers. Paths resolve
+// against the first preopened directory, which runtimes hand out as fd 3.
+const WASI_PREOPEN_FD: i32 = 3;
+const WASI_LOOKUP_SYMLINK_FOLLOW: i32 = 1;
+const WASI_O_CREAT: i32 = 1;
+const WASI_O_TRUNC: i32 = 8;
+const WASI_FDFLAGS_APPEND: i32 = 1;
+const WASI_RIGHT_FD_READ: i64 = 1 << 1;
+const WASI_RIGHT_FD_SEEK: i64 = 1 << 2;
+const WASI_RIGHT_FD_WRITE: i64 = 1 << 6;
+
+/// Math intrinsics that take and return floats (everything lowered to an
+/// `ark_host` math import except the integer `pow_mod`).
+fn is_float_math(name: &str) -> bool {
+    MATH_HOST_IMPORTS
+        .iter()
+        .any(|(import, _, intrinsics)| *import != "math_pow_mod" && intrinsics.contains(&name))
+}
+
+/// Content-addressed `func_index_map` key for a lifted lambda expression.
+fn lambda_key(lambda: &Expression) -> Result<String, WasmCompileError> {
+    let hash = calculate_hash(lambda).map_err(|e| WasmCompileError {
+        message: format!("failed to hash lambda: {}", e),
+        context: "lambda lifting".to_string(),
+        span: None,
+    })?;
+    Ok(format!("__lambda#{}", hash))
+}
+
+/// Append `value` to a source map `mappings` string as a Base64 VLQ: the
+/// sign in the lowest bit, then 5-bit groups, least significant first.
+fn vlq_push(out: &mut String, value: i64) {
+    const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut vlq = (value.unsigned_abs() << 1) | u64::from(value < 0);
+    loop {
+        let mut digit = (vlq & 31) as usize;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 32; // continuation bit
+        }
+        out.push(BASE64[digit] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
 // =============================================================================
 // Scope & Local Variable Tracking
 // =============================================================================
@@ -128,6 +984,16 @@ struct LocalScope {
     locals: HashMap<String, u32>,
     /// Next available local index
     next_local: u32,
+    /// Statically known type tag per local; `None` once bindings disagree
+    kinds: HashMap<String, Option<&'static str>>,
+    /// Field order of struct-valued locals, used to resolve field offsets
+    shapes: HashMap<String, Option<Vec<String>>>,
+    /// Scratch locals currently held, outermost first: (name, local index)
+    scratch_live: Vec<(String, u32)>,
+    /// Start of the innermost open scratch region within `scratch_live`
+    scratch_base: usize,
+    /// Every scratch local allocated so far; the i-th live scratch reuses slot i
+    scratch_pool: Vec<u32>,
 }
 
 impl LocalScope {
@@ -135,9 +1001,48 @@ impl LocalScope {
         Self {
             locals: HashMap::new(),
             next_local: param_count,
+            kinds: HashMap::new(),
+            shapes: HashMap::new(),
+            scratch_live: Vec::new(),
+            scratch_base: 0,
+            scratch_pool: Vec::new(),
         }
     }
 
+    /// Record the kind bound to `name`. A local keeps a static kind only if
+    /// every binding seen so far agrees and it is not a parameter.
+    fn record_kind(&mut self, name: &str, kind: Option<&'static str>) {
+        let first_binding = !self.locals.contains_key(name);
+        let merged = match self.kinds.get(name) {
+            Some(prev) => prev.and_then(|p| kind.filter(|k| *k == p)),
+            None if first_binding => kind,
+            None => None,
+        };
+        self.kinds.insert(name.to_string(), merged);
+    }
+
+    /// Statically known type tag of a local, if any.
+    fn kind(&self, name: &str) -> Option<&'static str> {
+        self.kinds.get(name).copied().flatten()
+    }
+
+    /// Record the struct field order bound to `name`, with the same
+    /// agreement rule as `record_kind`.
+    fn record_shape(&mut self, name: &str, shape: Option<Vec<String>>) {
+        let first_binding = !self.locals.contains_key(name);
+        let merged = match self.shapes.get(name) {
+            Some(prev) => prev.clone().filter(|p| shape.as_ref() == Some(p)),
+            None if first_binding => shape,
+            None => None,
+        };
+        self.shapes.insert(name.to_string(), merged);
+    }
+
+    /// Statically known field order of a struct-valued local, if any.
+    fn shape(&self, name: &str) -> Option<&[String]> {
+        self.shapes.get(name).and_then(|s| s.as_deref())
+    }
+
     /// Get or allocate a local variable index for the given name.
     fn get_or_alloc(&mut self, name: &str) -> u32 {
         if let Some(&idx) = self.locals.get(name) {
@@ -150,6 +1055,42 @@ impl LocalScope {
         }
     }
 
+    /// Get a scratch local for `name` in the innermost open region. Slots
+    /// are handed out stack-wise from a shared pool, so sibling expressions
+    /// reuse the same locals and the function only declares as many scratch
+    /// slots as its deepest nesting holds at once.
+    fn scratch(&mut self, name: &str) -> u32 {
+        if let Some((_, idx)) = self.scratch_live[self.scratch_base..]
+            .iter()
+            .find(|(held, _)| held == name)
+        {
+            return *idx;
+        }
+        let slot = self.scratch_live.len();
+        let idx = match self.scratch_pool.get(slot) {
+            Some(&idx) => idx,
+            None => {
+                let idx = self.next_local;
+                self.next_local += 1;
+                self.scratch_pool.push(idx);
+                idx
+            }
+        };
+        self.scratch_live.push((name.to_string(), idx));
+        idx
+    }
+
+    /// Open a scratch region; pass the returned token to `close_scratch`.
+    fn open_scratch(&mut self) -> usize {
+        std::mem::replace(&mut self.scratch_base, self.scratch_live.len())
+    }
+
+    /// Release every scratch local taken since the matching `open_scratch`.
+    fn close_scratch(&mut self, outer_base: usize) {
+        self.scratch_live.truncate(self.scratch_base);
+        self.scratch_base = outer_base;
+    }
+
     /// Get a local variable index (returns None if not found).
     fn get(&self, name: &str) -> Option<u32> {
         self.locals.get(name).copied()
@@ -166,6 +1107,7 @@ impl LocalScope {
 // =============================================================================
 
 /// Context for compiling a single function body.
+#[derive(Clone)]
 struct FuncContext {
     scope: LocalScope,
     instructions: Vec<Instruction<'static>>,
@@ -174,6 +1116,46 @@ struct FuncContext {
     string_data: Vec<(i32, Vec<u8>)>, // (offset, bytes)
     /// Next available string memory offset
     string_offset: i32,
+    /// Emit runtime shape/length checks that trap on violation
+    bounds_checks: bool,
+    /// Rank in `LOG_LEVELS` below which `sys.log` calls are dropped
+    log_level: usize,
+    /// Declared struct name → field order
+    struct_layouts: HashMap<String, Vec<String>>,
+    /// Declared enum name → variants (name, payload arity) in tag order
+    enum_layouts: HashMap<String, Vec<(String, usize)>>,
+    /// Exported top-level binding → mutable global that mirrors it
+    export_globals: HashMap<String, u32>,
+    /// Callee → (which parameters are floats, whether the result is)
+    float_signatures: HashMap<String, (Vec<bool>, bool)>,
+    /// The function being compiled returns a float
+    returns_float: bool,
+    /// User function → declared parameters, checked at direct call sites.
+    /// Its float parameters and result are real f64s (see `register_function`)
+    declared_params: HashMap<String, Vec<(String, ArkType)>>,
+    /// Local holding each parameter as an i64: the parameter itself, or the
+    /// local an f64 parameter's bits are copied to on entry
+    param_locals: Vec<u32>,
+    /// Lifted lambda key → names it captures (see `collect_lambdas`)
+    closure_captures: HashMap<String, Vec<String>>,
+    /// Parameter count → type index of `(i64 × n) -> i64`, for call_indirect
+    indirect_types: HashMap<usize, u32>,
+    /// Structured blocks (block/loop/if) currently open, kept by `emit`
+    block_depth: u32,
+    /// Enclosing Ark loops, innermost last: each one's label and the block
+    /// depth of its `break` and `continue` targets
+    loop_targets: Vec<(Option<String>, u32, u32)>,
+    /// Positions in `instructions` of the `i64.const`s that push a function
+    /// index as a value, renumbered when dead functions are dropped
+    func_refs: Vec<usize>,
+    /// Self-tail-recursive function being compiled: its name and the block
+    /// depth of the loop wrapping its body, which tail calls branch to
+    tail_loop: Option<(String, u32)>,
+    /// Global holding the remaining fuel, when metering is on
+    fuel_global: Option<u32>,
+    /// Leading non-parameter locals declared as v128 rather than i64 (see
+    /// `reserve_v128_locals`)
+    v128_locals: u32,
 }
 
 impl FuncContext {
@@ -184,13 +1166,81 @@ impl FuncContext {
             param_count,
             string_data: Vec::new(),
             string_offset: STRING_MEMORY_START,
+            bounds_checks: true,
+            log_level: 1,
+            struct_layouts: HashMap::new(),
+            enum_layouts: HashMap::new(),
+            export_globals: HashMap::new(),
+            float_signatures: HashMap::new(),
+            returns_float: false,
+            declared_params: HashMap::new(),
+            param_locals: (0..param_count).collect(),
+            closure_captures: HashMap::new(),
+            indirect_types: HashMap::new(),
+            block_depth: 0,
+            loop_targets: Vec::new(),
+            func_refs: Vec::new(),
+            tail_loop: None,
+            fuel_global: None,
+            v128_locals: 0,
         }
     }
 
+    /// Reserve `count` v128 locals and return the index of the first. They
+    /// are declared ahead of the i64 locals, so this must come before any
+    /// other local is allocated.
+    fn reserve_v128_locals(&mut self, count: u32) -> u32 {
+        assert_eq!(
+            self.scope.next_local, self.param_count,
+            "v128 locals must be reserved first"
+        );
+        self.scope.next_local += count;
+        self.v128_locals += count;
+        self.param_count
+    }
+
     fn emit(&mut self, instr: Instruction<'static>) {
+        match instr {
+            Instruction::Block(_) | Instruction::Loop(_) | Instruction::If(_) => {
+                self.block_depth += 1
+            }
+            Instruction::End => self.block_depth = self.block_depth.saturating_sub(1),
+            _ => {}
+        }
         self.instructions.push(instr);
     }
 
+    /// Spend one unit of fuel, trapping if there is none left. The fuel is
+    /// then set to -1, which is how the host tells this trap from others.
+    fn spend_fuel(&mut self) {
+        let Some(fuel) = self.fuel_global else {
+            return;
+        };
+        self.emit(Instruction::GlobalGet(fuel));
+        self.emit(Instruction::I64Const(0));
+        self.emit(Instruction::I64LeS);
+        self.emit(Instruction::If(BlockType::Empty));
+        self.emit(Instruction::I64Const(-1));
+        self.emit(Instruction::GlobalSet(fuel));
+        self.emit(Instruction::Unreachable);
+        self.emit(Instruction::End);
+        self.emit(Instruction::GlobalGet(fuel));
+        self.emit(Instruction::I64Const(1));
+        self.emit(Instruction::I64Sub);
+        self.emit(Instruction::GlobalSet(fuel));
+    }
+
+    /// Push function `func_idx` as a value: its table index.
+    fn emit_func_ref(&mut self, func_idx: u32) {
+        self.func_refs.push(self.instructions.len());
+        self.emit(Instruction::I64Const(func_idx as i64));
+    }
+
+    /// Branch to the block that was opened at `target_depth`.
+    fn emit_br_to(&mut self, target_depth: u32) {
+        self.emit(Instruction::Br(self.block_depth - target_depth));
+    }
+
     /// Allocate a string in linear memory, return (ptr, len).
     fn alloc_string(&mut self, s: &str) -> (i32, i32) {
         let bytes = s.as_bytes();
@@ -204,10 +1254,181 @@ impl FuncContext {
     }
 }
 
+// =============================================================================
+// Incremental Compilation Cache
+// =============================================================================
+
+/// Compiled function bodies from earlier builds, keyed by content hash.
+///
+/// Pass the same cache to [`WasmCodegen::compile_with_cache`] on every
+/// build; a function whose AST hash and module context (function indices,
+/// struct layouts, codegen settings) are unchanged reuses its previous body
+/// instead of being recompiled. Entries not used by the latest build are
+/// dropped, so the cache never outgrows the program.
+#[derive(Clone, Default)]
+pub struct CompileCache {
+    /// Key → (string offset the body was compiled at, compiled body)
+    entries: HashMap<String, (i32, FuncContext)>,
+    /// Keys looked up or stored during the current build
+    used: HashSet<String>,
+    /// Bodies reused in the most recent build
+    hits: usize,
+}
+
+impl CompileCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of cached function bodies.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Number of function bodies the most recent build reused.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    fn begin_build(&mut self) {
+        self.used.clear();
+        self.hits = 0;
+    }
+
+    fn finish_build(&mut self) {
+        let used = std::mem::take(&mut self.used);
+        self.entries.retain(|key, _| used.contains(key));
+    }
+
+    /// The cached body for `key`, if it can be placed at `data_offset`.
+    /// String constants are baked in at absolute addresses, so a body that
+    /// owns any is only reusable at the offset it was compiled at.
+    fn get(&mut self, key: &str, data_offset: i32) -> Option<FuncContext> {
+        let (offset, ctx) = self.entries.get(key)?;
+        if *offset != data_offset && !ctx.string_data.is_empty() {
+            return None;
+        }
+        let mut ctx = ctx.clone();
+        if ctx.string_data.is_empty() {
+            ctx.string_offset = data_offset;
+        }
+        self.used.insert(key.to_string());
+        self.hits += 1;
+        Some(ctx)
+    }
+
+    fn insert(&mut self, key: String, data_offset: i32, ctx: &FuncContext) {
+        self.used.insert(key.clone());
+        self.entries.insert(key, (data_offset, ctx.clone()));
+    }
+}
+
 // =============================================================================
 // WASM Code Generator
 // =============================================================================
 
+/// WASI version the module's system imports are written against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WasiTarget {
+    /// `wasi_snapshot_preview1` functions, which every WASI runtime accepts.
+    #[default]
+    Preview1,
+    /// WASI 0.2 interfaces (`wasi:cli`, `wasi:io`, `wasi:clocks`,
+    /// `wasi:random`) under their canonical ABI signatures, so the module
+    /// becomes a component without the Preview1 adapter. Output, clocks,
+    /// randomness and exit are supported; stdin, arguments, environment
+    /// and file intrinsics fail to compile. Exit statuses collapse to
+    /// success or failure.
+    Preview2,
+}
+
+/// Module-level settings for [`WasmCodegen::with_options`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WasmCodegenOptions {
+    /// Pages (64 KiB each) the linear memory starts with. Raised
+    /// automatically if the string data does not fit.
+    pub initial_memory_pages: u32,
+    /// Pages `__alloc` may grow the memory to before trapping. `None` leaves
+    /// it unbounded, up to the 4 GiB wasm32 limit.
+    pub max_memory_pages: Option<u32>,
+    /// WASI version to import system functions from.
+    pub wasi_target: WasiTarget,
+    /// Fuel the module starts with. Every function call and loop iteration
+    /// spends one unit and the module traps once it has none left. The
+    /// remaining fuel is the exported mutable global [`FUEL_GLOBAL`], which
+    /// the host may read or refill between calls. `None` emits no checks.
+    pub fuel: Option<u64>,
+    /// Lower the tensor intrinsics (`math.dot`, `math.matmul`, `math.add`,
+    /// `math.sub`, `math.mul_scalar`) to 128-bit SIMD kernels working on
+    /// two elements at a time. The module then needs a runtime with the
+    /// WASM SIMD proposal, which every current engine enables by default.
+    pub simd: bool,
+}
+
+impl Default for WasmCodegenOptions {
+    fn default() -> Self {
+        Self {
+            initial_memory_pages: 1,
+            max_memory_pages: Some(16),
+            wasi_target: WasiTarget::Preview1,
+            fuel: None,
+            simd: false,
+        }
+    }
+}
+
+/// Function indices of the WASI 0.2 imports behind the Preview1-shaped
+/// shims of [`WasiTarget::Preview2`].
+#[derive(Clone, Copy)]
+struct WasiPreview2Imports {
+    get_stdout: u32,
+    get_stderr: u32,
+    blocking_write_and_flush: u32,
+    drop_output_stream: u32,
+    wall_clock_now: u32,
+    monotonic_clock_now: u32,
+    get_random_u64: u32,
+    exit: u32,
+}
+
+/// Which functions and types make it into the emitted module, and at what
+/// index. Function indices count imports first, as in the module.
+struct ModuleLayout {
+    /// Function index during codegen → index in the module, `None` if dead
+    funcs: Vec<Option<u32>>,
+    /// Type index during codegen → index in the module, `None` if unused
+    types: Vec<Option<u32>>,
+    /// Exported functions: (export name, function index during codegen)
+    exports: Vec<(String, u32)>,
+}
+
+impl ModuleLayout {
+    fn func(&self, idx: u32) -> u32 {
+        self.funcs[idx as usize].expect("reference to a dead function")
+    }
+
+    fn ty(&self, idx: u32) -> u32 {
+        self.types[idx as usize].expect("reference to an unused type")
+    }
+
+    fn live_func_count(&self) -> u32 {
+        self.funcs.iter().flatten().count() as u32
+    }
+}
+
+/// Output of [`WasmCodegen::compile_component`].
+#[derive(Debug, Clone)]
+pub struct WasmComponent {
+    /// The encoded component
+    pub bytes: Vec<u8>,
+    /// The WIT package and world the component implements
+    pub wit: String,
+}
+
 /// The main WASM code generator. Compiles an ArkNode AST into a WASM binary.
 pub struct WasmCodegen {
     /// Type section: function signatures
@@ -216,10 +1437,14 @@ pub struct WasmCodegen {
     functions: Vec<(u32, String, FuncContext)>,
     /// WASI import count (shifts function indices)
     import_count: u32,
+    /// Imported functions in import-section order
+    imports: Vec<WasmImport>,
+    /// Import name → function index (position in `imports`)
+    import_map: HashMap<String, u32>,
     /// String constants to embed in data section
     data_segments: Vec<(i32, Vec<u8>)>,
-    /// Next data segment offset
-    #[allow(dead_code)]
+    /// Next free string-constant offset. Shared across functions so that
+    /// their string data never overlaps in linear memory.
     data_offset: i32,
     /// Global function name → function index
     func_index_map: HashMap<String, u32>,
@@ -230,6 +1455,61 @@ pub struct WasmCodegen {
     func_attributes: HashMap<String, Vec<String>>,
     /// Counter for generating unique lambda names
     lambda_counter: u32,
+    /// Embedder-provided imports, emitted after the built-in ones
+    host_imports: Vec<HostImport>,
+    /// Omit the timestamp from `ark.build` so identical input yields
+    /// byte-identical output
+    deterministic: bool,
+    /// SHA-256 of the serialized input AST, recorded in `ark.build`
+    source_hash: String,
+    /// Emit runtime checks that trap on out-of-bounds destructuring
+    bounds_checks: bool,
+    /// Rank in `LOG_LEVELS` below which `sys.log` calls are compiled out
+    log_level: usize,
+    /// Every `Call` target in the program, used to prune unused imports
+    called: HashSet<String>,
+    /// Declared struct name → field order, from `StructDecl`s
+    struct_layouts: HashMap<String, Vec<String>>,
+    /// Declared enum name → variants (name, payload arity), from `EnumDecl`s
+    enum_layouts: HashMap<String, Vec<(String, usize)>>,
+    /// Top-level `#[export] let` bindings, exported as globals 1.. in this
+    /// order. `Some(n)` is a constant initializer (immutable global); `None`
+    /// marks a mutable global that `_start` sets when the `let` runs.
+    exported_globals: Vec<(String, Option<i64>)>,
+    /// Functions and host imports with float parameters or results
+    float_signatures: HashMap<String, (Vec<bool>, bool)>,
+    /// User function → declared parameters, from `register_function`
+    declared_params: HashMap<String, Vec<(String, ArkType)>>,
+    /// Some value in the program is a float
+    uses_floats: bool,
+    /// Lifted lambda key → names it captures from the enclosing scope
+    closure_captures: HashMap<String, Vec<String>>,
+    /// Parameter count → type index of `(i64 × n) -> i64`, shared by lifted
+    /// lambdas and call_indirect sites
+    indirect_types: HashMap<usize, u32>,
+    /// Linear memory limits
+    options: WasmCodegenOptions,
+    /// Module name for the `name` custom section; `None` omits the section
+    debug_names: Option<String>,
+    /// URL written to the `sourceMappingURL` custom section, if any
+    source_map_url: Option<String>,
+    /// Function name → source span of its definition, for source maps
+    func_spans: HashMap<String, Span>,
+    /// Component mode: (core export name, Ark function) pairs that replace
+    /// the regular function and global exports
+    component_exports: Option<Vec<(String, String)>>,
+}
+
+/// A host function supplied by the embedder (see
+/// [`WasmCodegen::register_host_import`]).
+#[derive(Debug, Clone)]
+struct HostImport {
+    module: String,
+    name: String,
+    params: Vec<ValType>,
+    results: Vec<ValType>,
+    /// Ark call name that routes to this import
+    intrinsic: String,
 }
 
 impl Default for WasmCodegen {
@@ -244,13 +1524,172 @@ impl WasmCodegen {
             types: Vec::new(),
             functions: Vec::new(),
             import_count: 0,
+            imports: Vec::new(),
+            import_map: HashMap::new(),
             data_segments: Vec::new(),
-            data_offset: 0,
+            data_offset: STRING_MEMORY_START,
             func_index_map: HashMap::new(),
             heap_start: STRING_MEMORY_START, // will be bumped during compilation
             func_attributes: HashMap::new(),
             lambda_counter: 0,
+            host_imports: Vec::new(),
+            deterministic: false,
+            source_hash: String::new(),
+            bounds_checks: true,
+            log_level: 1,
+            called: HashSet::new(),
+            struct_layouts: HashMap::new(),
+            enum_layouts: HashMap::new(),
+            exported_globals: Vec::new(),
+            float_signatures: HashMap::new(),
+            declared_params: HashMap::new(),
+            uses_floats: false,
+            closure_captures: HashMap::new(),
+            indirect_types: HashMap::new(),
+            options: WasmCodegenOptions::default(),
+            debug_names: None,
+            source_map_url: None,
+            func_spans: HashMap::new(),
+            component_exports: None,
+        }
+    }
+
+    /// Create a code generator with the given memory limits.
+    pub fn with_options(options: WasmCodegenOptions) -> Result<Self, WasmCompileError> {
+        let invalid = |message: String| WasmCompileError {
+            message,
+            context: "with_options".to_string(),
+            span: None,
+        };
+        if options.initial_memory_pages == 0 {
+            return Err(invalid(
+                "initial_memory_pages must be at least 1".to_string(),
+            ));
+        }
+        if let Some(max) = options.max_memory_pages {
+            if max > WASM32_MAX_PAGES {
+                return Err(invalid(format!(
+                    "max_memory_pages {} exceeds the wasm32 limit of {}",
+                    max, WASM32_MAX_PAGES
+                )));
+            }
+            if max < options.initial_memory_pages {
+                return Err(invalid(format!(
+                    "max_memory_pages {} is below initial_memory_pages {}",
+                    max, options.initial_memory_pages
+                )));
+            }
+        }
+        if options.fuel.is_some_and(|fuel| fuel > i64::MAX as u64) {
+            return Err(invalid(format!("fuel must be at most {}", i64::MAX)));
+        }
+        Ok(Self {
+            options,
+            ..Self::new()
+        })
+    }
+
+    /// Produce byte-reproducible output: the `ark.build` section is written
+    /// without a timestamp.
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+    }
+
+    /// Emit a `name` custom section so that debuggers and disassemblers show
+    /// Ark names: `module_name` for the module, plus the name of every
+    /// function, global and named local. Scratch locals are listed as
+    /// `$scratch<i>`. `None` (the default) leaves the section out.
+    pub fn set_debug_names(&mut self, module_name: Option<&str>) {
+        self.debug_names = module_name.map(str::to_string);
+    }
+
+    /// Point runtimes at a source map: the URL is written to a
+    /// `sourceMappingURL` custom section, which browser devtools follow to
+    /// load the map returned by
+    /// [`compile_with_source_map`](Self::compile_with_source_map).
+    pub fn set_source_map_url(&mut self, url: Option<&str>) {
+        self.source_map_url = url.map(str::to_string);
+    }
+
+    /// Toggle runtime bounds checks (on by default). When enabled, a
+    /// destructure whose source is not a list/struct of sufficient length
+    /// traps instead of reading past the allocation, and so does an
+    /// `__alloc` request with a negative or oversized size or a `__free` of
+    /// a pointer outside the heap.
+    pub fn set_bounds_checks(&mut self, enabled: bool) {
+        self.bounds_checks = enabled;
+    }
+
+    /// Set the minimum `sys.log` level kept in the output (`info` by
+    /// default). Calls below it are dropped at compile time, arguments
+    /// included.
+    pub fn set_log_level(&mut self, level: &str) -> Result<(), WasmCompileError> {
+        self.log_level = LOG_LEVELS
+            .iter()
+            .position(|l| l.eq_ignore_ascii_case(level))
+            .ok_or_else(|| WasmCompileError {
+                message: format!("unknown log level '{}'", level),
+                context: "set_log_level".to_string(),
+                span: None,
+            })?;
+        Ok(())
+    }
+
+    /// Expose a host function to compiled Ark code.
+    ///
+    /// The function is imported as `module.name` with the given WASM
+    /// signature, and Ark calls to `ark_intrinsic_name(...)` are routed to it
+    /// through a generated `__host_<ark_intrinsic_name>` shim that converts
+    /// between Ark's uniform i64 values and the import's parameter and result
+    /// types. Supported value types are i32, i64, f32 and f64, with at most
+    /// one result; a missing result reads back as unit (0). f32 and f64
+    /// parameters and results are Ark floats, so an integer argument is
+    /// converted. Built-in intrinsic names take precedence over registered
+    /// ones.
+    pub fn register_host_import(
+        &mut self,
+        module: &str,
+        name: &str,
+        params: Vec<ValType>,
+        results: Vec<ValType>,
+        ark_intrinsic_name: &str,
+    ) -> Result<(), WasmCompileError> {
+        let context = format!("host import {}.{}", module, name);
+        if results.len() > 1 {
+            return Err(WasmCompileError {
+                message: "host imports may return at most one value".to_string(),
+                context,
+                span: None,
+            });
+        }
+        let supported =
+            |t: &ValType| matches!(t, ValType::I32 | ValType::I64 | ValType::F32 | ValType::F64);
+        if !params.iter().chain(results.iter()).all(supported) {
+            return Err(WasmCompileError {
+                message: "host import signatures are limited to i32/i64/f32/f64".to_string(),
+                context,
+                span: None,
+            });
+        }
+        if self
+            .host_imports
+            .iter()
+            .any(|h| h.intrinsic == ark_intrinsic_name)
+        {
+            return Err(WasmCompileError {
+                message: format!("'{}' is already bound to a host import", ark_intrinsic_name),
+                context,
+                span: None,
+            });
         }
+        self.host_imports.push(HostImport {
+            module: module.to_string(),
+            name: name.to_string(),
+            params,
+            results,
+            intrinsic: ark_intrinsic_name.to_string(),
+        });
+        Ok(())
     }
 
     // =========================================================================
@@ -259,21 +1698,200 @@ impl WasmCodegen {
 
     /// Compile an ArkNode AST to a WASM binary (Vec<u8>).
     /// This is the main entry point — equivalent to `Compiler::compile()`.
-    pub fn compile(mut self, node: &ArkNode) -> Result<Vec<u8>, WasmCompileError> {
-        // Phase 1: Optimize the AST (reuse existing optimizer)
-        let optimized = optimize(node.clone(), 2);
+    pub fn compile(self, node: &ArkNode) -> Result<Vec<u8>, WasmCompileError> {
+        Ok(self.build(node, None)?.emit_module())
+    }
+
+    /// Like [`compile`](Self::compile), but also return a Source Map v3
+    /// (JSON) that maps the code of every function back to the `func` line
+    /// it was defined on. Spans come from the AST, so parse with
+    /// [`parse_source_with_spans`](crate::parser::parse_source_with_spans);
+    /// functions without one are left unmapped.
+    pub fn compile_with_source_map(
+        self,
+        node: &ArkNode,
+    ) -> Result<(Vec<u8>, String), WasmCompileError> {
+        let codegen = self.build(node, None)?;
+        let wasm = codegen.emit_module();
+        let source_map = codegen.source_map(&wasm)?;
+        Ok((wasm, source_map))
+    }
+
+    /// Compile to a WASM Component implementing a generated WIT world: the
+    /// world of [`generate_wit`](crate::wit_gen::generate_wit) for
+    /// `package` (e.g. `"ark:program"`), exporting the `#[export]`
+    /// functions (all functions when none is marked). Top-level code runs
+    /// when the component is instantiated.
+    ///
+    /// `wasi_adapter` is a `wasi_snapshot_preview1` reactor adapter module
+    /// (as shipped with wasmtime); it maps the module's WASI Preview 1
+    /// imports onto WASI Preview 2, so the generator must target
+    /// [`WasiTarget::Preview1`]. Only `s64` values cross the component
+    /// boundary for now, so every exported parameter and result must be
+    /// an `Int` or untyped.
+    pub fn compile_component(
+        self,
+        node: &ArkNode,
+        package: &str,
+        wasi_adapter: &[u8],
+    ) -> Result<WasmComponent, WasmCompileError> {
+        let component_error = |e: &dyn fmt::Display| WasmCompileError {
+            message: e.to_string(),
+            context: "component".to_string(),
+            span: None,
+        };
+        if self.options.wasi_target != WasiTarget::Preview1 {
+            return Err(component_error(
+                &"the Preview1 adapter needs a module targeting WASI Preview 1",
+            ));
+        }
+        let (mut core, wit) = self.compile_component_core(node, package)?;
+
+        let mut resolve = wit_parser::Resolve::default();
+        let pkg = resolve
+            .push_str("ark.wit", &wit)
+            .map_err(|e| component_error(&e))?;
+        let world = resolve
+            .select_world(pkg, None)
+            .map_err(|e| component_error(&e))?;
+        wit_component::embed_component_metadata(
+            &mut core,
+            &resolve,
+            world,
+            wit_component::StringEncoding::UTF8,
+        )
+        .map_err(|e| component_error(&e))?;
+
+        let bytes = wit_component::ComponentEncoder::default()
+            .module(&core)
+            .and_then(|encoder| encoder.adapter("wasi_snapshot_preview1", wasi_adapter))
+            .and_then(|encoder| encoder.validate(true).encode())
+            .map_err(|e| component_error(&e))?;
+        Ok(WasmComponent { bytes, wit })
+    }
+
+    /// The core module of [`compile_component`](Self::compile_component)
+    /// and its WIT. Each WIT function is exported under its canonical ABI
+    /// name, `package/interface#function`, and `_start` as `_initialize`.
+    fn compile_component_core(
+        self,
+        node: &ArkNode,
+        package: &str,
+    ) -> Result<(Vec<u8>, String), WasmCompileError> {
+        let component_error = |message: String| WasmCompileError {
+            message,
+            context: "component".to_string(),
+            span: None,
+        };
+        let iface =
+            WitInterface::from_ast(node, package).map_err(|e| component_error(e.to_string()))?;
+        let func_defs = self.extract_function_defs(node);
 
-        // Phase 2: Register WASI imports
+        let mut exports = Vec::new();
+        for func in &iface.functions {
+            let func_def = func_defs
+                .iter()
+                .find(|def| to_wit_ident(&def.name) == func.name)
+                .ok_or_else(|| component_error(format!("no function for WIT '{}'", func.name)))?;
+            let is_s64 =
+                |ty: &ArkType| matches!(ty, ArkType::Integer | ArkType::Any | ArkType::Unknown);
+            if let Some((param, ty)) = func_def.inputs.iter().find(|(_, ty)| !is_s64(ty)) {
+                return Err(component_error(format!(
+                    "parameter '{}' of exported function '{}' is {:?}; only s64 values \
+                     cross the component boundary",
+                    param, func_def.name, ty
+                )));
+            }
+            if !is_s64(&func_def.output) {
+                return Err(component_error(format!(
+                    "exported function '{}' returns {:?}; only s64 values cross the \
+                     component boundary",
+                    func_def.name, func_def.output
+                )));
+            }
+            exports.push((
+                format!("{}/{}#{}", iface.package, iface.name, func.name),
+                func_def.name.clone(),
+            ));
+        }
+
+        let mut codegen = self.build(node, None)?;
+        codegen.component_exports = Some(exports);
+        Ok((codegen.emit_module(), iface.render()))
+    }
+
+    /// Like [`compile`](Self::compile), but reuse function bodies from
+    /// `cache` where the function is unchanged, and record this build's
+    /// bodies for the next one. The output is identical to an uncached
+    /// build.
+    pub fn compile_with_cache(
+        self,
+        node: &ArkNode,
+        cache: &mut CompileCache,
+    ) -> Result<Vec<u8>, WasmCompileError> {
+        cache.begin_build();
+        let wasm = self.build(node, Some(&mut *cache))?.emit_module();
+        cache.finish_build();
+        Ok(wasm)
+    }
+
+    /// Run every compilation phase short of emitting the module.
+    fn build(
+        mut self,
+        node: &ArkNode,
+        cache: Option<&mut CompileCache>,
+    ) -> Result<Self, WasmCompileError> {
+        // Phase 0: Every struct needs a finite layout
+        if let Some(err) = LinearChecker::check_struct_sizes(node).into_iter().next() {
+            return Err(WasmCompileError {
+                message: err.to_string(),
+                context: "struct layout".to_string(),
+                span: None,
+            });
+        }
+
+        let serialized = serde_json::to_vec(node).map_err(|e| WasmCompileError {
+            message: format!("Failed to serialize AST for hashing: {}", e),
+            context: "build info".to_string(),
+            span: None,
+        })?;
+        self.source_hash = hex::encode(Sha256::digest(&serialized));
+
+        // Phase 1: Optimize the AST (reuse existing optimizer)
+        let optimized = optimize(node.clone(), OPT_LEVEL);
+
+        // Phase 2: Register WASI imports (and the host imports actually used)
+        self.called = Self::called_names(&optimized);
+        self.struct_layouts = Self::declared_struct_layouts(&optimized);
+        self.enum_layouts = Self::declared_enum_layouts(&optimized);
+        self.exported_globals = Self::exported_lets(&optimized);
+        self.collect_float_signatures(&optimized);
         self.register_wasi_imports();
+        self.register_helpers()?;
 
         // Phase 3: Collect all top-level function definitions first (forward declarations)
         self.collect_functions(&optimized)?;
+        if let Some((name, _)) = self
+            .exported_globals
+            .iter()
+            .find(|(name, _)| self.func_index_map.contains_key(name))
+        {
+            return Err(WasmCompileError {
+                message: format!(
+                    "exported binding '{}' has the same name as a function",
+                    name
+                ),
+                context: "#[export] let".to_string(),
+                span: None,
+            });
+        }
 
         // Phase 3.5: Collect and register lambdas as synthetic top-level functions
         self.collect_lambdas(&optimized)?;
+        self.collect_indirect_call_types(&optimized);
 
         // Phase 4: Compile each function body
-        self.compile_collected_functions(&optimized)?;
+        self.compile_collected_functions(&optimized, cache)?;
 
         // Phase 4.5: Compile lambda function bodies
         self.compile_lambda_bodies(&optimized)?;
@@ -281,8 +1899,21 @@ impl WasmCodegen {
         // Phase 5: Compile top-level code as `_start` function
         self.compile_start_function(&optimized)?;
 
-        // Phase 6: Emit the WASM module
-        Ok(self.emit_module())
+        // Phase 6: Check that the static data fits the memory limits
+        let static_pages = self.static_memory_pages();
+        if let Some(max) = self.options.max_memory_pages {
+            if static_pages > max {
+                return Err(WasmCompileError {
+                    message: format!(
+                        "static data needs {} memory pages but max_memory_pages is {}",
+                        static_pages, max
+                    ),
+                    context: "memory limits".to_string(),
+                    span: None,
+                });
+            }
+        }
+        Ok(self)
     }
 
     /// Compile to WASM and return only the raw bytes (convenience).
@@ -297,38 +1928,28 @@ impl WasmCodegen {
 
     fn register_wasi_imports(&mut self) {
         // =====================================================================
-        // WASI Preview1 type signatures (6 unique types for 11 imports)
+        // WASI Preview1 imports (or WASI 0.2 imports behind Preview1 shims)
         // =====================================================================
+        const WASI: &str = "wasi_snapshot_preview1";
 
-        // Type 0: fd_write / fd_read (fd:i32, iovs:i32, iovs_len:i32, nwritten:i32) -> i32
-        self.types.push((
+        // (fd:i32, iovs:i32, iovs_len:i32, nwritten:i32) -> i32
+        let fd_rw_ty = self.push_type(
             vec![ValType::I32, ValType::I32, ValType::I32, ValType::I32],
             vec![ValType::I32],
-        ));
-
-        // Type 1: clock_time_get(clock_id:i32, precision:i64, timestamp_ptr:i32) -> i32
-        self.types.push((
+        );
+        // clock_time_get(clock_id:i32, precision:i64, timestamp_ptr:i32) -> i32
+        let clock_ty = self.push_type(
             vec![ValType::I32, ValType::I64, ValType::I32],
             vec![ValType::I32],
-        ));
-
-        // Type 2: random_get(buf:i32, buf_len:i32) -> i32
-        self.types
-            .push((vec![ValType::I32, ValType::I32], vec![ValType::I32]));
-
-        // Type 3: args_get / args_sizes_get / environ_get / environ_sizes_get / fd_close
-        //         (ptr1:i32, ptr2:i32) -> i32
-        // (same signature as random_get but semantically different — reuse type 2)
-        // Actually fd_close is (fd:i32) -> i32, needs its own. Let's keep type 3 = (i32,i32)->i32
-        // We can reuse type 2 for these since signature matches.
-
-        // Type 3: proc_exit(code:i32) -> ()
-        self.types.push((vec![ValType::I32], vec![]));
-
-        // Type 4: path_open(fd:i32, dirflags:i32, path:i32, path_len:i32,
-        //                   oflags:i32, rights_base:i64, rights_inherit:i64,
-        //                   fdflags:i32, opened_fd:i32) -> i32
-        self.types.push((
+        );
+        // (ptr1:i32, ptr2:i32) -> i32 — random_get, args_*, environ_*
+        let ptr_pair_ty = self.push_type(vec![ValType::I32, ValType::I32], vec![ValType::I32]);
+        // proc_exit(code:i32) -> ()
+        let proc_exit_ty = self.push_type(vec![ValType::I32], vec![]);
+        // path_open(fd:i32, dirflags:i32, path:i32, path_len:i32,
+        //           oflags:i32, rights_base:i64, rights_inherit:i64,
+        //           fdflags:i32, opened_fd:i32) -> i32
+        let path_open_ty = self.push_type(
             vec![
                 ValType::I32,
                 ValType::I32,
@@ -341,2457 +1962,10152 @@ impl WasmCodegen {
                 ValType::I32,
             ],
             vec![ValType::I32],
-        ));
+        );
+        // fd_close(fd:i32) -> i32
+        let fd_close_ty = self.push_type(vec![ValType::I32], vec![ValType::I32]);
+        // fd_seek(fd:i32, offset:i64, whence:i32, newoffset:i32) -> i32
+        let fd_seek_ty = self.push_type(
+            vec![ValType::I32, ValType::I64, ValType::I32, ValType::I32],
+            vec![ValType::I32],
+        );
 
-        // Type 5: fd_close(fd:i32) -> i32
-        self.types.push((vec![ValType::I32], vec![ValType::I32]));
+        let preview2 = match self.options.wasi_target {
+            WasiTarget::Preview1 => {
+                self.declare_import(WASI, "fd_write", fd_rw_ty);
+                self.declare_import(WASI, "fd_read", fd_rw_ty);
+                self.declare_import(WASI, "clock_time_get", clock_ty);
+                self.declare_import(WASI, "random_get", ptr_pair_ty);
+                self.declare_import(WASI, "args_get", ptr_pair_ty);
+                self.declare_import(WASI, "args_sizes_get", ptr_pair_ty);
+                self.declare_import(WASI, "environ_get", ptr_pair_ty);
+                self.declare_import(WASI, "environ_sizes_get", ptr_pair_ty);
+                self.declare_import(WASI, "proc_exit", proc_exit_ty);
+                self.declare_import(WASI, "path_open", path_open_ty);
+                self.declare_import(WASI, "fd_close", fd_close_ty);
+                self.declare_import(WASI, "fd_seek", fd_seek_ty);
+                None
+            }
+            WasiTarget::Preview2 => Some(self.declare_wasi_preview2_imports()),
+        };
 
         // =====================================================================
-        // Ark Host Import type signatures (5 unique types for 14 imports)
+        // Ark Host imports
         // =====================================================================
+        const HOST: &str = "ark_host";
 
-        // Type 6: (i64) -> i64  — math unary (sin, cos, tan, asin, acos, atan, sqrt)
-        self.types.push((vec![ValType::I64], vec![ValType::I64]));
-
-        // Type 7: (i64, i64) -> i64  — math binary (atan2, pow)
-        self.types
-            .push((vec![ValType::I64, ValType::I64], vec![ValType::I64]));
-
-        // Type 8: (i64, i64, i64) -> i64  — math ternary (pow_mod)
-        self.types.push((
+        // Float math takes and returns real f64; only pow_mod is integer
+        let unary_ty = self.push_type(vec![ValType::F64], vec![ValType::F64]);
+        let binary_ty = self.push_type(vec![ValType::F64, ValType::F64], vec![ValType::F64]);
+        let ternary_ty = self.push_type(
             vec![ValType::I64, ValType::I64, ValType::I64],
             vec![ValType::I64],
-        ));
-
-        // Type 9: (i32, i32, i32) -> i32  — memory-based (crypto_sha512, json_parse, json_stringify)
-        self.types.push((
+        );
+        // Memory-based: (in_ptr, in_len, out_ptr) -> out_len
+        let mem3_ty = self.push_type(
             vec![ValType::I32, ValType::I32, ValType::I32],
             vec![ValType::I32],
-        ));
-
-        // Type 10: (i32, i32, i32, i32) -> i32  — memory-based (ask_ai)
-        // Note: same as WASI fd_write type but under ark_host module
-        self.types.push((
+        );
+        // ask_ai: (prompt_ptr, prompt_len, out_ptr, out_cap) -> out_len
+        let mem4_ty = self.push_type(
             vec![ValType::I32, ValType::I32, ValType::I32, ValType::I32],
             vec![ValType::I32],
-        ));
+        );
 
-        self.import_count = TOTAL_IMPORT_COUNT;
+        for (name, arity, intrinsics) in MATH_HOST_IMPORTS {
+            if !intrinsics.iter().any(|i| self.called.contains(*i)) {
+                continue;
+            }
+            let ty = match arity {
+                1 => unary_ty,
+                2 => binary_ty,
+                _ => ternary_ty,
+            };
+            self.declare_import(HOST, name, ty);
+        }
+        self.declare_import(HOST, "crypto_sha512", mem3_ty);
+        self.declare_import(HOST, "json_parse", mem3_ty);
+        self.declare_import(HOST, "json_stringify", mem3_ty);
+        self.declare_import(HOST, "ask_ai", mem4_ty);
+        for (name, intrinsics) in DIGEST_HOST_IMPORTS {
+            if intrinsics.iter().any(|i| self.called.contains(*i)) {
+                self.declare_import(HOST, name, mem3_ty);
+            }
+        }
 
-        // =====================================================================
-        // __alloc internal function
-        // =====================================================================
+        // Embedder imports follow the built-ins: one fresh type each.
+        let mut host_import_indices = Vec::with_capacity(self.host_imports.len());
+        for import in self.host_imports.clone() {
+            let ty = self.push_type(import.params, import.results);
+            host_import_indices.push(self.declare_import(&import.module, &import.name, ty));
+        }
 
-        // Type for __alloc: (size: i64) -> i64 (matches Ark's i64 calling convention)
-        let alloc_type_idx = self.types.len() as u32;
-        self.types.push((vec![ValType::I64], vec![ValType::I64]));
-        let alloc_func_idx = self.import_count + self.functions.len() as u32;
-        self.func_index_map
-            .insert("__alloc".to_string(), alloc_func_idx);
+        self.import_count = self.imports.len() as u32;
 
-        // Build __alloc body:
-        //   fn __alloc(size: i32) -> i32 {
-        //       let ptr = global.__heap_ptr;
-        //       global.__heap_ptr = ptr + ((size + 7) & !7);  // 8-byte aligned
-        //       return ptr;
-        //   }
-        let mut ctx = FuncContext::new(1); // 1 parameter: size
-        let ptr_local = ctx.scope.get_or_alloc("__alloc_ptr");
+        if let Some(preview2) = preview2 {
+            self.register_wasi_preview2_shims(
+                &preview2,
+                fd_rw_ty,
+                clock_ty,
+                ptr_pair_ty,
+                proc_exit_ty,
+            );
+        }
+        self.register_allocator();
 
-        // ptr = global.get(0)  (__heap_ptr is global index 0)
-        ctx.emit(Instruction::GlobalGet(0));
-        ctx.emit(Instruction::I64ExtendI32U); // convert to i64 to store in local
-        ctx.emit(Instruction::LocalSet(ptr_local));
+        if self.called.contains("print") || self.called.contains("intrinsic_print") {
+            self.register_print_helpers();
+        }
 
-        // global.set(0, ptr + align8(size))
-        // align8(size) = (size + 7) & ~7
-        ctx.emit(Instruction::LocalGet(ptr_local));
-        ctx.emit(Instruction::I32WrapI64); // ptr as i32
-        ctx.emit(Instruction::LocalGet(0)); // size param (i64)
-        ctx.emit(Instruction::I32WrapI64); // size as i32
-        ctx.emit(Instruction::I32Const(7));
-        ctx.emit(Instruction::I32Add); // size + 7
-        ctx.emit(Instruction::I32Const(!7)); // ~7 = 0xFFFF_FFF8
-        ctx.emit(Instruction::I32And); // (size + 7) & ~7
-        ctx.emit(Instruction::I32Add); // ptr + aligned_size
-        ctx.emit(Instruction::GlobalSet(0)); // update __heap_ptr
+        self.register_host_import_shims(&host_import_indices);
+    }
 
-        // return ptr (already i64 from the I64ExtendI32U at store time)
-        ctx.emit(Instruction::LocalGet(ptr_local));
-        ctx.emit(Instruction::End);
+    /// Import the WASI 0.2 functions the Preview2 shims are built on, with
+    /// their canonical ABI core signatures.
+    fn declare_wasi_preview2_imports(&mut self) -> WasiPreview2Imports {
+        const STDOUT: &str = "wasi:cli/stdout@0.2.0";
+        const STDERR: &str = "wasi:cli/stderr@0.2.0";
+        const STREAMS: &str = "wasi:io/streams@0.2.0";
+        const WALL_CLOCK: &str = "wasi:clocks/wall-clock@0.2.0";
+        const MONOTONIC_CLOCK: &str = "wasi:clocks/monotonic-clock@0.2.0";
+        const RANDOM: &str = "wasi:random/random@0.2.0";
+        const EXIT: &str = "wasi:cli/exit@0.2.0";
+
+        // get-stdout / get-stderr: () -> own<output-stream>
+        let get_stream_ty = self.push_type(vec![], vec![ValType::I32]);
+        // blocking-write-and-flush(self, contents.ptr, contents.len, ret_ptr)
+        let write_ty = self.push_type(vec![ValType::I32; 4], vec![]);
+        // (handle) -> (), also wall-clock now(ret_ptr) and exit(status)
+        let i32_arg_ty = self.push_type(vec![ValType::I32], vec![]);
+        // monotonic-clock now / get-random-u64: () -> u64
+        let u64_ty = self.push_type(vec![], vec![ValType::I64]);
+
+        WasiPreview2Imports {
+            get_stdout: self.declare_import(STDOUT, "get-stdout", get_stream_ty),
+            get_stderr: self.declare_import(STDERR, "get-stderr", get_stream_ty),
+            blocking_write_and_flush: self.declare_import(
+                STREAMS,
+                "[method]output-stream.blocking-write-and-flush",
+                write_ty,
+            ),
+            drop_output_stream: self.declare_import(
+                STREAMS,
+                "[resource-drop]output-stream",
+                i32_arg_ty,
+            ),
+            wall_clock_now: self.declare_import(WALL_CLOCK, "now", i32_arg_ty),
+            monotonic_clock_now: self.declare_import(MONOTONIC_CLOCK, "now", u64_ty),
+            get_random_u64: self.declare_import(RANDOM, "get-random-u64", u64_ty),
+            exit: self.declare_import(EXIT, "exit", i32_arg_ty),
+        }
+    }
 
-        self.functions
-            .push((alloc_type_idx, "__alloc".to_string(), ctx));
+    /// Implement the Preview1 functions the backend calls (`fd_write`,
+    /// `clock_time_get`, `random_get` and `proc_exit`) on top of WASI 0.2,
+    /// publishing each shim under the import name its callers look up.
+    fn register_wasi_preview2_shims(
+        &mut self,
+        imports: &WasiPreview2Imports,
+        fd_write_ty: u32,
+        clock_ty: u32,
+        random_ty: u32,
+        proc_exit_ty: u32,
+    ) {
+        let shims: [(&str, u32, u32, fn(&mut FuncContext, &WasiPreview2Imports)); 4] = [
+            ("fd_write", fd_write_ty, 4, Self::emit_wasi_p2_fd_write),
+            (
+                "clock_time_get",
+                clock_ty,
+                3,
+                Self::emit_wasi_p2_clock_time_get,
+            ),
+            ("random_get", random_ty, 2, Self::emit_wasi_p2_random_get),
+            ("proc_exit", proc_exit_ty, 1, Self::emit_wasi_p2_proc_exit),
+        ];
+        for (name, type_idx, param_count, emit_body) in shims {
+            let mut ctx = FuncContext::new(param_count);
+            emit_body(&mut ctx, imports);
+            ctx.emit(Instruction::End);
+
+            let func_idx = self.import_count + self.functions.len() as u32;
+            self.func_index_map
+                .insert(format!("{}{}", IMPORT_KEY_PREFIX, name), func_idx);
+            self.functions
+                .push((type_idx, format!("__wasi_{}", name), ctx));
+        }
     }
 
-    // =========================================================================
-    // Function Collection (Forward Declarations)
-    // =========================================================================
+    /// `fd_write(fd, iovs, iovs_len, nwritten) -> errno`: fd 2 goes to
+    /// stderr and anything else to stdout, in writes of at most 4096 bytes
+    /// as `blocking-write-and-flush` requires. A failed write is EIO.
+    fn emit_wasi_p2_fd_write(ctx: &mut FuncContext, imports: &WasiPreview2Imports) {
+        let WasiPreview2Imports {
+            get_stdout,
+            get_stderr,
+            blocking_write_and_flush,
+            drop_output_stream,
+            ..
+        } = *imports;
+        let stream = ctx.scope.get_or_alloc("__wasi_stream");
+        let iov = ctx.scope.get_or_alloc("__wasi_iov");
+        let iov_end = ctx.scope.get_or_alloc("__wasi_iov_end");
+        let ptr = ctx.scope.get_or_alloc("__wasi_ptr");
+        let len = ctx.scope.get_or_alloc("__wasi_len");
+        let chunk = ctx.scope.get_or_alloc("__wasi_chunk");
+        let total = ctx.scope.get_or_alloc("__wasi_total");
+        let mem32 = |offset| wasm_encoder::MemArg {
+            offset,
+            align: 2,
+            memory_index: 0,
+        };
 
-    fn collect_functions(&mut self, node: &ArkNode) -> Result<(), WasmCompileError> {
-        match node {
-            ArkNode::Function(func_def) => {
-                self.register_function(func_def)?;
-            }
-            ArkNode::Statement(Statement::Function(func_def)) => {
-                self.register_function(func_def)?;
-            }
-            ArkNode::Statement(Statement::Block(stmts)) => {
-                for stmt in stmts {
-                    if let Statement::Function(func_def) = stmt {
-                        self.register_function(func_def)?;
-                    }
-                }
-            }
-            _ => {}
-        }
-        Ok(())
-    }
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I32Const(2));
+        ctx.emit(Instruction::I32Eq);
+        ctx.emit(Instruction::If(BlockType::Result(ValType::I32)));
+        ctx.emit(Instruction::Call(get_stderr));
+        ctx.emit(Instruction::Else);
+        ctx.emit(Instruction::Call(get_stdout));
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::I64ExtendI32U);
+        ctx.emit(Instruction::LocalSet(stream));
 
-    // =========================================================================
-    // Lambda Collection (Pre-scan for lambda lifting)
-    // =========================================================================
+        // iov walks the iovec array up to iov_end = iovs + 8 * iovs_len
+        ctx.emit(Instruction::LocalGet(1));
+        ctx.emit(Instruction::I64ExtendI32U);
+        ctx.emit(Instruction::LocalTee(iov));
+        ctx.emit(Instruction::LocalGet(2));
+        ctx.emit(Instruction::I64ExtendI32U);
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::I64Mul);
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalSet(iov_end));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(total));
 
-    /// Walk the AST to find all `Expression::Lambda` nodes and register them
-    /// as synthetic top-level functions (`__lambda_0`, `__lambda_1`, etc.).
-    /// This enables call_indirect dispatch for higher-order functions.
-    fn collect_lambdas(&mut self, node: &ArkNode) -> Result<(), WasmCompileError> {
-        match node {
-            ArkNode::Statement(Statement::Block(stmts)) => {
-                for stmt in stmts {
-                    self.scan_stmt_for_lambdas(stmt)?;
-                }
-            }
-            ArkNode::Statement(stmt) => {
-                self.scan_stmt_for_lambdas(stmt)?;
-            }
-            ArkNode::Function(func_def) => {
-                self.scan_stmts_for_lambdas_in_body(&func_def.body)?;
-            }
-            _ => {}
-        }
-        Ok(())
-    }
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(iov));
+            ctx.emit(Instruction::LocalGet(iov_end));
+            ctx.emit(Instruction::I64GeU);
+            ctx.emit(Instruction::BrIf(1));
 
-    /// Recursively scan a statement for lambda expressions.
-    fn scan_stmt_for_lambdas(&mut self, stmt: &Statement) -> Result<(), WasmCompileError> {
-        match stmt {
-            Statement::Let { value, .. } => {
-                self.scan_expr_for_lambdas(value)?;
-            }
-            Statement::Block(stmts) => {
-                for s in stmts {
-                    self.scan_stmt_for_lambdas(s)?;
-                }
-            }
-            Statement::Expression(expr) => {
-                self.scan_expr_for_lambdas(expr)?;
-            }
-            Statement::Return(expr) => {
-                self.scan_expr_for_lambdas(expr)?;
-            }
-            Statement::If {
-                condition,
-                then_block,
-                else_block,
-            } => {
-                self.scan_expr_for_lambdas(condition)?;
-                for s in then_block {
-                    self.scan_stmt_for_lambdas(s)?;
-                }
-                if let Some(else_stmts) = else_block {
-                    for s in else_stmts {
-                        self.scan_stmt_for_lambdas(s)?;
-                    }
-                }
-            }
-            Statement::While { condition, body } => {
-                self.scan_expr_for_lambdas(condition)?;
-                for s in body {
-                    self.scan_stmt_for_lambdas(s)?;
+            ctx.emit(Instruction::LocalGet(iov));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I64Load32U(mem32(0)));
+            ctx.emit(Instruction::LocalSet(ptr));
+            ctx.emit(Instruction::LocalGet(iov));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I64Load32U(mem32(4)));
+            ctx.emit(Instruction::LocalSet(len));
+
+            ctx.emit(Instruction::Block(BlockType::Empty));
+            ctx.emit(Instruction::Loop(BlockType::Empty));
+            {
+                ctx.emit(Instruction::LocalGet(len));
+                ctx.emit(Instruction::I64Eqz);
+                ctx.emit(Instruction::BrIf(1));
+
+                // chunk = min(len, 4096)
+                ctx.emit(Instruction::LocalGet(len));
+                ctx.emit(Instruction::I64Const(4096));
+                ctx.emit(Instruction::LocalGet(len));
+                ctx.emit(Instruction::I64Const(4096));
+                ctx.emit(Instruction::I64LtU);
+                ctx.emit(Instruction::Select);
+                ctx.emit(Instruction::LocalSet(chunk));
+
+                ctx.emit(Instruction::LocalGet(stream));
+                ctx.emit(Instruction::I32WrapI64);
+                ctx.emit(Instruction::LocalGet(ptr));
+                ctx.emit(Instruction::I32WrapI64);
+                ctx.emit(Instruction::LocalGet(chunk));
+                ctx.emit(Instruction::I32WrapI64);
+                ctx.emit(Instruction::I32Const(WASI_P2_RET_AREA));
+                ctx.emit(Instruction::Call(blocking_write_and_flush));
+
+                // result<_, stream-error>: a non-zero tag is an error
+                ctx.emit(Instruction::I32Const(WASI_P2_RET_AREA));
+                ctx.emit(Instruction::I32Load8U(wasm_encoder::MemArg {
+                    offset: 0,
+                    align: 0,
+                    memory_index: 0,
+                }));
+                ctx.emit(Instruction::If(BlockType::Empty));
+                ctx.emit(Instruction::LocalGet(stream));
+                ctx.emit(Instruction::I32WrapI64);
+                ctx.emit(Instruction::Call(drop_output_stream));
+                ctx.emit(Instruction::I32Const(29)); // EIO
+                ctx.emit(Instruction::Return);
+                ctx.emit(Instruction::End);
+
+                for (local, op) in [(ptr, Instruction::I64Add), (len, Instruction::I64Sub)] {
+                    ctx.emit(Instruction::LocalGet(local));
+                    ctx.emit(Instruction::LocalGet(chunk));
+                    ctx.emit(op);
+                    ctx.emit(Instruction::LocalSet(local));
                 }
+                ctx.emit(Instruction::LocalGet(total));
+                ctx.emit(Instruction::LocalGet(chunk));
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::LocalSet(total));
+                ctx.emit(Instruction::Br(0));
             }
-            Statement::Function(func_def) => {
-                self.scan_stmts_for_lambdas_in_body(&func_def.body)?;
-            }
-            _ => {}
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::End);
+
+            ctx.emit(Instruction::LocalGet(iov));
+            ctx.emit(Instruction::I64Const(8));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(iov));
+            ctx.emit(Instruction::Br(0));
         }
-        Ok(())
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
+
+        ctx.emit(Instruction::LocalGet(stream));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::Call(drop_output_stream));
+
+        ctx.emit(Instruction::LocalGet(3));
+        ctx.emit(Instruction::LocalGet(total));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I32Store(mem32(0)));
+        ctx.emit(Instruction::I32Const(0));
     }
 
-    /// Scan a MastNode body for lambdas.
-    fn scan_stmts_for_lambdas_in_body(&mut self, body: &MastNode) -> Result<(), WasmCompileError> {
-        match &body.content {
-            ArkNode::Statement(Statement::Block(stmts)) => {
-                for s in stmts {
-                    self.scan_stmt_for_lambdas(s)?;
-                }
-            }
-            ArkNode::Statement(stmt) => {
-                self.scan_stmt_for_lambdas(stmt)?;
-            }
-            _ => {}
+    /// `clock_time_get(clock_id, precision, timestamp_ptr) -> errno`: the
+    /// monotonic clock (id 1) reads `wasi:clocks/monotonic-clock`, every
+    /// other id the wall clock, converted to nanoseconds.
+    fn emit_wasi_p2_clock_time_get(ctx: &mut FuncContext, imports: &WasiPreview2Imports) {
+        let WasiPreview2Imports {
+            wall_clock_now,
+            monotonic_clock_now,
+            ..
+        } = *imports;
+        ctx.emit(Instruction::LocalGet(2));
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I32Const(1));
+        ctx.emit(Instruction::I32Eq);
+        ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+        ctx.emit(Instruction::Call(monotonic_clock_now));
+        ctx.emit(Instruction::Else);
+        {
+            // datetime { seconds: u64 @0, nanoseconds: u32 @8 }
+            ctx.emit(Instruction::I32Const(WASI_P2_RET_AREA));
+            ctx.emit(Instruction::Call(wall_clock_now));
+            ctx.emit(Instruction::I32Const(WASI_P2_RET_AREA));
+            ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                offset: 0,
+                align: 3,
+                memory_index: 0,
+            }));
+            ctx.emit(Instruction::I64Const(1_000_000_000));
+            ctx.emit(Instruction::I64Mul);
+            ctx.emit(Instruction::I32Const(WASI_P2_RET_AREA));
+            ctx.emit(Instruction::I64Load32U(wasm_encoder::MemArg {
+                offset: 8,
+                align: 2,
+                memory_index: 0,
+            }));
+            ctx.emit(Instruction::I64Add);
         }
-        Ok(())
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: 0,
+        }));
+        ctx.emit(Instruction::I32Const(0));
     }
 
-    /// Scan an expression for lambda sub-expressions and register them.
-    fn scan_expr_for_lambdas(&mut self, expr: &Expression) -> Result<(), WasmCompileError> {
-        match expr {
-            Expression::Lambda {
-                params,
-                body: _body,
-            } => {
-                // Register this lambda as a synthetic top-level function
-                let lambda_name = format!("__lambda_{}", self.lambda_counter);
-                self.lambda_counter += 1;
+    /// `random_get(buf, buf_len) -> errno`: fills the buffer a byte at a
+    /// time from one `get-random-u64` per 8 bytes.
+    fn emit_wasi_p2_random_get(ctx: &mut FuncContext, imports: &WasiPreview2Imports) {
+        let i = ctx.scope.get_or_alloc("__wasi_i");
+        let bits = ctx.scope.get_or_alloc("__wasi_bits");
+
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(i));
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(i));
+            ctx.emit(Instruction::LocalGet(1));
+            ctx.emit(Instruction::I64ExtendI32U);
+            ctx.emit(Instruction::I64GeU);
+            ctx.emit(Instruction::BrIf(1));
+
+            ctx.emit(Instruction::LocalGet(i));
+            ctx.emit(Instruction::I64Const(7));
+            ctx.emit(Instruction::I64And);
+            ctx.emit(Instruction::I64Eqz);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            ctx.emit(Instruction::Call(imports.get_random_u64));
+            ctx.emit(Instruction::LocalSet(bits));
+            ctx.emit(Instruction::End);
 
-                let param_types: Vec<ValType> = params.iter().map(|_| ValType::I64).collect();
-                let return_types = vec![ValType::I64];
+            ctx.emit(Instruction::LocalGet(0));
+            ctx.emit(Instruction::LocalGet(i));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I32Add);
+            ctx.emit(Instruction::LocalGet(bits));
+            ctx.emit(Instruction::I64Store8(wasm_encoder::MemArg {
+                offset: 0,
+                align: 0,
+                memory_index: 0,
+            }));
+            ctx.emit(Instruction::LocalGet(bits));
+            ctx.emit(Instruction::I64Const(8));
+            ctx.emit(Instruction::I64ShrU);
+            ctx.emit(Instruction::LocalSet(bits));
 
-                let type_idx = self.types.len() as u32;
-                self.types.push((param_types, return_types));
+            ctx.emit(Instruction::LocalGet(i));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(i));
+            ctx.emit(Instruction::Br(0));
+        }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::I32Const(0));
+    }
 
-                let func_idx = self.import_count + self.functions.len() as u32;
-                self.func_index_map.insert(lambda_name.clone(), func_idx);
+    /// `proc_exit(code)`: `exit` with `ok` for 0 and `err` otherwise.
+    fn emit_wasi_p2_proc_exit(ctx: &mut FuncContext, imports: &WasiPreview2Imports) {
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I32Const(0));
+        ctx.emit(Instruction::I32Ne);
+        ctx.emit(Instruction::Call(imports.exit));
+        ctx.emit(Instruction::Unreachable);
+    }
 
-                let ctx = FuncContext::new(params.len() as u32);
-                self.functions.push((type_idx, lambda_name, ctx));
-            }
-            Expression::Call { args, .. } => {
-                for arg in args {
-                    self.scan_expr_for_lambdas(arg)?;
-                }
-            }
-            Expression::List(items) => {
-                for item in items {
-                    self.scan_expr_for_lambdas(item)?;
-                }
-            }
-            Expression::StructInit { fields } => {
-                for (_, val) in fields {
-                    self.scan_expr_for_lambdas(val)?;
-                }
-            }
-            Expression::GetField { obj, .. } => {
-                self.scan_expr_for_lambdas(obj)?;
-            }
-            Expression::Match { scrutinee, arms } => {
-                self.scan_expr_for_lambdas(scrutinee)?;
-                for (_, arm_expr) in arms {
-                    self.scan_expr_for_lambdas(arm_expr)?;
-                }
-            }
-            _ => {} // Variable, Literal, Integer — no lambdas inside
+    /// Emit the [`WASI_HELPERS`], [`MAP_HELPERS`], [`STRING_HELPERS`],
+    /// [`PERSISTENT_HELPERS`], [`PERSISTENT_NODE_HELPERS`],
+    /// [`BIGINT_HELPERS`], [`BIGINT_NODE_HELPERS`], [`TENSOR_HELPERS`]
+    /// (or [`TENSOR_SIMD_HELPERS`]) and [`DIGEST_HELPERS`] the program
+    /// calls: `__fs_read(path)`, `__fs_write(path, text)`,
+    /// `__fs_append(path, text)`, `__fs_open(path)`,
+    /// `__fs_read_chunk(fd, max)`, `__fs_seek(fd, offset)`, `__fs_close(fd)`,
+    /// `__args_get(index)`, `__env_get(name)`, `__map_get(map, key)`,
+    /// `__map_set(map, key, value)`, `__map_keys(map)`, the `__str_*`
+    /// UTF-8 helpers, the `__pvec_*` and `__pmap_*` persistent
+    /// collections, the `__big_*` big integers, the `__tensor_*` tensor
+    /// kernels and the `__crypto_*` digests. Paths are packed strings resolved against the
+    /// first preopened directory; any failed WASI call traps, as the
+    /// interpreter raises an error. Every helper is numbered before any
+    /// body is emitted, so helpers may call each other and themselves.
+    fn register_helpers(&mut self) -> Result<(), WasmCompileError> {
+        let tensor_helpers = if self.options.simd {
+            TENSOR_SIMD_HELPERS
+        } else {
+            TENSOR_HELPERS
+        };
+        let needed: Vec<_> = WASI_HELPERS
+            .iter()
+            .chain(MAP_HELPERS)
+            .chain(STRING_HELPERS)
+            .chain(PERSISTENT_HELPERS)
+            .chain(PERSISTENT_NODE_HELPERS)
+            .chain(BIGINT_HELPERS)
+            .chain(BIGINT_NODE_HELPERS)
+            .chain(tensor_helpers)
+            .chain(DIGEST_HELPERS)
+            .filter(|(_, _, intrinsics, _)| intrinsics.iter().any(|i| self.called.contains(*i)))
+            .collect();
+        for (slot, (name, ..)) in needed.iter().enumerate() {
+            let func_idx = self.import_count + (self.functions.len() + slot) as u32;
+            self.func_index_map.insert(name.to_string(), func_idx);
+        }
+        for (name, arity, _, emit_body) in needed {
+            let ty = self.push_type(vec![ValType::I64; *arity], vec![ValType::I64]);
+            let mut ctx = FuncContext::new(*arity as u32);
+            emit_body(&mut ctx, &self.func_index_map)?;
+            ctx.emit(Instruction::End);
+            self.functions.push((ty, name.to_string(), ctx));
         }
         Ok(())
     }
 
-    // =========================================================================
-    // Lambda Body Compilation
-    // =========================================================================
-
-    /// Compile the bodies of all registered lambda functions.
-    /// Walks the AST again to find Expression::Lambda nodes, matches them
-    /// to their registered __lambda_N slots, and compiles the body.
-    fn compile_lambda_bodies(&mut self, node: &ArkNode) -> Result<(), WasmCompileError> {
-        let mut lambda_exprs: Vec<(Vec<String>, Vec<Statement>)> = Vec::new();
-        Self::collect_lambda_exprs(node, &mut lambda_exprs);
+    /// Open the packed path in local 0 under the preopened directory and
+    /// leave the new fd (as i32) on the stack. Uses scratch bytes 48–51.
+    fn emit_fs_open(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+        oflags: i32,
+        rights: i64,
+        fdflags: i32,
+    ) -> Result<(), WasmCompileError> {
+        ctx.emit(Instruction::I32Const(WASI_PREOPEN_FD));
+        ctx.emit(Instruction::I32Const(WASI_LOOKUP_SYMLINK_FOLLOW));
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I64Const(32));
+        ctx.emit(Instruction::I64ShrU);
+        ctx.emit(Instruction::I32WrapI64); // path ptr
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I32WrapI64); // path len
+        ctx.emit(Instruction::I32Const(oflags));
+        ctx.emit(Instruction::I64Const(rights));
+        ctx.emit(Instruction::I64Const(0)); // rights inherited by nothing
+        ctx.emit(Instruction::I32Const(fdflags));
+        ctx.emit(Instruction::I32Const(48));
+        ctx.emit(Instruction::Call(Self::import_index(
+            func_map,
+            "path_open",
+        )?));
+        Self::emit_trap_on_errno(ctx);
 
-        let func_index_map = self.func_index_map.clone();
+        ctx.emit(Instruction::I32Const(48));
+        ctx.emit(Instruction::I32Load(wasm_encoder::MemArg {
+            offset: 0,
+            align: 2,
+            memory_index: 0,
+        }));
+        Ok(())
+    }
 
-        for (idx, (params, body)) in lambda_exprs.iter().enumerate() {
-            let lambda_name = format!("__lambda_{}", idx);
-            if let Some(&func_idx) = func_index_map.get(&lambda_name) {
-                // Find which slot in self.functions corresponds to this func_idx
-                let slot = (func_idx - self.import_count) as usize;
-                if slot < self.functions.len() {
-                    let param_count = params.len() as u32;
-                    let mut ctx = FuncContext::new(param_count);
+    /// Pop a WASI errno and trap unless it is zero.
+    fn emit_trap_on_errno(ctx: &mut FuncContext) {
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::Unreachable);
+        ctx.emit(Instruction::End);
+    }
 
-                    // Register parameter names as locals
-                    for (j, name) in params.iter().enumerate() {
-                        ctx.scope.locals.insert(name.clone(), j as u32);
-                    }
+    /// Store `{ buf_ptr, buf_len }` from two i64 locals into the iovec at
+    /// scratch bytes 32–39.
+    fn emit_fs_iovec(ctx: &mut FuncContext, ptr: u32, len: u32) {
+        for (local, offset) in [(ptr, 0), (len, 4)] {
+            ctx.emit(Instruction::I32Const(32));
+            ctx.emit(Instruction::LocalGet(local));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I32Store(wasm_encoder::MemArg {
+                offset,
+                align: 2,
+                memory_index: 0,
+            }));
+        }
+    }
 
-                    // Compile each body statement
-                    let body_len = body.len();
-                    if body_len == 0 {
-                        ctx.emit(Instruction::I64Const(0));
-                    } else {
-                        for (i, stmt) in body.iter().enumerate() {
-                            let is_last = i == body_len - 1;
-                            Self::compile_stmt(&mut ctx, stmt, is_last, &func_index_map)?;
-                        }
-                    }
+    /// Body of `__fs_read(path)`: read the whole file into a heap buffer
+    /// that doubles (from 4 KiB) whenever a read fills it, and return it as
+    /// a packed string.
+    fn emit_fs_read(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let alloc = func_map["__alloc"];
+        let free = func_map["__free"];
+        let fd = ctx.scope.get_or_alloc("__fs_fd");
+        let buf = ctx.scope.get_or_alloc("__fs_buf");
+        let cap = ctx.scope.get_or_alloc("__fs_cap");
+        let len = ctx.scope.get_or_alloc("__fs_len");
+        let grown = ctx.scope.get_or_alloc("__fs_grown");
+        let spare = ctx.scope.get_or_alloc("__fs_spare");
+        let nread = ctx.scope.get_or_alloc("__fs_nread");
+
+        Self::emit_fs_open(ctx, func_map, 0, WASI_RIGHT_FD_READ, 0)?;
+        ctx.emit(Instruction::I64ExtendI32U);
+        ctx.emit(Instruction::LocalSet(fd));
 
-                    // Ensure End opcode
-                    ctx.emit(Instruction::End);
+        ctx.emit(Instruction::I64Const(4096));
+        ctx.emit(Instruction::LocalTee(cap));
+        ctx.emit(Instruction::Call(alloc));
+        ctx.emit(Instruction::LocalSet(buf));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(len));
 
-                    // Place compiled context in the right function slot
-                    self.functions[slot].2 = ctx;
-                }
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            // Full buffer: move the bytes so far into one twice the size
+            ctx.emit(Instruction::LocalGet(len));
+            ctx.emit(Instruction::LocalGet(cap));
+            ctx.emit(Instruction::I64Eq);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            {
+                ctx.emit(Instruction::LocalGet(cap));
+                ctx.emit(Instruction::I64Const(2));
+                ctx.emit(Instruction::I64Mul);
+                ctx.emit(Instruction::LocalTee(cap));
+                ctx.emit(Instruction::Call(alloc));
+                ctx.emit(Instruction::LocalTee(grown));
+                ctx.emit(Instruction::I32WrapI64);
+                ctx.emit(Instruction::LocalGet(buf));
+                ctx.emit(Instruction::I32WrapI64);
+                ctx.emit(Instruction::LocalGet(len));
+                ctx.emit(Instruction::I32WrapI64);
+                ctx.emit(Instruction::MemoryCopy {
+                    src_mem: 0,
+                    dst_mem: 0,
+                });
+                ctx.emit(Instruction::LocalGet(buf));
+                ctx.emit(Instruction::Call(free));
+                ctx.emit(Instruction::Drop);
+                ctx.emit(Instruction::LocalGet(grown));
+                ctx.emit(Instruction::LocalSet(buf));
             }
+            ctx.emit(Instruction::End);
+
+            // fd_read(fd, iovs=32, 1, nread=48) into buf[len..cap]
+            ctx.emit(Instruction::LocalGet(buf));
+            ctx.emit(Instruction::LocalGet(len));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(nread));
+            ctx.emit(Instruction::LocalGet(cap));
+            ctx.emit(Instruction::LocalGet(len));
+            ctx.emit(Instruction::I64Sub);
+            ctx.emit(Instruction::LocalSet(spare));
+            Self::emit_fs_iovec(ctx, nread, spare);
+            ctx.emit(Instruction::LocalGet(fd));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I32Const(32));
+            ctx.emit(Instruction::I32Const(1));
+            ctx.emit(Instruction::I32Const(48));
+            ctx.emit(Instruction::Call(Self::import_index(func_map, "fd_read")?));
+            Self::emit_trap_on_errno(ctx);
+
+            // Stop at end of file (a read of 0 bytes)
+            ctx.emit(Instruction::I32Const(48));
+            ctx.emit(Instruction::I64Load32U(wasm_encoder::MemArg {
+                offset: 0,
+                align: 2,
+                memory_index: 0,
+            }));
+            ctx.emit(Instruction::LocalTee(nread));
+            ctx.emit(Instruction::I64Eqz);
+            ctx.emit(Instruction::BrIf(1));
+            ctx.emit(Instruction::LocalGet(len));
+            ctx.emit(Instruction::LocalGet(nread));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(len));
+            ctx.emit(Instruction::Br(0));
         }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
 
+        ctx.emit(Instruction::LocalGet(fd));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::Call(Self::import_index(func_map, "fd_close")?));
+        ctx.emit(Instruction::Drop);
+
+        // buf << 32 | len
+        ctx.emit(Instruction::LocalGet(buf));
+        ctx.emit(Instruction::I64Const(32));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::LocalGet(len));
+        ctx.emit(Instruction::I64Or);
         Ok(())
     }
 
-    /// Collect all lambda expressions from the AST (in order of appearance).
-    fn collect_lambda_exprs(node: &ArkNode, out: &mut Vec<(Vec<String>, Vec<Statement>)>) {
-        match node {
-            ArkNode::Statement(Statement::Block(stmts)) => {
-                for stmt in stmts {
-                    Self::collect_lambda_exprs_from_stmt(stmt, out);
-                }
-            }
-            ArkNode::Statement(stmt) => {
-                Self::collect_lambda_exprs_from_stmt(stmt, out);
-            }
-            ArkNode::Function(func_def) => {
-                Self::collect_lambda_exprs_from_mast(&func_def.body, out);
-            }
-            _ => {}
-        }
+    /// Body of `__fs_open(path)`: open the file for reading and seeking; its
+    /// fd is the handle the other streaming helpers take.
+    fn emit_fs_open_stream(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_fs_open(ctx, func_map, 0, WASI_RIGHT_FD_READ | WASI_RIGHT_FD_SEEK, 0)?;
+        ctx.emit(Instruction::I64ExtendI32U);
+        Ok(())
     }
 
-    fn collect_lambda_exprs_from_stmt(
-        stmt: &Statement,
-        out: &mut Vec<(Vec<String>, Vec<Statement>)>,
-    ) {
-        match stmt {
-            Statement::Let { value, .. } => {
-                Self::collect_lambda_exprs_from_expr(value, out);
-            }
-            Statement::Block(stmts) => {
-                for s in stmts {
-                    Self::collect_lambda_exprs_from_stmt(s, out);
-                }
-            }
-            Statement::Expression(expr) => {
-                Self::collect_lambda_exprs_from_expr(expr, out);
-            }
-            Statement::Return(expr) => {
-                Self::collect_lambda_exprs_from_expr(expr, out);
-            }
-            Statement::If {
-                condition,
-                then_block,
-                else_block,
-            } => {
-                Self::collect_lambda_exprs_from_expr(condition, out);
-                for s in then_block {
-                    Self::collect_lambda_exprs_from_stmt(s, out);
-                }
-                if let Some(else_stmts) = else_block {
-                    for s in else_stmts {
-                        Self::collect_lambda_exprs_from_stmt(s, out);
-                    }
-                }
-            }
-            Statement::While { condition, body } => {
-                Self::collect_lambda_exprs_from_expr(condition, out);
-                for s in body {
-                    Self::collect_lambda_exprs_from_stmt(s, out);
-                }
-            }
-            Statement::Function(func_def) => {
-                Self::collect_lambda_exprs_from_mast(&func_def.body, out);
-            }
-            _ => {}
-        }
-    }
+    /// Body of `__fs_read_chunk(fd, max)`: read from the current position
+    /// until `max` bytes or end of file, and return the list `[chunk, fd]`,
+    /// handing the handle back as the interpreter does. A negative `max`
+    /// traps.
+    fn emit_fs_read_chunk(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let buf = ctx.scope.get_or_alloc("__fs_buf");
+        let len = ctx.scope.get_or_alloc("__fs_len");
+        let spare = ctx.scope.get_or_alloc("__fs_spare");
+        let nread = ctx.scope.get_or_alloc("__fs_nread");
+        let list = ctx.scope.get_or_alloc("__fs_list");
 
-    fn collect_lambda_exprs_from_mast(
-        mast: &MastNode,
-        out: &mut Vec<(Vec<String>, Vec<Statement>)>,
-    ) {
-        match &mast.content {
-            ArkNode::Statement(Statement::Block(stmts)) => {
-                for s in stmts {
-                    Self::collect_lambda_exprs_from_stmt(s, out);
-                }
-            }
-            ArkNode::Statement(stmt) => {
-                Self::collect_lambda_exprs_from_stmt(stmt, out);
-            }
-            _ => {}
-        }
-    }
+        ctx.emit(Instruction::LocalGet(1));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::I64LtS);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::Unreachable);
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::LocalGet(1));
+        ctx.emit(Instruction::Call(func_map["__alloc"]));
+        ctx.emit(Instruction::LocalSet(buf));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(len));
 
-    fn collect_lambda_exprs_from_expr(
-        expr: &Expression,
-        out: &mut Vec<(Vec<String>, Vec<Statement>)>,
-    ) {
-        match expr {
-            Expression::Lambda { params, body } => {
-                out.push((params.clone(), body.clone()));
-            }
-            Expression::Call { args, .. } => {
-                for arg in args {
-                    Self::collect_lambda_exprs_from_expr(arg, out);
-                }
-            }
-            Expression::List(items) => {
-                for item in items {
-                    Self::collect_lambda_exprs_from_expr(item, out);
-                }
-            }
-            Expression::StructInit { fields } => {
-                for (_, val) in fields {
-                    Self::collect_lambda_exprs_from_expr(val, out);
-                }
-            }
-            Expression::GetField { obj, .. } => {
-                Self::collect_lambda_exprs_from_expr(obj, out);
-            }
-            Expression::Match { scrutinee, arms } => {
-                Self::collect_lambda_exprs_from_expr(scrutinee, out);
-                for (_, arm_expr) in arms {
-                    Self::collect_lambda_exprs_from_expr(arm_expr, out);
-                }
-            }
-            _ => {}
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(len));
+            ctx.emit(Instruction::LocalGet(1));
+            ctx.emit(Instruction::I64GeS);
+            ctx.emit(Instruction::BrIf(1));
+
+            // fd_read(fd, iovs=32, 1, nread=48) into buf[len..max]
+            ctx.emit(Instruction::LocalGet(buf));
+            ctx.emit(Instruction::LocalGet(len));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(nread));
+            ctx.emit(Instruction::LocalGet(1));
+            ctx.emit(Instruction::LocalGet(len));
+            ctx.emit(Instruction::I64Sub);
+            ctx.emit(Instruction::LocalSet(spare));
+            Self::emit_fs_iovec(ctx, nread, spare);
+            ctx.emit(Instruction::LocalGet(0));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I32Const(32));
+            ctx.emit(Instruction::I32Const(1));
+            ctx.emit(Instruction::I32Const(48));
+            ctx.emit(Instruction::Call(Self::import_index(func_map, "fd_read")?));
+            Self::emit_trap_on_errno(ctx);
+
+            // Stop at end of file (a read of 0 bytes)
+            ctx.emit(Instruction::I32Const(48));
+            ctx.emit(Instruction::I64Load32U(wasm_encoder::MemArg {
+                offset: 0,
+                align: 2,
+                memory_index: 0,
+            }));
+            ctx.emit(Instruction::LocalTee(nread));
+            ctx.emit(Instruction::I64Eqz);
+            ctx.emit(Instruction::BrIf(1));
+            ctx.emit(Instruction::LocalGet(len));
+            ctx.emit(Instruction::LocalGet(nread));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(len));
+            ctx.emit(Instruction::Br(0));
         }
-    }
-
-    fn register_function(&mut self, func_def: &FunctionDef) -> Result<(), WasmCompileError> {
-        let param_types: Vec<ValType> = func_def.inputs.iter().map(|_| ValType::I64).collect();
-        let return_types = vec![ValType::I64]; // All functions return i64 for now
-
-        let type_idx = self.types.len() as u32;
-        self.types.push((param_types, return_types));
-
-        let func_idx = self.import_count + self.functions.len() as u32;
-        self.func_index_map.insert(func_def.name.clone(), func_idx);
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
 
-        // Create empty context — will be filled during compile phase
-        let ctx = FuncContext::new(func_def.inputs.len() as u32);
-        self.functions.push((type_idx, func_def.name.clone(), ctx));
+        // [buf << 32 | len, fd]
+        ctx.emit(Instruction::I64Const(24));
+        Self::emit_alloc_object(ctx, HEAP_TAG_LIST, func_map, "__fs_read_chunk")?;
+        ctx.emit(Instruction::LocalSet(list));
+        ctx.emit(Instruction::LocalGet(list));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I64Const(2));
+        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: 0,
+        }));
+        ctx.emit(Instruction::LocalGet(list));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::LocalGet(buf));
+        ctx.emit(Instruction::I64Const(32));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::LocalGet(len));
+        ctx.emit(Instruction::I64Or);
+        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+            offset: 8,
+            align: 3,
+            memory_index: 0,
+        }));
+        ctx.emit(Instruction::LocalGet(list));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+            offset: 16,
+            align: 3,
+            memory_index: 0,
+        }));
+        ctx.emit(Instruction::LocalGet(list));
+        Ok(())
+    }
 
-        // Store function attributes for selective export decisions
-        if !func_def.attributes.is_empty() {
-            self.func_attributes
-                .insert(func_def.name.clone(), func_def.attributes.clone());
-        }
+    /// Body of `__fs_seek(fd, offset)`: move the read position to byte
+    /// `offset` from the start of the file and return the fd.
+    fn emit_fs_seek(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::LocalGet(1));
+        ctx.emit(Instruction::I32Const(0)); // whence: SET
+        ctx.emit(Instruction::I32Const(48));
+        ctx.emit(Instruction::Call(Self::import_index(func_map, "fd_seek")?));
+        Self::emit_trap_on_errno(ctx);
+        ctx.emit(Instruction::LocalGet(0));
+        Ok(())
+    }
 
+    /// Body of `__fs_close(fd)`. Returns Unit.
+    fn emit_fs_close(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::Call(Self::import_index(func_map, "fd_close")?));
+        Self::emit_trap_on_errno(ctx);
+        ctx.emit(Instruction::I64Const(0));
         Ok(())
     }
 
-    // =========================================================================
-    // Selective Export Logic
-    // =========================================================================
+    /// Body of `__fs_write(path, text)`.
+    fn emit_fs_overwrite(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_fs_write(ctx, func_map, false)
+    }
 
-    /// Determine if a function should be exported in the WASM binary.
-    /// Returns true if the function has the `#[export]` attribute
-    /// or is a system function (_start, main, __alloc).
-    fn should_export(&self, name: &str) -> bool {
-        // System functions are always exported
-        if matches!(name, "_start" | "main" | "__alloc") {
-            return true;
-        }
-        // Check if function has #[export] attribute
-        if let Some(attrs) = self.func_attributes.get(name) {
-            return attrs.iter().any(|a| a == "export");
-        }
-        false
+    /// Body of `__fs_append(path, text)`.
+    fn emit_fs_append(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_fs_write(ctx, func_map, true)
     }
 
-    // =========================================================================
-    // Function Body Compilation
-    // =========================================================================
+    /// Write all of `text`, however many `fd_write` calls that takes, to a
+    /// file created if needed and truncated unless appending. Returns Unit.
+    fn emit_fs_write(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+        append: bool,
+    ) -> Result<(), WasmCompileError> {
+        let fd = ctx.scope.get_or_alloc("__fs_fd");
+        let ptr = ctx.scope.get_or_alloc("__fs_ptr");
+        let len = ctx.scope.get_or_alloc("__fs_len");
+        let written = ctx.scope.get_or_alloc("__fs_written");
 
-    fn compile_collected_functions(&mut self, node: &ArkNode) -> Result<(), WasmCompileError> {
-        // We need to build contexts for each registered function
-        let func_defs = self.extract_function_defs(node);
-        let func_index_map = self.func_index_map.clone();
+        let (oflags, fdflags) = if append {
+            (WASI_O_CREAT, WASI_FDFLAGS_APPEND)
+        } else {
+            (WASI_O_CREAT | WASI_O_TRUNC, 0)
+        };
+        Self::emit_fs_open(ctx, func_map, oflags, WASI_RIGHT_FD_WRITE, fdflags)?;
+        ctx.emit(Instruction::I64ExtendI32U);
+        ctx.emit(Instruction::LocalSet(fd));
 
-        for (i, func_def) in func_defs.iter().enumerate() {
-            let param_count = func_def.inputs.len() as u32;
-            let mut ctx = FuncContext::new(param_count);
+        ctx.emit(Instruction::LocalGet(1));
+        ctx.emit(Instruction::I64Const(32));
+        ctx.emit(Instruction::I64ShrU);
+        ctx.emit(Instruction::LocalSet(ptr));
+        ctx.emit(Instruction::LocalGet(1));
+        ctx.emit(Instruction::I64Const(0xFFFF_FFFF));
+        ctx.emit(Instruction::I64And);
+        ctx.emit(Instruction::LocalSet(len));
+
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(len));
+            ctx.emit(Instruction::I64Eqz);
+            ctx.emit(Instruction::BrIf(1));
 
-            // Register parameter names as locals
-            for (j, (name, _)) in func_def.inputs.iter().enumerate() {
-                ctx.scope.locals.insert(name.clone(), j as u32);
+            // fd_write(fd, iovs=32, 1, nwritten=48) of text[ptr..ptr+len]
+            Self::emit_fs_iovec(ctx, ptr, len);
+            ctx.emit(Instruction::LocalGet(fd));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I32Const(32));
+            ctx.emit(Instruction::I32Const(1));
+            ctx.emit(Instruction::I32Const(48));
+            ctx.emit(Instruction::Call(Self::import_index(func_map, "fd_write")?));
+            Self::emit_trap_on_errno(ctx);
+
+            ctx.emit(Instruction::I32Const(48));
+            ctx.emit(Instruction::I64Load32U(wasm_encoder::MemArg {
+                offset: 0,
+                align: 2,
+                memory_index: 0,
+            }));
+            ctx.emit(Instruction::LocalSet(written));
+            for (local, op) in [(ptr, Instruction::I64Add), (len, Instruction::I64Sub)] {
+                ctx.emit(Instruction::LocalGet(local));
+                ctx.emit(Instruction::LocalGet(written));
+                ctx.emit(op);
+                ctx.emit(Instruction::LocalSet(local));
             }
-
-            // Compile the function body
-            Self::compile_mast_node(&mut ctx, &func_def.body, &func_index_map)?;
-
-            // Ensure we return something
-            ctx.emit(Instruction::End);
-
-            // Skip internal functions (__alloc is at index 0)
-            let internal_offset = 1; // __alloc
-            self.functions[i + internal_offset].2 = ctx;
+            ctx.emit(Instruction::Br(0));
         }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
 
+        ctx.emit(Instruction::LocalGet(fd));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::Call(Self::import_index(func_map, "fd_close")?));
+        ctx.emit(Instruction::Drop);
+        ctx.emit(Instruction::I64Const(0));
         Ok(())
     }
 
-    fn extract_function_defs(&self, node: &ArkNode) -> Vec<FunctionDef> {
-        let mut defs = Vec::new();
-        match node {
-            ArkNode::Function(func_def) => {
-                defs.push(func_def.clone());
-            }
-            ArkNode::Statement(Statement::Function(func_def)) => {
-                defs.push(func_def.clone());
-            }
-            ArkNode::Statement(Statement::Block(stmts)) => {
-                for stmt in stmts {
-                    if let Statement::Function(func_def) = stmt {
-                        defs.push(func_def.clone());
-                    }
-                }
-            }
-            _ => {}
-        }
-        defs
-    }
-
-    // =========================================================================
-    // _start Function (Top-Level Code)
-    // =========================================================================
-
-    fn compile_start_function(&mut self, node: &ArkNode) -> Result<(), WasmCompileError> {
-        let type_idx = self.types.len() as u32;
-        // _start: () -> ()
-        self.types.push((vec![], vec![]));
-
-        let func_idx = self.import_count + self.functions.len() as u32;
-        self.func_index_map.insert("_start".to_string(), func_idx);
+    /// Fetch a WASI string vector (arguments or environment) with
+    /// `sizes_get` and `get`: `count` entries, whose NUL-terminated strings
+    /// live in `buf`, pointed to by the i32 array at `table`. Both buffers
+    /// come from `__alloc`.
+    fn emit_wasi_strings(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+        (sizes_get, get): (&str, &str),
+        (count, table, buf): (u32, u32, u32),
+    ) -> Result<(), WasmCompileError> {
+        let alloc = func_map["__alloc"];
+        let load_u32 = |offset| {
+            Instruction::I64Load32U(wasm_encoder::MemArg {
+                offset,
+                align: 2,
+                memory_index: 0,
+            })
+        };
 
-        let mut ctx = FuncContext::new(0);
-        let func_index_map = self.func_index_map.clone();
+        // sizes_get(count=48, buf_size=52)
+        ctx.emit(Instruction::I32Const(48));
+        ctx.emit(Instruction::I32Const(52));
+        ctx.emit(Instruction::Call(Self::import_index(func_map, sizes_get)?));
+        Self::emit_trap_on_errno(ctx);
+
+        ctx.emit(Instruction::I32Const(48));
+        ctx.emit(load_u32(0));
+        ctx.emit(Instruction::LocalTee(count));
+        ctx.emit(Instruction::I64Const(4));
+        ctx.emit(Instruction::I64Mul);
+        ctx.emit(Instruction::Call(alloc));
+        ctx.emit(Instruction::LocalSet(table));
+        ctx.emit(Instruction::I32Const(48));
+        ctx.emit(load_u32(4));
+        ctx.emit(Instruction::Call(alloc));
+        ctx.emit(Instruction::LocalSet(buf));
+
+        ctx.emit(Instruction::LocalGet(table));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::LocalGet(buf));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::Call(Self::import_index(func_map, get)?));
+        Self::emit_trap_on_errno(ctx);
+        Ok(())
+    }
 
-        match node {
-            ArkNode::Statement(Statement::Block(stmts)) => {
-                for stmt in stmts {
-                    // Skip function definitions (already compiled)
-                    if matches!(stmt, Statement::Function(_)) {
-                        continue;
-                    }
-                    Self::compile_stmt(&mut ctx, stmt, false, &func_index_map)?;
-                }
-            }
-            ArkNode::Statement(stmt) => {
-                if !matches!(stmt, Statement::Function(_)) {
-                    Self::compile_stmt(&mut ctx, stmt, false, &func_index_map)?;
-                }
-            }
-            ArkNode::Expression(expr) => {
-                Self::compile_expr(&mut ctx, expr, &func_index_map)?;
-                ctx.emit(Instruction::Drop);
-            }
-            _ => {}
+    /// Copy the NUL-terminated string at `ptr` into a fresh `__alloc` block
+    /// and push it as a packed string. Clobbers `len` and `dst`.
+    fn emit_copy_c_string(ctx: &mut FuncContext, alloc: u32, ptr: u32, len: u32, dst: u32) {
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(len));
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(ptr));
+            ctx.emit(Instruction::LocalGet(len));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I32Load8U(wasm_encoder::MemArg {
+                offset: 0,
+                align: 0,
+                memory_index: 0,
+            }));
+            ctx.emit(Instruction::I32Eqz);
+            ctx.emit(Instruction::BrIf(1));
+            ctx.emit(Instruction::LocalGet(len));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(len));
+            ctx.emit(Instruction::Br(0));
         }
-
         ctx.emit(Instruction::End);
-        self.functions.push((type_idx, "_start".to_string(), ctx));
+        ctx.emit(Instruction::End);
 
-        Ok(())
+        ctx.emit(Instruction::LocalGet(len));
+        ctx.emit(Instruction::Call(alloc));
+        ctx.emit(Instruction::LocalTee(dst));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::LocalGet(ptr));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::LocalGet(len));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::MemoryCopy {
+            src_mem: 0,
+            dst_mem: 0,
+        });
+        ctx.emit(Instruction::LocalGet(dst));
+        ctx.emit(Instruction::I64Const(32));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::LocalGet(len));
+        ctx.emit(Instruction::I64Or);
     }
 
-    // =========================================================================
-    // Statement Compilation
-    // =========================================================================
+    /// Free the two buffers of [`emit_wasi_strings`](Self::emit_wasi_strings).
+    fn emit_free_wasi_strings(ctx: &mut FuncContext, free: u32, table: u32, buf: u32) {
+        for local in [table, buf] {
+            ctx.emit(Instruction::LocalGet(local));
+            ctx.emit(Instruction::Call(free));
+            ctx.emit(Instruction::Drop);
+        }
+    }
 
-    fn compile_mast_node(
+    /// Body of `__args_get(index)`: a copy of argument `index` (0 is the
+    /// program name). Traps when `index` is out of range.
+    fn emit_args_get(
         ctx: &mut FuncContext,
-        mast: &MastNode,
         func_map: &HashMap<String, u32>,
     ) -> Result<(), WasmCompileError> {
-        match &mast.content {
-            ArkNode::Statement(stmt) => Self::compile_stmt(ctx, stmt, true, func_map),
-            ArkNode::Expression(expr) => Self::compile_expr(ctx, expr, func_map),
-            ArkNode::Function(_) => Ok(()), // Already handled
-            ArkNode::Type(_) => Ok(()),
-        }
+        let count = ctx.scope.get_or_alloc("__args_count");
+        let table = ctx.scope.get_or_alloc("__args_table");
+        let buf = ctx.scope.get_or_alloc("__args_buf");
+        let ptr = ctx.scope.get_or_alloc("__args_ptr");
+        let len = ctx.scope.get_or_alloc("__args_len");
+        let dst = ctx.scope.get_or_alloc("__args_dst");
+        let result = ctx.scope.get_or_alloc("__args_result");
+        Self::emit_wasi_strings(
+            ctx,
+            func_map,
+            ("args_sizes_get", "args_get"),
+            (count, table, buf),
+        )?;
+
+        // Unsigned, so a negative index is out of range too
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::LocalGet(count));
+        ctx.emit(Instruction::I64GeU);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::Unreachable);
+        ctx.emit(Instruction::End);
+
+        ctx.emit(Instruction::LocalGet(table));
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I64Const(4));
+        ctx.emit(Instruction::I64Mul);
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I64Load32U(wasm_encoder::MemArg {
+            offset: 0,
+            align: 2,
+            memory_index: 0,
+        }));
+        ctx.emit(Instruction::LocalSet(ptr));
+        Self::emit_copy_c_string(ctx, func_map["__alloc"], ptr, len, dst);
+        ctx.emit(Instruction::LocalSet(result));
+
+        Self::emit_free_wasi_strings(ctx, func_map["__free"], table, buf);
+        ctx.emit(Instruction::LocalGet(result));
+        Ok(())
     }
 
-    fn compile_stmt(
+    /// Body of `__env_get(name)`: a copy of the value of the first
+    /// `name=value` environment entry, or the empty string.
+    fn emit_env_get(
         ctx: &mut FuncContext,
-        stmt: &Statement,
-        preserve: bool,
         func_map: &HashMap<String, u32>,
     ) -> Result<(), WasmCompileError> {
-        match stmt {
-            // -----------------------------------------------------------------
-            // Let binding: evaluate expression, store in local
-            // -----------------------------------------------------------------
-            Statement::Let { name, value, .. } => {
-                Self::compile_expr(ctx, value, func_map)?;
-                let idx = ctx.scope.get_or_alloc(name);
-                ctx.emit(Instruction::LocalSet(idx));
-                Ok(())
-            }
+        let count = ctx.scope.get_or_alloc("__env_count");
+        let table = ctx.scope.get_or_alloc("__env_table");
+        let buf = ctx.scope.get_or_alloc("__env_buf");
+        let entry = ctx.scope.get_or_alloc("__env_entry");
+        let name_len = ctx.scope.get_or_alloc("__env_name_len");
+        let k = ctx.scope.get_or_alloc("__env_k");
+        let j = ctx.scope.get_or_alloc("__env_j");
+        let len = ctx.scope.get_or_alloc("__env_len");
+        let dst = ctx.scope.get_or_alloc("__env_dst");
+        let result = ctx.scope.get_or_alloc("__env_result");
+        let load_u8 = |ctx: &mut FuncContext, base: u32, index: u32| {
+            ctx.emit(Instruction::LocalGet(base));
+            ctx.emit(Instruction::LocalGet(index));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I32Load8U(wasm_encoder::MemArg {
+                offset: 0,
+                align: 0,
+                memory_index: 0,
+            }));
+        };
+        Self::emit_wasi_strings(
+            ctx,
+            func_map,
+            ("environ_sizes_get", "environ_get"),
+            (count, table, buf),
+        )?;
+
+        // Local 0 becomes the name's pointer once its length is split off
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I64Const(0xFFFF_FFFF));
+        ctx.emit(Instruction::I64And);
+        ctx.emit(Instruction::LocalSet(name_len));
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I64Const(32));
+        ctx.emit(Instruction::I64ShrU);
+        ctx.emit(Instruction::LocalSet(0));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(result));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(k));
 
-            // -----------------------------------------------------------------
-            // Expression statement: compile and optionally drop result
-            // -----------------------------------------------------------------
-            Statement::Expression(expr) => {
-                Self::compile_expr(ctx, expr, func_map)?;
-                if !preserve {
-                    ctx.emit(Instruction::Drop);
-                }
-                Ok(())
-            }
+        ctx.emit(Instruction::Block(BlockType::Empty)); // found or exhausted
+        ctx.emit(Instruction::Loop(BlockType::Empty)); // each entry
+        {
+            ctx.emit(Instruction::LocalGet(k));
+            ctx.emit(Instruction::LocalGet(count));
+            ctx.emit(Instruction::I64GeU);
+            ctx.emit(Instruction::BrIf(1));
+
+            ctx.emit(Instruction::LocalGet(table));
+            ctx.emit(Instruction::LocalGet(k));
+            ctx.emit(Instruction::I64Const(4));
+            ctx.emit(Instruction::I64Mul);
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I64Load32U(wasm_encoder::MemArg {
+                offset: 0,
+                align: 2,
+                memory_index: 0,
+            }));
+            ctx.emit(Instruction::LocalSet(entry));
+            ctx.emit(Instruction::LocalGet(k));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(k));
 
-            // -----------------------------------------------------------------
-            // Block: compile each statement, last one preserves value
-            // -----------------------------------------------------------------
-            Statement::Block(stmts) => {
-                let len = stmts.len();
-                if len == 0 && preserve {
-                    ctx.emit(Instruction::I64Const(0)); // Unit
-                    return Ok(());
-                }
-                for (i, s) in stmts.iter().enumerate() {
-                    let is_last = i == len - 1;
-                    Self::compile_stmt(ctx, s, preserve && is_last, func_map)?;
+            ctx.emit(Instruction::Block(BlockType::Empty)); // not this entry
+            {
+                // entry starts with the name's bytes...
+                ctx.emit(Instruction::I64Const(0));
+                ctx.emit(Instruction::LocalSet(j));
+                ctx.emit(Instruction::Loop(BlockType::Empty));
+                ctx.emit(Instruction::LocalGet(j));
+                ctx.emit(Instruction::LocalGet(name_len));
+                ctx.emit(Instruction::I64LtU);
+                ctx.emit(Instruction::If(BlockType::Empty));
+                {
+                    load_u8(ctx, entry, j);
+                    load_u8(ctx, 0, j);
+                    ctx.emit(Instruction::I32Ne);
+                    ctx.emit(Instruction::BrIf(2));
+                    ctx.emit(Instruction::LocalGet(j));
+                    ctx.emit(Instruction::I64Const(1));
+                    ctx.emit(Instruction::I64Add);
+                    ctx.emit(Instruction::LocalSet(j));
+                    ctx.emit(Instruction::Br(1));
                 }
-                Ok(())
-            }
+                ctx.emit(Instruction::End);
+                ctx.emit(Instruction::End);
 
-            // -----------------------------------------------------------------
-            // Return: compile expression and return
-            // -----------------------------------------------------------------
-            Statement::Return(expr) => {
-                Self::compile_expr(ctx, expr, func_map)?;
-                ctx.emit(Instruction::Return);
-                Ok(())
+                // ...followed by '='
+                load_u8(ctx, entry, name_len);
+                ctx.emit(Instruction::I32Const(b'=' as i32));
+                ctx.emit(Instruction::I32Ne);
+                ctx.emit(Instruction::BrIf(0));
+
+                ctx.emit(Instruction::LocalGet(entry));
+                ctx.emit(Instruction::LocalGet(name_len));
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::I64Const(1));
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::LocalSet(entry));
+                Self::emit_copy_c_string(ctx, func_map["__alloc"], entry, len, dst);
+                ctx.emit(Instruction::LocalSet(result));
+                ctx.emit(Instruction::Br(2));
             }
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::Br(0));
+        }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
 
-            // -----------------------------------------------------------------
-            // If/Else: uses WASM block/if structured control flow
-            // -----------------------------------------------------------------
-            Statement::If {
-                condition,
-                then_block,
-                else_block,
-            } => {
-                Self::compile_expr(ctx, condition, func_map)?;
-                // Convert i64 condition to i32 for br_if
-                ctx.emit(Instruction::I32WrapI64);
+        Self::emit_free_wasi_strings(ctx, func_map["__free"], table, buf);
+        ctx.emit(Instruction::LocalGet(result));
+        Ok(())
+    }
 
-                if preserve {
-                    // If that produces a value
-                    ctx.emit(Instruction::If(wasm_encoder::BlockType::Result(
-                        ValType::I64,
-                    )));
-                } else {
-                    ctx.emit(Instruction::If(wasm_encoder::BlockType::Empty));
-                }
+    /// Push byte `index` of the packed string in `packed` as an i32.
+    fn emit_string_byte(ctx: &mut FuncContext, packed: u32, index: u32) {
+        ctx.emit(Instruction::LocalGet(packed));
+        ctx.emit(Instruction::I64Const(32));
+        ctx.emit(Instruction::I64ShrU);
+        ctx.emit(Instruction::LocalGet(index));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I32Load8U(wasm_encoder::MemArg {
+            offset: 0,
+            align: 0,
+            memory_index: 0,
+        }));
+    }
 
-                // Then block
-                let then_len = then_block.len();
-                if then_len == 0 && preserve {
-                    ctx.emit(Instruction::I64Const(0)); // Unit
-                } else {
-                    for (i, s) in then_block.iter().enumerate() {
-                        let is_last = i == then_len - 1;
-                        Self::compile_stmt(ctx, s, preserve && is_last, func_map)?;
-                    }
-                }
+    /// Trap unless local 0 holds a map, then load its table capacity and
+    /// pointer into `cap` and `table`.
+    fn emit_map_header(ctx: &mut FuncContext, cap: u32, table: u32) {
+        Self::emit_heap_object_guard(ctx, 0, HEAP_TAG_MAP, 0);
+        for (local, offset) in [(cap, 8), (table, 16)] {
+            ctx.emit(Instruction::LocalGet(0));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                offset,
+                align: 3,
+                memory_index: 0,
+            }));
+            ctx.emit(Instruction::LocalSet(local));
+        }
+    }
 
-                // Else block
-                if let Some(else_stmts) = else_block {
-                    ctx.emit(Instruction::Else);
-                    let else_len = else_stmts.len();
-                    if else_len == 0 && preserve {
-                        ctx.emit(Instruction::I64Const(0)); // Unit
-                    } else {
-                        for (i, s) in else_stmts.iter().enumerate() {
-                            let is_last = i == else_len - 1;
-                            Self::compile_stmt(ctx, s, preserve && is_last, func_map)?;
-                        }
-                    }
-                } else if preserve {
-                    ctx.emit(Instruction::Else);
-                    ctx.emit(Instruction::I64Const(0)); // implicit Unit
-                }
+    /// Set `hash` to the 64-bit FNV-1a hash of the bytes of the packed
+    /// string `key` and `len` to their count, using `i` as the counter.
+    fn emit_fnv1a(ctx: &mut FuncContext, key: u32, (hash, len, i): (u32, u32, u32)) {
+        ctx.emit(Instruction::LocalGet(key));
+        ctx.emit(Instruction::I64Const(0xFFFF_FFFF));
+        ctx.emit(Instruction::I64And);
+        ctx.emit(Instruction::LocalSet(len));
+        ctx.emit(Instruction::I64Const(0xcbf2_9ce4_8422_2325_u64 as i64)); // FNV offset basis
+        ctx.emit(Instruction::LocalSet(hash));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(i));
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(i));
+            ctx.emit(Instruction::LocalGet(len));
+            ctx.emit(Instruction::I64GeU);
+            ctx.emit(Instruction::BrIf(1));
+            ctx.emit(Instruction::LocalGet(hash));
+            Self::emit_string_byte(ctx, key, i);
+            ctx.emit(Instruction::I64ExtendI32U);
+            ctx.emit(Instruction::I64Xor);
+            ctx.emit(Instruction::I64Const(0x100_0000_01b3)); // FNV prime
+            ctx.emit(Instruction::I64Mul);
+            ctx.emit(Instruction::LocalSet(hash));
+            ctx.emit(Instruction::LocalGet(i));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(i));
+            ctx.emit(Instruction::Br(0));
+        }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
+    }
+
+    /// Find the packed string `key` in the `cap`-entry table at `table`,
+    /// leaving in `entry` the address of the entry holding it, or of the
+    /// empty entry where it belongs. Keys hash with FNV-1a over their bytes
+    /// and compare by content; linear probing always ends at an empty entry
+    /// because `__map_set` keeps tables at most 3/4 full.
+    fn emit_map_probe(ctx: &mut FuncContext, table: u32, cap: u32, key: u32, entry: u32) {
+        let hash = ctx.scope.get_or_alloc("__map_hash");
+        let len = ctx.scope.get_or_alloc("__map_len");
+        let i = ctx.scope.get_or_alloc("__map_i");
+        let other = ctx.scope.get_or_alloc("__map_other");
+        Self::emit_fnv1a(ctx, key, (hash, len, i));
+
+        // From here on `hash` is the entry index
+        ctx.emit(Instruction::LocalGet(hash));
+        ctx.emit(Instruction::LocalGet(cap));
+        ctx.emit(Instruction::I64Const(1));
+        ctx.emit(Instruction::I64Sub);
+        ctx.emit(Instruction::I64And);
+        ctx.emit(Instruction::LocalSet(hash));
 
+        ctx.emit(Instruction::Block(BlockType::Empty)); // found
+        ctx.emit(Instruction::Loop(BlockType::Empty)); // each entry
+        {
+            ctx.emit(Instruction::LocalGet(table));
+            ctx.emit(Instruction::LocalGet(hash));
+            ctx.emit(Instruction::I64Const(4));
+            ctx.emit(Instruction::I64Shl);
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(entry));
+            ctx.emit(Instruction::LocalGet(entry));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                offset: 0,
+                align: 3,
+                memory_index: 0,
+            }));
+            ctx.emit(Instruction::LocalSet(other));
+
+            // An empty entry or the very same string ends the probe
+            ctx.emit(Instruction::LocalGet(other));
+            ctx.emit(Instruction::I64Eqz);
+            ctx.emit(Instruction::LocalGet(other));
+            ctx.emit(Instruction::LocalGet(key));
+            ctx.emit(Instruction::I64Eq);
+            ctx.emit(Instruction::I32Or);
+            ctx.emit(Instruction::BrIf(1));
+
+            ctx.emit(Instruction::Block(BlockType::Empty)); // differs
+            {
+                ctx.emit(Instruction::LocalGet(other));
+                ctx.emit(Instruction::I64Const(0xFFFF_FFFF));
+                ctx.emit(Instruction::I64And);
+                ctx.emit(Instruction::LocalGet(len));
+                ctx.emit(Instruction::I64Ne);
+                ctx.emit(Instruction::BrIf(0));
+                ctx.emit(Instruction::I64Const(0));
+                ctx.emit(Instruction::LocalSet(i));
+                ctx.emit(Instruction::Loop(BlockType::Empty));
+                ctx.emit(Instruction::LocalGet(i));
+                ctx.emit(Instruction::LocalGet(len));
+                ctx.emit(Instruction::I64GeU);
+                ctx.emit(Instruction::BrIf(3)); // every byte matched
+                Self::emit_string_byte(ctx, other, i);
+                Self::emit_string_byte(ctx, key, i);
+                ctx.emit(Instruction::I32Ne);
+                ctx.emit(Instruction::BrIf(1));
+                ctx.emit(Instruction::LocalGet(i));
+                ctx.emit(Instruction::I64Const(1));
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::LocalSet(i));
+                ctx.emit(Instruction::Br(0));
                 ctx.emit(Instruction::End);
-                Ok(())
             }
+            ctx.emit(Instruction::End);
 
-            // -----------------------------------------------------------------
-            // While loop: block + loop + br_if
-            // -----------------------------------------------------------------
-            Statement::While { condition, body } => {
-                // block $break
-                //   loop $continue
-                //     condition
-                //     i32.eqz
-                //     br_if $break
-                //     body...
-                //     br $continue
-                //   end
-                // end
-                ctx.emit(Instruction::Block(wasm_encoder::BlockType::Empty));
-                ctx.emit(Instruction::Loop(wasm_encoder::BlockType::Empty));
+            ctx.emit(Instruction::LocalGet(hash));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalGet(cap));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Sub);
+            ctx.emit(Instruction::I64And);
+            ctx.emit(Instruction::LocalSet(hash));
+            ctx.emit(Instruction::Br(0));
+        }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
+    }
 
-                // Evaluate condition
-                Self::compile_expr(ctx, condition, func_map)?;
-                ctx.emit(Instruction::I32WrapI64);
-                ctx.emit(Instruction::I32Eqz);
-                ctx.emit(Instruction::BrIf(1)); // break out of block
+    /// Body of `__map_get(map, key)`: the value stored under `key`, or 0
+    /// (unit) when there is none.
+    fn emit_map_get(
+        ctx: &mut FuncContext,
+        _func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let cap = ctx.scope.get_or_alloc("__map_cap");
+        let table = ctx.scope.get_or_alloc("__map_table");
+        let entry = ctx.scope.get_or_alloc("__map_entry");
+        Self::emit_map_header(ctx, cap, table);
+        Self::emit_map_probe(ctx, table, cap, 1, entry);
 
-                // Body
-                for s in body {
-                    Self::compile_stmt(ctx, s, false, func_map)?;
-                }
+        ctx.emit(Instruction::LocalGet(entry));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: 0,
+        }));
+        ctx.emit(Instruction::I64Eqz);
+        ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::Else);
+        ctx.emit(Instruction::LocalGet(entry));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+            offset: 8,
+            align: 3,
+            memory_index: 0,
+        }));
+        ctx.emit(Instruction::End);
+        Ok(())
+    }
 
-                ctx.emit(Instruction::Br(0)); // continue loop
-                ctx.emit(Instruction::End); // end loop
-                ctx.emit(Instruction::End); // end block
+    /// Body of `__map_set(map, key, value)`: store `value` under `key` in
+    /// place and return the map. The table doubles before an insertion
+    /// would leave it more than 3/4 full.
+    fn emit_map_set(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let cap = ctx.scope.get_or_alloc("__map_cap");
+        let table = ctx.scope.get_or_alloc("__map_table");
+        let entry = ctx.scope.get_or_alloc("__map_entry");
+        let old_cap = ctx.scope.get_or_alloc("__map_old_cap");
+        let old_table = ctx.scope.get_or_alloc("__map_old_table");
+        let slot = ctx.scope.get_or_alloc("__map_slot");
+        let old_entry = ctx.scope.get_or_alloc("__map_old_entry");
+        let moved = ctx.scope.get_or_alloc("__map_moved");
+        let load = |ctx: &mut FuncContext, addr: u32, offset: u64| {
+            ctx.emit(Instruction::LocalGet(addr));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                offset,
+                align: 3,
+                memory_index: 0,
+            }));
+        };
+        let store = |ctx: &mut FuncContext, addr: u32, offset: u64, value: u32| {
+            ctx.emit(Instruction::LocalGet(addr));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::LocalGet(value));
+            ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                offset,
+                align: 3,
+                memory_index: 0,
+            }));
+        };
+        Self::emit_map_header(ctx, cap, table);
 
-                if preserve {
-                    ctx.emit(Instruction::I64Const(0)); // Unit
-                }
-                Ok(())
-            }
+        // (count + 1) * 4 > cap * 3 → rehash into a table twice the size
+        load(ctx, 0, 0);
+        ctx.emit(Instruction::I64Const(1));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::I64Const(4));
+        ctx.emit(Instruction::I64Mul);
+        ctx.emit(Instruction::LocalGet(cap));
+        ctx.emit(Instruction::I64Const(3));
+        ctx.emit(Instruction::I64Mul);
+        ctx.emit(Instruction::I64GtU);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(cap));
+            ctx.emit(Instruction::LocalSet(old_cap));
+            ctx.emit(Instruction::LocalGet(table));
+            ctx.emit(Instruction::LocalSet(old_table));
+            ctx.emit(Instruction::LocalGet(cap));
+            ctx.emit(Instruction::I64Const(2));
+            ctx.emit(Instruction::I64Mul);
+            ctx.emit(Instruction::LocalSet(cap));
+            ctx.emit(Instruction::LocalGet(cap));
+            ctx.emit(Instruction::I64Const(16));
+            ctx.emit(Instruction::I64Mul);
+            ctx.emit(Instruction::Call(func_map["__alloc"]));
+            ctx.emit(Instruction::LocalSet(table));
+            store(ctx, 0, 8, cap);
+            store(ctx, 0, 16, table);
 
-            // -----------------------------------------------------------------
-            // For loop: desugar to while (not yet fully supported)
-            // -----------------------------------------------------------------
-            Statement::For { .. } => {
-                // TODO: Implement for loop desugaring
-                if preserve {
-                    ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::LocalSet(slot));
+            ctx.emit(Instruction::Block(BlockType::Empty));
+            ctx.emit(Instruction::Loop(BlockType::Empty));
+            {
+                ctx.emit(Instruction::LocalGet(slot));
+                ctx.emit(Instruction::LocalGet(old_cap));
+                ctx.emit(Instruction::I64GeU);
+                ctx.emit(Instruction::BrIf(1));
+                ctx.emit(Instruction::LocalGet(old_table));
+                ctx.emit(Instruction::LocalGet(slot));
+                ctx.emit(Instruction::I64Const(4));
+                ctx.emit(Instruction::I64Shl);
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::LocalSet(old_entry));
+                load(ctx, old_entry, 0);
+                ctx.emit(Instruction::LocalSet(moved));
+                ctx.emit(Instruction::LocalGet(moved));
+                ctx.emit(Instruction::I64Const(0));
+                ctx.emit(Instruction::I64Ne);
+                ctx.emit(Instruction::If(BlockType::Empty));
+                {
+                    Self::emit_map_probe(ctx, table, cap, moved, entry);
+                    store(ctx, entry, 0, moved);
+                    load(ctx, old_entry, 8);
+                    ctx.emit(Instruction::LocalSet(moved));
+                    store(ctx, entry, 8, moved);
                 }
-                Ok(())
+                ctx.emit(Instruction::End);
+                ctx.emit(Instruction::LocalGet(slot));
+                ctx.emit(Instruction::I64Const(1));
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::LocalSet(slot));
+                ctx.emit(Instruction::Br(0));
             }
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::End);
 
-            // -----------------------------------------------------------------
-            // SetField: obj.field = value
-            // -----------------------------------------------------------------
-            Statement::SetField {
-                obj_name,
-                field: _field,
-                value,
-            } => {
-                // Look up the struct pointer from the local variable
-                if let Some(local_idx) = ctx.scope.locals.get(obj_name).copied() {
-                    // Compile value
-                    Self::compile_expr(ctx, value, func_map)?;
-                    let val_local = ctx.scope.get_or_alloc("__setfield_val");
-                    ctx.emit(Instruction::LocalSet(val_local));
+            ctx.emit(Instruction::LocalGet(old_table));
+            ctx.emit(Instruction::Call(func_map["__free"]));
+            ctx.emit(Instruction::Drop);
+        }
+        ctx.emit(Instruction::End);
 
-                    // Load struct ptr
-                    ctx.emit(Instruction::LocalGet(local_idx));
-                    ctx.emit(Instruction::I32WrapI64);
-                    // Store value at first field offset (ptr + 8)
-                    // TODO: Use field name to determine offset when type info available
-                    ctx.emit(Instruction::LocalGet(val_local));
-                    ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
-                        offset: 8,
-                        align: 3,
-                        memory_index: 0,
-                    }));
-                }
-                if preserve {
-                    ctx.emit(Instruction::I64Const(0));
-                }
-                Ok(())
-            }
+        Self::emit_map_probe(ctx, table, cap, 1, entry);
+        // A new key bumps the entry count
+        load(ctx, entry, 0);
+        ctx.emit(Instruction::I64Eqz);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I32WrapI64);
+        load(ctx, 0, 0);
+        ctx.emit(Instruction::I64Const(1));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: 0,
+        }));
+        ctx.emit(Instruction::End);
+        store(ctx, entry, 0, 1);
+        store(ctx, entry, 8, 2);
+        ctx.emit(Instruction::LocalGet(0));
+        Ok(())
+    }
 
-            // -----------------------------------------------------------------
-            // LetDestructure: bind names to sequential list elements
-            // let [a, b, c] = my_list;
-            // → a = list[0], b = list[1], c = list[2]
-            // -----------------------------------------------------------------
-            Statement::LetDestructure { names, value } => {
-                // Compile the list/value expression
-                Self::compile_expr(ctx, value, func_map)?;
-                let list_ptr = ctx.scope.get_or_alloc("__destructure_ptr");
-                ctx.emit(Instruction::LocalSet(list_ptr));
+    /// Body of `__map_keys(map)`: a new list of the map's keys, sorted by
+    /// byte order like the interpreter's `pmap.keys`.
+    fn emit_map_keys(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let cap = ctx.scope.get_or_alloc("__map_cap");
+        let table = ctx.scope.get_or_alloc("__map_table");
+        let count = ctx.scope.get_or_alloc("__map_count");
+        let list = ctx.scope.get_or_alloc("__map_list");
+        let slot = ctx.scope.get_or_alloc("__map_slot");
+        let n = ctx.scope.get_or_alloc("__map_n");
+        let key = ctx.scope.get_or_alloc("__map_key");
+        // Address of list element `index`
+        let element = |ctx: &mut FuncContext, index: u32| {
+            ctx.emit(Instruction::LocalGet(list));
+            ctx.emit(Instruction::LocalGet(index));
+            ctx.emit(Instruction::I64Const(8));
+            ctx.emit(Instruction::I64Mul);
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::I32WrapI64);
+        };
+        let element_mem = wasm_encoder::MemArg {
+            offset: 8,
+            align: 3,
+            memory_index: 0,
+        };
+        Self::emit_map_header(ctx, cap, table);
 
-                // Bind each name to list element at ptr + 8 + 8*i
-                for (i, name) in names.iter().enumerate() {
-                    let name_local = ctx.scope.get_or_alloc(name);
-                    ctx.emit(Instruction::LocalGet(list_ptr));
-                    ctx.emit(Instruction::I32WrapI64);
-                    ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
-                        offset: (8 + 8 * i) as u64,
-                        align: 3,
-                        memory_index: 0,
-                    }));
-                    ctx.emit(Instruction::LocalSet(name_local));
-                }
-                if preserve {
-                    ctx.emit(Instruction::I64Const(0));
-                }
-                Ok(())
-            }
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: 0,
+        }));
+        ctx.emit(Instruction::LocalSet(count));
+        ctx.emit(Instruction::LocalGet(count));
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::I64Mul);
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::I64Add);
+        Self::emit_alloc_object(ctx, HEAP_TAG_LIST, func_map, "__map_keys")?;
+        ctx.emit(Instruction::LocalSet(list));
+        ctx.emit(Instruction::LocalGet(list));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::LocalGet(count));
+        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: 0,
+        }));
 
-            // -----------------------------------------------------------------
-            // Function: already compiled in collect phase — skip
-            // -----------------------------------------------------------------
-            Statement::Function(_) => Ok(()),
+        // Copy the keys out in table order...
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(slot));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(n));
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(slot));
+            ctx.emit(Instruction::LocalGet(cap));
+            ctx.emit(Instruction::I64GeU);
+            ctx.emit(Instruction::BrIf(1));
+            ctx.emit(Instruction::LocalGet(table));
+            ctx.emit(Instruction::LocalGet(slot));
+            ctx.emit(Instruction::I64Const(4));
+            ctx.emit(Instruction::I64Shl);
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                offset: 0,
+                align: 3,
+                memory_index: 0,
+            }));
+            ctx.emit(Instruction::LocalSet(key));
+            ctx.emit(Instruction::LocalGet(key));
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::I64Ne);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            element(ctx, n);
+            ctx.emit(Instruction::LocalGet(key));
+            ctx.emit(Instruction::I64Store(element_mem));
+            ctx.emit(Instruction::LocalGet(n));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(n));
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::LocalGet(slot));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(slot));
+            ctx.emit(Instruction::Br(0));
+        }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
 
-            // -----------------------------------------------------------------
-            // Import / StructDecl: metadata only, no codegen
-            // -----------------------------------------------------------------
-            Statement::Import(_)
-            | Statement::StructDecl(_)
-            | Statement::EnumDecl(_)
-            | Statement::TraitDecl(_)
-            | Statement::ImplBlock(_) => Ok(()),
+        Self::emit_sort_strings(ctx, list, count);
+        ctx.emit(Instruction::LocalGet(list));
+        Ok(())
+    }
 
-            // -----------------------------------------------------------------
-            // Break / Continue: br instructions
-            // -----------------------------------------------------------------
-            Statement::Break => {
-                ctx.emit(Instruction::Br(1)); // break out of enclosing block
-                Ok(())
-            }
-            Statement::Continue => {
-                ctx.emit(Instruction::Br(0)); // jump to loop header
-                Ok(())
+    /// Sort the first `count` elements of the list in `list`, all packed
+    /// strings, by byte order with an insertion sort.
+    fn emit_sort_strings(ctx: &mut FuncContext, list: u32, count: u32) {
+        let n = ctx.scope.get_or_alloc("__sort_n");
+        let key = ctx.scope.get_or_alloc("__sort_key");
+        let prev = ctx.scope.get_or_alloc("__sort_prev");
+        let j = ctx.scope.get_or_alloc("__sort_j");
+        let less = ctx.scope.get_or_alloc("__sort_less");
+        let idx = ctx.scope.get_or_alloc("__sort_idx");
+        let common = ctx.scope.get_or_alloc("__sort_common");
+        // Address of list element `index`
+        let element = |ctx: &mut FuncContext, index: u32| {
+            ctx.emit(Instruction::LocalGet(list));
+            ctx.emit(Instruction::LocalGet(index));
+            ctx.emit(Instruction::I64Const(8));
+            ctx.emit(Instruction::I64Mul);
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::I32WrapI64);
+        };
+        let element_mem = wasm_encoder::MemArg {
+            offset: 8,
+            align: 3,
+            memory_index: 0,
+        };
+
+        // `n` walks the unsorted suffix
+        ctx.emit(Instruction::I64Const(1));
+        ctx.emit(Instruction::LocalSet(n));
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(n));
+            ctx.emit(Instruction::LocalGet(count));
+            ctx.emit(Instruction::I64GeU);
+            ctx.emit(Instruction::BrIf(1));
+            element(ctx, n);
+            ctx.emit(Instruction::I64Load(element_mem));
+            ctx.emit(Instruction::LocalSet(key));
+            ctx.emit(Instruction::LocalGet(n));
+            ctx.emit(Instruction::LocalSet(j));
+
+            ctx.emit(Instruction::Block(BlockType::Empty)); // placed
+            ctx.emit(Instruction::Loop(BlockType::Empty)); // shift
+            {
+                ctx.emit(Instruction::LocalGet(j));
+                ctx.emit(Instruction::I64Eqz);
+                ctx.emit(Instruction::BrIf(1));
+                ctx.emit(Instruction::LocalGet(j));
+                ctx.emit(Instruction::I64Const(1));
+                ctx.emit(Instruction::I64Sub);
+                ctx.emit(Instruction::LocalSet(j));
+                element(ctx, j);
+                ctx.emit(Instruction::I64Load(element_mem));
+                ctx.emit(Instruction::LocalSet(prev));
+                Self::emit_string_less(ctx, key, prev, (less, idx, common));
+                // key >= prev: it belongs just after `prev`
+                ctx.emit(Instruction::LocalGet(less));
+                ctx.emit(Instruction::I64Eqz);
+                ctx.emit(Instruction::If(BlockType::Empty));
+                ctx.emit(Instruction::LocalGet(j));
+                ctx.emit(Instruction::I64Const(1));
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::LocalSet(j));
+                ctx.emit(Instruction::Br(2));
+                ctx.emit(Instruction::End);
+                // j + 1 = prev
+                ctx.emit(Instruction::LocalGet(list));
+                ctx.emit(Instruction::LocalGet(j));
+                ctx.emit(Instruction::I64Const(8));
+                ctx.emit(Instruction::I64Mul);
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::I32WrapI64);
+                ctx.emit(Instruction::LocalGet(prev));
+                ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                    offset: 16,
+                    align: 3,
+                    memory_index: 0,
+                }));
+                ctx.emit(Instruction::Br(0));
             }
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::End);
+
+            element(ctx, j);
+            ctx.emit(Instruction::LocalGet(key));
+            ctx.emit(Instruction::I64Store(element_mem));
+            ctx.emit(Instruction::LocalGet(n));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(n));
+            ctx.emit(Instruction::Br(0));
         }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
     }
 
-    // =========================================================================
-    // Expression Compilation
-    // =========================================================================
-
-    fn compile_expr(
+    /// Set `less` to 1 when packed string `a` sorts before `b` byte-wise,
+    /// else 0, using `idx` and `common` as scratch.
+    fn emit_string_less(
         ctx: &mut FuncContext,
-        expr: &Expression,
-        func_map: &HashMap<String, u32>,
-    ) -> Result<(), WasmCompileError> {
-        match expr {
-            // -----------------------------------------------------------------
-            // Integer literal → i64.const
-            // -----------------------------------------------------------------
-            Expression::Integer(n) => {
-                ctx.emit(Instruction::I64Const(*n));
-                Ok(())
-            }
+        a: u32,
+        b: u32,
+        (less, idx, common): (u32, u32, u32),
+    ) {
+        let len = |ctx: &mut FuncContext, packed: u32| {
+            ctx.emit(Instruction::LocalGet(packed));
+            ctx.emit(Instruction::I64Const(0xFFFF_FFFF));
+            ctx.emit(Instruction::I64And);
+        };
+        // A proper prefix sorts first
+        len(ctx, a);
+        len(ctx, b);
+        ctx.emit(Instruction::I64LtU);
+        ctx.emit(Instruction::I64ExtendI32U);
+        ctx.emit(Instruction::LocalSet(less));
+        len(ctx, a);
+        len(ctx, b);
+        len(ctx, a);
+        len(ctx, b);
+        ctx.emit(Instruction::I64LtU);
+        ctx.emit(Instruction::Select);
+        ctx.emit(Instruction::LocalSet(common));
 
-            // -----------------------------------------------------------------
-            // String literal → store in linear memory, push packed ptr|len
-            // -----------------------------------------------------------------
-            Expression::Literal(s) => {
-                let (ptr, len) = ctx.alloc_string(s);
-                // Pack as (ptr << 32) | len — both fit in i64
-                let packed = ((ptr as i64) << 32) | (len as i64 & 0xFFFFFFFF);
-                ctx.emit(Instruction::I64Const(packed));
-                Ok(())
-            }
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(idx));
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(idx));
+            ctx.emit(Instruction::LocalGet(common));
+            ctx.emit(Instruction::I64GeU);
+            ctx.emit(Instruction::BrIf(1));
+            Self::emit_string_byte(ctx, a, idx);
+            Self::emit_string_byte(ctx, b, idx);
+            ctx.emit(Instruction::I32Ne);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            Self::emit_string_byte(ctx, a, idx);
+            Self::emit_string_byte(ctx, b, idx);
+            ctx.emit(Instruction::I32LtU);
+            ctx.emit(Instruction::I64ExtendI32U);
+            ctx.emit(Instruction::LocalSet(less));
+            ctx.emit(Instruction::Br(2));
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::LocalGet(idx));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(idx));
+            ctx.emit(Instruction::Br(0));
+        }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
+    }
 
-            // -----------------------------------------------------------------
-            // Variable → local.get
-            // -----------------------------------------------------------------
-            Expression::Variable(name) => {
-                if let Some(idx) = ctx.scope.get(name) {
-                    ctx.emit(Instruction::LocalGet(idx));
-                } else {
-                    // Could be a function reference — push function index as i64
-                    if let Some(&func_idx) = func_map.get(name) {
-                        ctx.emit(Instruction::I64Const(func_idx as i64));
-                    } else {
-                        // Allocate as new local (might be used before definition in some patterns)
-                        let idx = ctx.scope.get_or_alloc(name);
-                        ctx.emit(Instruction::LocalGet(idx));
-                    }
-                }
-                Ok(())
-            }
+    /// Push the byte length of the packed string in `packed`.
+    fn emit_string_len(ctx: &mut FuncContext, packed: u32) {
+        ctx.emit(Instruction::LocalGet(packed));
+        ctx.emit(Instruction::I64Const(0xFFFF_FFFF));
+        ctx.emit(Instruction::I64And);
+    }
 
-            // -----------------------------------------------------------------
-            // Function call
-            // -----------------------------------------------------------------
-            Expression::Call {
-                function_hash,
-                args,
-            } => {
-                match function_hash.as_str() {
-                    // Arithmetic intrinsics → direct WASM opcodes
-                    "intrinsic_add" | "add" => {
-                        Self::compile_binary_op(ctx, args, Instruction::I64Add, func_map)?;
-                    }
-                    "intrinsic_sub" | "sub" => {
-                        Self::compile_binary_op(ctx, args, Instruction::I64Sub, func_map)?;
-                    }
-                    "intrinsic_mul" | "mul" => {
-                        Self::compile_binary_op(ctx, args, Instruction::I64Mul, func_map)?;
-                    }
-                    "intrinsic_div" | "div" => {
-                        Self::compile_binary_op(ctx, args, Instruction::I64DivS, func_map)?;
-                    }
-                    "intrinsic_mod" | "modulo" => {
-                        Self::compile_binary_op(ctx, args, Instruction::I64RemS, func_map)?;
-                    }
+    /// Push 1 when byte `index` of the packed string in `packed` is a UTF-8
+    /// continuation byte (`10xxxxxx`), else 0.
+    fn emit_is_continuation(ctx: &mut FuncContext, packed: u32, index: u32) {
+        Self::emit_string_byte(ctx, packed, index);
+        ctx.emit(Instruction::I32Const(0xC0));
+        ctx.emit(Instruction::I32And);
+        ctx.emit(Instruction::I32Const(0x80));
+        ctx.emit(Instruction::I32Eq);
+    }
 
-                    // Comparison intrinsics → WASM comparison + extend to i64
-                    "intrinsic_eq" | "eq" => {
-                        Self::compile_compare_op(ctx, args, Instruction::I64Eq, func_map)?;
-                    }
-                    "intrinsic_neq" | "neq" => {
-                        Self::compile_compare_op(ctx, args, Instruction::I64Ne, func_map)?;
-                    }
-                    "intrinsic_gt" | "gt" => {
-                        Self::compile_compare_op(ctx, args, Instruction::I64GtS, func_map)?;
-                    }
-                    "intrinsic_lt" | "lt" => {
-                        Self::compile_compare_op(ctx, args, Instruction::I64LtS, func_map)?;
-                    }
-                    "intrinsic_ge" | "ge" => {
-                        Self::compile_compare_op(ctx, args, Instruction::I64GeS, func_map)?;
-                    }
-                    "intrinsic_le" | "le" => {
-                        Self::compile_compare_op(ctx, args, Instruction::I64LeS, func_map)?;
-                    }
+    /// Set `offset` to the byte offset of character `n` of the packed
+    /// string in local 0, counting in `count`. `n` may equal the character
+    /// count (giving the byte length); a negative or larger `n` traps.
+    fn emit_utf8_offset(ctx: &mut FuncContext, n: u32, offset: u32, count: u32) {
+        let bump = |ctx: &mut FuncContext, local: u32| {
+            ctx.emit(Instruction::LocalGet(local));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(local));
+        };
+        ctx.emit(Instruction::LocalGet(n));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::I64LtS);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::Unreachable);
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(offset));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(count));
 
-                    // Logical intrinsics
-                    "intrinsic_and" | "and" => {
-                        Self::compile_binary_op(ctx, args, Instruction::I64And, func_map)?;
-                    }
-                    "intrinsic_or" | "or" => {
-                        Self::compile_binary_op(ctx, args, Instruction::I64Or, func_map)?;
-                    }
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(count));
+            ctx.emit(Instruction::LocalGet(n));
+            ctx.emit(Instruction::I64Eq);
+            ctx.emit(Instruction::BrIf(1));
+            // Ran out of characters before reaching `n`
+            ctx.emit(Instruction::LocalGet(offset));
+            Self::emit_string_len(ctx, 0);
+            ctx.emit(Instruction::I64GeU);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            ctx.emit(Instruction::Unreachable);
+            ctx.emit(Instruction::End);
 
-                    // Print → WASI fd_write
-                    "print" | "intrinsic_print" => {
-                        Self::compile_print(ctx, args, func_map)?;
-                    }
+            // Step over the lead byte and its continuation bytes
+            bump(ctx, offset);
+            ctx.emit(Instruction::Block(BlockType::Empty));
+            ctx.emit(Instruction::Loop(BlockType::Empty));
+            {
+                ctx.emit(Instruction::LocalGet(offset));
+                Self::emit_string_len(ctx, 0);
+                ctx.emit(Instruction::I64GeU);
+                ctx.emit(Instruction::BrIf(1));
+                Self::emit_is_continuation(ctx, 0, offset);
+                ctx.emit(Instruction::I32Eqz);
+                ctx.emit(Instruction::BrIf(1));
+                bump(ctx, offset);
+                ctx.emit(Instruction::Br(0));
+            }
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::End);
+            bump(ctx, count);
+            ctx.emit(Instruction::Br(0));
+        }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
+    }
 
-                    // =========================================================
-                    // String Intrinsics (packed i64: ptr<<32 | len)
-                    // =========================================================
+    /// Push the packed substring of local 0 between byte offsets `start`
+    /// and `end`, sharing its bytes as `string_slice` does.
+    fn emit_substring(ctx: &mut FuncContext, start: u32, end: u32) {
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I64Const(32));
+        ctx.emit(Instruction::I64ShrU);
+        ctx.emit(Instruction::LocalGet(start));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::I64Const(32));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::LocalGet(end));
+        ctx.emit(Instruction::LocalGet(start));
+        ctx.emit(Instruction::I64Sub);
+        ctx.emit(Instruction::I64Or);
+    }
 
-                    // string_len(s) → extract len from packed i64
-                    "string_len" | "intrinsic_string_len" => {
-                        if args.len() != 1 {
-                            return Err(WasmCompileError {
-                                message: "string_len requires 1 argument".to_string(),
-                                context: "string_len".to_string(),
-                            });
-                        }
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        // len = packed & 0xFFFFFFFF
-                        ctx.emit(Instruction::I64Const(0xFFFFFFFF));
-                        ctx.emit(Instruction::I64And);
-                    }
+    /// Body of `__str_char_len(s)`: the number of UTF-8 characters in `s`,
+    /// i.e. the bytes that are not continuation bytes.
+    fn emit_str_char_len(
+        ctx: &mut FuncContext,
+        _func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let idx = ctx.scope.get_or_alloc("__str_idx");
+        let count = ctx.scope.get_or_alloc("__str_count");
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(idx));
+            Self::emit_string_len(ctx, 0);
+            ctx.emit(Instruction::I64GeU);
+            ctx.emit(Instruction::BrIf(1));
+            ctx.emit(Instruction::LocalGet(count));
+            Self::emit_is_continuation(ctx, 0, idx);
+            ctx.emit(Instruction::I32Eqz);
+            ctx.emit(Instruction::I64ExtendI32U);
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(count));
+            ctx.emit(Instruction::LocalGet(idx));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(idx));
+            ctx.emit(Instruction::Br(0));
+        }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::LocalGet(count));
+        Ok(())
+    }
 
-                    // string_concat(a, b) → allocate new buffer, copy both, repack
-                    "string_concat" | "intrinsic_string_concat" => {
-                        if args.len() != 2 {
-                            return Err(WasmCompileError {
-                                message: "string_concat requires 2 arguments".to_string(),
-                                context: "string_concat".to_string(),
-                            });
-                        }
-                        let packed_a = ctx.scope.get_or_alloc("__str_concat_a");
-                        let packed_b = ctx.scope.get_or_alloc("__str_concat_b");
-                        let ptr_a = ctx.scope.get_or_alloc("__str_ptr_a");
-                        let len_a = ctx.scope.get_or_alloc("__str_len_a");
-                        let ptr_b = ctx.scope.get_or_alloc("__str_ptr_b");
-                        let len_b = ctx.scope.get_or_alloc("__str_len_b");
-                        let new_ptr = ctx.scope.get_or_alloc("__str_new_ptr");
+    /// Body of `__str_char_at(s, index)`: character `index` of `s` as a
+    /// one-character string. Traps when `index` is out of range.
+    fn emit_str_char_at(
+        ctx: &mut FuncContext,
+        _func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let start = ctx.scope.get_or_alloc("__str_start");
+        let end = ctx.scope.get_or_alloc("__str_end");
+        let next = ctx.scope.get_or_alloc("__str_next");
+        let count = ctx.scope.get_or_alloc("__str_count");
+        Self::emit_utf8_offset(ctx, 1, start, count);
+        ctx.emit(Instruction::LocalGet(1));
+        ctx.emit(Instruction::I64Const(1));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalSet(next));
+        Self::emit_utf8_offset(ctx, next, end, count);
+        Self::emit_substring(ctx, start, end);
+        Ok(())
+    }
 
-                        // Evaluate and store both args
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::LocalSet(packed_a));
-                        Self::compile_expr(ctx, &args[1], func_map)?;
-                        ctx.emit(Instruction::LocalSet(packed_b));
+    /// Body of `__str_slice_chars(s, start, end)`: the characters of `s`
+    /// from `start` up to (not including) `end`. Traps unless
+    /// `0 <= start <= end <= string_char_len(s)`.
+    fn emit_str_slice_chars(
+        ctx: &mut FuncContext,
+        _func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let start = ctx.scope.get_or_alloc("__str_start");
+        let end = ctx.scope.get_or_alloc("__str_end");
+        let count = ctx.scope.get_or_alloc("__str_count");
+        ctx.emit(Instruction::LocalGet(2));
+        ctx.emit(Instruction::LocalGet(1));
+        ctx.emit(Instruction::I64LtS);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::Unreachable);
+        ctx.emit(Instruction::End);
+        Self::emit_utf8_offset(ctx, 1, start, count);
+        Self::emit_utf8_offset(ctx, 2, end, count);
+        Self::emit_substring(ctx, start, end);
+        Ok(())
+    }
 
-                        // Unpack a: ptr_a = (packed_a >> 32) as i32, len_a = packed_a & 0xFFFFFFFF
-                        ctx.emit(Instruction::LocalGet(packed_a));
-                        ctx.emit(Instruction::I64Const(32));
-                        ctx.emit(Instruction::I64ShrU);
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I64ExtendI32U);
-                        ctx.emit(Instruction::LocalSet(ptr_a));
+    /// Body of `__str_to_upper(s)`, see [`Self::emit_str_case`].
+    fn emit_str_to_upper(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_str_case(ctx, func_map, STRING_UPPER_RANGES)
+    }
 
-                        ctx.emit(Instruction::LocalGet(packed_a));
-                        ctx.emit(Instruction::I64Const(0xFFFFFFFF));
-                        ctx.emit(Instruction::I64And);
-                        ctx.emit(Instruction::LocalSet(len_a));
+    /// Body of `__str_to_lower(s)`, see [`Self::emit_str_case`].
+    fn emit_str_to_lower(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_str_case(ctx, func_map, STRING_LOWER_RANGES)
+    }
 
-                        // Unpack b: ptr_b, len_b
-                        ctx.emit(Instruction::LocalGet(packed_b));
-                        ctx.emit(Instruction::I64Const(32));
-                        ctx.emit(Instruction::I64ShrU);
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I64ExtendI32U);
-                        ctx.emit(Instruction::LocalSet(ptr_b));
+    /// Copy the packed string in local 0 into a new buffer, decoding each
+    /// one- and two-byte UTF-8 sequence and shifting its code point by the
+    /// first matching `(first, last, skip, delta)` range. Every mapped
+    /// character keeps its encoded width, so the copy has the same length;
+    /// longer sequences are copied unchanged.
+    fn emit_str_case(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+        ranges: &[(i64, i64, i64, i64)],
+    ) -> Result<(), WasmCompileError> {
+        let len = ctx.scope.get_or_alloc("__str_len");
+        let dst = ctx.scope.get_or_alloc("__str_dst");
+        let idx = ctx.scope.get_or_alloc("__str_idx");
+        let lead = ctx.scope.get_or_alloc("__str_lead");
+        let code = ctx.scope.get_or_alloc("__str_code");
+        let mapped = ctx.scope.get_or_alloc("__str_mapped");
+        let next = ctx.scope.get_or_alloc("__str_next");
+        let store = |ctx: &mut FuncContext, offset: u64| {
+            ctx.emit(Instruction::I32Store8(wasm_encoder::MemArg {
+                offset,
+                align: 0,
+                memory_index: 0,
+            }));
+        };
+        let dst_addr = |ctx: &mut FuncContext| {
+            ctx.emit(Instruction::LocalGet(dst));
+            ctx.emit(Instruction::LocalGet(idx));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::I32WrapI64);
+        };
 
-                        ctx.emit(Instruction::LocalGet(packed_b));
-                        ctx.emit(Instruction::I64Const(0xFFFFFFFF));
-                        ctx.emit(Instruction::I64And);
-                        ctx.emit(Instruction::LocalSet(len_b));
+        Self::emit_string_len(ctx, 0);
+        ctx.emit(Instruction::LocalTee(len));
+        ctx.emit(Instruction::Call(func_map["__alloc"]));
+        ctx.emit(Instruction::LocalSet(dst));
 
-                        // Allocate new buffer: __alloc(len_a + len_b)
-                        ctx.emit(Instruction::LocalGet(len_a));
-                        ctx.emit(Instruction::LocalGet(len_b));
-                        ctx.emit(Instruction::I64Add);
-                        if let Some(&alloc_idx) = func_map.get("__alloc") {
-                            ctx.emit(Instruction::Call(alloc_idx));
-                        } else {
-                            return Err(WasmCompileError {
-                                message: "__alloc not found".to_string(),
-                                context: "string_concat".to_string(),
-                            });
-                        }
-                        ctx.emit(Instruction::LocalSet(new_ptr)); // new_ptr (i64, but value is i32-range)
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(idx));
+            ctx.emit(Instruction::LocalGet(len));
+            ctx.emit(Instruction::I64GeU);
+            ctx.emit(Instruction::BrIf(1));
+            Self::emit_string_byte(ctx, 0, idx);
+            ctx.emit(Instruction::I64ExtendI32U);
+            ctx.emit(Instruction::LocalSet(lead));
+            ctx.emit(Instruction::LocalGet(idx));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(next));
+
+            // code = the decoded code point, or -1 to copy the byte as is
+            ctx.emit(Instruction::I64Const(-1));
+            ctx.emit(Instruction::LocalSet(code));
+            ctx.emit(Instruction::LocalGet(lead));
+            ctx.emit(Instruction::I64Const(0x80));
+            ctx.emit(Instruction::I64LtU);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            ctx.emit(Instruction::LocalGet(lead));
+            ctx.emit(Instruction::LocalSet(code));
+            ctx.emit(Instruction::Else);
+            // A two-byte sequence `110xxxxx 10yyyyyy` with both bytes present
+            ctx.emit(Instruction::LocalGet(lead));
+            ctx.emit(Instruction::I64Const(0xE0));
+            ctx.emit(Instruction::I64And);
+            ctx.emit(Instruction::I64Const(0xC0));
+            ctx.emit(Instruction::I64Eq);
+            ctx.emit(Instruction::LocalGet(next));
+            ctx.emit(Instruction::LocalGet(len));
+            ctx.emit(Instruction::I64LtU);
+            ctx.emit(Instruction::I32And);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            ctx.emit(Instruction::LocalGet(lead));
+            ctx.emit(Instruction::I64Const(0x1F));
+            ctx.emit(Instruction::I64And);
+            ctx.emit(Instruction::I64Const(6));
+            ctx.emit(Instruction::I64Shl);
+            Self::emit_string_byte(ctx, 0, next);
+            ctx.emit(Instruction::I32Const(0x3F));
+            ctx.emit(Instruction::I32And);
+            ctx.emit(Instruction::I64ExtendI32U);
+            ctx.emit(Instruction::I64Or);
+            ctx.emit(Instruction::LocalSet(code));
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::End);
 
-                        // memory.copy(new_ptr, ptr_a, len_a)
-                        ctx.emit(Instruction::LocalGet(new_ptr));
-                        ctx.emit(Instruction::I32WrapI64); // dest
-                        ctx.emit(Instruction::LocalGet(ptr_a));
-                        ctx.emit(Instruction::I32WrapI64); // src
-                        ctx.emit(Instruction::LocalGet(len_a));
-                        ctx.emit(Instruction::I32WrapI64); // size
-                        ctx.emit(Instruction::MemoryCopy {
-                            src_mem: 0,
-                            dst_mem: 0,
-                        });
+            ctx.emit(Instruction::LocalGet(code));
+            ctx.emit(Instruction::LocalSet(mapped));
+            for &(first, last, skip, delta) in ranges {
+                ctx.emit(Instruction::LocalGet(code));
+                ctx.emit(Instruction::I64Const(first));
+                ctx.emit(Instruction::I64GeS);
+                ctx.emit(Instruction::LocalGet(code));
+                ctx.emit(Instruction::I64Const(last));
+                ctx.emit(Instruction::I64LeS);
+                ctx.emit(Instruction::I32And);
+                ctx.emit(Instruction::LocalGet(code));
+                ctx.emit(Instruction::I64Const(skip));
+                ctx.emit(Instruction::I64Ne);
+                ctx.emit(Instruction::I32And);
+                ctx.emit(Instruction::If(BlockType::Empty));
+                ctx.emit(Instruction::LocalGet(code));
+                ctx.emit(Instruction::I64Const(delta));
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::LocalSet(mapped));
+                ctx.emit(Instruction::End);
+            }
 
-                        // memory.copy(new_ptr + len_a, ptr_b, len_b)
-                        ctx.emit(Instruction::LocalGet(new_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::LocalGet(len_a));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I32Add); // dest = new_ptr + len_a
-                        ctx.emit(Instruction::LocalGet(ptr_b));
-                        ctx.emit(Instruction::I32WrapI64); // src
-                        ctx.emit(Instruction::LocalGet(len_b));
-                        ctx.emit(Instruction::I32WrapI64); // size
-                        ctx.emit(Instruction::MemoryCopy {
-                            src_mem: 0,
-                            dst_mem: 0,
-                        });
+            ctx.emit(Instruction::LocalGet(code));
+            ctx.emit(Instruction::I64Const(0x80));
+            ctx.emit(Instruction::I64GeS);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            {
+                // Re-encode the two-byte sequence
+                dst_addr(ctx);
+                ctx.emit(Instruction::LocalGet(mapped));
+                ctx.emit(Instruction::I64Const(6));
+                ctx.emit(Instruction::I64ShrU);
+                ctx.emit(Instruction::I64Const(0xC0));
+                ctx.emit(Instruction::I64Or);
+                ctx.emit(Instruction::I32WrapI64);
+                store(ctx, 0);
+                dst_addr(ctx);
+                ctx.emit(Instruction::LocalGet(mapped));
+                ctx.emit(Instruction::I64Const(0x3F));
+                ctx.emit(Instruction::I64And);
+                ctx.emit(Instruction::I64Const(0x80));
+                ctx.emit(Instruction::I64Or);
+                ctx.emit(Instruction::I32WrapI64);
+                store(ctx, 1);
+                ctx.emit(Instruction::LocalGet(next));
+                ctx.emit(Instruction::I64Const(1));
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::LocalSet(next));
+            }
+            ctx.emit(Instruction::Else);
+            {
+                // ASCII (possibly mapped) or a byte copied unchanged
+                dst_addr(ctx);
+                ctx.emit(Instruction::LocalGet(lead));
+                ctx.emit(Instruction::LocalGet(mapped));
+                ctx.emit(Instruction::LocalGet(code));
+                ctx.emit(Instruction::I64Const(0));
+                ctx.emit(Instruction::I64LtS);
+                ctx.emit(Instruction::Select);
+                ctx.emit(Instruction::I32WrapI64);
+                store(ctx, 0);
+            }
+            ctx.emit(Instruction::End);
 
-                        // Pack result: (new_ptr << 32) | (len_a + len_b)
-                        ctx.emit(Instruction::LocalGet(new_ptr));
-                        ctx.emit(Instruction::I64Const(32));
-                        ctx.emit(Instruction::I64Shl);
-                        ctx.emit(Instruction::LocalGet(len_a));
-                        ctx.emit(Instruction::LocalGet(len_b));
-                        ctx.emit(Instruction::I64Add);
-                        ctx.emit(Instruction::I64Or);
-                    }
+            ctx.emit(Instruction::LocalGet(next));
+            ctx.emit(Instruction::LocalSet(idx));
+            ctx.emit(Instruction::Br(0));
+        }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
 
-                    // string_eq(a, b) → 1 if equal, 0 if not
-                    "string_eq" | "intrinsic_string_eq" => {
-                        if args.len() != 2 {
-                            return Err(WasmCompileError {
-                                message: "string_eq requires 2 arguments".to_string(),
-                                context: "string_eq".to_string(),
-                            });
-                        }
-                        let packed_a = ctx.scope.get_or_alloc("__streq_a");
-                        let packed_b = ctx.scope.get_or_alloc("__streq_b");
-                        let ptr_a = ctx.scope.get_or_alloc("__streq_ptr_a");
-                        let len_a = ctx.scope.get_or_alloc("__streq_len_a");
-                        let ptr_b = ctx.scope.get_or_alloc("__streq_ptr_b");
-                        let len_b = ctx.scope.get_or_alloc("__streq_len_b");
-                        let idx = ctx.scope.get_or_alloc("__streq_idx");
-                        let result = ctx.scope.get_or_alloc("__streq_result");
+        // Pack: (dst << 32) | len
+        ctx.emit(Instruction::LocalGet(dst));
+        ctx.emit(Instruction::I64Const(32));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::LocalGet(len));
+        ctx.emit(Instruction::I64Or);
+        Ok(())
+    }
 
-                        // Evaluate args
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::LocalSet(packed_a));
-                        Self::compile_expr(ctx, &args[1], func_map)?;
-                        ctx.emit(Instruction::LocalSet(packed_b));
+    /// Push the word at the address in `addr` plus `offset`.
+    fn emit_load_word(ctx: &mut FuncContext, addr: u32, offset: u64) {
+        ctx.emit(Instruction::LocalGet(addr));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+            offset,
+            align: 3,
+            memory_index: 0,
+        }));
+    }
 
-                        // Unpack a
-                        ctx.emit(Instruction::LocalGet(packed_a));
-                        ctx.emit(Instruction::I64Const(32));
-                        ctx.emit(Instruction::I64ShrU);
-                        ctx.emit(Instruction::LocalSet(ptr_a));
+    /// Store `value` in the word at the address in `addr` plus `offset`.
+    fn emit_store_word(ctx: &mut FuncContext, addr: u32, offset: u64, value: u32) {
+        ctx.emit(Instruction::LocalGet(addr));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::LocalGet(value));
+        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+            offset,
+            align: 3,
+            memory_index: 0,
+        }));
+    }
 
-                        ctx.emit(Instruction::LocalGet(packed_a));
-                        ctx.emit(Instruction::I64Const(0xFFFFFFFF));
-                        ctx.emit(Instruction::I64And);
-                        ctx.emit(Instruction::LocalSet(len_a));
+    /// Copy `len` bytes from the address in `src` to the one in `dst`.
+    fn emit_copy_bytes(ctx: &mut FuncContext, dst: u32, src: u32, len: u32) {
+        for local in [dst, src, len] {
+            ctx.emit(Instruction::LocalGet(local));
+            ctx.emit(Instruction::I32WrapI64);
+        }
+        ctx.emit(Instruction::MemoryCopy {
+            src_mem: 0,
+            dst_mem: 0,
+        });
+    }
 
-                        // Unpack b
-                        ctx.emit(Instruction::LocalGet(packed_b));
-                        ctx.emit(Instruction::I64Const(32));
-                        ctx.emit(Instruction::I64ShrU);
-                        ctx.emit(Instruction::LocalSet(ptr_b));
+    /// Set `equal` to 1 when the packed strings `a` and `b` hold the same
+    /// bytes, else 0, using `i` as scratch.
+    fn emit_strings_equal(ctx: &mut FuncContext, a: u32, b: u32, (equal, i): (u32, u32)) {
+        ctx.emit(Instruction::LocalGet(a));
+        ctx.emit(Instruction::LocalGet(b));
+        ctx.emit(Instruction::I64Eq);
+        ctx.emit(Instruction::I64ExtendI32U);
+        ctx.emit(Instruction::LocalSet(equal));
 
-                        ctx.emit(Instruction::LocalGet(packed_b));
-                        ctx.emit(Instruction::I64Const(0xFFFFFFFF));
-                        ctx.emit(Instruction::I64And);
-                        ctx.emit(Instruction::LocalSet(len_b));
+        // Different pointers of the same length: compare byte by byte
+        ctx.emit(Instruction::LocalGet(equal));
+        ctx.emit(Instruction::I64Eqz);
+        Self::emit_string_len(ctx, a);
+        Self::emit_string_len(ctx, b);
+        ctx.emit(Instruction::I64Eq);
+        ctx.emit(Instruction::I32And);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        {
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::LocalSet(equal));
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::LocalSet(i));
+            ctx.emit(Instruction::Block(BlockType::Empty));
+            ctx.emit(Instruction::Loop(BlockType::Empty));
+            ctx.emit(Instruction::LocalGet(i));
+            Self::emit_string_len(ctx, a);
+            ctx.emit(Instruction::I64GeU);
+            ctx.emit(Instruction::BrIf(1));
+            Self::emit_string_byte(ctx, a, i);
+            Self::emit_string_byte(ctx, b, i);
+            ctx.emit(Instruction::I32Ne);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::LocalSet(equal));
+            ctx.emit(Instruction::Br(2));
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::LocalGet(i));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(i));
+            ctx.emit(Instruction::Br(0));
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::End);
+        }
+        ctx.emit(Instruction::End);
+    }
 
-                        // Default result = 1 (assume equal)
-                        ctx.emit(Instruction::I64Const(1));
-                        ctx.emit(Instruction::LocalSet(result));
+    /// Set `slot` to the address of slot `(index >> shift) & 31` of the
+    /// vector trie node in `node`.
+    fn emit_pvec_slot(ctx: &mut FuncContext, node: u32, index: u32, shift: u32, slot: u32) {
+        ctx.emit(Instruction::LocalGet(node));
+        ctx.emit(Instruction::LocalGet(index));
+        ctx.emit(Instruction::LocalGet(shift));
+        ctx.emit(Instruction::I64ShrU);
+        ctx.emit(Instruction::I64Const(31));
+        ctx.emit(Instruction::I64And);
+        ctx.emit(Instruction::I64Const(3));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalSet(slot));
+    }
 
-                        // If len_a != len_b → result = 0, skip loop
-                        ctx.emit(Instruction::LocalGet(len_a));
-                        ctx.emit(Instruction::LocalGet(len_b));
-                        ctx.emit(Instruction::I64Ne);
-                        ctx.emit(Instruction::If(BlockType::Empty));
-                        ctx.emit(Instruction::I64Const(0));
-                        ctx.emit(Instruction::LocalSet(result));
-                        ctx.emit(Instruction::Else);
+    /// Set `copy` to a new vector trie node holding the slots of the one in
+    /// `node`, or to an empty node when `node` is 0.
+    fn emit_pvec_copy_node(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+        node: u32,
+        copy: u32,
+    ) {
+        ctx.emit(Instruction::I64Const(PVEC_NODE_BYTES));
+        ctx.emit(Instruction::Call(func_map["__alloc"]));
+        ctx.emit(Instruction::LocalSet(copy));
+        ctx.emit(Instruction::LocalGet(node));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::I64Ne);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::LocalGet(copy));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::LocalGet(node));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I32Const(PVEC_NODE_BYTES as i32));
+        ctx.emit(Instruction::MemoryCopy {
+            src_mem: 0,
+            dst_mem: 0,
+        });
+        ctx.emit(Instruction::End);
+    }
 
-                        // Byte-by-byte comparison loop
-                        ctx.emit(Instruction::I64Const(0));
-                        ctx.emit(Instruction::LocalSet(idx));
+    /// Body of `__pvec_new()`: an empty persistent vector.
+    fn emit_pvec_new(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        ctx.emit(Instruction::I64Const(24));
+        Self::emit_alloc_object(ctx, HEAP_TAG_PVEC, func_map, "__pvec_new")
+    }
 
-                        ctx.emit(Instruction::Block(BlockType::Empty)); // outer block for break
-                        ctx.emit(Instruction::Loop(BlockType::Empty));
+    /// Body of `__pvec_len(vec)`.
+    fn emit_pvec_len(
+        ctx: &mut FuncContext,
+        _func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_heap_object_guard(ctx, 0, HEAP_TAG_PVEC, 0);
+        Self::emit_load_word(ctx, 0, 0);
+        Ok(())
+    }
 
-                        // if idx >= len_a → break
-                        ctx.emit(Instruction::LocalGet(idx));
-                        ctx.emit(Instruction::LocalGet(len_a));
-                        ctx.emit(Instruction::I64GeU);
-                        ctx.emit(Instruction::BrIf(1)); // break out of block
+    /// Body of `__pvec_get(vec, index)`: the element at `index`, or 0
+    /// (unit) when it is out of range, as in the interpreter.
+    fn emit_pvec_get(
+        ctx: &mut FuncContext,
+        _func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let node = ctx.scope.get_or_alloc("__pvec_node");
+        let shift = ctx.scope.get_or_alloc("__pvec_shift");
+        Self::emit_heap_object_guard(ctx, 0, HEAP_TAG_PVEC, 0);
+
+        // A negative index compares as a huge unsigned one
+        ctx.emit(Instruction::LocalGet(1));
+        Self::emit_load_word(ctx, 0, 0);
+        ctx.emit(Instruction::I64GeU);
+        ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::Else);
+        {
+            Self::emit_load_word(ctx, 0, 16);
+            ctx.emit(Instruction::LocalSet(node));
+            Self::emit_load_word(ctx, 0, 8);
+            ctx.emit(Instruction::LocalSet(shift));
+            ctx.emit(Instruction::Block(BlockType::Empty));
+            ctx.emit(Instruction::Loop(BlockType::Empty));
+            {
+                ctx.emit(Instruction::LocalGet(shift));
+                ctx.emit(Instruction::I64Eqz);
+                ctx.emit(Instruction::BrIf(1));
+                Self::emit_pvec_slot(ctx, node, 1, shift, node);
+                Self::emit_load_word(ctx, node, 0);
+                ctx.emit(Instruction::LocalSet(node));
+                ctx.emit(Instruction::LocalGet(shift));
+                ctx.emit(Instruction::I64Const(5));
+                ctx.emit(Instruction::I64Sub);
+                ctx.emit(Instruction::LocalSet(shift));
+                ctx.emit(Instruction::Br(0));
+            }
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::End);
+            Self::emit_pvec_slot(ctx, node, 1, shift, node);
+            Self::emit_load_word(ctx, node, 0);
+        }
+        ctx.emit(Instruction::End);
+        Ok(())
+    }
 
-                        // Compare bytes: mem[ptr_a + idx] vs mem[ptr_b + idx]
-                        ctx.emit(Instruction::LocalGet(ptr_a));
-                        ctx.emit(Instruction::LocalGet(idx));
-                        ctx.emit(Instruction::I64Add);
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I32Load8U(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 0,
-                            memory_index: 0,
-                        }));
+    /// Body of `__pvec_set(vec, index, value)`: a new vector with `value` at
+    /// `index`, which is at most the length; writing at the length appends.
+    /// Callers check the vector and the index.
+    fn emit_pvec_set(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let shift = ctx.scope.get_or_alloc("__pvec_shift");
+        let root = ctx.scope.get_or_alloc("__pvec_root");
+        let node = ctx.scope.get_or_alloc("__pvec_node");
+        let level = ctx.scope.get_or_alloc("__pvec_level");
+        let slot = ctx.scope.get_or_alloc("__pvec_slot");
+        let child = ctx.scope.get_or_alloc("__pvec_child");
+        let vec = ctx.scope.get_or_alloc("__pvec_vec");
+        Self::emit_load_word(ctx, 0, 8);
+        ctx.emit(Instruction::LocalSet(shift));
+        Self::emit_load_word(ctx, 0, 16);
+        ctx.emit(Instruction::LocalSet(root));
+
+        // An index past what the trie can reach puts a new root above it
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(1));
+            ctx.emit(Instruction::LocalGet(shift));
+            ctx.emit(Instruction::I64ShrU);
+            ctx.emit(Instruction::I64Const(31));
+            ctx.emit(Instruction::I64LeU);
+            ctx.emit(Instruction::BrIf(1));
+            ctx.emit(Instruction::I64Const(PVEC_NODE_BYTES));
+            ctx.emit(Instruction::Call(func_map["__alloc"]));
+            ctx.emit(Instruction::LocalSet(node));
+            Self::emit_store_word(ctx, node, 0, root);
+            ctx.emit(Instruction::LocalGet(node));
+            ctx.emit(Instruction::LocalSet(root));
+            ctx.emit(Instruction::LocalGet(shift));
+            ctx.emit(Instruction::I64Const(5));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(shift));
+            ctx.emit(Instruction::Br(0));
+        }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
 
-                        ctx.emit(Instruction::LocalGet(ptr_b));
-                        ctx.emit(Instruction::LocalGet(idx));
-                        ctx.emit(Instruction::I64Add);
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I32Load8U(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 0,
-                            memory_index: 0,
-                        }));
+        // Copy each node on the path down to the element's leaf
+        Self::emit_pvec_copy_node(ctx, func_map, root, node);
+        ctx.emit(Instruction::LocalGet(node));
+        ctx.emit(Instruction::LocalSet(root));
+        ctx.emit(Instruction::LocalGet(shift));
+        ctx.emit(Instruction::LocalSet(level));
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(level));
+            ctx.emit(Instruction::I64Eqz);
+            ctx.emit(Instruction::BrIf(1));
+            Self::emit_pvec_slot(ctx, node, 1, level, slot);
+            Self::emit_load_word(ctx, slot, 0);
+            ctx.emit(Instruction::LocalSet(child));
+            Self::emit_pvec_copy_node(ctx, func_map, child, node);
+            Self::emit_store_word(ctx, slot, 0, node);
+            ctx.emit(Instruction::LocalGet(level));
+            ctx.emit(Instruction::I64Const(5));
+            ctx.emit(Instruction::I64Sub);
+            ctx.emit(Instruction::LocalSet(level));
+            ctx.emit(Instruction::Br(0));
+        }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
+        Self::emit_pvec_slot(ctx, node, 1, level, slot);
+        Self::emit_store_word(ctx, slot, 0, 2);
+
+        ctx.emit(Instruction::I64Const(24));
+        Self::emit_alloc_object(ctx, HEAP_TAG_PVEC, func_map, "__pvec_set")?;
+        ctx.emit(Instruction::LocalSet(vec));
+        // len + (index == len)
+        ctx.emit(Instruction::LocalGet(vec));
+        ctx.emit(Instruction::I32WrapI64);
+        Self::emit_load_word(ctx, 0, 0);
+        ctx.emit(Instruction::LocalGet(1));
+        Self::emit_load_word(ctx, 0, 0);
+        ctx.emit(Instruction::I64Eq);
+        ctx.emit(Instruction::I64ExtendI32U);
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: 0,
+        }));
+        Self::emit_store_word(ctx, vec, 8, shift);
+        Self::emit_store_word(ctx, vec, 16, root);
+        ctx.emit(Instruction::LocalGet(vec));
+        Ok(())
+    }
 
-                        ctx.emit(Instruction::I32Ne);
-                        ctx.emit(Instruction::If(BlockType::Empty));
-                        ctx.emit(Instruction::I64Const(0));
-                        ctx.emit(Instruction::LocalSet(result));
-                        ctx.emit(Instruction::Br(2)); // break out of outer block
-                        ctx.emit(Instruction::End); // end if
+    /// Body of `__pvec_conj(vec, value)`: a new vector with `value` appended.
+    fn emit_pvec_conj(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_heap_object_guard(ctx, 0, HEAP_TAG_PVEC, 0);
+        ctx.emit(Instruction::LocalGet(0));
+        Self::emit_load_word(ctx, 0, 0);
+        ctx.emit(Instruction::LocalGet(1));
+        ctx.emit(Instruction::Call(func_map["__pvec_set"]));
+        Ok(())
+    }
 
-                        // idx += 1
-                        ctx.emit(Instruction::LocalGet(idx));
-                        ctx.emit(Instruction::I64Const(1));
-                        ctx.emit(Instruction::I64Add);
-                        ctx.emit(Instruction::LocalSet(idx));
+    /// Body of `__pvec_assoc(vec, index, value)`: a new vector with `value`
+    /// at `index`. An index out of range traps, as the interpreter raises
+    /// an error.
+    fn emit_pvec_assoc(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_heap_object_guard(ctx, 0, HEAP_TAG_PVEC, 0);
+        ctx.emit(Instruction::LocalGet(1));
+        Self::emit_load_word(ctx, 0, 0);
+        ctx.emit(Instruction::I64GeU);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::Unreachable);
+        ctx.emit(Instruction::End);
+        for param in 0..3 {
+            ctx.emit(Instruction::LocalGet(param));
+        }
+        ctx.emit(Instruction::Call(func_map["__pvec_set"]));
+        Ok(())
+    }
 
-                        ctx.emit(Instruction::Br(0)); // continue loop
-                        ctx.emit(Instruction::End); // end loop
-                        ctx.emit(Instruction::End); // end block
+    /// Body of `__pvec_pop(vec)`: the vector without its last element,
+    /// sharing the whole trie. Popping an empty vector traps.
+    fn emit_pvec_pop(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let vec = ctx.scope.get_or_alloc("__pvec_vec");
+        Self::emit_heap_object_guard(ctx, 0, HEAP_TAG_PVEC, 0);
+        Self::emit_load_word(ctx, 0, 0);
+        ctx.emit(Instruction::I64Eqz);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::Unreachable);
+        ctx.emit(Instruction::End);
 
-                        ctx.emit(Instruction::End); // end else (len check)
+        ctx.emit(Instruction::I64Const(24));
+        Self::emit_alloc_object(ctx, HEAP_TAG_PVEC, func_map, "__pvec_pop")?;
+        ctx.emit(Instruction::LocalSet(vec));
+        for offset in [0, 8, 16] {
+            ctx.emit(Instruction::LocalGet(vec));
+            ctx.emit(Instruction::I32WrapI64);
+            Self::emit_load_word(ctx, 0, offset);
+            if offset == 0 {
+                ctx.emit(Instruction::I64Const(1));
+                ctx.emit(Instruction::I64Sub);
+            }
+            ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                offset,
+                align: 3,
+                memory_index: 0,
+            }));
+        }
+        ctx.emit(Instruction::LocalGet(vec));
+        Ok(())
+    }
 
-                        // Push result
-                        ctx.emit(Instruction::LocalGet(result));
-                    }
+    /// Set `index` to the entry a key indexes by at trie depth `level`: 5
+    /// bits of its hash per level, then 4 bits per level of its length and
+    /// after that of its bytes, low bits first and 0 past the last byte.
+    fn emit_hamt_index(ctx: &mut FuncContext, key: u32, hash: u32, level: u32, index: u32) {
+        let past_length = HAMT_HASH_LEVELS + 8;
+        ctx.emit(Instruction::LocalGet(level));
+        ctx.emit(Instruction::I64Const(HAMT_HASH_LEVELS));
+        ctx.emit(Instruction::I64LtU);
+        ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+        {
+            ctx.emit(Instruction::LocalGet(hash));
+            ctx.emit(Instruction::LocalGet(level));
+            ctx.emit(Instruction::I64Const(5));
+            ctx.emit(Instruction::I64Mul);
+            ctx.emit(Instruction::I64ShrU);
+            ctx.emit(Instruction::I64Const(31));
+            ctx.emit(Instruction::I64And);
+        }
+        ctx.emit(Instruction::Else);
+        ctx.emit(Instruction::LocalGet(level));
+        ctx.emit(Instruction::I64Const(past_length));
+        ctx.emit(Instruction::I64LtU);
+        ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+        {
+            Self::emit_string_len(ctx, key);
+            ctx.emit(Instruction::LocalGet(level));
+            ctx.emit(Instruction::I64Const(HAMT_HASH_LEVELS));
+            ctx.emit(Instruction::I64Sub);
+            ctx.emit(Instruction::I64Const(2));
+            ctx.emit(Instruction::I64Shl);
+            ctx.emit(Instruction::I64ShrU);
+            ctx.emit(Instruction::I64Const(15));
+            ctx.emit(Instruction::I64And);
+        }
+        ctx.emit(Instruction::Else);
+        {
+            // Two levels per byte; `index` holds the byte's offset for now
+            ctx.emit(Instruction::LocalGet(level));
+            ctx.emit(Instruction::I64Const(past_length));
+            ctx.emit(Instruction::I64Sub);
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64ShrU);
+            ctx.emit(Instruction::LocalSet(index));
+            ctx.emit(Instruction::LocalGet(index));
+            Self::emit_string_len(ctx, key);
+            ctx.emit(Instruction::I64LtU);
+            ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+            Self::emit_string_byte(ctx, key, index);
+            ctx.emit(Instruction::I64ExtendI32U);
+            ctx.emit(Instruction::LocalGet(level));
+            ctx.emit(Instruction::I64Const(past_length));
+            ctx.emit(Instruction::I64Sub);
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64And);
+            ctx.emit(Instruction::I64Const(2));
+            ctx.emit(Instruction::I64Shl);
+            ctx.emit(Instruction::I64ShrU);
+            ctx.emit(Instruction::I64Const(15));
+            ctx.emit(Instruction::I64And);
+            ctx.emit(Instruction::Else);
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::End);
+        }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::LocalSet(index));
+    }
 
-                    // string_slice(s, start, end) → zero-copy substring
-                    "string_slice" | "intrinsic_string_slice" => {
-                        if args.len() != 3 {
-                            return Err(WasmCompileError {
-                                message: "string_slice requires 3 arguments (str, start, end)"
-                                    .to_string(),
-                                context: "string_slice".to_string(),
-                            });
-                        }
-                        let packed = ctx.scope.get_or_alloc("__str_slice_packed");
-                        let start = ctx.scope.get_or_alloc("__str_slice_start");
-                        let end = ctx.scope.get_or_alloc("__str_slice_end");
+    /// Load the bitmap of the map trie node in `node` into `bitmap`, set
+    /// `bit` to bit `index` and `entry` to the address that entry has, or
+    /// would have, among the node's entries.
+    fn emit_hamt_entry(
+        ctx: &mut FuncContext,
+        node: u32,
+        index: u32,
+        (bitmap, bit, entry): (u32, u32, u32),
+    ) {
+        Self::emit_load_word(ctx, node, 0);
+        ctx.emit(Instruction::LocalSet(bitmap));
+        ctx.emit(Instruction::I64Const(1));
+        ctx.emit(Instruction::LocalGet(index));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::LocalSet(bit));
+        // node + 8 + 16 * popcount(bitmap & (bit - 1))
+        ctx.emit(Instruction::LocalGet(node));
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalGet(bitmap));
+        ctx.emit(Instruction::LocalGet(bit));
+        ctx.emit(Instruction::I64Const(1));
+        ctx.emit(Instruction::I64Sub);
+        ctx.emit(Instruction::I64And);
+        ctx.emit(Instruction::I64Popcnt);
+        ctx.emit(Instruction::I64Const(4));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalSet(entry));
+    }
 
-                        // Evaluate args
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::LocalSet(packed));
-                        Self::compile_expr(ctx, &args[1], func_map)?;
-                        ctx.emit(Instruction::LocalSet(start));
-                        Self::compile_expr(ctx, &args[2], func_map)?;
-                        ctx.emit(Instruction::LocalSet(end));
+    /// Set `local` to `base + delta - origin`: the address in a copied node
+    /// matching `delta` in the node at `origin` when `base` is the copy.
+    fn emit_rebase(ctx: &mut FuncContext, base: u32, delta: u32, origin: u32, local: u32) {
+        ctx.emit(Instruction::LocalGet(base));
+        ctx.emit(Instruction::LocalGet(delta));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalGet(origin));
+        ctx.emit(Instruction::I64Sub);
+        ctx.emit(Instruction::LocalSet(local));
+    }
 
-                        // Extract original ptr: (packed >> 32)
-                        ctx.emit(Instruction::LocalGet(packed));
-                        ctx.emit(Instruction::I64Const(32));
-                        ctx.emit(Instruction::I64ShrU);
-                        // new_ptr = original_ptr + start
-                        ctx.emit(Instruction::LocalGet(start));
-                        ctx.emit(Instruction::I64Add);
-                        // shift left 32 to pack as high bits
-                        ctx.emit(Instruction::I64Const(32));
-                        ctx.emit(Instruction::I64Shl);
-                        // new_len = end - start
-                        ctx.emit(Instruction::LocalGet(end));
-                        ctx.emit(Instruction::LocalGet(start));
-                        ctx.emit(Instruction::I64Sub);
-                        // Pack: (new_ptr << 32) | new_len
-                        ctx.emit(Instruction::I64Or);
-                    }
+    /// Body of `__pmap_find(root, key)`: the address of the entry holding
+    /// `key` in the trie under `root`, or 0 when there is none.
+    fn emit_pmap_find(
+        ctx: &mut FuncContext,
+        _func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let hash = ctx.scope.get_or_alloc("__pmap_hash");
+        let len = ctx.scope.get_or_alloc("__pmap_len");
+        let i = ctx.scope.get_or_alloc("__pmap_i");
+        let node = ctx.scope.get_or_alloc("__pmap_node");
+        let level = ctx.scope.get_or_alloc("__pmap_level");
+        let index = ctx.scope.get_or_alloc("__pmap_index");
+        let bitmap = ctx.scope.get_or_alloc("__pmap_bitmap");
+        let bit = ctx.scope.get_or_alloc("__pmap_bit");
+        let entry = ctx.scope.get_or_alloc("__pmap_entry");
+        let other = ctx.scope.get_or_alloc("__pmap_other");
+        let equal = ctx.scope.get_or_alloc("__pmap_equal");
+        let found = ctx.scope.get_or_alloc("__pmap_found");
+        Self::emit_fnv1a(ctx, 1, (hash, len, i));
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::LocalSet(node));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(level));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(found));
 
-                    // =========================================================
-                    // Tier 1: List & Struct intrinsics (linear memory ops)
-                    // =========================================================
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(node));
+            ctx.emit(Instruction::I64Eqz);
+            ctx.emit(Instruction::BrIf(1));
+            Self::emit_hamt_index(ctx, 1, hash, level, index);
+            Self::emit_hamt_entry(ctx, node, index, (bitmap, bit, entry));
+            ctx.emit(Instruction::LocalGet(bitmap));
+            ctx.emit(Instruction::LocalGet(bit));
+            ctx.emit(Instruction::I64And);
+            ctx.emit(Instruction::I64Eqz);
+            ctx.emit(Instruction::BrIf(1));
+            Self::emit_load_word(ctx, entry, 0);
+            ctx.emit(Instruction::LocalSet(other));
+
+            // A child node: go down a level
+            ctx.emit(Instruction::LocalGet(other));
+            ctx.emit(Instruction::I64Eqz);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            Self::emit_load_word(ctx, entry, 8);
+            ctx.emit(Instruction::LocalSet(node));
+            ctx.emit(Instruction::LocalGet(level));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(level));
+            ctx.emit(Instruction::Br(1));
+            ctx.emit(Instruction::End);
 
-                    // len(list_or_struct) → read length/field_count header at ptr[0]
-                    "len" | "intrinsic_len" => {
-                        if args.len() != 1 {
-                            return Err(WasmCompileError {
-                                message: "len requires 1 argument".to_string(),
-                                context: "intrinsic_len".to_string(),
-                            });
-                        }
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::I32WrapI64); // ptr
-                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 3,
-                            memory_index: 0,
-                        }));
-                    }
+            // A key: this one or none
+            Self::emit_strings_equal(ctx, other, 1, (equal, i));
+            ctx.emit(Instruction::LocalGet(entry));
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::LocalGet(equal));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::Select);
+            ctx.emit(Instruction::LocalSet(found));
+        }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::LocalGet(found));
+        Ok(())
+    }
 
-                    // list.get(list, index) → load list[index]
-                    // ptr + 8 + 8*index
-                    "intrinsic_list_get" | "sys.list.get" | "list.get" => {
-                        if args.len() != 2 {
-                            return Err(WasmCompileError {
-                                message: "list.get requires 2 arguments (list, index)".to_string(),
-                                context: "intrinsic_list_get".to_string(),
-                            });
-                        }
-                        // Compile list ptr
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        let list_ptr = ctx.scope.get_or_alloc("__intrinsic_list_ptr");
-                        ctx.emit(Instruction::LocalSet(list_ptr));
+    /// Body of `__pmap_node_assoc(node, key, value, hash, level)`: a copy
+    /// of the trie node in `node` (0 for none), at depth `level`, with
+    /// `value` stored under `key`, whose hash is `hash`. Only the nodes on
+    /// the way to the key are copied.
+    fn emit_pmap_node_assoc(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let index = ctx.scope.get_or_alloc("__pmap_index");
+        let bitmap = ctx.scope.get_or_alloc("__pmap_bitmap");
+        let bit = ctx.scope.get_or_alloc("__pmap_bit");
+        let entry = ctx.scope.get_or_alloc("__pmap_entry");
+        let count = ctx.scope.get_or_alloc("__pmap_count");
+        let copy = ctx.scope.get_or_alloc("__pmap_copy");
+        let other = ctx.scope.get_or_alloc("__pmap_other");
+        let equal = ctx.scope.get_or_alloc("__pmap_equal");
+        let i = ctx.scope.get_or_alloc("__pmap_i");
+        let len = ctx.scope.get_or_alloc("__pmap_len");
+        let other_hash = ctx.scope.get_or_alloc("__pmap_other_hash");
+        let child = ctx.scope.get_or_alloc("__pmap_child");
+        let dst = ctx.scope.get_or_alloc("__pmap_dst");
+        let src = ctx.scope.get_or_alloc("__pmap_src");
+        let size = ctx.scope.get_or_alloc("__pmap_size");
+        let recurse = func_map["__pmap_node_assoc"];
+        // With node, key and value pushed: hash, the next level and the call
+        let descend = |ctx: &mut FuncContext, hash: u32| {
+            ctx.emit(Instruction::LocalGet(hash));
+            ctx.emit(Instruction::LocalGet(4));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::Call(recurse));
+        };
+        Self::emit_hamt_index(ctx, 1, 3, 4, index);
 
-                        // Compute address: ptr + 8 + 8*index
-                        ctx.emit(Instruction::LocalGet(list_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        // Calculate offset: 8 + 8 * index
-                        Self::compile_expr(ctx, &args[1], func_map)?;
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Mul);
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Add);
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I32Add); // base + offset
+        // No node yet: one holding just this entry
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I64Eqz);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        {
+            ctx.emit(Instruction::I64Const(24));
+            ctx.emit(Instruction::Call(func_map["__alloc"]));
+            ctx.emit(Instruction::LocalSet(copy));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::LocalGet(index));
+            ctx.emit(Instruction::I64Shl);
+            ctx.emit(Instruction::LocalSet(bit));
+            Self::emit_store_word(ctx, copy, 0, bit);
+            Self::emit_store_word(ctx, copy, 8, 1);
+            Self::emit_store_word(ctx, copy, 16, 2);
+            ctx.emit(Instruction::LocalGet(copy));
+            ctx.emit(Instruction::Return);
+        }
+        ctx.emit(Instruction::End);
 
-                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 3,
-                            memory_index: 0,
-                        }));
-                    }
+        Self::emit_hamt_entry(ctx, 0, index, (bitmap, bit, entry));
+        ctx.emit(Instruction::LocalGet(bitmap));
+        ctx.emit(Instruction::I64Popcnt);
+        ctx.emit(Instruction::LocalSet(count));
 
-                    // list.set(list, index, value) → store value at list[index]
-                    "intrinsic_list_set" | "sys.list.set" | "list.set" => {
-                        if args.len() != 3 {
-                            return Err(WasmCompileError {
-                                message: "list.set requires 3 arguments (list, index, value)"
-                                    .to_string(),
-                                context: "intrinsic_list_set".to_string(),
-                            });
-                        }
-                        let list_ptr = ctx.scope.get_or_alloc("__intrinsic_list_ptr");
-                        let set_val = ctx.scope.get_or_alloc("__intrinsic_set_val");
+        // A free bit: a node one entry larger, the new one in its place
+        ctx.emit(Instruction::LocalGet(bitmap));
+        ctx.emit(Instruction::LocalGet(bit));
+        ctx.emit(Instruction::I64And);
+        ctx.emit(Instruction::I64Eqz);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(count));
+            ctx.emit(Instruction::I64Const(4));
+            ctx.emit(Instruction::I64Shl);
+            ctx.emit(Instruction::I64Const(24));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::Call(func_map["__alloc"]));
+            ctx.emit(Instruction::LocalSet(copy));
+            ctx.emit(Instruction::LocalGet(bitmap));
+            ctx.emit(Instruction::LocalGet(bit));
+            ctx.emit(Instruction::I64Or);
+            ctx.emit(Instruction::LocalSet(bitmap));
+            Self::emit_store_word(ctx, copy, 0, bitmap);
+
+            // The entries before it...
+            ctx.emit(Instruction::LocalGet(copy));
+            ctx.emit(Instruction::I64Const(8));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(dst));
+            ctx.emit(Instruction::LocalGet(0));
+            ctx.emit(Instruction::I64Const(8));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(src));
+            ctx.emit(Instruction::LocalGet(entry));
+            ctx.emit(Instruction::LocalGet(src));
+            ctx.emit(Instruction::I64Sub);
+            ctx.emit(Instruction::LocalSet(size));
+            Self::emit_copy_bytes(ctx, dst, src, size);
+
+            // ...the new entry...
+            Self::emit_rebase(ctx, copy, entry, 0, other);
+            Self::emit_store_word(ctx, other, 0, 1);
+            Self::emit_store_word(ctx, other, 8, 2);
+
+            // ...and the entries after it
+            ctx.emit(Instruction::LocalGet(other));
+            ctx.emit(Instruction::I64Const(16));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(dst));
+            ctx.emit(Instruction::LocalGet(0));
+            ctx.emit(Instruction::LocalGet(count));
+            ctx.emit(Instruction::I64Const(4));
+            ctx.emit(Instruction::I64Shl);
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::I64Const(8));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalGet(entry));
+            ctx.emit(Instruction::I64Sub);
+            ctx.emit(Instruction::LocalSet(size));
+            Self::emit_copy_bytes(ctx, dst, entry, size);
+            ctx.emit(Instruction::LocalGet(copy));
+            ctx.emit(Instruction::Return);
+        }
+        ctx.emit(Instruction::End);
 
-                        // Compile list ptr
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::LocalSet(list_ptr));
+        // The bit is taken: copy the node, then change that entry
+        ctx.emit(Instruction::LocalGet(count));
+        ctx.emit(Instruction::I64Const(4));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalSet(size));
+        ctx.emit(Instruction::LocalGet(size));
+        ctx.emit(Instruction::Call(func_map["__alloc"]));
+        ctx.emit(Instruction::LocalSet(copy));
+        Self::emit_copy_bytes(ctx, copy, 0, size);
+        Self::emit_rebase(ctx, copy, entry, 0, entry);
+        Self::emit_load_word(ctx, entry, 0);
+        ctx.emit(Instruction::LocalSet(other));
+
+        // A child node takes the key one level down
+        ctx.emit(Instruction::LocalGet(other));
+        ctx.emit(Instruction::I64Eqz);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        {
+            Self::emit_load_word(ctx, entry, 8);
+            ctx.emit(Instruction::LocalGet(1));
+            ctx.emit(Instruction::LocalGet(2));
+            descend(ctx, 3);
+            ctx.emit(Instruction::LocalSet(child));
+            Self::emit_store_word(ctx, entry, 8, child);
+            ctx.emit(Instruction::LocalGet(copy));
+            ctx.emit(Instruction::Return);
+        }
+        ctx.emit(Instruction::End);
 
-                        // Compile value (3rd arg) first, stash it
-                        Self::compile_expr(ctx, &args[2], func_map)?;
-                        ctx.emit(Instruction::LocalSet(set_val));
+        // The same key gets the new value
+        Self::emit_strings_equal(ctx, other, 1, (equal, i));
+        ctx.emit(Instruction::LocalGet(equal));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        Self::emit_store_word(ctx, entry, 8, 2);
+        ctx.emit(Instruction::LocalGet(copy));
+        ctx.emit(Instruction::Return);
+        ctx.emit(Instruction::End);
 
-                        // Compute store address: ptr + 8 + 8*index
-                        ctx.emit(Instruction::LocalGet(list_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        Self::compile_expr(ctx, &args[1], func_map)?;
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Mul);
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Add);
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I32Add); // addr
+        // Another key: both move into a new child node
+        Self::emit_fnv1a(ctx, other, (other_hash, len, i));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalGet(other));
+        Self::emit_load_word(ctx, entry, 8);
+        descend(ctx, other_hash);
+        ctx.emit(Instruction::LocalGet(1));
+        ctx.emit(Instruction::LocalGet(2));
+        descend(ctx, 3);
+        ctx.emit(Instruction::LocalSet(child));
+        ctx.emit(Instruction::LocalGet(entry));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: 0,
+        }));
+        Self::emit_store_word(ctx, entry, 8, child);
+        ctx.emit(Instruction::LocalGet(copy));
+        Ok(())
+    }
 
-                        // Store value
-                        ctx.emit(Instruction::LocalGet(set_val));
-                        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 3,
-                            memory_index: 0,
-                        }));
+    /// Body of `__pmap_node_dissoc(node, key, hash, level)`: a copy of the
+    /// trie node in `node`, at depth `level`, without `key`, or 0 when no
+    /// entry is left. The key must be in the trie.
+    fn emit_pmap_node_dissoc(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let index = ctx.scope.get_or_alloc("__pmap_index");
+        let bitmap = ctx.scope.get_or_alloc("__pmap_bitmap");
+        let bit = ctx.scope.get_or_alloc("__pmap_bit");
+        let entry = ctx.scope.get_or_alloc("__pmap_entry");
+        let count = ctx.scope.get_or_alloc("__pmap_count");
+        let copy = ctx.scope.get_or_alloc("__pmap_copy");
+        let child = ctx.scope.get_or_alloc("__pmap_child");
+        let dst = ctx.scope.get_or_alloc("__pmap_dst");
+        let src = ctx.scope.get_or_alloc("__pmap_src");
+        let size = ctx.scope.get_or_alloc("__pmap_size");
+        Self::emit_hamt_index(ctx, 1, 2, 3, index);
+        Self::emit_hamt_entry(ctx, 0, index, (bitmap, bit, entry));
+        ctx.emit(Instruction::LocalGet(bitmap));
+        ctx.emit(Instruction::I64Popcnt);
+        ctx.emit(Instruction::LocalSet(count));
+
+        // A child node: remove the key from it, and keep it unless emptied
+        Self::emit_load_word(ctx, entry, 0);
+        ctx.emit(Instruction::I64Eqz);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        {
+            Self::emit_load_word(ctx, entry, 8);
+            ctx.emit(Instruction::LocalGet(1));
+            ctx.emit(Instruction::LocalGet(2));
+            ctx.emit(Instruction::LocalGet(3));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::Call(func_map["__pmap_node_dissoc"]));
+            ctx.emit(Instruction::LocalSet(child));
+            ctx.emit(Instruction::LocalGet(child));
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::I64Ne);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            {
+                ctx.emit(Instruction::LocalGet(count));
+                ctx.emit(Instruction::I64Const(4));
+                ctx.emit(Instruction::I64Shl);
+                ctx.emit(Instruction::I64Const(8));
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::LocalSet(size));
+                ctx.emit(Instruction::LocalGet(size));
+                ctx.emit(Instruction::Call(func_map["__alloc"]));
+                ctx.emit(Instruction::LocalSet(copy));
+                Self::emit_copy_bytes(ctx, copy, 0, size);
+                Self::emit_rebase(ctx, copy, entry, 0, entry);
+                Self::emit_store_word(ctx, entry, 8, child);
+                ctx.emit(Instruction::LocalGet(copy));
+                ctx.emit(Instruction::Return);
+            }
+            ctx.emit(Instruction::End);
+        }
+        ctx.emit(Instruction::End);
 
-                        // Return the list ptr (for chaining)
-                        ctx.emit(Instruction::LocalGet(list_ptr));
-                    }
+        // Drop the entry; a node left with none goes as well
+        ctx.emit(Instruction::LocalGet(count));
+        ctx.emit(Instruction::I64Const(1));
+        ctx.emit(Instruction::I64Eq);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::Return);
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::LocalGet(count));
+        ctx.emit(Instruction::I64Const(4));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::I64Sub);
+        ctx.emit(Instruction::Call(func_map["__alloc"]));
+        ctx.emit(Instruction::LocalSet(copy));
+        ctx.emit(Instruction::LocalGet(bitmap));
+        ctx.emit(Instruction::LocalGet(bit));
+        ctx.emit(Instruction::I64Xor);
+        ctx.emit(Instruction::LocalSet(bitmap));
+        Self::emit_store_word(ctx, copy, 0, bitmap);
+
+        // The entries before it...
+        ctx.emit(Instruction::LocalGet(copy));
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalSet(dst));
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalSet(src));
+        ctx.emit(Instruction::LocalGet(entry));
+        ctx.emit(Instruction::LocalGet(src));
+        ctx.emit(Instruction::I64Sub);
+        ctx.emit(Instruction::LocalSet(size));
+        Self::emit_copy_bytes(ctx, dst, src, size);
+
+        // ...and the ones after it, closing the gap
+        Self::emit_rebase(ctx, copy, entry, 0, dst);
+        ctx.emit(Instruction::LocalGet(entry));
+        ctx.emit(Instruction::I64Const(16));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalSet(src));
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::LocalGet(count));
+        ctx.emit(Instruction::I64Const(4));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalGet(src));
+        ctx.emit(Instruction::I64Sub);
+        ctx.emit(Instruction::LocalSet(size));
+        Self::emit_copy_bytes(ctx, dst, src, size);
+        ctx.emit(Instruction::LocalGet(copy));
+        Ok(())
+    }
 
-                    // list.append(list, value) → allocate new list with len+1,
-                    // copy old data, store new value at end, return new ptr
-                    "intrinsic_list_append" | "sys.list.append" | "list.append" => {
-                        if args.len() != 2 {
-                            return Err(WasmCompileError {
-                                message: "list.append requires 2 arguments (list, value)"
-                                    .to_string(),
-                                context: "intrinsic_list_append".to_string(),
-                            });
-                        }
-                        let old_ptr = ctx.scope.get_or_alloc("__append_old_ptr");
-                        let old_len = ctx.scope.get_or_alloc("__append_old_len");
-                        let new_ptr = ctx.scope.get_or_alloc("__append_new_ptr");
-                        let append_val = ctx.scope.get_or_alloc("__append_val");
-                        let copy_i = ctx.scope.get_or_alloc("__append_i");
+    /// Loop over the entries of the map trie node in `node`, running
+    /// `visit` for each with its address in `entry`.
+    fn emit_hamt_each(
+        ctx: &mut FuncContext,
+        node: u32,
+        (entry, end): (u32, u32),
+        visit: impl Fn(&mut FuncContext),
+    ) {
+        ctx.emit(Instruction::LocalGet(node));
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalSet(entry));
+        ctx.emit(Instruction::LocalGet(entry));
+        Self::emit_load_word(ctx, node, 0);
+        ctx.emit(Instruction::I64Popcnt);
+        ctx.emit(Instruction::I64Const(4));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalSet(end));
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(entry));
+            ctx.emit(Instruction::LocalGet(end));
+            ctx.emit(Instruction::I64GeU);
+            ctx.emit(Instruction::BrIf(1));
+            visit(ctx);
+            ctx.emit(Instruction::LocalGet(entry));
+            ctx.emit(Instruction::I64Const(16));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(entry));
+            ctx.emit(Instruction::Br(0));
+        }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
+    }
 
-                        // Compile old list ptr
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::LocalSet(old_ptr));
+    /// Body of `__pmap_collect(node, list, n)`: write the keys in the trie
+    /// under `node` to `list` from element `n` on, returning the element
+    /// after the last one written.
+    fn emit_pmap_collect(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let entry = ctx.scope.get_or_alloc("__pmap_entry");
+        let end = ctx.scope.get_or_alloc("__pmap_end");
+        let key = ctx.scope.get_or_alloc("__pmap_key");
+        let recurse = func_map["__pmap_collect"];
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I64Eqz);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::LocalGet(2));
+        ctx.emit(Instruction::Return);
+        ctx.emit(Instruction::End);
 
-                        // Compile append value
-                        Self::compile_expr(ctx, &args[1], func_map)?;
-                        ctx.emit(Instruction::LocalSet(append_val));
+        Self::emit_hamt_each(ctx, 0, (entry, end), |ctx| {
+            Self::emit_load_word(ctx, entry, 0);
+            ctx.emit(Instruction::LocalSet(key));
+            ctx.emit(Instruction::LocalGet(key));
+            ctx.emit(Instruction::I64Eqz);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            Self::emit_load_word(ctx, entry, 8);
+            ctx.emit(Instruction::LocalGet(1));
+            ctx.emit(Instruction::LocalGet(2));
+            ctx.emit(Instruction::Call(recurse));
+            ctx.emit(Instruction::LocalSet(2));
+            ctx.emit(Instruction::Else);
+            // list[n] = key
+            ctx.emit(Instruction::LocalGet(1));
+            ctx.emit(Instruction::LocalGet(2));
+            ctx.emit(Instruction::I64Const(3));
+            ctx.emit(Instruction::I64Shl);
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::LocalGet(key));
+            ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                offset: 8,
+                align: 3,
+                memory_index: 0,
+            }));
+            ctx.emit(Instruction::LocalGet(2));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(2));
+            ctx.emit(Instruction::End);
+        });
+        ctx.emit(Instruction::LocalGet(2));
+        Ok(())
+    }
 
-                        // Read old length
-                        ctx.emit(Instruction::LocalGet(old_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 3,
-                            memory_index: 0,
-                        }));
-                        ctx.emit(Instruction::LocalSet(old_len));
+    /// Body of `__pmap_put(map, key, value)`: a new map with `value` stored
+    /// under `key`. Callers check the map.
+    fn emit_pmap_put(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let root = ctx.scope.get_or_alloc("__pmap_root");
+        let found = ctx.scope.get_or_alloc("__pmap_found");
+        let hash = ctx.scope.get_or_alloc("__pmap_hash");
+        let len = ctx.scope.get_or_alloc("__pmap_len");
+        let i = ctx.scope.get_or_alloc("__pmap_i");
+        let map = ctx.scope.get_or_alloc("__pmap_map");
+        Self::emit_load_word(ctx, 0, 8);
+        ctx.emit(Instruction::LocalSet(root));
+        ctx.emit(Instruction::LocalGet(root));
+        ctx.emit(Instruction::LocalGet(1));
+        ctx.emit(Instruction::Call(func_map["__pmap_find"]));
+        ctx.emit(Instruction::LocalSet(found));
+        Self::emit_fnv1a(ctx, 1, (hash, len, i));
+        ctx.emit(Instruction::LocalGet(root));
+        ctx.emit(Instruction::LocalGet(1));
+        ctx.emit(Instruction::LocalGet(2));
+        ctx.emit(Instruction::LocalGet(hash));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::Call(func_map["__pmap_node_assoc"]));
+        ctx.emit(Instruction::LocalSet(root));
+
+        ctx.emit(Instruction::I64Const(16));
+        Self::emit_alloc_object(ctx, HEAP_TAG_PMAP, func_map, "__pmap_put")?;
+        ctx.emit(Instruction::LocalSet(map));
+        // A new key bumps the entry count
+        ctx.emit(Instruction::LocalGet(map));
+        ctx.emit(Instruction::I32WrapI64);
+        Self::emit_load_word(ctx, 0, 0);
+        ctx.emit(Instruction::LocalGet(found));
+        ctx.emit(Instruction::I64Eqz);
+        ctx.emit(Instruction::I64ExtendI32U);
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: 0,
+        }));
+        Self::emit_store_word(ctx, map, 8, root);
+        ctx.emit(Instruction::LocalGet(map));
+        Ok(())
+    }
 
-                        // Allocate new list: 8 + 8*(old_len + 1)
-                        ctx.emit(Instruction::LocalGet(old_len));
-                        ctx.emit(Instruction::I64Const(1));
-                        ctx.emit(Instruction::I64Add);
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Mul);
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Add); // total size
-                        if let Some(&alloc_idx) = func_map.get("__alloc") {
-                            ctx.emit(Instruction::Call(alloc_idx));
-                        } else {
-                            return Err(WasmCompileError {
-                                message: "__alloc not found".to_string(),
-                                context: "list.append".to_string(),
-                            });
-                        }
-                        ctx.emit(Instruction::LocalSet(new_ptr));
+    /// Body of `__pmap_node_merge(map, node)`: `map` with every entry of the
+    /// trie under `node` put into it.
+    fn emit_pmap_node_merge(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let entry = ctx.scope.get_or_alloc("__pmap_entry");
+        let end = ctx.scope.get_or_alloc("__pmap_end");
+        let key = ctx.scope.get_or_alloc("__pmap_key");
+        let recurse = func_map["__pmap_node_merge"];
+        let put = func_map["__pmap_put"];
+        ctx.emit(Instruction::LocalGet(1));
+        ctx.emit(Instruction::I64Eqz);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::Return);
+        ctx.emit(Instruction::End);
 
-                        // Store new length = old_len + 1
-                        ctx.emit(Instruction::LocalGet(new_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::LocalGet(old_len));
-                        ctx.emit(Instruction::I64Const(1));
-                        ctx.emit(Instruction::I64Add);
-                        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 3,
-                            memory_index: 0,
-                        }));
+        Self::emit_hamt_each(ctx, 1, (entry, end), |ctx| {
+            Self::emit_load_word(ctx, entry, 0);
+            ctx.emit(Instruction::LocalSet(key));
+            ctx.emit(Instruction::LocalGet(key));
+            ctx.emit(Instruction::I64Eqz);
+            ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+            ctx.emit(Instruction::LocalGet(0));
+            Self::emit_load_word(ctx, entry, 8);
+            ctx.emit(Instruction::Call(recurse));
+            ctx.emit(Instruction::Else);
+            ctx.emit(Instruction::LocalGet(0));
+            ctx.emit(Instruction::LocalGet(key));
+            Self::emit_load_word(ctx, entry, 8);
+            ctx.emit(Instruction::Call(put));
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::LocalSet(0));
+        });
+        ctx.emit(Instruction::LocalGet(0));
+        Ok(())
+    }
 
-                        // Copy old elements: for i in 0..old_len
-                        ctx.emit(Instruction::I64Const(0));
-                        ctx.emit(Instruction::LocalSet(copy_i));
+    /// Body of `__pmap_new()`: an empty persistent map.
+    fn emit_pmap_new(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        ctx.emit(Instruction::I64Const(16));
+        Self::emit_alloc_object(ctx, HEAP_TAG_PMAP, func_map, "__pmap_new")
+    }
 
-                        // block { loop {
-                        ctx.emit(Instruction::Block(BlockType::Empty));
-                        ctx.emit(Instruction::Loop(BlockType::Empty));
+    /// Body of `__pmap_assoc(map, key, value)`.
+    fn emit_pmap_assoc(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_heap_object_guard(ctx, 0, HEAP_TAG_PMAP, 0);
+        for param in 0..3 {
+            ctx.emit(Instruction::LocalGet(param));
+        }
+        ctx.emit(Instruction::Call(func_map["__pmap_put"]));
+        Ok(())
+    }
 
-                        // if i >= old_len, break
-                        ctx.emit(Instruction::LocalGet(copy_i));
-                        ctx.emit(Instruction::LocalGet(old_len));
-                        ctx.emit(Instruction::I64GeS);
-                        ctx.emit(Instruction::BrIf(1));
+    /// Body of `__pmap_dissoc(map, key)`: a new map without `key`, or the
+    /// same map when it has no such key.
+    fn emit_pmap_dissoc(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let root = ctx.scope.get_or_alloc("__pmap_root");
+        let hash = ctx.scope.get_or_alloc("__pmap_hash");
+        let len = ctx.scope.get_or_alloc("__pmap_len");
+        let i = ctx.scope.get_or_alloc("__pmap_i");
+        let map = ctx.scope.get_or_alloc("__pmap_map");
+        Self::emit_heap_object_guard(ctx, 0, HEAP_TAG_PMAP, 0);
+        Self::emit_load_word(ctx, 0, 8);
+        ctx.emit(Instruction::LocalSet(root));
+        ctx.emit(Instruction::LocalGet(root));
+        ctx.emit(Instruction::LocalGet(1));
+        ctx.emit(Instruction::Call(func_map["__pmap_find"]));
+        ctx.emit(Instruction::I64Eqz);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::Return);
+        ctx.emit(Instruction::End);
 
-                        // new_ptr[8 + 8*i] = old_ptr[8 + 8*i]
-                        // dst address
-                        ctx.emit(Instruction::LocalGet(new_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::LocalGet(copy_i));
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Mul);
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Add);
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I32Add); // dst addr
+        Self::emit_fnv1a(ctx, 1, (hash, len, i));
+        ctx.emit(Instruction::LocalGet(root));
+        ctx.emit(Instruction::LocalGet(1));
+        ctx.emit(Instruction::LocalGet(hash));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::Call(func_map["__pmap_node_dissoc"]));
+        ctx.emit(Instruction::LocalSet(root));
+        ctx.emit(Instruction::I64Const(16));
+        Self::emit_alloc_object(ctx, HEAP_TAG_PMAP, func_map, "__pmap_dissoc")?;
+        ctx.emit(Instruction::LocalSet(map));
+        ctx.emit(Instruction::LocalGet(map));
+        ctx.emit(Instruction::I32WrapI64);
+        Self::emit_load_word(ctx, 0, 0);
+        ctx.emit(Instruction::I64Const(1));
+        ctx.emit(Instruction::I64Sub);
+        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: 0,
+        }));
+        Self::emit_store_word(ctx, map, 8, root);
+        ctx.emit(Instruction::LocalGet(map));
+        Ok(())
+    }
 
-                        // src value: old_ptr[8 + 8*i]
-                        ctx.emit(Instruction::LocalGet(old_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::LocalGet(copy_i));
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Mul);
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Add);
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I32Add); // src addr
-                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 3,
-                            memory_index: 0,
-                        }));
+    /// Body of `__pmap_get(map, key)`: the value stored under `key`, or 0
+    /// (unit) when there is none.
+    fn emit_pmap_get(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let entry = ctx.scope.get_or_alloc("__pmap_entry");
+        Self::emit_heap_object_guard(ctx, 0, HEAP_TAG_PMAP, 0);
+        Self::emit_load_word(ctx, 0, 8);
+        ctx.emit(Instruction::LocalGet(1));
+        ctx.emit(Instruction::Call(func_map["__pmap_find"]));
+        ctx.emit(Instruction::LocalSet(entry));
+        ctx.emit(Instruction::LocalGet(entry));
+        ctx.emit(Instruction::I64Eqz);
+        ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::Else);
+        Self::emit_load_word(ctx, entry, 8);
+        ctx.emit(Instruction::End);
+        Ok(())
+    }
 
-                        // store
-                        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 3,
-                            memory_index: 0,
-                        }));
+    /// Body of `__pmap_keys(map)`: a new list of the map's keys, sorted by
+    /// byte order like the interpreter's.
+    fn emit_pmap_keys(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let count = ctx.scope.get_or_alloc("__pmap_count");
+        let list = ctx.scope.get_or_alloc("__pmap_list");
+        Self::emit_heap_object_guard(ctx, 0, HEAP_TAG_PMAP, 0);
+        Self::emit_load_word(ctx, 0, 0);
+        ctx.emit(Instruction::LocalSet(count));
+        ctx.emit(Instruction::LocalGet(count));
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::I64Mul);
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::I64Add);
+        Self::emit_alloc_object(ctx, HEAP_TAG_LIST, func_map, "__pmap_keys")?;
+        ctx.emit(Instruction::LocalSet(list));
+        Self::emit_store_word(ctx, list, 0, count);
+
+        Self::emit_load_word(ctx, 0, 8);
+        ctx.emit(Instruction::LocalGet(list));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::Call(func_map["__pmap_collect"]));
+        ctx.emit(Instruction::Drop);
+        Self::emit_sort_strings(ctx, list, count);
+        ctx.emit(Instruction::LocalGet(list));
+        Ok(())
+    }
 
-                        // i += 1
-                        ctx.emit(Instruction::LocalGet(copy_i));
-                        ctx.emit(Instruction::I64Const(1));
-                        ctx.emit(Instruction::I64Add);
-                        ctx.emit(Instruction::LocalSet(copy_i));
+    /// Body of `__pmap_merge(map, other)`: `map` with the entries of `other`
+    /// put into it, so `other` wins where both have a key.
+    fn emit_pmap_merge(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_heap_object_guard(ctx, 0, HEAP_TAG_PMAP, 0);
+        Self::emit_heap_object_guard(ctx, 1, HEAP_TAG_PMAP, 0);
+        ctx.emit(Instruction::LocalGet(0));
+        Self::emit_load_word(ctx, 1, 8);
+        ctx.emit(Instruction::Call(func_map["__pmap_node_merge"]));
+        Ok(())
+    }
 
-                        ctx.emit(Instruction::Br(0)); // continue loop
-                        ctx.emit(Instruction::End); // end loop
-                        ctx.emit(Instruction::End); // end block
+    /// Add `delta` to the i64 in `local`.
+    fn emit_local_add(ctx: &mut FuncContext, local: u32, delta: i64) {
+        ctx.emit(Instruction::LocalGet(local));
+        ctx.emit(Instruction::I64Const(delta));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalSet(local));
+    }
 
-                        // Store appended value at new_ptr[8 + 8*old_len]
-                        ctx.emit(Instruction::LocalGet(new_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::LocalGet(old_len));
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Mul);
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Add);
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I32Add);
-                        ctx.emit(Instruction::LocalGet(append_val));
-                        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 3,
-                            memory_index: 0,
-                        }));
+    /// Push the address of limb `index` of the big integer in `big`, less
+    /// the 16 bytes of header and sign that the limb loads and stores add
+    /// back as their offset.
+    fn emit_limb_base(ctx: &mut FuncContext, big: u32, index: u32) {
+        ctx.emit(Instruction::LocalGet(big));
+        ctx.emit(Instruction::LocalGet(index));
+        ctx.emit(Instruction::I64Const(2));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::I32WrapI64);
+    }
 
-                        // Return new list ptr
-                        ctx.emit(Instruction::LocalGet(new_ptr));
-                    }
+    /// Push limb `index` of the big integer in `big`.
+    fn emit_load_limb(ctx: &mut FuncContext, big: u32, index: u32) {
+        Self::emit_limb_base(ctx, big, index);
+        ctx.emit(Instruction::I64Load32U(wasm_encoder::MemArg {
+            offset: 16,
+            align: 2,
+            memory_index: 0,
+        }));
+    }
 
-                    // list.pop(list[, index]) → decrement length, return removed element
-                    // Without index: removes last element
-                    "intrinsic_list_pop" | "sys.list.pop" | "list.pop" => {
-                        if args.is_empty() {
-                            return Err(WasmCompileError {
-                                message: "list.pop requires at least 1 argument (list)".to_string(),
-                                context: "intrinsic_list_pop".to_string(),
-                            });
-                        }
-                        let list_ptr = ctx.scope.get_or_alloc("__pop_list_ptr");
-                        let old_len = ctx.scope.get_or_alloc("__pop_old_len");
+    /// Store the low 32 bits of `value` as limb `index` of `big`.
+    fn emit_store_limb(ctx: &mut FuncContext, big: u32, index: u32, value: u32) {
+        Self::emit_limb_base(ctx, big, index);
+        ctx.emit(Instruction::LocalGet(value));
+        ctx.emit(Instruction::I64Store32(wasm_encoder::MemArg {
+            offset: 16,
+            align: 2,
+            memory_index: 0,
+        }));
+    }
 
-                        // Compile list ptr
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::LocalSet(list_ptr));
+    /// Push limb `index` of `big`, or 0 from `count` limbs on.
+    fn emit_limb_or_zero(ctx: &mut FuncContext, big: u32, count: u32, index: u32) {
+        ctx.emit(Instruction::LocalGet(index));
+        ctx.emit(Instruction::LocalGet(count));
+        ctx.emit(Instruction::I64LtU);
+        ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+        Self::emit_load_limb(ctx, big, index);
+        ctx.emit(Instruction::Else);
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::End);
+    }
 
-                        // Read old length
-                        ctx.emit(Instruction::LocalGet(list_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 3,
-                            memory_index: 0,
-                        }));
-                        ctx.emit(Instruction::LocalSet(old_len));
+    /// Set `value` to `value - borrow` taken modulo 2^32, and `borrow` to 1
+    /// when that wrapped, else 0: the step of a limb-by-limb subtraction.
+    fn emit_borrow(ctx: &mut FuncContext, value: u32, borrow: u32) {
+        ctx.emit(Instruction::LocalGet(value));
+        ctx.emit(Instruction::LocalGet(borrow));
+        ctx.emit(Instruction::I64Sub);
+        ctx.emit(Instruction::LocalSet(value));
+        ctx.emit(Instruction::LocalGet(value));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::I64LtS);
+        ctx.emit(Instruction::I64ExtendI32U);
+        ctx.emit(Instruction::LocalSet(borrow));
+        ctx.emit(Instruction::LocalGet(value));
+        ctx.emit(Instruction::LocalGet(borrow));
+        ctx.emit(Instruction::I64Const(32));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalSet(value));
+    }
 
-                        // Read last element: ptr[8 + 8*(len-1)]
-                        ctx.emit(Instruction::LocalGet(list_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::LocalGet(old_len));
-                        ctx.emit(Instruction::I64Const(1));
-                        ctx.emit(Instruction::I64Sub);
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Mul);
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Add);
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I32Add);
-                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 3,
-                            memory_index: 0,
-                        }));
+    /// Open a loop running while `index` counts up from 0 to below `end`;
+    /// close it with [`Self::emit_count_up_end`].
+    fn emit_count_up(ctx: &mut FuncContext, index: u32, end: u32) {
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(index));
+        Self::emit_count_from(ctx, index, end);
+    }
 
-                        // Decrement length in-place
-                        let popped = ctx.scope.get_or_alloc("__pop_result");
-                        ctx.emit(Instruction::LocalSet(popped));
+    /// Open a loop like [`Self::emit_count_up`]'s that starts from the
+    /// current value of `index`.
+    fn emit_count_from(ctx: &mut FuncContext, index: u32, end: u32) {
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        ctx.emit(Instruction::LocalGet(index));
+        ctx.emit(Instruction::LocalGet(end));
+        ctx.emit(Instruction::I64GeU);
+        ctx.emit(Instruction::BrIf(1));
+    }
 
-                        ctx.emit(Instruction::LocalGet(list_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::LocalGet(old_len));
-                        ctx.emit(Instruction::I64Const(1));
-                        ctx.emit(Instruction::I64Sub);
-                        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 3,
-                            memory_index: 0,
-                        }));
+    /// Close a loop opened by [`Self::emit_count_up`].
+    fn emit_count_up_end(ctx: &mut FuncContext, index: u32) {
+        Self::emit_count_step_end(ctx, index, 1);
+    }
 
-                        // Return popped value
-                        ctx.emit(Instruction::LocalGet(popped));
-                    }
+    /// Close a counting loop, advancing `index` by `step` each time round.
+    fn emit_count_step_end(ctx: &mut FuncContext, index: u32, step: i64) {
+        Self::emit_local_add(ctx, index, step);
+        ctx.emit(Instruction::Br(0));
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
+    }
 
-                    // list.delete(list, index) → shift elements left, decrement length
-                    "intrinsic_list_delete" | "sys.list.delete" | "list.delete" => {
-                        if args.len() != 2 {
-                            return Err(WasmCompileError {
-                                message: "list.delete requires 2 arguments (list, index)"
-                                    .to_string(),
-                                context: "intrinsic_list_delete".to_string(),
-                            });
-                        }
-                        let list_ptr = ctx.scope.get_or_alloc("__del_list_ptr");
-                        let del_idx = ctx.scope.get_or_alloc("__del_idx");
-                        let del_len = ctx.scope.get_or_alloc("__del_len");
-                        let del_i = ctx.scope.get_or_alloc("__del_i");
+    /// Body of `__big_alloc(n)`: a zero-filled, non-negative big integer
+    /// of `n` limbs.
+    fn emit_big_alloc(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let big = ctx.scope.get_or_alloc("__big_ptr");
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I64Const(2));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::I64Const(16));
+        ctx.emit(Instruction::I64Add);
+        Self::emit_alloc_object(ctx, HEAP_TAG_BIGINT, func_map, "__big_alloc")?;
+        ctx.emit(Instruction::LocalSet(big));
+        Self::emit_store_word(ctx, big, 0, 0);
+        ctx.emit(Instruction::LocalGet(big));
+        Ok(())
+    }
 
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::LocalSet(list_ptr));
-                        Self::compile_expr(ctx, &args[1], func_map)?;
-                        ctx.emit(Instruction::LocalSet(del_idx));
+    /// Body of `__big_trim(big)`: drop the zero limbs at the top of `big`
+    /// in place, and its sign when nothing is left. Returns `big`.
+    fn emit_big_trim(
+        ctx: &mut FuncContext,
+        _func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let n = ctx.scope.get_or_alloc("__big_n");
+        let top = ctx.scope.get_or_alloc("__big_top");
+        Self::emit_load_word(ctx, 0, 0);
+        ctx.emit(Instruction::LocalSet(n));
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(n));
+            ctx.emit(Instruction::I64Eqz);
+            ctx.emit(Instruction::BrIf(1));
+            ctx.emit(Instruction::LocalGet(n));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Sub);
+            ctx.emit(Instruction::LocalSet(top));
+            Self::emit_load_limb(ctx, 0, top);
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::I64Ne);
+            ctx.emit(Instruction::BrIf(1));
+            ctx.emit(Instruction::LocalGet(top));
+            ctx.emit(Instruction::LocalSet(n));
+            ctx.emit(Instruction::Br(0));
+        }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
+        Self::emit_store_word(ctx, 0, 0, n);
 
-                        // Read length
-                        ctx.emit(Instruction::LocalGet(list_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 3,
-                            memory_index: 0,
-                        }));
-                        ctx.emit(Instruction::LocalSet(del_len));
+        // Zero is never negative
+        ctx.emit(Instruction::LocalGet(n));
+        ctx.emit(Instruction::I64Eqz);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        Self::emit_store_word(ctx, 0, 8, n);
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::LocalGet(0));
+        Ok(())
+    }
 
-                        // Shift elements left: for i = index..len-1: list[i] = list[i+1]
-                        ctx.emit(Instruction::LocalGet(del_idx));
-                        ctx.emit(Instruction::LocalSet(del_i));
+    /// Body of `__big_of(value)`: `value` when it is a big integer, else a
+    /// new one holding it as an integer, as the interpreter accepts either.
+    fn emit_big_of(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let big = ctx.scope.get_or_alloc("__big_ptr");
+        let magnitude = ctx.scope.get_or_alloc("__big_magnitude");
+        let sign = ctx.scope.get_or_alloc("__big_sign");
+
+        // 8 <= value < __heap_ptr, tagged as a big integer
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::I64GeU);
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::GlobalGet(0));
+        ctx.emit(Instruction::I64ExtendI32U);
+        ctx.emit(Instruction::I64LtU);
+        ctx.emit(Instruction::I32And);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(0));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I32Const(8));
+            ctx.emit(Instruction::I32Sub);
+            ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                offset: 0,
+                align: 3,
+                memory_index: 0,
+            }));
+            ctx.emit(Instruction::I64Const(HEAP_TAG_BIGINT));
+            ctx.emit(Instruction::I64Eq);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            ctx.emit(Instruction::LocalGet(0));
+            ctx.emit(Instruction::Return);
+            ctx.emit(Instruction::End);
+        }
+        ctx.emit(Instruction::End);
 
-                        ctx.emit(Instruction::Block(BlockType::Empty));
-                        ctx.emit(Instruction::Loop(BlockType::Empty));
+        // |value| as a u64, so i64::MIN becomes 2^63
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I64Const(63));
+        ctx.emit(Instruction::I64ShrU);
+        ctx.emit(Instruction::LocalSet(sign));
+        ctx.emit(Instruction::LocalGet(sign));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I64Sub);
+        ctx.emit(Instruction::Else);
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::LocalSet(magnitude));
+
+        ctx.emit(Instruction::I64Const(2));
+        ctx.emit(Instruction::Call(func_map["__big_alloc"]));
+        ctx.emit(Instruction::LocalSet(big));
+        Self::emit_store_word(ctx, big, 8, sign);
+        for (offset, shift) in [(16, 0), (20, 32)] {
+            ctx.emit(Instruction::LocalGet(big));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::LocalGet(magnitude));
+            ctx.emit(Instruction::I64Const(shift));
+            ctx.emit(Instruction::I64ShrU);
+            ctx.emit(Instruction::I64Store32(wasm_encoder::MemArg {
+                offset,
+                align: 2,
+                memory_index: 0,
+            }));
+        }
+        ctx.emit(Instruction::LocalGet(big));
+        ctx.emit(Instruction::Call(func_map["__big_trim"]));
+        Ok(())
+    }
 
-                        // if i >= len - 1, break
-                        ctx.emit(Instruction::LocalGet(del_i));
-                        ctx.emit(Instruction::LocalGet(del_len));
-                        ctx.emit(Instruction::I64Const(1));
-                        ctx.emit(Instruction::I64Sub);
-                        ctx.emit(Instruction::I64GeS);
-                        ctx.emit(Instruction::BrIf(1));
+    /// Body of `__big_mag_cmp(a, b)`: -1, 0 or 1 as the magnitude of `a` is
+    /// less than, equal to or greater than that of `b`.
+    fn emit_big_mag_cmp(
+        ctx: &mut FuncContext,
+        _func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let na = ctx.scope.get_or_alloc("__big_na");
+        let nb = ctx.scope.get_or_alloc("__big_nb");
+        let i = ctx.scope.get_or_alloc("__big_i");
+        let x = ctx.scope.get_or_alloc("__big_x");
+        let y = ctx.scope.get_or_alloc("__big_y");
+        // Return -1 or 1 as `first` is below or above `second`, if they differ
+        let decide = |ctx: &mut FuncContext, first: u32, second: u32| {
+            ctx.emit(Instruction::LocalGet(first));
+            ctx.emit(Instruction::LocalGet(second));
+            ctx.emit(Instruction::I64Ne);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            ctx.emit(Instruction::I64Const(-1));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::LocalGet(first));
+            ctx.emit(Instruction::LocalGet(second));
+            ctx.emit(Instruction::I64LtU);
+            ctx.emit(Instruction::Select);
+            ctx.emit(Instruction::Return);
+            ctx.emit(Instruction::End);
+        };
+        Self::emit_load_word(ctx, 0, 0);
+        ctx.emit(Instruction::LocalSet(na));
+        Self::emit_load_word(ctx, 1, 0);
+        ctx.emit(Instruction::LocalSet(nb));
+        decide(ctx, na, nb);
+
+        // Same length: the highest limb that differs decides
+        ctx.emit(Instruction::LocalGet(na));
+        ctx.emit(Instruction::LocalSet(i));
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(i));
+            ctx.emit(Instruction::I64Eqz);
+            ctx.emit(Instruction::BrIf(1));
+            Self::emit_local_add(ctx, i, -1);
+            Self::emit_load_limb(ctx, 0, i);
+            ctx.emit(Instruction::LocalSet(x));
+            Self::emit_load_limb(ctx, 1, i);
+            ctx.emit(Instruction::LocalSet(y));
+            decide(ctx, x, y);
+            ctx.emit(Instruction::Br(0));
+        }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::I64Const(0));
+        Ok(())
+    }
 
-                        // list[i] = list[i+1]
-                        // dst: ptr + 8 + 8*i
-                        ctx.emit(Instruction::LocalGet(list_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::LocalGet(del_i));
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Mul);
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Add);
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I32Add);
+    /// Body of `__big_mag_add(a, b)`: a new big integer holding the sum of
+    /// the magnitudes of `a` and `b`.
+    fn emit_big_mag_add(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let na = ctx.scope.get_or_alloc("__big_na");
+        let nb = ctx.scope.get_or_alloc("__big_nb");
+        let n = ctx.scope.get_or_alloc("__big_n");
+        let sum = ctx.scope.get_or_alloc("__big_result");
+        let i = ctx.scope.get_or_alloc("__big_i");
+        let carry = ctx.scope.get_or_alloc("__big_carry");
+        let t = ctx.scope.get_or_alloc("__big_t");
+        Self::emit_load_word(ctx, 0, 0);
+        ctx.emit(Instruction::LocalSet(na));
+        Self::emit_load_word(ctx, 1, 0);
+        ctx.emit(Instruction::LocalSet(nb));
+        ctx.emit(Instruction::LocalGet(na));
+        ctx.emit(Instruction::LocalGet(nb));
+        ctx.emit(Instruction::LocalGet(na));
+        ctx.emit(Instruction::LocalGet(nb));
+        ctx.emit(Instruction::I64GtU);
+        ctx.emit(Instruction::Select);
+        ctx.emit(Instruction::LocalSet(n));
+        ctx.emit(Instruction::LocalGet(n));
+        ctx.emit(Instruction::I64Const(1));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::Call(func_map["__big_alloc"]));
+        ctx.emit(Instruction::LocalSet(sum));
 
-                        // src: ptr + 8 + 8*(i+1)
-                        ctx.emit(Instruction::LocalGet(list_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::LocalGet(del_i));
-                        ctx.emit(Instruction::I64Const(1));
-                        ctx.emit(Instruction::I64Add);
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Mul);
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Add);
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I32Add);
-                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 3,
-                            memory_index: 0,
-                        }));
-                        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 3,
-                            memory_index: 0,
-                        }));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(carry));
+        Self::emit_count_up(ctx, i, n);
+        {
+            Self::emit_limb_or_zero(ctx, 0, na, i);
+            Self::emit_limb_or_zero(ctx, 1, nb, i);
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalGet(carry));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(t));
+            Self::emit_store_limb(ctx, sum, i, t);
+            ctx.emit(Instruction::LocalGet(t));
+            ctx.emit(Instruction::I64Const(32));
+            ctx.emit(Instruction::I64ShrU);
+            ctx.emit(Instruction::LocalSet(carry));
+        }
+        Self::emit_count_up_end(ctx, i);
+        Self::emit_store_limb(ctx, sum, n, carry);
+        ctx.emit(Instruction::LocalGet(sum));
+        ctx.emit(Instruction::Call(func_map["__big_trim"]));
+        Ok(())
+    }
 
-                        // i += 1
-                        ctx.emit(Instruction::LocalGet(del_i));
-                        ctx.emit(Instruction::I64Const(1));
-                        ctx.emit(Instruction::I64Add);
-                        ctx.emit(Instruction::LocalSet(del_i));
+    /// Body of `__big_mag_sub(a, b)`: a new big integer holding the
+    /// magnitude of `a` less that of `b`, which must not be larger.
+    fn emit_big_mag_sub(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let na = ctx.scope.get_or_alloc("__big_na");
+        let nb = ctx.scope.get_or_alloc("__big_nb");
+        let diff = ctx.scope.get_or_alloc("__big_result");
+        let i = ctx.scope.get_or_alloc("__big_i");
+        let borrow = ctx.scope.get_or_alloc("__big_borrow");
+        let t = ctx.scope.get_or_alloc("__big_t");
+        Self::emit_load_word(ctx, 0, 0);
+        ctx.emit(Instruction::LocalSet(na));
+        Self::emit_load_word(ctx, 1, 0);
+        ctx.emit(Instruction::LocalSet(nb));
+        ctx.emit(Instruction::LocalGet(na));
+        ctx.emit(Instruction::Call(func_map["__big_alloc"]));
+        ctx.emit(Instruction::LocalSet(diff));
 
-                        ctx.emit(Instruction::Br(0));
-                        ctx.emit(Instruction::End); // loop
-                        ctx.emit(Instruction::End); // block
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(borrow));
+        Self::emit_count_up(ctx, i, na);
+        {
+            Self::emit_load_limb(ctx, 0, i);
+            Self::emit_limb_or_zero(ctx, 1, nb, i);
+            ctx.emit(Instruction::I64Sub);
+            ctx.emit(Instruction::LocalSet(t));
+            Self::emit_borrow(ctx, t, borrow);
+            Self::emit_store_limb(ctx, diff, i, t);
+        }
+        Self::emit_count_up_end(ctx, i);
+        ctx.emit(Instruction::LocalGet(diff));
+        ctx.emit(Instruction::Call(func_map["__big_trim"]));
+        Ok(())
+    }
 
-                        // Decrement length
-                        ctx.emit(Instruction::LocalGet(list_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::LocalGet(del_len));
-                        ctx.emit(Instruction::I64Const(1));
-                        ctx.emit(Instruction::I64Sub);
-                        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 3,
-                            memory_index: 0,
-                        }));
+    /// Body of `__big_mag_rem(a, m)`: a new big integer holding the
+    /// magnitude of `a` modulo that of a nonzero `m`. The bits of `a` are
+    /// shifted into the remainder from the top, taking `m` off whenever
+    /// the remainder reaches it, as `bigint.rs` does.
+    fn emit_big_mag_rem(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let na = ctx.scope.get_or_alloc("__big_na");
+        let nm = ctx.scope.get_or_alloc("__big_nm");
+        let len = ctx.scope.get_or_alloc("__big_len");
+        let rem = ctx.scope.get_or_alloc("__big_result");
+        let bit = ctx.scope.get_or_alloc("__big_bit");
+        let i = ctx.scope.get_or_alloc("__big_i");
+        let carry = ctx.scope.get_or_alloc("__big_carry");
+        let x = ctx.scope.get_or_alloc("__big_x");
+        let y = ctx.scope.get_or_alloc("__big_y");
+        let less = ctx.scope.get_or_alloc("__big_less");
+        let borrow = ctx.scope.get_or_alloc("__big_borrow");
+        Self::emit_load_word(ctx, 0, 0);
+        ctx.emit(Instruction::LocalSet(na));
+        Self::emit_load_word(ctx, 1, 0);
+        ctx.emit(Instruction::LocalSet(nm));
+        // The remainder stays below 2m, so one limb more than m holds it
+        ctx.emit(Instruction::LocalGet(nm));
+        ctx.emit(Instruction::I64Const(1));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalTee(len));
+        ctx.emit(Instruction::Call(func_map["__big_alloc"]));
+        ctx.emit(Instruction::LocalSet(rem));
+
+        ctx.emit(Instruction::LocalGet(na));
+        ctx.emit(Instruction::I64Const(5));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::LocalSet(bit));
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(bit));
+            ctx.emit(Instruction::I64Eqz);
+            ctx.emit(Instruction::BrIf(1));
+            Self::emit_local_add(ctx, bit, -1);
+
+            // rem = rem << 1 | bit `bit` of a
+            ctx.emit(Instruction::LocalGet(bit));
+            ctx.emit(Instruction::I64Const(5));
+            ctx.emit(Instruction::I64ShrU);
+            ctx.emit(Instruction::LocalSet(i));
+            Self::emit_load_limb(ctx, 0, i);
+            ctx.emit(Instruction::LocalGet(bit));
+            ctx.emit(Instruction::I64Const(31));
+            ctx.emit(Instruction::I64And);
+            ctx.emit(Instruction::I64ShrU);
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64And);
+            ctx.emit(Instruction::LocalSet(carry));
+            Self::emit_count_up(ctx, i, len);
+            {
+                Self::emit_load_limb(ctx, rem, i);
+                ctx.emit(Instruction::LocalSet(x));
+                ctx.emit(Instruction::LocalGet(x));
+                ctx.emit(Instruction::I64Const(1));
+                ctx.emit(Instruction::I64Shl);
+                ctx.emit(Instruction::LocalGet(carry));
+                ctx.emit(Instruction::I64Or);
+                ctx.emit(Instruction::LocalSet(y));
+                Self::emit_store_limb(ctx, rem, i, y);
+                ctx.emit(Instruction::LocalGet(x));
+                ctx.emit(Instruction::I64Const(31));
+                ctx.emit(Instruction::I64ShrU);
+                ctx.emit(Instruction::LocalSet(carry));
+            }
+            Self::emit_count_up_end(ctx, i);
 
-                        // Return list ptr
-                        ctx.emit(Instruction::LocalGet(list_ptr));
-                    }
+            // less = rem < m, from the top limb down
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::LocalSet(less));
+            ctx.emit(Instruction::LocalGet(len));
+            ctx.emit(Instruction::LocalSet(i));
+            ctx.emit(Instruction::Block(BlockType::Empty));
+            ctx.emit(Instruction::Loop(BlockType::Empty));
+            {
+                ctx.emit(Instruction::LocalGet(i));
+                ctx.emit(Instruction::I64Eqz);
+                ctx.emit(Instruction::BrIf(1));
+                Self::emit_local_add(ctx, i, -1);
+                Self::emit_load_limb(ctx, rem, i);
+                ctx.emit(Instruction::LocalSet(x));
+                Self::emit_limb_or_zero(ctx, 1, nm, i);
+                ctx.emit(Instruction::LocalSet(y));
+                ctx.emit(Instruction::LocalGet(x));
+                ctx.emit(Instruction::LocalGet(y));
+                ctx.emit(Instruction::I64Ne);
+                ctx.emit(Instruction::If(BlockType::Empty));
+                ctx.emit(Instruction::LocalGet(x));
+                ctx.emit(Instruction::LocalGet(y));
+                ctx.emit(Instruction::I64LtU);
+                ctx.emit(Instruction::I64ExtendI32U);
+                ctx.emit(Instruction::LocalSet(less));
+                ctx.emit(Instruction::Br(2));
+                ctx.emit(Instruction::End);
+                ctx.emit(Instruction::Br(0));
+            }
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::End);
 
-                    // struct.get(struct, field_index) → load struct[field_index]
-                    "intrinsic_struct_get" | "sys.struct.get" | "struct.get" => {
-                        if args.len() != 2 {
-                            return Err(WasmCompileError {
-                                message: "struct.get requires 2 arguments (struct, field_index)"
-                                    .to_string(),
-                                context: "intrinsic_struct_get".to_string(),
-                            });
-                        }
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        let s_ptr = ctx.scope.get_or_alloc("__sget_ptr");
-                        ctx.emit(Instruction::LocalSet(s_ptr));
+            // rem -= m unless it is below
+            ctx.emit(Instruction::LocalGet(less));
+            ctx.emit(Instruction::I64Eqz);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            {
+                ctx.emit(Instruction::I64Const(0));
+                ctx.emit(Instruction::LocalSet(borrow));
+                Self::emit_count_up(ctx, i, len);
+                Self::emit_load_limb(ctx, rem, i);
+                Self::emit_limb_or_zero(ctx, 1, nm, i);
+                ctx.emit(Instruction::I64Sub);
+                ctx.emit(Instruction::LocalSet(x));
+                Self::emit_borrow(ctx, x, borrow);
+                Self::emit_store_limb(ctx, rem, i, x);
+                Self::emit_count_up_end(ctx, i);
+            }
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::Br(0));
+        }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::LocalGet(rem));
+        ctx.emit(Instruction::Call(func_map["__big_trim"]));
+        Ok(())
+    }
 
-                        ctx.emit(Instruction::LocalGet(s_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        Self::compile_expr(ctx, &args[1], func_map)?;
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Mul);
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Add);
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I32Add);
+    /// Body of `__big_product(a, b)`: a new big integer holding `a * b`, by
+    /// schoolbook multiplication of the limbs.
+    fn emit_big_product(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let na = ctx.scope.get_or_alloc("__big_na");
+        let nb = ctx.scope.get_or_alloc("__big_nb");
+        let product = ctx.scope.get_or_alloc("__big_result");
+        let i = ctx.scope.get_or_alloc("__big_i");
+        let j = ctx.scope.get_or_alloc("__big_j");
+        let k = ctx.scope.get_or_alloc("__big_k");
+        let x = ctx.scope.get_or_alloc("__big_x");
+        let carry = ctx.scope.get_or_alloc("__big_carry");
+        let t = ctx.scope.get_or_alloc("__big_t");
+        Self::emit_load_word(ctx, 0, 0);
+        ctx.emit(Instruction::LocalSet(na));
+        Self::emit_load_word(ctx, 1, 0);
+        ctx.emit(Instruction::LocalSet(nb));
+        ctx.emit(Instruction::LocalGet(na));
+        ctx.emit(Instruction::LocalGet(nb));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::Call(func_map["__big_alloc"]));
+        ctx.emit(Instruction::LocalSet(product));
+
+        Self::emit_count_up(ctx, i, na);
+        {
+            Self::emit_load_limb(ctx, 0, i);
+            ctx.emit(Instruction::LocalSet(x));
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::LocalSet(carry));
+            Self::emit_count_up(ctx, j, nb);
+            {
+                // t = product[i+j] + x * b[j] + carry, which fits in a u64
+                ctx.emit(Instruction::LocalGet(i));
+                ctx.emit(Instruction::LocalGet(j));
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::LocalSet(k));
+                Self::emit_load_limb(ctx, product, k);
+                ctx.emit(Instruction::LocalGet(x));
+                Self::emit_load_limb(ctx, 1, j);
+                ctx.emit(Instruction::I64Mul);
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::LocalGet(carry));
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::LocalSet(t));
+                Self::emit_store_limb(ctx, product, k, t);
+                ctx.emit(Instruction::LocalGet(t));
+                ctx.emit(Instruction::I64Const(32));
+                ctx.emit(Instruction::I64ShrU);
+                ctx.emit(Instruction::LocalSet(carry));
+            }
+            Self::emit_count_up_end(ctx, j);
+            ctx.emit(Instruction::LocalGet(i));
+            ctx.emit(Instruction::LocalGet(nb));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(k));
+            Self::emit_store_limb(ctx, product, k, carry);
+        }
+        Self::emit_count_up_end(ctx, i);
 
-                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 3,
-                            memory_index: 0,
-                        }));
-                    }
+        ctx.emit(Instruction::LocalGet(product));
+        ctx.emit(Instruction::I32WrapI64);
+        Self::emit_load_word(ctx, 0, 8);
+        Self::emit_load_word(ctx, 1, 8);
+        ctx.emit(Instruction::I64Xor);
+        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+            offset: 8,
+            align: 3,
+            memory_index: 0,
+        }));
+        ctx.emit(Instruction::LocalGet(product));
+        ctx.emit(Instruction::Call(func_map["__big_trim"]));
+        Ok(())
+    }
 
-                    // struct.set(struct, field_index, value) → store at struct[field_index]
-                    "intrinsic_struct_set" | "sys.struct.set" | "struct.set" => {
-                        if args.len() != 3 {
-                            return Err(WasmCompileError {
-                                message:
-                                    "struct.set requires 3 arguments (struct, field_index, value)"
-                                        .to_string(),
-                                context: "intrinsic_struct_set".to_string(),
-                            });
-                        }
-                        let s_ptr = ctx.scope.get_or_alloc("__sset_ptr");
-                        let s_val = ctx.scope.get_or_alloc("__sset_val");
+    /// Body of `__big_remainder(a, m)`: a new big integer holding `a`
+    /// modulo `m`, from 0 up to `|m|`. A zero `m` traps, as the
+    /// interpreter raises an error.
+    fn emit_big_remainder(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let rem = ctx.scope.get_or_alloc("__big_result");
+        Self::emit_load_word(ctx, 1, 0);
+        ctx.emit(Instruction::I64Eqz);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::Unreachable);
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::LocalGet(1));
+        ctx.emit(Instruction::Call(func_map["__big_mag_rem"]));
+        ctx.emit(Instruction::LocalSet(rem));
 
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::LocalSet(s_ptr));
-                        Self::compile_expr(ctx, &args[2], func_map)?;
-                        ctx.emit(Instruction::LocalSet(s_val));
+        // A negative `a` with a remainder left counts back from |m|
+        Self::emit_load_word(ctx, 0, 8);
+        ctx.emit(Instruction::I32WrapI64);
+        Self::emit_load_word(ctx, rem, 0);
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::I64Ne);
+        ctx.emit(Instruction::I32And);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::LocalGet(1));
+        ctx.emit(Instruction::LocalGet(rem));
+        ctx.emit(Instruction::Call(func_map["__big_mag_sub"]));
+        ctx.emit(Instruction::LocalSet(rem));
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::LocalGet(rem));
+        Ok(())
+    }
 
-                        // Compute address: ptr + 8 + 8*field_index
-                        ctx.emit(Instruction::LocalGet(s_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        Self::compile_expr(ctx, &args[1], func_map)?;
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Mul);
-                        ctx.emit(Instruction::I64Const(8));
-                        ctx.emit(Instruction::I64Add);
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I32Add);
+    /// Replace each of the first `count` parameters with its `__big_of`.
+    fn emit_big_params(ctx: &mut FuncContext, func_map: &HashMap<String, u32>, count: u32) {
+        for param in 0..count {
+            ctx.emit(Instruction::LocalGet(param));
+            ctx.emit(Instruction::Call(func_map["__big_of"]));
+            ctx.emit(Instruction::LocalSet(param));
+        }
+    }
 
-                        ctx.emit(Instruction::LocalGet(s_val));
-                        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 3,
-                            memory_index: 0,
-                        }));
+    /// Body of `__big_from_string(s)`: the big integer the decimal digits
+    /// of `s`, with an optional leading `-`, spell. Anything else traps.
+    fn emit_big_from_string(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let len = ctx.scope.get_or_alloc("__big_len");
+        let i = ctx.scope.get_or_alloc("__big_i");
+        let j = ctx.scope.get_or_alloc("__big_j");
+        let negative = ctx.scope.get_or_alloc("__big_sign");
+        let cap = ctx.scope.get_or_alloc("__big_cap");
+        let big = ctx.scope.get_or_alloc("__big_result");
+        let carry = ctx.scope.get_or_alloc("__big_carry");
+        let t = ctx.scope.get_or_alloc("__big_t");
+        Self::emit_string_len(ctx, 0);
+        ctx.emit(Instruction::LocalSet(len));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(i));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(negative));
+        ctx.emit(Instruction::LocalGet(len));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::I64Ne);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        Self::emit_string_byte(ctx, 0, i);
+        ctx.emit(Instruction::I32Const(b'-' as i32));
+        ctx.emit(Instruction::I32Eq);
+        ctx.emit(Instruction::I64ExtendI32U);
+        ctx.emit(Instruction::LocalSet(negative));
+        ctx.emit(Instruction::End);
 
-                        // Return struct ptr
-                        ctx.emit(Instruction::LocalGet(s_ptr));
-                    }
+        // The digits start after the sign, and there must be some
+        ctx.emit(Instruction::LocalGet(negative));
+        ctx.emit(Instruction::LocalSet(i));
+        ctx.emit(Instruction::LocalGet(i));
+        ctx.emit(Instruction::LocalGet(len));
+        ctx.emit(Instruction::I64GeU);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::Unreachable);
+        ctx.emit(Instruction::End);
 
-                    // struct.has(struct, field_index) → 1 if field_index < field_count, else 0
-                    "intrinsic_struct_has" | "sys.struct.has" | "struct.has" => {
-                        if args.len() != 2 {
-                            return Err(WasmCompileError {
-                                message: "struct.has requires 2 arguments (struct, field_index)"
-                                    .to_string(),
-                                context: "intrinsic_struct_has".to_string(),
-                            });
-                        }
-                        // Read field_count from header
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 3,
-                            memory_index: 0,
-                        }));
+        // A limb holds more than nine digits
+        ctx.emit(Instruction::LocalGet(len));
+        ctx.emit(Instruction::I64Const(9));
+        ctx.emit(Instruction::I64DivU);
+        ctx.emit(Instruction::I64Const(1));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalTee(cap));
+        ctx.emit(Instruction::Call(func_map["__big_alloc"]));
+        ctx.emit(Instruction::LocalSet(big));
 
-                        // Compare: field_index < field_count → 1, else 0
-                        Self::compile_expr(ctx, &args[1], func_map)?;
-                        ctx.emit(Instruction::I64GtS); // field_count > field_index
-                        ctx.emit(Instruction::I64ExtendI32U); // bool → i64
-                    }
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(i));
+            ctx.emit(Instruction::LocalGet(len));
+            ctx.emit(Instruction::I64GeU);
+            ctx.emit(Instruction::BrIf(1));
+            Self::emit_string_byte(ctx, 0, i);
+            ctx.emit(Instruction::I64ExtendI32U);
+            ctx.emit(Instruction::I64Const(b'0' as i64));
+            ctx.emit(Instruction::I64Sub);
+            ctx.emit(Instruction::LocalTee(carry));
+            ctx.emit(Instruction::I64Const(9));
+            ctx.emit(Instruction::I64GtU);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            ctx.emit(Instruction::Unreachable);
+            ctx.emit(Instruction::End);
 
-                    // =========================================================
-                    // Tier 2: WASI-backed intrinsics
-                    // =========================================================
+            // big = big * 10 + digit
+            Self::emit_count_up(ctx, j, cap);
+            Self::emit_load_limb(ctx, big, j);
+            ctx.emit(Instruction::I64Const(10));
+            ctx.emit(Instruction::I64Mul);
+            ctx.emit(Instruction::LocalGet(carry));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(t));
+            Self::emit_store_limb(ctx, big, j, t);
+            ctx.emit(Instruction::LocalGet(t));
+            ctx.emit(Instruction::I64Const(32));
+            ctx.emit(Instruction::I64ShrU);
+            ctx.emit(Instruction::LocalSet(carry));
+            Self::emit_count_up_end(ctx, j);
+            Self::emit_local_add(ctx, i, 1);
+            ctx.emit(Instruction::Br(0));
+        }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
+        Self::emit_store_word(ctx, big, 8, negative);
+        ctx.emit(Instruction::LocalGet(big));
+        ctx.emit(Instruction::Call(func_map["__big_trim"]));
+        Ok(())
+    }
 
-                    // sys.time.now() → nanoseconds since epoch as i64
-                    // Uses: clock_time_get(clock_id=0 (realtime), precision=1, timestamp_ptr)
-                    "intrinsic_time_now" | "time.now" | "sys.time.now" => {
-                        let ts_ptr = ctx.scope.get_or_alloc("__time_ts_ptr");
+    /// Body of `__big_add(a, b)`.
+    fn emit_big_add(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let sum = ctx.scope.get_or_alloc("__big_result");
+        let sign = ctx.scope.get_or_alloc("__big_sign");
+        let call = |ctx: &mut FuncContext, helper: &str, first: u32, second: u32| {
+            ctx.emit(Instruction::LocalGet(first));
+            ctx.emit(Instruction::LocalGet(second));
+            ctx.emit(Instruction::Call(func_map[helper]));
+            ctx.emit(Instruction::LocalSet(sum));
+        };
+        Self::emit_big_params(ctx, func_map, 2);
+        Self::emit_load_word(ctx, 0, 8);
+        ctx.emit(Instruction::LocalSet(sign));
+
+        // Same signs add magnitudes; otherwise the larger magnitude loses
+        // the smaller one and keeps its sign
+        Self::emit_load_word(ctx, 1, 8);
+        ctx.emit(Instruction::LocalGet(sign));
+        ctx.emit(Instruction::I64Eq);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        call(ctx, "__big_mag_add", 0, 1);
+        ctx.emit(Instruction::Else);
+        {
+            ctx.emit(Instruction::LocalGet(0));
+            ctx.emit(Instruction::LocalGet(1));
+            ctx.emit(Instruction::Call(func_map["__big_mag_cmp"]));
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::I64LtS);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            call(ctx, "__big_mag_sub", 1, 0);
+            Self::emit_load_word(ctx, 1, 8);
+            ctx.emit(Instruction::LocalSet(sign));
+            ctx.emit(Instruction::Else);
+            call(ctx, "__big_mag_sub", 0, 1);
+            ctx.emit(Instruction::End);
+        }
+        ctx.emit(Instruction::End);
+        Self::emit_store_word(ctx, sum, 8, sign);
+        ctx.emit(Instruction::LocalGet(sum));
+        ctx.emit(Instruction::Call(func_map["__big_trim"]));
+        Ok(())
+    }
 
-                        // Allocate 8 bytes for the timestamp result
-                        ctx.emit(Instruction::I64Const(8));
-                        if let Some(&alloc_idx) = func_map.get("__alloc") {
-                            ctx.emit(Instruction::Call(alloc_idx));
-                        } else {
-                            return Err(WasmCompileError {
-                                message: "__alloc not found".to_string(),
-                                context: "sys.time.now".to_string(),
-                            });
-                        }
-                        ctx.emit(Instruction::LocalSet(ts_ptr));
+    /// Body of `__big_mul(a, b)`.
+    fn emit_big_mul(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_big_params(ctx, func_map, 2);
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::LocalGet(1));
+        ctx.emit(Instruction::Call(func_map["__big_product"]));
+        Ok(())
+    }
 
-                        // Call clock_time_get(0, 1, ts_ptr)
-                        ctx.emit(Instruction::I32Const(0)); // clock_id = REALTIME
-                        ctx.emit(Instruction::I64Const(1)); // precision = 1ns
-                        ctx.emit(Instruction::LocalGet(ts_ptr));
-                        ctx.emit(Instruction::I32WrapI64); // ptr as i32
-                        ctx.emit(Instruction::Call(WASI_CLOCK_TIME_GET_FUNC_IDX));
-                        ctx.emit(Instruction::Drop); // drop errno
+    /// Body of `__big_mod(a, m)`.
+    fn emit_big_mod(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_big_params(ctx, func_map, 2);
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::LocalGet(1));
+        ctx.emit(Instruction::Call(func_map["__big_remainder"]));
+        Ok(())
+    }
 
-                        // Load the 8-byte timestamp
-                        ctx.emit(Instruction::LocalGet(ts_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 3,
-                            memory_index: 0,
-                        }));
-                    }
+    /// Body of `__big_pow_mod(base, exponent, m)`: square and multiply over
+    /// the exponent's bits, lowest first, reducing modulo `m` at each step.
+    /// A negative exponent traps.
+    fn emit_big_pow_mod(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let result = ctx.scope.get_or_alloc("__big_result");
+        let base = ctx.scope.get_or_alloc("__big_base");
+        let bit = ctx.scope.get_or_alloc("__big_bit");
+        let bits = ctx.scope.get_or_alloc("__big_bits");
+        let i = ctx.scope.get_or_alloc("__big_i");
+        // local = local * factor mod m
+        let mul_mod = |ctx: &mut FuncContext, local: u32, factor: u32| {
+            ctx.emit(Instruction::LocalGet(local));
+            ctx.emit(Instruction::LocalGet(factor));
+            ctx.emit(Instruction::Call(func_map["__big_product"]));
+            ctx.emit(Instruction::LocalGet(2));
+            ctx.emit(Instruction::Call(func_map["__big_remainder"]));
+            ctx.emit(Instruction::LocalSet(local));
+        };
+        Self::emit_big_params(ctx, func_map, 3);
+        Self::emit_load_word(ctx, 1, 8);
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::Unreachable);
+        ctx.emit(Instruction::End);
 
-                    // sys.io.read_line() → reads from stdin (fd=0) into buffer, returns i64 bytes read
-                    // Uses: fd_read(fd=0, iovs_ptr, iovs_len=1, nread_ptr) -> errno
-                    "intrinsic_io_read_line" | "sys.io.read_line" => {
-                        let buf_ptr = ctx.scope.get_or_alloc("__read_buf_ptr");
-                        let iov_ptr = ctx.scope.get_or_alloc("__read_iov_ptr");
-                        let nread_ptr = ctx.scope.get_or_alloc("__read_nread_ptr");
+        // 1 mod m, which is 0 when m is 1
+        ctx.emit(Instruction::I64Const(1));
+        ctx.emit(Instruction::Call(func_map["__big_of"]));
+        ctx.emit(Instruction::LocalGet(2));
+        ctx.emit(Instruction::Call(func_map["__big_remainder"]));
+        ctx.emit(Instruction::LocalSet(result));
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::LocalGet(2));
+        ctx.emit(Instruction::Call(func_map["__big_remainder"]));
+        ctx.emit(Instruction::LocalSet(base));
+
+        Self::emit_load_word(ctx, 1, 0);
+        ctx.emit(Instruction::I64Const(5));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::LocalSet(bits));
+        Self::emit_count_up(ctx, bit, bits);
+        {
+            ctx.emit(Instruction::LocalGet(bit));
+            ctx.emit(Instruction::I64Const(5));
+            ctx.emit(Instruction::I64ShrU);
+            ctx.emit(Instruction::LocalSet(i));
+            Self::emit_load_limb(ctx, 1, i);
+            ctx.emit(Instruction::LocalGet(bit));
+            ctx.emit(Instruction::I64Const(31));
+            ctx.emit(Instruction::I64And);
+            ctx.emit(Instruction::I64ShrU);
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64And);
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            mul_mod(ctx, result, base);
+            ctx.emit(Instruction::End);
+            mul_mod(ctx, base, base);
+        }
+        Self::emit_count_up_end(ctx, bit);
+        ctx.emit(Instruction::LocalGet(result));
+        Ok(())
+    }
 
-                        // Allocate 1024 bytes for read buffer
-                        ctx.emit(Instruction::I64Const(1024));
-                        if let Some(&alloc_idx) = func_map.get("__alloc") {
-                            ctx.emit(Instruction::Call(alloc_idx));
-                        } else {
-                            return Err(WasmCompileError {
-                                message: "__alloc not found".to_string(),
-                                context: "sys.io.read_line".to_string(),
-                            });
-                        }
-                        ctx.emit(Instruction::LocalSet(buf_ptr));
+    /// Body of `__big_cmp(a, b)`: -1, 0 or 1 as `a` is less than, equal to
+    /// or greater than `b`.
+    fn emit_big_cmp(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let order = ctx.scope.get_or_alloc("__big_order");
+        Self::emit_big_params(ctx, func_map, 2);
+        Self::emit_load_word(ctx, 0, 8);
+        Self::emit_load_word(ctx, 1, 8);
+        ctx.emit(Instruction::I64Ne);
+        ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+        {
+            // Signs differ: the negative one is less
+            Self::emit_load_word(ctx, 1, 8);
+            Self::emit_load_word(ctx, 0, 8);
+            ctx.emit(Instruction::I64Sub);
+        }
+        ctx.emit(Instruction::Else);
+        {
+            // Same sign: compare magnitudes, reversed when negative
+            ctx.emit(Instruction::LocalGet(0));
+            ctx.emit(Instruction::LocalGet(1));
+            ctx.emit(Instruction::Call(func_map["__big_mag_cmp"]));
+            ctx.emit(Instruction::LocalSet(order));
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::LocalGet(order));
+            ctx.emit(Instruction::I64Sub);
+            ctx.emit(Instruction::LocalGet(order));
+            Self::emit_load_word(ctx, 0, 8);
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::Select);
+        }
+        ctx.emit(Instruction::End);
+        Ok(())
+    }
 
-                        // Allocate 8 bytes for iovec: [buf_ptr:i32, buf_len:i32]
-                        ctx.emit(Instruction::I64Const(8));
-                        if let Some(&alloc_idx) = func_map.get("__alloc") {
-                            ctx.emit(Instruction::Call(alloc_idx));
-                        } else {
-                            return Err(WasmCompileError {
-                                message: "__alloc not found".to_string(),
-                                context: "sys.io.read_line iov".to_string(),
-                            });
-                        }
-                        ctx.emit(Instruction::LocalSet(iov_ptr));
+    /// Body of `__big_to_string(big)`: decimal digits, `-` first when
+    /// negative. A copy of the magnitude is divided by ten a digit at a
+    /// time, the digits filling a buffer from the end.
+    fn emit_big_to_string(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let n = ctx.scope.get_or_alloc("__big_n");
+        let copy = ctx.scope.get_or_alloc("__big_copy");
+        let buf = ctx.scope.get_or_alloc("__big_buf");
+        let size = ctx.scope.get_or_alloc("__big_size");
+        let pos = ctx.scope.get_or_alloc("__big_pos");
+        let i = ctx.scope.get_or_alloc("__big_i");
+        let rem = ctx.scope.get_or_alloc("__big_rem");
+        let cur = ctx.scope.get_or_alloc("__big_cur");
+        let quotient = ctx.scope.get_or_alloc("__big_quotient");
+        // Push the address of byte `pos` of the buffer
+        let buf_at_pos = |ctx: &mut FuncContext| {
+            ctx.emit(Instruction::LocalGet(buf));
+            ctx.emit(Instruction::LocalGet(pos));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::I32WrapI64);
+        };
+        let store_byte = |ctx: &mut FuncContext| {
+            ctx.emit(Instruction::I64Store8(wasm_encoder::MemArg {
+                offset: 0,
+                align: 0,
+                memory_index: 0,
+            }));
+        };
+        Self::emit_big_params(ctx, func_map, 1);
+        Self::emit_load_word(ctx, 0, 0);
+        ctx.emit(Instruction::LocalSet(n));
+        ctx.emit(Instruction::LocalGet(n));
+        ctx.emit(Instruction::Call(func_map["__big_alloc"]));
+        ctx.emit(Instruction::LocalSet(copy));
+        ctx.emit(Instruction::LocalGet(copy));
+        ctx.emit(Instruction::I64Const(16));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I64Const(16));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::LocalGet(n));
+        ctx.emit(Instruction::I64Const(2));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::MemoryCopy {
+            src_mem: 0,
+            dst_mem: 0,
+        });
 
-                        // Allocate 4 bytes for nread
-                        ctx.emit(Instruction::I64Const(4));
-                        if let Some(&alloc_idx) = func_map.get("__alloc") {
-                            ctx.emit(Instruction::Call(alloc_idx));
-                        } else {
-                            return Err(WasmCompileError {
-                                message: "__alloc not found".to_string(),
-                                context: "sys.io.read_line nread".to_string(),
-                            });
-                        }
-                        ctx.emit(Instruction::LocalSet(nread_ptr));
+        // At most ten digits per limb, a sign, and the digit of zero
+        ctx.emit(Instruction::LocalGet(n));
+        ctx.emit(Instruction::I64Const(10));
+        ctx.emit(Instruction::I64Mul);
+        ctx.emit(Instruction::I64Const(2));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalTee(size));
+        ctx.emit(Instruction::LocalTee(pos));
+        ctx.emit(Instruction::Call(func_map["__alloc"]));
+        ctx.emit(Instruction::LocalSet(buf));
+
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            // rem = copy mod 10, copy = copy / 10, from the top limb down
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::LocalSet(rem));
+            ctx.emit(Instruction::LocalGet(n));
+            ctx.emit(Instruction::LocalSet(i));
+            ctx.emit(Instruction::Block(BlockType::Empty));
+            ctx.emit(Instruction::Loop(BlockType::Empty));
+            {
+                ctx.emit(Instruction::LocalGet(i));
+                ctx.emit(Instruction::I64Eqz);
+                ctx.emit(Instruction::BrIf(1));
+                Self::emit_local_add(ctx, i, -1);
+                ctx.emit(Instruction::LocalGet(rem));
+                ctx.emit(Instruction::I64Const(32));
+                ctx.emit(Instruction::I64Shl);
+                Self::emit_load_limb(ctx, copy, i);
+                ctx.emit(Instruction::I64Or);
+                ctx.emit(Instruction::LocalSet(cur));
+                ctx.emit(Instruction::LocalGet(cur));
+                ctx.emit(Instruction::I64Const(10));
+                ctx.emit(Instruction::I64DivU);
+                ctx.emit(Instruction::LocalSet(quotient));
+                Self::emit_store_limb(ctx, copy, i, quotient);
+                ctx.emit(Instruction::LocalGet(cur));
+                ctx.emit(Instruction::I64Const(10));
+                ctx.emit(Instruction::I64RemU);
+                ctx.emit(Instruction::LocalSet(rem));
+                ctx.emit(Instruction::Br(0));
+            }
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::End);
+            Self::emit_local_add(ctx, pos, -1);
+            buf_at_pos(ctx);
+            ctx.emit(Instruction::LocalGet(rem));
+            ctx.emit(Instruction::I64Const(b'0' as i64));
+            ctx.emit(Instruction::I64Add);
+            store_byte(ctx);
+
+            // Drop the limbs the division emptied; go on while any is left
+            ctx.emit(Instruction::Block(BlockType::Empty));
+            ctx.emit(Instruction::Loop(BlockType::Empty));
+            {
+                ctx.emit(Instruction::LocalGet(n));
+                ctx.emit(Instruction::I64Eqz);
+                ctx.emit(Instruction::BrIf(1));
+                ctx.emit(Instruction::LocalGet(n));
+                ctx.emit(Instruction::I64Const(1));
+                ctx.emit(Instruction::I64Sub);
+                ctx.emit(Instruction::LocalSet(i));
+                Self::emit_load_limb(ctx, copy, i);
+                ctx.emit(Instruction::I64Const(0));
+                ctx.emit(Instruction::I64Ne);
+                ctx.emit(Instruction::BrIf(1));
+                ctx.emit(Instruction::LocalGet(i));
+                ctx.emit(Instruction::LocalSet(n));
+                ctx.emit(Instruction::Br(0));
+            }
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::LocalGet(n));
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::I64Ne);
+            ctx.emit(Instruction::BrIf(0));
+        }
+        ctx.emit(Instruction::End);
 
-                        // Fill iovec: iov[0] = buf_ptr, iov[4] = 1024
-                        ctx.emit(Instruction::LocalGet(iov_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::LocalGet(buf_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I32Store(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 2,
+        Self::emit_load_word(ctx, 0, 8);
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        Self::emit_local_add(ctx, pos, -1);
+        buf_at_pos(ctx);
+        ctx.emit(Instruction::I64Const(b'-' as i64));
+        store_byte(ctx);
+        ctx.emit(Instruction::End);
+
+        // (buf + pos) << 32 | (size - pos)
+        ctx.emit(Instruction::LocalGet(buf));
+        ctx.emit(Instruction::LocalGet(pos));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::I64Const(32));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::LocalGet(size));
+        ctx.emit(Instruction::LocalGet(pos));
+        ctx.emit(Instruction::I64Sub);
+        ctx.emit(Instruction::I64Or);
+        Ok(())
+    }
+
+    /// Trap if the i32 on the stack is nonzero.
+    fn emit_trap_if(ctx: &mut FuncContext) {
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::Unreachable);
+        ctx.emit(Instruction::End);
+    }
+
+    /// Store the constant `value` in the word at the address in `addr`
+    /// plus `offset`.
+    fn emit_store_const(ctx: &mut FuncContext, addr: u32, offset: u64, value: i64) {
+        ctx.emit(Instruction::LocalGet(addr));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I64Const(value));
+        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+            offset,
+            align: 3,
+            memory_index: 0,
+        }));
+    }
+
+    /// Push the address of the element `offset` bytes into the list in
+    /// `list`, less the 8 bytes of header that element loads and stores
+    /// add back as their offset.
+    fn emit_element_base(ctx: &mut FuncContext, list: u32, offset: u32) {
+        ctx.emit(Instruction::LocalGet(list));
+        ctx.emit(Instruction::LocalGet(offset));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::I32WrapI64);
+    }
+
+    /// Push the element `offset` bytes into the list in `list`.
+    fn emit_load_element(ctx: &mut FuncContext, list: u32, offset: u32) {
+        Self::emit_element_base(ctx, list, offset);
+        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+            offset: 8,
+            align: 3,
+            memory_index: 0,
+        }));
+    }
+
+    /// Push the two elements from `offset` bytes into the list in `list` as
+    /// the lanes of a v128.
+    fn emit_load_lanes(ctx: &mut FuncContext, list: u32, offset: u32) {
+        Self::emit_element_base(ctx, list, offset);
+        ctx.emit(Instruction::V128Load(wasm_encoder::MemArg {
+            offset: 8,
+            align: 3,
+            memory_index: 0,
+        }));
+    }
+
+    /// Set `offset` to `index * 8`, the byte offset of element `index`.
+    fn emit_element_offset(ctx: &mut FuncContext, index: u32, offset: u32) {
+        ctx.emit(Instruction::LocalGet(index));
+        ctx.emit(Instruction::I64Const(3));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::LocalSet(offset));
+    }
+
+    /// Load the `data` and `shape` lists of the tensor in `tensor` into
+    /// `data` and `shape`, trapping unless it is a struct of two lists.
+    fn emit_tensor_parts(ctx: &mut FuncContext, tensor: u32, data: u32, shape: u32) {
+        Self::emit_heap_object_guard(ctx, tensor, HEAP_TAG_STRUCT, 2);
+        for (part, offset) in [(data, 8), (shape, 16)] {
+            Self::emit_load_word(ctx, tensor, offset);
+            ctx.emit(Instruction::LocalSet(part));
+            Self::emit_heap_object_guard(ctx, part, HEAP_TAG_LIST, 0);
+        }
+    }
+
+    /// Load a 2D tensor's dimensions from `shape` into `rows` and `cols`,
+    /// trapping unless there are two, both below 2^32 so that their product
+    /// cannot wrap, and the product is the length of `data`.
+    fn emit_matrix_dims(ctx: &mut FuncContext, data: u32, shape: u32, (rows, cols): (u32, u32)) {
+        Self::emit_load_word(ctx, shape, 0);
+        ctx.emit(Instruction::I64Const(2));
+        ctx.emit(Instruction::I64Ne);
+        Self::emit_trap_if(ctx);
+        Self::emit_load_word(ctx, shape, 8);
+        ctx.emit(Instruction::LocalSet(rows));
+        Self::emit_load_word(ctx, shape, 16);
+        ctx.emit(Instruction::LocalSet(cols));
+
+        // A negative dimension has its top bits set
+        ctx.emit(Instruction::LocalGet(rows));
+        ctx.emit(Instruction::LocalGet(cols));
+        ctx.emit(Instruction::I64Or);
+        ctx.emit(Instruction::I64Const(32));
+        ctx.emit(Instruction::I64ShrU);
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::I64Ne);
+        ctx.emit(Instruction::LocalGet(rows));
+        ctx.emit(Instruction::LocalGet(cols));
+        ctx.emit(Instruction::I64Mul);
+        Self::emit_load_word(ctx, data, 0);
+        ctx.emit(Instruction::I64Ne);
+        ctx.emit(Instruction::I32Or);
+        Self::emit_trap_if(ctx);
+    }
+
+    /// Allocate a list of `len` zeroed elements into `list`.
+    fn emit_new_list(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+        len: u32,
+        list: u32,
+    ) -> Result<(), WasmCompileError> {
+        ctx.emit(Instruction::LocalGet(len));
+        ctx.emit(Instruction::I64Const(3));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::I64Add);
+        Self::emit_alloc_object(ctx, HEAP_TAG_LIST, func_map, "__tensor")?;
+        ctx.emit(Instruction::LocalSet(list));
+        Self::emit_store_word(ctx, list, 0, len);
+        Ok(())
+    }
+
+    /// Copy the list in `src` into a new list in `list`.
+    fn emit_copy_list(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+        src: u32,
+        list: u32,
+    ) -> Result<(), WasmCompileError> {
+        let len = ctx.scope.get_or_alloc("__tensor_copy_len");
+        Self::emit_load_word(ctx, src, 0);
+        ctx.emit(Instruction::LocalSet(len));
+        Self::emit_new_list(ctx, func_map, len, list)?;
+        // Header included, which the two lists share
+        ctx.emit(Instruction::LocalGet(len));
+        ctx.emit(Instruction::I64Const(3));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalSet(len));
+        Self::emit_copy_bytes(ctx, list, src, len);
+        Ok(())
+    }
+
+    /// Push a new tensor of the 2D shape `[rows, cols]` whose elements are
+    /// the list in `data`.
+    fn emit_new_matrix(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+        data: u32,
+        (rows, cols): (u32, u32),
+    ) -> Result<(), WasmCompileError> {
+        let shape = ctx.scope.get_or_alloc("__tensor_new_shape");
+        ctx.emit(Instruction::I64Const(24));
+        Self::emit_alloc_object(ctx, HEAP_TAG_LIST, func_map, "__tensor")?;
+        ctx.emit(Instruction::LocalSet(shape));
+        Self::emit_store_const(ctx, shape, 0, 2);
+        Self::emit_store_word(ctx, shape, 8, rows);
+        Self::emit_store_word(ctx, shape, 16, cols);
+        Self::emit_new_tensor(ctx, func_map, data, shape)
+    }
+
+    /// Push a new tensor of the lists in `data` and `shape`.
+    fn emit_new_tensor(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+        data: u32,
+        shape: u32,
+    ) -> Result<(), WasmCompileError> {
+        let tensor = ctx.scope.get_or_alloc("__tensor_new");
+        ctx.emit(Instruction::I64Const(24));
+        Self::emit_alloc_object(ctx, HEAP_TAG_STRUCT, func_map, "__tensor")?;
+        ctx.emit(Instruction::LocalSet(tensor));
+        Self::emit_store_const(ctx, tensor, 0, 2);
+        Self::emit_store_word(ctx, tensor, 8, data);
+        Self::emit_store_word(ctx, tensor, 16, shape);
+        ctx.emit(Instruction::LocalGet(tensor));
+        Ok(())
+    }
+
+    /// Body of `__tensor_new(data, shape)`: a tensor of copies of the two
+    /// lists, which are not checked against each other, as in the
+    /// interpreter.
+    fn emit_tensor_new(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let data = ctx.scope.get_or_alloc("__tensor_data");
+        let shape = ctx.scope.get_or_alloc("__tensor_shape");
+        Self::emit_heap_object_guard(ctx, 0, HEAP_TAG_LIST, 0);
+        Self::emit_heap_object_guard(ctx, 1, HEAP_TAG_LIST, 0);
+        Self::emit_copy_list(ctx, func_map, 0, data)?;
+        Self::emit_copy_list(ctx, func_map, 1, shape)?;
+        Self::emit_new_tensor(ctx, func_map, data, shape)
+    }
+
+    /// Body of `__tensor_dot(a, b)` with scalar multiplies.
+    fn emit_tensor_dot(
+        ctx: &mut FuncContext,
+        _func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_dot_kernel(ctx, false);
+        Ok(())
+    }
+
+    /// Body of `__tensor_dot(a, b)` on v128 lanes.
+    fn emit_tensor_dot_simd(
+        ctx: &mut FuncContext,
+        _func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_dot_kernel(ctx, true);
+        Ok(())
+    }
+
+    /// The sum of the products of the elements of the tensors `a` and `b`,
+    /// wrapping on overflow. Traps unless they have as many elements. With
+    /// `simd`, pairs of elements accumulate in the two lanes of a v128 and
+    /// only an odd last element is multiplied on its own.
+    fn emit_dot_kernel(ctx: &mut FuncContext, simd: bool) {
+        let lanes = simd.then(|| ctx.reserve_v128_locals(1));
+        let a = ctx.scope.get_or_alloc("__tensor_a");
+        let b = ctx.scope.get_or_alloc("__tensor_b");
+        let shape = ctx.scope.get_or_alloc("__tensor_shape");
+        let end = ctx.scope.get_or_alloc("__tensor_end");
+        let offset = ctx.scope.get_or_alloc("__tensor_offset");
+        let sum = ctx.scope.get_or_alloc("__tensor_sum");
+        Self::emit_tensor_parts(ctx, 0, a, shape);
+        Self::emit_tensor_parts(ctx, 1, b, shape);
+        Self::emit_load_word(ctx, a, 0);
+        Self::emit_load_word(ctx, b, 0);
+        ctx.emit(Instruction::I64Ne);
+        Self::emit_trap_if(ctx);
+        Self::emit_load_word(ctx, a, 0);
+        ctx.emit(Instruction::I64Const(3));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::LocalSet(end));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(sum));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(offset));
+
+        if let Some(lanes) = lanes {
+            let pairs_end = ctx.scope.get_or_alloc("__tensor_pairs_end");
+            ctx.emit(Instruction::LocalGet(end));
+            ctx.emit(Instruction::I64Const(-16));
+            ctx.emit(Instruction::I64And);
+            ctx.emit(Instruction::LocalSet(pairs_end));
+            Self::emit_count_from(ctx, offset, pairs_end);
+            {
+                ctx.emit(Instruction::LocalGet(lanes));
+                Self::emit_load_lanes(ctx, a, offset);
+                Self::emit_load_lanes(ctx, b, offset);
+                ctx.emit(Instruction::I64x2Mul);
+                ctx.emit(Instruction::I64x2Add);
+                ctx.emit(Instruction::LocalSet(lanes));
+            }
+            Self::emit_count_step_end(ctx, offset, 16);
+            ctx.emit(Instruction::LocalGet(lanes));
+            ctx.emit(Instruction::I64x2ExtractLane(0));
+            ctx.emit(Instruction::LocalGet(lanes));
+            ctx.emit(Instruction::I64x2ExtractLane(1));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(sum));
+        }
+
+        Self::emit_count_from(ctx, offset, end);
+        {
+            ctx.emit(Instruction::LocalGet(sum));
+            Self::emit_load_element(ctx, a, offset);
+            Self::emit_load_element(ctx, b, offset);
+            ctx.emit(Instruction::I64Mul);
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(sum));
+        }
+        Self::emit_count_step_end(ctx, offset, 8);
+        ctx.emit(Instruction::LocalGet(sum));
+    }
+
+    /// Body of `__tensor_matmul(a, b)` with scalar multiplies.
+    fn emit_tensor_matmul(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_matmul_kernel(ctx, func_map, false)
+    }
+
+    /// Body of `__tensor_matmul(a, b)` on v128 lanes.
+    fn emit_tensor_matmul_simd(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_matmul_kernel(ctx, func_map, true)
+    }
+
+    /// The product of the matrices `a` = [m, k] and `b` = [k, n], wrapping
+    /// on overflow, as a new [m, n] tensor. Traps unless both are 2D and
+    /// the inner dimensions agree. Runs in i-p-j order, adding `a[i][p]`
+    /// times row `p` of `b` to row `i` of the result, so both rows are
+    /// walked in memory order; with `simd`, two columns at a time.
+    fn emit_matmul_kernel(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+        simd: bool,
+    ) -> Result<(), WasmCompileError> {
+        let scale = simd.then(|| ctx.reserve_v128_locals(1));
+        let a = ctx.scope.get_or_alloc("__tensor_a");
+        let b = ctx.scope.get_or_alloc("__tensor_b");
+        let c = ctx.scope.get_or_alloc("__tensor_c");
+        let shape = ctx.scope.get_or_alloc("__tensor_shape");
+        let m = ctx.scope.get_or_alloc("__tensor_m");
+        let k = ctx.scope.get_or_alloc("__tensor_k");
+        let inner = ctx.scope.get_or_alloc("__tensor_inner");
+        let n = ctx.scope.get_or_alloc("__tensor_n");
+        let len = ctx.scope.get_or_alloc("__tensor_len");
+        let row_bytes = ctx.scope.get_or_alloc("__tensor_row_bytes");
+        let pairs_end = ctx.scope.get_or_alloc("__tensor_pairs_end");
+        let i = ctx.scope.get_or_alloc("__tensor_i");
+        let p = ctx.scope.get_or_alloc("__tensor_p");
+        let j = ctx.scope.get_or_alloc("__tensor_j");
+        let x = ctx.scope.get_or_alloc("__tensor_x");
+        let a_row = ctx.scope.get_or_alloc("__tensor_a_row");
+        let b_row = ctx.scope.get_or_alloc("__tensor_b_row");
+        let c_row = ctx.scope.get_or_alloc("__tensor_c_row");
+        Self::emit_tensor_parts(ctx, 0, a, shape);
+        Self::emit_matrix_dims(ctx, a, shape, (m, k));
+        Self::emit_tensor_parts(ctx, 1, b, shape);
+        Self::emit_matrix_dims(ctx, b, shape, (inner, n));
+        ctx.emit(Instruction::LocalGet(k));
+        ctx.emit(Instruction::LocalGet(inner));
+        ctx.emit(Instruction::I64Ne);
+        Self::emit_trap_if(ctx);
+
+        ctx.emit(Instruction::LocalGet(m));
+        ctx.emit(Instruction::LocalGet(n));
+        ctx.emit(Instruction::I64Mul);
+        ctx.emit(Instruction::LocalSet(len));
+        Self::emit_new_list(ctx, func_map, len, c)?;
+        Self::emit_element_offset(ctx, n, row_bytes);
+        ctx.emit(Instruction::LocalGet(row_bytes));
+        ctx.emit(Instruction::I64Const(-16));
+        ctx.emit(Instruction::I64And);
+        ctx.emit(Instruction::LocalSet(pairs_end));
+
+        // a_row and c_row: byte offsets of row i in `a` and the result
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(a_row));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(c_row));
+        Self::emit_count_up(ctx, i, m);
+        {
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::LocalSet(b_row));
+            Self::emit_count_up(ctx, p, k);
+            {
+                Self::emit_load_element(ctx, a, a_row);
+                ctx.emit(Instruction::LocalSet(x));
+                Self::emit_local_add(ctx, a_row, 8);
+                ctx.emit(Instruction::I64Const(0));
+                ctx.emit(Instruction::LocalSet(j));
+
+                if let Some(scale) = scale {
+                    ctx.emit(Instruction::LocalGet(x));
+                    ctx.emit(Instruction::I64x2Splat);
+                    ctx.emit(Instruction::LocalSet(scale));
+                    Self::emit_count_from(ctx, j, pairs_end);
+                    {
+                        Self::emit_matmul_row_base(ctx, c, c_row, j);
+                        Self::emit_matmul_row_base(ctx, c, c_row, j);
+                        ctx.emit(Instruction::V128Load(wasm_encoder::MemArg {
+                            offset: 8,
+                            align: 3,
                             memory_index: 0,
                         }));
-                        ctx.emit(Instruction::LocalGet(iov_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I32Const(1024));
-                        ctx.emit(Instruction::I32Store(wasm_encoder::MemArg {
-                            offset: 4,
-                            align: 2,
+                        Self::emit_matmul_row_base(ctx, b, b_row, j);
+                        ctx.emit(Instruction::V128Load(wasm_encoder::MemArg {
+                            offset: 8,
+                            align: 3,
                             memory_index: 0,
                         }));
-
-                        // Call fd_read(0, iov_ptr, 1, nread_ptr)
-                        ctx.emit(Instruction::I32Const(0)); // fd = stdin
-                        ctx.emit(Instruction::LocalGet(iov_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I32Const(1)); // iovs_len
-                        ctx.emit(Instruction::LocalGet(nread_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::Call(WASI_FD_READ_FUNC_IDX));
-                        ctx.emit(Instruction::Drop); // drop errno
-
-                        // Return nread as i64
-                        ctx.emit(Instruction::LocalGet(nread_ptr));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::I32Load(wasm_encoder::MemArg {
-                            offset: 0,
-                            align: 2,
+                        ctx.emit(Instruction::LocalGet(scale));
+                        ctx.emit(Instruction::I64x2Mul);
+                        ctx.emit(Instruction::I64x2Add);
+                        ctx.emit(Instruction::V128Store(wasm_encoder::MemArg {
+                            offset: 8,
+                            align: 3,
                             memory_index: 0,
                         }));
-                        ctx.emit(Instruction::I64ExtendI32U);
                     }
+                    Self::emit_count_step_end(ctx, j, 16);
+                }
 
-                    // sys.crypto.random_bytes(n) → fills buffer with n random bytes, returns ptr as i64
-                    // Uses: random_get(buf_ptr, buf_len) -> errno
-                    "intrinsic_crypto_random_bytes" | "sys.crypto.random_bytes" => {
-                        if args.len() != 1 {
-                            return Err(WasmCompileError {
-                                message: "sys.crypto.random_bytes requires 1 argument (byte_count)"
-                                    .to_string(),
-                                context: "sys.crypto.random_bytes".to_string(),
-                            });
-                        }
-                        let rand_len = ctx.scope.get_or_alloc("__rand_len");
-                        let rand_buf = ctx.scope.get_or_alloc("__rand_buf");
+                Self::emit_count_from(ctx, j, row_bytes);
+                {
+                    Self::emit_matmul_row_base(ctx, c, c_row, j);
+                    Self::emit_matmul_row_base(ctx, c, c_row, j);
+                    ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                        offset: 8,
+                        align: 3,
+                        memory_index: 0,
+                    }));
+                    ctx.emit(Instruction::LocalGet(x));
+                    Self::emit_matmul_row_base(ctx, b, b_row, j);
+                    ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                        offset: 8,
+                        align: 3,
+                        memory_index: 0,
+                    }));
+                    ctx.emit(Instruction::I64Mul);
+                    ctx.emit(Instruction::I64Add);
+                    ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                        offset: 8,
+                        align: 3,
+                        memory_index: 0,
+                    }));
+                }
+                Self::emit_count_step_end(ctx, j, 8);
+                ctx.emit(Instruction::LocalGet(b_row));
+                ctx.emit(Instruction::LocalGet(row_bytes));
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::LocalSet(b_row));
+            }
+            Self::emit_count_up_end(ctx, p);
+            ctx.emit(Instruction::LocalGet(c_row));
+            ctx.emit(Instruction::LocalGet(row_bytes));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::LocalSet(c_row));
+        }
+        Self::emit_count_up_end(ctx, i);
+        Self::emit_new_matrix(ctx, func_map, c, (m, n))
+    }
 
-                        // Compile byte count
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::LocalSet(rand_len));
+    /// Push the base address of column byte offset `column` in the row at
+    /// byte offset `row` of the list in `list` (see
+    /// [`Self::emit_element_base`]).
+    fn emit_matmul_row_base(ctx: &mut FuncContext, list: u32, row: u32, column: u32) {
+        ctx.emit(Instruction::LocalGet(list));
+        ctx.emit(Instruction::LocalGet(row));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalGet(column));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::I32WrapI64);
+    }
 
-                        // Allocate buffer
-                        ctx.emit(Instruction::LocalGet(rand_len));
-                        if let Some(&alloc_idx) = func_map.get("__alloc") {
-                            ctx.emit(Instruction::Call(alloc_idx));
-                        } else {
-                            return Err(WasmCompileError {
-                                message: "__alloc not found".to_string(),
-                                context: "sys.crypto.random_bytes".to_string(),
-                            });
-                        }
-                        ctx.emit(Instruction::LocalSet(rand_buf));
+    /// Body of `__tensor_transpose(t)`: `t` = [m, n] as a new [n, m]
+    /// tensor. Traps unless `t` is 2D.
+    fn emit_tensor_transpose(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let data = ctx.scope.get_or_alloc("__tensor_data");
+        let shape = ctx.scope.get_or_alloc("__tensor_shape");
+        let out = ctx.scope.get_or_alloc("__tensor_out");
+        let m = ctx.scope.get_or_alloc("__tensor_m");
+        let n = ctx.scope.get_or_alloc("__tensor_n");
+        let len = ctx.scope.get_or_alloc("__tensor_len");
+        let i = ctx.scope.get_or_alloc("__tensor_i");
+        let j = ctx.scope.get_or_alloc("__tensor_j");
+        let src = ctx.scope.get_or_alloc("__tensor_src");
+        let dst = ctx.scope.get_or_alloc("__tensor_dst");
+        Self::emit_tensor_parts(ctx, 0, data, shape);
+        Self::emit_matrix_dims(ctx, data, shape, (m, n));
+        ctx.emit(Instruction::LocalGet(m));
+        ctx.emit(Instruction::LocalGet(n));
+        ctx.emit(Instruction::I64Mul);
+        ctx.emit(Instruction::LocalSet(len));
+        Self::emit_new_list(ctx, func_map, len, out)?;
+
+        // out[j][i] = data[i][j], reading `data` in memory order
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(src));
+        Self::emit_count_up(ctx, i, m);
+        {
+            Self::emit_element_offset(ctx, i, dst);
+            Self::emit_count_up(ctx, j, n);
+            {
+                Self::emit_element_base(ctx, out, dst);
+                Self::emit_load_element(ctx, data, src);
+                ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                    offset: 8,
+                    align: 3,
+                    memory_index: 0,
+                }));
+                Self::emit_local_add(ctx, src, 8);
+                ctx.emit(Instruction::LocalGet(dst));
+                ctx.emit(Instruction::LocalGet(m));
+                ctx.emit(Instruction::I64Const(3));
+                ctx.emit(Instruction::I64Shl);
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::LocalSet(dst));
+            }
+            Self::emit_count_up_end(ctx, j);
+        }
+        Self::emit_count_up_end(ctx, i);
+        Self::emit_new_matrix(ctx, func_map, out, (n, m))
+    }
 
-                        // Call random_get(buf_ptr, buf_len)
-                        ctx.emit(Instruction::LocalGet(rand_buf));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::LocalGet(rand_len));
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::Call(WASI_RANDOM_GET_FUNC_IDX));
-                        ctx.emit(Instruction::Drop); // drop errno
+    /// Body of `__tensor_add(a, b)` with scalar adds.
+    fn emit_tensor_add(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_elementwise_kernel(ctx, func_map, TensorOp::Add, false)
+    }
 
-                        // Return buffer ptr as i64
-                        ctx.emit(Instruction::LocalGet(rand_buf));
-                    }
+    /// Body of `__tensor_add(a, b)` on v128 lanes.
+    fn emit_tensor_add_simd(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_elementwise_kernel(ctx, func_map, TensorOp::Add, true)
+    }
 
-                    // sys.exit(code?) → proc_exit, never returns
-                    "sys.exit" | "exit" | "intrinsic_exit" => {
-                        if args.is_empty() {
-                            ctx.emit(Instruction::I32Const(0)); // default exit code
-                        } else {
-                            Self::compile_expr(ctx, &args[0], func_map)?;
-                            ctx.emit(Instruction::I32WrapI64);
-                        }
-                        ctx.emit(Instruction::Call(WASI_PROC_EXIT_FUNC_IDX));
-                        // proc_exit never returns, but we need a value on stack for WASM validation
-                        ctx.emit(Instruction::Unreachable);
-                    }
+    /// Body of `__tensor_sub(a, b)` with scalar subtracts.
+    fn emit_tensor_sub(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_elementwise_kernel(ctx, func_map, TensorOp::Sub, false)
+    }
 
-                    // fd_close(fd) → close file descriptor
-                    "fd_close" | "sys.io.close" => {
-                        if args.len() != 1 {
-                            return Err(WasmCompileError {
-                                message: "fd_close requires 1 argument (fd)".to_string(),
-                                context: "fd_close".to_string(),
-                            });
-                        }
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::Call(WASI_FD_CLOSE_FUNC_IDX));
-                        ctx.emit(Instruction::I64ExtendI32U); // errno as i64
-                    }
+    /// Body of `__tensor_sub(a, b)` on v128 lanes.
+    fn emit_tensor_sub_simd(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_elementwise_kernel(ctx, func_map, TensorOp::Sub, true)
+    }
 
-                    // =============================================================
-                    // Tier 3: Ark Host Import Intrinsics
-                    // =============================================================
+    /// Body of `__tensor_mul_scalar(t, s)` with scalar multiplies.
+    fn emit_tensor_mul_scalar(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_elementwise_kernel(ctx, func_map, TensorOp::Scale, false)
+    }
 
-                    // --- Math unary: f64 reinterpret via i64 ---
-                    // math.sin(x), math.cos(x), math.tan(x)
-                    // math.asin(x), math.acos(x), math.atan(x), math.sqrt(x)
-                    "intrinsic_math_sin" | "math.sin" => {
-                        if args.len() != 1 {
+    /// Body of `__tensor_mul_scalar(t, s)` on v128 lanes.
+    fn emit_tensor_mul_scalar_simd(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_elementwise_kernel(ctx, func_map, TensorOp::Scale, true)
+    }
+
+    /// A new tensor of the shape of the tensor `a`, each element `op`
+    /// applied to `a`'s element and either the matching element of the
+    /// tensor `b`, which must have the same shape, or the scalar `s` (for
+    /// [`TensorOp::Scale`]). Wraps on overflow. With `simd`, two elements
+    /// at a time.
+    fn emit_elementwise_kernel(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+        op: TensorOp,
+        simd: bool,
+    ) -> Result<(), WasmCompileError> {
+        let by_scalar = matches!(op, TensorOp::Scale);
+        let scalar_lanes = (simd && by_scalar).then(|| ctx.reserve_v128_locals(1));
+        let a = ctx.scope.get_or_alloc("__tensor_a");
+        let b = ctx.scope.get_or_alloc("__tensor_b");
+        let a_shape = ctx.scope.get_or_alloc("__tensor_a_shape");
+        let b_shape = ctx.scope.get_or_alloc("__tensor_b_shape");
+        let out = ctx.scope.get_or_alloc("__tensor_out");
+        let shape = ctx.scope.get_or_alloc("__tensor_shape");
+        let len = ctx.scope.get_or_alloc("__tensor_len");
+        let end = ctx.scope.get_or_alloc("__tensor_end");
+        let offset = ctx.scope.get_or_alloc("__tensor_offset");
+        Self::emit_tensor_parts(ctx, 0, a, a_shape);
+        if !by_scalar {
+            Self::emit_tensor_parts(ctx, 1, b, b_shape);
+            // Same shape, and as many elements so neither is read past its end
+            Self::emit_load_word(ctx, a_shape, 0);
+            Self::emit_load_word(ctx, b_shape, 0);
+            ctx.emit(Instruction::I64Ne);
+            Self::emit_load_word(ctx, a, 0);
+            Self::emit_load_word(ctx, b, 0);
+            ctx.emit(Instruction::I64Ne);
+            ctx.emit(Instruction::I32Or);
+            Self::emit_trap_if(ctx);
+            Self::emit_load_word(ctx, a_shape, 0);
+            ctx.emit(Instruction::I64Const(3));
+            ctx.emit(Instruction::I64Shl);
+            ctx.emit(Instruction::LocalSet(end));
+            Self::emit_count_up(ctx, offset, end);
+            {
+                Self::emit_load_element(ctx, a_shape, offset);
+                Self::emit_load_element(ctx, b_shape, offset);
+                ctx.emit(Instruction::I64Ne);
+                Self::emit_trap_if(ctx);
+            }
+            Self::emit_count_step_end(ctx, offset, 8);
+        }
+
+        Self::emit_load_word(ctx, a, 0);
+        ctx.emit(Instruction::LocalSet(len));
+        Self::emit_new_list(ctx, func_map, len, out)?;
+        Self::emit_element_offset(ctx, len, end);
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::LocalSet(offset));
+
+        if simd {
+            let pairs_end = ctx.scope.get_or_alloc("__tensor_pairs_end");
+            ctx.emit(Instruction::LocalGet(end));
+            ctx.emit(Instruction::I64Const(-16));
+            ctx.emit(Instruction::I64And);
+            ctx.emit(Instruction::LocalSet(pairs_end));
+            if let Some(lanes) = scalar_lanes {
+                ctx.emit(Instruction::LocalGet(1));
+                ctx.emit(Instruction::I64x2Splat);
+                ctx.emit(Instruction::LocalSet(lanes));
+            }
+            Self::emit_count_from(ctx, offset, pairs_end);
+            {
+                Self::emit_element_base(ctx, out, offset);
+                Self::emit_load_lanes(ctx, a, offset);
+                match scalar_lanes {
+                    Some(lanes) => ctx.emit(Instruction::LocalGet(lanes)),
+                    None => Self::emit_load_lanes(ctx, b, offset),
+                }
+                ctx.emit(op.lanes());
+                ctx.emit(Instruction::V128Store(wasm_encoder::MemArg {
+                    offset: 8,
+                    align: 3,
+                    memory_index: 0,
+                }));
+            }
+            Self::emit_count_step_end(ctx, offset, 16);
+        }
+
+        Self::emit_count_from(ctx, offset, end);
+        {
+            Self::emit_element_base(ctx, out, offset);
+            Self::emit_load_element(ctx, a, offset);
+            if by_scalar {
+                ctx.emit(Instruction::LocalGet(1));
+            } else {
+                Self::emit_load_element(ctx, b, offset);
+            }
+            ctx.emit(op.scalar());
+            ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                offset: 8,
+                align: 3,
+                memory_index: 0,
+            }));
+        }
+        Self::emit_count_step_end(ctx, offset, 8);
+
+        Self::emit_copy_list(ctx, func_map, a_shape, shape)?;
+        Self::emit_new_tensor(ctx, func_map, out, shape)
+    }
+
+    /// Body of `__crypto_sha256(data)`.
+    fn emit_crypto_sha256(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_digest_hex(ctx, func_map, "crypto_sha256")
+    }
+
+    /// Body of `__crypto_keccak256(data)`.
+    fn emit_crypto_keccak256(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_digest_hex(ctx, func_map, "crypto_keccak256")
+    }
+
+    /// Body of `__crypto_blake3(data)`.
+    fn emit_crypto_blake3(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        Self::emit_digest_hex(ctx, func_map, "crypto_blake3")
+    }
+
+    /// The 64 lowercase hex digits, as a packed string, of the digest the
+    /// `ark_host` import `import` computes for the packed string in local 0,
+    /// like the interpreter's `sys.crypto.*`. A failed host call traps.
+    fn emit_digest_hex(
+        ctx: &mut FuncContext,
+        func_map: &HashMap<String, u32>,
+        import: &str,
+    ) -> Result<(), WasmCompileError> {
+        let out = ctx.scope.get_or_alloc("__digest_out");
+        let i = ctx.scope.get_or_alloc("__digest_i");
+        let end = ctx.scope.get_or_alloc("__digest_end");
+        let byte = ctx.scope.get_or_alloc("__digest_byte");
+        let nibble = ctx.scope.get_or_alloc("__digest_nibble");
+        ctx.emit(Instruction::I64Const(64));
+        ctx.emit(Instruction::Call(func_map["__alloc"]));
+        ctx.emit(Instruction::LocalSet(out));
+
+        // The raw digest lands in the upper half of the buffer. Expanding it
+        // front to back writes bytes 2i and 2i+1 after reading byte 32+i, so
+        // it never overwrites a byte still to be read.
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I64Const(32));
+        ctx.emit(Instruction::I64ShrU);
+        ctx.emit(Instruction::I32WrapI64); // data ptr
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I32WrapI64); // data len
+        ctx.emit(Instruction::LocalGet(out));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I32Const(32));
+        ctx.emit(Instruction::I32Add);
+        ctx.emit(Instruction::Call(Self::import_index(func_map, import)?));
+        Self::emit_trap_if(ctx);
+
+        ctx.emit(Instruction::I64Const(32));
+        ctx.emit(Instruction::LocalSet(end));
+        Self::emit_count_up(ctx, i, end);
+        {
+            ctx.emit(Instruction::LocalGet(out));
+            ctx.emit(Instruction::LocalGet(i));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I64Load8U(wasm_encoder::MemArg {
+                offset: 32,
+                align: 0,
+                memory_index: 0,
+            }));
+            ctx.emit(Instruction::LocalSet(byte));
+            for (high, offset) in [(true, 0), (false, 1)] {
+                ctx.emit(Instruction::LocalGet(out));
+                ctx.emit(Instruction::LocalGet(i));
+                ctx.emit(Instruction::I64Const(1));
+                ctx.emit(Instruction::I64Shl);
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::I32WrapI64);
+                ctx.emit(Instruction::LocalGet(byte));
+                if high {
+                    ctx.emit(Instruction::I64Const(4));
+                    ctx.emit(Instruction::I64ShrU);
+                } else {
+                    ctx.emit(Instruction::I64Const(0xF));
+                    ctx.emit(Instruction::I64And);
+                }
+                // '0' + n, plus the gap up to 'a' when n > 9
+                ctx.emit(Instruction::LocalTee(nibble));
+                ctx.emit(Instruction::I64Const(b'0' as i64));
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::LocalGet(nibble));
+                ctx.emit(Instruction::I64Const(9));
+                ctx.emit(Instruction::I64GtU);
+                ctx.emit(Instruction::I64ExtendI32U);
+                ctx.emit(Instruction::I64Const((b'a' - b'0' - 10) as i64));
+                ctx.emit(Instruction::I64Mul);
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::I64Store8(wasm_encoder::MemArg {
+                    offset,
+                    align: 0,
+                    memory_index: 0,
+                }));
+            }
+        }
+        Self::emit_count_up_end(ctx, i);
+
+        // out << 32 | 64
+        ctx.emit(Instruction::LocalGet(out));
+        ctx.emit(Instruction::I64Const(32));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::I64Const(64));
+        ctx.emit(Instruction::I64Or);
+        Ok(())
+    }
+
+    /// Register the heap allocator: `__alloc(size) -> ptr` and
+    /// `__free(ptr) -> 0`, both (i64) -> i64 like every Ark function.
+    ///
+    /// Every block carries its payload size in the word before the returned
+    /// pointer. `__free` pushes a block onto the free list for its size class
+    /// (the first payload word links to the next free block); `__alloc` takes
+    /// the first fitting block from that list, zeroed, and otherwise bumps
+    /// `__heap_ptr`, growing memory as needed. Blocks are neither split nor
+    /// coalesced.
+    fn register_allocator(&mut self) {
+        let alloc_type_idx = self.push_type(vec![ValType::I64], vec![ValType::I64]);
+
+        // __alloc(size)
+        let mut ctx = FuncContext::new(1); // 1 parameter: size
+        let aligned = ctx.scope.get_or_alloc("__alloc_aligned");
+        let head = ctx.scope.get_or_alloc("__alloc_head");
+        let prev = ctx.scope.get_or_alloc("__alloc_prev");
+        let block = ctx.scope.get_or_alloc("__alloc_block");
+        let end = ctx.scope.get_or_alloc("__alloc_end");
+
+        // aligned = align8(size), computed in i64
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I64Const(7));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::I64Const(!7));
+        ctx.emit(Instruction::I64And);
+        ctx.emit(Instruction::LocalSet(aligned));
+
+        // Bounds-check mode: trap if align8(size) is negative or does not
+        // fit in an i32. Zero is a valid (empty) allocation.
+        if self.bounds_checks {
+            ctx.emit(Instruction::LocalGet(aligned));
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::I64LtS);
+            ctx.emit(Instruction::LocalGet(aligned));
+            ctx.emit(Instruction::I64Const(i32::MAX as i64));
+            ctx.emit(Instruction::I64GtS);
+            ctx.emit(Instruction::I32Or);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            ctx.emit(Instruction::Unreachable);
+            ctx.emit(Instruction::End);
+        }
+
+        // Every payload can hold a free-list link: aligned = max(aligned, 8)
+        ctx.emit(Instruction::LocalGet(aligned));
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::LocalGet(aligned));
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::I64GtS);
+        ctx.emit(Instruction::Select);
+        ctx.emit(Instruction::LocalSet(aligned));
+
+        // First fit from the size class's free list
+        Self::emit_free_list_head(&mut ctx, aligned);
+        ctx.emit(Instruction::LocalSet(head));
+        ctx.emit(Instruction::LocalGet(head));
+        ctx.emit(Instruction::LocalSet(prev));
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            // block = *prev; the end of the list falls through to the bump
+            ctx.emit(Instruction::LocalGet(prev));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I64Load32U(wasm_encoder::MemArg {
+                offset: 0,
+                align: 2,
+                memory_index: 0,
+            }));
+            ctx.emit(Instruction::LocalSet(block));
+            ctx.emit(Instruction::LocalGet(block));
+            ctx.emit(Instruction::I64Eqz);
+            ctx.emit(Instruction::BrIf(1));
+
+            // size(block) >= aligned: unlink it, zero it and hand it out
+            Self::emit_block_size(&mut ctx, block);
+            ctx.emit(Instruction::LocalGet(aligned));
+            ctx.emit(Instruction::I64GeU);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            {
+                ctx.emit(Instruction::LocalGet(prev));
+                ctx.emit(Instruction::I32WrapI64);
+                ctx.emit(Instruction::LocalGet(block));
+                ctx.emit(Instruction::I32WrapI64);
+                ctx.emit(Instruction::I32Load(wasm_encoder::MemArg {
+                    offset: 0,
+                    align: 2,
+                    memory_index: 0,
+                }));
+                ctx.emit(Instruction::I32Store(wasm_encoder::MemArg {
+                    offset: 0,
+                    align: 2,
+                    memory_index: 0,
+                }));
+
+                ctx.emit(Instruction::LocalGet(block));
+                ctx.emit(Instruction::I32WrapI64);
+                ctx.emit(Instruction::I32Const(0));
+                Self::emit_block_size(&mut ctx, block);
+                ctx.emit(Instruction::I32WrapI64);
+                ctx.emit(Instruction::MemoryFill(0));
+
+                ctx.emit(Instruction::LocalGet(block));
+                ctx.emit(Instruction::Return);
+            }
+            ctx.emit(Instruction::End);
+
+            // The link lives in the payload's first word, so the block is
+            // also the next `prev`
+            ctx.emit(Instruction::LocalGet(block));
+            ctx.emit(Instruction::LocalSet(prev));
+            ctx.emit(Instruction::Br(0));
+        }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
+
+        // Bump: the header goes at __heap_ptr, the payload right after it
+        ctx.emit(Instruction::GlobalGet(0));
+        ctx.emit(Instruction::I64ExtendI32U);
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalSet(block));
+        ctx.emit(Instruction::LocalGet(block));
+        ctx.emit(Instruction::LocalGet(aligned));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalSet(end));
+
+        // Grow memory by enough pages to cover `end`; trap at the maximum
+        ctx.emit(Instruction::LocalGet(end));
+        ctx.emit(Instruction::MemorySize(0));
+        ctx.emit(Instruction::I64ExtendI32U);
+        ctx.emit(Instruction::I64Const(16));
+        ctx.emit(Instruction::I64Shl);
+        ctx.emit(Instruction::I64GtU);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(end));
+            ctx.emit(Instruction::MemorySize(0));
+            ctx.emit(Instruction::I64ExtendI32U);
+            ctx.emit(Instruction::I64Const(16));
+            ctx.emit(Instruction::I64Shl);
+            ctx.emit(Instruction::I64Sub);
+            ctx.emit(Instruction::I64Const(0xFFFF));
+            ctx.emit(Instruction::I64Add);
+            ctx.emit(Instruction::I64Const(16));
+            ctx.emit(Instruction::I64ShrU);
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::MemoryGrow(0));
+            ctx.emit(Instruction::I32Const(-1));
+            ctx.emit(Instruction::I32Eq);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            ctx.emit(Instruction::Unreachable);
+            ctx.emit(Instruction::End);
+        }
+        ctx.emit(Instruction::End);
+
+        // Header: the payload size, in the word before the payload
+        ctx.emit(Instruction::LocalGet(block));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I32Const(8));
+        ctx.emit(Instruction::I32Sub);
+        ctx.emit(Instruction::LocalGet(aligned));
+        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: 0,
+        }));
+        ctx.emit(Instruction::LocalGet(end));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::GlobalSet(0)); // update __heap_ptr
+
+        ctx.emit(Instruction::LocalGet(block));
+        ctx.emit(Instruction::End);
+
+        let alloc_func_idx = self.import_count + self.functions.len() as u32;
+        self.func_index_map
+            .insert("__alloc".to_string(), alloc_func_idx);
+        self.functions
+            .push((alloc_type_idx, "__alloc".to_string(), ctx));
+
+        // __free(ptr)
+        let mut ctx = FuncContext::new(1); // 1 parameter: ptr
+        let head = ctx.scope.get_or_alloc("__free_head");
+        let size = ctx.scope.get_or_alloc("__free_size");
+
+        // Freeing null is a no-op
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I64Eqz);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::Return);
+        ctx.emit(Instruction::End);
+
+        // Bounds-check mode: trap on a pointer that cannot have come from
+        // __alloc (misaligned, or outside the allocated heap range)
+        if self.bounds_checks {
+            ctx.emit(Instruction::LocalGet(0));
+            ctx.emit(Instruction::I64Const(16));
+            ctx.emit(Instruction::I64LtU);
+            ctx.emit(Instruction::LocalGet(0));
+            ctx.emit(Instruction::GlobalGet(0));
+            ctx.emit(Instruction::I64ExtendI32U);
+            ctx.emit(Instruction::I64GeU);
+            ctx.emit(Instruction::I32Or);
+            ctx.emit(Instruction::LocalGet(0));
+            ctx.emit(Instruction::I64Const(7));
+            ctx.emit(Instruction::I64And);
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::I64Ne);
+            ctx.emit(Instruction::I32Or);
+            ctx.emit(Instruction::If(BlockType::Empty));
+            ctx.emit(Instruction::Unreachable);
+            ctx.emit(Instruction::End);
+        }
+
+        Self::emit_block_size(&mut ctx, 0);
+        ctx.emit(Instruction::LocalSet(size));
+        Self::emit_free_list_head(&mut ctx, size);
+        ctx.emit(Instruction::LocalSet(head));
+
+        // *ptr = *head; *head = ptr
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::LocalGet(head));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I32Load(wasm_encoder::MemArg {
+            offset: 0,
+            align: 2,
+            memory_index: 0,
+        }));
+        ctx.emit(Instruction::I32Store(wasm_encoder::MemArg {
+            offset: 0,
+            align: 2,
+            memory_index: 0,
+        }));
+        ctx.emit(Instruction::LocalGet(head));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::LocalGet(0));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I32Store(wasm_encoder::MemArg {
+            offset: 0,
+            align: 2,
+            memory_index: 0,
+        }));
+
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::End);
+
+        let free_func_idx = self.import_count + self.functions.len() as u32;
+        self.func_index_map
+            .insert("__free".to_string(), free_func_idx);
+        self.functions
+            .push((alloc_type_idx, "__free".to_string(), ctx));
+    }
+
+    /// Push the address of the free-list head for payloads of `size_local`
+    /// bytes (an 8-aligned i64 of at least 8).
+    fn emit_free_list_head(ctx: &mut FuncContext, size_local: u32) {
+        // FREE_LIST_BASE + 4 * (size / 8 - 1)
+        ctx.emit(Instruction::LocalGet(size_local));
+        ctx.emit(Instruction::I64Const(3));
+        ctx.emit(Instruction::I64ShrU);
+        ctx.emit(Instruction::I64Const(1));
+        ctx.emit(Instruction::I64Sub);
+        ctx.emit(Instruction::I64Const(4));
+        ctx.emit(Instruction::I64Mul);
+        ctx.emit(Instruction::I64Const(FREE_LIST_BASE as i64));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::I64Const(FREE_LIST_LARGE as i64));
+        ctx.emit(Instruction::LocalGet(size_local));
+        ctx.emit(Instruction::I64Const(FREE_LIST_MAX_SMALL));
+        ctx.emit(Instruction::I64LeU);
+        ctx.emit(Instruction::Select);
+    }
+
+    /// Push the payload size recorded in the header of the block whose
+    /// payload pointer is in `ptr_local`.
+    fn emit_block_size(ctx: &mut FuncContext, ptr_local: u32) {
+        ctx.emit(Instruction::LocalGet(ptr_local));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I32Const(8));
+        ctx.emit(Instruction::I32Sub);
+        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: 0,
+        }));
+    }
+
+    /// Emit the shared `print` helpers, `__print_i64(value)` (itoa + newline)
+    /// and `__print_str(packed)`, plus `__print_f64(bits)` when the program
+    /// has floats, so each print site is a single call instead of an inlined
+    /// copy of the conversion and fd_write.
+    fn register_print_helpers(&mut self) {
+        let helper_ty = self.push_type(vec![ValType::I64], vec![]);
+        let fd_write = self.func_index_map[&format!("{}fd_write", IMPORT_KEY_PREFIX)];
+        let mut helpers: Vec<(&str, fn(&mut FuncContext, u32))> = vec![
+            ("__print_i64", Self::emit_print_i64),
+            ("__print_str", Self::emit_print_str),
+        ];
+        if self.uses_floats {
+            helpers.push(("__print_f64", Self::emit_print_f64));
+        }
+        for (name, emit_body) in helpers {
+            let mut ctx = FuncContext::new(1); // 1 parameter: the value
+            emit_body(&mut ctx, fd_write);
+            ctx.emit(Instruction::End);
+
+            let func_idx = self.import_count + self.functions.len() as u32;
+            self.func_index_map.insert(name.to_string(), func_idx);
+            self.functions.push((helper_ty, name.to_string(), ctx));
+        }
+    }
+
+    /// Append a function type and return its index.
+    fn push_type(&mut self, params: Vec<ValType>, results: Vec<ValType>) -> u32 {
+        self.types.push((params, results));
+        (self.types.len() - 1) as u32
+    }
+
+    /// Append an import and publish its function index under its name.
+    ///
+    /// Must run before any internal function is registered, since imports
+    /// occupy the low end of the function index space.
+    fn declare_import(&mut self, module: &str, name: &str, type_idx: u32) -> u32 {
+        let idx = self.imports.len() as u32;
+        self.imports.push(WasmImport {
+            module: module.to_string(),
+            name: name.to_string(),
+            type_idx,
+        });
+        // First declaration wins, so embedder imports can't rebind a built-in.
+        if !self.import_map.contains_key(name) {
+            self.import_map.insert(name.to_string(), idx);
+            self.func_index_map
+                .insert(format!("{}{}", IMPORT_KEY_PREFIX, name), idx);
+        }
+        idx
+    }
+
+    /// Function index of the import registered under `name`.
+    fn import_index(func_map: &HashMap<String, u32>, name: &str) -> Result<u32, WasmCompileError> {
+        func_map
+            .get(&format!("{}{}", IMPORT_KEY_PREFIX, name))
+            .copied()
+            .ok_or_else(|| WasmCompileError {
+                message: format!("Import '{}' is not registered", name),
+                context: "import lookup".to_string(),
+                span: None,
+            })
+    }
+
+    /// Emit one `__host_<intrinsic>` function per embedder import, adapting
+    /// the i64 calling convention to the import's signature.
+    fn register_host_import_shims(&mut self, import_indices: &[u32]) {
+        let imports = self.host_imports.clone();
+        for (import, &import_idx) in imports.iter().zip(import_indices) {
+            let param_count = import.params.len() as u32;
+
+            let shim_type_idx = self.types.len() as u32;
+            self.types
+                .push((vec![ValType::I64; import.params.len()], vec![ValType::I64]));
+
+            let mut ctx = FuncContext::new(param_count);
+            for (i, param) in import.params.iter().enumerate() {
+                ctx.emit(Instruction::LocalGet(i as u32));
+                match param {
+                    ValType::I32 => ctx.emit(Instruction::I32WrapI64),
+                    ValType::F32 => {
+                        ctx.emit(Instruction::F64ReinterpretI64);
+                        ctx.emit(Instruction::F32DemoteF64);
+                    }
+                    ValType::F64 => ctx.emit(Instruction::F64ReinterpretI64),
+                    _ => {}
+                }
+            }
+            ctx.emit(Instruction::Call(import_idx));
+            match import.results.first() {
+                None => ctx.emit(Instruction::I64Const(0)),
+                Some(ValType::I32) => ctx.emit(Instruction::I64ExtendI32S),
+                Some(ValType::F32) => {
+                    ctx.emit(Instruction::F64PromoteF32);
+                    ctx.emit(Instruction::I64ReinterpretF64);
+                }
+                Some(ValType::F64) => ctx.emit(Instruction::I64ReinterpretF64),
+                Some(_) => {}
+            }
+            ctx.emit(Instruction::End);
+
+            let shim_name = format!("__host_{}", import.intrinsic);
+            let func_idx = self.import_count + self.functions.len() as u32;
+            self.func_index_map.insert(shim_name.clone(), func_idx);
+            self.func_index_map
+                .insert(import.intrinsic.clone(), func_idx);
+            self.functions.push((shim_type_idx, shim_name, ctx));
+        }
+    }
+
+    // =========================================================================
+    // Function Collection (Forward Declarations)
+    // =========================================================================
+
+    fn collect_functions(&mut self, node: &ArkNode) -> Result<(), WasmCompileError> {
+        match node {
+            ArkNode::Function(func_def) => {
+                self.register_function(func_def)?;
+            }
+            ArkNode::Statement(Statement::Function(func_def)) => {
+                self.register_function(func_def)?;
+            }
+            ArkNode::Statement(Statement::Block(stmts)) => {
+                for stmt in stmts {
+                    if let Statement::Function(func_def) = stmt {
+                        self.register_function(func_def)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // =========================================================================
+    // Lambda Collection (Pre-scan for lambda lifting)
+    // =========================================================================
+
+    /// Walk the AST to find all `Expression::Lambda` nodes and register them
+    /// as synthetic top-level functions (`__lambda_0`, `__lambda_1`, etc.).
+    /// This enables call_indirect dispatch for higher-order functions.
+    ///
+    /// Each lambda is also registered under a content-hash alias (see
+    /// [`lambda_key`]) so that `compile_expr` can resolve a lambda expression
+    /// to its own slot regardless of where it is encountered.
+    ///
+    /// A lifted lambda takes its closure record as a hidden first parameter
+    /// (`env`) and loads the variables it captures from it on entry. It
+    /// captures every name it references that is bound somewhere in the
+    /// program and is not one of its parameters or a function, so the list
+    /// depends only on the lambda itself and identical lambdas still share
+    /// a body. Captures are by value, taken when the lambda is evaluated.
+    fn collect_lambdas(&mut self, node: &ArkNode) -> Result<(), WasmCompileError> {
+        let bound = Self::bound_names(node);
+        for lambda in Self::lifted_lambdas(node) {
+            let Expression::Lambda { params, body } = &lambda else {
+                continue;
+            };
+            let lambda_name = format!("__lambda_{}", self.lambda_counter);
+            self.lambda_counter += 1;
+
+            // (env, params...) -> i64
+            let type_idx = self.indirect_type(params.len() + 1);
+
+            let func_idx = self.import_count + self.functions.len() as u32;
+            self.func_index_map.insert(lambda_name.clone(), func_idx);
+            // Structurally identical lambdas compile to identical bodies, so
+            // the first registration wins.
+            let key = lambda_key(&lambda)?;
+            self.func_index_map.entry(key.clone()).or_insert(func_idx);
+            let captures = Self::referenced_names(body)
+                .into_iter()
+                .filter(|name| {
+                    bound.contains(name)
+                        && !params.contains(name)
+                        && !self.func_index_map.contains_key(name)
+                })
+                .collect();
+            self.closure_captures.insert(key, captures);
+
+            let ctx = FuncContext::new(params.len() as u32 + 1);
+            self.functions.push((type_idx, lambda_name, ctx));
+        }
+        Ok(())
+    }
+
+    /// Type index of `(i64 × param_count) -> i64`, registered on first use.
+    /// Shared so that call_indirect sites and lifted lambdas agree on it.
+    fn indirect_type(&mut self, param_count: usize) -> u32 {
+        if let Some(&type_idx) = self.indirect_types.get(&param_count) {
+            return type_idx;
+        }
+        let type_idx = self.types.len() as u32;
+        self.types
+            .push((vec![ValType::I64; param_count], vec![ValType::I64]));
+        self.indirect_types.insert(param_count, type_idx);
+        type_idx
+    }
+
+    /// Register the types a call through a variable may need: one for a
+    /// plain function taking its arguments, one for a closure that also
+    /// takes its `env`. Calls to functions and intrinsics are skipped.
+    fn collect_indirect_call_types(&mut self, node: &ArkNode) {
+        struct CallCollector {
+            calls: Vec<(String, usize)>,
+        }
+
+        impl Visitor for CallCollector {
+            fn visit_expression(&mut self, expr: &Expression) {
+                if let Expression::Call {
+                    function_hash,
+                    args,
+                } = expr
+                {
+                    self.calls.push((function_hash.clone(), args.len()));
+                }
+                walk_expression(self, expr);
+            }
+        }
+
+        let bound = Self::bound_names(node);
+        let mut collector = CallCollector { calls: Vec::new() };
+        collector.visit_node(node);
+        for (name, arity) in collector.calls {
+            if bound.contains(&name) && !self.func_index_map.contains_key(&name) {
+                self.indirect_type(arity);
+                self.indirect_type(arity + 1);
+            }
+        }
+    }
+
+    /// Fresh context for compiling a function body with the module-wide
+    /// settings applied.
+    fn body_context(&self, param_count: u32) -> FuncContext {
+        let mut ctx = FuncContext::new(param_count);
+        ctx.string_offset = self.data_offset;
+        ctx.bounds_checks = self.bounds_checks;
+        ctx.log_level = self.log_level;
+        ctx.struct_layouts = self.struct_layouts.clone();
+        ctx.enum_layouts = self.enum_layouts.clone();
+        ctx.float_signatures = self.float_signatures.clone();
+        ctx.declared_params = self.declared_params.clone();
+        ctx.closure_captures = self.closure_captures.clone();
+        ctx.indirect_types = self.indirect_types.clone();
+        ctx.fuel_global = self.fuel_global();
+        ctx
+    }
+
+    /// Index of the fuel global: after `__heap_ptr` and the exported
+    /// bindings. `None` when the module is not metered.
+    fn fuel_global(&self) -> Option<u32> {
+        self.options
+            .fuel
+            .map(|_| 1 + self.exported_globals.len() as u32)
+    }
+
+    /// Record which functions and host imports take or return floats, and
+    /// whether any float appears in the program at all.
+    fn collect_float_signatures(&mut self, node: &ArkNode) {
+        struct FloatCollector {
+            signatures: HashMap<String, (Vec<bool>, bool)>,
+            found: bool,
+        }
+
+        impl Visitor for FloatCollector {
+            fn visit_function(&mut self, func: &FunctionDef) {
+                let params: Vec<bool> = func
+                    .inputs
+                    .iter()
+                    .map(|(_, ty)| *ty == ArkType::Float)
+                    .collect();
+                let result = func.output == ArkType::Float;
+                if result || params.contains(&true) {
+                    self.signatures.insert(func.name.clone(), (params, result));
+                }
+                walk_function(self, func);
+            }
+
+            fn visit_expression(&mut self, expr: &Expression) {
+                match expr {
+                    Expression::Literal(s) if float_literal(s).is_some() => self.found = true,
+                    Expression::Call { function_hash, .. } if is_float_math(function_hash) => {
+                        self.found = true
+                    }
+                    _ => {}
+                }
+                walk_expression(self, expr);
+            }
+        }
+
+        let mut collector = FloatCollector {
+            signatures: HashMap::new(),
+            found: false,
+        };
+        collector.visit_node(node);
+
+        let is_float = |t: &ValType| matches!(t, ValType::F32 | ValType::F64);
+        for import in &self.host_imports {
+            let params: Vec<bool> = import.params.iter().map(is_float).collect();
+            let result = import.results.iter().any(is_float);
+            if result || params.contains(&true) {
+                collector
+                    .signatures
+                    .insert(import.intrinsic.clone(), (params, result));
+            }
+        }
+
+        self.uses_floats = collector.found || !collector.signatures.is_empty();
+        self.float_signatures = collector.signatures;
+    }
+
+    /// Top-level `#[export] let` bindings in source order. Integer and
+    /// boolean literals (after constant folding) become constant
+    /// initializers; any other value is captured at runtime.
+    fn exported_lets(node: &ArkNode) -> Vec<(String, Option<i64>)> {
+        let stmts = match node {
+            ArkNode::Statement(Statement::Block(stmts)) => stmts,
+            _ => return Vec::new(),
+        };
+        stmts
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Statement::Export(inner) => match &**inner {
+                    Statement::Let { name, value, .. } => {
+                        let constant = match value {
+                            Expression::Integer(n) => Some(*n),
+                            Expression::Literal(s) if s == "true" || s == "false" => {
+                                Some((s == "true") as i64)
+                            }
+                            _ => None,
+                        };
+                        Some((name.clone(), constant))
+                    }
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Field order of every struct declared in the AST.
+    fn declared_struct_layouts(node: &ArkNode) -> HashMap<String, Vec<String>> {
+        struct LayoutCollector {
+            found: HashMap<String, Vec<String>>,
+        }
+
+        impl Visitor for LayoutCollector {
+            fn visit_statement(&mut self, stmt: &Statement) {
+                if let Statement::StructDecl(decl) = stmt {
+                    let fields = decl.fields.iter().map(|(name, _)| name.clone()).collect();
+                    self.found.insert(decl.name.clone(), fields);
+                }
+                walk_statement(self, stmt);
+            }
+        }
+
+        let mut collector = LayoutCollector {
+            found: HashMap::new(),
+        };
+        collector.visit_node(node);
+        collector.found
+    }
+
+    /// Variants (name, payload arity) of every enum declared in the AST, in
+    /// declaration order: a variant's position is its tag. The built-in
+    /// `Result` and `Option` are included unless the program redeclares them.
+    fn declared_enum_layouts(node: &ArkNode) -> HashMap<String, Vec<(String, usize)>> {
+        struct LayoutCollector {
+            found: HashMap<String, Vec<(String, usize)>>,
+        }
+
+        impl Visitor for LayoutCollector {
+            fn visit_statement(&mut self, stmt: &Statement) {
+                if let Statement::EnumDecl(decl) = stmt {
+                    let variants = decl
+                        .variants
+                        .iter()
+                        .map(|v| (v.name.clone(), v.fields.len()))
+                        .collect();
+                    self.found.insert(decl.name.clone(), variants);
+                }
+                walk_statement(self, stmt);
+            }
+        }
+
+        let mut collector = LayoutCollector {
+            found: HashMap::new(),
+        };
+        for decl in EnumDecl::builtins() {
+            collector.visit_statement(&Statement::EnumDecl(decl));
+        }
+        collector.visit_node(node);
+        collector.found
+    }
+
+    /// Names of all functions and intrinsics called anywhere in the AST.
+    fn called_names(node: &ArkNode) -> HashSet<String> {
+        struct CallCollector {
+            found: HashSet<String>,
+        }
+
+        impl Visitor for CallCollector {
+            fn visit_expression(&mut self, expr: &Expression) {
+                match expr {
+                    Expression::Call { function_hash, .. } => {
+                        self.found.insert(function_hash.clone());
+                    }
+                    // Lowered to `__map_set` calls
+                    Expression::MapInit { .. } => {
+                        self.found.insert("map.set".to_string());
+                    }
+                    _ => {}
+                }
+                walk_expression(self, expr);
+            }
+        }
+
+        let mut collector = CallCollector {
+            found: HashSet::new(),
+        };
+        collector.visit_node(node);
+        collector.found
+    }
+
+    /// All lambda expressions in the AST, in canonical `ast::Visitor` order.
+    /// Both registration and body compilation go through this so that
+    /// `__lambda_N` always names the N-th lambda returned here.
+    fn lifted_lambdas(node: &ArkNode) -> Vec<Expression> {
+        struct LambdaCollector {
+            found: Vec<Expression>,
+        }
+
+        impl Visitor for LambdaCollector {
+            fn visit_expression(&mut self, expr: &Expression) {
+                if matches!(expr, Expression::Lambda { .. }) {
+                    self.found.push(expr.clone());
+                }
+                walk_expression(self, expr);
+            }
+        }
+
+        let mut collector = LambdaCollector { found: Vec::new() };
+        collector.visit_node(node);
+        collector.found
+    }
+
+    /// Every name bound anywhere in the program: `let`s, destructuring,
+    /// `for` variables, match bindings and function or lambda parameters.
+    fn bound_names(node: &ArkNode) -> HashSet<String> {
+        struct BindingCollector {
+            names: HashSet<String>,
+        }
+
+        impl Visitor for BindingCollector {
+            fn visit_function(&mut self, func: &FunctionDef) {
+                self.names
+                    .extend(func.inputs.iter().map(|(name, _)| name.clone()));
+                walk_function(self, func);
+            }
+
+            fn visit_statement(&mut self, stmt: &Statement) {
+                match stmt {
+                    Statement::Let { name, .. } | Statement::For { variable: name, .. } => {
+                        self.names.insert(name.clone());
+                    }
+                    Statement::LetDestructure { names, rest, .. } => {
+                        self.names.extend(names.iter().chain(rest).cloned());
+                    }
+                    _ => {}
+                }
+                walk_statement(self, stmt);
+            }
+
+            fn visit_expression(&mut self, expr: &Expression) {
+                match expr {
+                    Expression::Lambda { params, .. } => {
+                        self.names.extend(params.iter().cloned());
+                    }
+                    Expression::Match { arms, .. } => {
+                        for (pattern, _) in arms {
+                            match pattern {
+                                Pattern::Variable(name) => {
+                                    self.names.insert(name.clone());
+                                }
+                                Pattern::EnumVariant { bindings, .. } => {
+                                    self.names.extend(bindings.iter().cloned());
+                                }
+                                Pattern::Literal(_) | Pattern::Wildcard => {}
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                walk_expression(self, expr);
+            }
+        }
+
+        let mut collector = BindingCollector {
+            names: HashSet::new(),
+        };
+        collector.visit_node(node);
+        collector.names
+    }
+
+    /// Names a lambda body reads, calls or mutates, in first-use order.
+    /// Nested lambdas count: their captures pass through this one.
+    fn referenced_names(body: &[Statement]) -> Vec<String> {
+        struct ReferenceCollector {
+            names: Vec<String>,
+        }
+
+        impl ReferenceCollector {
+            fn add(&mut self, name: &str) {
+                if !self.names.iter().any(|n| n == name) {
+                    self.names.push(name.to_string());
+                }
+            }
+        }
+
+        impl Visitor for ReferenceCollector {
+            fn visit_statement(&mut self, stmt: &Statement) {
+                if let Statement::SetField { obj_name, .. } = stmt {
+                    self.add(obj_name);
+                }
+                walk_statement(self, stmt);
+            }
+
+            fn visit_expression(&mut self, expr: &Expression) {
+                match expr {
+                    Expression::Variable(name) => self.add(name),
+                    Expression::Call { function_hash, .. } => self.add(function_hash),
+                    _ => {}
+                }
+                walk_expression(self, expr);
+            }
+        }
+
+        let mut collector = ReferenceCollector { names: Vec::new() };
+        for stmt in body {
+            collector.visit_statement(stmt);
+        }
+        collector.names
+    }
+
+    // =========================================================================
+    // Lambda Body Compilation
+    // =========================================================================
+
+    /// Compile the bodies of all registered lambda functions.
+    /// Re-runs the canonical lambda traversal and compiles the N-th lambda
+    /// into the `__lambda_N` slot reserved by `collect_lambdas`.
+    fn compile_lambda_bodies(&mut self, node: &ArkNode) -> Result<(), WasmCompileError> {
+        let func_index_map = self.func_index_map.clone();
+
+        for (idx, lambda) in Self::lifted_lambdas(node).iter().enumerate() {
+            let Expression::Lambda { params, body } = lambda else {
+                continue;
+            };
+            let lambda_name = format!("__lambda_{}", idx);
+            if let Some(&func_idx) = func_index_map.get(&lambda_name) {
+                // Find which slot in self.functions corresponds to this func_idx
+                let slot = (func_idx - self.import_count) as usize;
+                if slot < self.functions.len() {
+                    // Local 0 is the closure record (env)
+                    let param_count = params.len() as u32 + 1;
+                    let mut ctx = self.body_context(param_count);
+
+                    // Register parameter names as locals
+                    for (j, name) in params.iter().enumerate() {
+                        ctx.scope.locals.insert(name.clone(), j as u32 + 1);
+                    }
+
+                    // Load captured variables out of env
+                    let captures = self.closure_captures[&lambda_key(lambda)?].clone();
+                    for (k, name) in captures.iter().enumerate() {
+                        let local = ctx.scope.get_or_alloc(name);
+                        ctx.emit(Instruction::LocalGet(0));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                            offset: (16 + 8 * k) as u64,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+                        ctx.emit(Instruction::LocalSet(local));
+                    }
+
+                    ctx.spend_fuel();
+
+                    // Compile each body statement
+                    let body_len = body.len();
+                    if body_len == 0 {
+                        ctx.emit(Instruction::I64Const(0));
+                    } else {
+                        for (i, stmt) in body.iter().enumerate() {
+                            let is_last = i == body_len - 1;
+                            Self::compile_stmt(&mut ctx, stmt, is_last, &func_index_map)?;
+                        }
+                    }
+
+                    // Ensure End opcode
+                    ctx.emit(Instruction::End);
+
+                    // Place compiled context in the right function slot
+                    self.data_offset = ctx.string_offset;
+                    self.functions[slot].2 = ctx;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Declare `func_def` with the WASM signature of its annotations: a
+    /// `Float` parameter or result is an f64, anything else an i64. A
+    /// function with an f64 in its signature also gets an i64 adapter,
+    /// `__i64_<name>`, which stands in for it wherever it is used as a value
+    /// and called through the table.
+    fn register_function(&mut self, func_def: &FunctionDef) -> Result<(), WasmCompileError> {
+        let param_types: Vec<ValType> = func_def
+            .inputs
+            .iter()
+            .map(|(_, ty)| val_type_of(ty))
+            .collect();
+        let return_type = val_type_of(&func_def.output);
+        let typed = return_type != ValType::I64 || param_types.contains(&ValType::F64);
+
+        let type_idx = self.types.len() as u32;
+        self.types.push((param_types.clone(), vec![return_type]));
+
+        let func_idx = self.import_count + self.functions.len() as u32;
+        self.func_index_map.insert(func_def.name.clone(), func_idx);
+        self.declared_params
+            .insert(func_def.name.clone(), func_def.inputs.clone());
+
+        // Create empty context — will be filled during compile phase
+        let ctx = FuncContext::new(func_def.inputs.len() as u32);
+        self.functions.push((type_idx, func_def.name.clone(), ctx));
+
+        if typed {
+            self.register_i64_adapter(&func_def.name, func_idx, &param_types, return_type);
+        }
+
+        // Store function attributes for selective export decisions
+        if !func_def.attributes.is_empty() {
+            self.func_attributes
+                .insert(func_def.name.clone(), func_def.attributes.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Emit `__i64_<name>`, which takes and returns the i64 convention and
+    /// calls the typed function `func_idx`, reinterpreting float bits.
+    fn register_i64_adapter(
+        &mut self,
+        name: &str,
+        func_idx: u32,
+        param_types: &[ValType],
+        return_type: ValType,
+    ) {
+        let type_idx = self.indirect_type(param_types.len());
+        let mut ctx = FuncContext::new(param_types.len() as u32);
+        for (i, ty) in param_types.iter().enumerate() {
+            ctx.emit(Instruction::LocalGet(i as u32));
+            if *ty == ValType::F64 {
+                ctx.emit(Instruction::F64ReinterpretI64);
+            }
+        }
+        ctx.emit(Instruction::Call(func_idx));
+        if return_type == ValType::F64 {
+            ctx.emit(Instruction::I64ReinterpretF64);
+        }
+        ctx.emit(Instruction::End);
+
+        let adapter_name = format!("__i64_{}", name);
+        let adapter_idx = self.import_count + self.functions.len() as u32;
+        self.func_index_map
+            .insert(adapter_name.clone(), adapter_idx);
+        self.functions.push((type_idx, adapter_name, ctx));
+    }
+
+    // =========================================================================
+    // Selective Export Logic
+    // =========================================================================
+
+    /// Determine if a function should be exported in the WASM binary.
+    /// Returns true if the function has the `#[export]` attribute
+    /// or is a system function (_start, main, __alloc, __free).
+    fn should_export(&self, name: &str) -> bool {
+        // System functions are always exported
+        if matches!(name, "_start" | "main" | "__alloc" | "__free") {
+            return true;
+        }
+        // Check if function has #[export] attribute
+        if let Some(attrs) = self.func_attributes.get(name) {
+            return attrs.iter().any(|a| a == "export");
+        }
+        false
+    }
+
+    // =========================================================================
+    // Function Body Compilation
+    // =========================================================================
+
+    fn compile_collected_functions(
+        &mut self,
+        node: &ArkNode,
+        mut cache: Option<&mut CompileCache>,
+    ) -> Result<(), WasmCompileError> {
+        // We need to build contexts for each registered function
+        let func_defs = self.extract_function_defs(node);
+        let func_index_map = self.func_index_map.clone();
+        let module_key = match cache {
+            Some(_) => self.cache_module_key()?,
+            None => String::new(),
+        };
+
+        for func_def in &func_defs {
+            // Internal functions (__alloc, host shims) precede user code, so
+            // resolve the slot through the index map.
+            let slot = (func_index_map[&func_def.name] - self.import_count) as usize;
+            let start_offset = self.data_offset;
+            if let Some(span) = &func_def.body.span {
+                self.func_spans.insert(func_def.name.clone(), span.clone());
+            }
+
+            let cache_key = match cache {
+                Some(_) => {
+                    let hash = calculate_hash(func_def).map_err(|e| WasmCompileError {
+                        message: format!("failed to hash function: {}", e),
+                        context: func_def.name.clone(),
+                        span: None,
+                    })?;
+                    format!("{}:{}", module_key, hash)
+                }
+                None => String::new(),
+            };
+            if let Some(ctx) = cache
+                .as_deref_mut()
+                .and_then(|cache| cache.get(&cache_key, start_offset))
+            {
+                self.data_offset = ctx.string_offset;
+                self.functions[slot].2 = ctx;
+                continue;
+            }
+
+            let param_count = func_def.inputs.len() as u32;
+            let mut ctx = self.body_context(param_count);
+
+            // Register parameter names as locals. A declared Float, String or
+            // struct type seeds the static kind/shape; it is recorded before
+            // the name is bound so that it counts as the first binding. An
+            // f64 parameter is copied into an i64 local holding its bits,
+            // the representation the body works with.
+            for (j, (name, ty)) in func_def.inputs.iter().enumerate() {
+                match ty {
+                    ArkType::Float => {
+                        ctx.scope.record_kind(name, Some("float"));
+                        let local = ctx.scope.get_or_alloc(name);
+                        ctx.emit(Instruction::LocalGet(j as u32));
+                        ctx.emit(Instruction::I64ReinterpretF64);
+                        ctx.emit(Instruction::LocalSet(local));
+                        ctx.param_locals[j] = local;
+                        continue;
+                    }
+                    ArkType::String => ctx.scope.record_kind(name, Some("string")),
+                    ArkType::Struct(struct_name, _) => {
+                        if let Some(layout) = ctx.struct_layouts.get(struct_name).cloned() {
+                            ctx.scope.record_kind(name, Some("struct"));
+                            ctx.scope.record_shape(name, Some(layout));
+                        }
+                    }
+                    _ => {}
+                }
+                ctx.scope.locals.insert(name.clone(), j as u32);
+            }
+            ctx.returns_float = func_def.output == ArkType::Float;
+
+            // A self-tail-recursive body runs in a loop that its tail calls
+            // branch back to after rebinding the parameters
+            let tail_recursive = Self::has_self_tail_call(func_def);
+            if tail_recursive {
+                ctx.emit(Instruction::Loop(BlockType::Result(ValType::I64)));
+                ctx.tail_loop = Some((func_def.name.clone(), ctx.block_depth));
+            }
+            // Inside the loop, so tail calls pay too
+            ctx.spend_fuel();
+
+            // Compile the function body
+            Self::compile_mast_node(&mut ctx, &func_def.body, &func_index_map)
+                .map_err(|e| e.within(&func_def.body.span))?;
+            if tail_recursive {
+                ctx.emit(Instruction::End);
+            }
+            if ctx.returns_float {
+                ctx.emit(Instruction::F64ReinterpretI64);
+            }
+
+            // Ensure we return something
+            ctx.emit(Instruction::End);
+
+            if let Some(cache) = cache.as_deref_mut() {
+                cache.insert(cache_key, start_offset, &ctx);
+            }
+            self.data_offset = ctx.string_offset;
+            self.functions[slot].2 = ctx;
+        }
+
+        Ok(())
+    }
+
+    /// Whether `func` contains `return func(...)` with one argument per
+    /// parameter anywhere in its body.
+    fn has_self_tail_call(func: &FunctionDef) -> bool {
+        struct TailCallFinder<'a> {
+            func: &'a FunctionDef,
+            found: bool,
+        }
+
+        impl Visitor for TailCallFinder<'_> {
+            fn visit_statement(&mut self, stmt: &Statement) {
+                if let Statement::Return(Expression::Call {
+                    function_hash,
+                    args,
+                }) = stmt
+                {
+                    self.found |=
+                        *function_hash == self.func.name && args.len() == self.func.inputs.len();
+                }
+                walk_statement(self, stmt);
+            }
+        }
+
+        let mut finder = TailCallFinder { func, found: false };
+        finder.visit_node(&func.body.content);
+        finder.found
+    }
+
+    /// Hash of everything outside a function's own AST that its compiled
+    /// body depends on. Part of every [`CompileCache`] key.
+    fn cache_module_key(&self) -> Result<String, WasmCompileError> {
+        let context = serde_json::json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "func_index_map": self.func_index_map,
+            "struct_layouts": self.struct_layouts,
+            "enum_layouts": self.enum_layouts,
+            "float_signatures": self.float_signatures,
+            "declared_params": self.declared_params,
+            "closure_captures": self.closure_captures,
+            "indirect_types": self.indirect_types,
+            "bounds_checks": self.bounds_checks,
+            "log_level": self.log_level,
+            "fuel_global": self.fuel_global(),
+        });
+        calculate_hash(&context).map_err(|e| WasmCompileError {
+            message: format!("failed to hash module context: {}", e),
+            context: "compile cache".to_string(),
+            span: None,
+        })
+    }
+
+    fn extract_function_defs(&self, node: &ArkNode) -> Vec<FunctionDef> {
+        let mut defs = Vec::new();
+        match node {
+            ArkNode::Function(func_def) => {
+                defs.push(func_def.clone());
+            }
+            ArkNode::Statement(Statement::Function(func_def)) => {
+                defs.push(func_def.clone());
+            }
+            ArkNode::Statement(Statement::Block(stmts)) => {
+                for stmt in stmts {
+                    if let Statement::Function(func_def) = stmt {
+                        defs.push(func_def.clone());
+                    }
+                }
+            }
+            _ => {}
+        }
+        defs
+    }
+
+    // =========================================================================
+    // _start Function (Top-Level Code)
+    // =========================================================================
+
+    fn compile_start_function(&mut self, node: &ArkNode) -> Result<(), WasmCompileError> {
+        let type_idx = self.types.len() as u32;
+        // _start: () -> ()
+        self.types.push((vec![], vec![]));
+
+        let func_idx = self.import_count + self.functions.len() as u32;
+        self.func_index_map.insert("_start".to_string(), func_idx);
+
+        let mut ctx = self.body_context(0);
+        let func_index_map = self.func_index_map.clone();
+        for (i, (name, constant)) in self.exported_globals.iter().enumerate() {
+            if constant.is_none() {
+                ctx.export_globals.insert(name.clone(), 1 + i as u32);
+            }
+        }
+
+        match node {
+            ArkNode::Statement(Statement::Block(stmts)) => {
+                for stmt in stmts {
+                    // Skip function definitions (already compiled)
+                    if matches!(stmt, Statement::Function(_)) {
+                        continue;
+                    }
+                    Self::compile_stmt(&mut ctx, stmt, false, &func_index_map)?;
+                }
+            }
+            ArkNode::Statement(stmt) => {
+                if !matches!(stmt, Statement::Function(_)) {
+                    Self::compile_stmt(&mut ctx, stmt, false, &func_index_map)?;
+                }
+            }
+            ArkNode::Expression(expr) => {
+                Self::compile_expr(&mut ctx, expr, &func_index_map)?;
+                ctx.emit(Instruction::Drop);
+            }
+            _ => {}
+        }
+
+        ctx.emit(Instruction::End);
+        self.data_offset = ctx.string_offset;
+        self.functions.push((type_idx, "_start".to_string(), ctx));
+
+        Ok(())
+    }
+
+    // =========================================================================
+    // Statement Compilation
+    // =========================================================================
+
+    fn compile_mast_node(
+        ctx: &mut FuncContext,
+        mast: &MastNode,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        match &mast.content {
+            ArkNode::Statement(stmt) => Self::compile_stmt(ctx, stmt, true, func_map),
+            ArkNode::Expression(expr) => Self::compile_expr(ctx, expr, func_map),
+            ArkNode::Function(_) => Ok(()), // Already handled
+            ArkNode::Type(_) => Ok(()),
+        }
+    }
+
+    /// Compile a statement; scratch locals it takes are released afterwards.
+    fn compile_stmt(
+        ctx: &mut FuncContext,
+        stmt: &Statement,
+        preserve: bool,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let outer = ctx.scope.open_scratch();
+        let result = Self::lower_stmt(ctx, stmt, preserve, func_map);
+        ctx.scope.close_scratch(outer);
+        result
+    }
+
+    fn lower_stmt(
+        ctx: &mut FuncContext,
+        stmt: &Statement,
+        preserve: bool,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        match stmt {
+            // -----------------------------------------------------------------
+            // Let binding: evaluate expression, store in local
+            // `let p: Point := {x: 1}` lays the struct out by Point's declared
+            // field order, zero-filling fields the initializer omits.
+            // -----------------------------------------------------------------
+            Statement::Let { name, ty, value } => {
+                let layout = match (ty, value) {
+                    (Some(ArkType::Struct(struct_name, _)), Expression::StructInit { .. }) => {
+                        ctx.struct_layouts.get(struct_name).cloned()
+                    }
+                    _ => None,
+                };
+                match (&layout, value) {
+                    (Some(layout), Expression::StructInit { fields }) => {
+                        Self::compile_struct_init(ctx, fields, Some(layout), func_map)?
+                    }
+                    _ => Self::compile_expr(ctx, value, func_map)?,
+                }
+                let kind = Self::static_type_of(ctx, value);
+                ctx.scope.record_kind(name, kind);
+                let shape = layout.or_else(|| Self::static_shape_of(ctx, value));
+                ctx.scope.record_shape(name, shape);
+                let idx = ctx.scope.get_or_alloc(name);
+                ctx.emit(Instruction::LocalSet(idx));
+                Ok(())
+            }
+
+            // -----------------------------------------------------------------
+            // Expression statement: compile and optionally drop result
+            // -----------------------------------------------------------------
+            Statement::Expression(expr) => {
+                Self::compile_expr(ctx, expr, func_map)?;
+                if !preserve {
+                    ctx.emit(Instruction::Drop);
+                }
+                Ok(())
+            }
+
+            // -----------------------------------------------------------------
+            // Block: compile each statement, last one preserves value
+            // -----------------------------------------------------------------
+            Statement::Block(stmts) => {
+                let len = stmts.len();
+                if len == 0 && preserve {
+                    ctx.emit(Instruction::I64Const(0)); // Unit
+                    return Ok(());
+                }
+                for (i, s) in stmts.iter().enumerate() {
+                    let is_last = i == len - 1;
+                    Self::compile_stmt(ctx, s, preserve && is_last, func_map)?;
+                }
+                Ok(())
+            }
+
+            // -----------------------------------------------------------------
+            // TryCatch: a trap cannot be resumed inside the module, so only
+            // the body is compiled. A failing body aborts the run and the
+            // host reports the trap under its `RuntimeError` code (see
+            // `wasm_runner::trap_error`).
+            // -----------------------------------------------------------------
+            Statement::TryCatch { body, .. } => {
+                Self::compile_stmt(ctx, &Statement::Block(body.clone()), preserve, func_map)
+            }
+
+            // -----------------------------------------------------------------
+            // Return: compile expression and return
+            // -----------------------------------------------------------------
+            Statement::Return(Expression::Call {
+                function_hash,
+                args,
+            }) if ctx
+                .tail_loop
+                .as_ref()
+                .is_some_and(|(name, _)| name == function_hash)
+                && args.len() == ctx.param_count as usize
+                && ctx.scope.get(function_hash).is_none() =>
+            {
+                // Self tail call: evaluate every argument first, then
+                // rebind the parameters (popped last to first) and restart
+                Self::check_call_args(ctx, function_hash, args)?;
+                Self::compile_call_args(ctx, function_hash, args, false, func_map)?;
+                for j in (0..ctx.param_locals.len()).rev() {
+                    ctx.emit(Instruction::LocalSet(ctx.param_locals[j]));
+                }
+                let loop_depth = ctx.tail_loop.as_ref().map_or(0, |(_, depth)| *depth);
+                ctx.emit_br_to(loop_depth);
+                Ok(())
+            }
+            Statement::Return(expr) => {
+                if ctx.returns_float {
+                    Self::compile_f64_operand(ctx, expr, func_map)?;
+                } else {
+                    Self::compile_expr(ctx, expr, func_map)?;
+                }
+                ctx.emit(Instruction::Return);
+                Ok(())
+            }
+
+            // -----------------------------------------------------------------
+            // If/Else: uses WASM block/if structured control flow
+            // -----------------------------------------------------------------
+            Statement::If {
+                condition,
+                then_block,
+                else_block,
+            } => {
+                Self::compile_expr(ctx, condition, func_map)?;
+                // Convert i64 condition to i32 for br_if
+                ctx.emit(Instruction::I32WrapI64);
+
+                if preserve {
+                    // If that produces a value
+                    ctx.emit(Instruction::If(wasm_encoder::BlockType::Result(
+                        ValType::I64,
+                    )));
+                } else {
+                    ctx.emit(Instruction::If(wasm_encoder::BlockType::Empty));
+                }
+
+                // Then block
+                let then_len = then_block.len();
+                if then_len == 0 && preserve {
+                    ctx.emit(Instruction::I64Const(0)); // Unit
+                } else {
+                    for (i, s) in then_block.iter().enumerate() {
+                        let is_last = i == then_len - 1;
+                        Self::compile_stmt(ctx, s, preserve && is_last, func_map)?;
+                    }
+                }
+
+                // Else block
+                if let Some(else_stmts) = else_block {
+                    ctx.emit(Instruction::Else);
+                    let else_len = else_stmts.len();
+                    if else_len == 0 && preserve {
+                        ctx.emit(Instruction::I64Const(0)); // Unit
+                    } else {
+                        for (i, s) in else_stmts.iter().enumerate() {
+                            let is_last = i == else_len - 1;
+                            Self::compile_stmt(ctx, s, preserve && is_last, func_map)?;
+                        }
+                    }
+                } else if preserve {
+                    ctx.emit(Instruction::Else);
+                    ctx.emit(Instruction::I64Const(0)); // implicit Unit
+                }
+
+                ctx.emit(Instruction::End);
+                Ok(())
+            }
+
+            // -----------------------------------------------------------------
+            // While loop: block + loop + br_if
+            // -----------------------------------------------------------------
+            Statement::While {
+                condition,
+                body,
+                label,
+            } => {
+                // block $break
+                //   loop $continue
+                //     condition
+                //     i32.eqz
+                //     br_if $break
+                //     body...
+                //     br $continue
+                //   end
+                // end
+                ctx.emit(Instruction::Block(wasm_encoder::BlockType::Empty));
+                let break_depth = ctx.block_depth;
+                ctx.emit(Instruction::Loop(wasm_encoder::BlockType::Empty));
+                let continue_depth = ctx.block_depth;
+                ctx.spend_fuel();
+
+                // Evaluate condition
+                Self::compile_expr(ctx, condition, func_map)?;
+                ctx.emit(Instruction::I32WrapI64);
+                ctx.emit(Instruction::I32Eqz);
+                ctx.emit(Instruction::BrIf(1)); // break out of block
+
+                // Body
+                ctx.loop_targets
+                    .push((label.clone(), break_depth, continue_depth));
+                for s in body {
+                    Self::compile_stmt(ctx, s, false, func_map)?;
+                }
+                ctx.loop_targets.pop();
+
+                ctx.emit(Instruction::Br(0)); // continue loop
+                ctx.emit(Instruction::End); // end loop
+                ctx.emit(Instruction::End); // end block
+
+                if preserve {
+                    ctx.emit(Instruction::I64Const(0)); // Unit
+                }
+                Ok(())
+            }
+
+            // -----------------------------------------------------------------
+            // For loop: desugared to the while-loop shape (see `lower_for`)
+            // -----------------------------------------------------------------
+            Statement::For {
+                variable,
+                iterable,
+                body,
+                label,
+            } => {
+                Self::lower_for(ctx, variable, iterable, body, label.as_deref(), func_map)?;
+                if preserve {
+                    ctx.emit(Instruction::I64Const(0));
+                }
+                Ok(())
+            }
+
+            // -----------------------------------------------------------------
+            // SetField: obj.field = value
+            // -----------------------------------------------------------------
+            Statement::SetField {
+                obj_name,
+                field,
+                value,
+                obj,
+            } => {
+                // The target is either a general object expression evaluating
+                // to a struct pointer, or the local variable `obj_name`.
+                let target = match obj {
+                    Some(obj) => Some(obj.as_ref().clone()),
+                    None => ctx
+                        .scope
+                        .get(obj_name)
+                        .map(|_| Expression::Variable(obj_name.clone())),
+                };
+                if let Some(target) = target {
+                    let slot = Self::field_slot(ctx, &target, field).unwrap_or(0);
+
+                    // Compile value
+                    Self::compile_expr(ctx, value, func_map)?;
+                    let val_local = ctx.scope.scratch("__setfield_val");
+                    ctx.emit(Instruction::LocalSet(val_local));
+
+                    // Load struct ptr and store the value in the field's slot
+                    Self::compile_expr(ctx, &target, func_map)?;
+                    ctx.emit(Instruction::I32WrapI64);
+                    ctx.emit(Instruction::LocalGet(val_local));
+                    ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                        offset: (8 + 8 * slot) as u64,
+                        align: 3,
+                        memory_index: 0,
+                    }));
+                }
+                if preserve {
+                    ctx.emit(Instruction::I64Const(0));
+                }
+                Ok(())
+            }
+
+            // -----------------------------------------------------------------
+            // LetDestructure: bind names to sequential list elements
+            // let [a, b, c] = my_list;
+            // → a = list[0], b = list[1], c = list[2]
+            //
+            // With `by_field` (let {y, x} = point) each name is bound to the
+            // struct field of the same name, at the slot the struct's
+            // StructInit layout assigned to it.
+            //
+            // With bounds checks on, the source's heap tag and length header
+            // are verified first and a mismatch traps.
+            // -----------------------------------------------------------------
+            Statement::LetDestructure {
+                names,
+                value,
+                by_field,
+                rest,
+            } => {
+                if let Some(rest) = rest {
+                    return Err(WasmCompileError {
+                        message: format!("rest binding '...{}' is not supported", rest),
+                        context: "Statement::LetDestructure".to_string(),
+                        span: None,
+                    });
+                }
+                let slots = if *by_field {
+                    let shape =
+                        Self::static_shape_of(ctx, value).ok_or_else(|| WasmCompileError {
+                            message: "struct layout of destructured value is not known statically"
+                                .to_string(),
+                            context: "Statement::LetDestructure".to_string(),
+                            span: None,
+                        })?;
+                    names
+                        .iter()
+                        .map(|name| {
+                            shape
+                                .iter()
+                                .position(|f| f == name)
+                                .ok_or_else(|| WasmCompileError {
+                                    message: format!("struct has no field '{}'", name),
+                                    context: "Statement::LetDestructure".to_string(),
+                                    span: None,
+                                })
+                        })
+                        .collect::<Result<Vec<_>, _>>()?
+                } else {
+                    (0..names.len()).collect()
+                };
+
+                // Compile the list/value expression
+                Self::compile_expr(ctx, value, func_map)?;
+                let list_ptr = ctx.scope.scratch("__destructure_ptr");
+                ctx.emit(Instruction::LocalSet(list_ptr));
+
+                if ctx.bounds_checks {
+                    let (tag, min_len) = if *by_field {
+                        (HEAP_TAG_STRUCT, slots.iter().max().map_or(0, |m| m + 1))
+                    } else {
+                        (HEAP_TAG_LIST, names.len())
+                    };
+                    Self::emit_heap_object_guard(ctx, list_ptr, tag, min_len);
+                }
+
+                // Bind each name to the element at ptr + 8 + 8*slot
+                for (name, slot) in names.iter().zip(slots) {
+                    ctx.scope.record_kind(name, None);
+                    ctx.scope.record_shape(name, None);
+                    let name_local = ctx.scope.get_or_alloc(name);
+                    ctx.emit(Instruction::LocalGet(list_ptr));
+                    ctx.emit(Instruction::I32WrapI64);
+                    ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                        offset: (8 + 8 * slot) as u64,
+                        align: 3,
+                        memory_index: 0,
+                    }));
+                    ctx.emit(Instruction::LocalSet(name_local));
+                }
+                if preserve {
+                    ctx.emit(Instruction::I64Const(0));
+                }
+                Ok(())
+            }
+
+            // -----------------------------------------------------------------
+            // Function: already compiled in collect phase — skip
+            // -----------------------------------------------------------------
+            Statement::Function(_) => Ok(()),
+
+            // -----------------------------------------------------------------
+            // #[export] let: bind as usual, then mirror a non-constant value
+            // into its exported global (constants were baked in at emit time)
+            // -----------------------------------------------------------------
+            Statement::Export(inner) => {
+                Self::lower_stmt(ctx, inner, preserve, func_map)?;
+                if let Statement::Let { name, .. } = &**inner {
+                    if let (Some(&global), Some(local)) =
+                        (ctx.export_globals.get(name), ctx.scope.get(name))
+                    {
+                        ctx.emit(Instruction::LocalGet(local));
+                        ctx.emit(Instruction::GlobalSet(global));
+                    }
+                }
+                Ok(())
+            }
+
+            // -----------------------------------------------------------------
+            // Import / StructDecl: metadata only, no codegen
+            // -----------------------------------------------------------------
+            Statement::Import(_)
+            | Statement::StructDecl(_)
+            | Statement::EnumDecl(_)
+            | Statement::TraitDecl(_)
+            | Statement::ImplBlock(_) => Ok(()),
+
+            // -----------------------------------------------------------------
+            // Break / Continue: br to the targets of the innermost loop, or
+            // of the labelled one, however deeply nested in if/match blocks
+            // -----------------------------------------------------------------
+            Statement::Break { label } => {
+                let (break_depth, _) = Self::target_loop(ctx, label.as_deref())?;
+                ctx.emit_br_to(break_depth);
+                Ok(())
+            }
+            Statement::Continue { label } => {
+                let (_, continue_depth) = Self::target_loop(ctx, label.as_deref())?;
+                ctx.emit_br_to(continue_depth);
+                Ok(())
+            }
+        }
+    }
+
+    /// Branch targets of the loop a `break`/`continue` belongs to: the
+    /// innermost one, or the nearest enclosing loop carrying `label`.
+    fn target_loop(ctx: &FuncContext, label: Option<&str>) -> Result<(u32, u32), WasmCompileError> {
+        let target = match label {
+            None => ctx.loop_targets.last(),
+            Some(name) => ctx
+                .loop_targets
+                .iter()
+                .rfind(|(l, _, _)| l.as_deref() == Some(name)),
+        };
+        match (target, label) {
+            (Some(&(_, break_depth, continue_depth)), _) => Ok((break_depth, continue_depth)),
+            (None, None) => Err(WasmCompileError {
+                message: "break/continue outside of a loop".to_string(),
+                context: "compile_stmt".to_string(),
+                span: None,
+            }),
+            (None, Some(name)) => Err(WasmCompileError {
+                message: format!("no enclosing loop labelled '{}'", name),
+                context: "compile_stmt".to_string(),
+                span: None,
+            }),
+        }
+    }
+
+    /// Lower `for variable in iterable { body }` to the while-loop shape.
+    ///
+    /// A range (`a..b`, `a..=b`) counts an index from `a` without building a
+    /// list; any other iterable must be a list, walked by index. `variable`
+    /// is rebound from the hidden index on every iteration, so assigning to
+    /// it in the body does not change the iteration, as in the interpreter.
+    ///
+    ///   block $break
+    ///     loop
+    ///       br_if $break (idx past end)
+    ///       variable = range ? idx : list[idx]
+    ///       block $continue
+    ///         body...
+    ///       end
+    ///       idx += 1
+    ///       br 0
+    ///     end
+    ///   end
+    fn lower_for(
+        ctx: &mut FuncContext,
+        variable: &str,
+        iterable: &Expression,
+        body: &[Statement],
+        label: Option<&str>,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let range = match iterable {
+            Expression::Call {
+                function_hash,
+                args,
+            } if args.len() == 2
+                && matches!(
+                    function_hash.as_str(),
+                    "range_exclusive" | "range_inclusive"
+                ) =>
+            {
+                Some((&args[0], &args[1], function_hash == "range_inclusive"))
+            }
+            _ => None,
+        };
+
+        let idx = ctx.scope.scratch("__for_idx");
+        let end = ctx.scope.scratch("__for_end");
+        let (list, inclusive) = match range {
+            Some((start, stop, inclusive)) => {
+                Self::compile_expr(ctx, start, func_map)?;
+                ctx.emit(Instruction::LocalSet(idx));
+                Self::compile_expr(ctx, stop, func_map)?;
+                ctx.emit(Instruction::LocalSet(end));
+                (None, inclusive)
+            }
+            None => {
+                let list = ctx.scope.scratch("__for_list");
+                Self::compile_expr(ctx, iterable, func_map)?;
+                ctx.emit(Instruction::LocalSet(list));
+                if ctx.bounds_checks {
+                    Self::emit_heap_object_guard(ctx, list, HEAP_TAG_LIST, 0);
+                }
+                ctx.emit(Instruction::I64Const(0));
+                ctx.emit(Instruction::LocalSet(idx));
+                ctx.emit(Instruction::LocalGet(list));
+                ctx.emit(Instruction::I32WrapI64);
+                ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                    offset: 0,
+                    align: 3,
+                    memory_index: 0,
+                }));
+                ctx.emit(Instruction::LocalSet(end));
+                (Some(list), false)
+            }
+        };
+
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        let break_depth = ctx.block_depth;
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        ctx.spend_fuel();
+
+        ctx.emit(Instruction::LocalGet(idx));
+        ctx.emit(Instruction::LocalGet(end));
+        ctx.emit(if inclusive {
+            Instruction::I64GtS
+        } else {
+            Instruction::I64GeS
+        });
+        ctx.emit(Instruction::BrIf(1));
+
+        // variable = idx, or the list element at ptr + 8 + 8*idx
+        let kind = list.is_none().then_some("integer");
+        ctx.scope.record_kind(variable, kind);
+        ctx.scope.record_shape(variable, None);
+        let var_local = ctx.scope.get_or_alloc(variable);
+        match list {
+            None => ctx.emit(Instruction::LocalGet(idx)),
+            Some(list) => {
+                ctx.emit(Instruction::LocalGet(list));
+                ctx.emit(Instruction::LocalGet(idx));
+                ctx.emit(Instruction::I64Const(3));
+                ctx.emit(Instruction::I64Shl);
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::I32WrapI64);
+                ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                    offset: 8,
+                    align: 3,
+                    memory_index: 0,
+                }));
+            }
+        }
+        ctx.emit(Instruction::LocalSet(var_local));
+
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        let continue_depth = ctx.block_depth;
+        ctx.loop_targets
+            .push((label.map(str::to_string), break_depth, continue_depth));
+        for s in body {
+            Self::compile_stmt(ctx, s, false, func_map)?;
+        }
+        ctx.loop_targets.pop();
+        ctx.emit(Instruction::End); // end $continue
+
+        ctx.emit(Instruction::LocalGet(idx));
+        ctx.emit(Instruction::I64Const(1));
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::LocalSet(idx));
+        ctx.emit(Instruction::Br(0));
+        ctx.emit(Instruction::End); // end loop
+        ctx.emit(Instruction::End); // end $break
+        Ok(())
+    }
+
+    // =========================================================================
+    // Expression Compilation
+    // =========================================================================
+
+    /// Compile an expression, leaving its value on the stack. Scratch locals
+    /// it takes are released afterwards for the next expression to reuse.
+    fn compile_expr(
+        ctx: &mut FuncContext,
+        expr: &Expression,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let outer = ctx.scope.open_scratch();
+        let result = Self::lower_expr(ctx, expr, func_map);
+        ctx.scope.close_scratch(outer);
+        result
+    }
+
+    fn lower_expr(
+        ctx: &mut FuncContext,
+        expr: &Expression,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        match expr {
+            // -----------------------------------------------------------------
+            // Integer literal → i64.const
+            // -----------------------------------------------------------------
+            Expression::Integer(n) => {
+                ctx.emit(Instruction::I64Const(*n));
+                Ok(())
+            }
+
+            // -----------------------------------------------------------------
+            // String literal → store in linear memory, push packed ptr|len
+            // -----------------------------------------------------------------
+            // Boolean literals are 1/0, like comparison results
+            Expression::Literal(s) if s == "true" || s == "false" => {
+                ctx.emit(Instruction::I64Const((s == "true") as i64));
+                Ok(())
+            }
+            Expression::Literal(s) => {
+                if let Some(f) = float_literal(s) {
+                    ctx.emit(Instruction::I64Const(f.to_bits() as i64));
+                    return Ok(());
+                }
+                let (ptr, len) = ctx.alloc_string(s);
+                // Pack as (ptr << 32) | len — both fit in i64
+                let packed = ((ptr as i64) << 32) | (len as i64 & 0xFFFFFFFF);
+                ctx.emit(Instruction::I64Const(packed));
+                Ok(())
+            }
+
+            // -----------------------------------------------------------------
+            // Variable → local.get
+            // -----------------------------------------------------------------
+            Expression::Variable(name) => {
+                if let Some(idx) = ctx.scope.get(name) {
+                    ctx.emit(Instruction::LocalGet(idx));
+                } else {
+                    // Could be a function reference — push function index as
+                    // i64, through its i64 adapter if it has a typed signature
+                    let adapter = format!("__i64_{}", name);
+                    if let Some(&func_idx) = func_map.get(&adapter).or_else(|| func_map.get(name)) {
+                        ctx.emit_func_ref(func_idx);
+                    } else {
+                        // Allocate as new local (might be used before definition in some patterns)
+                        let idx = ctx.scope.get_or_alloc(name);
+                        ctx.emit(Instruction::LocalGet(idx));
+                    }
+                }
+                Ok(())
+            }
+
+            // -----------------------------------------------------------------
+            // Function call
+            // -----------------------------------------------------------------
+            Expression::Call {
+                function_hash,
+                args,
+            } => {
+                match function_hash.as_str() {
+                    // Arithmetic intrinsics → direct WASM opcodes
+                    "intrinsic_add" | "add" => {
+                        Self::compile_binary_op(
+                            ctx,
+                            args,
+                            Instruction::I64Add,
+                            Some(Instruction::F64Add),
+                            func_map,
+                        )?;
+                    }
+                    "intrinsic_sub" | "sub" => {
+                        Self::compile_binary_op(
+                            ctx,
+                            args,
+                            Instruction::I64Sub,
+                            Some(Instruction::F64Sub),
+                            func_map,
+                        )?;
+                    }
+                    "intrinsic_mul" | "mul" => {
+                        Self::compile_binary_op(
+                            ctx,
+                            args,
+                            Instruction::I64Mul,
+                            Some(Instruction::F64Mul),
+                            func_map,
+                        )?;
+                    }
+                    "intrinsic_div" | "div" => {
+                        Self::compile_binary_op(
+                            ctx,
+                            args,
+                            Instruction::I64DivS,
+                            Some(Instruction::F64Div),
+                            func_map,
+                        )?;
+                    }
+                    "intrinsic_mod" | "modulo" => {
+                        Self::compile_binary_op(ctx, args, Instruction::I64RemS, None, func_map)?;
+                    }
+
+                    // Comparison intrinsics → WASM comparison + extend to i64
+                    "intrinsic_eq" | "eq" => {
+                        Self::compile_compare_op(
+                            ctx,
+                            args,
+                            Instruction::I64Eq,
+                            Instruction::F64Eq,
+                            func_map,
+                        )?;
+                    }
+                    "intrinsic_neq" | "neq" => {
+                        Self::compile_compare_op(
+                            ctx,
+                            args,
+                            Instruction::I64Ne,
+                            Instruction::F64Ne,
+                            func_map,
+                        )?;
+                    }
+                    "intrinsic_gt" | "gt" => {
+                        Self::compile_compare_op(
+                            ctx,
+                            args,
+                            Instruction::I64GtS,
+                            Instruction::F64Gt,
+                            func_map,
+                        )?;
+                    }
+                    "intrinsic_lt" | "lt" => {
+                        Self::compile_compare_op(
+                            ctx,
+                            args,
+                            Instruction::I64LtS,
+                            Instruction::F64Lt,
+                            func_map,
+                        )?;
+                    }
+                    "intrinsic_ge" | "ge" => {
+                        Self::compile_compare_op(
+                            ctx,
+                            args,
+                            Instruction::I64GeS,
+                            Instruction::F64Ge,
+                            func_map,
+                        )?;
+                    }
+                    "intrinsic_le" | "le" => {
+                        Self::compile_compare_op(
+                            ctx,
+                            args,
+                            Instruction::I64LeS,
+                            Instruction::F64Le,
+                            func_map,
+                        )?;
+                    }
+
+                    // Logical intrinsics
+                    "intrinsic_and" | "and" => {
+                        Self::compile_logical_op(ctx, args, Instruction::I64And, func_map)?;
+                    }
+                    "intrinsic_or" | "or" => {
+                        Self::compile_logical_op(ctx, args, Instruction::I64Or, func_map)?;
+                    }
+
+                    // Print → WASI fd_write
+                    "print" | "intrinsic_print" => {
+                        Self::compile_print(ctx, args, func_map)?;
+                    }
+
+                    "sys.log" | "intrinsic_log" => {
+                        Self::compile_log(ctx, args, func_map)?;
+                    }
+
+                    "assert" | "assert_eq" => {
+                        Self::compile_assert(ctx, function_hash, args, func_map)?;
+                    }
+
+                    // Identity: calls are never folded or dropped, so the value is kept
+                    "sys.bench.black_box" | "intrinsic_bench_black_box" => {
+                        if args.len() != 1 {
+                            return Err(WasmCompileError {
+                                message: "sys.bench.black_box requires 1 argument".to_string(),
+                                context: "sys.bench.black_box".to_string(),
+                                span: None,
+                            });
+                        }
+                        Self::compile_expr(ctx, &args[0], func_map)?;
+                    }
+
+                    // No debugger attaches to a module, so there is nothing to pause
+                    "sys.debug.break" | "intrinsic_debug_break" => {
+                        ctx.emit(Instruction::I64Const(0));
+                    }
+
+                    // =========================================================
+                    // String Intrinsics (packed i64: ptr<<32 | len)
+                    // =========================================================
+
+                    // string_len(s) → extract len from packed i64
+                    "string_len" | "intrinsic_string_len" => {
+                        if args.len() != 1 {
+                            return Err(WasmCompileError {
+                                message: "string_len requires 1 argument".to_string(),
+                                context: "string_len".to_string(),
+                                span: None,
+                            });
+                        }
+                        Self::compile_expr(ctx, &args[0], func_map)?;
+                        // len = packed & 0xFFFFFFFF
+                        ctx.emit(Instruction::I64Const(0xFFFFFFFF));
+                        ctx.emit(Instruction::I64And);
+                    }
+
+                    // string_concat(a, b) → allocate new buffer, copy both, repack
+                    "string_concat" | "intrinsic_string_concat" => {
+                        if args.len() != 2 {
+                            return Err(WasmCompileError {
+                                message: "string_concat requires 2 arguments".to_string(),
+                                context: "string_concat".to_string(),
+                                span: None,
+                            });
+                        }
+                        let packed_a = ctx.scope.scratch("__str_concat_a");
+                        let packed_b = ctx.scope.scratch("__str_concat_b");
+                        let ptr_a = ctx.scope.scratch("__str_ptr_a");
+                        let len_a = ctx.scope.scratch("__str_len_a");
+                        let ptr_b = ctx.scope.scratch("__str_ptr_b");
+                        let len_b = ctx.scope.scratch("__str_len_b");
+                        let new_ptr = ctx.scope.scratch("__str_new_ptr");
+
+                        // Evaluate and store both args
+                        Self::compile_expr(ctx, &args[0], func_map)?;
+                        ctx.emit(Instruction::LocalSet(packed_a));
+                        Self::compile_expr(ctx, &args[1], func_map)?;
+                        ctx.emit(Instruction::LocalSet(packed_b));
+
+                        // Unpack a: ptr_a = (packed_a >> 32) as i32, len_a = packed_a & 0xFFFFFFFF
+                        ctx.emit(Instruction::LocalGet(packed_a));
+                        ctx.emit(Instruction::I64Const(32));
+                        ctx.emit(Instruction::I64ShrU);
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I64ExtendI32U);
+                        ctx.emit(Instruction::LocalSet(ptr_a));
+
+                        ctx.emit(Instruction::LocalGet(packed_a));
+                        ctx.emit(Instruction::I64Const(0xFFFFFFFF));
+                        ctx.emit(Instruction::I64And);
+                        ctx.emit(Instruction::LocalSet(len_a));
+
+                        // Unpack b: ptr_b, len_b
+                        ctx.emit(Instruction::LocalGet(packed_b));
+                        ctx.emit(Instruction::I64Const(32));
+                        ctx.emit(Instruction::I64ShrU);
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I64ExtendI32U);
+                        ctx.emit(Instruction::LocalSet(ptr_b));
+
+                        ctx.emit(Instruction::LocalGet(packed_b));
+                        ctx.emit(Instruction::I64Const(0xFFFFFFFF));
+                        ctx.emit(Instruction::I64And);
+                        ctx.emit(Instruction::LocalSet(len_b));
+
+                        // Allocate new buffer: __alloc(len_a + len_b)
+                        ctx.emit(Instruction::LocalGet(len_a));
+                        ctx.emit(Instruction::LocalGet(len_b));
+                        ctx.emit(Instruction::I64Add);
+                        if let Some(&alloc_idx) = func_map.get("__alloc") {
+                            ctx.emit(Instruction::Call(alloc_idx));
+                        } else {
+                            return Err(WasmCompileError {
+                                message: "__alloc not found".to_string(),
+                                context: "string_concat".to_string(),
+                                span: None,
+                            });
+                        }
+                        ctx.emit(Instruction::LocalSet(new_ptr)); // new_ptr (i64, but value is i32-range)
+
+                        // memory.copy(new_ptr, ptr_a, len_a)
+                        ctx.emit(Instruction::LocalGet(new_ptr));
+                        ctx.emit(Instruction::I32WrapI64); // dest
+                        ctx.emit(Instruction::LocalGet(ptr_a));
+                        ctx.emit(Instruction::I32WrapI64); // src
+                        ctx.emit(Instruction::LocalGet(len_a));
+                        ctx.emit(Instruction::I32WrapI64); // size
+                        ctx.emit(Instruction::MemoryCopy {
+                            src_mem: 0,
+                            dst_mem: 0,
+                        });
+
+                        // memory.copy(new_ptr + len_a, ptr_b, len_b)
+                        ctx.emit(Instruction::LocalGet(new_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::LocalGet(len_a));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I32Add); // dest = new_ptr + len_a
+                        ctx.emit(Instruction::LocalGet(ptr_b));
+                        ctx.emit(Instruction::I32WrapI64); // src
+                        ctx.emit(Instruction::LocalGet(len_b));
+                        ctx.emit(Instruction::I32WrapI64); // size
+                        ctx.emit(Instruction::MemoryCopy {
+                            src_mem: 0,
+                            dst_mem: 0,
+                        });
+
+                        // Operands built just for this call are dead now
+                        for (arg, ptr) in [(&args[0], ptr_a), (&args[1], ptr_b)] {
+                            if Self::fresh_allocation(arg) == Some("string") {
+                                Self::emit_free(ctx, ptr, 0, func_map)?;
+                            }
+                        }
+
+                        // Pack result: (new_ptr << 32) | (len_a + len_b)
+                        ctx.emit(Instruction::LocalGet(new_ptr));
+                        ctx.emit(Instruction::I64Const(32));
+                        ctx.emit(Instruction::I64Shl);
+                        ctx.emit(Instruction::LocalGet(len_a));
+                        ctx.emit(Instruction::LocalGet(len_b));
+                        ctx.emit(Instruction::I64Add);
+                        ctx.emit(Instruction::I64Or);
+                    }
+
+                    // string_eq(a, b) → 1 if equal, 0 if not
+                    "string_eq" | "intrinsic_string_eq" => {
+                        if args.len() != 2 {
+                            return Err(WasmCompileError {
+                                message: "string_eq requires 2 arguments".to_string(),
+                                context: "string_eq".to_string(),
+                                span: None,
+                            });
+                        }
+                        let packed_a = ctx.scope.scratch("__streq_a");
+                        let packed_b = ctx.scope.scratch("__streq_b");
+                        let ptr_a = ctx.scope.scratch("__streq_ptr_a");
+                        let len_a = ctx.scope.scratch("__streq_len_a");
+                        let ptr_b = ctx.scope.scratch("__streq_ptr_b");
+                        let len_b = ctx.scope.scratch("__streq_len_b");
+                        let idx = ctx.scope.scratch("__streq_idx");
+                        let result = ctx.scope.scratch("__streq_result");
+
+                        // Evaluate args
+                        Self::compile_expr(ctx, &args[0], func_map)?;
+                        ctx.emit(Instruction::LocalSet(packed_a));
+                        Self::compile_expr(ctx, &args[1], func_map)?;
+                        ctx.emit(Instruction::LocalSet(packed_b));
+
+                        // Unpack a
+                        ctx.emit(Instruction::LocalGet(packed_a));
+                        ctx.emit(Instruction::I64Const(32));
+                        ctx.emit(Instruction::I64ShrU);
+                        ctx.emit(Instruction::LocalSet(ptr_a));
+
+                        ctx.emit(Instruction::LocalGet(packed_a));
+                        ctx.emit(Instruction::I64Const(0xFFFFFFFF));
+                        ctx.emit(Instruction::I64And);
+                        ctx.emit(Instruction::LocalSet(len_a));
+
+                        // Unpack b
+                        ctx.emit(Instruction::LocalGet(packed_b));
+                        ctx.emit(Instruction::I64Const(32));
+                        ctx.emit(Instruction::I64ShrU);
+                        ctx.emit(Instruction::LocalSet(ptr_b));
+
+                        ctx.emit(Instruction::LocalGet(packed_b));
+                        ctx.emit(Instruction::I64Const(0xFFFFFFFF));
+                        ctx.emit(Instruction::I64And);
+                        ctx.emit(Instruction::LocalSet(len_b));
+
+                        // Default result = 1 (assume equal)
+                        ctx.emit(Instruction::I64Const(1));
+                        ctx.emit(Instruction::LocalSet(result));
+
+                        // If len_a != len_b → result = 0, skip loop
+                        ctx.emit(Instruction::LocalGet(len_a));
+                        ctx.emit(Instruction::LocalGet(len_b));
+                        ctx.emit(Instruction::I64Ne);
+                        ctx.emit(Instruction::If(BlockType::Empty));
+                        ctx.emit(Instruction::I64Const(0));
+                        ctx.emit(Instruction::LocalSet(result));
+                        ctx.emit(Instruction::Else);
+
+                        // Byte-by-byte comparison loop
+                        ctx.emit(Instruction::I64Const(0));
+                        ctx.emit(Instruction::LocalSet(idx));
+
+                        ctx.emit(Instruction::Block(BlockType::Empty)); // outer block for break
+                        ctx.emit(Instruction::Loop(BlockType::Empty));
+
+                        // if idx >= len_a → break
+                        ctx.emit(Instruction::LocalGet(idx));
+                        ctx.emit(Instruction::LocalGet(len_a));
+                        ctx.emit(Instruction::I64GeU);
+                        ctx.emit(Instruction::BrIf(1)); // break out of block
+
+                        // Compare bytes: mem[ptr_a + idx] vs mem[ptr_b + idx]
+                        ctx.emit(Instruction::LocalGet(ptr_a));
+                        ctx.emit(Instruction::LocalGet(idx));
+                        ctx.emit(Instruction::I64Add);
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I32Load8U(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 0,
+                            memory_index: 0,
+                        }));
+
+                        ctx.emit(Instruction::LocalGet(ptr_b));
+                        ctx.emit(Instruction::LocalGet(idx));
+                        ctx.emit(Instruction::I64Add);
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I32Load8U(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 0,
+                            memory_index: 0,
+                        }));
+
+                        ctx.emit(Instruction::I32Ne);
+                        ctx.emit(Instruction::If(BlockType::Empty));
+                        ctx.emit(Instruction::I64Const(0));
+                        ctx.emit(Instruction::LocalSet(result));
+                        ctx.emit(Instruction::Br(2)); // break out of outer block
+                        ctx.emit(Instruction::End); // end if
+
+                        // idx += 1
+                        ctx.emit(Instruction::LocalGet(idx));
+                        ctx.emit(Instruction::I64Const(1));
+                        ctx.emit(Instruction::I64Add);
+                        ctx.emit(Instruction::LocalSet(idx));
+
+                        ctx.emit(Instruction::Br(0)); // continue loop
+                        ctx.emit(Instruction::End); // end loop
+                        ctx.emit(Instruction::End); // end block
+
+                        ctx.emit(Instruction::End); // end else (len check)
+
+                        // Push result
+                        ctx.emit(Instruction::LocalGet(result));
+                    }
+
+                    // string_slice(s, start, end) → zero-copy substring
+                    "string_slice" | "intrinsic_string_slice" => {
+                        if args.len() != 3 {
+                            return Err(WasmCompileError {
+                                message: "string_slice requires 3 arguments (str, start, end)"
+                                    .to_string(),
+                                context: "string_slice".to_string(),
+                                span: None,
+                            });
+                        }
+                        let packed = ctx.scope.scratch("__str_slice_packed");
+                        let start = ctx.scope.scratch("__str_slice_start");
+                        let end = ctx.scope.scratch("__str_slice_end");
+
+                        // Evaluate args
+                        Self::compile_expr(ctx, &args[0], func_map)?;
+                        ctx.emit(Instruction::LocalSet(packed));
+                        Self::compile_expr(ctx, &args[1], func_map)?;
+                        ctx.emit(Instruction::LocalSet(start));
+                        Self::compile_expr(ctx, &args[2], func_map)?;
+                        ctx.emit(Instruction::LocalSet(end));
+
+                        // Extract original ptr: (packed >> 32)
+                        ctx.emit(Instruction::LocalGet(packed));
+                        ctx.emit(Instruction::I64Const(32));
+                        ctx.emit(Instruction::I64ShrU);
+                        // new_ptr = original_ptr + start
+                        ctx.emit(Instruction::LocalGet(start));
+                        ctx.emit(Instruction::I64Add);
+                        // shift left 32 to pack as high bits
+                        ctx.emit(Instruction::I64Const(32));
+                        ctx.emit(Instruction::I64Shl);
+                        // new_len = end - start
+                        ctx.emit(Instruction::LocalGet(end));
+                        ctx.emit(Instruction::LocalGet(start));
+                        ctx.emit(Instruction::I64Sub);
+                        // Pack: (new_ptr << 32) | new_len
+                        ctx.emit(Instruction::I64Or);
+                    }
+
+                    // =========================================================
+                    // Tier 1: List & Struct intrinsics (linear memory ops)
+                    // =========================================================
+
+                    // len(list_or_struct) → read length/field_count header at ptr[0]
+                    "len" | "intrinsic_len" => {
+                        if args.len() != 1 {
+                            return Err(WasmCompileError {
+                                message: "len requires 1 argument".to_string(),
+                                context: "intrinsic_len".to_string(),
+                                span: None,
+                            });
+                        }
+                        Self::compile_expr(ctx, &args[0], func_map)?;
+                        ctx.emit(Instruction::I32WrapI64); // ptr
+                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+                    }
+
+                    // list.get(list, index) / list[index] → load list[index]
+                    // ptr + 8 + 8*index
+                    "intrinsic_list_get" | "sys.list.get" | "list.get" | "get_item" => {
+                        if args.len() != 2 {
+                            return Err(WasmCompileError {
+                                message: "list.get requires 2 arguments (list, index)".to_string(),
+                                context: "intrinsic_list_get".to_string(),
+                                span: None,
+                            });
+                        }
+                        // Compile list ptr
+                        Self::compile_expr(ctx, &args[0], func_map)?;
+                        let list_ptr = ctx.scope.scratch("__intrinsic_list_ptr");
+                        ctx.emit(Instruction::LocalSet(list_ptr));
+
+                        // Compute address: ptr + 8 + 8*index
+                        ctx.emit(Instruction::LocalGet(list_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        // Calculate offset: 8 + 8 * index
+                        Self::compile_expr(ctx, &args[1], func_map)?;
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Mul);
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Add);
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I32Add); // base + offset
+
+                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+                    }
+
+                    // list.set(list, index, value) → store value at list[index]
+                    "intrinsic_list_set" | "sys.list.set" | "list.set" => {
+                        if args.len() != 3 {
+                            return Err(WasmCompileError {
+                                message: "list.set requires 3 arguments (list, index, value)"
+                                    .to_string(),
+                                context: "intrinsic_list_set".to_string(),
+                                span: None,
+                            });
+                        }
+                        let list_ptr = ctx.scope.scratch("__intrinsic_list_ptr");
+                        let set_val = ctx.scope.scratch("__intrinsic_set_val");
+
+                        // Compile list ptr
+                        Self::compile_expr(ctx, &args[0], func_map)?;
+                        ctx.emit(Instruction::LocalSet(list_ptr));
+
+                        // Compile value (3rd arg) first, stash it
+                        Self::compile_expr(ctx, &args[2], func_map)?;
+                        ctx.emit(Instruction::LocalSet(set_val));
+
+                        // Compute store address: ptr + 8 + 8*index
+                        ctx.emit(Instruction::LocalGet(list_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        Self::compile_expr(ctx, &args[1], func_map)?;
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Mul);
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Add);
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I32Add); // addr
+
+                        // Store value
+                        ctx.emit(Instruction::LocalGet(set_val));
+                        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+
+                        // Return the list ptr (for chaining)
+                        ctx.emit(Instruction::LocalGet(list_ptr));
+                    }
+
+                    // list.append(list, value) → allocate new list with len+1,
+                    // copy old data, store new value at end, return new ptr
+                    "intrinsic_list_append" | "sys.list.append" | "list.append" => {
+                        if args.len() != 2 {
+                            return Err(WasmCompileError {
+                                message: "list.append requires 2 arguments (list, value)"
+                                    .to_string(),
+                                context: "intrinsic_list_append".to_string(),
+                                span: None,
+                            });
+                        }
+                        let old_ptr = ctx.scope.scratch("__append_old_ptr");
+                        let old_len = ctx.scope.scratch("__append_old_len");
+                        let new_ptr = ctx.scope.scratch("__append_new_ptr");
+                        let append_val = ctx.scope.scratch("__append_val");
+                        let copy_i = ctx.scope.scratch("__append_i");
+
+                        // Compile old list ptr
+                        Self::compile_expr(ctx, &args[0], func_map)?;
+                        ctx.emit(Instruction::LocalSet(old_ptr));
+
+                        // Compile append value
+                        Self::compile_expr(ctx, &args[1], func_map)?;
+                        ctx.emit(Instruction::LocalSet(append_val));
+
+                        // Read old length
+                        ctx.emit(Instruction::LocalGet(old_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+                        ctx.emit(Instruction::LocalSet(old_len));
+
+                        // Allocate new list: 8 + 8*(old_len + 1)
+                        ctx.emit(Instruction::LocalGet(old_len));
+                        ctx.emit(Instruction::I64Const(1));
+                        ctx.emit(Instruction::I64Add);
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Mul);
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Add); // total size
+                        Self::emit_alloc_object(ctx, HEAP_TAG_LIST, func_map, "list.append")?;
+                        ctx.emit(Instruction::LocalSet(new_ptr));
+
+                        // Store new length = old_len + 1
+                        ctx.emit(Instruction::LocalGet(new_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::LocalGet(old_len));
+                        ctx.emit(Instruction::I64Const(1));
+                        ctx.emit(Instruction::I64Add);
+                        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+
+                        // Copy old elements: for i in 0..old_len
+                        ctx.emit(Instruction::I64Const(0));
+                        ctx.emit(Instruction::LocalSet(copy_i));
+
+                        // block { loop {
+                        ctx.emit(Instruction::Block(BlockType::Empty));
+                        ctx.emit(Instruction::Loop(BlockType::Empty));
+
+                        // if i >= old_len, break
+                        ctx.emit(Instruction::LocalGet(copy_i));
+                        ctx.emit(Instruction::LocalGet(old_len));
+                        ctx.emit(Instruction::I64GeS);
+                        ctx.emit(Instruction::BrIf(1));
+
+                        // new_ptr[8 + 8*i] = old_ptr[8 + 8*i]
+                        // dst address
+                        ctx.emit(Instruction::LocalGet(new_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::LocalGet(copy_i));
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Mul);
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Add);
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I32Add); // dst addr
+
+                        // src value: old_ptr[8 + 8*i]
+                        ctx.emit(Instruction::LocalGet(old_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::LocalGet(copy_i));
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Mul);
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Add);
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I32Add); // src addr
+                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+
+                        // store
+                        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+
+                        // i += 1
+                        ctx.emit(Instruction::LocalGet(copy_i));
+                        ctx.emit(Instruction::I64Const(1));
+                        ctx.emit(Instruction::I64Add);
+                        ctx.emit(Instruction::LocalSet(copy_i));
+
+                        ctx.emit(Instruction::Br(0)); // continue loop
+                        ctx.emit(Instruction::End); // end loop
+                        ctx.emit(Instruction::End); // end block
+
+                        // Store appended value at new_ptr[8 + 8*old_len]
+                        ctx.emit(Instruction::LocalGet(new_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::LocalGet(old_len));
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Mul);
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Add);
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I32Add);
+                        ctx.emit(Instruction::LocalGet(append_val));
+                        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+
+                        // A list built just for this call is dead once copied
+                        // (its block starts at the tag word)
+                        if Self::fresh_allocation(&args[0]) == Some("list") {
+                            Self::emit_free(ctx, old_ptr, 8, func_map)?;
+                        }
+
+                        // Return new list ptr
+                        ctx.emit(Instruction::LocalGet(new_ptr));
+                    }
+
+                    // list.pop(list[, index]) → decrement length, return removed element
+                    // Without index: removes last element
+                    "intrinsic_list_pop" | "sys.list.pop" | "list.pop" => {
+                        if args.is_empty() {
+                            return Err(WasmCompileError {
+                                message: "list.pop requires at least 1 argument (list)".to_string(),
+                                context: "intrinsic_list_pop".to_string(),
+                                span: None,
+                            });
+                        }
+                        let list_ptr = ctx.scope.scratch("__pop_list_ptr");
+                        let old_len = ctx.scope.scratch("__pop_old_len");
+
+                        // Compile list ptr
+                        Self::compile_expr(ctx, &args[0], func_map)?;
+                        ctx.emit(Instruction::LocalSet(list_ptr));
+
+                        // Read old length
+                        ctx.emit(Instruction::LocalGet(list_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+                        ctx.emit(Instruction::LocalSet(old_len));
+
+                        // Read last element: ptr[8 + 8*(len-1)]
+                        ctx.emit(Instruction::LocalGet(list_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::LocalGet(old_len));
+                        ctx.emit(Instruction::I64Const(1));
+                        ctx.emit(Instruction::I64Sub);
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Mul);
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Add);
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I32Add);
+                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+
+                        // Decrement length in-place
+                        let popped = ctx.scope.scratch("__pop_result");
+                        ctx.emit(Instruction::LocalSet(popped));
+
+                        ctx.emit(Instruction::LocalGet(list_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::LocalGet(old_len));
+                        ctx.emit(Instruction::I64Const(1));
+                        ctx.emit(Instruction::I64Sub);
+                        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+
+                        // Return popped value
+                        ctx.emit(Instruction::LocalGet(popped));
+                    }
+
+                    // list.delete(list, index) → shift elements left, decrement length
+                    "intrinsic_list_delete" | "sys.list.delete" | "list.delete" => {
+                        if args.len() != 2 {
+                            return Err(WasmCompileError {
+                                message: "list.delete requires 2 arguments (list, index)"
+                                    .to_string(),
+                                context: "intrinsic_list_delete".to_string(),
+                                span: None,
+                            });
+                        }
+                        let list_ptr = ctx.scope.scratch("__del_list_ptr");
+                        let del_idx = ctx.scope.scratch("__del_idx");
+                        let del_len = ctx.scope.scratch("__del_len");
+                        let del_i = ctx.scope.scratch("__del_i");
+
+                        Self::compile_expr(ctx, &args[0], func_map)?;
+                        ctx.emit(Instruction::LocalSet(list_ptr));
+                        Self::compile_expr(ctx, &args[1], func_map)?;
+                        ctx.emit(Instruction::LocalSet(del_idx));
+
+                        // Read length
+                        ctx.emit(Instruction::LocalGet(list_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+                        ctx.emit(Instruction::LocalSet(del_len));
+
+                        // Shift elements left: for i = index..len-1: list[i] = list[i+1]
+                        ctx.emit(Instruction::LocalGet(del_idx));
+                        ctx.emit(Instruction::LocalSet(del_i));
+
+                        ctx.emit(Instruction::Block(BlockType::Empty));
+                        ctx.emit(Instruction::Loop(BlockType::Empty));
+
+                        // if i >= len - 1, break
+                        ctx.emit(Instruction::LocalGet(del_i));
+                        ctx.emit(Instruction::LocalGet(del_len));
+                        ctx.emit(Instruction::I64Const(1));
+                        ctx.emit(Instruction::I64Sub);
+                        ctx.emit(Instruction::I64GeS);
+                        ctx.emit(Instruction::BrIf(1));
+
+                        // list[i] = list[i+1]
+                        // dst: ptr + 8 + 8*i
+                        ctx.emit(Instruction::LocalGet(list_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::LocalGet(del_i));
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Mul);
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Add);
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I32Add);
+
+                        // src: ptr + 8 + 8*(i+1)
+                        ctx.emit(Instruction::LocalGet(list_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::LocalGet(del_i));
+                        ctx.emit(Instruction::I64Const(1));
+                        ctx.emit(Instruction::I64Add);
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Mul);
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Add);
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I32Add);
+                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+                        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+
+                        // i += 1
+                        ctx.emit(Instruction::LocalGet(del_i));
+                        ctx.emit(Instruction::I64Const(1));
+                        ctx.emit(Instruction::I64Add);
+                        ctx.emit(Instruction::LocalSet(del_i));
+
+                        ctx.emit(Instruction::Br(0));
+                        ctx.emit(Instruction::End); // loop
+                        ctx.emit(Instruction::End); // block
+
+                        // Decrement length
+                        ctx.emit(Instruction::LocalGet(list_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::LocalGet(del_len));
+                        ctx.emit(Instruction::I64Const(1));
+                        ctx.emit(Instruction::I64Sub);
+                        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+
+                        // Return list ptr
+                        ctx.emit(Instruction::LocalGet(list_ptr));
+                    }
+
+                    // struct.get(struct, field_index) → load struct[field_index]
+                    "intrinsic_struct_get" | "sys.struct.get" | "struct.get" => {
+                        if args.len() != 2 {
+                            return Err(WasmCompileError {
+                                message: "struct.get requires 2 arguments (struct, field_index)"
+                                    .to_string(),
+                                context: "intrinsic_struct_get".to_string(),
+                                span: None,
+                            });
+                        }
+                        Self::compile_expr(ctx, &args[0], func_map)?;
+                        let s_ptr = ctx.scope.scratch("__sget_ptr");
+                        ctx.emit(Instruction::LocalSet(s_ptr));
+
+                        ctx.emit(Instruction::LocalGet(s_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        Self::compile_expr(ctx, &args[1], func_map)?;
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Mul);
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Add);
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I32Add);
+
+                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+                    }
+
+                    // struct.set(struct, field_index, value) → store at struct[field_index]
+                    "intrinsic_struct_set" | "sys.struct.set" | "struct.set" => {
+                        if args.len() != 3 {
+                            return Err(WasmCompileError {
+                                message:
+                                    "struct.set requires 3 arguments (struct, field_index, value)"
+                                        .to_string(),
+                                context: "intrinsic_struct_set".to_string(),
+                                span: None,
+                            });
+                        }
+                        let s_ptr = ctx.scope.scratch("__sset_ptr");
+                        let s_val = ctx.scope.scratch("__sset_val");
+
+                        Self::compile_expr(ctx, &args[0], func_map)?;
+                        ctx.emit(Instruction::LocalSet(s_ptr));
+                        Self::compile_expr(ctx, &args[2], func_map)?;
+                        ctx.emit(Instruction::LocalSet(s_val));
+
+                        // Compute address: ptr + 8 + 8*field_index
+                        ctx.emit(Instruction::LocalGet(s_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        Self::compile_expr(ctx, &args[1], func_map)?;
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Mul);
+                        ctx.emit(Instruction::I64Const(8));
+                        ctx.emit(Instruction::I64Add);
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I32Add);
+
+                        ctx.emit(Instruction::LocalGet(s_val));
+                        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+
+                        // Return struct ptr
+                        ctx.emit(Instruction::LocalGet(s_ptr));
+                    }
+
+                    // struct.has(struct, field_index) → 1 if field_index < field_count, else 0
+                    "intrinsic_struct_has" | "sys.struct.has" | "struct.has" => {
+                        if args.len() != 2 {
+                            return Err(WasmCompileError {
+                                message: "struct.has requires 2 arguments (struct, field_index)"
+                                    .to_string(),
+                                context: "intrinsic_struct_has".to_string(),
+                                span: None,
+                            });
+                        }
+                        // Read field_count from header
+                        Self::compile_expr(ctx, &args[0], func_map)?;
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+
+                        // Compare: field_index < field_count → 1, else 0
+                        Self::compile_expr(ctx, &args[1], func_map)?;
+                        ctx.emit(Instruction::I64GtS); // field_count > field_index
+                        ctx.emit(Instruction::I64ExtendI32U); // bool → i64
+                    }
+
+                    // sys.type_of(v) → packed type-name string
+                    // Uses the statically known kind when there is one, else
+                    // inspects the heap type tag at v-8. A bare i64 carries no
+                    // runtime type, so integers and booleans of unknown static
+                    // type both report "integer".
+                    "intrinsic_type_of" | "sys.type_of" => {
+                        if args.len() != 1 {
+                            return Err(WasmCompileError {
+                                message: "sys.type_of requires 1 argument".to_string(),
+                                context: "sys.type_of".to_string(),
+                                span: None,
+                            });
+                        }
+                        if let Some(tag) = Self::static_type_of(ctx, &args[0]) {
+                            Self::emit_packed_string(ctx, tag);
+                        } else {
+                            Self::compile_expr(ctx, &args[0], func_map)?;
+                            Self::emit_heap_type_of(ctx);
+                        }
+                    }
+
+                    // =========================================================
+                    // Tier 2: WASI-backed intrinsics
+                    // =========================================================
+
+                    // sys.time.now() → nanoseconds since epoch as i64
+                    // Uses: clock_time_get(clock_id=0 (realtime), precision=1, timestamp_ptr)
+                    "intrinsic_time_now" | "time.now" | "sys.time.now" => {
+                        let ts_ptr = ctx.scope.scratch("__time_ts_ptr");
+
+                        // Allocate 8 bytes for the timestamp result
+                        ctx.emit(Instruction::I64Const(8));
+                        if let Some(&alloc_idx) = func_map.get("__alloc") {
+                            ctx.emit(Instruction::Call(alloc_idx));
+                        } else {
+                            return Err(WasmCompileError {
+                                message: "__alloc not found".to_string(),
+                                context: "sys.time.now".to_string(),
+                                span: None,
+                            });
+                        }
+                        ctx.emit(Instruction::LocalSet(ts_ptr));
+
+                        // Call clock_time_get(0, 1, ts_ptr)
+                        ctx.emit(Instruction::I32Const(0)); // clock_id = REALTIME
+                        ctx.emit(Instruction::I64Const(1)); // precision = 1ns
+                        ctx.emit(Instruction::LocalGet(ts_ptr));
+                        ctx.emit(Instruction::I32WrapI64); // ptr as i32
+                        ctx.emit(Instruction::Call(Self::import_index(
+                            func_map,
+                            "clock_time_get",
+                        )?));
+                        ctx.emit(Instruction::Drop); // drop errno
+
+                        // Load the 8-byte timestamp
+                        ctx.emit(Instruction::LocalGet(ts_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+                    }
+
+                    // sys.io.read_line() → reads from stdin (fd=0) into buffer, returns i64 bytes read
+                    // Uses: fd_read(fd=0, iovs_ptr, iovs_len=1, nread_ptr) -> errno
+                    "intrinsic_io_read_line" | "sys.io.read_line" => {
+                        let buf_ptr = ctx.scope.scratch("__read_buf_ptr");
+                        let iov_ptr = ctx.scope.scratch("__read_iov_ptr");
+                        let nread_ptr = ctx.scope.scratch("__read_nread_ptr");
+
+                        // Allocate 1024 bytes for read buffer
+                        ctx.emit(Instruction::I64Const(1024));
+                        if let Some(&alloc_idx) = func_map.get("__alloc") {
+                            ctx.emit(Instruction::Call(alloc_idx));
+                        } else {
+                            return Err(WasmCompileError {
+                                message: "__alloc not found".to_string(),
+                                context: "sys.io.read_line".to_string(),
+                                span: None,
+                            });
+                        }
+                        ctx.emit(Instruction::LocalSet(buf_ptr));
+
+                        // Allocate 8 bytes for iovec: [buf_ptr:i32, buf_len:i32]
+                        ctx.emit(Instruction::I64Const(8));
+                        if let Some(&alloc_idx) = func_map.get("__alloc") {
+                            ctx.emit(Instruction::Call(alloc_idx));
+                        } else {
+                            return Err(WasmCompileError {
+                                message: "__alloc not found".to_string(),
+                                context: "sys.io.read_line iov".to_string(),
+                                span: None,
+                            });
+                        }
+                        ctx.emit(Instruction::LocalSet(iov_ptr));
+
+                        // Allocate 4 bytes for nread
+                        ctx.emit(Instruction::I64Const(4));
+                        if let Some(&alloc_idx) = func_map.get("__alloc") {
+                            ctx.emit(Instruction::Call(alloc_idx));
+                        } else {
+                            return Err(WasmCompileError {
+                                message: "__alloc not found".to_string(),
+                                context: "sys.io.read_line nread".to_string(),
+                                span: None,
+                            });
+                        }
+                        ctx.emit(Instruction::LocalSet(nread_ptr));
+
+                        // Fill iovec: iov[0] = buf_ptr, iov[4] = 1024
+                        ctx.emit(Instruction::LocalGet(iov_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::LocalGet(buf_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I32Store(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 2,
+                            memory_index: 0,
+                        }));
+                        ctx.emit(Instruction::LocalGet(iov_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I32Const(1024));
+                        ctx.emit(Instruction::I32Store(wasm_encoder::MemArg {
+                            offset: 4,
+                            align: 2,
+                            memory_index: 0,
+                        }));
+
+                        // Call fd_read(0, iov_ptr, 1, nread_ptr)
+                        ctx.emit(Instruction::I32Const(0)); // fd = stdin
+                        ctx.emit(Instruction::LocalGet(iov_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I32Const(1)); // iovs_len
+                        ctx.emit(Instruction::LocalGet(nread_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::Call(Self::import_index(func_map, "fd_read")?));
+                        ctx.emit(Instruction::Drop); // drop errno
+
+                        // Return nread as i64
+                        ctx.emit(Instruction::LocalGet(nread_ptr));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::I32Load(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 2,
+                            memory_index: 0,
+                        }));
+                        ctx.emit(Instruction::I64ExtendI32U);
+                    }
+
+                    // sys.crypto.random_bytes(n) → fills buffer with n random bytes, returns ptr as i64
+                    // Uses: random_get(buf_ptr, buf_len) -> errno
+                    "intrinsic_crypto_random_bytes" | "sys.crypto.random_bytes" => {
+                        if args.len() != 1 {
+                            return Err(WasmCompileError {
+                                message: "sys.crypto.random_bytes requires 1 argument (byte_count)"
+                                    .to_string(),
+                                context: "sys.crypto.random_bytes".to_string(),
+                                span: None,
+                            });
+                        }
+                        let rand_len = ctx.scope.scratch("__rand_len");
+                        let rand_buf = ctx.scope.scratch("__rand_buf");
+
+                        // Compile byte count
+                        Self::compile_expr(ctx, &args[0], func_map)?;
+                        ctx.emit(Instruction::LocalSet(rand_len));
+
+                        // Allocate buffer
+                        ctx.emit(Instruction::LocalGet(rand_len));
+                        if let Some(&alloc_idx) = func_map.get("__alloc") {
+                            ctx.emit(Instruction::Call(alloc_idx));
+                        } else {
+                            return Err(WasmCompileError {
+                                message: "__alloc not found".to_string(),
+                                context: "sys.crypto.random_bytes".to_string(),
+                                span: None,
+                            });
+                        }
+                        ctx.emit(Instruction::LocalSet(rand_buf));
+
+                        // Call random_get(buf_ptr, buf_len)
+                        ctx.emit(Instruction::LocalGet(rand_buf));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::LocalGet(rand_len));
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::Call(Self::import_index(
+                            func_map,
+                            "random_get",
+                        )?));
+                        ctx.emit(Instruction::Drop); // drop errno
+
+                        // Return buffer ptr as i64
+                        ctx.emit(Instruction::LocalGet(rand_buf));
+                    }
+
+                    // sys.random.int(lo, hi) → uniform i64 in [lo, hi]
+                    // Draws 8 bytes via random_get into scratch bytes 0–7 and
+                    // rejects draws at or above the largest multiple of the
+                    // span, so the final `% span` has no modulo bias.
+                    "sys.random.int" => {
+                        if args.len() != 2 {
+                            return Err(WasmCompileError {
+                                message: "sys.random.int requires 2 arguments (lo, hi)".to_string(),
+                                context: "sys.random.int".to_string(),
+                                span: None,
+                            });
+                        }
+                        let lo = ctx.scope.scratch("__rand_lo");
+                        let span = ctx.scope.scratch("__rand_span");
+                        let zone = ctx.scope.scratch("__rand_zone");
+                        let draw = ctx.scope.scratch("__rand_draw");
+
+                        Self::compile_expr(ctx, &args[0], func_map)?;
+                        ctx.emit(Instruction::LocalSet(lo));
+                        Self::compile_expr(ctx, &args[1], func_map)?;
+                        ctx.emit(Instruction::LocalSet(span));
+
+                        if ctx.bounds_checks {
+                            ctx.emit(Instruction::LocalGet(lo));
+                            ctx.emit(Instruction::LocalGet(span));
+                            ctx.emit(Instruction::I64GtS);
+                            ctx.emit(Instruction::If(BlockType::Empty));
+                            ctx.emit(Instruction::Unreachable);
+                            ctx.emit(Instruction::End);
+                        }
+
+                        // span = hi - lo + 1; wraps to 0 for the full i64 range
+                        ctx.emit(Instruction::LocalGet(span));
+                        ctx.emit(Instruction::LocalGet(lo));
+                        ctx.emit(Instruction::I64Sub);
+                        ctx.emit(Instruction::I64Const(1));
+                        ctx.emit(Instruction::I64Add);
+                        ctx.emit(Instruction::LocalSet(span));
+
+                        // zone = (u64::MAX / span) * span, or 0 (accept all) when span == 0
+                        ctx.emit(Instruction::LocalGet(span));
+                        ctx.emit(Instruction::I64Eqz);
+                        ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+                        ctx.emit(Instruction::I64Const(0));
+                        ctx.emit(Instruction::Else);
+                        ctx.emit(Instruction::I64Const(-1));
+                        ctx.emit(Instruction::LocalGet(span));
+                        ctx.emit(Instruction::I64DivU);
+                        ctx.emit(Instruction::LocalGet(span));
+                        ctx.emit(Instruction::I64Mul);
+                        ctx.emit(Instruction::End);
+                        ctx.emit(Instruction::LocalSet(zone));
+
+                        // loop { draw = random_get(0, 8); while span != 0 && draw >= zone }
+                        ctx.emit(Instruction::Loop(BlockType::Empty));
+                        ctx.emit(Instruction::I32Const(0));
+                        ctx.emit(Instruction::I32Const(8));
+                        ctx.emit(Instruction::Call(Self::import_index(
+                            func_map,
+                            "random_get",
+                        )?));
+                        ctx.emit(Instruction::Drop); // drop errno
+                        ctx.emit(Instruction::I32Const(0));
+                        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 3,
+                            memory_index: 0,
+                        }));
+                        ctx.emit(Instruction::LocalSet(draw));
+                        ctx.emit(Instruction::LocalGet(span));
+                        ctx.emit(Instruction::I64Const(0));
+                        ctx.emit(Instruction::I64Ne);
+                        ctx.emit(Instruction::LocalGet(draw));
+                        ctx.emit(Instruction::LocalGet(zone));
+                        ctx.emit(Instruction::I64GeU);
+                        ctx.emit(Instruction::I32And);
+                        ctx.emit(Instruction::BrIf(0));
+                        ctx.emit(Instruction::End);
+
+                        // lo + (span == 0 ? draw : draw % span)
+                        ctx.emit(Instruction::LocalGet(lo));
+                        ctx.emit(Instruction::LocalGet(span));
+                        ctx.emit(Instruction::I64Eqz);
+                        ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+                        ctx.emit(Instruction::LocalGet(draw));
+                        ctx.emit(Instruction::Else);
+                        ctx.emit(Instruction::LocalGet(draw));
+                        ctx.emit(Instruction::LocalGet(span));
+                        ctx.emit(Instruction::I64RemU);
+                        ctx.emit(Instruction::End);
+                        ctx.emit(Instruction::I64Add);
+                    }
+
+                    // sys.exit(code?) → proc_exit, never returns
+                    "sys.exit" | "exit" | "intrinsic_exit" => {
+                        if args.is_empty() {
+                            ctx.emit(Instruction::I32Const(0)); // default exit code
+                        } else {
+                            Self::compile_expr(ctx, &args[0], func_map)?;
+                            ctx.emit(Instruction::I32WrapI64);
+                        }
+                        ctx.emit(Instruction::Call(Self::import_index(
+                            func_map,
+                            "proc_exit",
+                        )?));
+                        // proc_exit never returns, but we need a value on stack for WASM validation
+                        ctx.emit(Instruction::Unreachable);
+                    }
+
+                    // fd_close(fd) → close file descriptor
+                    "fd_close" | "sys.io.close" => {
+                        if args.len() != 1 {
+                            return Err(WasmCompileError {
+                                message: "fd_close requires 1 argument (fd)".to_string(),
+                                context: "fd_close".to_string(),
+                                span: None,
+                            });
+                        }
+                        Self::compile_expr(ctx, &args[0], func_map)?;
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::Call(Self::import_index(func_map, "fd_close")?));
+                        ctx.emit(Instruction::I64ExtendI32U); // errno as i64
+                    }
+
+                    // sys.args.count() → number of arguments, program name included
+                    "sys.args.count" | "intrinsic_args_count" => {
+                        if !args.is_empty() {
+                            return Err(WasmCompileError {
+                                message: "sys.args.count takes no arguments".to_string(),
+                                context: "sys.args.count".to_string(),
+                                span: None,
+                            });
+                        }
+                        // args_sizes_get(argc=48, buf_size=52)
+                        ctx.emit(Instruction::I32Const(48));
+                        ctx.emit(Instruction::I32Const(52));
+                        ctx.emit(Instruction::Call(Self::import_index(
+                            func_map,
+                            "args_sizes_get",
+                        )?));
+                        Self::emit_trap_on_errno(ctx);
+                        ctx.emit(Instruction::I32Const(48));
+                        ctx.emit(Instruction::I64Load32U(wasm_encoder::MemArg {
+                            offset: 0,
+                            align: 2,
+                            memory_index: 0,
+                        }));
+                    }
+
+                    // sys.fs.read(path) → file contents as a string
+                    // sys.fs.write(path, text) / sys.fs.append(path, text) → Unit
+                    // sys.args.get(index) / sys.env.get(name) → string
+                    // Calls the helper; see `register_helpers`.
+                    "sys.fs.read"
+                    | "sys_fs_read"
+                    | "intrinsic_fs_read"
+                    | "sys.fs.write"
+                    | "sys_fs_write"
+                    | "intrinsic_fs_write"
+                    | "sys.fs.append"
+                    | "sys_fs_append"
+                    | "intrinsic_fs_append"
+                    | "sys.fs.open"
+                    | "intrinsic_fs_open"
+                    | "sys.fs.read_chunk"
+                    | "intrinsic_fs_read_chunk"
+                    | "sys.fs.seek"
+                    | "intrinsic_fs_seek"
+                    | "sys.fs.close"
+                    | "intrinsic_fs_close"
+                    | "sys.args.get"
+                    | "intrinsic_args_get"
+                    | "sys.env.get"
+                    | "intrinsic_env_get"
+                    | "map.get"
+                    | "intrinsic_map_get"
+                    | "map.set"
+                    | "intrinsic_map_set"
+                    | "map.keys"
+                    | "intrinsic_map_keys"
+                    | "string_char_len"
+                    | "intrinsic_string_char_len"
+                    | "string_char_at"
+                    | "intrinsic_string_char_at"
+                    | "string_slice_chars"
+                    | "intrinsic_string_slice_chars"
+                    | "string_to_upper"
+                    | "intrinsic_string_to_upper"
+                    | "string_to_lower"
+                    | "intrinsic_string_to_lower"
+                    | "pvec.new"
+                    | "sys.pvec.new"
+                    | "intrinsic_pvec_new"
+                    | "pvec.conj"
+                    | "sys.pvec.conj"
+                    | "intrinsic_pvec_conj"
+                    | "pvec.get"
+                    | "sys.pvec.get"
+                    | "intrinsic_pvec_get"
+                    | "pvec.assoc"
+                    | "sys.pvec.assoc"
+                    | "intrinsic_pvec_assoc"
+                    | "pvec.pop"
+                    | "sys.pvec.pop"
+                    | "intrinsic_pvec_pop"
+                    | "pvec.len"
+                    | "sys.pvec.len"
+                    | "intrinsic_pvec_len"
+                    | "pmap.new"
+                    | "sys.pmap.new"
+                    | "intrinsic_pmap_new"
+                    | "pmap.assoc"
+                    | "sys.pmap.assoc"
+                    | "intrinsic_pmap_assoc"
+                    | "pmap.dissoc"
+                    | "sys.pmap.dissoc"
+                    | "intrinsic_pmap_dissoc"
+                    | "pmap.get"
+                    | "sys.pmap.get"
+                    | "intrinsic_pmap_get"
+                    | "pmap.keys"
+                    | "sys.pmap.keys"
+                    | "intrinsic_pmap_keys"
+                    | "pmap.merge"
+                    | "sys.pmap.merge"
+                    | "intrinsic_pmap_merge"
+                    | "bigint.from_string"
+                    | "sys.bigint.from_string"
+                    | "intrinsic_bigint_from_string"
+                    | "bigint.add"
+                    | "sys.bigint.add"
+                    | "intrinsic_bigint_add"
+                    | "bigint.mul"
+                    | "sys.bigint.mul"
+                    | "intrinsic_bigint_mul"
+                    | "bigint.mod"
+                    | "sys.bigint.mod"
+                    | "intrinsic_bigint_mod"
+                    | "bigint.pow_mod"
+                    | "sys.bigint.pow_mod"
+                    | "intrinsic_bigint_pow_mod"
+                    | "bigint.cmp"
+                    | "sys.bigint.cmp"
+                    | "intrinsic_bigint_cmp"
+                    | "bigint.to_string"
+                    | "sys.bigint.to_string"
+                    | "intrinsic_bigint_to_string"
+                    | "math.Tensor"
+                    | "intrinsic_math_tensor"
+                    | "math.dot"
+                    | "intrinsic_math_dot"
+                    | "math.matmul"
+                    | "intrinsic_math_matmul"
+                    | "math.transpose"
+                    | "intrinsic_math_transpose"
+                    | "math.add"
+                    | "intrinsic_math_tensor_add"
+                    | "math.sub"
+                    | "intrinsic_math_tensor_sub"
+                    | "math.mul_scalar"
+                    | "intrinsic_math_mul_scalar"
+                    | "intrinsic_crypto_sha256"
+                    | "sys.crypto.sha256"
+                    | "intrinsic_crypto_keccak256"
+                    | "sys.crypto.keccak256"
+                    | "intrinsic_crypto_blake3"
+                    | "sys.crypto.blake3" => {
+                        let (helper, arity, _, _) = WASI_HELPERS
+                            .iter()
+                            .chain(MAP_HELPERS)
+                            .chain(STRING_HELPERS)
+                            .chain(PERSISTENT_HELPERS)
+                            .chain(BIGINT_HELPERS)
+                            .chain(TENSOR_HELPERS)
+                            .chain(DIGEST_HELPERS)
+                            .find(|(_, _, intrinsics, _)| {
+                                intrinsics.contains(&function_hash.as_str())
+                            })
+                            .expect("every helper intrinsic has a helper");
+                        if args.len() != *arity {
+                            return Err(WasmCompileError {
+                                message: format!(
+                                    "{} requires {} argument(s), got {}",
+                                    function_hash,
+                                    arity,
+                                    args.len()
+                                ),
+                                context: function_hash.clone(),
+                                span: None,
+                            });
+                        }
+                        for arg in args {
+                            Self::compile_expr(ctx, arg, func_map)?;
+                        }
+                        ctx.emit(Instruction::Call(func_map[*helper]));
+                    }
+
+                    // =============================================================
+                    // Tier 3: Ark Host Import Intrinsics
+                    // =============================================================
+
+                    // --- Math unary: f64 in, f64 out ---
+                    // math.sin(x), math.cos(x), math.tan(x)
+                    // math.asin(x), math.acos(x), math.atan(x), math.sqrt(x)
+                    "intrinsic_math_sin" | "math.sin" => {
+                        if args.len() != 1 {
                             return Err(WasmCompileError {
                                 message: "math.sin needs 1 arg".into(),
                                 context: "math.sin".into(),
+                                span: None,
                             });
                         }
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::Call(ARK_HOST_MATH_SIN_FUNC_IDX));
+                        Self::compile_float_math(ctx, args, "math_sin", func_map)?;
                     }
                     "intrinsic_math_cos" | "math.cos" => {
                         if args.len() != 1 {
                             return Err(WasmCompileError {
                                 message: "math.cos needs 1 arg".into(),
                                 context: "math.cos".into(),
+                                span: None,
                             });
                         }
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::Call(ARK_HOST_MATH_COS_FUNC_IDX));
+                        Self::compile_float_math(ctx, args, "math_cos", func_map)?;
                     }
                     "intrinsic_math_tan" | "math.tan" => {
                         if args.len() != 1 {
                             return Err(WasmCompileError {
                                 message: "math.tan needs 1 arg".into(),
                                 context: "math.tan".into(),
+                                span: None,
                             });
                         }
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::Call(ARK_HOST_MATH_TAN_FUNC_IDX));
+                        Self::compile_float_math(ctx, args, "math_tan", func_map)?;
                     }
                     "intrinsic_math_asin" | "math.asin" => {
                         if args.len() != 1 {
                             return Err(WasmCompileError {
                                 message: "math.asin needs 1 arg".into(),
                                 context: "math.asin".into(),
+                                span: None,
                             });
                         }
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::Call(ARK_HOST_MATH_ASIN_FUNC_IDX));
+                        Self::compile_float_math(ctx, args, "math_asin", func_map)?;
                     }
                     "intrinsic_math_acos" | "math.acos" => {
                         if args.len() != 1 {
                             return Err(WasmCompileError {
                                 message: "math.acos needs 1 arg".into(),
                                 context: "math.acos".into(),
+                                span: None,
                             });
                         }
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::Call(ARK_HOST_MATH_ACOS_FUNC_IDX));
+                        Self::compile_float_math(ctx, args, "math_acos", func_map)?;
                     }
                     "intrinsic_math_atan" | "math.atan" => {
                         if args.len() != 1 {
                             return Err(WasmCompileError {
                                 message: "math.atan needs 1 arg".into(),
                                 context: "math.atan".into(),
+                                span: None,
                             });
                         }
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::Call(ARK_HOST_MATH_ATAN_FUNC_IDX));
+                        Self::compile_float_math(ctx, args, "math_atan", func_map)?;
                     }
                     "intrinsic_math_sqrt" | "math.sqrt" => {
                         if args.len() != 1 {
                             return Err(WasmCompileError {
                                 message: "math.sqrt needs 1 arg".into(),
                                 context: "math.sqrt".into(),
+                                span: None,
+                            });
+                        }
+                        Self::compile_float_math(ctx, args, "math_sqrt", func_map)?;
+                    }
+
+                    // --- Rounding: native f64 opcodes, no host call ---
+                    // Input is a float (or an integer, converted); the result
+                    // is a plain integer, matching the interpreter.
+                    "intrinsic_math_floor" | "math.floor" | "sys.math.floor" => {
+                        Self::compile_rounding(ctx, args, func_map, Instruction::F64Floor)?;
+                    }
+                    "intrinsic_math_ceil" | "math.ceil" | "sys.math.ceil" => {
+                        Self::compile_rounding(ctx, args, func_map, Instruction::F64Ceil)?;
+                    }
+                    "intrinsic_math_trunc" | "math.trunc" | "sys.math.trunc" => {
+                        Self::compile_rounding(ctx, args, func_map, Instruction::F64Trunc)?;
+                    }
+                    "intrinsic_math_round" | "math.round" | "sys.math.round" => {
+                        if args.len() != 1 {
+                            return Err(WasmCompileError {
+                                message: "math.round needs 1 arg".into(),
+                                context: "math.round".into(),
+                                span: None,
+                            });
+                        }
+                        // F64Nearest rounds half-to-even, but the interpreter
+                        // rounds half away from zero. Reconcile by computing
+                        // trunc(x) + (|x - trunc(x)| >= 0.5 ? sign(x) : 0).
+                        let x_bits = ctx.scope.scratch("__round_x");
+                        Self::compile_f64_operand(ctx, &args[0], func_map)?;
+                        ctx.emit(Instruction::I64ReinterpretF64);
+                        ctx.emit(Instruction::LocalSet(x_bits));
+                        // trunc(x)
+                        ctx.emit(Instruction::LocalGet(x_bits));
+                        ctx.emit(Instruction::F64ReinterpretI64);
+                        ctx.emit(Instruction::F64Trunc);
+                        // copysign(1.0, x), or 0.0 below the midpoint
+                        ctx.emit(Instruction::F64Const(1.0));
+                        ctx.emit(Instruction::LocalGet(x_bits));
+                        ctx.emit(Instruction::F64ReinterpretI64);
+                        ctx.emit(Instruction::F64Copysign);
+                        ctx.emit(Instruction::F64Const(0.0));
+                        ctx.emit(Instruction::LocalGet(x_bits));
+                        ctx.emit(Instruction::F64ReinterpretI64);
+                        ctx.emit(Instruction::LocalGet(x_bits));
+                        ctx.emit(Instruction::F64ReinterpretI64);
+                        ctx.emit(Instruction::F64Trunc);
+                        ctx.emit(Instruction::F64Sub);
+                        ctx.emit(Instruction::F64Abs);
+                        ctx.emit(Instruction::F64Const(0.5));
+                        ctx.emit(Instruction::F64Ge);
+                        ctx.emit(Instruction::Select);
+                        ctx.emit(Instruction::F64Add);
+                        ctx.emit(Instruction::I64TruncSatF64S);
+                    }
+
+                    // --- Math binary ---
+                    "intrinsic_math_atan2" | "math.atan2" => {
+                        if args.len() != 2 {
+                            return Err(WasmCompileError {
+                                message: "math.atan2 needs 2 args".into(),
+                                context: "math.atan2".into(),
+                                span: None,
+                            });
+                        }
+                        Self::compile_float_math(ctx, args, "math_atan2", func_map)?;
+                    }
+                    "intrinsic_math_pow" | "math.pow" => {
+                        if args.len() != 2 {
+                            return Err(WasmCompileError {
+                                message: "math.pow needs 2 args".into(),
+                                context: "math.pow".into(),
+                                span: None,
+                            });
+                        }
+                        Self::compile_float_math(ctx, args, "math_pow", func_map)?;
+                    }
+
+                    // --- Math ternary ---
+                    "intrinsic_pow_mod" | "math.pow_mod" | "sys.math.pow_mod" => {
+                        if args.len() != 3 {
+                            return Err(WasmCompileError {
+                                message: "pow_mod needs 3 args".into(),
+                                context: "pow_mod".into(),
+                                span: None,
+                            });
+                        }
+                        Self::compile_expr(ctx, &args[0], func_map)?; // base
+                        Self::compile_expr(ctx, &args[1], func_map)?; // exp
+                        Self::compile_expr(ctx, &args[2], func_map)?; // modulus
+                        ctx.emit(Instruction::Call(Self::import_index(
+                            func_map,
+                            "math_pow_mod",
+                        )?));
+                    }
+
+                    // --- Crypto: SHA-512 ---
+                    // sys.crypto.sha512(data_ptr) => allocates 64-byte output, returns ptr as i64
+                    "intrinsic_crypto_sha512" | "sys.crypto.sha512" => {
+                        // Arg is the data pointer (packed: upper 32 = ptr, lower 32 = len)
+                        // For simplicity: arg[0] = ptr (i64), arg[1] = len (i64)
+                        // Host call: crypto_sha512(data_ptr:i32, data_len:i32, out_ptr:i32) -> i32
+                        if args.len() < 2 {
+                            return Err(WasmCompileError {
+                                message: "sha512 needs 2 args (ptr, len)".to_string(),
+                                context: "sha512".to_string(),
+                                span: None,
+                            });
+                        }
+                        // Allocate 64 bytes for output
+                        ctx.emit(Instruction::I64Const(64));
+                        let alloc_idx = *func_map.get("__alloc").expect("operation failed");
+                        ctx.emit(Instruction::Call(alloc_idx));
+                        // out_ptr is on stack as i64; save to local
+                        let out_local = ctx.scope.scratch("__host_out_sha512");
+                        ctx.emit(Instruction::LocalSet(out_local));
+
+                        // Push args: data_ptr (i32), data_len (i32), out_ptr (i32)
+                        Self::compile_expr(ctx, &args[0], func_map)?;
+                        ctx.emit(Instruction::I32WrapI64); // data_ptr
+                        Self::compile_expr(ctx, &args[1], func_map)?;
+                        ctx.emit(Instruction::I32WrapI64); // data_len
+                        ctx.emit(Instruction::LocalGet(out_local));
+                        ctx.emit(Instruction::I32WrapI64); // out_ptr
+
+                        ctx.emit(Instruction::Call(Self::import_index(
+                            func_map,
+                            "crypto_sha512",
+                        )?));
+                        ctx.emit(Instruction::Drop); // drop errno
+                        ctx.emit(Instruction::LocalGet(out_local)); // return out_ptr as i64
+                    }
+
+                    // --- JSON ---
+                    "sys.json.parse" | "intrinsic_json_parse" => {
+                        if args.len() < 2 {
+                            return Err(WasmCompileError {
+                                message: "json.parse needs 2 args (str_ptr, str_len)".to_string(),
+                                context: "json.parse".to_string(),
+                                span: None,
+                            });
+                        }
+                        // Allocate output buffer (4KB)
+                        ctx.emit(Instruction::I64Const(4096));
+                        let alloc_idx = *func_map.get("__alloc").expect("operation failed");
+                        ctx.emit(Instruction::Call(alloc_idx));
+                        let out_local = ctx.scope.scratch("__host_out_sha512");
+                        ctx.emit(Instruction::LocalSet(out_local));
+
+                        Self::compile_expr(ctx, &args[0], func_map)?;
+                        ctx.emit(Instruction::I32WrapI64);
+                        Self::compile_expr(ctx, &args[1], func_map)?;
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::LocalGet(out_local));
+                        ctx.emit(Instruction::I32WrapI64);
+
+                        ctx.emit(Instruction::Call(Self::import_index(
+                            func_map,
+                            "json_parse",
+                        )?));
+                        ctx.emit(Instruction::I64ExtendI32U); // bytes written as i64
+                    }
+
+                    "sys.json.stringify" | "intrinsic_json_stringify" => {
+                        if args.len() < 2 {
+                            return Err(WasmCompileError {
+                                message: "json.stringify needs 2 args (val_ptr, val_len)"
+                                    .to_string(),
+                                context: "json.stringify".to_string(),
+                                span: None,
+                            });
+                        }
+                        ctx.emit(Instruction::I64Const(4096));
+                        let alloc_idx = *func_map.get("__alloc").expect("operation failed");
+                        ctx.emit(Instruction::Call(alloc_idx));
+                        let out_local = ctx.scope.scratch("__host_out_sha512");
+                        ctx.emit(Instruction::LocalSet(out_local));
+
+                        Self::compile_expr(ctx, &args[0], func_map)?;
+                        ctx.emit(Instruction::I32WrapI64);
+                        Self::compile_expr(ctx, &args[1], func_map)?;
+                        ctx.emit(Instruction::I32WrapI64);
+                        ctx.emit(Instruction::LocalGet(out_local));
+                        ctx.emit(Instruction::I32WrapI64);
+
+                        ctx.emit(Instruction::Call(Self::import_index(
+                            func_map,
+                            "json_stringify",
+                        )?));
+                        ctx.emit(Instruction::I64ExtendI32U);
+                    }
+
+                    // --- AI ---
+                    "intrinsic_ask_ai" | "sys.ai.ask" | "ai.ask" => {
+                        // ask_ai(prompt_ptr, prompt_len, out_ptr, out_cap) -> bytes_written
+                        if args.len() < 2 {
+                            return Err(WasmCompileError {
+                                message: "ask_ai needs 2 args (prompt_ptr, prompt_len)".to_string(),
+                                context: "ask_ai".to_string(),
+                                span: None,
                             });
                         }
+                        // Allocate 4KB output buffer
+                        ctx.emit(Instruction::I64Const(4096));
+                        let alloc_idx = *func_map.get("__alloc").expect("operation failed");
+                        ctx.emit(Instruction::Call(alloc_idx));
+                        let out_local = ctx.scope.scratch("__host_out_sha512");
+                        ctx.emit(Instruction::LocalSet(out_local));
+
                         Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::Call(ARK_HOST_MATH_SQRT_FUNC_IDX));
+                        ctx.emit(Instruction::I32WrapI64); // prompt_ptr
+                        Self::compile_expr(ctx, &args[1], func_map)?;
+                        ctx.emit(Instruction::I32WrapI64); // prompt_len
+                        ctx.emit(Instruction::LocalGet(out_local));
+                        ctx.emit(Instruction::I32WrapI64); // out_ptr
+                        ctx.emit(Instruction::I32Const(4096)); // out_cap
+
+                        ctx.emit(Instruction::Call(Self::import_index(func_map, "ask_ai")?));
+                        ctx.emit(Instruction::I64ExtendI32U); // bytes written as i64
+                    }
+
+                    // A local holding a function value
+                    name if !func_map.contains_key(name) && ctx.scope.get(name).is_some() => {
+                        Self::compile_indirect_call(ctx, name, args, func_map)?;
+                    }
+
+                    _ => {
+                        // A user function takes its float arguments and
+                        // returns its float result as real f64s
+                        let native = ctx.declared_params.contains_key(function_hash.as_str());
+                        let returns_f64 = native
+                            && ctx
+                                .float_signatures
+                                .get(function_hash)
+                                .is_some_and(|(_, r)| *r);
+                        Self::check_call_args(ctx, function_hash, args)?;
+                        Self::compile_call_args(ctx, function_hash, args, native, func_map)?;
+                        // Look up function index
+                        if let Some(&func_idx) = func_map.get(function_hash) {
+                            ctx.emit(Instruction::Call(func_idx));
+                            if returns_f64 {
+                                ctx.emit(Instruction::I64ReinterpretF64);
+                            }
+                        } else {
+                            return Err(WasmCompileError {
+                                message: format!("Unknown function: {}", function_hash),
+                                context: "compile_expr::Call".to_string(),
+                                span: None,
+                            });
+                        }
                     }
+                }
+                Ok(())
+            }
+
+            // -----------------------------------------------------------------
+            // List literal → allocate in linear memory via __alloc
+            // Layout: [length: i64 (8B)] [elem_0: i64 (8B)] [elem_1: i64 (8B)] ...
+            // (preceded by the heap type tag at ptr-8)
+            // Returns: ptr as i64
+            // -----------------------------------------------------------------
+            Expression::List(items) => {
+                let list_len = items.len();
+                let alloc_size = 8 + 8 * list_len; // header + elements
+
+                ctx.emit(Instruction::I64Const(alloc_size as i64));
+                Self::emit_alloc_object(ctx, HEAP_TAG_LIST, func_map, "Expression::List")?;
+
+                // Store returned ptr in a local
+                let list_ptr = ctx.scope.scratch("__list_ptr");
+                ctx.emit(Instruction::LocalSet(list_ptr));
+
+                // Store length at ptr[0] (i64)
+                ctx.emit(Instruction::LocalGet(list_ptr));
+                ctx.emit(Instruction::I32WrapI64); // memory address must be i32
+                ctx.emit(Instruction::I64Const(list_len as i64));
+                ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                    offset: 0,
+                    align: 3, // 8-byte aligned
+                    memory_index: 0,
+                }));
+
+                // Store each element at ptr + 8 + 8*i
+                for (i, item) in items.iter().enumerate() {
+                    ctx.emit(Instruction::LocalGet(list_ptr));
+                    ctx.emit(Instruction::I32WrapI64);
+                    Self::compile_expr(ctx, item, func_map)?;
+                    ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                        offset: (8 + 8 * i) as u64,
+                        align: 3,
+                        memory_index: 0,
+                    }));
+                }
+
+                // Push ptr as the list value
+                ctx.emit(Instruction::LocalGet(list_ptr));
+                Ok(())
+            }
+
+            // -----------------------------------------------------------------
+            // Struct init → allocate in linear memory via __alloc
+            // Layout: [field_count: i64 (8B)] [field_0: i64] [field_1: i64] ...
+            // (preceded by the heap type tag at ptr-8)
+            // Returns: ptr as i64
+            // -----------------------------------------------------------------
+            Expression::StructInit { fields } => {
+                Self::compile_struct_init(ctx, fields, None, func_map)
+            }
+
+            // -----------------------------------------------------------------
+            // Map literal → empty map plus one `__map_set` call per entry
+            // Layout: [count: i64 (8B)] [capacity: i64] [table ptr: i64]
+            // (preceded by the heap type tag at ptr-8; see `HEAP_TAG_MAP`)
+            // Returns: ptr as i64
+            // -----------------------------------------------------------------
+            Expression::MapInit { entries } => Self::compile_map_init(ctx, entries, func_map),
+
+            // -----------------------------------------------------------------
+            // Field access → load from linear memory
+            // Struct fields are stored sequentially starting at ptr+8.
+            // The field's slot comes from the object's static shape (see
+            // `static_shape_of`) when known, else from the declared structs
+            // (see `declared_field_slot`); failing both, the first field is
+            // loaded. `.len`/`.length` on anything else reads a list header.
+            // -----------------------------------------------------------------
+            Expression::GetField { obj, field } => {
+                // Slot of `field` when the object's struct layout is known
+                let known_slot = Self::static_shape_of(ctx, obj)
+                    .and_then(|shape| shape.iter().position(|f| f == field));
+
+                // Compile the object (should return a ptr as i64)
+                Self::compile_expr(ctx, obj, func_map)?;
+
+                let obj_ptr = ctx.scope.scratch("__getfield_ptr");
+                ctx.emit(Instruction::LocalSet(obj_ptr));
+
+                let offset = match known_slot {
+                    Some(slot) => 8 + 8 * slot,
+                    // Special case: ".length" on lists → read header at ptr[0]
+                    None if field == "length" || field == "len" => 0,
+                    None => 8 + 8 * Self::declared_field_slot(ctx, field).unwrap_or(0),
+                };
+                ctx.emit(Instruction::LocalGet(obj_ptr));
+                ctx.emit(Instruction::I32WrapI64);
+                ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                    offset: offset as u64,
+                    align: 3,
+                    memory_index: 0,
+                }));
+                Ok(())
+            }
+
+            // -----------------------------------------------------------------
+            // Match expression → decision tree
+            // The scrutinee is evaluated exactly once into `__match_val`,
+            // which every test and binding reads. Enum variant patterns
+            // branch on the variant tag through a br_table, as do dense
+            // integer literals; other literal arms form an if/else chain.
+            // The first matching arm wins, so arms after a wildcard or
+            // variable pattern are never compiled.
+            // -----------------------------------------------------------------
+            Expression::Match { scrutinee, arms } => {
+                Self::compile_expr(ctx, scrutinee, func_map)?;
+                let match_val = ctx.scope.scratch("__match_val");
+                ctx.emit(Instruction::LocalSet(match_val));
 
-                    // --- Math binary ---
-                    "intrinsic_math_atan2" | "math.atan2" => {
-                        if args.len() != 2 {
-                            return Err(WasmCompileError {
-                                message: "math.atan2 needs 2 args".into(),
-                                context: "math.atan2".into(),
-                            });
-                        }
-                        Self::compile_expr(ctx, &args[0], func_map)?; // y
-                        Self::compile_expr(ctx, &args[1], func_map)?; // x
-                        ctx.emit(Instruction::Call(ARK_HOST_MATH_ATAN2_FUNC_IDX));
-                    }
-                    "intrinsic_math_pow" | "math.pow" => {
-                        if args.len() != 2 {
-                            return Err(WasmCompileError {
-                                message: "math.pow needs 2 args".into(),
-                                context: "math.pow".into(),
-                            });
-                        }
-                        Self::compile_expr(ctx, &args[0], func_map)?; // base
-                        Self::compile_expr(ctx, &args[1], func_map)?; // exp
-                        ctx.emit(Instruction::Call(ARK_HOST_MATH_POW_FUNC_IDX));
-                    }
+                let live = arms
+                    .iter()
+                    .position(|(pattern, _)| {
+                        matches!(pattern, Pattern::Wildcard | Pattern::Variable(_))
+                    })
+                    .map_or(arms.len(), |catch_all| catch_all + 1);
+                let arms = &arms[..live];
+
+                if arms
+                    .iter()
+                    .any(|(pattern, _)| matches!(pattern, Pattern::EnumVariant { .. }))
+                {
+                    Self::compile_enum_match(ctx, scrutinee, arms, match_val, func_map)
+                } else if let Some((min, table)) = Self::dense_literal_table(arms) {
+                    Self::compile_table_match(ctx, scrutinee, arms, match_val, min, table, func_map)
+                } else {
+                    Self::compile_match_chain(ctx, scrutinee, arms, match_val, func_map)
+                }
+            }
 
-                    // --- Math ternary ---
-                    "intrinsic_pow_mod" | "math.pow_mod" | "sys.math.pow_mod" => {
-                        if args.len() != 3 {
-                            return Err(WasmCompileError {
-                                message: "pow_mod needs 3 args".into(),
-                                context: "pow_mod".into(),
-                            });
+            // -----------------------------------------------------------------
+            // Lambda expression → closure record
+            // Lambdas are registered during collect_lambdas and hoisted to
+            // top-level synthetic functions (__lambda_N). The value is a heap
+            // object holding the lambda's table index and a snapshot of the
+            // variables it captures; calls go through call_indirect.
+            // -----------------------------------------------------------------
+            Expression::Lambda { body, .. } => {
+                let key = lambda_key(expr)?;
+                if let Some(&func_idx) = func_map.get(&key) {
+                    let captures = ctx.closure_captures.get(&key).cloned().unwrap_or_default();
+                    Self::compile_closure(ctx, func_idx, &captures, func_map)?;
+                } else {
+                    // Lambda not pre-registered — emit inline block
+                    let body_len = body.len();
+                    if body_len == 0 {
+                        ctx.emit(Instruction::I64Const(0));
+                    } else {
+                        for (i, stmt) in body.iter().enumerate() {
+                            let is_last = i == body_len - 1;
+                            Self::compile_stmt(ctx, stmt, is_last, func_map)?;
                         }
-                        Self::compile_expr(ctx, &args[0], func_map)?; // base
-                        Self::compile_expr(ctx, &args[1], func_map)?; // exp
-                        Self::compile_expr(ctx, &args[2], func_map)?; // modulus
-                        ctx.emit(Instruction::Call(ARK_HOST_MATH_POW_MOD_FUNC_IDX));
                     }
+                }
+                Ok(())
+            }
+            // -----------------------------------------------------------------
+            // Enum variant → tagged heap object
+            // [HEAP_TAG_ENUM @ ptr-8][variant index @ ptr][payload @ ptr+8 ...]
+            // -----------------------------------------------------------------
+            Expression::EnumInit {
+                enum_name,
+                variant,
+                args,
+            } => Self::compile_enum_init(ctx, enum_name, variant, args, func_map),
+            Expression::Propagate(inner) => Self::compile_propagate(ctx, inner, func_map),
+        }
+    }
 
-                    // --- Crypto: SHA-512 ---
-                    // sys.crypto.sha512(data_ptr) => allocates 64-byte output, returns ptr as i64
-                    "intrinsic_crypto_sha512" | "sys.crypto.sha512" => {
-                        // Arg is the data pointer (packed: upper 32 = ptr, lower 32 = len)
-                        // For simplicity: arg[0] = ptr (i64), arg[1] = len (i64)
-                        // Host call: crypto_sha512(data_ptr:i32, data_len:i32, out_ptr:i32) -> i32
-                        if args.len() < 2 {
-                            return Err(WasmCompileError {
-                                message: "sha512 needs 2 args (ptr, len)".to_string(),
-                                context: "sha512".to_string(),
-                            });
-                        }
-                        // Allocate 64 bytes for output
-                        ctx.emit(Instruction::I64Const(64));
-                        let alloc_idx = *func_map.get("__alloc").expect("operation failed");
-                        ctx.emit(Instruction::Call(alloc_idx));
-                        // out_ptr is on stack as i64; save to local
-                        let out_local = ctx.scope.get_or_alloc("__host_out_sha512");
-                        ctx.emit(Instruction::LocalSet(out_local));
+    // =========================================================================
+    // Instruction Helpers
+    // =========================================================================
 
-                        // Push args: data_ptr (i32), data_len (i32), out_ptr (i32)
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::I32WrapI64); // data_ptr
-                        Self::compile_expr(ctx, &args[1], func_map)?;
-                        ctx.emit(Instruction::I32WrapI64); // data_len
-                        ctx.emit(Instruction::LocalGet(out_local));
-                        ctx.emit(Instruction::I32WrapI64); // out_ptr
+    /// Reject a call to a user function with the wrong number of arguments,
+    /// or with an argument whose static type contradicts the parameter's
+    /// declared `Integer`, `Float` or `String` (an integer may be passed as
+    /// a float).
+    fn check_call_args(
+        ctx: &FuncContext,
+        function_hash: &str,
+        args: &[Expression],
+    ) -> Result<(), WasmCompileError> {
+        let Some(params) = ctx.declared_params.get(function_hash) else {
+            return Ok(());
+        };
+        let error = |message: String| WasmCompileError {
+            message,
+            context: "compile_expr::Call".to_string(),
+            span: None,
+        };
+        if args.len() != params.len() {
+            return Err(error(format!(
+                "function '{}' takes {} argument(s), got {}",
+                function_hash,
+                params.len(),
+                args.len()
+            )));
+        }
+        for ((param, ty), arg) in params.iter().zip(args) {
+            let (Some(expected), Some(actual)) = (checked_kind(ty), Self::static_type_of(ctx, arg))
+            else {
+                continue;
+            };
+            let compatible = actual == expected || (expected == "float" && actual == "integer");
+            if !compatible && matches!(actual, "integer" | "float" | "string") {
+                return Err(error(format!(
+                    "parameter '{}' of '{}' is declared {:?}, got a value of type {}",
+                    param, function_hash, ty, actual
+                )));
+            }
+        }
+        Ok(())
+    }
 
-                        ctx.emit(Instruction::Call(ARK_HOST_CRYPTO_SHA512_FUNC_IDX));
-                        ctx.emit(Instruction::Drop); // drop errno
-                        ctx.emit(Instruction::LocalGet(out_local)); // return out_ptr as i64
-                    }
+    /// Push the arguments of a call to `function_hash`; a float parameter
+    /// converts an integer argument. With `native`, float arguments are left
+    /// as f64s for a typed callee; otherwise they are passed as their bits.
+    fn compile_call_args(
+        ctx: &mut FuncContext,
+        function_hash: &str,
+        args: &[Expression],
+        native: bool,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let float_params = ctx
+            .float_signatures
+            .get(function_hash)
+            .map(|(params, _)| params.clone())
+            .unwrap_or_default();
+        for (i, arg) in args.iter().enumerate() {
+            if float_params.get(i).copied().unwrap_or(false) {
+                Self::compile_f64_operand(ctx, arg, func_map)?;
+                if !native {
+                    ctx.emit(Instruction::I64ReinterpretF64);
+                }
+            } else {
+                Self::compile_expr(ctx, arg, func_map)?;
+            }
+        }
+        Ok(())
+    }
 
-                    // --- JSON ---
-                    "sys.json.parse" | "intrinsic_json_parse" => {
-                        if args.len() < 2 {
-                            return Err(WasmCompileError {
-                                message: "json.parse needs 2 args (str_ptr, str_len)".to_string(),
-                                context: "json.parse".to_string(),
-                            });
-                        }
-                        // Allocate output buffer (4KB)
-                        ctx.emit(Instruction::I64Const(4096));
-                        let alloc_idx = *func_map.get("__alloc").expect("operation failed");
-                        ctx.emit(Instruction::Call(alloc_idx));
-                        let out_local = ctx.scope.get_or_alloc("__host_out_sha512");
-                        ctx.emit(Instruction::LocalSet(out_local));
+    /// Allocate a closure record for the lifted function `func_idx`, copying
+    /// in the current value of each captured local. A capture that is not a
+    /// local here reads as 0, like any unbound variable.
+    fn compile_closure(
+        ctx: &mut FuncContext,
+        func_idx: u32,
+        captures: &[String],
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let slots = 1 + captures.len();
+        ctx.emit(Instruction::I64Const(8 + 8 * slots as i64));
+        Self::emit_alloc_object(ctx, HEAP_TAG_CLOSURE, func_map, "Expression::Lambda")?;
+        let closure = ctx.scope.scratch("__closure_ptr");
+        ctx.emit(Instruction::LocalSet(closure));
+
+        let store = |ctx: &mut FuncContext, offset: u64, value: Instruction<'static>| {
+            ctx.emit(Instruction::LocalGet(closure));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(value);
+            ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                offset,
+                align: 3,
+                memory_index: 0,
+            }));
+        };
+        store(ctx, 0, Instruction::I64Const(slots as i64));
+        // Table index == function index: the element segment maps them 1:1
+        ctx.emit(Instruction::LocalGet(closure));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit_func_ref(func_idx);
+        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+            offset: 8,
+            align: 3,
+            memory_index: 0,
+        }));
+        for (k, name) in captures.iter().enumerate() {
+            let value = match ctx.scope.get(name) {
+                Some(local) => Instruction::LocalGet(local),
+                None => Instruction::I64Const(0),
+            };
+            store(ctx, (16 + 8 * k) as u64, value);
+        }
 
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::I32WrapI64);
-                        Self::compile_expr(ctx, &args[1], func_map)?;
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::LocalGet(out_local));
-                        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::LocalGet(closure));
+        Ok(())
+    }
 
-                        ctx.emit(Instruction::Call(ARK_HOST_JSON_PARSE_FUNC_IDX));
-                        ctx.emit(Instruction::I64ExtendI32U); // bytes written as i64
-                    }
+    /// Call the function value held in local `name` through the table. A
+    /// named function used as a value is its table index; a lambda is a
+    /// closure record, passed as the hidden `env` argument ahead of the
+    /// others. Records live in the heap, above every table index, so
+    /// `value < table.size` tells the two apart.
+    fn compile_indirect_call(
+        ctx: &mut FuncContext,
+        name: &str,
+        args: &[Expression],
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let Some(callee) = ctx.scope.get(name) else {
+            return Err(WasmCompileError {
+                message: format!("'{}' is not a local", name),
+                context: "indirect call".to_string(),
+                span: None,
+            });
+        };
+        let type_for = |param_count: usize| {
+            ctx.indirect_types
+                .get(&param_count)
+                .copied()
+                .ok_or_else(|| WasmCompileError {
+                    message: format!("no call_indirect type for {} parameter(s)", param_count),
+                    context: "indirect call".to_string(),
+                    span: None,
+                })
+        };
+        let plain_type = type_for(args.len())?;
+        let closure_type = type_for(args.len() + 1)?;
+
+        // Evaluate the arguments once, ahead of the dispatch
+        let mut arg_locals = Vec::with_capacity(args.len());
+        for (i, arg) in args.iter().enumerate() {
+            Self::compile_expr(ctx, arg, func_map)?;
+            let local = ctx.scope.scratch(&format!("__icall_arg{}", i));
+            ctx.emit(Instruction::LocalSet(local));
+            arg_locals.push(local);
+        }
 
-                    "sys.json.stringify" | "intrinsic_json_stringify" => {
-                        if args.len() < 2 {
-                            return Err(WasmCompileError {
-                                message: "json.stringify needs 2 args (val_ptr, val_len)"
-                                    .to_string(),
-                                context: "json.stringify".to_string(),
-                            });
-                        }
-                        ctx.emit(Instruction::I64Const(4096));
-                        let alloc_idx = *func_map.get("__alloc").expect("operation failed");
-                        ctx.emit(Instruction::Call(alloc_idx));
-                        let out_local = ctx.scope.get_or_alloc("__host_out_sha512");
-                        ctx.emit(Instruction::LocalSet(out_local));
+        ctx.emit(Instruction::LocalGet(callee));
+        ctx.emit(Instruction::TableSize(0));
+        ctx.emit(Instruction::I64ExtendI32U);
+        ctx.emit(Instruction::I64LtU);
+        ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+        {
+            for &local in &arg_locals {
+                ctx.emit(Instruction::LocalGet(local));
+            }
+            ctx.emit(Instruction::LocalGet(callee));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::CallIndirect {
+                type_index: plain_type,
+                table_index: 0,
+            });
+        }
+        ctx.emit(Instruction::Else);
+        {
+            if ctx.bounds_checks {
+                Self::emit_heap_object_guard(ctx, callee, HEAP_TAG_CLOSURE, 1);
+            }
+            ctx.emit(Instruction::LocalGet(callee));
+            for &local in &arg_locals {
+                ctx.emit(Instruction::LocalGet(local));
+            }
+            ctx.emit(Instruction::LocalGet(callee));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                offset: 8,
+                align: 3,
+                memory_index: 0,
+            }));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::CallIndirect {
+                type_index: closure_type,
+                table_index: 0,
+            });
+        }
+        ctx.emit(Instruction::End);
+        Ok(())
+    }
 
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::I32WrapI64);
-                        Self::compile_expr(ctx, &args[1], func_map)?;
-                        ctx.emit(Instruction::I32WrapI64);
-                        ctx.emit(Instruction::LocalGet(out_local));
-                        ctx.emit(Instruction::I32WrapI64);
+    /// The kind of value `expr` evaluates to ("string" or "list") when it is
+    /// a heap allocation made just for it: a list literal, or the result of
+    /// a concat or append. Nothing else can reference such a value, so a
+    /// consumer that copies it may free it.
+    fn fresh_allocation(expr: &Expression) -> Option<&'static str> {
+        match expr {
+            Expression::List(_) => Some("list"),
+            Expression::Call { function_hash, .. } => match function_hash.as_str() {
+                "string_concat" | "intrinsic_string_concat" => Some("string"),
+                "intrinsic_list_append" | "sys.list.append" | "list.append" => Some("list"),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
 
-                        ctx.emit(Instruction::Call(ARK_HOST_JSON_STRINGIFY_FUNC_IDX));
-                        ctx.emit(Instruction::I64ExtendI32U);
-                    }
+    /// Return the block at `ptr_local - header` to the allocator.
+    fn emit_free(
+        ctx: &mut FuncContext,
+        ptr_local: u32,
+        header: i64,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let free_idx = *func_map.get("__free").ok_or_else(|| WasmCompileError {
+            message: "__free not found — allocator not registered".to_string(),
+            context: "emit_free".to_string(),
+            span: None,
+        })?;
+        ctx.emit(Instruction::LocalGet(ptr_local));
+        if header != 0 {
+            ctx.emit(Instruction::I64Const(header));
+            ctx.emit(Instruction::I64Sub);
+        }
+        ctx.emit(Instruction::Call(free_idx));
+        ctx.emit(Instruction::Drop);
+        Ok(())
+    }
+
+    /// Allocate a tagged heap object. Expects the object size (header + slots)
+    /// as i64 on the stack; leaves the object pointer (past the tag word).
+    fn emit_alloc_object(
+        ctx: &mut FuncContext,
+        tag: i64,
+        func_map: &HashMap<String, u32>,
+        context: &str,
+    ) -> Result<(), WasmCompileError> {
+        let alloc_idx = *func_map.get("__alloc").ok_or_else(|| WasmCompileError {
+            message: "__alloc not found — allocator not registered".to_string(),
+            context: context.to_string(),
+            span: None,
+        })?;
+        ctx.emit(Instruction::I64Const(8)); // room for the tag word
+        ctx.emit(Instruction::I64Add);
+        ctx.emit(Instruction::Call(alloc_idx));
+        let base = ctx.scope.scratch("__obj_base");
+        ctx.emit(Instruction::LocalSet(base));
+
+        ctx.emit(Instruction::LocalGet(base));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I64Const(tag));
+        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: 0,
+        }));
+
+        ctx.emit(Instruction::LocalGet(base));
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::I64Add);
+        Ok(())
+    }
+
+    /// Call the `ark_host` float math import `import` with `args` as f64,
+    /// leaving the f64 result as float bits.
+    fn compile_float_math(
+        ctx: &mut FuncContext,
+        args: &[Expression],
+        import: &str,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        for arg in args {
+            Self::compile_f64_operand(ctx, arg, func_map)?;
+        }
+        ctx.emit(Instruction::Call(Self::import_index(func_map, import)?));
+        ctx.emit(Instruction::I64ReinterpretF64);
+        Ok(())
+    }
+
+    /// Lower a one-argument rounding intrinsic: take the argument as an f64,
+    /// apply `op`, and saturate the result to an i64 integer.
+    fn compile_rounding(
+        ctx: &mut FuncContext,
+        args: &[Expression],
+        func_map: &HashMap<String, u32>,
+        op: Instruction<'static>,
+    ) -> Result<(), WasmCompileError> {
+        if args.len() != 1 {
+            return Err(WasmCompileError {
+                message: "rounding intrinsics need 1 arg".into(),
+                context: "math rounding".into(),
+                span: None,
+            });
+        }
+        Self::compile_f64_operand(ctx, &args[0], func_map)?;
+        ctx.emit(op);
+        ctx.emit(Instruction::I64TruncSatF64S);
+        Ok(())
+    }
+
+    /// Push a string constant as a packed `(ptr << 32) | len` value.
+    fn emit_packed_string(ctx: &mut FuncContext, s: &str) {
+        let (ptr, len) = ctx.alloc_string(s);
+        let packed = ((ptr as i64) << 32) | (len as i64 & 0xFFFFFFFF);
+        ctx.emit(Instruction::I64Const(packed));
+    }
+
+    /// Type tag of `expr` when it can be determined at compile time.
+    fn static_type_of(ctx: &FuncContext, expr: &Expression) -> Option<&'static str> {
+        match expr {
+            Expression::Integer(_) => Some("integer"),
+            Expression::Literal(s) => Some(if s.parse::<i64>().is_ok() {
+                "integer"
+            } else if float_literal(s).is_some() {
+                "float"
+            } else if s == "true" || s == "false" {
+                "boolean"
+            } else {
+                "string"
+            }),
+            Expression::Variable(name) => ctx.scope.kind(name),
+            Expression::List(_) => Some("list"),
+            Expression::StructInit { .. } => Some("struct"),
+            Expression::MapInit { .. } => Some("pmap"),
+            Expression::Lambda { .. } => Some("function"),
+            Expression::EnumInit { .. } => Some("enum"),
+            Expression::Call {
+                function_hash,
+                args,
+            } => match function_hash.as_str() {
+                "intrinsic_add" | "add" | "intrinsic_sub" | "sub" | "intrinsic_mul" | "mul"
+                | "intrinsic_div" | "div" => Some(if Self::has_float_operand(ctx, args) {
+                    "float"
+                } else {
+                    "integer"
+                }),
+                name if is_float_math(name) => Some("float"),
+                name if ctx.float_signatures.get(name).is_some_and(|(_, r)| *r) => Some("float"),
+                "intrinsic_mod"
+                | "modulo"
+                | "len"
+                | "intrinsic_len"
+                | "string_len"
+                | "intrinsic_string_len"
+                | "string_char_len"
+                | "intrinsic_string_char_len"
+                | "sys.args.count"
+                | "intrinsic_args_count"
+                | "pvec.len"
+                | "sys.pvec.len"
+                | "intrinsic_pvec_len"
+                | "bigint.cmp"
+                | "sys.bigint.cmp"
+                | "intrinsic_bigint_cmp"
+                | "math.dot"
+                | "intrinsic_math_dot" => Some("integer"),
+                "intrinsic_eq"
+                | "eq"
+                | "intrinsic_neq"
+                | "neq"
+                | "intrinsic_gt"
+                | "gt"
+                | "intrinsic_lt"
+                | "lt"
+                | "intrinsic_ge"
+                | "ge"
+                | "intrinsic_le"
+                | "le"
+                | "intrinsic_and"
+                | "and"
+                | "intrinsic_or"
+                | "or"
+                | "string_eq"
+                | "intrinsic_string_eq" => Some("boolean"),
+                "string_concat"
+                | "intrinsic_string_concat"
+                | "string_slice"
+                | "intrinsic_string_slice"
+                | "string_char_at"
+                | "intrinsic_string_char_at"
+                | "string_slice_chars"
+                | "intrinsic_string_slice_chars"
+                | "string_to_upper"
+                | "intrinsic_string_to_upper"
+                | "string_to_lower"
+                | "intrinsic_string_to_lower"
+                | "intrinsic_type_of"
+                | "sys.type_of"
+                | "sys.fs.read"
+                | "sys_fs_read"
+                | "intrinsic_fs_read"
+                | "sys.args.get"
+                | "intrinsic_args_get"
+                | "sys.env.get"
+                | "intrinsic_env_get"
+                | "bigint.to_string"
+                | "sys.bigint.to_string"
+                | "intrinsic_bigint_to_string"
+                | "intrinsic_crypto_sha256"
+                | "sys.crypto.sha256"
+                | "intrinsic_crypto_keccak256"
+                | "sys.crypto.keccak256"
+                | "intrinsic_crypto_blake3"
+                | "sys.crypto.blake3" => Some("string"),
+                "intrinsic_list_append"
+                | "sys.list.append"
+                | "list.append"
+                | "sys.fs.read_chunk"
+                | "intrinsic_fs_read_chunk" => Some("list"),
+                "map.set" | "intrinsic_map_set" => Some("pmap"),
+                "map.keys" | "intrinsic_map_keys" => Some("list"),
+                "pvec.new"
+                | "sys.pvec.new"
+                | "intrinsic_pvec_new"
+                | "pvec.conj"
+                | "sys.pvec.conj"
+                | "intrinsic_pvec_conj"
+                | "pvec.assoc"
+                | "sys.pvec.assoc"
+                | "intrinsic_pvec_assoc"
+                | "pvec.pop"
+                | "sys.pvec.pop"
+                | "intrinsic_pvec_pop" => Some("pvec"),
+                "pmap.new"
+                | "sys.pmap.new"
+                | "intrinsic_pmap_new"
+                | "pmap.assoc"
+                | "sys.pmap.assoc"
+                | "intrinsic_pmap_assoc"
+                | "pmap.dissoc"
+                | "sys.pmap.dissoc"
+                | "intrinsic_pmap_dissoc"
+                | "pmap.merge"
+                | "sys.pmap.merge"
+                | "intrinsic_pmap_merge" => Some("pmap"),
+                "pmap.keys" | "sys.pmap.keys" | "intrinsic_pmap_keys" => Some("list"),
+                "bigint.from_string"
+                | "sys.bigint.from_string"
+                | "intrinsic_bigint_from_string"
+                | "bigint.add"
+                | "sys.bigint.add"
+                | "intrinsic_bigint_add"
+                | "bigint.mul"
+                | "sys.bigint.mul"
+                | "intrinsic_bigint_mul"
+                | "bigint.mod"
+                | "sys.bigint.mod"
+                | "intrinsic_bigint_mod"
+                | "bigint.pow_mod"
+                | "sys.bigint.pow_mod"
+                | "intrinsic_bigint_pow_mod" => Some("bigint"),
+                name if Self::is_tensor_result(name) => Some("struct"),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Field order of a struct-valued `expr` when it can be determined at
+    /// compile time. Slot `i` of the struct lives at `ptr + 8 + 8*i`.
+    fn static_shape_of(ctx: &FuncContext, expr: &Expression) -> Option<Vec<String>> {
+        match expr {
+            Expression::StructInit { fields } => {
+                Some(fields.iter().map(|(name, _)| name.clone()).collect())
+            }
+            Expression::Variable(name) => ctx.scope.shape(name).map(|s| s.to_vec()),
+            Expression::Call { function_hash, .. } if Self::is_tensor_result(function_hash) => {
+                Some(vec!["data".to_string(), "shape".to_string()])
+            }
+            _ => None,
+        }
+    }
 
-                    // --- AI ---
-                    "intrinsic_ask_ai" | "sys.ai.ask" | "ai.ask" => {
-                        // ask_ai(prompt_ptr, prompt_len, out_ptr, out_cap) -> bytes_written
-                        if args.len() < 2 {
-                            return Err(WasmCompileError {
-                                message: "ask_ai needs 2 args (prompt_ptr, prompt_len)".to_string(),
-                                context: "ask_ai".to_string(),
-                            });
-                        }
-                        // Allocate 4KB output buffer
-                        ctx.emit(Instruction::I64Const(4096));
-                        let alloc_idx = *func_map.get("__alloc").expect("operation failed");
-                        ctx.emit(Instruction::Call(alloc_idx));
-                        let out_local = ctx.scope.get_or_alloc("__host_out_sha512");
-                        ctx.emit(Instruction::LocalSet(out_local));
+    /// Whether the intrinsic `name` returns a tensor (see [`TENSOR_HELPERS`]).
+    fn is_tensor_result(name: &str) -> bool {
+        TENSOR_HELPERS
+            .iter()
+            .filter(|(helper, ..)| *helper != "__tensor_dot")
+            .any(|(_, _, intrinsics, _)| intrinsics.contains(&name))
+    }
 
-                        Self::compile_expr(ctx, &args[0], func_map)?;
-                        ctx.emit(Instruction::I32WrapI64); // prompt_ptr
-                        Self::compile_expr(ctx, &args[1], func_map)?;
-                        ctx.emit(Instruction::I32WrapI64); // prompt_len
-                        ctx.emit(Instruction::LocalGet(out_local));
-                        ctx.emit(Instruction::I32WrapI64); // out_ptr
-                        ctx.emit(Instruction::I32Const(4096)); // out_cap
+    /// Slot of `field` in the struct `obj` evaluates to: from its static
+    /// shape when known, else from the declared structs.
+    fn field_slot(ctx: &FuncContext, obj: &Expression, field: &str) -> Option<usize> {
+        match Self::static_shape_of(ctx, obj) {
+            Some(shape) => shape.iter().position(|f| f == field),
+            None => Self::declared_field_slot(ctx, field),
+        }
+    }
 
-                        ctx.emit(Instruction::Call(ARK_HOST_ASK_AI_FUNC_IDX));
-                        ctx.emit(Instruction::I64ExtendI32U); // bytes written as i64
-                    }
+    /// Slot of `field` according to the `StructDecl`s, when every declared
+    /// struct that has the field puts it at the same position.
+    fn declared_field_slot(ctx: &FuncContext, field: &str) -> Option<usize> {
+        let mut slots = ctx
+            .struct_layouts
+            .values()
+            .filter_map(|layout| layout.iter().position(|f| f == field));
+        let first = slots.next()?;
+        slots.all(|slot| slot == first).then_some(first)
+    }
 
-                    _ => {
-                        // Compile arguments
-                        for arg in args {
-                            Self::compile_expr(ctx, arg, func_map)?;
-                        }
-                        // Look up function index
-                        if let Some(&func_idx) = func_map.get(function_hash) {
-                            ctx.emit(Instruction::Call(func_idx));
-                        } else {
-                            return Err(WasmCompileError {
-                                message: format!("Unknown function: {}", function_hash),
-                                context: "compile_expr::Call".to_string(),
-                            });
-                        }
-                    }
-                }
-                Ok(())
-            }
+    /// Allocate an empty map and insert `entries` in order through
+    /// `__map_set`, leaving the map pointer on the stack.
+    fn compile_map_init(
+        ctx: &mut FuncContext,
+        entries: &[(Expression, Expression)],
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let context = "Expression::MapInit";
+        let helper = |name: &str| {
+            func_map.get(name).copied().ok_or_else(|| WasmCompileError {
+                message: format!("{} not found — map helpers not registered", name),
+                context: context.to_string(),
+                span: None,
+            })
+        };
+        let alloc_idx = helper("__alloc")?;
+        let set_idx = helper("__map_set")?;
+
+        ctx.emit(Instruction::I64Const(24));
+        Self::emit_alloc_object(ctx, HEAP_TAG_MAP, func_map, context)?;
+        let map = ctx.scope.scratch("__map_ptr");
+        ctx.emit(Instruction::LocalSet(map));
+        ctx.emit(Instruction::LocalGet(map));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I64Const(MAP_INITIAL_CAPACITY));
+        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+            offset: 8,
+            align: 3,
+            memory_index: 0,
+        }));
+        ctx.emit(Instruction::LocalGet(map));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I64Const(MAP_INITIAL_CAPACITY * 16));
+        ctx.emit(Instruction::Call(alloc_idx));
+        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+            offset: 16,
+            align: 3,
+            memory_index: 0,
+        }));
 
-            // -----------------------------------------------------------------
-            // List literal → allocate in linear memory via __alloc
-            // Layout: [length: i64 (8B)] [elem_0: i64 (8B)] [elem_1: i64 (8B)] ...
-            // Returns: ptr as i64
-            // -----------------------------------------------------------------
-            Expression::List(items) => {
-                let list_len = items.len();
-                let alloc_size = 8 + 8 * list_len; // header + elements
+        // Each `__map_set` returns the map for the next one
+        ctx.emit(Instruction::LocalGet(map));
+        for (key, value) in entries {
+            Self::compile_expr(ctx, key, func_map)?;
+            Self::compile_expr(ctx, value, func_map)?;
+            ctx.emit(Instruction::Call(set_idx));
+        }
+        Ok(())
+    }
 
-                // Call __alloc(size) — __alloc is in func_index_map
-                ctx.emit(Instruction::I64Const(alloc_size as i64));
-                if let Some(&alloc_idx) = func_map.get("__alloc") {
-                    ctx.emit(Instruction::Call(alloc_idx));
-                } else {
-                    return Err(WasmCompileError {
-                        message: "__alloc not found — bump allocator not registered".to_string(),
-                        context: "Expression::List".to_string(),
-                    });
-                }
+    /// Allocate a struct and store `fields` into it, leaving its pointer on
+    /// the stack. Without a `layout` fields occupy slots in initializer
+    /// order; with one, each field goes to its declared slot and slots the
+    /// initializer omits are zero-filled.
+    fn compile_struct_init(
+        ctx: &mut FuncContext,
+        fields: &[(String, Expression)],
+        layout: Option<&[String]>,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let slots = match layout {
+            Some(layout) => fields
+                .iter()
+                .map(|(name, _)| {
+                    layout
+                        .iter()
+                        .position(|f| f == name)
+                        .ok_or_else(|| WasmCompileError {
+                            message: format!("struct has no field '{}'", name),
+                            context: "Expression::StructInit".to_string(),
+                            span: None,
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => (0..fields.len()).collect(),
+        };
+        let field_count = layout.map_or(fields.len(), |l| l.len());
+        let alloc_size = 8 + 8 * field_count;
 
-                // Store returned ptr in a local
-                let list_ptr = ctx.scope.get_or_alloc("__list_ptr");
-                ctx.emit(Instruction::LocalSet(list_ptr));
+        ctx.emit(Instruction::I64Const(alloc_size as i64));
+        Self::emit_alloc_object(ctx, HEAP_TAG_STRUCT, func_map, "Expression::StructInit")?;
 
-                // Store length at ptr[0] (i64)
-                ctx.emit(Instruction::LocalGet(list_ptr));
-                ctx.emit(Instruction::I32WrapI64); // memory address must be i32
-                ctx.emit(Instruction::I64Const(list_len as i64));
-                ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
-                    offset: 0,
-                    align: 3, // 8-byte aligned
-                    memory_index: 0,
-                }));
+        let struct_ptr = ctx.scope.scratch("__struct_ptr");
+        ctx.emit(Instruction::LocalSet(struct_ptr));
 
-                // Store each element at ptr + 8 + 8*i
-                for (i, item) in items.iter().enumerate() {
-                    ctx.emit(Instruction::LocalGet(list_ptr));
-                    ctx.emit(Instruction::I32WrapI64);
-                    Self::compile_expr(ctx, item, func_map)?;
-                    ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
-                        offset: (8 + 8 * i) as u64,
-                        align: 3,
-                        memory_index: 0,
-                    }));
-                }
+        // Store field count at ptr[0]
+        ctx.emit(Instruction::LocalGet(struct_ptr));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I64Const(field_count as i64));
+        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: 0,
+        }));
 
-                // Push ptr as the list value
-                ctx.emit(Instruction::LocalGet(list_ptr));
-                Ok(())
-            }
+        // Store each field value at ptr + 8 + 8*slot, in initializer order
+        for ((_name, value), &slot) in fields.iter().zip(&slots) {
+            ctx.emit(Instruction::LocalGet(struct_ptr));
+            ctx.emit(Instruction::I32WrapI64);
+            Self::compile_expr(ctx, value, func_map)?;
+            ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                offset: (8 + 8 * slot) as u64,
+                align: 3,
+                memory_index: 0,
+            }));
+        }
 
-            // -----------------------------------------------------------------
-            // Struct init → allocate in linear memory via __alloc
-            // Layout: [field_count: i64 (8B)] [field_0: i64] [field_1: i64] ...
-            // Returns: ptr as i64
-            // -----------------------------------------------------------------
-            Expression::StructInit { fields } => {
-                let field_count = fields.len();
-                let alloc_size = 8 + 8 * field_count;
+        // Don't rely on the heap being clear: zero the omitted slots
+        for slot in (0..field_count).filter(|s| !slots.contains(s)) {
+            ctx.emit(Instruction::LocalGet(struct_ptr));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                offset: (8 + 8 * slot) as u64,
+                align: 3,
+                memory_index: 0,
+            }));
+        }
 
-                ctx.emit(Instruction::I64Const(alloc_size as i64));
-                if let Some(&alloc_idx) = func_map.get("__alloc") {
-                    ctx.emit(Instruction::Call(alloc_idx));
-                } else {
-                    return Err(WasmCompileError {
-                        message: "__alloc not found".to_string(),
-                        context: "Expression::StructInit".to_string(),
-                    });
-                }
+        ctx.emit(Instruction::LocalGet(struct_ptr));
+        Ok(())
+    }
 
-                let struct_ptr = ctx.scope.get_or_alloc("__struct_ptr");
-                ctx.emit(Instruction::LocalSet(struct_ptr));
+    /// Allocate an enum value: the variant's index among its enum's declared
+    /// variants in the header word, then one slot per payload field. Leaves
+    /// its pointer on the stack.
+    fn compile_enum_init(
+        ctx: &mut FuncContext,
+        enum_name: &str,
+        variant: &str,
+        args: &[Expression],
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let context = "Expression::EnumInit";
+        let (tag, arity) = ctx
+            .enum_layouts
+            .get(enum_name)
+            .and_then(|variants| {
+                variants
+                    .iter()
+                    .enumerate()
+                    .find(|(_, (name, _))| name == variant)
+            })
+            .map(|(tag, (_, arity))| (tag, *arity))
+            .ok_or_else(|| WasmCompileError {
+                message: format!("unknown enum variant '{}::{}'", enum_name, variant),
+                context: context.to_string(),
+                span: None,
+            })?;
+        if args.len() != arity {
+            return Err(WasmCompileError {
+                message: format!(
+                    "{}::{} takes {} field(s), got {}",
+                    enum_name,
+                    variant,
+                    arity,
+                    args.len()
+                ),
+                context: context.to_string(),
+                span: None,
+            });
+        }
 
-                // Store field count at ptr[0]
-                ctx.emit(Instruction::LocalGet(struct_ptr));
-                ctx.emit(Instruction::I32WrapI64);
-                ctx.emit(Instruction::I64Const(field_count as i64));
-                ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
-                    offset: 0,
-                    align: 3,
-                    memory_index: 0,
-                }));
+        ctx.emit(Instruction::I64Const(8 + 8 * arity as i64));
+        Self::emit_alloc_object(ctx, HEAP_TAG_ENUM, func_map, context)?;
+        let enum_ptr = ctx.scope.scratch("__enum_ptr");
+        ctx.emit(Instruction::LocalSet(enum_ptr));
 
-                // Store each field value at ptr + 8 + 8*i
-                for (i, (_name, value)) in fields.iter().enumerate() {
-                    ctx.emit(Instruction::LocalGet(struct_ptr));
-                    ctx.emit(Instruction::I32WrapI64);
-                    Self::compile_expr(ctx, value, func_map)?;
-                    ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
-                        offset: (8 + 8 * i) as u64,
-                        align: 3,
-                        memory_index: 0,
-                    }));
-                }
+        ctx.emit(Instruction::LocalGet(enum_ptr));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I64Const(tag as i64));
+        ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: 0,
+        }));
+        for (j, arg) in args.iter().enumerate() {
+            ctx.emit(Instruction::LocalGet(enum_ptr));
+            ctx.emit(Instruction::I32WrapI64);
+            Self::compile_expr(ctx, arg, func_map)?;
+            ctx.emit(Instruction::I64Store(wasm_encoder::MemArg {
+                offset: (8 + 8 * j) as u64,
+                align: 3,
+                memory_index: 0,
+            }));
+        }
 
-                ctx.emit(Instruction::LocalGet(struct_ptr));
-                Ok(())
-            }
+        ctx.emit(Instruction::LocalGet(enum_ptr));
+        Ok(())
+    }
 
-            // -----------------------------------------------------------------
-            // Field access → load from linear memory
-            // Struct fields are stored sequentially starting at ptr+8.
-            // We use compile-time field name → index mapping.
-            // For now, field index is determined by convention:
-            //   - We check the obj expression to determine struct shape
-            //   - Fall back to returning 0 for unknown fields
-            // -----------------------------------------------------------------
-            Expression::GetField { obj, field } => {
-                // Compile the object (should return a ptr as i64)
-                Self::compile_expr(ctx, obj, func_map)?;
+    /// `inner?`: a built-in `Result` or `Option` succeeds with tag 0 (`Ok`,
+    /// `Some`), leaving its payload on the stack; any other tag returns the
+    /// enum pointer from the function unchanged.
+    fn compile_propagate(
+        ctx: &mut FuncContext,
+        inner: &Expression,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        if ctx.returns_float {
+            return Err(WasmCompileError {
+                message: "'?' cannot return a Result or Option from a Float function".to_string(),
+                context: "Expression::Propagate".to_string(),
+                span: None,
+            });
+        }
+        Self::compile_expr(ctx, inner, func_map)?;
+        let enum_ptr = ctx.scope.scratch("__propagate_ptr");
+        ctx.emit(Instruction::LocalSet(enum_ptr));
 
-                let obj_ptr = ctx.scope.get_or_alloc("__getfield_ptr");
-                ctx.emit(Instruction::LocalSet(obj_ptr));
+        ctx.emit(Instruction::LocalGet(enum_ptr));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: 0,
+        }));
+        ctx.emit(Instruction::I64Const(0));
+        ctx.emit(Instruction::I64Ne);
+        ctx.emit(Instruction::If(wasm_encoder::BlockType::Empty));
+        ctx.emit(Instruction::LocalGet(enum_ptr));
+        ctx.emit(Instruction::Return);
+        ctx.emit(Instruction::End);
 
-                // Special case: ".length" on lists → read header at ptr[0]
-                if field == "length" || field == "len" {
-                    ctx.emit(Instruction::LocalGet(obj_ptr));
-                    ctx.emit(Instruction::I32WrapI64);
-                    ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
-                        offset: 0,
-                        align: 3,
-                        memory_index: 0,
-                    }));
-                } else {
-                    // For struct fields, use field name hash to determine index.
-                    // This is a simple sequential scheme: field at index i is at
-                    // ptr + 8 + 8*i. Without type info, we use a name-based
-                    // lookup approach where the first field is index 0.
-                    // For now, emit a load from field index 0 as default.
-                    // Full field resolution requires passing struct type info.
-                    ctx.emit(Instruction::LocalGet(obj_ptr));
+        ctx.emit(Instruction::LocalGet(enum_ptr));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+            offset: 8,
+            align: 3,
+            memory_index: 0,
+        }));
+        Ok(())
+    }
+
+    /// Bind the variables of a match arm's pattern from the matched value in
+    /// `match_val`, then compile the arm's body.
+    fn compile_match_arm(
+        ctx: &mut FuncContext,
+        scrutinee: &Expression,
+        (pattern, body): &(Pattern, Expression),
+        match_val: u32,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        match pattern {
+            Pattern::Variable(name) => {
+                let kind = Self::static_type_of(ctx, scrutinee);
+                ctx.scope.record_kind(name, kind);
+                let shape = Self::static_shape_of(ctx, scrutinee);
+                ctx.scope.record_shape(name, shape);
+                let var_local = ctx.scope.get_or_alloc(name);
+                ctx.emit(Instruction::LocalGet(match_val));
+                ctx.emit(Instruction::LocalSet(var_local));
+            }
+            Pattern::EnumVariant { bindings, .. } => {
+                // Payload field j is slot j of the enum object
+                for (j, name) in bindings.iter().enumerate() {
+                    if name == "_" {
+                        continue;
+                    }
+                    ctx.scope.record_kind(name, None);
+                    ctx.scope.record_shape(name, None);
+                    let var_local = ctx.scope.get_or_alloc(name);
+                    ctx.emit(Instruction::LocalGet(match_val));
                     ctx.emit(Instruction::I32WrapI64);
                     ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
-                        offset: 8, // first field after header
+                        offset: (8 + 8 * j) as u64,
                         align: 3,
                         memory_index: 0,
                     }));
+                    ctx.emit(Instruction::LocalSet(var_local));
                 }
-                Ok(())
             }
+            Pattern::Literal(_) | Pattern::Wildcard => {}
+        }
+        Self::compile_expr(ctx, body, func_map)
+    }
 
-            // -----------------------------------------------------------------
-            // Match expression → if/else chain
-            // Compiles to: evaluate scrutinee, compare with each pattern,
-            // first match executes its arm body.
-            // -----------------------------------------------------------------
-            Expression::Match { scrutinee, arms } => {
-                // Compile scrutinee once, store in local
-                Self::compile_expr(ctx, scrutinee, func_map)?;
-                let match_val = ctx.scope.get_or_alloc("__match_val");
-                ctx.emit(Instruction::LocalSet(match_val));
-
-                // Generate nested if/else chain
-                // For N arms, we generate N-1 if/else blocks, last arm is default
-                if arms.is_empty() {
-                    ctx.emit(Instruction::I64Const(0)); // Unit
-                    return Ok(());
-                }
-
-                let last_idx = arms.len() - 1;
-                for (i, (pattern, body)) in arms.iter().enumerate() {
-                    let is_last = i == last_idx;
-
-                    match pattern {
-                        Pattern::Wildcard | Pattern::Variable(_) => {
-                            // Wildcard/variable always matches — compile body
-                            if let Pattern::Variable(name) = pattern {
-                                let var_local = ctx.scope.get_or_alloc(name);
-                                ctx.emit(Instruction::LocalGet(match_val));
-                                ctx.emit(Instruction::LocalSet(var_local));
-                            }
-                            Self::compile_expr(ctx, body, func_map)?;
-                            // Close any open if/else blocks from previous arms
-                            for _ in 0..i {
-                                ctx.emit(Instruction::End);
-                            }
-                            return Ok(());
-                        }
-                        Pattern::Literal(lit_str) => {
-                            // Compare scrutinee to the literal value
-                            ctx.emit(Instruction::LocalGet(match_val));
-                            // Try to parse as integer
-                            if let Ok(n) = lit_str.parse::<i64>() {
-                                ctx.emit(Instruction::I64Const(n));
-                            } else {
-                                // String literal comparison — for now, use 0
-                                ctx.emit(Instruction::I64Const(0));
-                            }
-                            ctx.emit(Instruction::I64Eq);
-
-                            if is_last {
-                                // Last arm: if matches, body; else unit
-                                ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
-                                Self::compile_expr(ctx, body, func_map)?;
-                                ctx.emit(Instruction::Else);
-                                ctx.emit(Instruction::I64Const(0)); // no match = Unit
-                                ctx.emit(Instruction::End);
-                            } else {
-                                ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
-                                Self::compile_expr(ctx, body, func_map)?;
-                                ctx.emit(Instruction::Else);
-                                // Next arm continues in the else branch
-                            }
-                        }
-                        Pattern::EnumVariant { .. } => {
-                            // Enum variant pattern: treat like wildcard for now
-                            Self::compile_expr(ctx, body, func_map)?;
-                            for _ in 0..i {
-                                ctx.emit(Instruction::End);
-                            }
-                            return Ok(());
-                        }
-                    }
-                }
+    /// The i64 a literal pattern compares equal to.
+    fn literal_pattern_value(lit: &str) -> i64 {
+        match lit {
+            "true" => 1,
+            "false" => 0,
+            // String literal comparison — for now, use 0
+            _ => lit.parse().unwrap_or(0),
+        }
+    }
 
-                // Close remaining if/else blocks (for non-wildcard last arms)
-                // Each Literal arm that's not last opens an if/else
-                // The last Literal arm closes with End inside the loop
-                // So we need (arms.len() - 1) End instructions
-                for _ in 0..(arms.len().saturating_sub(1)) {
-                    ctx.emit(Instruction::End);
-                }
+    /// Lower literal match `arms` to an if/else chain, testing them in
+    /// order. Without a catch-all arm an unmatched value yields Unit.
+    fn compile_match_chain(
+        ctx: &mut FuncContext,
+        scrutinee: &Expression,
+        arms: &[(Pattern, Expression)],
+        match_val: u32,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let mut open = 0;
+        for arm in arms {
+            let Pattern::Literal(lit) = &arm.0 else {
+                // The catch-all, always last
+                Self::compile_match_arm(ctx, scrutinee, arm, match_val, func_map)?;
+                break;
+            };
+            ctx.emit(Instruction::LocalGet(match_val));
+            ctx.emit(Instruction::I64Const(Self::literal_pattern_value(lit)));
+            ctx.emit(Instruction::I64Eq);
+            ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+            Self::compile_match_arm(ctx, scrutinee, arm, match_val, func_map)?;
+            ctx.emit(Instruction::Else);
+            open += 1;
+        }
+        if matches!(arms.last(), None | Some((Pattern::Literal(_), _))) {
+            ctx.emit(Instruction::I64Const(0)); // no arm matched: Unit
+        }
+        for _ in 0..open {
+            ctx.emit(Instruction::End);
+        }
+        Ok(())
+    }
 
-                Ok(())
+    /// Jump table for match `arms` whose literal patterns are integers dense
+    /// enough for a br_table: the smallest value, and for each value from
+    /// there the first arm matching it. Values no arm names go to index
+    /// `n`, the number of literal arms: the catch-all arm if there is one.
+    fn dense_literal_table(arms: &[(Pattern, Expression)]) -> Option<(i64, Vec<u32>)> {
+        let mut values = Vec::new();
+        for (pattern, _) in arms {
+            if let Pattern::Literal(lit) = pattern {
+                values.push(lit.parse::<i64>().ok()?);
             }
+        }
+        if values.len() < MATCH_TABLE_MIN_ARMS {
+            return None;
+        }
+        let min = *values.iter().min()?;
+        let max = *values.iter().max()?;
+        let span = max.checked_sub(min)?.checked_add(1)?;
+        if span > MATCH_TABLE_MAX_SPAN || span > 2 * values.len() as i64 {
+            return None;
+        }
+        let mut table = vec![values.len() as u32; span as usize];
+        // Fill from the last arm so that the first arm for a value wins
+        for (arm, value) in values.iter().enumerate().rev() {
+            table[(value - min) as usize] = arm as u32;
+        }
+        Some((min, table))
+    }
 
-            // -----------------------------------------------------------------
-            // Lambda expression → push table index as i64
-            // Lambdas are registered during collect_lambdas and hoisted to
-            // top-level synthetic functions (__lambda_N). The lambda's
-            // table index is pushed onto the stack for call_indirect.
-            // -----------------------------------------------------------------
-            Expression::Lambda { params, body } => {
-                // Look up all registered lambda names to find this one
-                // Use a counter based on how many lambdas we've seen in
-                // this function context so far
-                let mut found_idx: Option<u32> = None;
-                for idx in 0..100 {
-                    let name = format!("__lambda_{}", idx);
-                    if let Some(&func_idx) = func_map.get(&name) {
-                        // Check if this is the right lambda by matching param count
-                        // For now, just use the first unmatched lambda by index
-                        found_idx = Some(func_idx);
-                        break;
-                    }
+    /// Lower a match on dense integer literals (see
+    /// [`dense_literal_table`](Self::dense_literal_table)) to a br_table
+    /// indexed by the scrutinee minus `min`.
+    fn compile_table_match(
+        ctx: &mut FuncContext,
+        scrutinee: &Expression,
+        arms: &[(Pattern, Expression)],
+        match_val: u32,
+        min: i64,
+        table: Vec<u32>,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let selector = ctx.scope.scratch("__match_index");
+        ctx.emit(Instruction::LocalGet(match_val));
+        ctx.emit(Instruction::I64Const(min));
+        ctx.emit(Instruction::I64Sub);
+        ctx.emit(Instruction::LocalSet(selector));
+
+        // Target `literals` is the catch-all arm, or Unit without one
+        let literals = arms
+            .iter()
+            .filter(|(pattern, _)| matches!(pattern, Pattern::Literal(_)))
+            .count();
+        Self::emit_table_switch(
+            ctx,
+            selector,
+            table,
+            literals as u32,
+            literals + 1,
+            |ctx, target| match arms.get(target) {
+                Some(arm) => Self::compile_match_arm(ctx, scrutinee, arm, match_val, func_map),
+                None => {
+                    ctx.emit(Instruction::I64Const(0)); // no arm matched: Unit
+                    Ok(())
                 }
+            },
+        )
+    }
+
+    /// Lower a match with enum variant patterns: a br_table on the variant
+    /// tag jumps to the first arm naming each variant, or to the catch-all
+    /// arm, and the arm binds the payload fields. Every variant of the enum
+    /// must be covered one way or the other.
+    fn compile_enum_match(
+        ctx: &mut FuncContext,
+        scrutinee: &Expression,
+        arms: &[(Pattern, Expression)],
+        match_val: u32,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let error = |message: String| WasmCompileError {
+            message,
+            context: "Expression::Match".to_string(),
+            span: None,
+        };
 
-                if let Some(func_idx) = found_idx {
-                    // Push the function index as i64 — this can be used with
-                    // call_indirect via the WASM Table
-                    ctx.emit(Instruction::I64Const(func_idx as i64));
-                } else {
-                    // Lambda not pre-registered — emit inline block
-                    let body_len = body.len();
-                    if body_len == 0 {
-                        ctx.emit(Instruction::I64Const(0));
-                    } else {
-                        for (i, stmt) in body.iter().enumerate() {
-                            let is_last = i == body_len - 1;
-                            Self::compile_stmt(ctx, stmt, is_last, func_map)?;
-                        }
+        let mut enum_name: Option<&str> = None;
+        for (pattern, _) in arms {
+            match pattern {
+                Pattern::EnumVariant {
+                    enum_name: name, ..
+                } => match enum_name {
+                    Some(first) if first != name.as_str() => {
+                        return Err(error(format!(
+                            "match mixes variants of enums '{}' and '{}'",
+                            first, name
+                        )));
                     }
+                    _ => enum_name = Some(name.as_str()),
+                },
+                Pattern::Literal(lit) => {
+                    return Err(error(format!(
+                        "match mixes enum variant and literal ({}) patterns",
+                        lit
+                    )));
                 }
-                let _ = params; // params used during pre-registration
-                Ok(())
+                Pattern::Wildcard | Pattern::Variable(_) => {}
             }
-            Expression::EnumInit {
-                enum_name: _,
-                variant: _,
-                args,
-            } => {
-                // WASM: enum values are represented as i64 tags
-                // For now, push the number of fields as the tag
-                if args.is_empty() {
-                    ctx.emit(Instruction::I64Const(0));
-                } else {
-                    // Compile first field only (simplified WASM representation)
-                    Self::compile_expr(ctx, &args[0], func_map)?;
-                }
-                Ok(())
+        }
+        let enum_name = enum_name.unwrap_or_default();
+        let variants = ctx
+            .enum_layouts
+            .get(enum_name)
+            .cloned()
+            .ok_or_else(|| error(format!("unknown enum '{}'", enum_name)))?;
+
+        for (pattern, _) in arms {
+            let Pattern::EnumVariant {
+                variant, bindings, ..
+            } = pattern
+            else {
+                continue;
+            };
+            let (_, arity) = variants
+                .iter()
+                .find(|(name, _)| name == variant)
+                .ok_or_else(|| {
+                    error(format!("enum '{}' has no variant '{}'", enum_name, variant))
+                })?;
+            if bindings.len() > *arity {
+                return Err(error(format!(
+                    "{}::{} has {} field(s), but the pattern binds {}",
+                    enum_name,
+                    variant,
+                    arity,
+                    bindings.len()
+                )));
             }
         }
+
+        let catch_all = arms
+            .iter()
+            .position(|(pattern, _)| matches!(pattern, Pattern::Wildcard | Pattern::Variable(_)));
+        let mut missing = Vec::new();
+        let table: Vec<u32> = variants
+            .iter()
+            .map(|(name, _)| {
+                let arm = arms
+                    .iter()
+                    .position(|(pattern, _)| {
+                        matches!(pattern, Pattern::EnumVariant { variant, .. } if variant == name)
+                    })
+                    .or(catch_all);
+                arm.unwrap_or_else(|| {
+                    missing.push(name.as_str());
+                    arms.len()
+                }) as u32
+            })
+            .collect();
+        if !missing.is_empty() {
+            return Err(error(format!(
+                "non-exhaustive match on enum '{}': missing {}",
+                enum_name,
+                missing.join(", ")
+            )));
+        }
+
+        if ctx.bounds_checks {
+            Self::emit_heap_object_guard(ctx, match_val, HEAP_TAG_ENUM, 0);
+        }
+        let tag = ctx.scope.scratch("__match_tag");
+        ctx.emit(Instruction::LocalGet(match_val));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: 0,
+        }));
+        ctx.emit(Instruction::LocalSet(tag));
+
+        // A tag past the enum's variants (only possible for a value of some
+        // other enum) goes to the catch-all arm, or traps
+        let default = catch_all.unwrap_or(arms.len());
+        Self::emit_table_switch(
+            ctx,
+            tag,
+            table,
+            default as u32,
+            arms.len() + 1,
+            |ctx, target| match arms.get(target) {
+                Some(arm) => Self::compile_match_arm(ctx, scrutinee, arm, match_val, func_map),
+                None => {
+                    ctx.emit(Instruction::Unreachable);
+                    Ok(())
+                }
+            },
+        )
+    }
+
+    /// Dispatch on the i64 in `selector` through a br_table: to target
+    /// `table[selector]`, or to `default` when the selector is out of range.
+    /// `emit_target(ctx, t)` emits the code of target `t` (of `targets`),
+    /// which leaves the result; the whole switch leaves that result.
+    fn emit_table_switch(
+        ctx: &mut FuncContext,
+        selector: u32,
+        table: Vec<u32>,
+        default: u32,
+        targets: usize,
+        mut emit_target: impl FnMut(&mut FuncContext, usize) -> Result<(), WasmCompileError>,
+    ) -> Result<(), WasmCompileError> {
+        ctx.emit(Instruction::Block(BlockType::Result(ValType::I64)));
+        for _ in 0..targets {
+            ctx.emit(Instruction::Block(BlockType::Empty));
+        }
+
+        // An out-of-range selector must not wrap into the table as an i32
+        let len = table.len();
+        ctx.emit(Instruction::LocalGet(selector));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I32Const(len as i32));
+        ctx.emit(Instruction::LocalGet(selector));
+        ctx.emit(Instruction::I64Const(len as i64));
+        ctx.emit(Instruction::I64LtU);
+        ctx.emit(Instruction::Select);
+        ctx.emit(Instruction::BrTable(table.into(), default));
+
+        // Block t ends right before target t's code, which then leaves the
+        // remaining targets' blocks for the result block
+        for t in 0..targets {
+            ctx.emit(Instruction::End);
+            emit_target(ctx, t)?;
+            ctx.emit(Instruction::Br((targets - 1 - t) as u32));
+        }
+        ctx.emit(Instruction::End);
+        Ok(())
     }
 
-    // =========================================================================
-    // Instruction Helpers
-    // =========================================================================
+    /// Trap unless the heap object in `ptr_local` carries `tag` and its
+    /// length header is at least `min_len`. Values outside the allocated
+    /// heap range trap without being dereferenced.
+    fn emit_heap_object_guard(ctx: &mut FuncContext, ptr_local: u32, tag: i64, min_len: usize) {
+        // !(8 <= ptr < __heap_ptr)
+        ctx.emit(Instruction::LocalGet(ptr_local));
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::I64GeU);
+        ctx.emit(Instruction::LocalGet(ptr_local));
+        ctx.emit(Instruction::GlobalGet(0));
+        ctx.emit(Instruction::I64ExtendI32U);
+        ctx.emit(Instruction::I64LtU);
+        ctx.emit(Instruction::I32And);
+        ctx.emit(Instruction::I32Eqz);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::Unreachable);
+        ctx.emit(Instruction::End);
+
+        // ptr[-8] != tag || ptr[0] < min_len
+        ctx.emit(Instruction::LocalGet(ptr_local));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I32Const(8));
+        ctx.emit(Instruction::I32Sub);
+        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: 0,
+        }));
+        ctx.emit(Instruction::I64Const(tag));
+        ctx.emit(Instruction::I64Ne);
+        ctx.emit(Instruction::LocalGet(ptr_local));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+            offset: 0,
+            align: 3,
+            memory_index: 0,
+        }));
+        ctx.emit(Instruction::I64Const(min_len as i64));
+        ctx.emit(Instruction::I64LtS);
+        ctx.emit(Instruction::I32Or);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        ctx.emit(Instruction::Unreachable);
+        ctx.emit(Instruction::End);
+    }
+
+    /// Map the i64 on the stack to a packed type-name string by reading the
+    /// heap type tag. Only values inside the allocated heap range are
+    /// dereferenced; anything else is reported as "integer".
+    fn emit_heap_type_of(ctx: &mut FuncContext) {
+        let val = ctx.scope.scratch("__typeof_val");
+        let tag = ctx.scope.scratch("__typeof_tag");
+        ctx.emit(Instruction::LocalSet(val));
+
+        // 8 <= val < __heap_ptr
+        ctx.emit(Instruction::LocalGet(val));
+        ctx.emit(Instruction::I64Const(8));
+        ctx.emit(Instruction::I64GeU);
+        ctx.emit(Instruction::LocalGet(val));
+        ctx.emit(Instruction::GlobalGet(0));
+        ctx.emit(Instruction::I64ExtendI32U);
+        ctx.emit(Instruction::I64LtU);
+        ctx.emit(Instruction::I32And);
+        ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+        {
+            ctx.emit(Instruction::LocalGet(val));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I32Const(8));
+            ctx.emit(Instruction::I32Sub);
+            ctx.emit(Instruction::I64Load(wasm_encoder::MemArg {
+                offset: 0,
+                align: 3,
+                memory_index: 0,
+            }));
+            ctx.emit(Instruction::LocalSet(tag));
+
+            ctx.emit(Instruction::LocalGet(tag));
+            ctx.emit(Instruction::I64Const(HEAP_TAG_LIST));
+            ctx.emit(Instruction::I64Eq);
+            ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+            Self::emit_packed_string(ctx, "list");
+            ctx.emit(Instruction::Else);
+            ctx.emit(Instruction::LocalGet(tag));
+            ctx.emit(Instruction::I64Const(HEAP_TAG_STRUCT));
+            ctx.emit(Instruction::I64Eq);
+            ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+            Self::emit_packed_string(ctx, "struct");
+            ctx.emit(Instruction::Else);
+            ctx.emit(Instruction::LocalGet(tag));
+            ctx.emit(Instruction::I64Const(HEAP_TAG_CLOSURE));
+            ctx.emit(Instruction::I64Eq);
+            ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+            Self::emit_packed_string(ctx, "function");
+            ctx.emit(Instruction::Else);
+            ctx.emit(Instruction::LocalGet(tag));
+            ctx.emit(Instruction::I64Const(HEAP_TAG_ENUM));
+            ctx.emit(Instruction::I64Eq);
+            ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+            Self::emit_packed_string(ctx, "enum");
+            ctx.emit(Instruction::Else);
+            ctx.emit(Instruction::LocalGet(tag));
+            ctx.emit(Instruction::I64Const(HEAP_TAG_MAP));
+            ctx.emit(Instruction::I64Eq);
+            ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+            Self::emit_packed_string(ctx, "pmap");
+            ctx.emit(Instruction::Else);
+            ctx.emit(Instruction::LocalGet(tag));
+            ctx.emit(Instruction::I64Const(HEAP_TAG_PVEC));
+            ctx.emit(Instruction::I64Eq);
+            ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+            Self::emit_packed_string(ctx, "pvec");
+            ctx.emit(Instruction::Else);
+            ctx.emit(Instruction::LocalGet(tag));
+            ctx.emit(Instruction::I64Const(HEAP_TAG_PMAP));
+            ctx.emit(Instruction::I64Eq);
+            ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+            Self::emit_packed_string(ctx, "pmap");
+            ctx.emit(Instruction::Else);
+            ctx.emit(Instruction::LocalGet(tag));
+            ctx.emit(Instruction::I64Const(HEAP_TAG_BIGINT));
+            ctx.emit(Instruction::I64Eq);
+            ctx.emit(Instruction::If(BlockType::Result(ValType::I64)));
+            Self::emit_packed_string(ctx, "bigint");
+            ctx.emit(Instruction::Else);
+            Self::emit_packed_string(ctx, "integer");
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::End);
+            ctx.emit(Instruction::End);
+        }
+        ctx.emit(Instruction::Else);
+        Self::emit_packed_string(ctx, "integer");
+        ctx.emit(Instruction::End);
+    }
+
+    /// Whether a binary operation on `args` is done in f64: at least one
+    /// operand is statically a float.
+    fn has_float_operand(ctx: &FuncContext, args: &[Expression]) -> bool {
+        args.iter()
+            .any(|arg| Self::static_type_of(ctx, arg) == Some("float"))
+    }
+
+    /// Compile `expr` and leave it on the stack as an f64: a static integer
+    /// is converted, anything else is taken to be float bits already.
+    fn compile_f64_operand(
+        ctx: &mut FuncContext,
+        expr: &Expression,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let is_integer = Self::static_type_of(ctx, expr) == Some("integer");
+        Self::compile_expr(ctx, expr, func_map)?;
+        ctx.emit(if is_integer {
+            Instruction::F64ConvertI64S
+        } else {
+            Instruction::F64ReinterpretI64
+        });
+        Ok(())
+    }
 
+    /// Compile an arithmetic op. With a float operand, and `float_op` given,
+    /// both sides are computed in f64 and the result is float bits.
     fn compile_binary_op(
         ctx: &mut FuncContext,
         args: &[Expression],
         op: Instruction<'static>,
+        float_op: Option<Instruction<'static>>,
         func_map: &HashMap<String, u32>,
     ) -> Result<(), WasmCompileError> {
         if args.len() != 2 {
             return Err(WasmCompileError {
                 message: format!("Binary op requires 2 args, got {}", args.len()),
                 context: "compile_binary_op".to_string(),
+                span: None,
             });
         }
+        if let Some(float_op) = float_op.filter(|_| Self::has_float_operand(ctx, args)) {
+            Self::compile_f64_operand(ctx, &args[0], func_map)?;
+            Self::compile_f64_operand(ctx, &args[1], func_map)?;
+            ctx.emit(float_op);
+            ctx.emit(Instruction::I64ReinterpretF64);
+            return Ok(());
+        }
         Self::compile_expr(ctx, &args[0], func_map)?;
         Self::compile_expr(ctx, &args[1], func_map)?;
         ctx.emit(op);
         Ok(())
     }
 
+    /// Like `compile_binary_op`, but each operand is first normalized to 0/1
+    /// so that e.g. `and(2, 1)` is true, as in the interpreter.
+    fn compile_logical_op(
+        ctx: &mut FuncContext,
+        args: &[Expression],
+        op: Instruction<'static>,
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        if args.len() != 2 {
+            return Err(WasmCompileError {
+                message: format!("Logical op requires 2 args, got {}", args.len()),
+                context: "compile_logical_op".to_string(),
+                span: None,
+            });
+        }
+        for arg in args {
+            Self::compile_expr(ctx, arg, func_map)?;
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::I64Ne);
+            ctx.emit(Instruction::I64ExtendI32U);
+        }
+        ctx.emit(op);
+        Ok(())
+    }
+
     fn compile_compare_op(
         ctx: &mut FuncContext,
         args: &[Expression],
         op: Instruction<'static>,
+        float_op: Instruction<'static>,
         func_map: &HashMap<String, u32>,
     ) -> Result<(), WasmCompileError> {
         if args.len() != 2 {
             return Err(WasmCompileError {
                 message: format!("Comparison requires 2 args, got {}", args.len()),
                 context: "compile_compare_op".to_string(),
+                span: None,
             });
         }
-        Self::compile_expr(ctx, &args[0], func_map)?;
-        Self::compile_expr(ctx, &args[1], func_map)?;
-        ctx.emit(op);
+        if Self::has_float_operand(ctx, args) {
+            Self::compile_f64_operand(ctx, &args[0], func_map)?;
+            Self::compile_f64_operand(ctx, &args[1], func_map)?;
+            ctx.emit(float_op);
+        } else {
+            Self::compile_expr(ctx, &args[0], func_map)?;
+            Self::compile_expr(ctx, &args[1], func_map)?;
+            ctx.emit(op);
+        }
         // Comparison returns i32 in WASM — extend to i64 for Ark's uniform type
         ctx.emit(Instruction::I64ExtendI32U);
         Ok(())
@@ -2800,8 +12116,9 @@ impl WasmCodegen {
     /// Compile `print(arg)` → type-dispatched output via WASI fd_write.
     ///
     /// Dispatches at compile time based on AST node type:
-    /// - `Expression::Literal(s)` → string print (unpack ptr|len, fd_write)
-    /// - Everything else → integer-to-ASCII conversion (itoa) + fd_write
+    /// - Statically a float → `__print_f64` (fixed-point text + fd_write)
+    /// - `Expression::Literal(s)` → `__print_str` (unpack ptr|len, fd_write)
+    /// - Everything else → `__print_i64` (itoa + fd_write)
     ///
     /// Memory layout (scratch region bytes 0–63):
     ///   0–23:  digit buffer (max 20 digits + sign + newline + padding)
@@ -2815,15 +12132,22 @@ impl WasmCodegen {
         func_map: &HashMap<String, u32>,
     ) -> Result<(), WasmCompileError> {
         for arg in args {
-            // Compile-time dispatch: string literals vs everything else
-            match arg {
-                Expression::Literal(_) => {
-                    Self::compile_print_string(ctx, arg, func_map)?;
-                }
-                _ => {
-                    Self::compile_print_integer(ctx, arg, func_map)?;
-                }
-            }
+            // Compile-time dispatch on the static type: floats, strings
+            // (literals, string-valued locals and string intrinsics),
+            // everything else. Literals keep the string path whatever they
+            // spell.
+            let helper = match (arg, Self::static_type_of(ctx, arg)) {
+                (_, Some("float")) => "__print_f64",
+                (Expression::Literal(_), _) | (_, Some("string")) => "__print_str",
+                _ => "__print_i64",
+            };
+            Self::compile_expr(ctx, arg, func_map)?;
+            let helper_idx = func_map.get(helper).ok_or_else(|| WasmCompileError {
+                message: format!("{} not found", helper),
+                context: "print".to_string(),
+                span: None,
+            })?;
+            ctx.emit(Instruction::Call(*helper_idx));
         }
 
         // Print returns Unit (0)
@@ -2831,21 +12155,13 @@ impl WasmCodegen {
         Ok(())
     }
 
-    /// Compile string print: unpack packed ptr|len from i64, fd_write string + newline.
+    /// Body of `__print_str(packed)`: unpack ptr|len, fd_write string + newline.
     ///
     /// String literals are packed as `(ptr << 32) | len` in a single i64.
     /// This function unpacks them, builds two iovecs (string + newline), and
     /// uses fd_write with iovs_len=2 to output them in a single syscall.
-    fn compile_print_string(
-        ctx: &mut FuncContext,
-        arg: &Expression,
-        func_map: &HashMap<String, u32>,
-    ) -> Result<(), WasmCompileError> {
-        // Compile the expression (pushes packed ptr|len as i64)
-        Self::compile_expr(ctx, arg, func_map)?;
-
-        let packed_local = ctx.scope.get_or_alloc("__print_val");
-        ctx.emit(Instruction::LocalSet(packed_local));
+    fn emit_print_str(ctx: &mut FuncContext, fd_write: u32) {
+        let packed_local = 0; // the parameter
 
         // Extract ptr = (packed >> 32) as i32
         // Extract len = (packed & 0xFFFFFFFF) as i32
@@ -2902,37 +12218,184 @@ impl WasmCodegen {
         ctx.emit(Instruction::I32Const(32)); // iovs pointer (two iovecs)
         ctx.emit(Instruction::I32Const(2)); // iovs_len = 2 (string + newline)
         ctx.emit(Instruction::I32Const(48)); // nwritten pointer
-        ctx.emit(Instruction::Call(0)); // fd_write is import index 0
+        ctx.emit(Instruction::Call(fd_write));
         ctx.emit(Instruction::Drop); // drop fd_write return value
+    }
+
+    /// `assert(cond, message?)` and `assert_eq(left, right, message?)`:
+    /// a failure logs `[ERROR] assertion failed: message` to stderr and the
+    /// call returns 0, a pass returns 1. The message must be a string
+    /// literal; values are not shown, as the VM does, since there is no
+    /// generic formatter in WASM.
+    fn compile_assert(
+        ctx: &mut FuncContext,
+        name: &str,
+        args: &[Expression],
+        func_map: &HashMap<String, u32>,
+    ) -> Result<(), WasmCompileError> {
+        let arity = if name == "assert" { 1 } else { 2 };
+        let message = match &args[arity.min(args.len())..] {
+            [] if args.len() == arity => "assertion failed".to_string(),
+            [Expression::Literal(m)] => format!("assertion failed: {}", m),
+            _ => {
+                return Err(WasmCompileError {
+                    message: format!(
+                        "{} expects {} value(s) and an optional string literal message",
+                        name, arity
+                    ),
+                    context: name.to_string(),
+                    span: None,
+                });
+            }
+        };
 
+        if name == "assert" {
+            Self::compile_expr(ctx, &args[0], func_map)?;
+        } else {
+            Self::compile_compare_op(
+                ctx,
+                &args[..2],
+                Instruction::I64Eq,
+                Instruction::F64Eq,
+                func_map,
+            )?;
+        }
+        let passed = ctx.scope.scratch("__assert_ok");
+        ctx.emit(Instruction::LocalTee(passed));
+        ctx.emit(Instruction::I64Eqz);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        Self::compile_log(
+            ctx,
+            &[
+                Expression::Literal("error".to_string()),
+                Expression::Literal(message),
+            ],
+            func_map,
+        )?;
+        ctx.emit(Instruction::Drop);
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::LocalGet(passed));
         Ok(())
     }
 
-    /// Compile integer print: itoa conversion + fd_write.
+    /// Compile `sys.log(level, message)` to a single fd_write of
+    /// `[LEVEL] message\n` on fd 2; `sys.log(message)` logs at info under a
+    /// `[LOG]` prefix, as in the interpreter. The level must be a
+    /// string literal so calls below the configured level can be dropped
+    /// here; the message is a packed string. Structured fields are
+    /// interpreter-only.
     ///
-    /// Converts an i64 value to its decimal ASCII string representation in
-    /// linear memory, then writes it to stdout via fd_write. Handles negative
-    /// numbers and appends a newline.
-    fn compile_print_integer(
+    /// Memory layout (scratch region): newline at 24, three iovecs at
+    /// 32/40/48 (prefix, message, newline), nwritten at 56.
+    fn compile_log(
         ctx: &mut FuncContext,
-        arg: &Expression,
+        args: &[Expression],
         func_map: &HashMap<String, u32>,
     ) -> Result<(), WasmCompileError> {
-        Self::compile_expr(ctx, arg, func_map)?;
+        let (rank, tag, message) = match args {
+            [message] => (1, "LOG".to_string(), message),
+            [Expression::Literal(level), message] => {
+                let rank = LOG_LEVELS
+                    .iter()
+                    .position(|l| l.eq_ignore_ascii_case(level))
+                    .ok_or_else(|| WasmCompileError {
+                        message: format!("unknown log level '{}'", level),
+                        context: "sys.log".to_string(),
+                        span: None,
+                    })?;
+                (rank, LOG_LEVELS[rank].to_uppercase(), message)
+            }
+            _ => {
+                return Err(WasmCompileError {
+                    message: "sys.log expects (level, message) with a literal level; \
+                              structured fields are not supported in WASM"
+                        .to_string(),
+                    context: "sys.log".to_string(),
+                    span: None,
+                });
+            }
+        };
+
+        if rank >= ctx.log_level {
+            let prefix = format!("[{}] ", tag);
+            let (prefix_ptr, prefix_len) = ctx.alloc_string(&prefix);
+
+            Self::compile_expr(ctx, message, func_map)?;
+            let packed_local = ctx.scope.scratch("__log_msg");
+            ctx.emit(Instruction::LocalSet(packed_local));
+
+            ctx.emit(Instruction::I32Const(24));
+            ctx.emit(Instruction::I32Const(10)); // '\n'
+            ctx.emit(Instruction::I32Store8(wasm_encoder::MemArg {
+                offset: 0,
+                align: 0,
+                memory_index: 0,
+            }));
+
+            let store_i32 = wasm_encoder::MemArg {
+                offset: 0,
+                align: 2,
+                memory_index: 0,
+            };
+
+            // iovec[0] at 32: the "[LEVEL] " prefix
+            ctx.emit(Instruction::I32Const(32));
+            ctx.emit(Instruction::I32Const(prefix_ptr));
+            ctx.emit(Instruction::I32Store(store_i32));
+            ctx.emit(Instruction::I32Const(36));
+            ctx.emit(Instruction::I32Const(prefix_len));
+            ctx.emit(Instruction::I32Store(store_i32));
+
+            // iovec[1] at 40: the message, ptr = packed >> 32, len = low 32 bits
+            ctx.emit(Instruction::I32Const(40));
+            ctx.emit(Instruction::LocalGet(packed_local));
+            ctx.emit(Instruction::I64Const(32));
+            ctx.emit(Instruction::I64ShrU);
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I32Store(store_i32));
+            ctx.emit(Instruction::I32Const(44));
+            ctx.emit(Instruction::LocalGet(packed_local));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I32Store(store_i32));
+
+            // iovec[2] at 48: the newline byte
+            ctx.emit(Instruction::I32Const(48));
+            ctx.emit(Instruction::I32Const(24));
+            ctx.emit(Instruction::I32Store(store_i32));
+            ctx.emit(Instruction::I32Const(52));
+            ctx.emit(Instruction::I32Const(1));
+            ctx.emit(Instruction::I32Store(store_i32));
+
+            // fd_write(fd=2, iovs=32, iovs_len=3, nwritten=56)
+            ctx.emit(Instruction::I32Const(2)); // fd = stderr
+            ctx.emit(Instruction::I32Const(32));
+            ctx.emit(Instruction::I32Const(3));
+            ctx.emit(Instruction::I32Const(56));
+            ctx.emit(Instruction::Call(Self::import_index(func_map, "fd_write")?));
+            ctx.emit(Instruction::Drop);
+        }
+
+        // sys.log returns Unit (0)
+        ctx.emit(Instruction::I64Const(0));
+        Ok(())
+    }
 
-        // We need several scratch locals:
-        //   __print_val    : the i64 value to print
+    /// Body of `__print_i64(value)`: itoa conversion + fd_write.
+    ///
+    /// Converts an i64 value to its decimal ASCII string representation in
+    /// linear memory, then writes it to stdout via fd_write. Handles negative
+    /// numbers and appends a newline.
+    fn emit_print_i64(ctx: &mut FuncContext, fd_write: u32) {
+        // Locals:
+        //   0              : the i64 value to print (parameter)
         //   __print_neg    : 1 if negative, 0 if positive (i64)
         //   __print_pos    : current write position in digit buffer (i64 used as i32)
         //   __print_digit  : temp for digit extraction
-        let val_local = ctx.scope.get_or_alloc("__print_val");
+        let val_local = 0;
         let neg_local = ctx.scope.get_or_alloc("__print_neg");
         let pos_local = ctx.scope.get_or_alloc("__print_pos");
         let digit_local = ctx.scope.get_or_alloc("__print_digit");
 
-        // Store the expression result
-        ctx.emit(Instruction::LocalSet(val_local));
-
         // Initialize: not negative
         ctx.emit(Instruction::I64Const(0));
         ctx.emit(Instruction::LocalSet(neg_local));
@@ -3031,231 +12494,513 @@ impl WasmCodegen {
         }
         ctx.emit(Instruction::End); // end if/else
 
-        // If negative, prepend '-'
-        ctx.emit(Instruction::LocalGet(neg_local));
-        ctx.emit(Instruction::I64Const(1));
-        ctx.emit(Instruction::I64Eq);
-        ctx.emit(Instruction::If(BlockType::Empty));
+        // If negative, prepend '-'
+        ctx.emit(Instruction::LocalGet(neg_local));
+        ctx.emit(Instruction::I64Const(1));
+        ctx.emit(Instruction::I64Eq);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(pos_local));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Sub);
+            ctx.emit(Instruction::LocalSet(pos_local));
+            // mem[pos] = '-' (ASCII 45)
+            ctx.emit(Instruction::LocalGet(pos_local));
+            ctx.emit(Instruction::I32WrapI64);
+            ctx.emit(Instruction::I32Const(45)); // '-'
+            ctx.emit(Instruction::I32Store8(wasm_encoder::MemArg {
+                offset: 0,
+                align: 0,
+                memory_index: 0,
+            }));
+        }
+        ctx.emit(Instruction::End);
+
+        // Append newline at position 20 (right after digit area ending at 19)
+        ctx.emit(Instruction::I32Const(20));
+        ctx.emit(Instruction::I32Const(10)); // '\n'
+        ctx.emit(Instruction::I32Store8(wasm_encoder::MemArg {
+            offset: 0,
+            align: 0,
+            memory_index: 0,
+        }));
+
+        // Digits are at mem[pos..19], newline at mem[20]
+        // Total output length = 21 - pos (includes newline)
+        // Build iovec at offset 32: { buf_ptr: i32, buf_len: i32 }
+        ctx.emit(Instruction::I32Const(32)); // iovec address
+        ctx.emit(Instruction::LocalGet(pos_local));
+        ctx.emit(Instruction::I32WrapI64); // buf_ptr = pos
+        ctx.emit(Instruction::I32Store(wasm_encoder::MemArg {
+            offset: 0,
+            align: 2,
+            memory_index: 0,
+        }));
+
+        // buf_len = 21 - pos
+        ctx.emit(Instruction::I32Const(36)); // iovec + 4
+        ctx.emit(Instruction::I32Const(21));
+        ctx.emit(Instruction::LocalGet(pos_local));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::I32Sub); // 21 - pos
+        ctx.emit(Instruction::I32Store(wasm_encoder::MemArg {
+            offset: 0,
+            align: 2,
+            memory_index: 0,
+        }));
+
+        // Call fd_write(fd=1, iovs=32, iovs_len=1, nwritten=48)
+        ctx.emit(Instruction::I32Const(1)); // fd = stdout
+        ctx.emit(Instruction::I32Const(32)); // iovs pointer
+        ctx.emit(Instruction::I32Const(1)); // iovs count
+        ctx.emit(Instruction::I32Const(48)); // nwritten pointer
+        ctx.emit(Instruction::Call(fd_write));
+        ctx.emit(Instruction::Drop); // drop fd_write return value
+    }
+
+    /// Body of `__print_f64(bits)`: print a float with up to six decimals
+    /// (trailing zeros trimmed, at least one kept) and a newline.
+    ///
+    /// The value is rounded to millionths as a u64, so magnitudes beyond
+    /// about 1.8e13 print saturated and NaN prints as `0.0`. The text is
+    /// built right to left ending at mem[22] (the newline); at most 14
+    /// integer digits, the point, 6 decimals and a sign fit in bytes 0–21.
+    fn emit_print_f64(ctx: &mut FuncContext, fd_write: u32) {
+        let bits_local = 0;
+        let neg_local = ctx.scope.get_or_alloc("__printf_neg");
+        let int_local = ctx.scope.get_or_alloc("__printf_int");
+        let frac_local = ctx.scope.get_or_alloc("__printf_frac");
+        let digits_local = ctx.scope.get_or_alloc("__printf_digits");
+        let pos_local = ctx.scope.get_or_alloc("__printf_pos");
+        let store8 = || {
+            Instruction::I32Store8(wasm_encoder::MemArg {
+                offset: 0,
+                align: 0,
+                memory_index: 0,
+            })
+        };
+        // pos--; mem[pos] = <value pushed by `push_byte`>
+        let emit_put = |ctx: &mut FuncContext, push_byte: &dyn Fn(&mut FuncContext)| {
+            ctx.emit(Instruction::LocalGet(pos_local));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Sub);
+            ctx.emit(Instruction::LocalSet(pos_local));
+            ctx.emit(Instruction::LocalGet(pos_local));
+            ctx.emit(Instruction::I32WrapI64);
+            push_byte(ctx);
+            ctx.emit(store8());
+        };
+        // Write the last decimal digit of `local`, then divide it by 10
+        let emit_digit = |ctx: &mut FuncContext, local: u32| {
+            emit_put(ctx, &|ctx| {
+                ctx.emit(Instruction::LocalGet(local));
+                ctx.emit(Instruction::I64Const(10));
+                ctx.emit(Instruction::I64RemU);
+                ctx.emit(Instruction::I64Const(48));
+                ctx.emit(Instruction::I64Add);
+                ctx.emit(Instruction::I32WrapI64);
+            });
+            ctx.emit(Instruction::LocalGet(local));
+            ctx.emit(Instruction::I64Const(10));
+            ctx.emit(Instruction::I64DivU);
+            ctx.emit(Instruction::LocalSet(local));
+        };
+
+        // neg = x < 0
+        ctx.emit(Instruction::LocalGet(bits_local));
+        ctx.emit(Instruction::F64ReinterpretI64);
+        ctx.emit(Instruction::F64Const(0.0));
+        ctx.emit(Instruction::F64Lt);
+        ctx.emit(Instruction::I64ExtendI32U);
+        ctx.emit(Instruction::LocalSet(neg_local));
+
+        // scaled = round(|x| * 1e6); int = scaled / 1e6; frac = scaled % 1e6
+        ctx.emit(Instruction::LocalGet(bits_local));
+        ctx.emit(Instruction::F64ReinterpretI64);
+        ctx.emit(Instruction::F64Abs);
+        ctx.emit(Instruction::F64Const(1_000_000.0));
+        ctx.emit(Instruction::F64Mul);
+        ctx.emit(Instruction::F64Nearest);
+        ctx.emit(Instruction::I64TruncSatF64U);
+        ctx.emit(Instruction::LocalTee(int_local));
+        ctx.emit(Instruction::I64Const(1_000_000));
+        ctx.emit(Instruction::I64RemU);
+        ctx.emit(Instruction::LocalSet(frac_local));
+        ctx.emit(Instruction::LocalGet(int_local));
+        ctx.emit(Instruction::I64Const(1_000_000));
+        ctx.emit(Instruction::I64DivU);
+        ctx.emit(Instruction::LocalSet(int_local));
+
+        // Trim trailing zeros: while digits > 1 && frac % 10 == 0
+        ctx.emit(Instruction::I64Const(6));
+        ctx.emit(Instruction::LocalSet(digits_local));
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
         {
-            ctx.emit(Instruction::LocalGet(pos_local));
+            ctx.emit(Instruction::LocalGet(digits_local));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Eq);
+            ctx.emit(Instruction::BrIf(1));
+            ctx.emit(Instruction::LocalGet(frac_local));
+            ctx.emit(Instruction::I64Const(10));
+            ctx.emit(Instruction::I64RemU);
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::I64Ne);
+            ctx.emit(Instruction::BrIf(1));
+            ctx.emit(Instruction::LocalGet(frac_local));
+            ctx.emit(Instruction::I64Const(10));
+            ctx.emit(Instruction::I64DivU);
+            ctx.emit(Instruction::LocalSet(frac_local));
+            ctx.emit(Instruction::LocalGet(digits_local));
             ctx.emit(Instruction::I64Const(1));
             ctx.emit(Instruction::I64Sub);
-            ctx.emit(Instruction::LocalSet(pos_local));
-            // mem[pos] = '-' (ASCII 45)
-            ctx.emit(Instruction::LocalGet(pos_local));
-            ctx.emit(Instruction::I32WrapI64);
-            ctx.emit(Instruction::I32Const(45)); // '-'
-            ctx.emit(Instruction::I32Store8(wasm_encoder::MemArg {
-                offset: 0,
-                align: 0,
-                memory_index: 0,
-            }));
+            ctx.emit(Instruction::LocalSet(digits_local));
+            ctx.emit(Instruction::Br(0));
         }
         ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
 
-        // Append newline at position 20 (right after digit area ending at 19)
-        ctx.emit(Instruction::I32Const(20));
+        // Newline at mem[22]; text grows leftwards from there
+        ctx.emit(Instruction::I32Const(22));
         ctx.emit(Instruction::I32Const(10)); // '\n'
-        ctx.emit(Instruction::I32Store8(wasm_encoder::MemArg {
-            offset: 0,
-            align: 0,
-            memory_index: 0,
-        }));
+        ctx.emit(store8());
+        ctx.emit(Instruction::I64Const(22));
+        ctx.emit(Instruction::LocalSet(pos_local));
 
-        // Digits are at mem[pos..19], newline at mem[20]
-        // Total output length = 21 - pos (includes newline)
-        // Build iovec at offset 32: { buf_ptr: i32, buf_len: i32 }
-        ctx.emit(Instruction::I32Const(32)); // iovec address
+        // Decimals, zero-padded to `digits`
+        ctx.emit(Instruction::Block(BlockType::Empty));
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            ctx.emit(Instruction::LocalGet(digits_local));
+            ctx.emit(Instruction::I64Eqz);
+            ctx.emit(Instruction::BrIf(1));
+            emit_digit(ctx, frac_local);
+            ctx.emit(Instruction::LocalGet(digits_local));
+            ctx.emit(Instruction::I64Const(1));
+            ctx.emit(Instruction::I64Sub);
+            ctx.emit(Instruction::LocalSet(digits_local));
+            ctx.emit(Instruction::Br(0));
+        }
+        ctx.emit(Instruction::End);
+        ctx.emit(Instruction::End);
+
+        emit_put(ctx, &|ctx| ctx.emit(Instruction::I32Const(46))); // '.'
+
+        // Integer part, at least one digit
+        ctx.emit(Instruction::Loop(BlockType::Empty));
+        {
+            emit_digit(ctx, int_local);
+            ctx.emit(Instruction::LocalGet(int_local));
+            ctx.emit(Instruction::I64Const(0));
+            ctx.emit(Instruction::I64Ne);
+            ctx.emit(Instruction::BrIf(0));
+        }
+        ctx.emit(Instruction::End);
+
+        ctx.emit(Instruction::LocalGet(neg_local));
+        ctx.emit(Instruction::I32WrapI64);
+        ctx.emit(Instruction::If(BlockType::Empty));
+        emit_put(ctx, &|ctx| ctx.emit(Instruction::I32Const(45))); // '-'
+        ctx.emit(Instruction::End);
+
+        // iovec at 32: { buf_ptr: pos, buf_len: 23 - pos }
+        ctx.emit(Instruction::I32Const(32));
         ctx.emit(Instruction::LocalGet(pos_local));
-        ctx.emit(Instruction::I32WrapI64); // buf_ptr = pos
+        ctx.emit(Instruction::I32WrapI64);
         ctx.emit(Instruction::I32Store(wasm_encoder::MemArg {
             offset: 0,
             align: 2,
             memory_index: 0,
         }));
-
-        // buf_len = 21 - pos
-        ctx.emit(Instruction::I32Const(36)); // iovec + 4
-        ctx.emit(Instruction::I32Const(21));
+        ctx.emit(Instruction::I32Const(36));
+        ctx.emit(Instruction::I32Const(23));
         ctx.emit(Instruction::LocalGet(pos_local));
         ctx.emit(Instruction::I32WrapI64);
-        ctx.emit(Instruction::I32Sub); // 21 - pos
+        ctx.emit(Instruction::I32Sub);
         ctx.emit(Instruction::I32Store(wasm_encoder::MemArg {
             offset: 0,
             align: 2,
             memory_index: 0,
         }));
 
-        // Call fd_write(fd=1, iovs=32, iovs_len=1, nwritten=48)
-        ctx.emit(Instruction::I32Const(1)); // fd = stdout
-        ctx.emit(Instruction::I32Const(32)); // iovs pointer
-        ctx.emit(Instruction::I32Const(1)); // iovs count
-        ctx.emit(Instruction::I32Const(48)); // nwritten pointer
-        ctx.emit(Instruction::Call(0)); // fd_write is import index 0
-        ctx.emit(Instruction::Drop); // drop fd_write return value
-
-        Ok(())
+        // fd_write(fd=1, iovs=32, iovs_len=1, nwritten=48)
+        ctx.emit(Instruction::I32Const(1));
+        ctx.emit(Instruction::I32Const(32));
+        ctx.emit(Instruction::I32Const(1));
+        ctx.emit(Instruction::I32Const(48));
+        ctx.emit(Instruction::Call(fd_write));
+        ctx.emit(Instruction::Drop);
     }
 
     // =========================================================================
     // Module Emission
     // =========================================================================
 
+    /// Initial `__heap_ptr`: past all string data, 8-aligned, and never
+    /// below 2048 so the low scratch and free-list bytes stay clear.
+    fn static_heap_start(&self) -> i32 {
+        let mut max_string_end = STRING_MEMORY_START;
+        for (_, _, ctx) in &self.functions {
+            for (offset, bytes) in &ctx.string_data {
+                let end = *offset + bytes.len() as i32;
+                if end > max_string_end {
+                    max_string_end = end;
+                }
+            }
+        }
+        ((max_string_end + 7) & !7).max(2048)
+    }
+
+    /// Pages needed to hold the string data and the initial heap pointer.
+    fn static_memory_pages(&self) -> u32 {
+        (self.static_heap_start() as u32).div_ceil(65536)
+    }
+
+    /// The `name` section: module, function, local and global names.
+    /// Imports come first in function index order, as in the code section.
+    fn name_section(&self, module_name: &str, layout: &ModuleLayout) -> wasm_encoder::NameSection {
+        let mut functions = wasm_encoder::NameMap::new();
+        for (i, import) in self.imports.iter().enumerate() {
+            if let Some(func_idx) = layout.funcs[i] {
+                functions.append(func_idx, &import.name);
+            }
+        }
+        let mut locals = wasm_encoder::IndirectNameMap::new();
+        for (i, (_, name, ctx)) in self.functions.iter().enumerate() {
+            let Some(func_idx) = layout.funcs[self.import_count as usize + i] else {
+                continue;
+            };
+            functions.append(func_idx, name);
+
+            let mut named: Vec<(u32, String)> = ctx
+                .scope
+                .locals
+                .iter()
+                .map(|(name, &idx)| (idx, name.clone()))
+                .collect();
+            named.extend(
+                ctx.scope
+                    .scratch_pool
+                    .iter()
+                    .enumerate()
+                    .map(|(slot, &idx)| (idx, format!("$scratch{}", slot))),
+            );
+            named.sort();
+            named.dedup_by_key(|(idx, _)| *idx);
+            if !named.is_empty() {
+                let mut local_names = wasm_encoder::NameMap::new();
+                for (idx, name) in &named {
+                    local_names.append(*idx, name);
+                }
+                locals.append(func_idx, &local_names);
+            }
+        }
+
+        let mut globals = wasm_encoder::NameMap::new();
+        globals.append(0, "__heap_ptr");
+        for (i, (name, _)) in self.exported_globals.iter().enumerate() {
+            globals.append(1 + i as u32, name);
+        }
+        if let Some(fuel) = self.fuel_global() {
+            globals.append(fuel, FUEL_GLOBAL);
+        }
+
+        let mut names = wasm_encoder::NameSection::new();
+        names.module(module_name);
+        names.functions(&functions);
+        names.locals(&locals);
+        names.globals(&globals);
+        names
+    }
+
+    /// Source Map v3 for `wasm`, this code generator's output. Each function
+    /// gets one segment at the start of its body (for wasm the generated
+    /// column is the byte offset in the module) pointing at its `func`
+    /// line. Functions without a span get an unmapped segment so that they
+    /// don't inherit the position of the function before them.
+    fn source_map(&self, wasm: &[u8]) -> Result<String, WasmCompileError> {
+        let mut body_offsets = Vec::new();
+        for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+            let payload = payload.map_err(|e| WasmCompileError {
+                message: format!("failed to read the emitted module: {}", e),
+                context: "source map".to_string(),
+                span: None,
+            })?;
+            if let wasmparser::Payload::CodeSectionEntry(body) = payload {
+                body_offsets.push(body.range().start as i64);
+            }
+        }
+
+        let layout = self.layout();
+        let live_functions = self
+            .functions
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| layout.funcs[self.import_count as usize + i].is_some());
+
+        let mut sources: Vec<&str> = Vec::new();
+        let mut mappings = String::new();
+        // Segment fields are deltas from the previous segment's
+        let (mut prev_offset, mut prev_source, mut prev_line, mut prev_col) = (0, 0, 0, 0);
+        for ((_, (_, name, _)), offset) in live_functions.zip(body_offsets) {
+            if !mappings.is_empty() {
+                mappings.push(',');
+            }
+            vlq_push(&mut mappings, offset - prev_offset);
+            prev_offset = offset;
+
+            let Some(span) = self.func_spans.get(name) else {
+                continue;
+            };
+            let source = match sources.iter().position(|file| *file == span.file) {
+                Some(i) => i as i64,
+                None => {
+                    sources.push(&span.file);
+                    sources.len() as i64 - 1
+                }
+            };
+            let line = i64::from(span.start_line.saturating_sub(1));
+            let col = i64::from(span.start_col.saturating_sub(1));
+            vlq_push(&mut mappings, source - prev_source);
+            vlq_push(&mut mappings, line - prev_line);
+            vlq_push(&mut mappings, col - prev_col);
+            (prev_source, prev_line, prev_col) = (source, line, col);
+        }
+
+        Ok(serde_json::json!({
+            "version": 3,
+            "sources": sources,
+            "names": [],
+            "mappings": mappings,
+        })
+        .to_string())
+    }
+
     fn emit_module(&self) -> Vec<u8> {
+        self.emit_module_with(&self.layout())
+    }
+
+    /// Functions to export, by their codegen index: everything in legacy
+    /// mode (no `#[export]` anywhere), else the `#[export]` and system
+    /// functions; in component mode, the WIT functions and `_initialize`.
+    fn exported_functions(&self) -> Vec<(String, u32)> {
+        if let Some(component_exports) = &self.component_exports {
+            let mut exports: Vec<(String, u32)> = component_exports
+                .iter()
+                .map(|(core_name, ark_name)| (core_name.clone(), self.func_index_map[ark_name]))
+                .collect();
+            exports.push(("_initialize".to_string(), self.func_index_map["_start"]));
+            return exports;
+        }
+
+        // Backward compatibility: if NO function has #[export], export everything
+        let has_any_export_attrs = self
+            .func_attributes
+            .values()
+            .any(|attrs| attrs.iter().any(|a| a == "export"));
+        self.functions
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, name, _))| !has_any_export_attrs || self.should_export(name))
+            .map(|(i, (_, name, _))| (name.clone(), self.import_count + i as u32))
+            .collect()
+    }
+
+    /// Drop what the exports can't reach: functions (and imports) are live
+    /// if exported, called, or used as a value by a live function; types
+    /// are live if a live function, import or `call_indirect` uses them.
+    fn layout(&self) -> ModuleLayout {
+        let exports = self.exported_functions();
+        let total_funcs = self.import_count as usize + self.functions.len();
+        let mut live_funcs = vec![false; total_funcs];
+        let mut pending: Vec<u32> = exports.iter().map(|(_, idx)| *idx).collect();
+        let mut live_types = vec![false; self.types.len()];
+        while let Some(idx) = pending.pop() {
+            if std::mem::replace(&mut live_funcs[idx as usize], true) {
+                continue;
+            }
+            let Some(slot) = (idx as usize).checked_sub(self.import_count as usize) else {
+                live_types[self.imports[idx as usize].type_idx as usize] = true;
+                continue;
+            };
+            let (type_idx, _, ctx) = &self.functions[slot];
+            live_types[*type_idx as usize] = true;
+            for instr in &ctx.instructions {
+                match instr {
+                    Instruction::Call(callee) => pending.push(*callee),
+                    Instruction::CallIndirect { type_index, .. } => {
+                        live_types[*type_index as usize] = true
+                    }
+                    _ => {}
+                }
+            }
+            for &pos in &ctx.func_refs {
+                if let Instruction::I64Const(func_idx) = ctx.instructions[pos] {
+                    pending.push(func_idx as u32);
+                }
+            }
+        }
+
+        let renumber = |live: Vec<bool>| {
+            let mut next = 0;
+            live.into_iter()
+                .map(|is_live| {
+                    is_live.then(|| {
+                        next += 1;
+                        next - 1
+                    })
+                })
+                .collect()
+        };
+        ModuleLayout {
+            funcs: renumber(live_funcs),
+            types: renumber(live_types),
+            exports,
+        }
+    }
+
+    fn emit_module_with(&self, layout: &ModuleLayout) -> Vec<u8> {
         let mut module = Module::new();
 
         // --- Type Section ---
         let mut types = TypeSection::new();
-        for (params, results) in &self.types {
-            types.ty().function(params.clone(), results.clone());
+        for (i, (params, results)) in self.types.iter().enumerate() {
+            if layout.types[i].is_some() {
+                types.ty().function(params.clone(), results.clone());
+            }
         }
         module.section(&types);
 
-        // --- Import Section (WASI Preview1) ---
+        // --- Import Section ---
         let mut imports = ImportSection::new();
-        // Index 0: fd_write
-        imports.import(
-            "wasi_snapshot_preview1",
-            "fd_write",
-            wasm_encoder::EntityType::Function(WASI_FD_WRITE_TYPE_IDX),
-        );
-        // Index 1: fd_read (same type as fd_write)
-        imports.import(
-            "wasi_snapshot_preview1",
-            "fd_read",
-            wasm_encoder::EntityType::Function(WASI_FD_WRITE_TYPE_IDX),
-        );
-        // Index 2: clock_time_get
-        imports.import(
-            "wasi_snapshot_preview1",
-            "clock_time_get",
-            wasm_encoder::EntityType::Function(WASI_CLOCK_TIME_GET_TYPE_IDX),
-        );
-        // Index 3: random_get
-        imports.import(
-            "wasi_snapshot_preview1",
-            "random_get",
-            wasm_encoder::EntityType::Function(WASI_RANDOM_GET_TYPE_IDX),
-        );
-        // Index 4: args_get
-        imports.import(
-            "wasi_snapshot_preview1",
-            "args_get",
-            wasm_encoder::EntityType::Function(WASI_RANDOM_GET_TYPE_IDX), // (i32,i32)->i32
-        );
-        // Index 5: args_sizes_get
-        imports.import(
-            "wasi_snapshot_preview1",
-            "args_sizes_get",
-            wasm_encoder::EntityType::Function(WASI_RANDOM_GET_TYPE_IDX),
-        );
-        // Index 6: environ_get
-        imports.import(
-            "wasi_snapshot_preview1",
-            "environ_get",
-            wasm_encoder::EntityType::Function(WASI_RANDOM_GET_TYPE_IDX),
-        );
-        // Index 7: environ_sizes_get
-        imports.import(
-            "wasi_snapshot_preview1",
-            "environ_sizes_get",
-            wasm_encoder::EntityType::Function(WASI_RANDOM_GET_TYPE_IDX),
-        );
-        // Index 8: proc_exit
-        imports.import(
-            "wasi_snapshot_preview1",
-            "proc_exit",
-            wasm_encoder::EntityType::Function(WASI_PROC_EXIT_TYPE_IDX),
-        );
-        // Index 9: path_open
-        imports.import(
-            "wasi_snapshot_preview1",
-            "path_open",
-            wasm_encoder::EntityType::Function(WASI_PATH_OPEN_TYPE_IDX),
-        );
-        // Index 10: fd_close
-        imports.import(
-            "wasi_snapshot_preview1",
-            "fd_close",
-            wasm_encoder::EntityType::Function(WASI_FD_CLOSE_TYPE_IDX),
-        );
-
-        // --- Ark Host Imports (index 11..24) ---
-        // Math unary: sin, cos, tan, asin, acos, atan, sqrt  (type 6: i64 -> i64)
-        for name in &[
-            "math_sin",
-            "math_cos",
-            "math_tan",
-            "math_asin",
-            "math_acos",
-            "math_atan",
-        ] {
-            imports.import(
-                "ark_host",
-                name,
-                wasm_encoder::EntityType::Function(ARK_HOST_UNARY_I64_TYPE_IDX),
-            );
+        for (i, import) in self.imports.iter().enumerate() {
+            if layout.funcs[i].is_some() {
+                imports.import(
+                    &import.module,
+                    &import.name,
+                    wasm_encoder::EntityType::Function(layout.ty(import.type_idx)),
+                );
+            }
         }
-        // Index 17: math_atan2  (type 7: i64,i64 -> i64)
-        imports.import(
-            "ark_host",
-            "math_atan2",
-            wasm_encoder::EntityType::Function(ARK_HOST_BINARY_I64_TYPE_IDX),
-        );
-        // Index 18: math_sqrt  (type 6: i64 -> i64)
-        imports.import(
-            "ark_host",
-            "math_sqrt",
-            wasm_encoder::EntityType::Function(ARK_HOST_UNARY_I64_TYPE_IDX),
-        );
-        // Index 19: math_pow  (type 7: i64,i64 -> i64)
-        imports.import(
-            "ark_host",
-            "math_pow",
-            wasm_encoder::EntityType::Function(ARK_HOST_BINARY_I64_TYPE_IDX),
-        );
-        // Index 20: math_pow_mod  (type 8: i64,i64,i64 -> i64)
-        imports.import(
-            "ark_host",
-            "math_pow_mod",
-            wasm_encoder::EntityType::Function(ARK_HOST_TERNARY_I64_TYPE_IDX),
-        );
-        // Index 21: crypto_sha512  (type 9: i32,i32,i32 -> i32)
-        imports.import(
-            "ark_host",
-            "crypto_sha512",
-            wasm_encoder::EntityType::Function(ARK_HOST_MEM_3I32_TYPE_IDX),
-        );
-        // Index 22: json_parse  (type 9: i32,i32,i32 -> i32)
-        imports.import(
-            "ark_host",
-            "json_parse",
-            wasm_encoder::EntityType::Function(ARK_HOST_MEM_3I32_TYPE_IDX),
-        );
-        // Index 23: json_stringify  (type 9: i32,i32,i32 -> i32)
-        imports.import(
-            "ark_host",
-            "json_stringify",
-            wasm_encoder::EntityType::Function(ARK_HOST_MEM_3I32_TYPE_IDX),
-        );
-        // Index 24: ask_ai  (type 10: i32,i32,i32,i32 -> i32)
-        imports.import(
-            "ark_host",
-            "ask_ai",
-            wasm_encoder::EntityType::Function(ARK_HOST_MEM_4I32_TYPE_IDX),
-        );
 
         module.section(&imports);
 
         // --- Function Section ---
+        let live_functions: Vec<&(u32, String, FuncContext)> = self
+            .functions
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| layout.funcs[self.import_count as usize + i].is_some())
+            .map(|(_, function)| function)
+            .collect();
         let mut functions = FunctionSection::new();
-        for (type_idx, _, _) in &self.functions {
-            functions.function(*type_idx);
+        for (type_idx, _, _) in &live_functions {
+            functions.function(layout.ty(*type_idx));
         }
         module.section(&functions);
 
         // --- Table Section (for call_indirect / lambda dispatch) ---
-        let total_funcs = self.import_count + self.functions.len() as u32;
+        let total_funcs = layout.live_func_count();
         if total_funcs > 0 {
             let mut tables = TableSection::new();
             tables.table(TableType {
@@ -3271,26 +13016,18 @@ impl WasmCodegen {
         // --- Memory Section ---
         let mut memories = MemorySection::new();
         memories.memory(MemoryType {
-            minimum: 1, // 1 page = 64KB
-            maximum: Some(16),
+            minimum: self
+                .options
+                .initial_memory_pages
+                .max(self.static_memory_pages()) as u64, // 1 page = 64KB
+            maximum: self.options.max_memory_pages.map(u64::from),
             memory64: false,
             shared: false,
             page_size_log2: None,
         });
         module.section(&memories);
 
-        // --- Compute heap start (after all string data) ---
-        let mut max_string_end = STRING_MEMORY_START;
-        for (_, _, ctx) in &self.functions {
-            for (offset, bytes) in &ctx.string_data {
-                let end = *offset + bytes.len() as i32;
-                if end > max_string_end {
-                    max_string_end = end;
-                }
-            }
-        }
-        // Align to 8 bytes and add safety padding
-        let heap_start = ((max_string_end + 7) & !7).max(2048);
+        let heap_start = self.static_heap_start();
 
         // --- Global Section (__heap_ptr) ---
         let mut globals = GlobalSection::new();
@@ -3302,35 +13039,49 @@ impl WasmCodegen {
             },
             &wasm_encoder::ConstExpr::i32_const(heap_start),
         );
+        // #[export] let bindings hold the raw Ark i64: an integer/boolean
+        // as-is, a string packed as ptr << 32 | len, and a list or struct as
+        // a pointer to its heap object (length at ptr, elements from ptr + 8)
+        for (_, constant) in &self.exported_globals {
+            globals.global(
+                GlobalType {
+                    val_type: ValType::I64,
+                    mutable: constant.is_none(),
+                    shared: false,
+                },
+                &wasm_encoder::ConstExpr::i64_const(constant.unwrap_or(0)),
+            );
+        }
+        if let Some(fuel) = self.options.fuel {
+            globals.global(
+                GlobalType {
+                    val_type: ValType::I64,
+                    mutable: true,
+                    shared: false,
+                },
+                &wasm_encoder::ConstExpr::i64_const(fuel as i64),
+            );
+        }
         module.section(&globals);
 
         // --- Export Section ---
         let mut exports = ExportSection::new();
         exports.export("memory", ExportKind::Memory, 0);
-        exports.export("__heap_ptr", ExportKind::Global, 0);
-
-        // Backward compatibility: if NO function has #[export], export everything
-        let has_any_export_attrs = self
-            .func_attributes
-            .values()
-            .any(|attrs| attrs.iter().any(|a| a == "export"));
-
-        for (i, (_, name, _)) in self.functions.iter().enumerate() {
-            let func_idx = self.import_count + i as u32;
-            if has_any_export_attrs {
-                // Selective mode: only export #[export]-marked or system functions
-                if self.should_export(name) {
-                    exports.export(name, ExportKind::Func, func_idx);
-                }
-            } else {
-                // Legacy mode: export everything (backward compat)
-                exports.export(name, ExportKind::Func, func_idx);
+        if self.component_exports.is_none() {
+            exports.export("__heap_ptr", ExportKind::Global, 0);
+            for (i, (name, _)) in self.exported_globals.iter().enumerate() {
+                exports.export(name, ExportKind::Global, 1 + i as u32);
+            }
+            if let Some(fuel) = self.fuel_global() {
+                exports.export(FUEL_GLOBAL, ExportKind::Global, fuel);
             }
         }
+        for (name, func_idx) in &layout.exports {
+            exports.export(name, ExportKind::Func, layout.func(*func_idx));
+        }
         module.section(&exports);
 
         // --- Element Section (populate table with function references) ---
-        let total_funcs = self.import_count + self.functions.len() as u32;
         if total_funcs > 0 {
             let mut elements = ElementSection::new();
             // Active element segment: fill table 0 starting at offset 0
@@ -3345,17 +13096,41 @@ impl WasmCodegen {
         }
 
         // --- Code Section ---
+        // Function and type indices are renumbered to the layout's
         let mut codes = CodeSection::new();
-        for (_, _, ctx) in &self.functions {
-            let extra_locals = ctx.scope.extra_local_count(ctx.param_count);
-            let locals: Vec<(u32, ValType)> = if extra_locals > 0 {
-                vec![(extra_locals, ValType::I64)]
-            } else {
-                vec![]
-            };
+        for (_, _, ctx) in &live_functions {
+            let extra_locals = ctx.scope.extra_local_count(ctx.param_count) - ctx.v128_locals;
+            let locals: Vec<(u32, ValType)> = [
+                (ctx.v128_locals, ValType::V128),
+                (extra_locals, ValType::I64),
+            ]
+            .into_iter()
+            .filter(|(count, _)| *count > 0)
+            .collect();
             let mut func = Function::new(locals);
-            for instr in &ctx.instructions {
-                func.instruction(instr);
+            let mut func_refs = ctx.func_refs.iter().peekable();
+            for (pos, instr) in ctx.instructions.iter().enumerate() {
+                match instr {
+                    Instruction::Call(callee) => {
+                        func.instruction(&Instruction::Call(layout.func(*callee)));
+                    }
+                    Instruction::CallIndirect {
+                        type_index,
+                        table_index,
+                    } => {
+                        func.instruction(&Instruction::CallIndirect {
+                            type_index: layout.ty(*type_index),
+                            table_index: *table_index,
+                        });
+                    }
+                    Instruction::I64Const(func_idx) if func_refs.next_if_eq(&&pos).is_some() => {
+                        let func_idx = layout.func(*func_idx as u32);
+                        func.instruction(&Instruction::I64Const(func_idx as i64));
+                    }
+                    _ => {
+                        func.instruction(instr);
+                    }
+                }
             }
             codes.function(&func);
         }
@@ -3390,8 +13165,59 @@ impl WasmCodegen {
             module.section(&data);
         }
 
+        // --- Custom Section: debug names ---
+        if let Some(module_name) = &self.debug_names {
+            module.section(&self.name_section(module_name, layout));
+        }
+
+        // --- Custom Section: source map URL (a length-prefixed string) ---
+        if let Some(url) = &self.source_map_url {
+            let mut data = Vec::new();
+            url.as_str().encode(&mut data);
+            module.section(&wasm_encoder::CustomSection {
+                name: "sourceMappingURL".into(),
+                data: data.into(),
+            });
+        }
+
+        // --- Custom Section: build provenance ---
+        let build_info = self.build_info().to_string();
+        module.section(&wasm_encoder::CustomSection {
+            name: BUILD_INFO_SECTION.into(),
+            data: build_info.as_bytes().into(),
+        });
+
         module.finish()
     }
+
+    /// Contents of the `ark.build` custom section.
+    fn build_info(&self) -> serde_json::Value {
+        let mut info = serde_json::json!({
+            "compiler": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+            "source_sha256": self.source_hash,
+            "opt_level": OPT_LEVEL,
+        });
+        if !self.deterministic {
+            info["timestamp"] = chrono::Utc::now().timestamp().into();
+        }
+        info
+    }
+}
+
+/// Read the `ark.build` provenance record (compiler, version, source_sha256,
+/// opt_level and, for non-deterministic builds, timestamp) from a module
+/// produced by [`WasmCodegen`]. Returns `None` if the section is absent or
+/// malformed.
+pub fn read_build_info(wasm: &[u8]) -> Option<serde_json::Value> {
+    for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+        if let wasmparser::Payload::CustomSection(reader) = payload.ok()? {
+            if reader.name() == BUILD_INFO_SECTION {
+                return serde_json::from_slice(reader.data()).ok();
+            }
+        }
+    }
+    None
 }
 
 // =============================================================================
@@ -3549,6 +13375,7 @@ mod tests {
                         ],
                     },
                 }],
+                label: None,
             },
             Statement::Return(Expression::Variable("x".to_string())),
         ]));
@@ -3786,6 +13613,112 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_enum_match_must_cover_every_variant() {
+        use crate::ast::{EnumDecl, EnumVariantDef};
+
+        let variant = |name: &str, arity: usize| EnumVariantDef {
+            name: name.to_string(),
+            fields: vec![ArkType::Integer; arity],
+        };
+        let arm = |variant: &str, bindings: &[&str]| {
+            (
+                Pattern::EnumVariant {
+                    enum_name: "Shape".to_string(),
+                    variant: variant.to_string(),
+                    bindings: bindings.iter().map(|b| b.to_string()).collect(),
+                },
+                Expression::Integer(1),
+            )
+        };
+        let program = |arms: Vec<(Pattern, Expression)>| {
+            ArkNode::Statement(Statement::Block(vec![
+                Statement::EnumDecl(EnumDecl {
+                    name: "Shape".to_string(),
+                    variants: vec![
+                        variant("Circle", 1),
+                        variant("Rect", 2),
+                        variant("Empty", 0),
+                    ],
+                }),
+                Statement::Expression(Expression::Match {
+                    scrutinee: Box::new(Expression::EnumInit {
+                        enum_name: "Shape".to_string(),
+                        variant: "Rect".to_string(),
+                        args: vec![Expression::Integer(3), Expression::Integer(4)],
+                    }),
+                    arms,
+                }),
+            ]))
+        };
+
+        let exhaustive = program(vec![
+            arm("Circle", &["r"]),
+            arm("Rect", &["w", "h"]),
+            arm("Empty", &[]),
+        ]);
+        let bytes = WasmCodegen::compile_to_bytes(&exhaustive).expect("exhaustive match failed");
+        wasmparser::Validator::new()
+            .validate_all(&bytes)
+            .expect("invalid module");
+
+        // A catch-all arm covers the variants no arm names
+        let with_catch_all = program(vec![
+            arm("Rect", &["w"]),
+            (Pattern::Wildcard, Expression::Integer(0)),
+        ]);
+        assert!(WasmCodegen::compile_to_bytes(&with_catch_all).is_ok());
+
+        let err = WasmCodegen::compile_to_bytes(&program(vec![arm("Circle", &["r"])]))
+            .expect_err("non-exhaustive match compiled");
+        assert!(
+            err.message.contains("missing Rect, Empty"),
+            "{}",
+            err.message
+        );
+
+        let err = WasmCodegen::compile_to_bytes(&program(vec![
+            arm("Circle", &["r", "extra"]),
+            (Pattern::Wildcard, Expression::Integer(0)),
+        ]))
+        .expect_err("too many bindings compiled");
+        assert!(err.message.contains("binds 2"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_dense_literal_table() {
+        let arms = |values: &[i64], catch_all: bool| {
+            let mut arms: Vec<_> = values
+                .iter()
+                .map(|v| (Pattern::Literal(v.to_string()), Expression::Integer(*v)))
+                .collect();
+            if catch_all {
+                arms.push((Pattern::Wildcard, Expression::Integer(0)));
+            }
+            arms
+        };
+
+        // The first arm for a value wins; gaps go to index 4, the catch-all
+        // arm or else "no match"
+        assert_eq!(
+            WasmCodegen::dense_literal_table(&arms(&[-1, 0, 2, 1], true)),
+            Some((-1, vec![0, 1, 3, 2]))
+        );
+        assert_eq!(
+            WasmCodegen::dense_literal_table(&arms(&[10, 12, 14, 10], false)),
+            Some((10, vec![0, 4, 1, 4, 2]))
+        );
+        // Too few arms, too sparse, or not all integers: if/else chain
+        assert_eq!(WasmCodegen::dense_literal_table(&arms(&[1, 2], true)), None);
+        assert_eq!(
+            WasmCodegen::dense_literal_table(&arms(&[1, 100, 1000], true)),
+            None
+        );
+        let mut mixed = arms(&[1, 2, 3], false);
+        mixed.push((Pattern::Literal("four".to_string()), Expression::Integer(4)));
+        assert_eq!(WasmCodegen::dense_literal_table(&mixed), None);
+    }
+
     #[test]
     fn test_compile_let_destructure() {
         // let [a, b] = [10, 20]
@@ -3793,6 +13726,8 @@ mod tests {
             Statement::LetDestructure {
                 names: vec!["a".to_string(), "b".to_string()],
                 value: Expression::List(vec![Expression::Integer(10), Expression::Integer(20)]),
+                by_field: false,
+                rest: None,
             },
             Statement::Expression(Expression::Variable("a".to_string())),
         ]));
@@ -3894,6 +13829,55 @@ mod tests {
         assert!(result.is_ok(), "list.append failed: {:?}", result.err());
     }
 
+    #[test]
+    fn test_scratch_locals_are_pooled_across_intrinsics() {
+        let call = |name: &str, args: Vec<Expression>| Expression::Call {
+            function_hash: name.to_string(),
+            args,
+        };
+        let var = |name: &str| Expression::Variable(name.to_string());
+        let lit = |s: &str| Expression::Literal(s.to_string());
+        let program = ArkNode::Statement(Statement::Block(vec![
+            Statement::Let {
+                name: "s".to_string(),
+                ty: None,
+                value: call("string_concat", vec![lit("a"), lit("b")]),
+            },
+            Statement::Expression(call("string_eq", vec![var("s"), lit("ab")])),
+            Statement::Expression(call(
+                "string_slice",
+                vec![var("s"), Expression::Integer(0), Expression::Integer(1)],
+            )),
+            Statement::Let {
+                name: "xs".to_string(),
+                ty: None,
+                value: Expression::List(vec![Expression::Integer(1), Expression::Integer(2)]),
+            },
+            Statement::Expression(call("list.append", vec![var("xs"), Expression::Integer(3)])),
+            Statement::Expression(call("list.pop", vec![var("xs")])),
+            Statement::Expression(call("list.delete", vec![var("xs"), Expression::Integer(0)])),
+            Statement::Expression(call("print", vec![call("len", vec![var("xs")])])),
+        ]));
+        let wasm = WasmCodegen::compile_to_bytes(&program).expect("compile failed");
+        wasmparser::Validator::new()
+            .validate_all(&wasm)
+            .expect("module should validate");
+
+        let mut max_locals = 0;
+        for payload in wasmparser::Parser::new(0).parse_all(&wasm) {
+            if let wasmparser::Payload::CodeSectionEntry(body) = payload.expect("valid wasm") {
+                let mut declared = 0;
+                for group in body.get_locals_reader().expect("locals") {
+                    declared += group.expect("valid locals").0;
+                }
+                max_locals = max_locals.max(declared);
+            }
+        }
+        // Unpooled, the scratch names above alone need over 30 slots. With
+        // the pool only the largest single intrinsic plus `s` and `xs` remain.
+        assert!(max_locals <= 12, "declared {} locals", max_locals);
+    }
+
     #[test]
     fn test_intrinsic_list_pop() {
         // let xs = [10, 20, 30]; list.pop(xs)
@@ -4151,6 +14135,80 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_const_fn_call_is_folded_before_codegen() {
+        let source = "#[const]\nfunc square(x) { return x * x }\nprint(square(5))";
+        let ast = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+        let bytes = WasmCodegen::compile_to_bytes(&ast).expect("compile failed");
+
+        let mut square_idx = None;
+        let mut calls = Vec::new();
+        for payload in wasmparser::Parser::new(0).parse_all(&bytes) {
+            match payload.expect("valid wasm") {
+                wasmparser::Payload::ExportSection(reader) => {
+                    for export in reader {
+                        let export = export.expect("valid export");
+                        if export.name == "square" {
+                            square_idx = Some(export.index);
+                        }
+                    }
+                }
+                wasmparser::Payload::CodeSectionEntry(body) => {
+                    for op in body.get_operators_reader().expect("operators") {
+                        if let wasmparser::Operator::Call { function_index } = op.expect("op") {
+                            calls.push(function_index);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        let square_idx = square_idx.expect("square is still exported");
+        assert!(!calls.contains(&square_idx), "square is still called");
+    }
+
+    #[test]
+    fn test_compile_cache_reuses_unchanged_functions() {
+        let program = |greeting: &str, factor: i64| {
+            format!(
+                "func greet() {{\n  print(\"{}\")\n  return 0\n}}\n\
+                 func double(x) {{\n  return x * 2\n}}\n\
+                 func scale(x) {{\n  return x * {}\n}}\n\
+                 print(scale(double(greet())))",
+                greeting, factor
+            )
+        };
+        let build = |source: &str, cache: Option<&mut CompileCache>| {
+            let ast = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+            let mut codegen = WasmCodegen::new();
+            codegen.set_deterministic(true);
+            match cache {
+                Some(cache) => codegen.compile_with_cache(&ast, cache),
+                None => codegen.compile(&ast),
+            }
+            .expect("compile failed")
+        };
+
+        let mut cache = CompileCache::new();
+        let v1 = program("hi", 3);
+        assert_eq!(build(&v1, Some(&mut cache)), build(&v1, None));
+        assert_eq!((cache.hits(), cache.len()), (0, 3));
+
+        // Unchanged rebuild reuses every body
+        assert_eq!(build(&v1, Some(&mut cache)), build(&v1, None));
+        assert_eq!(cache.hits(), 3);
+
+        // Editing one function recompiles only that one, even though its
+        // longer string shifts the data offsets of the functions after it
+        let v2 = program("hello there", 3);
+        assert_eq!(build(&v2, Some(&mut cache)), build(&v2, None));
+        assert_eq!((cache.hits(), cache.len()), (2, 3));
+
+        let v3 = program("hello there", 4);
+        assert_eq!(build(&v3, Some(&mut cache)), build(&v3, None));
+        assert_eq!((cache.hits(), cache.len()), (2, 3));
+    }
+
     // =========================================================================
     // Phase 14: String Operations Tests
     // =========================================================================
@@ -4297,4 +14355,763 @@ mod tests {
             valid.err()
         );
     }
+
+    #[test]
+    fn test_lambda_bodies_match_registration_order() {
+        fn lambda(op: &str, k: i64) -> Expression {
+            Expression::Lambda {
+                params: vec!["x".to_string()],
+                body: vec![Statement::Expression(Expression::Call {
+                    function_hash: op.to_string(),
+                    args: vec![
+                        Expression::Variable("x".to_string()),
+                        Expression::Integer(k),
+                    ],
+                })],
+            }
+        }
+
+        // A function returning a lambda that also appears in a call argument:
+        // it must resolve to that lambda's slot, not to __lambda_0.
+        let pick = FunctionDef {
+            name: "pick".to_string(),
+            inputs: vec![],
+            output: ArkType::Integer,
+            body: Box::new(mast(ArkNode::Statement(Statement::Return(lambda(
+                "sub", 3,
+            ))))),
+            attributes: vec![],
+        };
+        let apply_pick = FunctionDef {
+            name: "apply_pick".to_string(),
+            inputs: vec![],
+            output: ArkType::Integer,
+            body: Box::new(mast(ArkNode::Statement(Statement::Block(vec![
+                Statement::Let {
+                    name: "f".to_string(),
+                    ty: None,
+                    value: Expression::Call {
+                        function_hash: "pick".to_string(),
+                        args: vec![],
+                    },
+                },
+                Statement::Return(Expression::Call {
+                    function_hash: "f".to_string(),
+                    args: vec![Expression::Integer(5)],
+                }),
+            ])))),
+            attributes: vec![],
+        };
+
+        let program = ArkNode::Statement(Statement::Block(vec![
+            Statement::Let {
+                name: "c".to_string(),
+                ty: None,
+                value: Expression::Integer(1),
+            },
+            Statement::If {
+                condition: Expression::Variable("c".to_string()),
+                then_block: vec![Statement::Expression(lambda("add", 10))],
+                else_block: None,
+            },
+            Statement::While {
+                condition: Expression::Call {
+                    function_hash: "lt".to_string(),
+                    args: vec![
+                        Expression::Variable("c".to_string()),
+                        Expression::Integer(0),
+                    ],
+                },
+                body: vec![Statement::Expression(lambda("mul", 2))],
+                label: None,
+            },
+            Statement::Expression(Expression::Call {
+                function_hash: "intrinsic_add".to_string(),
+                args: vec![lambda("sub", 3), Expression::Integer(0)],
+            }),
+            Statement::Function(pick),
+            Statement::Function(apply_pick),
+        ]));
+
+        let bytes = WasmCodegen::compile_to_bytes(&program).expect("compile failed");
+        wasmparser::Validator::new()
+            .validate_all(&bytes)
+            .expect("invalid wasm");
+
+        let call = |name: &str, args: &[i64]| {
+            crate::wasm_runner::call_exported(&bytes, name, args)
+                .expect("call failed")
+                .expect("no result")
+        };
+        // Lifted lambdas take their (here unused) closure record first
+        assert_eq!(call("__lambda_0", &[0, 5]), 15, "lambda in if branch");
+        assert_eq!(call("__lambda_1", &[0, 5]), 10, "lambda in while body");
+        assert_eq!(call("__lambda_2", &[0, 5]), 2, "lambda in call argument");
+        assert_eq!(call("apply_pick", &[]), 2, "closure returned by pick");
+    }
+
+    #[test]
+    fn test_closures_capture_enclosing_locals() {
+        fn var(name: &str) -> Expression {
+            Expression::Variable(name.to_string())
+        }
+        fn call(name: &str, args: Vec<Expression>) -> Expression {
+            Expression::Call {
+                function_hash: name.to_string(),
+                args,
+            }
+        }
+        fn lambda(param: &str, body: Expression) -> Expression {
+            Expression::Lambda {
+                params: vec![param.to_string()],
+                body: vec![Statement::Expression(body)],
+            }
+        }
+        fn let_(name: &str, value: Expression) -> Statement {
+            Statement::Let {
+                name: name.to_string(),
+                ty: None,
+                value,
+            }
+        }
+        fn func(name: &str, inputs: &[&str], body: Vec<Statement>) -> Statement {
+            Statement::Function(FunctionDef {
+                name: name.to_string(),
+                inputs: inputs
+                    .iter()
+                    .map(|i| (i.to_string(), ArkType::Integer))
+                    .collect(),
+                output: ArkType::Integer,
+                body: Box::new(mast(ArkNode::Statement(Statement::Block(body)))),
+                attributes: vec![],
+            })
+        }
+
+        let program = ArkNode::Statement(Statement::Block(vec![
+            // func make_adder(n) { return func(x) { x + n } }
+            func(
+                "make_adder",
+                &["n"],
+                vec![Statement::Return(lambda(
+                    "x",
+                    call("add", vec![var("x"), var("n")]),
+                ))],
+            ),
+            // Captures are snapshots: rebinding `base` afterwards has no effect
+            func(
+                "scaled",
+                &[],
+                vec![
+                    let_("base", Expression::Integer(100)),
+                    let_("inc", call("make_adder", vec![Expression::Integer(5)])),
+                    let_(
+                        "scale",
+                        lambda(
+                            "x",
+                            call(
+                                "add",
+                                vec![
+                                    call(
+                                        "mul",
+                                        vec![call("inc", vec![var("x")]), Expression::Integer(2)],
+                                    ),
+                                    var("base"),
+                                ],
+                            ),
+                        ),
+                    ),
+                    let_("base", Expression::Integer(0)),
+                    Statement::Return(call("scale", vec![Expression::Integer(1)])),
+                ],
+            ),
+            // The middle lambda captures `a` on behalf of the innermost one
+            func(
+                "curried",
+                &[],
+                vec![
+                    let_("a", Expression::Integer(1)),
+                    let_(
+                        "g",
+                        lambda(
+                            "b",
+                            lambda(
+                                "c",
+                                call("add", vec![var("a"), call("add", vec![var("b"), var("c")])]),
+                            ),
+                        ),
+                    ),
+                    let_("h", call("g", vec![Expression::Integer(10)])),
+                    Statement::Return(call("h", vec![Expression::Integer(100)])),
+                ],
+            ),
+        ]));
+
+        let bytes = WasmCodegen::compile_to_bytes(&program).expect("compile failed");
+        wasmparser::Validator::new()
+            .validate_all(&bytes)
+            .expect("invalid wasm");
+        let run = |name: &str| {
+            crate::wasm_runner::call_exported(&bytes, name, &[])
+                .expect("call failed")
+                .expect("no result")
+        };
+        assert_eq!(run("scaled"), (1 + 5) * 2 + 100);
+        assert_eq!(run("curried"), 111);
+    }
+
+    #[test]
+    fn test_import_map_matches_import_section() {
+        let mut codegen = WasmCodegen::new();
+        codegen
+            .register_host_import(
+                "my_host",
+                "triple",
+                vec![ValType::I64],
+                vec![ValType::I64],
+                "host_triple",
+            )
+            .expect("register failed");
+        codegen.register_wasi_imports();
+        let import_map = &codegen.import_map;
+        // Keep every import, used or not
+        let keep_all = ModuleLayout {
+            funcs: (0..codegen.import_count + codegen.functions.len() as u32)
+                .map(Some)
+                .collect(),
+            types: (0..codegen.types.len() as u32).map(Some).collect(),
+            exports: codegen.exported_functions(),
+        };
+        let wasm = codegen.emit_module_with(&keep_all);
+
+        let mut section_names = Vec::new();
+        for payload in wasmparser::Parser::new(0).parse_all(&wasm) {
+            if let wasmparser::Payload::ImportSection(reader) = payload.expect("valid wasm") {
+                for import in reader {
+                    section_names.push(import.expect("valid import").name.to_string());
+                }
+            }
+        }
+
+        assert_eq!(import_map.len(), section_names.len());
+        for (position, name) in section_names.iter().enumerate() {
+            assert_eq!(import_map.get(name), Some(&(position as u32)), "{}", name);
+        }
+        assert_eq!(import_map["clock_time_get"], 2);
+        // No math intrinsics are called, so embedder imports follow the 11
+        // WASI imports and the 4 non-math `ark_host` imports directly.
+        assert_eq!(import_map["triple"], 15);
+    }
+
+    #[test]
+    fn test_only_called_math_imports_are_emitted() {
+        let program = ArkNode::Statement(Statement::Block(vec![Statement::Expression(
+            Expression::Call {
+                function_hash: "math.sin".to_string(),
+                args: vec![Expression::Integer(0)],
+            },
+        )]));
+        let wasm = WasmCodegen::compile_to_bytes(&program).expect("compile failed");
+
+        let mut math_imports = Vec::new();
+        let mut wasi_imports = 0;
+        for payload in wasmparser::Parser::new(0).parse_all(&wasm) {
+            if let wasmparser::Payload::ImportSection(reader) = payload.expect("valid wasm") {
+                for import in reader {
+                    let import = import.expect("valid import");
+                    if import.name.starts_with("math_") {
+                        math_imports.push(import.name.to_string());
+                    } else if import.module == "wasi_snapshot_preview1" {
+                        wasi_imports += 1;
+                    }
+                }
+            }
+        }
+        assert_eq!(math_imports, ["math_sin"]);
+        // Nothing prints or reads the clock, so no WASI import survives
+        assert_eq!(wasi_imports, 0);
+    }
+
+    #[test]
+    fn test_wasi_preview2_target_imports() {
+        let source = "print(\"hi\")\nprint(sys.time.now())\nprint(sys.random.int(1, 6))";
+        let ast = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+        let preview2 = || {
+            WasmCodegen::with_options(WasmCodegenOptions {
+                wasi_target: WasiTarget::Preview2,
+                ..WasmCodegenOptions::default()
+            })
+            .expect("valid options")
+        };
+        let wasm = preview2().compile(&ast).expect("compile failed");
+        wasmparser::Validator::new()
+            .validate_all(&wasm)
+            .expect("module should validate");
+
+        let mut modules = HashSet::new();
+        for payload in wasmparser::Parser::new(0).parse_all(&wasm) {
+            if let wasmparser::Payload::ImportSection(reader) = payload.expect("valid wasm") {
+                for import in reader {
+                    modules.insert(import.expect("valid import").module.to_string());
+                }
+            }
+        }
+        for module in [
+            "wasi:cli/stdout@0.2.0",
+            "wasi:io/streams@0.2.0",
+            "wasi:clocks/wall-clock@0.2.0",
+            "wasi:random/random@0.2.0",
+        ] {
+            assert!(
+                modules.contains(module),
+                "missing {}: {:?}",
+                module,
+                modules
+            );
+        }
+        assert!(
+            modules.iter().all(|module| module.starts_with("wasi:")),
+            "{:?}",
+            modules
+        );
+
+        // Stdin has no Preview2 shim
+        let read = crate::parser::parse_source("print(sys.io.read_line())", "test.ark")
+            .expect("parse failed");
+        let err = preview2()
+            .compile(&read)
+            .expect_err("stdin is Preview1-only");
+        assert!(err.message.contains("fd_read"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_fs_intrinsic_errors() {
+        let compile = |source: &str, options: WasmCodegenOptions| {
+            let ast = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+            WasmCodegen::with_options(options)
+                .expect("valid options")
+                .compile(&ast)
+        };
+
+        let err = compile("sys.fs.write(\"a.txt\")", WasmCodegenOptions::default())
+            .expect_err("write needs a path and text");
+        assert!(err.message.contains("requires 2"), "{}", err.message);
+
+        // Files go through Preview1's path_open, which Preview2 lacks
+        let preview2 = WasmCodegenOptions {
+            wasi_target: WasiTarget::Preview2,
+            ..WasmCodegenOptions::default()
+        };
+        let err = compile("print(sys.fs.read(\"a.txt\"))", preview2).expect_err("no path_open");
+        assert!(err.message.contains("path_open"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_math_import_table_covers_every_math_intrinsic() {
+        // A name missing from MATH_HOST_IMPORTS would leave its import
+        // undeclared and fail compilation here.
+        for (_, arity, intrinsics) in MATH_HOST_IMPORTS {
+            for intrinsic in *intrinsics {
+                let program = ArkNode::Expression(Expression::Call {
+                    function_hash: intrinsic.to_string(),
+                    args: vec![Expression::Integer(1); *arity],
+                });
+                let result = WasmCodegen::compile_to_bytes(&program);
+                assert!(result.is_ok(), "{}: {:?}", intrinsic, result.err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_recursive_struct_rejected_at_compile_time() {
+        use crate::ast::StructDecl;
+
+        let program = |next_ty: ArkType| {
+            ArkNode::Statement(Statement::Block(vec![
+                Statement::StructDecl(StructDecl {
+                    name: "Node".to_string(),
+                    fields: vec![
+                        ("value".to_string(), ArkType::Integer),
+                        ("next".to_string(), next_ty),
+                    ],
+                }),
+                Statement::Expression(Expression::Call {
+                    function_hash: "print".to_string(),
+                    args: vec![Expression::Integer(1)],
+                }),
+            ]))
+        };
+        let node_ty = ArkType::Struct("Node".to_string(), vec![]);
+
+        let err = WasmCodegen::compile_to_bytes(&program(node_ty.clone()))
+            .expect_err("infinite struct compiled");
+        assert!(err.message.contains("Node -> Node"), "{}", err.message);
+
+        let indirect = program(ArkType::List(Box::new(node_ty)));
+        assert!(WasmCodegen::compile_to_bytes(&indirect).is_ok());
+    }
+
+    #[test]
+    fn test_build_info_section() {
+        let program = ArkNode::Statement(Statement::Block(vec![Statement::Expression(
+            Expression::Call {
+                function_hash: "print".to_string(),
+                args: vec![Expression::Integer(1)],
+            },
+        )]));
+        let build = |deterministic: bool| {
+            let mut codegen = WasmCodegen::new();
+            codegen.set_deterministic(deterministic);
+            codegen.compile(&program).expect("compile failed")
+        };
+
+        let wasm = build(true);
+        let info = read_build_info(&wasm).expect("ark.build section missing");
+        assert_eq!(info["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(info["opt_level"], 2);
+        assert_eq!(info["source_sha256"].as_str().map(str::len), Some(64));
+        assert!(info.get("timestamp").is_none());
+        assert_eq!(
+            wasm,
+            build(true),
+            "deterministic builds must be byte-identical"
+        );
+
+        let info = read_build_info(&build(false)).expect("ark.build section missing");
+        assert!(info["timestamp"].is_i64());
+    }
+
+    #[test]
+    fn test_memory_limits_from_options() {
+        let program = ArkNode::Statement(Statement::Block(vec![Statement::Expression(
+            Expression::Call {
+                function_hash: "print".to_string(),
+                args: vec![Expression::Integer(1)],
+            },
+        )]));
+        let memory_limits = |options: WasmCodegenOptions| {
+            let wasm = WasmCodegen::with_options(options)
+                .expect("valid options")
+                .compile(&program)
+                .expect("compile failed");
+            for payload in wasmparser::Parser::new(0).parse_all(&wasm) {
+                if let wasmparser::Payload::MemorySection(reader) = payload.expect("valid wasm") {
+                    let memory = reader.into_iter().next().expect("one memory");
+                    let memory = memory.expect("valid memory");
+                    return (memory.initial, memory.maximum);
+                }
+            }
+            panic!("memory section missing");
+        };
+
+        assert_eq!(memory_limits(WasmCodegenOptions::default()), (1, Some(16)));
+        assert_eq!(
+            memory_limits(WasmCodegenOptions {
+                initial_memory_pages: 4,
+                max_memory_pages: None,
+                ..WasmCodegenOptions::default()
+            }),
+            (4, None)
+        );
+
+        for (initial, max) in [(0, Some(16)), (8, Some(4)), (1, Some(65537))] {
+            let err = WasmCodegen::with_options(WasmCodegenOptions {
+                initial_memory_pages: initial,
+                max_memory_pages: max,
+                ..WasmCodegenOptions::default()
+            })
+            .err()
+            .expect("invalid options must be rejected");
+            assert_eq!(err.context, "with_options");
+        }
+    }
+
+    #[test]
+    fn test_debug_name_section() {
+        let program = ArkNode::Statement(Statement::Block(vec![
+            Statement::Function(FunctionDef {
+                name: "double".to_string(),
+                inputs: vec![("n".to_string(), ArkType::Integer)],
+                output: ArkType::Integer,
+                body: Box::new(mast(ArkNode::Statement(Statement::Return(
+                    Expression::Call {
+                        function_hash: "intrinsic_mul".to_string(),
+                        args: vec![
+                            Expression::Variable("n".to_string()),
+                            Expression::Integer(2),
+                        ],
+                    },
+                )))),
+                attributes: vec![],
+            }),
+            Statement::Expression(Expression::Call {
+                function_hash: "print".to_string(),
+                args: vec![Expression::Integer(1)],
+            }),
+        ]));
+
+        let mut codegen = WasmCodegen::new();
+        codegen.set_debug_names(Some("demo"));
+        let wasm = codegen.compile(&program).expect("compile failed");
+        wasmparser::Validator::new()
+            .validate_all(&wasm)
+            .expect("module should validate");
+
+        let mut module_name = None;
+        let mut functions = HashMap::new();
+        let mut locals = HashMap::new();
+        for payload in wasmparser::Parser::new(0).parse_all(&wasm) {
+            let wasmparser::Payload::CustomSection(reader) = payload.expect("valid wasm") else {
+                continue;
+            };
+            let wasmparser::KnownCustom::Name(names) = reader.as_known() else {
+                continue;
+            };
+            for name in names {
+                match name.expect("valid name subsection") {
+                    wasmparser::Name::Module { name, .. } => module_name = Some(name.to_string()),
+                    wasmparser::Name::Function(map) => {
+                        for naming in map {
+                            let naming = naming.expect("valid naming");
+                            functions.insert(naming.name.to_string(), naming.index);
+                        }
+                    }
+                    wasmparser::Name::Local(map) => {
+                        for indirect in map {
+                            let indirect = indirect.expect("valid indirect naming");
+                            let names: Vec<String> = indirect
+                                .names
+                                .into_iter()
+                                .map(|n| n.expect("valid naming").name.to_string())
+                                .collect();
+                            locals.insert(indirect.index, names);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        assert_eq!(module_name.as_deref(), Some("demo"));
+        assert_eq!(functions["fd_write"], 0);
+        let double = functions["double"];
+        assert_eq!(locals[&double].first().map(String::as_str), Some("n"));
+        assert!(functions.contains_key("__alloc"));
+
+        let plain = WasmCodegen::compile_to_bytes(&program).expect("compile failed");
+        let custom_sections: Vec<String> = wasmparser::Parser::new(0)
+            .parse_all(&plain)
+            .filter_map(|payload| match payload.expect("valid wasm") {
+                wasmparser::Payload::CustomSection(reader) => Some(reader.name().to_string()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            custom_sections,
+            [BUILD_INFO_SECTION],
+            "name section is opt-in"
+        );
+    }
+
+    #[test]
+    fn test_vlq_encoding() {
+        let encode = |values: &[i64]| {
+            let mut out = String::new();
+            for &value in values {
+                vlq_push(&mut out, value);
+            }
+            out
+        };
+        assert_eq!(encode(&[0, 1, -1, 15, 16, -16]), "ACDegBhB");
+        assert_eq!(encode(&[1234]), "ktC");
+    }
+
+    #[test]
+    fn test_source_map_points_functions_at_their_definition() {
+        let source = "func double(n) {\n  return n * 2\n}\n\n\n  func triple(n) {\n  return n * 3\n}\nprint(triple(double(1)))";
+        let ast = crate::parser::parse_source_with_spans(source, "math.ark").expect("parse failed");
+        let mut codegen = WasmCodegen::new();
+        codegen.set_source_map_url(Some("math.wasm.map"));
+        let (wasm, map) = codegen
+            .compile_with_source_map(&ast)
+            .expect("compile failed");
+        wasmparser::Validator::new()
+            .validate_all(&wasm)
+            .expect("module should validate");
+
+        // Function name → body offset, via the export section
+        let mut exports = HashMap::new();
+        let mut bodies = Vec::new();
+        let mut import_count = 0;
+        let mut url = None;
+        for payload in wasmparser::Parser::new(0).parse_all(&wasm) {
+            match payload.expect("valid wasm") {
+                wasmparser::Payload::ImportSection(reader) => import_count = reader.count(),
+                wasmparser::Payload::ExportSection(reader) => {
+                    for export in reader {
+                        let export = export.expect("valid export");
+                        exports.insert(export.name.to_string(), export.index);
+                    }
+                }
+                wasmparser::Payload::CodeSectionEntry(body) => bodies.push(body.range().start),
+                wasmparser::Payload::CustomSection(reader)
+                    if reader.name() == "sourceMappingURL" =>
+                {
+                    let mut data = wasmparser::BinaryReader::new(reader.data(), 0);
+                    url = Some(data.read_string().expect("url").to_string());
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(url.as_deref(), Some("math.wasm.map"));
+
+        // Decode the single-line mappings into absolute segments
+        let map: serde_json::Value = serde_json::from_str(&map).expect("valid JSON");
+        assert_eq!(map["version"], 3);
+        assert_eq!(map["sources"], serde_json::json!(["math.ark"]));
+        let digits = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut totals = [0i64; 4];
+        let mut segments = HashMap::new();
+        for segment in map["mappings"].as_str().expect("mappings").split(',') {
+            let (mut fields, mut value, mut shift) = (Vec::new(), 0i64, 0);
+            for c in segment.chars() {
+                let digit = digits.find(c).expect("base64 digit") as i64;
+                value |= (digit & 31) << shift;
+                shift += 5;
+                if digit & 32 == 0 {
+                    let sign = if value & 1 == 1 { -1 } else { 1 };
+                    fields.push(sign * (value >> 1));
+                    (value, shift) = (0, 0);
+                }
+            }
+            for (total, delta) in totals.iter_mut().zip(&fields) {
+                *total += delta;
+            }
+            let position = (fields.len() == 4).then(|| (totals[2], totals[3]));
+            segments.insert(totals[0] as usize, position);
+        }
+
+        let position_of = |name: &str| {
+            let body = bodies[(exports[name] - import_count) as usize];
+            segments[&body]
+        };
+        // 0-based line and column of each `func` keyword
+        assert_eq!(position_of("double"), Some((0, 0)));
+        assert_eq!(position_of("triple"), Some((5, 2)));
+        assert_eq!(position_of("__alloc"), None);
+        assert_eq!(segments.len(), bodies.len());
+    }
+
+    #[test]
+    fn test_component_core_exports_wit_names() {
+        let source = "#[export]\nfunc add_one(n: Int) => Int {\n  return n + 1\n}\n\
+                      func helper(x) {\n  return x\n}\nprint(add_one(1))";
+        let ast = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+        let (core, wit) = WasmCodegen::new()
+            .compile_component_core(&ast, "ark:program")
+            .expect("compile failed");
+        wasmparser::Validator::new()
+            .validate_all(&core)
+            .expect("module should validate");
+
+        let mut exports = Vec::new();
+        for payload in wasmparser::Parser::new(0).parse_all(&core) {
+            if let wasmparser::Payload::ExportSection(reader) = payload.expect("valid wasm") {
+                for export in reader {
+                    exports.push(export.expect("valid export").name.to_string());
+                }
+            }
+        }
+        exports.sort();
+        assert_eq!(
+            exports,
+            ["_initialize", "ark:program/exports#add-one", "memory"]
+        );
+        assert!(wit.contains("add-one: func(n: s64) -> s64;"), "{}", wit);
+
+        let source = "#[export]\nfunc greet(name: String) {\n  return 0\n}";
+        let ast = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+        let err = WasmCodegen::new()
+            .compile_component_core(&ast, "ark:program")
+            .expect_err("string parameters are not supported yet");
+        assert_eq!(err.context, "component");
+        assert!(err.message.contains("'name'"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_unreachable_functions_and_imports_are_dropped() {
+        let source = "#[export]\nfunc live(x) {\n  return helper(x)\n}\n\
+                      func helper(x) {\n  return x + 1\n}\n\
+                      func dead(x) {\n  print(x)\n  return x\n}";
+        let ast = crate::parser::parse_source(source, "test.ark").expect("parse failed");
+        let mut codegen = WasmCodegen::new();
+        codegen.set_debug_names(Some("dce"));
+        let wasm = codegen.compile(&ast).expect("compile failed");
+        wasmparser::Validator::new()
+            .validate_all(&wasm)
+            .expect("module should validate");
+
+        let mut import_count = None;
+        let mut functions = Vec::new();
+        for payload in wasmparser::Parser::new(0).parse_all(&wasm) {
+            match payload.expect("valid wasm") {
+                wasmparser::Payload::ImportSection(reader) => import_count = Some(reader.count()),
+                wasmparser::Payload::CustomSection(reader) => {
+                    if let wasmparser::KnownCustom::Name(names) = reader.as_known() {
+                        for name in names {
+                            if let wasmparser::Name::Function(map) = name.expect("valid names") {
+                                for naming in map {
+                                    functions.push(naming.expect("valid naming").name.to_string());
+                                }
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        functions.sort();
+
+        // `dead` and the print helpers go, and with them every WASI import
+        assert_eq!(import_count.unwrap_or(0), 0);
+        assert_eq!(functions, ["__alloc", "__free", "_start", "helper", "live"]);
+        let result = crate::wasm_runner::call_exported(&wasm, "live", &[41]).expect("call failed");
+        assert_eq!(result, Some(42));
+    }
+
+    #[test]
+    fn test_calls_checked_against_declared_signatures() {
+        let compile = |calls: &str| {
+            let source = format!(
+                "func half(x: Float) => Float {{\n  return x / 2\n}}\n\
+                 func shout(s: String) {{\n  print(s)\n}}\n\
+                 func twice(n: Int) {{\n  return n * 2\n}}\n{}",
+                calls
+            );
+            let ast = crate::parser::parse_source(&source, "test.ark").expect("parse failed");
+            WasmCodegen::compile_to_bytes(&ast)
+        };
+
+        // An integer converts to a Float parameter; a typed function used as
+        // a value goes through its i64 adapter
+        let calls = "print(half(3))\nshout(\"hi\")\nprint(twice(4))\nf := half\nprint(f(1))";
+        let wasm = compile(calls).expect("compile failed");
+        wasmparser::Validator::new()
+            .validate_all(&wasm)
+            .expect("module should validate");
+
+        let err = compile("print(half(1, 2))").expect_err("extra argument compiled");
+        assert!(
+            err.message.contains("takes 1 argument(s), got 2"),
+            "{}",
+            err.message
+        );
+        let err = compile("print(twice(2.5))").expect_err("float truncated to Integer");
+        assert!(err.message.contains("'n' of 'twice'"), "{}", err.message);
+        let err = compile("shout(7)").expect_err("integer passed as String");
+        assert!(
+            err.message.contains("got a value of type integer"),
+            "{}",
+            err.message
+        );
+    }
 }
@@ -9,9 +9,8 @@
  * mechanism.
  *
  * Architecture:
- *   - Math functions use f64 reinterpret_cast (i64 ↔ f64 bit patterns)
+ *   - Math functions take and return native f64 (pow_mod is i64)
  *   - Crypto/JSON functions use linear memory for byte array I/O
- *   - All functions return i64 to maintain Ark's uniform value model
  */
 
 use wasmtime::{Caller, Extern, Linker};
@@ -28,22 +27,22 @@ use crate::wasm_runner::HostState;
 pub const ARK_HOST_MODULE: &str = "ark_host";
 
 /// Number of host imports provided by this module.
-pub const ARK_HOST_IMPORT_COUNT: u32 = 14;
+pub const ARK_HOST_IMPORT_COUNT: u32 = 17;
 
 // Host function indices (offset from WASI imports in the codegen function table)
 // These are the order in which functions appear in the import section AFTER the
 // 11 WASI imports.  So host function 0 is at global import index 11, etc.
 //
-// Math (i64 → reinterpreted f64 → i64):
-//   0: math_sin(x:i64) -> i64
-//   1: math_cos(x:i64) -> i64
-//   2: math_tan(x:i64) -> i64
-//   3: math_asin(x:i64) -> i64
-//   4: math_acos(x:i64) -> i64
-//   5: math_atan(x:i64) -> i64
-//   6: math_atan2(y:i64, x:i64) -> i64
-//   7: math_sqrt(x:i64) -> i64
-//   8: math_pow(base:i64, exp:i64) -> i64
+// Math (f64, except the integer pow_mod):
+//   0: math_sin(x:f64) -> f64
+//   1: math_cos(x:f64) -> f64
+//   2: math_tan(x:f64) -> f64
+//   3: math_asin(x:f64) -> f64
+//   4: math_acos(x:f64) -> f64
+//   5: math_atan(x:f64) -> f64
+//   6: math_atan2(y:f64, x:f64) -> f64
+//   7: math_sqrt(x:f64) -> f64
+//   8: math_pow(base:f64, exp:f64) -> f64
 //   9: math_pow_mod(base:i64, exp:i64, modulus:i64) -> i64
 //
 // Crypto (linear memory):
@@ -55,6 +54,11 @@ pub const ARK_HOST_IMPORT_COUNT: u32 = 14;
 //
 // AI:
 //  13: ask_ai(prompt_ptr:i32, prompt_len:i32, out_ptr:i32, out_cap:i32) -> i32 (bytes written)
+//
+// 32-byte digests (linear memory):
+//  14: crypto_sha256(data_ptr:i32, data_len:i32, out_ptr:i32) -> i32 (errno)
+//  15: crypto_keccak256(data_ptr:i32, data_len:i32, out_ptr:i32) -> i32 (errno)
+//  16: crypto_blake3(data_ptr:i32, data_len:i32, out_ptr:i32) -> i32 (errno)
 
 /// Error type alias
 type LinkResult = Result<(), crate::wasm_runner::WasmRunError>;
@@ -83,12 +87,12 @@ pub fn link_ark_host_imports(linker: &mut Linker<HostState>) -> LinkResult {
 }
 
 // =============================================================================
-// Math Imports — f64 reinterpret via i64 bit patterns
+// Math Imports — native f64
 // =============================================================================
 //
-// Ark integers are i64.  For trig/sqrt/pow we transmute the i64 to f64,
-// apply the operation, then transmute back.  This preserves full IEEE 754
-// precision without needing f64 WASM types in the Ark value model.
+// Trig/sqrt/pow take and return real f64. The codegen converts Ark floats
+// (f64 bits in an i64 slot) and integers to f64 at the call site and stores
+// the result back as float bits.
 
 fn link_math_imports(linker: &mut Linker<HostState>) -> LinkResult {
     // Helper macro to avoid boilerplate for unary f64 ops
@@ -98,11 +102,7 @@ fn link_math_imports(linker: &mut Linker<HostState>) -> LinkResult {
                 .func_wrap(
                     ARK_HOST_MODULE,
                     $name,
-                    |_caller: Caller<'_, HostState>, x: i64| -> i64 {
-                        let xf = f64::from_bits(x as u64);
-                        let result: f64 = $op(xf);
-                        result.to_bits() as i64
-                    },
+                    |_caller: Caller<'_, HostState>, x: f64| -> f64 { $op(x) },
                 )
                 .map_err(|e| link_err(format!("Failed to link {}: {}", $name, e)))?;
         };
@@ -121,12 +121,7 @@ fn link_math_imports(linker: &mut Linker<HostState>) -> LinkResult {
         .func_wrap(
             ARK_HOST_MODULE,
             "math_atan2",
-            |_caller: Caller<'_, HostState>, y: i64, x: i64| -> i64 {
-                let yf = f64::from_bits(y as u64);
-                let xf = f64::from_bits(x as u64);
-                let result = yf.atan2(xf);
-                result.to_bits() as i64
-            },
+            |_caller: Caller<'_, HostState>, y: f64, x: f64| -> f64 { y.atan2(x) },
         )
         .map_err(|e| link_err(format!("Failed to link math_atan2: {}", e)))?;
 
@@ -135,17 +130,11 @@ fn link_math_imports(linker: &mut Linker<HostState>) -> LinkResult {
         .func_wrap(
             ARK_HOST_MODULE,
             "math_pow",
-            |_caller: Caller<'_, HostState>, base: i64, exp: i64| -> i64 {
-                let bf = f64::from_bits(base as u64);
-                let ef = f64::from_bits(exp as u64);
-                let result = bf.powf(ef);
-                result.to_bits() as i64
-            },
+            |_caller: Caller<'_, HostState>, base: f64, exp: f64| -> f64 { base.powf(exp) },
         )
         .map_err(|e| link_err(format!("Failed to link math_pow: {}", e)))?;
 
-    // pow_mod(base, exp, modulus) → integer modular exponentiation
-    // This works on raw i64 values (NOT f64 reinterpret)
+    // pow_mod(base, exp, modulus) → integer modular exponentiation on i64
     linker
         .func_wrap(
             ARK_HOST_MODULE,
@@ -174,7 +163,7 @@ fn link_math_imports(linker: &mut Linker<HostState>) -> LinkResult {
 }
 
 // =============================================================================
-// Crypto Imports — SHA-512 via linear memory
+// Crypto Imports — SHA-512 and 32-byte digests via linear memory
 // =============================================================================
 
 fn link_crypto_imports(linker: &mut Linker<HostState>) -> LinkResult {
@@ -225,6 +214,50 @@ fn link_crypto_imports(linker: &mut Linker<HostState>) -> LinkResult {
         )
         .map_err(|e| link_err(format!("Failed to link crypto_sha512: {}", e)))?;
 
+    link_digest_import(linker, "crypto_sha256", crate::crypto::hash_sha256)?;
+    link_digest_import(linker, "crypto_keccak256", crate::crypto::hash_keccak256)?;
+    link_digest_import(linker, "crypto_blake3", crate::crypto::hash_blake3)?;
+
+    Ok(())
+}
+
+/// `name(data_ptr, data_len, out_ptr) -> errno`: writes the 32-byte
+/// `digest` of `data_len` bytes at `data_ptr` to `out_ptr`, with the
+/// errnos of `crypto_sha512`.
+fn link_digest_import(
+    linker: &mut Linker<HostState>,
+    name: &str,
+    digest: fn(&[u8]) -> [u8; 32],
+) -> LinkResult {
+    linker
+        .func_wrap(
+            ARK_HOST_MODULE,
+            name,
+            move |mut caller: Caller<'_, HostState>,
+                  data_ptr: i32,
+                  data_len: i32,
+                  out_ptr: i32|
+                  -> i32 {
+                let memory = match caller.get_export("memory") {
+                    Some(Extern::Memory(mem)) => mem,
+                    _ => return 1, // no memory export
+                };
+
+                let dp = data_ptr as u32 as usize;
+                let dl = data_len as u32 as usize;
+                let op = out_ptr as u32 as usize;
+
+                let mem_data = memory.data_mut(&mut caller);
+                if dp + dl > mem_data.len() || op + 32 > mem_data.len() {
+                    return 2; // EFAULT
+                }
+                let hash = digest(&mem_data[dp..dp + dl]);
+                mem_data[op..op + 32].copy_from_slice(&hash);
+
+                0 // success
+            },
+        )
+        .map_err(|e| link_err(format!("Failed to link {}: {}", name, e)))?;
     Ok(())
 }
 
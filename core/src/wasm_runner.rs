@@ -8,6 +8,7 @@
  *
  * The compiled Ark WASM imports a single WASI function:
  *   wasi_snapshot_preview1::fd_write(fd, iovs, iovs_len, nwritten) -> errno
+ * Writes to fd 1 (print) and fd 2 (sys.log) are captured separately.
  *
  * Print currently emits raw i64 bytes (8 bytes per value). This runner
  * interprets those bytes as little-endian i64 and formats them as decimal
@@ -16,8 +17,12 @@
  * LICENSE: DUAL-LICENSED (AGPLv3 or COMMERCIAL).
  */
 
+use crate::artifact::TrustPolicy;
+use crate::runtime::RuntimeError;
+use crate::wasm_codegen::FUEL_GLOBAL;
+use std::collections::HashMap;
 use std::fmt;
-use wasmtime::{Caller, Engine, Extern, Linker, Module, Store};
+use wasmtime::{Caller, Engine, Extern, Instance, Linker, Module, Store, Trap};
 
 // =============================================================================
 // Error Types
@@ -37,15 +42,143 @@ impl fmt::Display for WasmRunError {
 
 impl std::error::Error for WasmRunError {}
 
+/// The `RuntimeError` the VM raises for the failure a trap stands for, so
+/// a run reports the same error codes whichever backend executed it.
+pub fn trap_error(err: &wasmtime::Error) -> RuntimeError {
+    match err.downcast_ref::<Trap>() {
+        Some(Trap::IntegerDivisionByZero) => RuntimeError::DivisionByZero,
+        Some(Trap::StackOverflow) => RuntimeError::RecursionLimitExceeded,
+        Some(Trap::MemoryOutOfBounds | Trap::TableOutOfBounds | Trap::HeapMisaligned) => {
+            RuntimeError::AllocationError(err.to_string())
+        }
+        _ => RuntimeError::InvalidOperation(err.to_string()),
+    }
+}
+
+/// Message for a trap, tagged with its error code: `OutOfGas` when a
+/// metered module ran out of fuel, which leaves [`FUEL_GLOBAL`] at -1.
+fn trap_message(
+    what: &str,
+    err: &wasmtime::Error,
+    instance: &Instance,
+    store: &mut Store<HostState>,
+) -> String {
+    let out_of_fuel = instance
+        .get_global(&mut *store, FUEL_GLOBAL)
+        .and_then(|fuel| fuel.get(&mut *store).i64())
+        .is_some_and(|fuel| fuel < 0);
+    let code = if out_of_fuel {
+        RuntimeError::OutOfGas.code()
+    } else {
+        trap_error(err).code()
+    };
+    format!("{} trapped [{}]: {}", what, code, err)
+}
+
 // =============================================================================
-// Host State — Captures stdout from WASI fd_write
+// Host State — Captures stdout/stderr from WASI fd_write
 // =============================================================================
 
-/// Holds state for the WASM execution, primarily the captured stdout buffer.
+/// Holds state for the WASM execution, primarily the captured output buffers.
 #[derive(Debug, Clone, Default)]
 pub struct HostState {
     /// Raw bytes written to stdout via fd_write.
     pub stdout_raw: Vec<u8>,
+    /// Raw bytes written to stderr via fd_write.
+    pub stderr_raw: Vec<u8>,
+    /// Position in the reproducible stream served by random_get.
+    pub random_state: u64,
+    /// Contents of the in-memory directory preopened as fd 3, by path.
+    pub files: HashMap<String, Vec<u8>>,
+    /// Files opened with path_open, by descriptor (4 and up).
+    pub open_files: HashMap<i32, OpenFile>,
+    /// Command-line arguments served by args_get, program name first.
+    pub args: Vec<String>,
+    /// Environment variables served by environ_get, as (name, value).
+    pub env: Vec<(String, String)>,
+}
+
+/// A file opened through path_open in the preopened directory.
+#[derive(Debug, Clone)]
+pub struct OpenFile {
+    /// Path within the preopened directory.
+    pub path: String,
+    /// Where the next read or write starts.
+    pub offset: usize,
+    /// Writes go to the end of the file (`FDFLAGS_APPEND`).
+    pub append: bool,
+}
+
+impl HostState {
+    /// Write `bytes` to open file `fd`; `false` if `fd` is not open.
+    fn write_file(&mut self, fd: i32, bytes: &[u8]) -> bool {
+        let Some(file) = self.open_files.get_mut(&fd) else {
+            return false;
+        };
+        let contents = self.files.entry(file.path.clone()).or_default();
+        if file.append {
+            file.offset = contents.len();
+        }
+        let end = file.offset + bytes.len();
+        if contents.len() < end {
+            contents.resize(end, 0);
+        }
+        contents[file.offset..end].copy_from_slice(bytes);
+        file.offset = end;
+        true
+    }
+
+    /// Read up to `max` bytes from open file `fd`; `None` if `fd` is not
+    /// open.
+    fn read_file(&mut self, fd: i32, max: usize) -> Option<Vec<u8>> {
+        let file = self.open_files.get_mut(&fd)?;
+        let contents = self.files.get(&file.path).map_or(&[][..], Vec::as_slice);
+        let start = file.offset.min(contents.len());
+        let end = (start + max).min(contents.len());
+        file.offset = end;
+        Some(contents[start..end].to_vec())
+    }
+
+    /// Environment entries in WASI's `NAME=value` form.
+    fn environ(&self) -> Vec<String> {
+        self.env
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect()
+    }
+}
+
+/// Store the count and total NUL-terminated size of `strings` at
+/// `count_ptr` and `size_ptr`, as `args_sizes_get` and `environ_sizes_get`
+/// do.
+fn write_string_sizes(data: &mut [u8], strings: &[String], count_ptr: i32, size_ptr: i32) -> i32 {
+    let size: usize = strings.iter().map(|s| s.len() + 1).sum();
+    for (ptr, value) in [(count_ptr, strings.len()), (size_ptr, size)] {
+        let off = ptr as usize;
+        if off + 4 > data.len() {
+            return 21; // EFAULT
+        }
+        data[off..off + 4].copy_from_slice(&(value as u32).to_le_bytes());
+    }
+    0
+}
+
+/// Copy `strings` NUL-terminated into `buf_ptr`, with a pointer to each at
+/// `table_ptr`, as `args_get` and `environ_get` do.
+fn write_strings(data: &mut [u8], strings: &[String], table_ptr: i32, buf_ptr: i32) -> i32 {
+    let mut table = table_ptr as usize;
+    let mut buf = buf_ptr as usize;
+    for s in strings {
+        if table + 4 > data.len() || buf + s.len() + 1 > data.len() {
+            return 21; // EFAULT
+        }
+        data[table..table + 4].copy_from_slice(&(buf as u32).to_le_bytes());
+        data[buf..buf + s.len()].copy_from_slice(s.as_bytes());
+        data[buf + s.len()] = 0;
+        table += 4;
+        buf += s.len() + 1;
+    }
+    0
 }
 
 // =============================================================================
@@ -59,6 +192,8 @@ pub struct WasmOutput {
     pub stdout_raw: Vec<u8>,
     /// Formatted stdout: raw i64 bytes are converted to decimal strings.
     pub stdout: String,
+    /// Text written to stderr (`sys.log` output).
+    pub stderr: String,
 }
 
 impl WasmOutput {
@@ -78,22 +213,42 @@ impl WasmOutput {
 // Public API
 // =============================================================================
 
+/// Compile `bytes`, refusing a module whose signature does not satisfy
+/// the trust policy of `ARK_TRUSTED_KEYS` (see [`crate::artifact`]).
+fn load_module(engine: &Engine, bytes: &[u8], context: &str) -> Result<Module, WasmRunError> {
+    let module = Module::from_binary(engine, bytes).map_err(|e| WasmRunError {
+        message: format!("Failed to load WASM module: {}", e),
+        context: format!("{}::load", context),
+    })?;
+    TrustPolicy::from_env()
+        .and_then(|policy| policy.verify_wasm(bytes))
+        .map_err(|e| WasmRunError {
+            message: format!("Refusing to run module: {}", e),
+            context: format!("{}::verify", context),
+        })?;
+    Ok(module)
+}
+
 /// Run a compiled WASM binary's `_start` function and capture stdout.
 ///
 /// This is the main entry point for executing Ark WASM programs.
 pub fn run_wasm(bytes: &[u8]) -> Result<WasmOutput, WasmRunError> {
+    run_wasm_with_state(bytes, HostState::default())
+}
+
+/// Like [`run_wasm`], but starting from `state` — to pass command-line
+/// arguments, environment variables or files to the program.
+pub fn run_wasm_with_state(bytes: &[u8], state: HostState) -> Result<WasmOutput, WasmRunError> {
     let engine = Engine::default();
-    let module = Module::from_binary(&engine, bytes).map_err(|e| WasmRunError {
-        message: format!("Failed to load WASM module: {}", e),
-        context: "run_wasm::load".to_string(),
-    })?;
+    let module = load_module(&engine, bytes, "run_wasm")?;
 
     let mut linker = Linker::<HostState>::new(&engine);
     link_wasi_fd_write(&mut linker)?;
     link_wasi_stubs(&mut linker)?;
+    link_wasi_preview2(&mut linker)?;
     crate::wasm_host_imports::link_ark_host_imports(&mut linker)?;
 
-    let mut store = Store::new(&engine, HostState::default());
+    let mut store = Store::new(&engine, state);
 
     let instance = linker
         .instantiate(&mut store, &module)
@@ -111,32 +266,110 @@ pub fn run_wasm(bytes: &[u8]) -> Result<WasmOutput, WasmRunError> {
         })?;
 
     start.call(&mut store, ()).map_err(|e| WasmRunError {
-        message: format!("Execution trapped: {}", e),
+        message: trap_message("Execution", &e, &instance, &mut store),
         context: "run_wasm::call_start".to_string(),
     })?;
 
     let raw = store.data().stdout_raw.clone();
     let stdout = WasmOutput::format_raw(&raw);
+    let stderr = WasmOutput::format_raw(&store.data().stderr_raw);
 
     Ok(WasmOutput {
         stdout_raw: raw,
         stdout,
+        stderr,
     })
 }
 
 /// Call a specific exported function by name with i64 arguments.
 ///
-/// Returns the i64 return value(s) from the function.
+/// Returns the i64 return value(s) from the function. A function declared
+/// with `Float` parameters or result has f64s in its signature; those
+/// arguments and that result are passed as their bits.
 pub fn call_exported(bytes: &[u8], name: &str, args: &[i64]) -> Result<Option<i64>, WasmRunError> {
+    call_exported_with_output(bytes, name, args).map(|(result, _)| result)
+}
+
+/// Like [`call_exported`], but also returning what the call wrote to
+/// stdout and stderr.
+pub fn call_exported_with_output(
+    bytes: &[u8],
+    name: &str,
+    args: &[i64],
+) -> Result<(Option<i64>, WasmOutput), WasmRunError> {
+    let (mut store, instance, func) = instantiate_export(bytes, name, "call_exported")?;
+
+    // Build params and results arrays
+    let func_type = func.ty(&store);
+    let params: Vec<wasmtime::Val> = args
+        .iter()
+        .zip(func_type.params())
+        .map(|(&v, ty)| match ty {
+            wasmtime::ValType::F64 => wasmtime::Val::F64(v as u64),
+            _ => wasmtime::Val::I64(v),
+        })
+        .collect();
+    let result_count = func_type.results().len();
+    let mut results = vec![wasmtime::Val::I64(0); result_count];
+
+    func.call(&mut store, &params, &mut results)
+        .map_err(|e| WasmRunError {
+            message: trap_message(&format!("Call to '{}'", name), &e, &instance, &mut store),
+            context: "call_exported::call".to_string(),
+        })?;
+
+    // Extract first result as i64
+    let result = match results.first() {
+        Some(wasmtime::Val::I64(v)) => Some(*v),
+        Some(wasmtime::Val::I32(v)) => Some(*v as i64),
+        Some(wasmtime::Val::F64(bits)) => Some(*bits as i64),
+        _ => None,
+    };
+
+    let raw = store.data().stdout_raw.clone();
+    let output = WasmOutput {
+        stdout: WasmOutput::format_raw(&raw),
+        stderr: WasmOutput::format_raw(&store.data().stderr_raw),
+        stdout_raw: raw,
+    };
+    Ok((result, output))
+}
+
+/// Call the exported, argument-free function `name` `iterations` times in
+/// one instance and return how long the calls took, instantiation aside.
+pub fn time_exported(
+    bytes: &[u8],
+    name: &str,
+    iterations: u64,
+) -> Result<std::time::Duration, WasmRunError> {
+    let (mut store, instance, func) = instantiate_export(bytes, name, "time_exported")?;
+    let mut results = vec![wasmtime::Val::I64(0); func.ty(&store).results().len()];
+
+    let start = std::time::Instant::now();
+    for _ in 0..iterations {
+        func.call(&mut store, &[], &mut results)
+            .map_err(|e| WasmRunError {
+                message: trap_message(&format!("Call to '{}'", name), &e, &instance, &mut store),
+                context: "time_exported::call".to_string(),
+            })?;
+    }
+    Ok(start.elapsed())
+}
+
+/// Instantiate `bytes` with the host imports and look up the exported
+/// function `name`.
+fn instantiate_export(
+    bytes: &[u8],
+    name: &str,
+    context: &str,
+) -> Result<(Store<HostState>, Instance, wasmtime::Func), WasmRunError> {
     let engine = Engine::default();
-    let module = Module::from_binary(&engine, bytes).map_err(|e| WasmRunError {
-        message: format!("Failed to load WASM module: {}", e),
-        context: "call_exported::load".to_string(),
-    })?;
+    let module = load_module(&engine, bytes, context)?;
 
     let mut linker = Linker::<HostState>::new(&engine);
     link_wasi_fd_write(&mut linker)?;
     link_wasi_stubs(&mut linker)?;
+    link_wasi_preview2(&mut linker)?;
     crate::wasm_host_imports::link_ark_host_imports(&mut linker)?;
 
     let mut store = Store::new(&engine, HostState::default());
@@ -145,37 +378,64 @@ pub fn call_exported(bytes: &[u8], name: &str, args: &[i64]) -> Result<Option<i6
         .instantiate(&mut store, &module)
         .map_err(|e| WasmRunError {
             message: format!("Failed to instantiate: {}", e),
-            context: "call_exported::instantiate".to_string(),
+            context: format!("{}::instantiate", context),
         })?;
 
     let func = instance
         .get_func(&mut store, name)
         .ok_or_else(|| WasmRunError {
             message: format!("Export '{}' not found", name),
-            context: "call_exported::get_func".to_string(),
+            context: format!("{}::get_func", context),
         })?;
+    Ok((store, instance, func))
+}
 
-    // Build params and results arrays
-    let params: Vec<wasmtime::Val> = args.iter().map(|&v| wasmtime::Val::I64(v)).collect();
-    let func_type = func.ty(&store);
-    let result_count = func_type.results().len();
-    let mut results = vec![wasmtime::Val::I64(0); result_count];
+/// Run `_start`, then read the exported i64 global `name`.
+///
+/// Top-level `#[export] let` bindings are exported this way. The value is
+/// the raw Ark i64, so a string comes back packed as `ptr << 32 | len`.
+pub fn read_exported_global(bytes: &[u8], name: &str) -> Result<i64, WasmRunError> {
+    let engine = Engine::default();
+    let module = load_module(&engine, bytes, "read_exported_global")?;
 
-    func.call(&mut store, &params, &mut results)
+    let mut linker = Linker::<HostState>::new(&engine);
+    link_wasi_fd_write(&mut linker)?;
+    link_wasi_stubs(&mut linker)?;
+    link_wasi_preview2(&mut linker)?;
+    crate::wasm_host_imports::link_ark_host_imports(&mut linker)?;
+
+    let mut store = Store::new(&engine, HostState::default());
+
+    let instance = linker
+        .instantiate(&mut store, &module)
         .map_err(|e| WasmRunError {
-            message: format!("Call to '{}' trapped: {}", name, e),
-            context: "call_exported::call".to_string(),
+            message: format!("Failed to instantiate: {}", e),
+            context: "read_exported_global::instantiate".to_string(),
         })?;
 
-    // Extract first result as i64
-    if let Some(val) = results.first() {
-        match val {
-            wasmtime::Val::I64(v) => Ok(Some(*v)),
-            wasmtime::Val::I32(v) => Ok(Some(*v as i64)),
-            _ => Ok(None),
-        }
-    } else {
-        Ok(None)
+    let start = instance
+        .get_typed_func::<(), ()>(&mut store, "_start")
+        .map_err(|e| WasmRunError {
+            message: format!("No _start export: {}", e),
+            context: "read_exported_global::get_start".to_string(),
+        })?;
+    start.call(&mut store, ()).map_err(|e| WasmRunError {
+        message: trap_message("Execution", &e, &instance, &mut store),
+        context: "read_exported_global::call_start".to_string(),
+    })?;
+
+    let global = instance
+        .get_global(&mut store, name)
+        .ok_or_else(|| WasmRunError {
+            message: format!("Global export '{}' not found", name),
+            context: "read_exported_global::get_global".to_string(),
+        })?;
+    match global.get(&mut store) {
+        wasmtime::Val::I64(v) => Ok(v),
+        other => Err(WasmRunError {
+            message: format!("Global '{}' is not an i64: {:?}", name, other),
+            context: "read_exported_global::get".to_string(),
+        }),
     }
 }
 
@@ -187,7 +447,8 @@ pub fn call_exported(bytes: &[u8], name: &str, args: &[i64]) -> Result<Option<i6
 ///
 /// fd_write(fd: i32, iovs: i32, iovs_len: i32, nwritten_ptr: i32) -> i32
 ///
-/// We only handle fd=1 (stdout). The iov structure is:
+/// fd=1 (stdout) and fd=2 (stderr) are captured; other descriptors must be
+/// files opened with path_open. The iov structure is:
 ///   iov[i].buf_ptr: i32 at iovs + i*8
 ///   iov[i].buf_len: i32 at iovs + i*8 + 4
 fn link_wasi_fd_write(linker: &mut Linker<HostState>) -> Result<(), WasmRunError> {
@@ -201,11 +462,6 @@ fn link_wasi_fd_write(linker: &mut Linker<HostState>) -> Result<(), WasmRunError
              iovs_len: i32,
              nwritten_ptr: i32|
              -> i32 {
-                // Only capture stdout (fd=1)
-                if fd != 1 {
-                    return 0; // silently ignore other fds
-                }
-
                 let memory = match caller.get_export("memory") {
                     Some(Extern::Memory(mem)) => mem,
                     _ => return 8, // EBADF
@@ -213,10 +469,10 @@ fn link_wasi_fd_write(linker: &mut Linker<HostState>) -> Result<(), WasmRunError
 
                 let data = memory.data(&caller);
                 let mut total_written: u32 = 0;
+                let mut bytes_vec: Vec<u8> = Vec::new();
 
-                // Ark only uses iovs_len=1, so we handle the first iov directly
-                if iovs_len > 0 {
-                    let iov_offset = iovs as usize;
+                for i in 0..iovs_len.max(0) as usize {
+                    let iov_offset = iovs as usize + i * 8;
 
                     // Read buf_ptr and buf_len from iovec
                     if iov_offset + 8 > data.len() {
@@ -234,11 +490,18 @@ fn link_wasi_fd_write(linker: &mut Linker<HostState>) -> Result<(), WasmRunError
                         return 21; // EFAULT
                     }
 
-                    let bytes = &data[buf_ptr..buf_ptr + buf_len];
-                    let bytes_vec: Vec<u8> = bytes.to_vec();
+                    bytes_vec.extend_from_slice(&data[buf_ptr..buf_ptr + buf_len]);
                     total_written += buf_len as u32;
+                }
 
-                    caller.data_mut().stdout_raw.extend_from_slice(&bytes_vec);
+                match fd {
+                    1 => caller.data_mut().stdout_raw.extend_from_slice(&bytes_vec),
+                    2 => caller.data_mut().stderr_raw.extend_from_slice(&bytes_vec),
+                    _ => {
+                        if !caller.data_mut().write_file(fd, &bytes_vec) {
+                            return 8; // EBADF
+                        }
+                    }
                 }
 
                 // Write nwritten
@@ -272,20 +535,53 @@ fn link_wasi_fd_write(linker: &mut Linker<HostState>) -> Result<(), WasmRunError
 ///
 /// These provide the minimal functions that wasmtime needs to instantiate modules
 /// that declare Phase 11 WASI imports. Stubs return errno=0 (success) or
-/// reasonable defaults.
+/// reasonable defaults. path_open, fd_read, fd_seek and fd_close work on an in-memory
+/// directory preopened as fd 3 (see [`HostState::files`]).
 fn link_wasi_stubs(linker: &mut Linker<HostState>) -> Result<(), WasmRunError> {
     // fd_read(fd:i32, iovs:i32, iovs_len:i32, nread:i32) -> i32
     linker
         .func_wrap(
             "wasi_snapshot_preview1",
             "fd_read",
-            |_caller: Caller<'_, HostState>,
-             _fd: i32,
-             _iovs: i32,
-             _iovs_len: i32,
-             _nread: i32|
+            |mut caller: Caller<'_, HostState>,
+             fd: i32,
+             iovs: i32,
+             iovs_len: i32,
+             nread_ptr: i32|
              -> i32 {
-                0 // stub: success, 0 bytes read
+                let Some(Extern::Memory(memory)) = caller.get_export("memory") else {
+                    return 8; // EBADF
+                };
+                let (data, state) = memory.data_and_store_mut(&mut caller);
+                if !state.open_files.contains_key(&fd) {
+                    return 0; // stub: stdin reads nothing
+                }
+
+                let mut nread: u32 = 0;
+                for i in 0..iovs_len.max(0) as usize {
+                    let iov_offset = iovs as usize + i * 8;
+                    let Some(iov) = data.get(iov_offset..iov_offset + 8) else {
+                        return 21; // EFAULT
+                    };
+                    let buf_ptr = u32::from_le_bytes([iov[0], iov[1], iov[2], iov[3]]) as usize;
+                    let buf_len = u32::from_le_bytes([iov[4], iov[5], iov[6], iov[7]]) as usize;
+                    if buf_ptr + buf_len > data.len() {
+                        return 21; // EFAULT
+                    }
+                    let bytes = state.read_file(fd, buf_len).unwrap_or_default();
+                    data[buf_ptr..buf_ptr + bytes.len()].copy_from_slice(&bytes);
+                    nread += bytes.len() as u32;
+                    if bytes.len() < buf_len {
+                        break;
+                    }
+                }
+
+                let nread_offset = nread_ptr as usize;
+                match data.get_mut(nread_offset..nread_offset + 4) {
+                    Some(slot) => slot.copy_from_slice(&nread.to_le_bytes()),
+                    None => return 21, // EFAULT
+                }
+                0
             },
         )
         .map_err(|e| WasmRunError {
@@ -326,14 +622,16 @@ fn link_wasi_stubs(linker: &mut Linker<HostState>) -> Result<(), WasmRunError> {
             "wasi_snapshot_preview1",
             "random_get",
             |mut caller: Caller<'_, HostState>, buf: i32, buf_len: i32| -> i32 {
-                // Fill with pseudo-random bytes (simple counter pattern)
+                // Fill with a reproducible splitmix64 stream so repeated
+                // calls see different bytes but test runs stay stable
                 if let Some(Extern::Memory(memory)) = caller.get_export("memory") {
-                    let data = memory.data_mut(&mut caller);
+                    let (data, state) = memory.data_and_store_mut(&mut caller);
                     let offset = buf as usize;
                     let len = buf_len as usize;
                     if offset + len <= data.len() {
-                        for i in 0..len {
-                            data[offset + i] = (i % 256) as u8;
+                        for chunk in data[offset..offset + len].chunks_mut(8) {
+                            let z = next_random(&mut state.random_state);
+                            chunk.copy_from_slice(&z.to_le_bytes()[..chunk.len()]);
                         }
                     }
                 }
@@ -350,7 +648,15 @@ fn link_wasi_stubs(linker: &mut Linker<HostState>) -> Result<(), WasmRunError> {
         .func_wrap(
             "wasi_snapshot_preview1",
             "args_get",
-            |_caller: Caller<'_, HostState>, _argv: i32, _argv_buf: i32| -> i32 { 0 },
+            |mut caller: Caller<'_, HostState>, argv: i32, argv_buf: i32| -> i32 {
+                let args = caller.data().args.clone();
+                match caller.get_export("memory") {
+                    Some(Extern::Memory(memory)) => {
+                        write_strings(memory.data_mut(&mut caller), &args, argv, argv_buf)
+                    }
+                    _ => 21, // EFAULT
+                }
+            },
         )
         .map_err(|e| WasmRunError {
             message: format!("Failed to link args_get: {}", e),
@@ -363,20 +669,16 @@ fn link_wasi_stubs(linker: &mut Linker<HostState>) -> Result<(), WasmRunError> {
             "wasi_snapshot_preview1",
             "args_sizes_get",
             |mut caller: Caller<'_, HostState>, argc_ptr: i32, argv_buf_size_ptr: i32| -> i32 {
-                if let Some(Extern::Memory(memory)) = caller.get_export("memory") {
-                    let data = memory.data_mut(&mut caller);
-                    let argc_off = argc_ptr as usize;
-                    let buf_off = argv_buf_size_ptr as usize;
-                    if argc_off + 4 <= data.len() {
-                        data[argc_off..argc_off + 4].copy_from_slice(&0u32.to_le_bytes());
-                        // 0 args
-                    }
-                    if buf_off + 4 <= data.len() {
-                        data[buf_off..buf_off + 4].copy_from_slice(&0u32.to_le_bytes());
-                        // 0 buf size
-                    }
+                let args = caller.data().args.clone();
+                match caller.get_export("memory") {
+                    Some(Extern::Memory(memory)) => write_string_sizes(
+                        memory.data_mut(&mut caller),
+                        &args,
+                        argc_ptr,
+                        argv_buf_size_ptr,
+                    ),
+                    _ => 21, // EFAULT
                 }
-                0
             },
         )
         .map_err(|e| WasmRunError {
@@ -389,7 +691,15 @@ fn link_wasi_stubs(linker: &mut Linker<HostState>) -> Result<(), WasmRunError> {
         .func_wrap(
             "wasi_snapshot_preview1",
             "environ_get",
-            |_caller: Caller<'_, HostState>, _environ: i32, _environ_buf: i32| -> i32 { 0 },
+            |mut caller: Caller<'_, HostState>, environ: i32, environ_buf: i32| -> i32 {
+                let entries = caller.data().environ();
+                match caller.get_export("memory") {
+                    Some(Extern::Memory(memory)) => {
+                        write_strings(memory.data_mut(&mut caller), &entries, environ, environ_buf)
+                    }
+                    _ => 21, // EFAULT
+                }
+            },
         )
         .map_err(|e| WasmRunError {
             message: format!("Failed to link environ_get: {}", e),
@@ -405,18 +715,16 @@ fn link_wasi_stubs(linker: &mut Linker<HostState>) -> Result<(), WasmRunError> {
              environc_ptr: i32,
              environ_buf_size_ptr: i32|
              -> i32 {
-                if let Some(Extern::Memory(memory)) = caller.get_export("memory") {
-                    let data = memory.data_mut(&mut caller);
-                    let ec_off = environc_ptr as usize;
-                    let buf_off = environ_buf_size_ptr as usize;
-                    if ec_off + 4 <= data.len() {
-                        data[ec_off..ec_off + 4].copy_from_slice(&0u32.to_le_bytes());
-                    }
-                    if buf_off + 4 <= data.len() {
-                        data[buf_off..buf_off + 4].copy_from_slice(&0u32.to_le_bytes());
-                    }
+                let entries = caller.data().environ();
+                match caller.get_export("memory") {
+                    Some(Extern::Memory(memory)) => write_string_sizes(
+                        memory.data_mut(&mut caller),
+                        &entries,
+                        environc_ptr,
+                        environ_buf_size_ptr,
+                    ),
+                    _ => 21, // EFAULT
                 }
-                0
             },
         )
         .map_err(|e| WasmRunError {
@@ -443,18 +751,58 @@ fn link_wasi_stubs(linker: &mut Linker<HostState>) -> Result<(), WasmRunError> {
         .func_wrap(
             "wasi_snapshot_preview1",
             "path_open",
-            |_caller: Caller<'_, HostState>,
-             _fd: i32,
+            |mut caller: Caller<'_, HostState>,
+             fd: i32,
              _dirflags: i32,
-             _path: i32,
-             _path_len: i32,
-             _oflags: i32,
+             path: i32,
+             path_len: i32,
+             oflags: i32,
              _rights_base: i64,
              _rights_inherit: i64,
-             _fdflags: i32,
-             _opened_fd: i32|
+             fdflags: i32,
+             opened_fd_ptr: i32|
              -> i32 {
-                76 // ENOSYS — not supported in test environment
+                if fd != 3 {
+                    return 8; // EBADF: fd 3 is the only preopened directory
+                }
+                let Some(Extern::Memory(memory)) = caller.get_export("memory") else {
+                    return 8;
+                };
+                let (data, state) = memory.data_and_store_mut(&mut caller);
+                let start = path as usize;
+                let Some(path) = data.get(start..start + path_len as usize) else {
+                    return 21; // EFAULT
+                };
+                let Ok(path) = String::from_utf8(path.to_vec()) else {
+                    return 28; // EINVAL
+                };
+
+                // O_CREAT = 1, O_TRUNC = 8
+                if !state.files.contains_key(&path) {
+                    if oflags & 1 == 0 {
+                        return 44; // ENOENT
+                    }
+                    state.files.insert(path.clone(), Vec::new());
+                }
+                if oflags & 8 != 0 {
+                    state.files.entry(path.clone()).or_default().clear();
+                }
+
+                let opened = state.open_files.keys().max().map_or(4, |last| last + 1);
+                state.open_files.insert(
+                    opened,
+                    OpenFile {
+                        path,
+                        offset: 0,
+                        append: fdflags & 1 != 0, // FDFLAGS_APPEND
+                    },
+                );
+                let out = opened_fd_ptr as usize;
+                match data.get_mut(out..out + 4) {
+                    Some(slot) => slot.copy_from_slice(&opened.to_le_bytes()),
+                    None => return 21, // EFAULT
+                }
+                0
             },
         )
         .map_err(|e| WasmRunError {
@@ -467,13 +815,159 @@ fn link_wasi_stubs(linker: &mut Linker<HostState>) -> Result<(), WasmRunError> {
         .func_wrap(
             "wasi_snapshot_preview1",
             "fd_close",
-            |_caller: Caller<'_, HostState>, _fd: i32| -> i32 { 0 },
+            |mut caller: Caller<'_, HostState>, fd: i32| -> i32 {
+                caller.data_mut().open_files.remove(&fd);
+                0
+            },
         )
         .map_err(|e| WasmRunError {
             message: format!("Failed to link fd_close: {}", e),
             context: "link_wasi_stubs".to_string(),
         })?;
 
+    // fd_seek(fd:i32, offset:i64, whence:i32, newoffset:i32) -> i32
+    linker
+        .func_wrap(
+            "wasi_snapshot_preview1",
+            "fd_seek",
+            |mut caller: Caller<'_, HostState>,
+             fd: i32,
+             offset: i64,
+             whence: i32,
+             newoffset_ptr: i32|
+             -> i32 {
+                let Some(Extern::Memory(memory)) = caller.get_export("memory") else {
+                    return 8; // EBADF
+                };
+                let (data, state) = memory.data_and_store_mut(&mut caller);
+                let Some(file) = state.open_files.get_mut(&fd) else {
+                    return 8; // EBADF
+                };
+                // Only SEEK_SET (whence 0) is needed by the Ark helpers
+                if whence != 0 || offset < 0 {
+                    return 28; // EINVAL
+                }
+                file.offset = offset as usize;
+                let out = newoffset_ptr as usize;
+                match data.get_mut(out..out + 8) {
+                    Some(slot) => slot.copy_from_slice(&(offset as u64).to_le_bytes()),
+                    None => return 21, // EFAULT
+                }
+                0
+            },
+        )
+        .map_err(|e| WasmRunError {
+            message: format!("Failed to link fd_seek: {}", e),
+            context: "link_wasi_stubs".to_string(),
+        })?;
+
+    Ok(())
+}
+
+/// Next value of the reproducible splitmix64 stream behind the random stubs.
+fn next_random(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+// =============================================================================
+// WASI 0.2 Implementations (for `WasiTarget::Preview2` modules)
+// =============================================================================
+
+/// Link the WASI 0.2 functions a Preview2-targeted module imports, at their
+/// canonical ABI core signatures, with the same behavior as the Preview1
+/// stubs above.
+///
+/// Output streams are handle 1 (stdout) and 2 (stderr); dropping one is a
+/// no-op. Writes always succeed, storing the `ok` tag at the return pointer.
+fn link_wasi_preview2(linker: &mut Linker<HostState>) -> Result<(), WasmRunError> {
+    let link_error = |name: &str, e: wasmtime::Error| WasmRunError {
+        message: format!("Failed to link {}: {}", name, e),
+        context: "link_wasi_preview2".to_string(),
+    };
+
+    linker
+        .func_wrap("wasi:cli/stdout@0.2.0", "get-stdout", || -> i32 { 1 })
+        .map_err(|e| link_error("get-stdout", e))?;
+    linker
+        .func_wrap("wasi:cli/stderr@0.2.0", "get-stderr", || -> i32 { 2 })
+        .map_err(|e| link_error("get-stderr", e))?;
+
+    // [method]output-stream.blocking-write-and-flush(self, ptr, len, ret_ptr)
+    linker
+        .func_wrap(
+            "wasi:io/streams@0.2.0",
+            "[method]output-stream.blocking-write-and-flush",
+            |mut caller: Caller<'_, HostState>, stream: i32, ptr: i32, len: i32, ret_ptr: i32| {
+                let Some(Extern::Memory(memory)) = caller.get_export("memory") else {
+                    return;
+                };
+                let (data, state) = memory.data_and_store_mut(&mut caller);
+                let (start, end) = (ptr as usize, ptr as usize + len as usize);
+                if end <= data.len() {
+                    let bytes = &data[start..end];
+                    if stream == 2 {
+                        state.stderr_raw.extend_from_slice(bytes);
+                    } else {
+                        state.stdout_raw.extend_from_slice(bytes);
+                    }
+                }
+                if let Some(tag) = data.get_mut(ret_ptr as usize) {
+                    *tag = 0; // ok
+                }
+            },
+        )
+        .map_err(|e| link_error("blocking-write-and-flush", e))?;
+    linker
+        .func_wrap(
+            "wasi:io/streams@0.2.0",
+            "[resource-drop]output-stream",
+            |_stream: i32| {},
+        )
+        .map_err(|e| link_error("[resource-drop]output-stream", e))?;
+
+    // now(ret_ptr): datetime { seconds: u64, nanoseconds: u32 }, 1 second
+    // like the clock_time_get stub
+    linker
+        .func_wrap(
+            "wasi:clocks/wall-clock@0.2.0",
+            "now",
+            |mut caller: Caller<'_, HostState>, ret_ptr: i32| {
+                if let Some(Extern::Memory(memory)) = caller.get_export("memory") {
+                    let data = memory.data_mut(&mut caller);
+                    let offset = ret_ptr as usize;
+                    if offset + 12 <= data.len() {
+                        data[offset..offset + 8].copy_from_slice(&1u64.to_le_bytes());
+                        data[offset + 8..offset + 12].copy_from_slice(&0u32.to_le_bytes());
+                    }
+                }
+            },
+        )
+        .map_err(|e| link_error("wall-clock now", e))?;
+    linker
+        .func_wrap("wasi:clocks/monotonic-clock@0.2.0", "now", || -> i64 {
+            1_000_000_000
+        })
+        .map_err(|e| link_error("monotonic-clock now", e))?;
+
+    linker
+        .func_wrap(
+            "wasi:random/random@0.2.0",
+            "get-random-u64",
+            |mut caller: Caller<'_, HostState>| -> i64 {
+                next_random(&mut caller.data_mut().random_state) as i64
+            },
+        )
+        .map_err(|e| link_error("get-random-u64", e))?;
+
+    // exit(status: result), a no-op in tests like proc_exit
+    linker
+        .func_wrap("wasi:cli/exit@0.2.0", "exit", |_status: i32| {})
+        .map_err(|e| link_error("exit", e))?;
+
     Ok(())
 }
 
@@ -605,6 +1099,126 @@ print(0)
         );
     }
 
+    #[test]
+    fn test_e2e_print_non_literal_strings() {
+        let source = r#"
+func greet(name: String) {
+    print(name)
+    return 0
+}
+a := "ark"
+b := string_concat(a, "-wasm")
+print(a)
+print(b)
+print(string_concat(b, "!"))
+greet("hi")
+print(string_len(b))
+"#;
+        let wasm = compile_ark(source);
+        let output = run_wasm(&wasm).expect("run failed");
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(
+            lines,
+            ["ark", "ark-wasm", "ark-wasm!", "hi", "8"],
+            "stdout: {:?}",
+            output.stdout
+        );
+    }
+
+    #[test]
+    fn test_e2e_match_scrutinee_evaluated_once() {
+        // bump() prints 7 each time it runs; only the bound arm adds 100.
+        let source = r#"
+func bump() {
+    print(7)
+    return 2
+}
+match bump() {
+    1 => print(10),
+    3 => print(30),
+    n => print(n + 100)
+}
+"#;
+        let wasm = compile_ark(source);
+        let output = run_wasm(&wasm).expect("run failed");
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(lines, ["7", "102"], "stdout: {:?}", output.stdout);
+    }
+
+    #[test]
+    fn test_e2e_log_goes_to_stderr() {
+        let source = r#"
+print("out")
+sys.log("error", "disk full")
+sys.log("debug", "noise")
+sys.log("plain")
+"#;
+        let ast = parser::parse_source(source, "test.ark").expect("parse failed");
+        let mut codegen = WasmCodegen::new();
+        codegen.set_log_level("info").expect("known level");
+        let wasm = codegen.compile(&ast).expect("compile failed");
+        let output = run_wasm(&wasm).expect("run failed");
+
+        assert_eq!(output.stderr, "[ERROR] disk full\n[LOG] plain\n");
+        assert!(output.stdout.contains("out"), "{:?}", output.stdout);
+        assert!(!output.stdout.contains("disk full"), "{:?}", output.stdout);
+    }
+
+    #[test]
+    fn test_e2e_failed_assertions_log_and_return_false() {
+        let source = r#"
+#[export]
+func checks() => Int {
+    a := assert(1 + 1 == 2)
+    b := assert_eq(2 * 3, 7, "product")
+    return a * 10 + b
+}
+"#;
+        let wasm = compile_ark(source);
+        let (result, output) =
+            call_exported_with_output(&wasm, "checks", &[]).expect("call failed");
+
+        assert_eq!(result, Some(10));
+        assert_eq!(output.stderr, "[ERROR] assertion failed: product\n");
+    }
+
+    #[test]
+    fn test_e2e_random_int_stays_in_range() {
+        let source = r#"
+i := 0
+while i < 200 {
+    print(sys.random.int(-3, 4))
+    i := i + 1
+}
+"#;
+        let wasm = compile_ark(source);
+        let output = run_wasm(&wasm).expect("run failed");
+        let draws: Vec<i64> = output
+            .stdout
+            .lines()
+            .map(|l| l.trim().parse().expect("integer line"))
+            .collect();
+
+        assert_eq!(draws.len(), 200);
+        assert!(draws.iter().all(|n| (-3..=4).contains(n)), "{:?}", draws);
+        assert!(draws.iter().any(|&n| n != draws[0]), "{:?}", draws);
+    }
+
+    #[test]
+    fn test_e2e_exported_let_globals() {
+        let source = r#"
+#[export]
+let LIMIT := 42
+
+#[export]
+let TOTAL := LIMIT + 8
+"#;
+        let wasm = compile_ark(source);
+        assert_eq!(read_exported_global(&wasm, "LIMIT").expect("LIMIT"), 42);
+        assert_eq!(read_exported_global(&wasm, "TOTAL").expect("TOTAL"), 50);
+        assert!(read_exported_global(&wasm, "missing").is_err());
+    }
+
     #[test]
     fn test_e2e_print_integer() {
         // Explicit test: print(42) should output "42"
@@ -680,6 +1294,55 @@ print(3)
         );
     }
 
+    #[test]
+    fn test_e2e_print_helper_shared() {
+        // Ten integer prints should all call one shared itoa helper rather
+        // than each inlining the digit loop.
+        let source = r#"
+print(0)
+print(1)
+print(0 - 2)
+print(30)
+print(405)
+print(0 - 6789)
+print(12345)
+print(7)
+print(88)
+print(9000000000)
+"#;
+        let wasm = compile_ark(source);
+
+        let mut rem_count = 0;
+        for payload in wasmparser::Parser::new(0).parse_all(&wasm) {
+            if let wasmparser::Payload::CodeSectionEntry(body) = payload.expect("valid wasm") {
+                for op in body.get_operators_reader().expect("operators") {
+                    if matches!(op.expect("operator"), wasmparser::Operator::I64RemU) {
+                        rem_count += 1;
+                    }
+                }
+            }
+        }
+        assert_eq!(rem_count, 1, "itoa loop should be emitted exactly once");
+
+        let output = run_wasm(&wasm).expect("run failed");
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "0",
+                "1",
+                "-2",
+                "30",
+                "405",
+                "-6789",
+                "12345",
+                "7",
+                "88",
+                "9000000000"
+            ]
+        );
+    }
+
     #[test]
     fn test_e2e_nested_arithmetic() {
         // Nested expression: (3 + 4) * 6 = 42
@@ -740,6 +1403,386 @@ while i > 0 {
         );
     }
 
+    #[test]
+    fn test_e2e_for_loops_over_ranges_and_lists() {
+        let source = r#"
+total := 0
+for i in 0..5 {
+    total := total + i
+}
+print(total)
+for x in [3, 4, 5] {
+    if x == 4 {
+        continue
+    }
+    print(x)
+}
+for j in 1..=10 {
+    if j > 2 {
+        break
+    }
+    print(j * 100)
+}
+k := 0
+while k < 10 {
+    k := k + 1
+    if k == 3 {
+        break
+    }
+}
+print(k)
+"#;
+        let wasm = compile_ark(source);
+        let output = run_wasm(&wasm).expect("run failed");
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(lines, vec!["10", "3", "5", "100", "200", "3"]);
+    }
+
+    #[test]
+    fn test_e2e_labeled_break_and_continue() {
+        let source = r#"
+'outer: for x in 1..4 {
+    for y in 1..4 {
+        if y == 2 {
+            continue 'outer
+        }
+        if x == 3 {
+            break 'outer
+        }
+        print(x * 10 + y)
+    }
+}
+n := 0
+'scan: while n < 100 {
+    n := n + 1
+    for d in 0..3 {
+        if n * d == 6 {
+            break 'scan
+        }
+    }
+}
+print(n)
+"#;
+        let wasm = compile_ark(source);
+        let output = run_wasm(&wasm).expect("run failed");
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(lines, vec!["11", "21", "3"]);
+    }
+
+    #[test]
+    fn test_e2e_map_literal_get_set_keys() {
+        // Seven keys push the table past 3/4 of its initial 8 entries
+        let source = r#"
+m := {"one": 1, "two": 2, "three": 3}
+m := map.set(m, "four", 4)
+m := map.set(m, "two", 22)
+m := map.set(m, "five", 5)
+m := map.set(m, "six", 6)
+m := map.set(m, "seven", 7)
+print(map.get(m, "one"))
+print(map.get(m, "two"))
+print(map.get(m, "seven"))
+print(map.get(m, "eight"))
+keys := map.keys(m)
+print(len(keys))
+for k in keys {
+    print(string_concat("key ", k))
+}
+print(sys.type_of(m))
+"#;
+        let wasm = compile_ark(source);
+        let output = run_wasm(&wasm).expect("run failed");
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "1",
+                "22",
+                "7",
+                "0",
+                "7",
+                "key five",
+                "key four",
+                "key one",
+                "key seven",
+                "key six",
+                "key three",
+                "key two",
+                "pmap"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_e2e_pvec_three_level_trie() {
+        // 1100 elements need a root above two levels of 32-slot nodes
+        let source = r#"
+func build(n) {
+    v := pvec.new()
+    i := 0
+    while i < n {
+        v := pvec.conj(v, i * i)
+        i := i + 1
+    }
+    return v
+}
+v := build(1100)
+print(pvec.len(v))
+print(pvec.get(v, 0))
+print(pvec.get(v, 33))
+print(pvec.get(v, 1099))
+print(pvec.get(v, 1100))
+total := 0
+i := 0
+while i < 1100 {
+    total := total + pvec.get(v, i)
+    i := i + 1
+}
+print(total)
+w := pvec.assoc(v, 1057, 7)
+print(pvec.get(w, 1057))
+print(pvec.get(v, 1057))
+p := pvec.pop(v)
+print(pvec.len(p))
+q := pvec.conj(p, 5)
+print(pvec.get(q, 1099))
+print(pvec.get(v, 1099))
+print(len(q))
+print(sys.type_of(q))
+"#;
+        let wasm = compile_ark(source);
+        let output = run_wasm(&wasm).expect("run failed");
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "1100",
+                "0",
+                "1089",
+                "1207801",
+                "0",
+                "443061850",
+                "7",
+                "1117249",
+                "1099",
+                "5",
+                "1207801",
+                "1100",
+                "pvec"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_e2e_pmap_hamt_persistence() {
+        let source = r#"
+letters := "abcdefghijklmnopqrstuvwxyz"
+m := pmap.new()
+n := 0
+i := 0
+while i < 26 {
+    j := 0
+    while j < 26 {
+        k := string_concat(string_char_at(letters, i), string_char_at(letters, j))
+        m := pmap.assoc(m, k, n)
+        n := n + 1
+        j := j + 1
+    }
+    i := i + 1
+}
+print(len(m))
+print(pmap.get(m, "aa"))
+print(pmap.get(m, "zz"))
+print(pmap.get(m, "km"))
+print(pmap.get(m, "a"))
+m2 := pmap.assoc(m, "km", 1000)
+print(pmap.get(m2, "km"))
+print(pmap.get(m, "km"))
+print(len(m2))
+m3 := pmap.dissoc(m, "aa")
+print(len(m3))
+print(len(m))
+print(pmap.get(m3, "aa"))
+print(len(pmap.dissoc(m3, "missing")))
+keys := pmap.keys(m3)
+print(len(keys))
+print(string_concat("first ", list.get(keys, 0)))
+print(string_concat("last ", list.get(keys, 674)))
+total := 0
+for k in keys {
+    total := total + pmap.get(m3, k)
+}
+print(total)
+empty := m3
+for k in keys {
+    empty := pmap.dissoc(empty, k)
+}
+print(len(empty))
+print(len(pmap.keys(empty)))
+other := pmap.assoc(pmap.assoc(pmap.new(), "zz", 0), "new", 9)
+merged := pmap.merge(m, other)
+print(len(merged))
+print(pmap.get(merged, "zz"))
+print(pmap.get(merged, "new"))
+print(pmap.get(m, "zz"))
+print(sys.type_of(merged))
+"#;
+        let wasm = compile_ark(source);
+        let output = run_wasm(&wasm).expect("run failed");
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "676", "0", "675", "272", "0", "1000", "272", "676", "675", "676", "0", "675",
+                "675", "first ab", "last zz", "228150", "0", "0", "677", "0", "9", "675", "pmap"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_e2e_bigint_limbs() {
+        let source = r#"
+max := bigint.from_string("9223372036854775807")
+past := bigint.add(max, 1)
+print(bigint.to_string(past))
+print(bigint.to_string(bigint.mul(max, max)))
+print(bigint.to_string(bigint.add(bigint.from_string("-5"), 3)))
+print(bigint.to_string(bigint.mod(bigint.from_string("-12345678901234567890123"), 7)))
+print(bigint.to_string(bigint.pow_mod(4, 13, 497)))
+p := bigint.add(bigint.from_string("57896044618658097711785492504343953926634992332820282019728792003956564819968"), 0 - 19)
+print(bigint.to_string(bigint.pow_mod(2, bigint.add(p, 0 - 1), p)))
+print(bigint.cmp(max, past))
+print(bigint.cmp(past, max))
+print(bigint.cmp(bigint.from_string("-0"), 0))
+print(sys.type_of(past))
+"#;
+        let wasm = compile_ark(source);
+        let output = run_wasm(&wasm).expect("run failed");
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "9223372036854775808",
+                "85070591730234615847396907784232501249",
+                "-2",
+                "4",
+                "445",
+                "1",
+                "-1",
+                "1",
+                "0",
+                "bigint"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_e2e_tensor_kernels_with_and_without_simd() {
+        use crate::wasm_codegen::WasmCodegenOptions;
+
+        // Odd lengths leave a last element for the scalar tail of the SIMD loops
+        let source = r#"
+a := math.Tensor([1, 2, 3, 4, 5, 6], [2, 3])
+b := math.Tensor([7, 8, 9, 10, 11, 12], [3, 2])
+c := math.matmul(a, b)
+cd := c.data
+print(cd[0])
+print(cd[1])
+print(cd[2])
+print(cd[3])
+cs := c.shape
+print(cs[0])
+print(math.dot(a, a))
+t := math.transpose(a)
+td := t.data
+print(td[1])
+ts := t.shape
+print(ts[0])
+twice := math.add(a, a)
+sd := twice.data
+print(sd[5])
+diff := math.sub(a, math.mul_scalar(a, 2))
+dd := diff.data
+print(dd[2])
+v := math.Tensor([1, 2, 3, 4, 5], [5])
+print(math.dot(v, math.mul_scalar(v, 3)))
+"#;
+        let ast = parser::parse_source(source, "test.ark").expect("parse failed");
+        for simd in [false, true] {
+            let wasm = WasmCodegen::with_options(WasmCodegenOptions {
+                simd,
+                ..WasmCodegenOptions::default()
+            })
+            .expect("valid options")
+            .compile(&ast)
+            .expect("compile failed");
+            let output = run_wasm(&wasm).expect("run failed");
+            let lines: Vec<&str> = output.stdout.lines().collect();
+            assert_eq!(
+                lines,
+                vec![
+                    "58", "64", "139", "154", "2", "91", "4", "3", "12", "-3", "165"
+                ],
+                "simd: {}",
+                simd
+            );
+        }
+    }
+
+    #[test]
+    fn test_e2e_crypto_digests_via_host_imports() {
+        let source = r#"
+print(sys.crypto.sha256("abc"))
+print(sys.crypto.keccak256(""))
+print(sys.crypto.blake3("abc"))
+fox := "The quick brown fox jumps over the lazy dog"
+print(sys.crypto.keccak256(fox))
+"#;
+        let wasm = compile_ark(source);
+        let output = run_wasm(&wasm).expect("run failed");
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+                "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+                "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+                "4d741b6f1eb29cb2a9b9911c82f56fa8d73b04959d3d9d222895df6c0b28aa15",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_e2e_higher_order_function_calls() {
+        // Named functions passed as values are called via call_indirect
+        let source = r#"
+func double(x) {
+    return x * 2
+}
+func inc(x) {
+    return x + 1
+}
+func apply_twice(f, x) {
+    return f(f(x))
+}
+func map_sum(f, xs) {
+    total := 0
+    for x in xs {
+        total := total + f(x)
+    }
+    return total
+}
+print(apply_twice(double, 5))
+print(map_sum(inc, [1, 2, 3]))
+g := double
+print(g(21))
+"#;
+        let wasm = compile_ark(source);
+        let output = run_wasm(&wasm).expect("run failed");
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(lines, vec!["20", "9", "42"]);
+    }
+
     #[test]
     fn test_e2e_boolean_logic() {
         // Test boolean expression: 10 > 5 should print 1 (true)
@@ -772,4 +1815,900 @@ print(0)
         let result = call_exported(&wasm, "square", &[7]).expect("call failed");
         assert_eq!(result, Some(49), "square(7) should return 49");
     }
+
+    #[test]
+    fn test_e2e_set_field_on_list_element() {
+        let source = r#"
+p := {x: 1}
+xs := [p]
+xs[0].x := 42
+print(xs[0].x)
+print(p.x)
+"#;
+        let wasm = compile_ark(source);
+        let output = run_wasm(&wasm).expect("run failed");
+        assert_eq!(
+            output.stdout, "42\n42\n",
+            "field store through list element should be visible via both paths"
+        );
+    }
+
+    #[test]
+    fn test_e2e_type_of_list_and_struct() {
+        // kind_of's parameter has no static type, so it exercises the
+        // runtime heap tag; the direct calls resolve statically.
+        let source = r#"
+func kind_of(v) {
+    return sys.type_of(v)
+}
+func check() {
+    xs := [1, 2, 3]
+    p := {x: 1}
+    a := string_eq(sys.type_of(xs), "list")
+    b := string_eq(sys.type_of(p), "struct")
+    c := string_eq(kind_of(xs), "list")
+    d := string_eq(kind_of(p), "struct")
+    e := string_eq(kind_of(7), "integer")
+    return a + b + c + d + e
+}
+print(0)
+"#;
+        let wasm = compile_ark(source);
+        let result = call_exported(&wasm, "check", &[]).expect("call failed");
+        assert_eq!(result, Some(5));
+    }
+
+    #[test]
+    fn test_e2e_struct_destructure_by_field() {
+        // Names are listed out of declaration order; binding is by field name.
+        let source = r#"
+func check() {
+    point := {x: 3, y: 40}
+    let {y, x} := point
+    return x * 100 + y
+}
+print(0)
+"#;
+        let wasm = compile_ark(source);
+        let result = call_exported(&wasm, "check", &[]).expect("call failed");
+        assert_eq!(result, Some(340));
+    }
+
+    #[test]
+    fn test_e2e_struct_init_zero_fills_omitted_fields() {
+        let source = r#"
+class Pair {
+    first
+    second
+}
+func check() {
+    let p: Pair := {first: 7}
+    return p.first * 100 + p.second
+}
+print(0)
+"#;
+        let wasm = compile_ark(source);
+        let result = call_exported(&wasm, "check", &[]).expect("call failed");
+        assert_eq!(result, Some(700));
+    }
+
+    #[test]
+    fn test_e2e_field_offsets_follow_struct_decl() {
+        // Typed parameters take their layout from the declaration; accesses
+        // through a list element fall back to the declared field position.
+        let source = r#"
+class Point {
+    x
+    y
+    z
+}
+func sum_yz(p: Point) {
+    return p.y * 10 + p.z
+}
+func bump(p: Point) {
+    p.z := p.z + 5
+    return p.z
+}
+func check() {
+    let q: Point := {z: 3, y: 2, x: 1}
+    pts := [q]
+    pts[0].y := 7
+    return pts[0].x * 10000 + sum_yz(q) * 100 + bump(q)
+}
+print(0)
+"#;
+        let wasm = compile_ark(source);
+        let result = call_exported(&wasm, "check", &[]).expect("call failed");
+        assert_eq!(result, Some(10000 + 7300 + 8));
+    }
+
+    #[test]
+    fn test_e2e_alloc_rejects_negative_size() {
+        let source = r#"
+func check() {
+    xs := []
+    return len(xs)
+}
+print(0)
+"#;
+        let wasm = compile_ark(source);
+        assert_eq!(
+            call_exported(&wasm, "check", &[]).expect("call failed"),
+            Some(0)
+        );
+        assert!(call_exported(&wasm, "__alloc", &[8]).is_ok());
+
+        // Fault injection: a negative size must trap, not wrap the heap pointer
+        let err = call_exported(&wasm, "__alloc", &[-16]).expect_err("negative size must trap");
+        assert!(err.message.contains("trapped"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_e2e_free_lists_reuse_blocks() {
+        let source = r#"
+func reuse() {
+    a := __alloc(24)
+    __free(a)
+    b := __alloc(20)
+    big := __alloc(1000)
+    __free(big)
+    c := __alloc(600)
+    return (b - a) + (c - big)
+}
+func temporaries() {
+    xs := list.append(list.append([1], 2), 3)
+    a := __alloc(24)
+    b := __alloc(32)
+    c := __alloc(24)
+    score := 0
+    if a < xs {
+        score := score + 1
+    }
+    if b < xs {
+        score := score + 10
+    }
+    if c > xs {
+        score := score + 100
+    }
+    return score
+}
+func grow() {
+    a := __alloc(200000)
+    b := __alloc(8)
+    return b - a
+}
+func exhaust() {
+    return __alloc(2000000)
+}
+print(0)
+"#;
+        let wasm = compile_ark(source);
+        let call = |name: &str| call_exported(&wasm, name, &[]);
+
+        // A freed block is handed out again for a request of its size class
+        assert_eq!(call("reuse").expect("call failed"), Some(0));
+        // The intermediate lists of the nested append were released: [1]
+        // and [1, 2] come back for same-sized requests, a third is fresh
+        assert_eq!(call("temporaries").expect("call failed"), Some(111));
+        // Memory grows past the first page on demand, up to the maximum
+        assert_eq!(call("grow").expect("call failed"), Some(200008));
+        let err = call("exhaust").expect_err("allocation past the memory maximum must trap");
+        assert!(err.message.contains("trapped"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_e2e_memory_limits_follow_options() {
+        use crate::wasm_codegen::WasmCodegenOptions;
+
+        let source = r#"
+func big() {
+    a := __alloc(2000000)
+    b := __alloc(8)
+    return b - a
+}
+func medium() {
+    return __alloc(200000)
+}
+print(0)
+"#;
+        let ast = parser::parse_source(source, "test.ark").expect("parse failed");
+        let compile = |options| {
+            WasmCodegen::with_options(options)
+                .expect("valid options")
+                .compile(&ast)
+                .expect("compile failed")
+        };
+
+        // Unbounded: __alloc keeps growing memory past the default 16 pages
+        let unbounded = compile(WasmCodegenOptions {
+            initial_memory_pages: 1,
+            max_memory_pages: None,
+            ..WasmCodegenOptions::default()
+        });
+        assert_eq!(
+            call_exported(&unbounded, "big", &[]).expect("call failed"),
+            Some(2000008)
+        );
+
+        // A tighter maximum traps where the default would have grown
+        let tight = compile(WasmCodegenOptions {
+            initial_memory_pages: 1,
+            max_memory_pages: Some(2),
+            ..WasmCodegenOptions::default()
+        });
+        let err = call_exported(&tight, "medium", &[]).expect_err("must trap past 2 pages");
+        assert!(err.message.contains("trapped"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_e2e_fuel_interrupts_loops_and_recursion() {
+        use crate::wasm_codegen::WasmCodegenOptions;
+
+        let source = r#"
+func count(n) {
+    i := 0
+    while i < n {
+        i := i + 1
+    }
+    return i
+}
+func fact(n) {
+    if n <= 1 {
+        return 1
+    }
+    return n * fact(n - 1)
+}
+print(0)
+"#;
+        let ast = parser::parse_source(source, "test.ark").expect("parse failed");
+        let metered = WasmCodegen::with_options(WasmCodegenOptions {
+            fuel: Some(100),
+            ..WasmCodegenOptions::default()
+        })
+        .expect("valid options")
+        .compile(&ast)
+        .expect("compile failed");
+
+        assert_eq!(
+            call_exported(&metered, "count", &[10]).expect("call failed"),
+            Some(10)
+        );
+        assert_eq!(
+            call_exported(&metered, "fact", &[5]).expect("call failed"),
+            Some(120)
+        );
+        for (name, arg) in [("count", 1000), ("fact", 500)] {
+            let err = call_exported(&metered, name, &[arg]).expect_err("must run out of fuel");
+            assert!(err.message.contains("[OutOfGas]"), "{}", err.message);
+        }
+        // `_start` has no loops or calls, so spends nothing
+        assert_eq!(
+            read_exported_global(&metered, FUEL_GLOBAL).expect("read failed"),
+            100
+        );
+
+        let unmetered = WasmCodegen::new().compile(&ast).expect("compile failed");
+        assert_eq!(
+            call_exported(&unmetered, "count", &[1000]).expect("call failed"),
+            Some(1000)
+        );
+        assert!(read_exported_global(&unmetered, FUEL_GLOBAL).is_err());
+    }
+
+    #[test]
+    fn test_e2e_fs_write_append_read() {
+        let source = r#"
+sys.fs.write("notes.txt", "hello")
+sys.fs.append("notes.txt", " world")
+print(sys.fs.read("notes.txt"))
+sys.fs.write("notes.txt", "fresh")
+print(sys.fs.read("notes.txt"))
+s := "abcdefgh"
+i := 0
+while i < 10 {
+    s := string_concat(s, s)
+    i := i + 1
+}
+sys.fs.write("big.txt", s)
+print(string_len(sys.fs.read("big.txt")))
+"#;
+        let wasm = compile_ark(source);
+        let output = run_wasm(&wasm).expect("run failed");
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(
+            lines,
+            ["hello world", "fresh", "8192"],
+            "stdout: {:?}",
+            output.stdout
+        );
+
+        // Reading a file that does not exist traps
+        let wasm = compile_ark("print(sys.fs.read(\"missing.txt\"))");
+        let err = run_wasm(&wasm).expect_err("missing file must trap");
+        assert!(err.message.contains("trapped"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_e2e_string_utf8_intrinsics() {
+        let source = r#"
+s := "héllo, мир"
+print(string_len(s))
+print(string_char_len(s))
+print(string_char_at(s, 1))
+print(string_slice_chars(s, 7, 10))
+print(string_to_upper(s))
+print(string_to_lower("ÀÉÎ ΑΒΓ Ё x×"))
+"#;
+        let wasm = compile_ark(source);
+        let output = run_wasm(&wasm).expect("run failed");
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(
+            lines,
+            ["14", "10", "é", "мир", "HÉLLO, МИР", "àéî αβγ ё x×"],
+            "stdout: {:?}",
+            output.stdout
+        );
+    }
+
+    #[test]
+    fn test_e2e_string_char_at_out_of_range_traps() {
+        let wasm = compile_ark("print(string_char_at(\"мир\", 3))");
+        let err = run_wasm(&wasm).expect_err("index past the end must trap");
+        assert!(err.message.contains("trapped"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_e2e_fs_streaming_read() {
+        let source = r#"
+sys.fs.write("log.txt", "hello, world")
+f := sys.fs.open("log.txt")
+let (chunk, f) := sys.fs.read_chunk(f, 5)
+print(string_concat("> ", chunk))
+let (chunk, f) := sys.fs.read_chunk(f, 100)
+print(string_concat("> ", chunk))
+let (chunk, f) := sys.fs.read_chunk(f, 100)
+print(string_len(chunk))
+f := sys.fs.seek(f, 7)
+let (chunk, f) := sys.fs.read_chunk(f, 3)
+print(string_concat("> ", chunk))
+sys.fs.close(f)
+"#;
+        let wasm = compile_ark(source);
+        let output = run_wasm(&wasm).expect("run failed");
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(
+            lines,
+            ["> hello", "> , world", "0", "> wor"],
+            "stdout: {:?}",
+            output.stdout
+        );
+    }
+
+    #[test]
+    fn test_e2e_result_propagation() {
+        let source = r#"
+func half(n) {
+    if n % 2 == 1 {
+        return Err(n)
+    }
+    return Ok(n / 2)
+}
+func quarter(n) {
+    h := half(n)?
+    return Ok(half(h)?)
+}
+for n in [8, 6, 3] {
+    r := quarter(n)
+    match r {
+        Ok(q) => print(q),
+        Err(odd) => print(0 - odd)
+    }
+}
+"#;
+        let wasm = compile_ark(source);
+        let output = run_wasm(&wasm).expect("run failed");
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(lines, ["2", "-3", "-3"], "stdout: {:?}", output.stdout);
+    }
+
+    #[test]
+    fn test_e2e_enum_and_dense_integer_matches() {
+        use crate::ast::{ArkNode, EnumDecl, EnumVariantDef, Expression, Pattern, Statement};
+        use crate::types::ArkType;
+
+        let var = |name: &str| Expression::Variable(name.to_string());
+        let call = |f: &str, args: Vec<Expression>| Expression::Call {
+            function_hash: f.to_string(),
+            args,
+        };
+        let shape = |variant: &str, args: Vec<Expression>| Expression::EnumInit {
+            enum_name: "Shape".to_string(),
+            variant: variant.to_string(),
+            args,
+        };
+        let shape_arm = |variant: &str, bindings: &[&str], body: Expression| {
+            (
+                Pattern::EnumVariant {
+                    enum_name: "Shape".to_string(),
+                    variant: variant.to_string(),
+                    bindings: bindings.iter().map(|b| b.to_string()).collect(),
+                },
+                body,
+            )
+        };
+        let literal_arm = |value: i64| {
+            (
+                Pattern::Literal(value.to_string()),
+                Expression::Integer(value + 10),
+            )
+        };
+        let print_each = |variable: &str, values: Vec<Expression>, arms| Statement::For {
+            variable: variable.to_string(),
+            iterable: Expression::List(values),
+            body: vec![Statement::Expression(call(
+                "print",
+                vec![Expression::Match {
+                    scrutinee: Box::new(var(variable)),
+                    arms,
+                }],
+            ))],
+            label: None,
+        };
+
+        // enum Shape { Circle(Int), Rect(Int, Int), Empty }
+        // for s in [Circle(3), Rect(3, 4), Empty, Rect(5, 6)] {
+        //     print(match s { Circle(r) => r * r * 3, Rect(w, h) => w * h, Empty => 0 })
+        // }
+        // for i in [-2, 0, 1, 2, 3, 9] {
+        //     print(match i { 0 => 10, 1 => 11, 3 => 13, -2 => 8, other => other })
+        // }
+        let program = ArkNode::Statement(Statement::Block(vec![
+            Statement::EnumDecl(EnumDecl {
+                name: "Shape".to_string(),
+                variants: vec![
+                    EnumVariantDef {
+                        name: "Circle".to_string(),
+                        fields: vec![ArkType::Integer],
+                    },
+                    EnumVariantDef {
+                        name: "Rect".to_string(),
+                        fields: vec![ArkType::Integer, ArkType::Integer],
+                    },
+                    EnumVariantDef {
+                        name: "Empty".to_string(),
+                        fields: vec![],
+                    },
+                ],
+            }),
+            print_each(
+                "s",
+                vec![
+                    shape("Circle", vec![Expression::Integer(3)]),
+                    shape("Rect", vec![Expression::Integer(3), Expression::Integer(4)]),
+                    shape("Empty", vec![]),
+                    shape("Rect", vec![Expression::Integer(5), Expression::Integer(6)]),
+                ],
+                vec![
+                    shape_arm(
+                        "Circle",
+                        &["r"],
+                        call(
+                            "mul",
+                            vec![
+                                call("mul", vec![var("r"), var("r")]),
+                                Expression::Integer(3),
+                            ],
+                        ),
+                    ),
+                    shape_arm("Rect", &["w", "h"], call("mul", vec![var("w"), var("h")])),
+                    shape_arm("Empty", &[], Expression::Integer(0)),
+                ],
+            ),
+            print_each(
+                "i",
+                [-2, 0, 1, 2, 3, 9].map(Expression::Integer).to_vec(),
+                vec![
+                    literal_arm(0),
+                    literal_arm(1),
+                    literal_arm(3),
+                    literal_arm(-2),
+                    (Pattern::Variable("other".to_string()), var("other")),
+                ],
+            ),
+        ]));
+
+        let wasm = WasmCodegen::compile_to_bytes(&program).expect("compile failed");
+        let output = run_wasm(&wasm).expect("run failed");
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(
+            lines,
+            ["27", "12", "0", "30", "8", "10", "11", "2", "13", "9"],
+            "stdout: {:?}",
+            output.stdout
+        );
+    }
+
+    #[test]
+    fn test_e2e_args_and_env() {
+        let source = r#"
+print(sys.args.count())
+print(sys.args.get(0))
+print(sys.args.get(2))
+print(sys.env.get("HOME"))
+print(string_len(sys.env.get("HOM")))
+print(string_len(sys.env.get("MISSING")))
+"#;
+        let state = || HostState {
+            args: vec!["prog.wasm".into(), "--fast".into(), "input.txt".into()],
+            env: vec![
+                ("HOMER".into(), "simpson".into()),
+                ("HOME".into(), "/home/ark".into()),
+            ],
+            ..HostState::default()
+        };
+        let wasm = compile_ark(source);
+        let output = run_wasm_with_state(&wasm, state()).expect("run failed");
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(
+            lines,
+            ["3", "prog.wasm", "input.txt", "/home/ark", "0", "0"],
+            "stdout: {:?}",
+            output.stdout
+        );
+
+        // An index past the last argument traps
+        let wasm = compile_ark("print(sys.args.get(3))");
+        let err = run_wasm_with_state(&wasm, state()).expect_err("index 3 must trap");
+        assert!(err.message.contains("trapped"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_e2e_wasi_preview2_matches_preview1() {
+        use crate::wasm_codegen::{WasiTarget, WasmCodegenOptions};
+
+        // 8 KiB of text takes the shim more than one blocking write
+        let source = r#"
+s := "abcdefgh"
+i := 0
+while i < 10 {
+    s := string_concat(s, s)
+    i := i + 1
+}
+print(string_len(s))
+print(s)
+print(sys.time.now())
+print(sys.random.int(1, 1000000))
+sys.log("warn", "careful")
+"#;
+        let ast = parser::parse_source(source, "test.ark").expect("parse failed");
+        let run = |wasi_target| {
+            let wasm = WasmCodegen::with_options(WasmCodegenOptions {
+                wasi_target,
+                ..WasmCodegenOptions::default()
+            })
+            .expect("valid options")
+            .compile(&ast)
+            .expect("compile failed");
+            run_wasm(&wasm).expect("run failed")
+        };
+
+        let preview1 = run(WasiTarget::Preview1);
+        let preview2 = run(WasiTarget::Preview2);
+        assert!(
+            preview1.stdout.starts_with("8192\n"),
+            "{:?}",
+            preview1.stdout
+        );
+        assert_eq!(preview2.stdout, preview1.stdout);
+        assert_eq!(preview2.stderr, preview1.stderr);
+        assert_eq!(preview2.stderr, "[WARN] careful\n");
+    }
+
+    #[test]
+    fn test_e2e_self_tail_calls_run_in_constant_stack() {
+        let source = r#"
+func count(n, acc) {
+    if n == 0 {
+        return acc
+    }
+    return count(n - 1, acc + 1)
+}
+func swap_sub(a, b) {
+    if a <= 0 {
+        return b
+    }
+    return swap_sub(b - 1, a)
+}
+func fact(n) {
+    if n <= 1 {
+        return 1
+    }
+    return n * fact(n - 1)
+}
+print(0)
+"#;
+        let wasm = compile_ark(source);
+        // A million frames would exhaust the stack as real calls
+        assert_eq!(
+            call_exported(&wasm, "count", &[1_000_000, 0]).expect("call failed"),
+            Some(1_000_000)
+        );
+        // Arguments are all evaluated before any parameter is rebound
+        assert_eq!(
+            call_exported(&wasm, "swap_sub", &[3, 10]).expect("call failed"),
+            Some(7)
+        );
+        // Non-tail recursion still goes through ordinary calls
+        assert_eq!(
+            call_exported(&wasm, "fact", &[10]).expect("call failed"),
+            Some(3_628_800)
+        );
+    }
+
+    #[test]
+    fn test_e2e_destructure_short_list_traps() {
+        let source = r#"
+func sum3(xs) {
+    let [a, b, c] := xs
+    return a + b + c
+}
+func short() {
+    return sum3([1])
+}
+func exact() {
+    return sum3([1, 2, 3])
+}
+print(0)
+"#;
+        let wasm = compile_ark(source);
+        assert_eq!(
+            call_exported(&wasm, "exact", &[]).expect("call failed"),
+            Some(6)
+        );
+
+        let err = call_exported(&wasm, "short", &[]).expect_err("short list must trap");
+        assert!(err.message.contains("trapped"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_boolean_conditions_agree_across_backends() {
+        use crate::compiler::Compiler;
+        use crate::runtime::Value;
+        use crate::vm::VM;
+
+        let source = r#"
+func check() {
+    score := 0
+    if true {
+        score := score + 1
+    }
+    if false {
+        score := score + 1000
+    }
+    if 5 > 3 {
+        score := score + 2
+    }
+    if 2 && 1 {
+        score := score + 4
+    }
+    if 0 || false {
+        score := score + 1000
+    }
+    n := 3
+    while n {
+        n := n - 1
+        score := score + 8
+    }
+    return score
+}
+print(0)
+"#;
+        let expected = 1 + 2 + 4 + 3 * 8;
+
+        let wasm = compile_ark(source);
+        let result = call_exported(&wasm, "check", &[]).expect("call failed");
+        assert_eq!(result, Some(expected));
+
+        // Bytecode interpreter: run the same program, ending with the call
+        let program = format!("{}check()\n", source);
+        let ast = parser::parse_source(&program, "test.ark").expect("parse failed");
+        let chunk = Compiler::new().compile(&ast);
+        let mut vm = VM::new(chunk, "test", 0).expect("vm init failed");
+        let value = vm.run().expect("vm run failed");
+        assert_eq!(value, Value::Integer(expected));
+    }
+
+    #[test]
+    fn test_e2e_rounding_midpoints() {
+        let source = r#"
+func fl(x) {
+    return math.floor(x)
+}
+func ce(x) {
+    return math.ceil(x)
+}
+func tr(x) {
+    return math.trunc(x)
+}
+func ro(x) {
+    return math.round(x)
+}
+print(0)
+"#;
+        let wasm = compile_ark(source);
+        let call = |name: &str, x: f64| {
+            call_exported(&wasm, name, &[x.to_bits() as i64]).expect("call failed")
+        };
+        assert_eq!(call("fl", 2.5), Some(2));
+        assert_eq!(call("fl", -2.5), Some(-3));
+        assert_eq!(call("ce", 2.5), Some(3));
+        assert_eq!(call("ce", -2.5), Some(-2));
+        assert_eq!(call("tr", -2.5), Some(-2));
+        // Half away from zero, like the interpreter (not F64Nearest's 2 / -2)
+        assert_eq!(call("ro", 2.5), Some(3));
+        assert_eq!(call("ro", -2.5), Some(-3));
+        assert_eq!(call("ro", 2.4), Some(2));
+        assert_eq!(call("ro", -2.6), Some(-3));
+    }
+
+    #[test]
+    fn test_e2e_float_arithmetic_and_math() {
+        let source = r#"
+func area(r: Float) => Float {
+    return r * r * 3
+}
+let half := 0.5
+print(half + 0.25)
+print(10 / 2.5)
+print(0 - 2.5)
+print(math.sqrt(2))
+print(math.sin(1.5))
+print(area(2))
+if 0.1 + 0.2 > 0.3 {
+    print("gt")
+}
+print(math.floor(2.75))
+print(7 / 2)
+"#;
+        let wasm = compile_ark(source);
+        let output = run_wasm(&wasm).expect("run failed");
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "0.75", "4.0", "-2.5", "1.414214", "0.997495", "12.0", "gt", "2", "3"
+            ]
+        );
+
+        // A Float result comes back as f64 bits
+        let bits = call_exported(&wasm, "area", &[1.5f64.to_bits() as i64])
+            .expect("call failed")
+            .expect("area returns a value");
+        assert_eq!(f64::from_bits(bits as u64), 6.75);
+    }
+
+    #[test]
+    fn test_e2e_typed_float_signatures() {
+        let source = r#"
+func scale(x: Float, n) => Float {
+    if n == 0 {
+        return x
+    }
+    return scale(x * 2, n - 1)
+}
+func apply(f, v) {
+    return f(v, 1)
+}
+func show(x: Float) {
+    print(x)
+}
+print(scale(1.5, 2))
+show(apply(scale, 0.25))
+"#;
+        let wasm = compile_ark(source);
+        let output = run_wasm(&wasm).expect("run failed");
+        let lines: Vec<&str> = output.stdout.lines().collect();
+        assert_eq!(lines, vec!["6.0", "0.5"]);
+
+        // `scale` itself takes and returns real f64s
+        let engine = wasmtime::Engine::default();
+        let module = wasmtime::Module::from_binary(&engine, &wasm).expect("valid module");
+        let ty = module
+            .get_export("scale")
+            .and_then(|export| export.func().cloned())
+            .expect("scale is exported");
+        let params: Vec<bool> = ty
+            .params()
+            .map(|param| matches!(param, wasmtime::ValType::F64))
+            .collect();
+        assert_eq!(params, vec![true, false]);
+        assert!(
+            ty.results()
+                .all(|result| matches!(result, wasmtime::ValType::F64))
+        );
+
+        let bits = call_exported(&wasm, "scale", &[1.25f64.to_bits() as i64, 3])
+            .expect("call failed")
+            .expect("scale returns a value");
+        assert_eq!(f64::from_bits(bits as u64), 10.0);
+    }
+
+    #[test]
+    fn test_e2e_custom_host_import() {
+        use wasm_encoder::ValType;
+
+        let source = r#"
+func run(x) {
+    return host_triple(x) + 1
+}
+print(0)
+"#;
+        let ast = parser::parse_source(source, "test.ark").expect("parse failed");
+        let mut codegen = WasmCodegen::new();
+        codegen
+            .register_host_import(
+                "my_host",
+                "triple",
+                vec![ValType::I64],
+                vec![ValType::I64],
+                "host_triple",
+            )
+            .expect("register failed");
+        let wasm = codegen.compile(&ast).expect("compile failed");
+
+        let engine = Engine::default();
+        let module = Module::from_binary(&engine, &wasm).expect("load failed");
+        let mut linker = Linker::<HostState>::new(&engine);
+        link_wasi_fd_write(&mut linker).expect("link fd_write");
+        link_wasi_stubs(&mut linker).expect("link stubs");
+        crate::wasm_host_imports::link_ark_host_imports(&mut linker).expect("link ark_host");
+        linker
+            .func_wrap("my_host", "triple", |x: i64| x * 3)
+            .expect("link custom import");
+
+        let mut store = Store::new(&engine, HostState::default());
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .expect("instantiate failed");
+        let run = instance
+            .get_typed_func::<i64, i64>(&mut store, "run")
+            .expect("run export");
+        assert_eq!(run.call(&mut store, 14).expect("call failed"), 43);
+    }
+
+    #[test]
+    fn test_register_host_import_rejects_multi_value() {
+        use wasm_encoder::ValType;
+
+        let mut codegen = WasmCodegen::new();
+        let err = codegen
+            .register_host_import(
+                "my_host",
+                "pair",
+                vec![],
+                vec![ValType::I64, ValType::I64],
+                "host_pair",
+            )
+            .expect_err("multi-value results should be rejected");
+        assert!(err.message.contains("at most one value"));
+    }
+
+    #[test]
+    fn test_signed_modules_run_and_tampered_ones_are_refused() {
+        use ed25519_dalek::SigningKey;
+        use rand::rngs::OsRng;
+
+        let key = SigningKey::generate(&mut OsRng);
+        let signed =
+            crate::artifact::sign_wasm(&compile_ark("print(6 * 7)"), &key).expect("sign failed");
+        let output = run_wasm(&signed).expect("run failed");
+        assert!(output.stdout.contains("42"), "got: {}", output.stdout);
+
+        // Still a valid module, but no longer the one that was signed
+        let mut tampered = signed;
+        tampered.extend_from_slice(&[0, 2, 1, b'x']);
+        let err = run_wasm(&tampered).expect_err("tampered module ran");
+        assert_eq!(err.context, "run_wasm::verify");
+        assert!(err.message.contains("content hash mismatch"), "{}", err);
+    }
 }
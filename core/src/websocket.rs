@@ -0,0 +1,393 @@
+/*
+ * Copyright (c) 2026 Mohamad Al-Zawahreh (dba Sovereign Systems).
+ *
+ * WebSocket (RFC 6455) protocol for the `net.ws.*` intrinsics.
+ *
+ * The intrinsics own the sockets (see `SocketResource` in intrinsics.rs);
+ * this module only speaks the protocol over any `Read + Write` stream:
+ *   - the opening handshake, client and server side
+ *   - frame encoding, masking every client frame
+ *   - message reassembly across continuation frames, answering pings
+ *
+ * LICENSE: DUAL-LICENSED (AGPLv3 or COMMERCIAL).
+ */
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use sha1::{Digest, Sha1};
+use std::io::{self, Read, Write};
+
+/// Appended to the client's key to derive `Sec-WebSocket-Accept`.
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Largest HTTP head accepted during the opening handshake.
+const MAX_HANDSHAKE_BYTES: usize = 16 * 1024;
+
+/// Largest message `read_message` will buffer, fragments included.
+pub const MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+
+pub const OP_CONTINUATION: u8 = 0x0;
+pub const OP_TEXT: u8 = 0x1;
+pub const OP_BINARY: u8 = 0x2;
+pub const OP_CLOSE: u8 = 0x8;
+pub const OP_PING: u8 = 0x9;
+pub const OP_PONG: u8 = 0xA;
+
+/// A complete message from the peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    /// The peer closed the connection; the close has been acknowledged.
+    Close,
+}
+
+/// A single frame as read off the wire, already unmasked.
+struct Frame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// The `Sec-WebSocket-Accept` value answering `key`.
+pub fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(HANDSHAKE_GUID.as_bytes());
+    BASE64.encode(hasher.finalize())
+}
+
+/// Read an HTTP head up to and including the blank line. Reads a byte at a
+/// time so no frame data after the head is consumed.
+fn read_http_head<S: Read + ?Sized>(stream: &mut S) -> io::Result<String> {
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() > MAX_HANDSHAKE_BYTES {
+            return Err(invalid("handshake headers too large"));
+        }
+        if stream.read(&mut byte)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "connection closed during the handshake",
+            ));
+        }
+        head.push(byte[0]);
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+/// Value of header `name` (case-insensitive) in an HTTP head.
+fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.split("\r\n")
+        .skip(1)
+        .filter_map(|line| line.split_once(':'))
+        .find(|(n, _)| n.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+}
+
+/// Client side of the opening handshake: ask `host` to upgrade `path` and
+/// check that the server's accept key answers ours.
+pub fn client_handshake<S: Read + Write + ?Sized>(
+    stream: &mut S,
+    host: &str,
+    path: &str,
+) -> io::Result<()> {
+    let key = BASE64.encode(rand::random::<[u8; 16]>());
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\r\n",
+        path, host, key
+    )?;
+    stream.flush()?;
+
+    let head = read_http_head(stream)?;
+    if head.split_whitespace().nth(1) != Some("101") {
+        return Err(invalid(format!(
+            "server refused the upgrade: {}",
+            head.lines().next().unwrap_or_default()
+        )));
+    }
+    if header(&head, "Sec-WebSocket-Accept") != Some(accept_key(&key).as_str()) {
+        return Err(invalid("server sent a wrong Sec-WebSocket-Accept"));
+    }
+    Ok(())
+}
+
+/// Server side of the opening handshake: read the client's upgrade request
+/// and switch protocols. A request that is not an upgrade gets a 400.
+/// Returns the requested path.
+pub fn server_handshake<S: Read + Write + ?Sized>(stream: &mut S) -> io::Result<String> {
+    let head = read_http_head(stream)?;
+    let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or("/").to_string();
+    let is_upgrade = method == "GET"
+        && header(&head, "Upgrade").is_some_and(|v| v.eq_ignore_ascii_case("websocket"));
+
+    let Some(key) = header(&head, "Sec-WebSocket-Key").filter(|_| is_upgrade) else {
+        let _ = stream.write_all(
+            b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        );
+        return Err(invalid("not a WebSocket upgrade request"));
+    };
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(key)
+    )?;
+    stream.flush()?;
+    Ok(path)
+}
+
+/// Encode one final frame. Clients must mask every frame (RFC 6455 §5.3)
+/// and pass a `mask`; servers pass `None`.
+fn encode_frame(opcode: u8, payload: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 14);
+    frame.push(0x80 | opcode);
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        n if n < 126 => frame.push(mask_bit | n as u8),
+        n if n <= u16::MAX as usize => {
+            frame.push(mask_bit | 126);
+            frame.extend_from_slice(&(n as u16).to_be_bytes());
+        }
+        n => {
+            frame.push(mask_bit | 127);
+            frame.extend_from_slice(&(n as u64).to_be_bytes());
+        }
+    }
+    match mask {
+        Some(key) => {
+            frame.extend_from_slice(&key);
+            frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ key[i % 4]));
+        }
+        None => frame.extend_from_slice(payload),
+    }
+    frame
+}
+
+/// Send `payload` as a single frame, masked with a fresh key when `client`.
+pub fn write_message<S: Write + ?Sized>(
+    stream: &mut S,
+    opcode: u8,
+    payload: &[u8],
+    client: bool,
+) -> io::Result<()> {
+    let mask = client.then(rand::random::<[u8; 4]>);
+    stream.write_all(&encode_frame(opcode, payload, mask))?;
+    stream.flush()
+}
+
+/// Read one frame. Frames from a client must be masked and frames from a
+/// server must not be, so `from_client` is the peer's role.
+fn read_frame<S: Read + ?Sized>(stream: &mut S, from_client: bool) -> io::Result<Frame> {
+    let mut head = [0u8; 2];
+    stream.read_exact(&mut head)?;
+    if head[0] & 0x70 != 0 {
+        return Err(invalid("reserved frame bits are set"));
+    }
+    let masked = head[1] & 0x80 != 0;
+    if masked != from_client {
+        return Err(invalid(if from_client {
+            "client frame is not masked"
+        } else {
+            "server frame is masked"
+        }));
+    }
+    let len = match head[1] & 0x7F {
+        126 => {
+            let mut len = [0u8; 2];
+            stream.read_exact(&mut len)?;
+            u16::from_be_bytes(len) as u64
+        }
+        127 => {
+            let mut len = [0u8; 8];
+            stream.read_exact(&mut len)?;
+            u64::from_be_bytes(len)
+        }
+        n => n as u64,
+    };
+    if len > MAX_MESSAGE_BYTES as u64 {
+        return Err(invalid(format!("frame of {} bytes is too large", len)));
+    }
+
+    let mut key = [0u8; 4];
+    if masked {
+        stream.read_exact(&mut key)?;
+    }
+    let mut payload = vec![0u8; len as usize];
+    stream.read_exact(&mut payload)?;
+    if masked {
+        for (i, b) in payload.iter_mut().enumerate() {
+            *b ^= key[i % 4];
+        }
+    }
+    Ok(Frame {
+        fin: head[0] & 0x80 != 0,
+        opcode: head[0] & 0x0F,
+        payload,
+    })
+}
+
+/// Read the next text or binary message, joining its fragments. Pings are
+/// answered and pongs skipped along the way; a close frame is echoed back
+/// and reported as [`Message::Close`]. `client` is our own role.
+pub fn read_message<S: Read + Write + ?Sized>(stream: &mut S, client: bool) -> io::Result<Message> {
+    let mut message: Option<(u8, Vec<u8>)> = None;
+    loop {
+        let frame = read_frame(stream, !client)?;
+        match frame.opcode {
+            OP_PING => write_message(stream, OP_PONG, &frame.payload, client)?,
+            OP_PONG => {}
+            OP_CLOSE => {
+                // Echo the status code, if any, as the closing handshake
+                let code = &frame.payload[..frame.payload.len().min(2)];
+                let _ = write_message(stream, OP_CLOSE, code, client);
+                return Ok(Message::Close);
+            }
+            OP_TEXT | OP_BINARY if message.is_none() => {
+                message = Some((frame.opcode, frame.payload));
+            }
+            OP_TEXT | OP_BINARY => {
+                return Err(invalid("new message before the previous one finished"));
+            }
+            OP_CONTINUATION => match &mut message {
+                Some((_, data)) if data.len() + frame.payload.len() <= MAX_MESSAGE_BYTES => {
+                    data.extend_from_slice(&frame.payload);
+                }
+                Some(_) => return Err(invalid("message is too large")),
+                None => return Err(invalid("continuation frame without a message")),
+            },
+            other => return Err(invalid(format!("unknown opcode {:#x}", other))),
+        }
+
+        let is_control = frame.opcode & 0x8 != 0;
+        if frame.fin && !is_control {
+            return match message.take() {
+                Some((OP_TEXT, data)) => String::from_utf8(data)
+                    .map(Message::Text)
+                    .map_err(|_| invalid("text message is not valid UTF-8")),
+                Some((_, data)) => Ok(Message::Binary(data)),
+                None => Err(invalid("continuation frame without a message")),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A stream that reads from `input` and records what is written.
+    struct Duplex {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Duplex {
+        fn new(input: Vec<u8>) -> Self {
+            Duplex {
+                input: Cursor::new(input),
+                output: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for Duplex {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Duplex {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_accept_key_matches_rfc_example() {
+        // RFC 6455 §1.3
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhoRbYxzOo="
+        );
+    }
+
+    #[test]
+    fn test_frame_lengths_and_masking() {
+        let unmasked = encode_frame(OP_TEXT, b"Hello", None);
+        assert_eq!(unmasked, b"\x81\x05Hello");
+
+        // RFC 6455 §5.7: a masked "Hello"
+        let masked = encode_frame(OP_TEXT, b"Hello", Some([0x37, 0xfa, 0x21, 0x3d]));
+        assert_eq!(
+            masked,
+            [0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58]
+        );
+
+        let medium = encode_frame(OP_BINARY, &[7; 300], None);
+        assert_eq!(&medium[..4], &[0x82, 126, 0x01, 0x2c]);
+        let large = encode_frame(OP_BINARY, &[7; 70_000], None);
+        assert_eq!(&large[..2], &[0x82, 127]);
+        assert_eq!(u64::from_be_bytes(large[2..10].try_into().unwrap()), 70_000);
+    }
+
+    #[test]
+    fn test_read_message_joins_fragments_and_answers_ping() {
+        // Client-to-server frames: "Hel" + ping + "lo", then a close
+        let mask = Some([1, 2, 3, 4]);
+        let mut input = encode_frame(OP_TEXT, b"Hel", mask);
+        input[0] &= 0x7F; // not final
+        input.extend(encode_frame(OP_PING, b"hi", mask));
+        input.extend(encode_frame(OP_CONTINUATION, b"lo", mask));
+        input.extend(encode_frame(OP_CLOSE, &1000u16.to_be_bytes(), mask));
+
+        let mut stream = Duplex::new(input);
+        assert_eq!(
+            read_message(&mut stream, false).unwrap(),
+            Message::Text("Hello".to_string())
+        );
+        assert_eq!(read_message(&mut stream, false).unwrap(), Message::Close);
+
+        let mut replies = Vec::new();
+        replies.extend(encode_frame(OP_PONG, b"hi", None));
+        replies.extend(encode_frame(OP_CLOSE, &1000u16.to_be_bytes(), None));
+        assert_eq!(stream.output, replies);
+    }
+
+    #[test]
+    fn test_read_message_rejects_unmasked_client_frames() {
+        let mut stream = Duplex::new(encode_frame(OP_TEXT, b"hi", None));
+        assert!(read_message(&mut stream, false).is_err());
+    }
+
+    #[test]
+    fn test_handshake_round_trip() {
+        let request = "GET /chat HTTP/1.1\r\nHost: example.com\r\nUpgrade: websocket\r\n\
+                       Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                       Sec-WebSocket-Version: 13\r\n\r\n";
+        let mut stream = Duplex::new(request.as_bytes().to_vec());
+        assert_eq!(server_handshake(&mut stream).unwrap(), "/chat");
+        let response = String::from_utf8(stream.output).unwrap();
+        assert!(response.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+        assert!(response.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhoRbYxzOo=\r\n"));
+
+        let mut plain = Duplex::new(b"GET / HTTP/1.1\r\nHost: x\r\n\r\n".to_vec());
+        assert!(server_handshake(&mut plain).is_err());
+        assert!(String::from_utf8(plain.output)
+            .unwrap()
+            .starts_with("HTTP/1.1 400"));
+    }
+}
@@ -73,7 +73,7 @@ pub fn ark_type_to_wit(ty: &ArkType) -> String {
 }
 
 /// Convert a name to a valid WIT identifier (kebab-case).
-fn to_wit_ident(name: &str) -> String {
+pub(crate) fn to_wit_ident(name: &str) -> String {
     let mut result = String::new();
     for (i, c) in name.chars().enumerate() {
         if c.is_uppercase() && i > 0 {
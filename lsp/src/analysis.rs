@@ -0,0 +1,393 @@
+/*
+ * Copyright (c) 2026 Mohamad Al-Zawahreh (dba Sovereign Systems).
+ *
+ * This file is part of the Ark Sovereign Compiler.
+ *
+ * LICENSE: DUAL-LICENSED (AGPLv3 or COMMERCIAL).
+ *
+ * 1. OPEN SOURCE: You may use this file under the terms of the GNU Affero
+ * General Public License v3.0. If you link to this code, your ENTIRE
+ * application must be open-sourced under AGPLv3.
+ *
+ * 2. COMMERCIAL: For proprietary use, you must obtain a Commercial License
+ * from Sovereign Systems.
+ *
+ * PATENT NOTICE: Protected by US Patent App #63/935,467.
+ * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
+ */
+
+//! What the server knows about one open document: its diagnostics, the
+//! items it declares and the types the checker inferred for its bindings.
+//!
+//! Item locations come from the token stream, so symbols and go-to-definition
+//! keep working while the file has a syntax error. Positions are the
+//! compiler's: 1-based lines and columns, end column inclusive.
+
+use ark_0_zheng::ast::{self, ArkNode, Span, Statement, Visitor};
+use ark_0_zheng::checker::{BindingType, LinearChecker};
+use ark_0_zheng::diagnostics::Diagnostic;
+use ark_0_zheng::parser::{self, Lexer, Token, TokenKind};
+use ark_0_zheng::types::ArkType;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    Function,
+    Class,
+    Enum,
+    Trait,
+}
+
+/// A declared item.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// The whole item, from its keyword to its closing brace.
+    pub span: Span,
+    /// Just the name.
+    pub name_span: Span,
+    /// One-line summary, e.g. `func area(w: Int, h: Int) => Int`.
+    pub detail: String,
+}
+
+pub struct Document {
+    pub source: String,
+    pub diagnostics: Vec<Diagnostic>,
+    pub symbols: Vec<Symbol>,
+    identifiers: Vec<(String, Span)>,
+    bindings: Vec<BindingType>,
+}
+
+fn contains(span: &Span, line: u32, col: u32) -> bool {
+    (span.start_line, span.start_col) <= (line, col) && (line, col) <= (span.end_line, span.end_col)
+}
+
+fn show_type(name: &str, ty: &ArkType) -> String {
+    match ty {
+        ArkType::Any => name.to_string(),
+        ty => format!("{}: {}", name, ty),
+    }
+}
+
+fn signature(keyword: &str, name: &str, inputs: &[(String, ArkType)], output: &ArkType) -> String {
+    let params: Vec<String> = inputs.iter().map(|(n, ty)| show_type(n, ty)).collect();
+    match output {
+        ArkType::Any => format!("{} {}({})", keyword, name, params.join(", ")),
+        output => format!("{} {}({}) => {}", keyword, name, params.join(", "), output),
+    }
+}
+
+/// Hover text for every item the parser produced.
+fn item_details(node: &ArkNode) -> HashMap<(SymbolKind, String), String> {
+    struct Details(HashMap<(SymbolKind, String), String>);
+    impl Visitor for Details {
+        fn visit_function(&mut self, func: &ast::FunctionDef) {
+            self.0.insert(
+                (SymbolKind::Function, func.name.clone()),
+                signature("func", &func.name, &func.inputs, &func.output),
+            );
+            ast::walk_function(self, func);
+        }
+
+        fn visit_statement(&mut self, stmt: &Statement) {
+            match stmt {
+                Statement::StructDecl(decl) => {
+                    let fields: Vec<String> =
+                        decl.fields.iter().map(|(n, ty)| show_type(n, ty)).collect();
+                    self.0.insert(
+                        (SymbolKind::Class, decl.name.clone()),
+                        format!("class {} {{ {} }}", decl.name, fields.join(", ")),
+                    );
+                }
+                Statement::EnumDecl(decl) => {
+                    let variants: Vec<String> = decl
+                        .variants
+                        .iter()
+                        .map(|v| {
+                            if v.fields.is_empty() {
+                                v.name.clone()
+                            } else {
+                                let fields: Vec<String> =
+                                    v.fields.iter().map(|ty| ty.to_string()).collect();
+                                format!("{}({})", v.name, fields.join(", "))
+                            }
+                        })
+                        .collect();
+                    self.0.insert(
+                        (SymbolKind::Enum, decl.name.clone()),
+                        format!("enum {} {{ {} }}", decl.name, variants.join(", ")),
+                    );
+                }
+                Statement::TraitDecl(decl) => {
+                    let methods: Vec<String> = decl
+                        .methods
+                        .iter()
+                        .map(|m| signature("func", &m.name, &m.inputs, &m.output))
+                        .collect();
+                    self.0.insert(
+                        (SymbolKind::Trait, decl.name.clone()),
+                        format!("trait {} {{ {} }}", decl.name, methods.join("; ")),
+                    );
+                }
+                _ => {}
+            }
+            ast::walk_statement(self, stmt);
+        }
+    }
+    let mut details = Details(HashMap::new());
+    details.visit_node(node);
+    details.0
+}
+
+/// Find `func`/`class`/`enum`/`trait` items in the token stream. An item's
+/// extent runs to the brace closing its body; a trait method signature has
+/// no body and ends at its name.
+fn scan_items(tokens: &[Token], file: &str) -> Vec<(SymbolKind, String, Span, Span)> {
+    let span = |start: &Token, end_line: u32, end_col: u32| Span {
+        start_line: start.line,
+        start_col: start.col,
+        end_line,
+        end_col,
+        file: file.to_string(),
+    };
+    let mut items = Vec::new();
+    for (i, keyword) in tokens.iter().enumerate() {
+        let kind = match keyword.kind {
+            TokenKind::Func => SymbolKind::Function,
+            TokenKind::Class => SymbolKind::Class,
+            TokenKind::Enum => SymbolKind::Enum,
+            TokenKind::Trait => SymbolKind::Trait,
+            _ => continue,
+        };
+        // Anonymous functions have no name and are not items
+        let Some(name_tok) = tokens.get(i + 1) else {
+            continue;
+        };
+        let TokenKind::Identifier(name) = &name_tok.kind else {
+            continue;
+        };
+        let name_end = name_tok.col + name.chars().count() as u32 - 1;
+        let name_span = span(name_tok, name_tok.line, name_end);
+
+        let mut end = None;
+        let mut depth = 0;
+        for tok in &tokens[i + 2..] {
+            match tok.kind {
+                TokenKind::LBrace => depth += 1,
+                TokenKind::RBrace if depth == 0 => break,
+                TokenKind::RBrace => {
+                    depth -= 1;
+                    if depth == 0 {
+                        end = Some((tok.line, tok.col));
+                        break;
+                    }
+                }
+                TokenKind::Func | TokenKind::Class | TokenKind::Enum | TokenKind::Trait
+                    if depth == 0 =>
+                {
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let (end_line, end_col) = end.unwrap_or((name_tok.line, name_end));
+        items.push((
+            kind,
+            name.clone(),
+            span(keyword, end_line, end_col),
+            name_span,
+        ));
+    }
+    items
+}
+
+impl Document {
+    pub fn analyze(file: &str, source: &str) -> Self {
+        // A lexing error is reported by the parser below
+        let tokens = Lexer::new(source).tokenize().unwrap_or_default();
+        let identifiers = tokens
+            .iter()
+            .filter_map(|tok| match &tok.kind {
+                TokenKind::Identifier(name) => Some((
+                    name.clone(),
+                    Span {
+                        start_line: tok.line,
+                        start_col: tok.col,
+                        end_line: tok.line,
+                        end_col: tok.col + name.chars().count() as u32 - 1,
+                        file: file.to_string(),
+                    },
+                )),
+                _ => None,
+            })
+            .collect();
+
+        let (diagnostics, details, bindings) = match parser::parse_source_with_spans(source, file) {
+            Ok(node) => {
+                let diagnostics = match LinearChecker::check_diagnostics(&node) {
+                    Ok(found) => found.iter().map(|d| d.to_diagnostic()).collect(),
+                    Err(e) => vec![e.to_diagnostic()],
+                };
+                (
+                    diagnostics,
+                    item_details(&node),
+                    LinearChecker::binding_types(&node),
+                )
+            }
+            Err(e) => (vec![e.to_diagnostic()], HashMap::new(), Vec::new()),
+        };
+
+        let symbols = scan_items(&tokens, file)
+            .into_iter()
+            .map(|(kind, name, span, name_span)| {
+                let detail = details
+                    .get(&(kind, name.clone()))
+                    .cloned()
+                    .unwrap_or_else(|| match kind {
+                        SymbolKind::Function => format!("func {}", name),
+                        SymbolKind::Class => format!("class {}", name),
+                        SymbolKind::Enum => format!("enum {}", name),
+                        SymbolKind::Trait => format!("trait {}", name),
+                    });
+                Symbol {
+                    name,
+                    kind,
+                    span,
+                    name_span,
+                    detail,
+                }
+            })
+            .collect();
+
+        Document {
+            source: source.to_string(),
+            diagnostics,
+            symbols,
+            identifiers,
+            bindings,
+        }
+    }
+
+    /// The identifier token under a position.
+    fn identifier_at(&self, line: u32, col: u32) -> Option<&(String, Span)> {
+        self.identifiers
+            .iter()
+            .find(|(_, span)| contains(span, line, col))
+    }
+
+    /// Where the function, class, enum or trait named at a position is
+    /// declared.
+    pub fn definition(&self, line: u32, col: u32) -> Option<&Symbol> {
+        let (name, _) = self.identifier_at(line, col)?;
+        self.symbols.iter().find(|symbol| &symbol.name == name)
+    }
+
+    /// What to show for the identifier at a position, and its extent. A
+    /// variable shows its inferred type within the enclosing function; an
+    /// item shows its signature.
+    pub fn hover(&self, line: u32, col: u32) -> Option<(String, Span)> {
+        let (name, span) = self.identifier_at(line, col)?;
+        let function = self
+            .symbols
+            .iter()
+            .filter(|s| s.kind == SymbolKind::Function && contains(&s.span, line, col))
+            .max_by_key(|s| (s.span.start_line, s.span.start_col))
+            .map(|s| s.name.as_str());
+
+        // A name rebound to other types shows each of them
+        let mut types: Vec<String> = Vec::new();
+        for binding in &self.bindings {
+            let text = binding.to_string();
+            if &binding.name == name
+                && binding.function.as_deref() == function
+                && !types.contains(&text)
+            {
+                types.push(text);
+            }
+        }
+        if !types.is_empty() {
+            return Some((types.join("\n"), span.clone()));
+        }
+        self.symbols
+            .iter()
+            .find(|symbol| &symbol.name == name)
+            .map(|symbol| (symbol.detail.clone(), span.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+func area(w: Int, h: Int) => Int {
+    a := w * h
+    return a
+}
+
+enum Shape {
+    Square(Int),
+    Empty
+}
+
+class Point {
+    x
+    y
+}
+
+size := area(2, 3)
+";
+
+    #[test]
+    fn test_symbols_span_their_items() {
+        let doc = Document::analyze("app.ark", SOURCE);
+        assert!(doc.diagnostics.is_empty(), "{:?}", doc.diagnostics);
+        let summary: Vec<(&str, SymbolKind, u32, u32)> = doc
+            .symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.kind, s.span.start_line, s.span.end_line))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("area", SymbolKind::Function, 1, 4),
+                ("Shape", SymbolKind::Enum, 6, 9),
+                ("Point", SymbolKind::Class, 11, 14),
+            ]
+        );
+        assert_eq!(doc.symbols[0].detail, "func area(w: Int, h: Int) => Int");
+        assert_eq!(doc.symbols[1].detail, "enum Shape { Square(Int), Empty }");
+        assert_eq!(doc.symbols[0].name_span.start_col, 6);
+        assert_eq!(doc.symbols[0].name_span.end_col, 9);
+    }
+
+    #[test]
+    fn test_definition_and_hover() {
+        let doc = Document::analyze("app.ark", SOURCE);
+        // `area` in `size := area(2, 3)`
+        let target = doc.definition(16, 10).expect("definition");
+        assert_eq!(target.name, "area");
+        assert_eq!(target.name_span.start_line, 1);
+        assert!(doc.definition(2, 5).is_none(), "variables are not items");
+
+        let (text, span) = doc.hover(2, 5).expect("hover on a");
+        assert_eq!(text, "a: Int");
+        assert_eq!((span.start_col, span.end_col), (5, 5));
+        assert_eq!(doc.hover(16, 1).unwrap().0, "size: Int");
+        assert_eq!(
+            doc.hover(16, 9).unwrap().0,
+            "func area(w: Int, h: Int) => Int"
+        );
+        assert!(doc.hover(3, 1).is_none(), "whitespace");
+    }
+
+    #[test]
+    fn test_syntax_errors_keep_symbols() {
+        let doc = Document::analyze("app.ark", "func ok() {\n    return 1\n}\nfunc broken( {\n");
+        assert_eq!(doc.diagnostics.len(), 1);
+        assert!(doc.diagnostics[0].code.starts_with("E000"));
+        assert_eq!(doc.symbols[0].name, "ok");
+        assert_eq!(doc.symbols[0].detail, "func ok");
+    }
+}
@@ -0,0 +1,62 @@
+/*
+ * Copyright (c) 2026 Mohamad Al-Zawahreh (dba Sovereign Systems).
+ *
+ * This file is part of the Ark Sovereign Compiler.
+ *
+ * LICENSE: DUAL-LICENSED (AGPLv3 or COMMERCIAL).
+ *
+ * 1. OPEN SOURCE: You may use this file under the terms of the GNU Affero
+ * General Public License v3.0. If you link to this code, your ENTIRE
+ * application must be open-sourced under AGPLv3.
+ *
+ * 2. COMMERCIAL: For proprietary use, you must obtain a Commercial License
+ * from Sovereign Systems.
+ *
+ * PATENT NOTICE: Protected by US Patent App #63/935,467.
+ * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
+ */
+
+//! `ark-lsp` — Language Server Protocol frontend for Ark
+//!
+//! Speaks LSP over stdin/stdout. Provides:
+//!   - diagnostics from the parser and checker on open and change
+//!   - go-to-definition for functions, classes, enums and traits
+//!   - hover with the inferred type of variables and item signatures
+//!   - document symbols
+
+mod analysis;
+mod server;
+mod transport;
+
+use serde_json::Value;
+use server::Server;
+use std::io;
+use std::process;
+
+fn main() {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+    let mut server = Server::new();
+
+    loop {
+        let message = match transport::read_message(&mut input) {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("ark-lsp: {}", e);
+                process::exit(1);
+            }
+        };
+        if message.get("method").and_then(Value::as_str) == Some("exit") {
+            process::exit(if server.is_shut_down() { 0 } else { 1 });
+        }
+        for reply in server.handle(&message) {
+            if let Err(e) = transport::write_message(&mut output, &reply) {
+                eprintln!("ark-lsp: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+}
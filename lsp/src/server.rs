@@ -0,0 +1,341 @@
+/*
+ * Copyright (c) 2026 Mohamad Al-Zawahreh (dba Sovereign Systems).
+ *
+ * This file is part of the Ark Sovereign Compiler.
+ *
+ * LICENSE: DUAL-LICENSED (AGPLv3 or COMMERCIAL).
+ *
+ * 1. OPEN SOURCE: You may use this file under the terms of the GNU Affero
+ * General Public License v3.0. If you link to this code, your ENTIRE
+ * application must be open-sourced under AGPLv3.
+ *
+ * 2. COMMERCIAL: For proprietary use, you must obtain a Commercial License
+ * from Sovereign Systems.
+ *
+ * PATENT NOTICE: Protected by US Patent App #63/935,467.
+ * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
+ */
+
+//! Request dispatch: turns each client message into the replies and
+//! notifications to send back.
+//!
+//! Documents are synced in full on every change and re-analyzed then, which
+//! is also when their diagnostics are published. LSP positions are 0-based
+//! and exclusive at the end; the compiler's are 1-based and inclusive.
+//! Columns are counted in characters, which matches the client's UTF-16
+//! offsets everywhere outside the astral planes.
+
+use crate::analysis::{Document, SymbolKind};
+use ark_0_zheng::ast::Span;
+use ark_0_zheng::diagnostics::{Diagnostic, Severity};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shut_down: bool,
+}
+
+fn range(span: &Span) -> Value {
+    json!({
+        "start": {"line": span.start_line - 1, "character": span.start_col - 1},
+        "end": {"line": span.end_line - 1, "character": span.end_col},
+    })
+}
+
+/// Compiler line and column of an LSP `TextDocumentPositionParams`.
+fn position(params: &Value) -> Option<(u32, u32)> {
+    let position = params.get("position")?;
+    let line = position.get("line")?.as_u64()? as u32;
+    let character = position.get("character")?.as_u64()? as u32;
+    Some((line + 1, character + 1))
+}
+
+fn document_uri(params: &Value) -> Option<&str> {
+    params.get("textDocument")?.get("uri")?.as_str()
+}
+
+fn symbol_kind(kind: SymbolKind) -> u32 {
+    match kind {
+        SymbolKind::Class => 5,
+        SymbolKind::Enum => 10,
+        SymbolKind::Trait => 11,
+        SymbolKind::Function => 12,
+    }
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the client has asked the server to shut down; `exit` after
+    /// that is a clean exit.
+    pub fn is_shut_down(&self) -> bool {
+        self.shut_down
+    }
+
+    /// Handle one message. Requests get exactly one response; notifications
+    /// may produce `publishDiagnostics` notifications.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message.get("method").and_then(Value::as_str).unwrap_or("");
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let Some(id) = message.get("id").cloned() else {
+            return self.notify(method, &params);
+        };
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "hoverProvider": true,
+                    "documentSymbolProvider": true,
+                },
+                "serverInfo": {"name": "ark-lsp", "version": env!("CARGO_PKG_VERSION")},
+            })),
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Value::Null)
+            }
+            "textDocument/definition" => self.definition(&params),
+            "textDocument/hover" => self.hover(&params),
+            "textDocument/documentSymbol" => self.document_symbols(&params),
+            _ => Err((METHOD_NOT_FOUND, format!("unhandled method '{}'", method))),
+        };
+        let response = match result {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {"code": code, "message": message},
+            }),
+        };
+        vec![response]
+    }
+
+    fn notify(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let Some(uri) = document_uri(params) else {
+            return Vec::new();
+        };
+        let text = match method {
+            "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+            // Full sync: the last change holds the whole document
+            "textDocument/didChange" => params["contentChanges"]
+                .as_array()
+                .and_then(|changes| changes.last())
+                .and_then(|change| change["text"].as_str()),
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return vec![publish(uri, Vec::new())];
+            }
+            _ => None,
+        };
+        let Some(text) = text else {
+            return Vec::new();
+        };
+        let document = Document::analyze(uri, text);
+        let diagnostics = document
+            .diagnostics
+            .iter()
+            .map(|d| lsp_diagnostic(d, &document.source))
+            .collect();
+        self.documents.insert(uri.to_string(), document);
+        vec![publish(uri, diagnostics)]
+    }
+
+    fn lookup(&self, params: &Value) -> Result<(&str, &Document, u32, u32), (i64, String)> {
+        let invalid = || {
+            (
+                INVALID_PARAMS,
+                "expected a text document position".to_string(),
+            )
+        };
+        let uri = document_uri(params).ok_or_else(invalid)?;
+        let (line, col) = position(params).ok_or_else(invalid)?;
+        let document = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("document '{}' is not open", uri)))?;
+        Ok((uri, document, line, col))
+    }
+
+    fn definition(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let (uri, document, line, col) = self.lookup(params)?;
+        Ok(match document.definition(line, col) {
+            Some(symbol) => json!({"uri": uri, "range": range(&symbol.name_span)}),
+            None => Value::Null,
+        })
+    }
+
+    fn hover(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let (_, document, line, col) = self.lookup(params)?;
+        Ok(match document.hover(line, col) {
+            Some((text, span)) => json!({
+                "contents": {"kind": "markdown", "value": format!("```ark\n{}\n```", text)},
+                "range": range(&span),
+            }),
+            None => Value::Null,
+        })
+    }
+
+    fn document_symbols(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let uri = document_uri(params)
+            .ok_or_else(|| (INVALID_PARAMS, "expected a text document".to_string()))?;
+        let Some(document) = self.documents.get(uri) else {
+            return Ok(json!([]));
+        };
+        let symbols: Vec<Value> = document
+            .symbols
+            .iter()
+            .map(|symbol| {
+                json!({
+                    "name": symbol.name,
+                    "detail": symbol.detail,
+                    "kind": symbol_kind(symbol.kind),
+                    "range": range(&symbol.span),
+                    "selectionRange": range(&symbol.name_span),
+                })
+            })
+            .collect();
+        Ok(Value::Array(symbols))
+    }
+}
+
+fn publish(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": {"uri": uri, "diagnostics": diagnostics},
+    })
+}
+
+/// A compiler diagnostic as an LSP `Diagnostic`. Spans covering a whole
+/// function are narrowed to its first line, as the terminal renderer does;
+/// a diagnostic without a span goes on the first line of the file.
+fn lsp_diagnostic(diagnostic: &Diagnostic, source: &str) -> Value {
+    let extent = match &diagnostic.span {
+        Some(span) if span.end_line != span.start_line => {
+            let width = source
+                .lines()
+                .nth(span.start_line as usize - 1)
+                .map_or(span.start_col, |line| line.chars().count() as u32);
+            range(&Span {
+                end_line: span.start_line,
+                end_col: width.max(span.start_col),
+                ..span.clone()
+            })
+        }
+        Some(span) => range(span),
+        None => json!({
+            "start": {"line": 0, "character": 0},
+            "end": {"line": 0, "character": 0},
+        }),
+    };
+    let severity = match diagnostic.severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+        Severity::Note => 3,
+    };
+    let message = match &diagnostic.help {
+        Some(help) => format!("{}\nhelp: {}", diagnostic.message, help),
+        None => diagnostic.message.clone(),
+    };
+    json!({
+        "range": extent,
+        "severity": severity,
+        "code": diagnostic.code,
+        "source": "ark",
+        "message": message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(server: &mut Server, text: &str) -> Vec<Value> {
+        server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {"textDocument": {"uri": "file:///app.ark", "languageId": "ark", "version": 1, "text": text}},
+        }))
+    }
+
+    fn request(server: &mut Server, method: &str, line: u32, character: u32) -> Value {
+        let mut replies = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "id": 7,
+            "method": method,
+            "params": {
+                "textDocument": {"uri": "file:///app.ark"},
+                "position": {"line": line, "character": character},
+            },
+        }));
+        assert_eq!(replies.len(), 1);
+        let reply = replies.remove(0);
+        assert_eq!(reply["id"], 7);
+        reply
+    }
+
+    #[test]
+    fn test_open_publishes_diagnostics_on_the_function_line() {
+        let mut server = Server::new();
+        let init = server
+            .handle(&json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}));
+        assert_eq!(init[0]["result"]["capabilities"]["hoverProvider"], true);
+
+        let published = open(
+            &mut server,
+            "func f(n: Int) => Int {\n    return \"x\"\n}\n",
+        );
+        assert_eq!(published[0]["method"], "textDocument/publishDiagnostics");
+        let diagnostics = published[0]["params"]["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["code"], "E0106");
+        assert_eq!(
+            diagnostics[0]["range"],
+            json!({"start": {"line": 0, "character": 0}, "end": {"line": 0, "character": 23}})
+        );
+
+        // Fixing the file clears them
+        let published = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didChange",
+            "params": {
+                "textDocument": {"uri": "file:///app.ark", "version": 2},
+                "contentChanges": [{"text": "func f(n: Int) => Int {\n    return n\n}\n"}],
+            },
+        }));
+        assert_eq!(published[0]["params"]["diagnostics"], json!([]));
+    }
+
+    #[test]
+    fn test_definition_hover_and_symbols() {
+        let mut server = Server::new();
+        open(
+            &mut server,
+            "func double(n: Int) => Int {\n    return n * 2\n}\nx := double(4)\n",
+        );
+
+        let reply = request(&mut server, "textDocument/definition", 3, 6);
+        assert_eq!(
+            reply["result"]["range"],
+            json!({"start": {"line": 0, "character": 5}, "end": {"line": 0, "character": 11}})
+        );
+
+        let reply = request(&mut server, "textDocument/hover", 3, 0);
+        assert_eq!(reply["result"]["contents"]["value"], "```ark\nx: Int\n```");
+
+        let reply = request(&mut server, "textDocument/documentSymbol", 0, 0);
+        assert_eq!(reply["result"][0]["name"], "double");
+        assert_eq!(reply["result"][0]["kind"], 12);
+
+        let reply = request(&mut server, "textDocument/formatting", 0, 0);
+        assert_eq!(reply["error"]["code"], METHOD_NOT_FOUND);
+    }
+}
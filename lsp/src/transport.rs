@@ -0,0 +1,87 @@
+/*
+ * Copyright (c) 2026 Mohamad Al-Zawahreh (dba Sovereign Systems).
+ *
+ * This file is part of the Ark Sovereign Compiler.
+ *
+ * LICENSE: DUAL-LICENSED (AGPLv3 or COMMERCIAL).
+ *
+ * 1. OPEN SOURCE: You may use this file under the terms of the GNU Affero
+ * General Public License v3.0. If you link to this code, your ENTIRE
+ * application must be open-sourced under AGPLv3.
+ *
+ * 2. COMMERCIAL: For proprietary use, you must obtain a Commercial License
+ * from Sovereign Systems.
+ *
+ * PATENT NOTICE: Protected by US Patent App #63/935,467.
+ * NO IMPLIED LICENSE to rights of Mohamad Al-Zawahreh or Sovereign Systems.
+ */
+
+//! LSP base protocol: JSON-RPC messages framed by a `Content-Length`
+//! header and a blank line.
+
+use serde_json::Value;
+use std::io::{self, BufRead, Write};
+
+fn invalid(message: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Read the next message, or `None` once the client closes the stream.
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        // Other headers (Content-Type) only ever name the default encoding
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = Some(value.trim().parse::<usize>().map_err(invalid)?);
+        }
+    }
+    let length = length.ok_or_else(|| invalid("missing Content-Length header"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map(Some).map_err(invalid)
+}
+
+/// Write one message and flush it.
+pub fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_round_trip_and_end_of_stream() {
+        let mut buffer = Vec::new();
+        write_message(
+            &mut buffer,
+            &json!({"jsonrpc": "2.0", "method": "initialized"}),
+        )
+        .unwrap();
+        write_message(&mut buffer, &json!({"id": 1, "text": "héllo"})).unwrap();
+
+        let mut input = io::Cursor::new(buffer);
+        let first = read_message(&mut input).unwrap().unwrap();
+        assert_eq!(first["method"], "initialized");
+        let second = read_message(&mut input).unwrap().unwrap();
+        assert_eq!(second["text"], "héllo");
+        assert!(read_message(&mut input).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_missing_length_is_an_error() {
+        let mut input = io::Cursor::new(b"Content-Type: x\r\n\r\n{}".to_vec());
+        assert!(read_message(&mut input).is_err());
+    }
+}